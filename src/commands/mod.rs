@@ -1,7 +1,16 @@
+mod options;
+mod range;
+
 use std::collections::HashSet;
 use std::fs;
 use std::path::PathBuf;
 
+pub use options::{
+    OPTION_SPECS, OptionKind, OptionScope, OptionSpec, SetAction, SetArgument, find_option,
+    parse_set_arguments,
+};
+pub use range::{AddressBase, ExRange, LineAddress, parse_range};
+
 /// Parsed command from command line
 #[derive(Debug, Clone)]
 pub enum Command {
@@ -37,10 +46,12 @@ pub enum Command {
     Prev,
     /// :bd or :bdelete - Close the current buffer
     BufferDelete(bool),
-    /// :set option[=value] - Set an option
-    Set(String, Option<String>),
+    /// :set / :setlocal {option}... - Show or change options (`:set all` lists them)
+    Set { args: Vec<SetArgument>, local: bool },
     /// :[number] - Go to line number
     GotoLine(usize),
+    /// :[range] - Go to the last line of a range (`:$`, `:'a`, `:/pat/`)
+    GotoRange(ExRange),
     /// :LazyGit - Open lazygit
     LazyGit,
     /// :! command - Run shell command
@@ -71,16 +82,49 @@ pub enum Command {
     MarkdownPreview,
     /// :noh or :nohlsearch - Clear search highlights
     NoHighlight,
-    /// :s/pattern/replacement/flags or :%s/pattern/replacement/flags - Search and replace
+    /// :[range]s/pattern/replacement/[flags] [count] - Search and replace
     Substitute {
-        /// Range: None for current line, Some(true) for entire file (%)
-        entire_file: bool,
-        /// Search pattern
+        /// Line range; None for the current line
+        range: Option<ExRange>,
+        /// Search pattern (empty reuses the last search pattern)
         pattern: String,
         /// Replacement string
         replacement: String,
-        /// Global flag (replace all on line vs first only)
-        global: bool,
+        /// Parsed `[cegiIn]` flags
+        flags: SubstituteFlags,
+        /// Trailing count: substitute in `count` lines starting at the range end
+        count: Option<usize>,
+    },
+    /// :[range]g/pattern/command and :[range]v/pattern/command - Run an Ex
+    /// command on every line that matches (or, for `:v` and `:g!`, does not)
+    Global {
+        /// Line range; None for the whole file
+        range: Option<ExRange>,
+        /// Search pattern (empty reuses the last search pattern)
+        pattern: String,
+        /// Ex command run with the cursor on each marked line
+        command: String,
+        /// Mark the lines that do not match
+        invert: bool,
+    },
+    /// :[range]d [x] [count] - Delete lines, optionally into register x
+    DeleteLines {
+        /// Line range; None for the current line
+        range: Option<ExRange>,
+        /// Register to delete into
+        register: Option<char>,
+        /// Delete `count` lines starting at the range end
+        count: Option<usize>,
+    },
+    /// :[range]normal[!] {keys} - Execute keys as if typed in Normal mode
+    Normal {
+        /// Lines to run the keys on, starting in column 0 of each; None runs
+        /// them once at the cursor
+        range: Option<ExRange>,
+        /// Keys in mapping notation, e.g. `A;<Esc>`
+        keys: String,
+        /// `!` - ignore user key mappings
+        noremap: bool,
     },
     /// :ProjectReplace/pattern/replacement/flags - Preview project-wide replace
     ProjectReplace {
@@ -111,6 +155,22 @@ pub enum Command {
     Format,
     /// :codeaction - Show code actions (LSP)
     CodeAction,
+    /// :Symbols - Open fuzzy finder for the current buffer's symbols (LSP)
+    DocumentSymbols,
+    /// :WorkspaceSymbols - Search symbols across the workspace (LSP)
+    WorkspaceSymbols,
+    /// :CallHierarchy [incoming|outgoing] - Explore callers or callees (LSP)
+    CallHierarchy { outgoing: bool },
+    /// :TypeHierarchy [subtypes|supertypes] - Explore subtypes or supertypes (LSP)
+    TypeHierarchy { supertypes: bool },
+    /// :CodeLens [n] - Run the first or nth code lens of the cursor line (LSP)
+    CodeLens(Option<usize>),
+    /// :LspLog [server] - Show the JSON-RPC messages and stderr of the language servers
+    LspLog(Option<String>),
+    /// :LspInfo - Show the running language servers and what they negotiated
+    LspInfo,
+    /// :LspRestart [lang] - Restart language servers and reopen their documents
+    LspRestart(Option<String>),
     /// :rename <newname> - Rename symbol under cursor (LSP)
     Rename(String),
     /// :rename (no args) - Enter rename prompt mode (LSP)
@@ -177,6 +237,21 @@ pub enum Command {
     Unknown(String),
 }
 
+/// Flags accepted after `:s/pattern/replacement/`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SubstituteFlags {
+    /// `g` - replace every match in the line, not just the first
+    pub global: bool,
+    /// `c` - confirm each substitution
+    pub confirm: bool,
+    /// `n` - report the match count without substituting
+    pub count_only: bool,
+    /// `e` - no error when the pattern is not found
+    pub suppress_errors: bool,
+    /// `i` / `I` - override case sensitivity (Some(true) ignores case)
+    pub ignore_case: Option<bool>,
+}
+
 /// Result of executing a command
 #[derive(Debug)]
 pub enum CommandResult {
@@ -329,10 +404,16 @@ const COMMAND_SPECS: &[CommandSpec] = &[
     },
     CommandSpec {
         command: "set",
-        aliases: &[],
+        aliases: &["se"],
         description: "Set an editor option",
         takes_args: true,
     },
+    CommandSpec {
+        command: "setlocal",
+        aliases: &["setl"],
+        description: "Set an option for the current buffer",
+        takes_args: true,
+    },
     CommandSpec {
         command: "!",
         aliases: &[],
@@ -419,8 +500,8 @@ const COMMAND_SPECS: &[CommandSpec] = &[
     },
     CommandSpec {
         command: "s",
-        aliases: &[],
-        description: "Substitute on current line",
+        aliases: &["substitute"],
+        description: "Substitute in range (default: current line)",
         takes_args: true,
     },
     CommandSpec {
@@ -429,6 +510,30 @@ const COMMAND_SPECS: &[CommandSpec] = &[
         description: "Substitute in entire file",
         takes_args: true,
     },
+    CommandSpec {
+        command: "g",
+        aliases: &["global"],
+        description: "Run a command on lines matching a pattern",
+        takes_args: true,
+    },
+    CommandSpec {
+        command: "v",
+        aliases: &["vglobal"],
+        description: "Run a command on lines not matching a pattern",
+        takes_args: true,
+    },
+    CommandSpec {
+        command: "d",
+        aliases: &[],
+        description: "Delete lines in range (default: current line)",
+        takes_args: true,
+    },
+    CommandSpec {
+        command: "normal",
+        aliases: &["norm"],
+        description: "Execute Normal mode keys",
+        takes_args: true,
+    },
     CommandSpec {
         command: "ProjectReplace",
         aliases: &["projectreplace", "PReplace", "preplace"],
@@ -495,6 +600,54 @@ const COMMAND_SPECS: &[CommandSpec] = &[
         description: "Show LSP code actions",
         takes_args: false,
     },
+    CommandSpec {
+        command: "Symbols",
+        aliases: &["symbols", "DocumentSymbols", "outline"],
+        description: "Find symbols in current buffer",
+        takes_args: false,
+    },
+    CommandSpec {
+        command: "WorkspaceSymbols",
+        aliases: &["workspacesymbols", "wsymbols"],
+        description: "Search workspace symbols",
+        takes_args: false,
+    },
+    CommandSpec {
+        command: "CallHierarchy",
+        aliases: &["callhierarchy", "calls"],
+        description: "Explore incoming or outgoing calls",
+        takes_args: true,
+    },
+    CommandSpec {
+        command: "TypeHierarchy",
+        aliases: &["typehierarchy"],
+        description: "Explore subtypes or supertypes",
+        takes_args: true,
+    },
+    CommandSpec {
+        command: "CodeLens",
+        aliases: &["codelens"],
+        description: "Run a code lens of the cursor line",
+        takes_args: true,
+    },
+    CommandSpec {
+        command: "LspLog",
+        aliases: &["lsplog"],
+        description: "Show the language server protocol log",
+        takes_args: true,
+    },
+    CommandSpec {
+        command: "LspInfo",
+        aliases: &["lspinfo"],
+        description: "Show running language servers",
+        takes_args: false,
+    },
+    CommandSpec {
+        command: "LspRestart",
+        aliases: &["lsprestart"],
+        description: "Restart language servers",
+        takes_args: true,
+    },
     CommandSpec {
         command: "rn",
         aliases: &["lsprename", "LspRename"],
@@ -706,9 +859,93 @@ const MAX_HISTORY_ITEMS: usize = 20;
 const MAX_HISTORY_ENTRIES: usize = 500;
 
 /// Build fuzzy command suggestions for the current command-line input.
+///
+/// After `:set ` / `:setlocal ` the word being typed is completed against
+/// option names instead of commands.
 pub fn command_suggestions(input: &str, limit: usize) -> Vec<CommandSuggestion> {
-    let (_, token, _) = split_input_segments(input);
-    command_suggestions_for_token(token, limit)
+    let (_, token, _, target) = completion_segments(input);
+    suggestions_for_target(target, token, limit)
+}
+
+/// What the word under completion names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompletionTarget {
+    Command,
+    SetOption,
+}
+
+/// Split input into (text before, word to complete, text after, target).
+fn completion_segments(input: &str) -> (&str, &str, &str, CompletionTarget) {
+    let (leading, token, rest) = split_input_segments(input);
+    if !matches!(token, "se" | "set" | "setl" | "setlocal")
+        || !rest.starts_with(char::is_whitespace)
+    {
+        return (leading, token, rest, CompletionTarget::Command);
+    }
+
+    let mut word_start = input
+        .rfind(char::is_whitespace)
+        .map(|idx| idx + 1)
+        .unwrap_or(input.len());
+    let word = &input[word_start..];
+    // `nowrap` and `invlist` complete the option after the prefix.
+    if !OPTION_SPECS.iter().any(|spec| spec.name.starts_with(word)) {
+        if let Some(prefix) = ["no", "inv"].iter().find(|p| word.starts_with(**p)) {
+            word_start += prefix.len();
+        }
+    }
+
+    (
+        &input[..word_start],
+        &input[word_start..],
+        "",
+        CompletionTarget::SetOption,
+    )
+}
+
+fn suggestions_for_target(
+    target: CompletionTarget,
+    token: &str,
+    limit: usize,
+) -> Vec<CommandSuggestion> {
+    match target {
+        CompletionTarget::Command => command_suggestions_for_token(token, limit),
+        CompletionTarget::SetOption => option_suggestions_for_token(token, limit),
+    }
+}
+
+fn option_suggestions_for_token(token: &str, limit: usize) -> Vec<CommandSuggestion> {
+    // `ts=4` or `list?` is already complete.
+    if token.contains(|ch: char| !ch.is_ascii_alphanumeric()) {
+        return Vec::new();
+    }
+
+    let token_lower = token.to_lowercase();
+    let mut scored: Vec<(i32, CommandSuggestion)> = Vec::new();
+    for spec in OPTION_SPECS {
+        let best = std::iter::once(spec.name)
+            .chain(spec.short)
+            .filter_map(|alias| alias_match_score(&token_lower, alias).map(|score| (score, alias)))
+            .max_by_key(|(score, _)| *score);
+        if let Some((score, alias)) = best {
+            scored.push((
+                score,
+                CommandSuggestion {
+                    command: spec.name,
+                    description: spec.description,
+                    matched_alias: alias,
+                    takes_args: false,
+                },
+            ));
+        }
+    }
+
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.command.cmp(b.1.command)));
+    scored
+        .into_iter()
+        .take(limit.max(1))
+        .map(|(_, suggestion)| suggestion)
+        .collect()
 }
 
 fn command_suggestions_for_token(token: &str, limit: usize) -> Vec<CommandSuggestion> {
@@ -926,13 +1163,19 @@ pub fn parse_command(input: &str) -> Command {
         return Command::Shell(shell_cmd);
     }
 
-    // Handle line number
-    if let Ok(line_num) = input.parse::<usize>() {
-        return Command::GotoLine(line_num);
+    // Handle a leading range: `:42`, `:$`, `:'<,'>s/a/b/`, `:%s/a/b/`
+    let (range, input) = parse_range(input);
+    if let Some(range) = range {
+        return parse_ranged_command(range, input);
     }
 
-    // Handle substitute command: %s/pattern/replacement/flags or s/pattern/replacement/flags
-    if let Some(sub_cmd) = parse_substitute_command(input) {
+    // Handle global command: g/pattern/command
+    if let Some(global_cmd) = parse_global_command(input, None) {
+        return global_cmd;
+    }
+
+    // Handle substitute command: s/pattern/replacement/flags
+    if let Some(sub_cmd) = parse_substitute_command(input, None) {
         return sub_cmd;
     }
 
@@ -981,16 +1224,10 @@ pub fn parse_command(input: &str) -> Command {
         "bd!" | "bdelete!" => Command::BufferDelete(true),
 
         // Set options
-        "set" => {
-            if let Some(arg) = args {
-                let mut parts = arg.splitn(2, '=');
-                let option = parts.next().unwrap_or("").to_string();
-                let value = parts.next().map(|s| s.to_string());
-                Command::Set(option, value)
-            } else {
-                Command::Unknown("set: missing option".to_string())
-            }
-        }
+        "se" | "set" | "setl" | "setlocal" => Command::Set {
+            args: parse_set_arguments(args.unwrap_or("")),
+            local: matches!(cmd, "setl" | "setlocal"),
+        },
 
         // External tools
         "LazyGit" | "lazygit" | "lg" => Command::LazyGit,
@@ -1018,6 +1255,10 @@ pub fn parse_command(input: &str) -> Command {
         // Clear search highlight
         "noh" | "nohlsearch" => Command::NoHighlight,
 
+        // Line-oriented Ex commands
+        "d" => parse_delete_lines_args(None, args),
+        "norm" | "normal" | "norm!" | "normal!" => parse_normal_args(None, cmd, args),
+
         // Project-wide replace
         "ProjectReplaceApply" | "projectreplaceapply" | "PReplaceApply" | "preplaceapply" => {
             Command::ProjectReplaceApply
@@ -1055,6 +1296,42 @@ pub fn parse_command(input: &str) -> Command {
         // LSP commands
         "Format" | "format" => Command::Format,
         "codeaction" | "CodeAction" | "ca" => Command::CodeAction,
+        "Symbols" | "symbols" | "DocumentSymbols" | "outline" => Command::DocumentSymbols,
+        "WorkspaceSymbols" | "workspacesymbols" | "wsymbols" => Command::WorkspaceSymbols,
+        "CallHierarchy" | "callhierarchy" | "calls" => match args.map(str::trim) {
+            None | Some("") | Some("incoming") => Command::CallHierarchy { outgoing: false },
+            Some("outgoing") => Command::CallHierarchy { outgoing: true },
+            Some(other) => Command::Unknown(format!(
+                "CallHierarchy: expected incoming or outgoing, got {}",
+                other
+            )),
+        },
+        "TypeHierarchy" | "typehierarchy" => match args.map(str::trim) {
+            None | Some("") | Some("subtypes") => Command::TypeHierarchy { supertypes: false },
+            Some("supertypes") => Command::TypeHierarchy { supertypes: true },
+            Some(other) => Command::Unknown(format!(
+                "TypeHierarchy: expected subtypes or supertypes, got {}",
+                other
+            )),
+        },
+        "CodeLens" | "codelens" => match args.map(str::trim) {
+            None | Some("") => Command::CodeLens(None),
+            Some(number) => match number.parse::<usize>() {
+                Ok(number) if number > 0 => Command::CodeLens(Some(number)),
+                _ => Command::Unknown(format!("CodeLens: expected a lens number, got {}", number)),
+            },
+        },
+        "LspLog" | "lsplog" => Command::LspLog(
+            args.map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(str::to_string),
+        ),
+        "LspInfo" | "lspinfo" => Command::LspInfo,
+        "LspRestart" | "lsprestart" => Command::LspRestart(
+            args.map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(str::to_string),
+        ),
         "lsprename" | "LspRename" | "rn" => {
             if let Some(new_name) = args.filter(|s| !s.is_empty()) {
                 Command::Rename(new_name.to_string())
@@ -1159,47 +1436,213 @@ fn parse_terminal_rename_args(args: Option<&str>) -> Command {
     }
 }
 
-/// Parse a substitute command: %s/pattern/replacement/flags or s/pattern/replacement/flags
-fn parse_substitute_command(input: &str) -> Option<Command> {
-    // Check for %s or s prefix
-    let (entire_file, rest) = if input.starts_with("%s") {
-        (true, &input[2..])
-    } else if input.starts_with('s')
-        && input.len() > 1
-        && !input.chars().nth(1).unwrap().is_alphanumeric()
-    {
-        (false, &input[1..])
+/// Parse the command that follows an Ex range.
+fn parse_ranged_command(range: ExRange, rest: &str) -> Command {
+    if rest.is_empty() {
+        return match range.line_number() {
+            Some(line) => Command::GotoLine(line),
+            None => Command::GotoRange(range),
+        };
+    }
+
+    if let Some(global_cmd) = parse_global_command(rest, Some(range.clone())) {
+        return global_cmd;
+    }
+
+    if let Some(sub_cmd) = parse_substitute_command(rest, Some(range.clone())) {
+        return sub_cmd;
+    }
+
+    let mut parts = rest.splitn(2, char::is_whitespace);
+    let cmd = parts.next().unwrap_or(rest);
+    let args = parts.next().map(str::trim);
+    match cmd {
+        "d" | "delete" => parse_delete_lines_args(Some(range), args),
+        "norm" | "normal" | "norm!" | "normal!" => parse_normal_args(Some(range), cmd, args),
+        _ => Command::Unknown(format!("{}: no range allowed", cmd)),
+    }
+}
+
+/// Parse the keys after `:normal` or `:normal!`.
+fn parse_normal_args(range: Option<ExRange>, cmd: &str, args: Option<&str>) -> Command {
+    match args.filter(|keys| !keys.is_empty()) {
+        Some(keys) => Command::Normal {
+            range,
+            keys: keys.to_string(),
+            noremap: cmd.ends_with('!'),
+        },
+        None => Command::Unknown("normal: argument required".to_string()),
+    }
+}
+
+/// Parse `[x] [count]` after `:d`.
+fn parse_delete_lines_args(range: Option<ExRange>, args: Option<&str>) -> Command {
+    let mut args = args.unwrap_or("").trim();
+    let register = args
+        .chars()
+        .next()
+        .filter(|ch| !ch.is_ascii_digit() && !ch.is_whitespace());
+    if let Some(register) = register {
+        args = args[register.len_utf8()..].trim_start();
+    }
+
+    let count = if args.is_empty() {
+        None
     } else {
-        return None;
+        match args.parse::<usize>() {
+            Ok(0) => return Command::Unknown("d: positive count required".to_string()),
+            Ok(count) => Some(count),
+            Err(_) => return Command::Unknown(format!("d: trailing characters: {}", args)),
+        }
     };
 
-    // Must have a delimiter after s or %s
-    if rest.is_empty() {
+    Command::DeleteLines {
+        range,
+        register,
+        count,
+    }
+}
+
+/// Parse a global command: g/pattern/command, g!/pattern/command or v/pattern/command
+fn parse_global_command(input: &str, range: Option<ExRange>) -> Option<Command> {
+    let (rest, invert) = [
+        ("global!", true),
+        ("vglobal", true),
+        ("global", false),
+        ("g!", true),
+        ("g", false),
+        ("v", true),
+    ]
+    .iter()
+    .find_map(|(prefix, invert)| input.strip_prefix(prefix).map(|rest| (rest, *invert)))?;
+
+    let delimiter = rest.chars().next()?;
+    if delimiter.is_alphanumeric()
+        || delimiter.is_whitespace()
+        || matches!(delimiter, '\\' | '"' | '|')
+    {
         return None;
     }
+    let rest = &rest[delimiter.len_utf8()..];
+
+    // Only the first unescaped delimiter ends the pattern; the command may
+    // contain the delimiter itself, as in `:g/foo/s/a/b/`.
+    let mut pattern_end = rest.len();
+    let mut escaped = false;
+    for (idx, ch) in rest.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if ch == '\\' {
+            escaped = true;
+        } else if ch == delimiter {
+            pattern_end = idx;
+            break;
+        }
+    }
+    let pattern = unescape_delimiter(&rest[..pattern_end], delimiter);
+    // Without a command the marked lines are printed, as `:g/pattern/p`.
+    let command = match rest[pattern_end..]
+        .get(delimiter.len_utf8()..)
+        .unwrap_or("")
+        .trim()
+    {
+        "" => "p".to_string(),
+        command => command.to_string(),
+    };
+
+    Some(Command::Global {
+        range,
+        pattern,
+        command,
+        invert,
+    })
+}
+
+/// Parse a substitute command: s/pattern/replacement/flags [count]
+fn parse_substitute_command(input: &str, range: Option<ExRange>) -> Option<Command> {
+    parse_substitute_parts(input, range).map(|(command, _)| command)
+}
+
+/// Parse a `:s` command line that may still be being typed, for the live
+/// preview. The flag reports whether the replacement part has been started,
+/// so `:s/foo` can highlight matches without previewing a deletion.
+pub fn parse_incremental_substitute(input: &str) -> Option<(Command, bool)> {
+    let (range, rest) = parse_range(input.trim_start());
+    parse_substitute_parts(rest, range)
+}
+
+fn parse_substitute_parts(input: &str, range: Option<ExRange>) -> Option<(Command, bool)> {
+    let rest = input
+        .strip_prefix("substitute")
+        .or_else(|| input.strip_prefix('s'))?;
 
     // The delimiter is the first character (usually /)
     let delimiter = rest.chars().next()?;
+    if delimiter.is_alphanumeric()
+        || delimiter.is_whitespace()
+        || matches!(delimiter, '\\' | '"' | '|')
+    {
+        return None;
+    }
     let rest = &rest[delimiter.len_utf8()..];
 
     // Split by delimiter, handling escaped delimiters
     let parts = split_by_delimiter(rest, delimiter);
-    if parts.len() < 2 {
-        return None;
-    }
-
     let pattern = unescape_delimiter(&parts[0], delimiter);
-    let replacement = unescape_delimiter(&parts[1], delimiter);
-    let flags = if parts.len() > 2 { &parts[2] } else { "" };
+    let replacement = parts
+        .get(1)
+        .map(|part| unescape_delimiter(part, delimiter))
+        .unwrap_or_default();
+    let trailing = parts.get(2).map(String::as_str).unwrap_or("");
 
-    let global = flags.contains('g');
+    let has_replacement = parts.len() > 1;
 
-    Some(Command::Substitute {
-        entire_file,
-        pattern,
-        replacement,
-        global,
-    })
+    let (flags, count) = match parse_substitute_flags(trailing) {
+        Ok(parsed) => parsed,
+        Err(message) => return Some((Command::Unknown(message), has_replacement)),
+    };
+
+    Some((
+        Command::Substitute {
+            range,
+            pattern,
+            replacement,
+            flags,
+            count,
+        },
+        has_replacement,
+    ))
+}
+
+/// Parse `[flags] [count]` after the final substitute delimiter.
+fn parse_substitute_flags(input: &str) -> Result<(SubstituteFlags, Option<usize>), String> {
+    let mut flags = SubstituteFlags::default();
+    let input = input.trim_start();
+    let flag_len = input
+        .find(|ch: char| ch.is_whitespace() || ch.is_ascii_digit())
+        .unwrap_or(input.len());
+
+    for flag in input[..flag_len].chars() {
+        match flag {
+            'g' => flags.global = !flags.global,
+            'c' => flags.confirm = true,
+            'n' => flags.count_only = true,
+            'e' => flags.suppress_errors = true,
+            'i' => flags.ignore_case = Some(true),
+            'I' => flags.ignore_case = Some(false),
+            _ => return Err(format!("s: trailing characters: {}", &input[..flag_len])),
+        }
+    }
+
+    let count = input[flag_len..].trim();
+    if count.is_empty() {
+        return Ok((flags, None));
+    }
+    match count.parse::<usize>() {
+        Ok(0) => Err("s: positive count required".to_string()),
+        Ok(count) => Ok((flags, Some(count))),
+        Err(_) => Err(format!("s: trailing characters: {}", count)),
+    }
 }
 
 /// Parse a project replace command: ProjectReplace/pattern/replacement/flags
@@ -1566,12 +2009,12 @@ impl CommandLine {
         }
 
         self.popup_mode = CommandPopupMode::Completion;
-        let (prefix, token, suffix) = split_input_segments(&self.input);
+        let (prefix, token, suffix, target) = completion_segments(&self.input);
         if token.is_empty() {
             return false;
         }
 
-        let matches = command_suggestions_for_token(token, COMMAND_SPECS.len());
+        let matches = suggestions_for_target(target, token, COMMAND_SPECS.len());
         let common_prefix = longest_common_command_prefix(&matches);
         if common_prefix.is_empty() || common_prefix == token {
             return false;
@@ -1595,8 +2038,8 @@ impl CommandLine {
         self.history_popup_items.clear();
         self.history_popup_index = 0;
 
-        let (prefix, token, suffix) = split_input_segments(&self.input);
-        let matches = command_suggestions_for_token(token, COMMAND_SPECS.len());
+        let (prefix, token, suffix, target) = completion_segments(&self.input);
+        let matches = suggestions_for_target(target, token, COMMAND_SPECS.len());
         if matches.is_empty() {
             self.suggestions.clear();
             self.popup_mode = CommandPopupMode::None;
@@ -1684,7 +2127,7 @@ impl CommandLine {
             return false;
         };
 
-        let (prefix, _token, suffix) = split_input_segments(&self.input);
+        let (prefix, _token, suffix, _) = completion_segments(&self.input);
         let prefix = prefix.to_string();
         let suffix = suffix.to_string();
 
@@ -1914,6 +2357,18 @@ mod tests {
         assert!(matches!(parse_command("GitChanges"), Command::GitChanges));
         assert!(matches!(parse_command("changes"), Command::GitChanges));
         assert!(matches!(parse_command("gc"), Command::GitChanges));
+        match parse_command("'<,'>normal! ciw<C-r>0<Esc>") {
+            Command::Normal {
+                range,
+                keys,
+                noremap,
+            } => {
+                assert!(range.is_some());
+                assert_eq!(keys, "ciw<C-r>0<Esc>");
+                assert!(noremap);
+            }
+            other => panic!("expected Normal command, got {other:?}"),
+        }
     }
 
     #[test]
@@ -1978,6 +2433,86 @@ mod tests {
         );
     }
 
+    #[test]
+    fn substitute_parses_range_flags_and_count() {
+        match parse_command(r"'<,'>s#a\#b#\1&#gcI 3") {
+            Command::Substitute {
+                range,
+                pattern,
+                replacement,
+                flags,
+                count,
+            } => {
+                let range = range.expect("range");
+                assert_eq!(range.start.base, AddressBase::Mark('<'));
+                assert_eq!(pattern, "a#b");
+                assert_eq!(replacement, r"\1&");
+                assert_eq!(
+                    flags,
+                    SubstituteFlags {
+                        global: true,
+                        confirm: true,
+                        ignore_case: Some(false),
+                        ..SubstituteFlags::default()
+                    }
+                );
+                assert_eq!(count, Some(3));
+            }
+            other => panic!("expected Substitute command, got {other:?}"),
+        }
+
+        match parse_command("%s/x/y/") {
+            Command::Substitute { range, .. } => assert_eq!(range, Some(ExRange::whole_file())),
+            other => panic!("expected Substitute command, got {other:?}"),
+        }
+        assert!(matches!(
+            parse_command("s/x/y/q"),
+            Command::Unknown(message) if message == "s: trailing characters: q"
+        ));
+        assert!(matches!(parse_command("42"), Command::GotoLine(42)));
+        assert!(matches!(parse_command(".,$"), Command::GotoRange(_)));
+    }
+
+    #[test]
+    fn global_keeps_the_delimiter_inside_its_command() {
+        match parse_command(r"1,5g/a\/b/s/x/y/g") {
+            Command::Global {
+                range,
+                pattern,
+                command,
+                invert,
+            } => {
+                assert!(range.is_some());
+                assert_eq!(pattern, "a/b");
+                assert_eq!(command, "s/x/y/g");
+                assert!(!invert);
+            }
+            other => panic!("expected Global command, got {other:?}"),
+        }
+
+        assert!(matches!(
+            parse_command("v/keep/d"),
+            Command::Global {
+                invert: true,
+                range: None,
+                ..
+            }
+        ));
+        assert!(matches!(
+            parse_command("g!/keep/"),
+            Command::Global { invert: true, ref command, .. } if command == "p"
+        ));
+        assert!(matches!(parse_command("gc"), Command::GitChanges));
+        assert!(matches!(
+            parse_command("2,3d a"),
+            Command::DeleteLines {
+                range: Some(_),
+                register: Some('a'),
+                count: None
+            }
+        ));
+    }
+
     #[test]
     fn checkhealth_command_is_parseable_and_suggested() {
         assert!(matches!(parse_command("checkhealth"), Command::CheckHealth));
@@ -2052,6 +2587,59 @@ mod tests {
         );
     }
 
+    #[test]
+    fn hierarchy_commands_parse_their_direction() {
+        assert!(matches!(
+            parse_command("CallHierarchy"),
+            Command::CallHierarchy { outgoing: false }
+        ));
+        assert!(matches!(
+            parse_command("calls outgoing"),
+            Command::CallHierarchy { outgoing: true }
+        ));
+        assert!(matches!(
+            parse_command("TypeHierarchy"),
+            Command::TypeHierarchy { supertypes: false }
+        ));
+        assert!(matches!(
+            parse_command("TypeHierarchy supertypes"),
+            Command::TypeHierarchy { supertypes: true }
+        ));
+        assert!(matches!(
+            parse_command("CallHierarchy sideways"),
+            Command::Unknown(_)
+        ));
+    }
+
+    #[test]
+    fn code_lens_command_takes_an_optional_lens_number() {
+        assert!(matches!(parse_command("CodeLens"), Command::CodeLens(None)));
+        assert!(matches!(
+            parse_command("codelens 2"),
+            Command::CodeLens(Some(2))
+        ));
+        assert!(matches!(parse_command("CodeLens 0"), Command::Unknown(_)));
+        assert!(matches!(parse_command("CodeLens run"), Command::Unknown(_)));
+    }
+
+    #[test]
+    fn lsp_server_commands_take_an_optional_server_name() {
+        assert!(matches!(parse_command("LspLog"), Command::LspLog(None)));
+        assert!(matches!(
+            parse_command("lsplog eslint"),
+            Command::LspLog(Some(name)) if name == "eslint"
+        ));
+        assert!(matches!(parse_command("LspInfo"), Command::LspInfo));
+        assert!(matches!(
+            parse_command("LspRestart"),
+            Command::LspRestart(None)
+        ));
+        assert!(matches!(
+            parse_command("LspRestart python"),
+            Command::LspRestart(Some(name)) if name == "python"
+        ));
+    }
+
     #[test]
     fn config_commands_are_parseable_and_suggested() {
         assert!(matches!(parse_command("ConfigOpen"), Command::ConfigOpen));
@@ -2173,6 +2761,57 @@ mod tests {
         assert_eq!(line.input, "mkdir src/components");
     }
 
+    #[test]
+    fn set_parses_each_argument_and_its_action() {
+        match parse_command("setl nowrap ts=4 sw+=2 list? et! ff:dos") {
+            Command::Set { args, local } => {
+                assert!(local);
+                let actions: Vec<_> = args
+                    .iter()
+                    .map(|arg| (arg.name.as_str(), arg.action.clone()))
+                    .collect();
+                assert_eq!(
+                    actions,
+                    vec![
+                        ("nowrap", SetAction::Plain),
+                        ("ts", SetAction::Assign("4".to_string())),
+                        ("sw", SetAction::Add("2".to_string())),
+                        ("list", SetAction::Query),
+                        ("et", SetAction::Toggle),
+                        ("ff", SetAction::Assign("dos".to_string())),
+                    ]
+                );
+            }
+            other => panic!("expected :setlocal, got {other:?}"),
+        }
+        assert!(matches!(
+            parse_command("set"),
+            Command::Set { args, local: false } if args.is_empty()
+        ));
+    }
+
+    #[test]
+    fn set_completes_option_names_after_prefixes() {
+        let suggestions = command_suggestions("set nu rel", 5);
+        assert_eq!(suggestions[0].command, "relativenumber");
+
+        let suggestions = command_suggestions("set ts", 5);
+        assert_eq!(suggestions[0].command, "tabstop");
+        assert_eq!(suggestions[0].matched_alias, "ts");
+        assert!(command_suggestions("set ts=4", 5).is_empty());
+
+        let mut line = CommandLine::default();
+        line.input = "setlocal nolis".to_string();
+        line.cursor = line.input.chars().count();
+        line.refresh_command_suggestions();
+        assert!(line.accept_completion_selection());
+        assert_eq!(line.input, "setlocal nolist");
+
+        line.input = "set expa".to_string();
+        assert!(line.complete_longest_common_prefix());
+        assert_eq!(line.input, "set expandtab");
+    }
+
     #[test]
     fn terminal_session_commands_parse_arguments() {
         assert!(matches!(
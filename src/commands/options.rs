@@ -0,0 +1,232 @@
+//! `:set` / `:setlocal` arguments and the table of options they can change.
+//!
+//! Parsing keeps each argument's name as typed; whether `nowrap` means `no` +
+//! `wrap` depends on the option table, so the editor resolves it when applying.
+
+/// Value type of an option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionKind {
+    Bool,
+    Number,
+    String,
+}
+
+/// Whether an option has one value for the editor or one per buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionScope {
+    Global,
+    Buffer,
+}
+
+/// An option `:set` knows about.
+#[derive(Debug, Clone, Copy)]
+pub struct OptionSpec {
+    pub name: &'static str,
+    /// Vim's short name, e.g. `ts` for `tabstop`
+    pub short: Option<&'static str>,
+    pub kind: OptionKind,
+    pub scope: OptionScope,
+    pub description: &'static str,
+}
+
+pub const OPTION_SPECS: &[OptionSpec] = &[
+    OptionSpec {
+        name: "autoindent",
+        short: Some("ai"),
+        kind: OptionKind::Bool,
+        scope: OptionScope::Global,
+        description: "Indent new lines like the previous one",
+    },
+    OptionSpec {
+        name: "codelens",
+        short: None,
+        kind: OptionKind::Bool,
+        scope: OptionScope::Global,
+        description: "Show LSP code lenses above the lines they apply to",
+    },
+    OptionSpec {
+        name: "cursorline",
+        short: Some("cul"),
+        kind: OptionKind::Bool,
+        scope: OptionScope::Global,
+        description: "Highlight the cursor line",
+    },
+    OptionSpec {
+        name: "documenthighlight",
+        short: None,
+        kind: OptionKind::Bool,
+        scope: OptionScope::Global,
+        description: "Highlight LSP references to the symbol under the cursor",
+    },
+    OptionSpec {
+        name: "expandtab",
+        short: Some("et"),
+        kind: OptionKind::Bool,
+        scope: OptionScope::Buffer,
+        description: "Insert spaces instead of tabs",
+    },
+    OptionSpec {
+        name: "fileformat",
+        short: Some("ff"),
+        kind: OptionKind::String,
+        scope: OptionScope::Buffer,
+        description: "Line endings to write: unix or dos",
+    },
+    OptionSpec {
+        name: "ignorecase",
+        short: Some("ic"),
+        kind: OptionKind::Bool,
+        scope: OptionScope::Buffer,
+        description: "Ignore case in search patterns",
+    },
+    OptionSpec {
+        name: "inlayhints",
+        short: None,
+        kind: OptionKind::Bool,
+        scope: OptionScope::Global,
+        description: "Show LSP inlay hints",
+    },
+    OptionSpec {
+        name: "list",
+        short: None,
+        kind: OptionKind::Bool,
+        scope: OptionScope::Buffer,
+        description: "Show tabs and non-breaking spaces",
+    },
+    OptionSpec {
+        name: "number",
+        short: Some("nu"),
+        kind: OptionKind::Bool,
+        scope: OptionScope::Global,
+        description: "Show line numbers",
+    },
+    OptionSpec {
+        name: "readonly",
+        short: Some("ro"),
+        kind: OptionKind::Bool,
+        scope: OptionScope::Buffer,
+        description: "Refuse edits to the buffer",
+    },
+    OptionSpec {
+        name: "relativenumber",
+        short: Some("rnu"),
+        kind: OptionKind::Bool,
+        scope: OptionScope::Global,
+        description: "Show line numbers relative to the cursor",
+    },
+    OptionSpec {
+        name: "scrolloff",
+        short: Some("so"),
+        kind: OptionKind::Number,
+        scope: OptionScope::Global,
+        description: "Lines kept visible above and below the cursor",
+    },
+    OptionSpec {
+        name: "semantictokens",
+        short: None,
+        kind: OptionKind::Bool,
+        scope: OptionScope::Global,
+        description: "Highlight with LSP semantic tokens",
+    },
+    OptionSpec {
+        name: "shiftwidth",
+        short: Some("sw"),
+        kind: OptionKind::Number,
+        scope: OptionScope::Buffer,
+        description: "Columns per indent level (0: use tabstop)",
+    },
+    OptionSpec {
+        name: "smartcase",
+        short: Some("scs"),
+        kind: OptionKind::Bool,
+        scope: OptionScope::Buffer,
+        description: "Match case when the pattern has uppercase",
+    },
+    OptionSpec {
+        name: "tabstop",
+        short: Some("ts"),
+        kind: OptionKind::Number,
+        scope: OptionScope::Global,
+        description: "Columns a tab counts for",
+    },
+    OptionSpec {
+        name: "textwidth",
+        short: Some("tw"),
+        kind: OptionKind::Number,
+        scope: OptionScope::Buffer,
+        description: "Break lines typed past this column (0: off)",
+    },
+    OptionSpec {
+        name: "wrap",
+        short: None,
+        kind: OptionKind::Bool,
+        scope: OptionScope::Global,
+        description: "Soft-wrap long lines",
+    },
+];
+
+/// Look up an option by its full or short name.
+pub fn find_option(name: &str) -> Option<&'static OptionSpec> {
+    OPTION_SPECS
+        .iter()
+        .find(|spec| spec.name == name || spec.short == Some(name))
+}
+
+/// What one `:set` argument asks for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SetAction {
+    /// `opt` - turn a boolean on, or show any other option
+    Plain,
+    /// `opt?`
+    Query,
+    /// `opt!`
+    Toggle,
+    /// `opt=value` or `opt:value`
+    Assign(String),
+    /// `opt+=value`
+    Add(String),
+    /// `opt-=value`
+    Subtract(String),
+}
+
+/// One whitespace-separated `:set` argument, such as `ts=4` or `nowrap`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetArgument {
+    pub name: String,
+    pub action: SetAction,
+}
+
+/// Split `:set` arguments, e.g. `nu ts=4 list?`.
+pub fn parse_set_arguments(input: &str) -> Vec<SetArgument> {
+    input.split_whitespace().map(parse_set_argument).collect()
+}
+
+fn parse_set_argument(arg: &str) -> SetArgument {
+    let name_end = arg
+        .find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '_'))
+        .unwrap_or(arg.len());
+    let name = arg[..name_end].to_string();
+    let rest = &arg[name_end..];
+
+    let action = if rest.is_empty() {
+        SetAction::Plain
+    } else if rest == "?" {
+        SetAction::Query
+    } else if rest == "!" {
+        SetAction::Toggle
+    } else if let Some(value) = rest.strip_prefix("+=") {
+        SetAction::Add(value.to_string())
+    } else if let Some(value) = rest.strip_prefix("-=") {
+        SetAction::Subtract(value.to_string())
+    } else if let Some(value) = rest.strip_prefix('=').or_else(|| rest.strip_prefix(':')) {
+        SetAction::Assign(value.to_string())
+    } else {
+        // Leave the trailing characters in the name so the error shows them.
+        return SetArgument {
+            name: arg.to_string(),
+            action: SetAction::Plain,
+        };
+    };
+
+    SetArgument { name, action }
+}
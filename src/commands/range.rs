@@ -0,0 +1,272 @@
+//! Ex line ranges (`:10,20`, `:.,+5`, `:'<,'>`, `:/foo/,/bar/`, `:%`).
+//!
+//! Parsing only records the addresses; resolving them needs the cursor, marks
+//! and buffer text, so that happens on the editor side.
+
+/// The line an address starts from before any `+N` / `-N` offsets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressBase {
+    /// `.` (also implied by a bare `+N` / `-N`)
+    CurrentLine,
+    /// `$`
+    LastLine,
+    /// An absolute 1-indexed line number
+    Line(usize),
+    /// `'x` - the line of mark `x` (including `'<` and `'>`)
+    Mark(char),
+    /// `/pattern/` - the next line matching pattern
+    SearchForward(String),
+    /// `?pattern?` - the previous line matching pattern
+    SearchBackward(String),
+}
+
+/// One address in an Ex range, such as `.+3` or `'a-1`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineAddress {
+    pub base: AddressBase,
+    pub offset: isize,
+}
+
+impl LineAddress {
+    pub fn new(base: AddressBase) -> Self {
+        Self { base, offset: 0 }
+    }
+}
+
+/// A parsed Ex range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExRange {
+    pub start: LineAddress,
+    pub end: Option<LineAddress>,
+    /// Addresses were separated by `;`, so `end` is resolved from `start`
+    /// rather than from the cursor line.
+    pub end_from_start: bool,
+}
+
+impl ExRange {
+    /// `%` - every line in the buffer.
+    pub fn whole_file() -> Self {
+        Self {
+            start: LineAddress::new(AddressBase::Line(1)),
+            end: Some(LineAddress::new(AddressBase::LastLine)),
+            end_from_start: false,
+        }
+    }
+
+    /// A single absolute line number, used by `:{number}`.
+    pub fn line_number(&self) -> Option<usize> {
+        match (&self.start.base, self.start.offset, &self.end) {
+            (AddressBase::Line(line), 0, None) => Some(*line),
+            _ => None,
+        }
+    }
+}
+
+/// Split a leading Ex range off `input`, returning the range (if any) and the
+/// remaining command text.
+pub fn parse_range(input: &str) -> (Option<ExRange>, &str) {
+    let trimmed = input.trim_start();
+    if let Some(rest) = trimmed.strip_prefix('%') {
+        return (Some(ExRange::whole_file()), rest.trim_start());
+    }
+
+    let (start, rest) = parse_address(trimmed);
+    let rest_trimmed = rest.trim_start();
+    let separator = rest_trimmed
+        .chars()
+        .next()
+        .filter(|ch| matches!(ch, ',' | ';'));
+
+    let Some(separator) = separator else {
+        return match start {
+            Some(start) => (
+                Some(ExRange {
+                    start,
+                    end: None,
+                    end_from_start: false,
+                }),
+                rest_trimmed,
+            ),
+            None => (None, input),
+        };
+    };
+
+    let after_separator = &rest_trimmed[1..];
+    let (end, rest) = parse_address(after_separator);
+    let start = start.unwrap_or_else(|| LineAddress::new(AddressBase::CurrentLine));
+    let end = end.unwrap_or_else(|| LineAddress::new(AddressBase::CurrentLine));
+    (
+        Some(ExRange {
+            start,
+            end: Some(end),
+            end_from_start: separator == ';',
+        }),
+        rest.trim_start(),
+    )
+}
+
+fn parse_address(input: &str) -> (Option<LineAddress>, &str) {
+    let input = input.trim_start();
+    let mut chars = input.chars();
+    let Some(first) = chars.next() else {
+        return (None, input);
+    };
+
+    let (base, rest) = match first {
+        '.' => (Some(AddressBase::CurrentLine), &input[1..]),
+        '$' => (Some(AddressBase::LastLine), &input[1..]),
+        '0'..='9' => {
+            let digits = input
+                .find(|ch: char| !ch.is_ascii_digit())
+                .unwrap_or(input.len());
+            match input[..digits].parse::<usize>() {
+                Ok(line) => (Some(AddressBase::Line(line)), &input[digits..]),
+                Err(_) => return (None, input),
+            }
+        }
+        '\'' => match chars.next() {
+            Some(mark) => (Some(AddressBase::Mark(mark)), &input[1 + mark.len_utf8()..]),
+            None => return (None, input),
+        },
+        '/' | '?' => {
+            let body = &input[1..];
+            let (pattern, rest) = split_pattern(body, first);
+            let base = if first == '/' {
+                AddressBase::SearchForward(pattern)
+            } else {
+                AddressBase::SearchBackward(pattern)
+            };
+            (Some(base), rest)
+        }
+        '+' | '-' => (None, input),
+        _ => return (None, input),
+    };
+
+    let (offset, rest, has_offset) = parse_offsets(rest);
+    match base {
+        Some(base) => (Some(LineAddress { base, offset }), rest),
+        None if has_offset => (
+            Some(LineAddress {
+                base: AddressBase::CurrentLine,
+                offset,
+            }),
+            rest,
+        ),
+        None => (None, input),
+    }
+}
+
+/// Parse a run of `+N`, `-N`, `+` and `-` offsets.
+fn parse_offsets(mut input: &str) -> (isize, &str, bool) {
+    let mut offset = 0isize;
+    let mut found = false;
+    loop {
+        let trimmed = input.trim_start();
+        let sign = match trimmed.chars().next() {
+            Some('+') => 1,
+            Some('-') => -1,
+            _ => break,
+        };
+        let after_sign = &trimmed[1..];
+        let digits = after_sign
+            .find(|ch: char| !ch.is_ascii_digit())
+            .unwrap_or(after_sign.len());
+        let amount = if digits == 0 {
+            1
+        } else {
+            after_sign[..digits].parse::<isize>().unwrap_or(isize::MAX)
+        };
+        offset = offset.saturating_add(sign * amount);
+        input = &after_sign[digits..];
+        found = true;
+    }
+    (offset, input, found)
+}
+
+/// Split `pattern<delimiter>rest`, unescaping `\<delimiter>`. A missing closing
+/// delimiter ends the pattern at the end of the input, as in Vim.
+fn split_pattern(input: &str, delimiter: char) -> (String, &str) {
+    let mut pattern = String::new();
+    let mut chars = input.char_indices();
+    while let Some((idx, ch)) = chars.next() {
+        if ch == '\\' {
+            match chars.next() {
+                Some((_, next)) if next == delimiter => pattern.push(next),
+                Some((_, next)) => {
+                    pattern.push('\\');
+                    pattern.push(next);
+                }
+                None => pattern.push('\\'),
+            }
+        } else if ch == delimiter {
+            return (pattern, &input[idx + ch.len_utf8()..]);
+        } else {
+            pattern.push(ch);
+        }
+    }
+    (pattern, "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AddressBase, ExRange, LineAddress, parse_range};
+
+    fn address(base: AddressBase, offset: isize) -> LineAddress {
+        LineAddress { base, offset }
+    }
+
+    #[test]
+    fn parses_numeric_and_symbolic_ranges() {
+        let (range, rest) = parse_range("10,20s/a/b/");
+        assert_eq!(rest, "s/a/b/");
+        assert_eq!(
+            range,
+            Some(ExRange {
+                start: address(AddressBase::Line(10), 0),
+                end: Some(address(AddressBase::Line(20), 0)),
+                end_from_start: false,
+            })
+        );
+
+        let (range, rest) = parse_range(".,+5d");
+        assert_eq!(rest, "d");
+        let range = range.expect("range");
+        assert_eq!(range.start, address(AddressBase::CurrentLine, 0));
+        assert_eq!(range.end, Some(address(AddressBase::CurrentLine, 5)));
+
+        let (range, rest) = parse_range("%s/x/y/");
+        assert_eq!(rest, "s/x/y/");
+        assert_eq!(range, Some(ExRange::whole_file()));
+    }
+
+    #[test]
+    fn parses_mark_and_pattern_addresses() {
+        let (range, rest) = parse_range("'<,'>normal A;");
+        assert_eq!(rest, "normal A;");
+        let range = range.expect("range");
+        assert_eq!(range.start.base, AddressBase::Mark('<'));
+        assert_eq!(range.end.expect("end").base, AddressBase::Mark('>'));
+
+        let (range, rest) = parse_range(r"/fn\/x/;?end?-1s/a/b/");
+        assert_eq!(rest, "s/a/b/");
+        let range = range.expect("range");
+        assert_eq!(
+            range.start.base,
+            AddressBase::SearchForward("fn/x".to_string())
+        );
+        assert_eq!(
+            range.end,
+            Some(address(AddressBase::SearchBackward("end".to_string()), -1))
+        );
+        assert!(range.end_from_start);
+    }
+
+    #[test]
+    fn leaves_plain_commands_untouched() {
+        assert_eq!(parse_range("set number"), (None, "set number"));
+        assert_eq!(parse_range("s/a/b/"), (None, "s/a/b/"));
+        let (range, rest) = parse_range("42");
+        assert_eq!(range.and_then(|range| range.line_number()), Some(42));
+        assert_eq!(rest, "");
+    }
+}
@@ -538,32 +538,7 @@ fn parse_action(action: &str) -> LeaderAction {
         LeaderAction::Command(cmd.to_string())
     } else {
         // Otherwise, parse as key sequence
-        let mut keys = Vec::new();
-        let mut remaining = action;
-
-        while !remaining.is_empty() {
-            if remaining.starts_with('<') {
-                // Find the closing >
-                if let Some(end) = remaining.find('>') {
-                    let notation = &remaining[..=end];
-                    if let Some(key) = parse_key_notation(notation) {
-                        keys.push(key);
-                    }
-                    remaining = &remaining[end + 1..];
-                } else {
-                    break;
-                }
-            } else {
-                // Single character
-                let c = remaining.chars().next().unwrap();
-                if let Some(key) = parse_key_notation(&c.to_string()) {
-                    keys.push(key);
-                }
-                remaining = &remaining[c.len_utf8()..];
-            }
-        }
-
-        LeaderAction::Keys(keys)
+        LeaderAction::Keys(parse_key_sequence(action))
     }
 }
 
@@ -621,6 +596,34 @@ fn parse_explorer_mode_action(action: &str) -> Option<ExplorerModeAction> {
     }
 }
 
+/// Parse a sequence of keys such as `ciw<C-r>0<Esc>` into KeyEvents.
+///
+/// A `<` that does not start a recognised notation is taken literally, so
+/// `<<` stays a shift-left.
+pub fn parse_key_sequence(keys: &str) -> Vec<KeyEvent> {
+    let mut parsed = Vec::new();
+    let mut remaining = keys;
+
+    while let Some(c) = remaining.chars().next() {
+        if c == '<' {
+            if let Some(end) = remaining.find('>') {
+                if let Some(key) = parse_key_notation(&remaining[..=end]) {
+                    parsed.push(key);
+                    remaining = &remaining[end + 1..];
+                    continue;
+                }
+            }
+        }
+
+        if let Some(key) = parse_key_notation(&c.to_string()) {
+            parsed.push(key);
+        }
+        remaining = &remaining[c.len_utf8()..];
+    }
+
+    parsed
+}
+
 /// Parse a key notation string into a KeyEvent
 ///
 /// Supported formats:
@@ -917,6 +920,10 @@ mod tests {
         let expected = [
             ("ca", "codeaction"),
             ("rn", "rn"),
+            ("ci", "CallHierarchy incoming"),
+            ("co", "CallHierarchy outgoing"),
+            ("ct", "TypeHierarchy"),
+            ("cl", "CodeLens"),
             ("w", "w"),
             ("q", "q"),
             ("e", "Explorer"),
@@ -936,6 +943,8 @@ mod tests {
             ("t2", "TerminalSelect 2"),
             ("t3", "TerminalSelect 3"),
             ("t4", "TerminalSelect 4"),
+            ("fs", "Symbols"),
+            ("fS", "WorkspaceSymbols"),
             ("d", "FindDiagnostics"),
             ("D", "DiagnosticFloat"),
             ("gg", "LazyGit"),
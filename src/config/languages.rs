@@ -4,7 +4,7 @@
 
 use serde::Deserialize;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Configuration for a single language
 #[derive(Debug, Clone, Deserialize, Default)]
@@ -14,6 +14,11 @@ pub struct LanguageConfig {
     pub formatter: Option<FormatterConfig>,
     /// Tab width override for this language
     pub tab_width: Option<usize>,
+    /// File extensions highlighted with the runtime grammar of this name
+    /// (`~/.config/nevi/grammars/<name>/`)
+    pub extensions: Vec<String>,
+    /// Shebang interpreters (e.g. "lua") mapped to the runtime grammar
+    pub shebangs: Vec<String>,
 }
 
 /// External formatter configuration
@@ -53,6 +58,54 @@ impl LanguagesConfig {
             .get(language)
             .and_then(|config| config.tab_width)
     }
+
+    /// Runtime grammar mapped to a file by extension, or by the interpreter
+    /// in its first line. Names are sorted so overlapping mappings resolve
+    /// the same way every time.
+    pub fn runtime_language_for(
+        &self,
+        path: Option<&Path>,
+        first_line: Option<&str>,
+    ) -> Option<&str> {
+        let mut names: Vec<&String> = self.languages.keys().collect();
+        names.sort();
+
+        let extension = path.and_then(|p| p.extension()).and_then(|e| e.to_str());
+        if let Some(extension) = extension {
+            let found = names.iter().find(|name| {
+                self.languages[name.as_str()]
+                    .extensions
+                    .iter()
+                    .any(|ext| ext.trim_start_matches('.') == extension)
+            });
+            if let Some(name) = found {
+                return Some(name.as_str());
+            }
+        }
+
+        let interpreter = first_line.and_then(crate::syntax::shebang_interpreter)?;
+        names
+            .into_iter()
+            .find(|name| {
+                self.languages[name.as_str()]
+                    .shebangs
+                    .iter()
+                    .any(|shebang| shebang == interpreter)
+            })
+            .map(String::as_str)
+    }
+
+    /// Languages with an `extensions` or `shebangs` mapping, sorted
+    pub fn runtime_language_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self
+            .languages
+            .iter()
+            .filter(|(_, config)| !config.extensions.is_empty() || !config.shebangs.is_empty())
+            .map(|(name, _)| name.as_str())
+            .collect();
+        names.sort();
+        names
+    }
 }
 
 /// Get the path to languages.toml
@@ -184,6 +237,20 @@ fn default_languages_template() -> &'static str {
 # [shell]
 # formatter = { command = "shfmt", args = ["-filename", "{file}", "-"] }
 # tab_width = 2
+
+# ============================================================================
+# RUNTIME GRAMMARS
+# Put a compiled parser (zig.so / zig.dylib) and highlights.scm, plus optional
+# injections.scm and indents.scm, in ~/.config/nevi/grammars/zig/, then map
+# files to it here. :checkhealth reports whether each grammar loaded.
+# ============================================================================
+# [zig]
+# extensions = ["zig", "zon"]
+# tab_width = 4
+#
+# [lua]
+# extensions = ["lua"]
+# shebangs = ["lua", "luajit"]
 "#
 }
 
@@ -234,3 +301,49 @@ pub fn load_languages_config() -> LanguagesConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runtime_languages_map_extensions_and_shebangs() {
+        let config: LanguagesConfig = toml::from_str(
+            r#"
+            [zig]
+            extensions = [".zig", "zon"]
+
+            [lua]
+            extensions = ["lua"]
+            shebangs = ["lua", "luajit"]
+
+            [rust]
+            tab_width = 4
+            "#,
+        )
+        .expect("parse languages.toml");
+
+        assert_eq!(
+            config.runtime_language_for(Some(Path::new("build.zig")), None),
+            Some("zig")
+        );
+        assert_eq!(
+            config.runtime_language_for(Some(Path::new("build.zon")), None),
+            Some("zig")
+        );
+        assert_eq!(
+            config.runtime_language_for(Some(Path::new("tool")), Some("#!/usr/bin/env luajit")),
+            Some("lua")
+        );
+        assert_eq!(
+            config.runtime_language_for(None, Some("#!/usr/bin/lua")),
+            Some("lua")
+        );
+        assert_eq!(
+            config.runtime_language_for(Some(Path::new("main.rs")), None),
+            None
+        );
+        assert_eq!(config.runtime_language_names(), vec!["lua", "zig"]);
+        assert_eq!(config.get_tab_width("rust"), Some(4));
+    }
+}
@@ -7,6 +7,7 @@ pub mod keymap;
 pub mod languages;
 
 use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 use crate::explorer::DEFAULT_EXPLORER_WIDTH;
@@ -61,6 +62,19 @@ impl Default for AutosaveMode {
     }
 }
 
+/// Live preview of `:s` while it is typed (Neovim's `inccommand`)
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IncCommand {
+    /// No preview until the command runs
+    Off,
+    /// Highlight matches and show replacements in place
+    #[default]
+    NoSplit,
+    /// Like `nosplit`, plus a listing of affected lines outside the window
+    Split,
+}
+
 /// Editor behavior settings
 #[derive(Debug, Clone, Deserialize)]
 #[serde(default)]
@@ -92,6 +106,28 @@ pub struct EditorSettings {
     /// Use Nerd Font icons in explorer (default: true)
     /// Set to false to use Unicode fallback icons
     pub use_nerd_font_icons: bool,
+    /// Ignore case in search patterns (default: false)
+    pub ignore_case: bool,
+    /// Override ignore_case when the pattern contains uppercase (default: false)
+    pub smart_case: bool,
+    /// Live `:s` preview: "off", "nosplit" or "split" (default: "nosplit")
+    pub inc_command: IncCommand,
+    /// Indent with spaces rather than tabs (default: true)
+    pub expand_tab: bool,
+    /// Columns per indent level, 0 to follow tab_width (default: 0)
+    pub shift_width: usize,
+    /// Show tabs and non-breaking spaces (default: false)
+    pub list: bool,
+    /// Wrap typed text past this column, 0 to disable (default: 0)
+    pub text_width: usize,
+    /// Show LSP inlay hints (default: true)
+    pub inlay_hints: bool,
+    /// Lay LSP semantic tokens over tree-sitter highlighting (default: true)
+    pub semantic_tokens: bool,
+    /// Highlight LSP references to the symbol under the cursor (default: true)
+    pub document_highlight: bool,
+    /// Show LSP code lenses above the lines they apply to (default: true)
+    pub code_lens: bool,
 }
 
 impl Default for EditorSettings {
@@ -110,6 +146,17 @@ impl Default for EditorSettings {
             autosave: AutosaveMode::Off,
             autosave_delay_ms: 1000,
             use_nerd_font_icons: true,
+            ignore_case: false,
+            smart_case: false,
+            inc_command: IncCommand::NoSplit,
+            expand_tab: true,
+            shift_width: 0,
+            list: false,
+            text_width: 0,
+            inlay_hints: true,
+            semantic_tokens: true,
+            document_highlight: true,
+            code_lens: true,
         }
     }
 }
@@ -337,6 +384,26 @@ impl Default for KeymapSettings {
                     action: ":rn".to_string(),
                     desc: Some("Rename symbol".to_string()),
                 },
+                LeaderMapping {
+                    key: "ci".to_string(),
+                    action: ":CallHierarchy incoming".to_string(),
+                    desc: Some("Incoming calls".to_string()),
+                },
+                LeaderMapping {
+                    key: "co".to_string(),
+                    action: ":CallHierarchy outgoing".to_string(),
+                    desc: Some("Outgoing calls".to_string()),
+                },
+                LeaderMapping {
+                    key: "ct".to_string(),
+                    action: ":TypeHierarchy".to_string(),
+                    desc: Some("Type hierarchy".to_string()),
+                },
+                LeaderMapping {
+                    key: "cl".to_string(),
+                    action: ":CodeLens".to_string(),
+                    desc: Some("Run code lens".to_string()),
+                },
                 // File operations
                 LeaderMapping {
                     key: "w".to_string(),
@@ -379,6 +446,16 @@ impl Default for KeymapSettings {
                     action: ":FindBuffers".to_string(),
                     desc: Some("Find buffers".to_string()),
                 },
+                LeaderMapping {
+                    key: "fs".to_string(),
+                    action: ":Symbols".to_string(),
+                    desc: Some("Find symbols in current buffer".to_string()),
+                },
+                LeaderMapping {
+                    key: "fS".to_string(),
+                    action: ":WorkspaceSymbols".to_string(),
+                    desc: Some("Search workspace symbols".to_string()),
+                },
                 LeaderMapping {
                     key: "d".to_string(),
                     action: ":FindDiagnostics".to_string(),
@@ -634,7 +711,8 @@ impl Default for LspSettings {
     }
 }
 
-/// Per-language server configurations
+/// Per-language server configurations, plus user-defined servers for other
+/// filetypes or alongside the built-in ones
 #[derive(Debug, Clone, Deserialize)]
 #[serde(default)]
 pub struct LspServers {
@@ -651,6 +729,10 @@ pub struct LspServers {
     pub go: LspServerConfig,
     pub ruby: LspServerConfig,
     pub shell: LspServerConfig,
+    /// User-defined servers (`[lsp.servers.eslint]`, `[lsp.servers.clangd]`,
+    /// ...) by name, each attached to the `filetypes` it lists
+    #[serde(flatten)]
+    pub additional: BTreeMap<String, LspServerConfig>,
 }
 
 impl Default for LspServers {
@@ -663,6 +745,11 @@ impl Default for LspServers {
                 args: Vec::new(),
                 root_patterns: vec!["Cargo.toml".to_string(), "rust-project.json".to_string()],
                 file_extensions: vec!["rs".to_string()],
+                priority: 0,
+                filetypes: Vec::new(),
+                extension_filetypes: BTreeMap::new(),
+                settings: toml::Table::new(),
+                initialization_options: toml::Table::new(),
             },
             typescript: LspServerConfig {
                 enabled: true,
@@ -676,6 +763,11 @@ impl Default for LspServers {
                     "mts".to_string(),
                     "cts".to_string(),
                 ],
+                priority: 0,
+                filetypes: Vec::new(),
+                extension_filetypes: BTreeMap::new(),
+                settings: toml::Table::new(),
+                initialization_options: toml::Table::new(),
             },
             javascript: LspServerConfig {
                 enabled: true,
@@ -689,6 +781,11 @@ impl Default for LspServers {
                     "mjs".to_string(),
                     "cjs".to_string(),
                 ],
+                priority: 0,
+                filetypes: Vec::new(),
+                extension_filetypes: BTreeMap::new(),
+                settings: toml::Table::new(),
+                initialization_options: toml::Table::new(),
             },
             css: LspServerConfig {
                 enabled: true,
@@ -702,6 +799,11 @@ impl Default for LspServers {
                     "sass".to_string(),
                     "less".to_string(),
                 ],
+                priority: 0,
+                filetypes: Vec::new(),
+                extension_filetypes: BTreeMap::new(),
+                settings: toml::Table::new(),
+                initialization_options: toml::Table::new(),
             },
             json: LspServerConfig {
                 enabled: true,
@@ -710,6 +812,11 @@ impl Default for LspServers {
                 args: vec!["--stdio".to_string()],
                 root_patterns: vec!["package.json".to_string()],
                 file_extensions: vec!["json".to_string(), "jsonc".to_string()],
+                priority: 0,
+                filetypes: Vec::new(),
+                extension_filetypes: BTreeMap::new(),
+                settings: toml::Table::new(),
+                initialization_options: toml::Table::new(),
             },
             toml: LspServerConfig {
                 enabled: true,
@@ -718,6 +825,11 @@ impl Default for LspServers {
                 args: vec!["lsp".to_string(), "stdio".to_string()],
                 root_patterns: vec!["Cargo.toml".to_string(), "pyproject.toml".to_string()],
                 file_extensions: vec!["toml".to_string()],
+                priority: 0,
+                filetypes: Vec::new(),
+                extension_filetypes: BTreeMap::new(),
+                settings: toml::Table::new(),
+                initialization_options: toml::Table::new(),
             },
             markdown: LspServerConfig {
                 enabled: false, // Disabled by default - marksman has limited LSP support
@@ -726,6 +838,11 @@ impl Default for LspServers {
                 args: vec!["server".to_string()],
                 root_patterns: vec![".marksman.toml".to_string()],
                 file_extensions: vec!["md".to_string(), "markdown".to_string()],
+                priority: 0,
+                filetypes: Vec::new(),
+                extension_filetypes: BTreeMap::new(),
+                settings: toml::Table::new(),
+                initialization_options: toml::Table::new(),
             },
             html: LspServerConfig {
                 enabled: true,
@@ -734,6 +851,11 @@ impl Default for LspServers {
                 args: vec!["--stdio".to_string()],
                 root_patterns: vec!["package.json".to_string()],
                 file_extensions: vec!["html".to_string(), "htm".to_string()],
+                priority: 0,
+                filetypes: Vec::new(),
+                extension_filetypes: BTreeMap::new(),
+                settings: toml::Table::new(),
+                initialization_options: toml::Table::new(),
             },
             python: LspServerConfig {
                 enabled: true,
@@ -748,6 +870,11 @@ impl Default for LspServers {
                     "pyrightconfig.json".to_string(),
                 ],
                 file_extensions: vec!["py".to_string(), "pyi".to_string(), "pyw".to_string()],
+                priority: 0,
+                filetypes: Vec::new(),
+                extension_filetypes: BTreeMap::new(),
+                settings: toml::Table::new(),
+                initialization_options: toml::Table::new(),
             },
             php: LspServerConfig {
                 enabled: true,
@@ -760,6 +887,11 @@ impl Default for LspServers {
                     ".phpactor.yml".to_string(),
                 ],
                 file_extensions: vec!["php".to_string()],
+                priority: 0,
+                filetypes: Vec::new(),
+                extension_filetypes: BTreeMap::new(),
+                settings: toml::Table::new(),
+                initialization_options: toml::Table::new(),
             },
             go: LspServerConfig {
                 enabled: true,
@@ -768,6 +900,11 @@ impl Default for LspServers {
                 args: Vec::new(),
                 root_patterns: vec!["go.work".to_string(), "go.mod".to_string()],
                 file_extensions: vec!["go".to_string()],
+                priority: 0,
+                filetypes: Vec::new(),
+                extension_filetypes: BTreeMap::new(),
+                settings: toml::Table::new(),
+                initialization_options: toml::Table::new(),
             },
             ruby: LspServerConfig {
                 enabled: true,
@@ -787,6 +924,11 @@ impl Default for LspServers {
                     "ru".to_string(),
                     "podspec".to_string(),
                 ],
+                priority: 0,
+                filetypes: Vec::new(),
+                extension_filetypes: BTreeMap::new(),
+                settings: toml::Table::new(),
+                initialization_options: toml::Table::new(),
             },
             shell: LspServerConfig {
                 enabled: true,
@@ -803,11 +945,86 @@ impl Default for LspServers {
                     "ebuild".to_string(),
                     "eclass".to_string(),
                 ],
+                priority: 0,
+                filetypes: Vec::new(),
+                extension_filetypes: BTreeMap::new(),
+                settings: toml::Table::new(),
+                initialization_options: toml::Table::new(),
             },
+            additional: BTreeMap::new(),
         }
     }
 }
 
+/// Names of the built-in server tables (`[lsp.servers.rust]`, ...)
+const BUILTIN_LSP_SERVERS: [&str; 13] = [
+    "rust",
+    "typescript",
+    "javascript",
+    "css",
+    "json",
+    "toml",
+    "markdown",
+    "html",
+    "python",
+    "php",
+    "go",
+    "ruby",
+    "shell",
+];
+
+impl LspServers {
+    /// Drop the user-defined servers that could never start or attach, and
+    /// say why for each. Any table that isn't a built-in server's is read as
+    /// a new one, so these are mostly misspelled built-in names
+    /// (`[lsp.servers.rsut]`).
+    fn remove_invalid_additional(&mut self) -> Vec<String> {
+        let mut problems = Vec::new();
+        self.additional.retain(|name, config| {
+            let missing = if config.effective_command().is_empty() {
+                "`command`"
+            } else if config.filetypes.is_empty() && config.file_extensions.is_empty() {
+                "`filetypes` or `file_extensions`"
+            } else {
+                return true;
+            };
+            let hint = BUILTIN_LSP_SERVERS
+                .iter()
+                .map(|builtin| (edit_distance(name, builtin), builtin))
+                .filter(|(distance, builtin)| *distance <= builtin.len() / 3)
+                .min()
+                .map(|(_, builtin)| format!(" (did you mean [lsp.servers.{builtin}]?)"))
+                .unwrap_or_default();
+            problems.push(format!(
+                "[lsp.servers.{name}] is not a built-in server and has no {missing}{hint}"
+            ));
+            false
+        });
+        problems
+    }
+}
+
+/// Edits turning `a` into `b`, counting a swap of neighbours as one
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![(0..=b.len()).collect::<Vec<_>>()];
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            row[j] = (rows[i - 1][j] + 1)
+                .min(row[j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(rows[i - 2][j - 2] + 1);
+            }
+        }
+        rows.push(row);
+    }
+    rows[a.len()][b.len()]
+}
+
 /// Built-in LSP server presets
 /// These provide convenient shortcuts for common language servers
 #[derive(Debug, Clone, Deserialize, PartialEq)]
@@ -880,6 +1097,28 @@ pub struct LspServerConfig {
     /// File extensions this server handles
     #[serde(default)]
     pub file_extensions: Vec<String>,
+    /// Order among the servers attached to a file: the highest priority
+    /// capable server formats, and answers single-server requests first
+    #[serde(default)]
+    pub priority: i32,
+    /// Filetypes an additional server attaches to: built-in languages
+    /// (`typescript`, `python`, ...) or new ones (`c`, `lua`, ...) that its
+    /// `file_extensions` select. A language's own server always attaches to it.
+    #[serde(default)]
+    pub filetypes: Vec<String>,
+    /// Filetype of the files with an extension (`h = "cpp"`), for servers
+    /// whose `file_extensions` select more than one new filetype
+    #[serde(default)]
+    pub extension_filetypes: BTreeMap<String, String>,
+    /// Server settings (`[lsp.servers.rust.settings.rust-analyzer]`, ...),
+    /// answered to `workspace/configuration` by section and pushed with
+    /// `workspace/didChangeConfiguration`. Reloaded when the config changes.
+    #[serde(default)]
+    pub settings: toml::Table,
+    /// `initializationOptions` of the initialize request, merged over the
+    /// built-in ones. Take effect when the server starts.
+    #[serde(default)]
+    pub initialization_options: toml::Table,
 }
 
 impl LspServerConfig {
@@ -921,6 +1160,23 @@ impl LspServerConfig {
         }
         self.args.clone()
     }
+
+    /// `settings` as the JSON object sent to the server
+    pub fn settings_json(&self) -> serde_json::Value {
+        toml_table_to_json(&self.settings)
+    }
+
+    /// `initialization_options` as JSON, when any are set
+    pub fn initialization_options_json(&self) -> Option<serde_json::Value> {
+        if self.initialization_options.is_empty() {
+            return None;
+        }
+        Some(toml_table_to_json(&self.initialization_options))
+    }
+}
+
+fn toml_table_to_json(table: &toml::Table) -> serde_json::Value {
+    serde_json::to_value(table).unwrap_or_else(|_| serde_json::Value::Object(Default::default()))
 }
 
 fn default_true() -> bool {
@@ -994,6 +1250,17 @@ fn default_config_template() -> &'static str {
 # autosave = "off"           # Options: "off", "after_delay", "on_focus_change"
 # autosave_delay_ms = 1000   # Delay for after_delay mode
 # use_nerd_font_icons = true # Use Nerd Font icons in explorer (set false for Unicode fallback)
+# ignore_case = false        # Case-insensitive search (/, ?, *, #)
+# smart_case = false         # With ignore_case, stay case-sensitive if the pattern has uppercase
+# inc_command = "nosplit"    # Live :s preview: "off", "nosplit", or "split" (also lists off-screen lines)
+# expand_tab = true          # Indent with spaces instead of tabs
+# shift_width = 0            # Columns per indent level (0 = tab_width)
+# list = false               # Show tabs as ">" and non-breaking spaces as "+"
+# text_width = 0             # Break lines typed past this column (0 = off)
+# inlay_hints = true         # Show LSP type and parameter hints inline
+# semantic_tokens = true     # Refine highlighting with LSP semantic tokens
+# document_highlight = true  # Highlight references to the symbol under the cursor
+# code_lens = true           # Show LSP code lenses ("▶ Run test") above their lines
 
 # ============================================================================
 # THEME
@@ -1187,6 +1454,7 @@ fn default_config_template() -> &'static str {
 # gd               - Go to definition
 # gD               - Go to declaration
 # gI               - Go to implementation
+# gy               - Go to type definition
 # gf               - Open file under cursor
 # gx               - Open URL under cursor
 # gr               - Find references
@@ -1509,6 +1777,59 @@ fn default_config_template() -> &'static str {
 # [lsp.servers.typescript]
 # preset = "biome"
 # root_patterns = ["biome.json", "package.json"]
+#
+# ----------------------------------------------------------------------------
+# Server Settings
+# ----------------------------------------------------------------------------
+# `settings` answer the server's workspace/configuration requests, by section,
+# and are sent again when you save this file. `initialization_options` go in
+# the initialize request and apply the next time the server starts.
+# [lsp.servers.rust.settings.rust-analyzer]
+# cargo.features = "all"
+# check.command = "clippy"
+#
+# [lsp.servers.python.settings.python.analysis]
+# typeCheckingMode = "strict"
+#
+# [lsp.servers.typescript.initialization_options.preferences]
+# importModuleSpecifierPreference = "relative"
+#
+# ----------------------------------------------------------------------------
+# Additional LSP Servers
+# ----------------------------------------------------------------------------
+# Any other [lsp.servers.<name>] table defines a server of your own, and
+# needs a `command` or `preset` (a misspelled built-in server name is
+# skipped with a warning for lacking one). It attaches to the `filetypes` it
+# lists: built-in languages, where it runs alongside the language's own
+# server, or new filetypes, picked by `file_extensions`. Files of new
+# filetypes get LSP features without tree-sitter highlighting.
+#
+# Diagnostics and completions from every attached server are merged, and code
+# actions are gathered from all of them. Formatting and the other
+# single-answer requests go to the highest `priority` server that supports
+# them (default 0; the language's own server wins ties).
+# [lsp.servers.eslint]
+# preset = "eslint"
+# filetypes = ["typescript", "javascript"]
+#
+# [lsp.servers.ruff]
+# command = "ruff"
+# args = ["server"]
+# filetypes = ["python"]
+# priority = 1               # Format Python with ruff instead of pyright
+#
+# [lsp.servers.clangd]
+# command = "clangd"
+# filetypes = ["c", "cpp"]   # Sent as the document's languageId
+# file_extensions = ["c", "h", "cpp", "hpp", "cc"]
+# root_patterns = ["compile_commands.json", ".clangd"]
+# extension_filetypes = { h = "cpp" }  # Treat headers as C++ (default: C)
+#
+# [lsp.servers.lua]
+# command = "lua-language-server"
+# filetypes = ["lua"]
+# file_extensions = ["lua"]
+# root_patterns = [".luarc.json"]
 
 # ============================================================================
 # COPILOT
@@ -1555,6 +1876,27 @@ fn ensure_config_exists() {
     let _ = ensure_config_file_exists();
 }
 
+/// Read the LSP server configurations from the config file again, for
+/// pushing edited settings to running servers. Unlike `load_config`, a file
+/// that can't be read or parsed is an error rather than the defaults.
+/// User-defined servers that can't start are left out, with the reasons.
+pub fn load_lsp_servers() -> Result<(LspServers, Vec<String>), String> {
+    let path = config_path().ok_or_else(|| "Could not determine config path".to_string())?;
+    let content =
+        std::fs::read_to_string(&path).map_err(|e| format!("Failed to read config file: {}", e))?;
+    parse_lsp_servers(&content)
+}
+
+fn parse_lsp_servers(content: &str) -> Result<(LspServers, Vec<String>), String> {
+    let mut settings = toml::from_str::<Settings>(content)
+        .map_err(|e| format!("Failed to parse config file: {}", e))?;
+    let problems = settings.lsp.servers.remove_invalid_additional();
+    Ok((
+        merge_lsp_servers_with_defaults(settings.lsp.servers),
+        problems,
+    ))
+}
+
 /// Load settings from the config file
 /// Returns default settings if the file doesn't exist or can't be parsed
 /// User settings are merged with defaults - user values take precedence,
@@ -1574,6 +1916,9 @@ pub fn load_config() -> Settings {
     match std::fs::read_to_string(&path) {
         Ok(content) => match toml::from_str::<Settings>(&content) {
             Ok(mut user_settings) => {
+                for problem in user_settings.lsp.servers.remove_invalid_additional() {
+                    eprintln!("Warning: Ignoring LSP server: {}", problem);
+                }
                 // Merge leader mappings: defaults + user overrides
                 user_settings.keymap.leader_mappings =
                     merge_leader_mappings(&user_settings.keymap.leader_mappings);
@@ -1617,6 +1962,7 @@ fn merge_lsp_servers_with_defaults(user: LspServers) -> LspServers {
         go: merge_lsp_server_config(defaults.go, user.go),
         ruby: merge_lsp_server_config(defaults.ruby, user.ruby),
         shell: merge_lsp_server_config(defaults.shell, user.shell),
+        additional: user.additional,
     }
 }
 
@@ -1647,6 +1993,11 @@ fn merge_lsp_server_config(default: LspServerConfig, user: LspServerConfig) -> L
         } else {
             user.file_extensions
         },
+        priority: user.priority,
+        filetypes: user.filetypes,
+        extension_filetypes: user.extension_filetypes,
+        settings: user.settings,
+        initialization_options: user.initialization_options,
     }
 }
 
@@ -1717,6 +2068,92 @@ fn merge_explorer_mappings(user_mappings: &[ExplorerModeMapping]) -> Vec<Explore
 mod tests {
     use super::*;
 
+    #[test]
+    fn lsp_server_settings_and_initialization_options_become_json() {
+        let (servers, _) = parse_lsp_servers(
+            r#"
+            [lsp.servers.rust.settings.rust-analyzer]
+            cargo.features = "all"
+            check.command = "clippy"
+
+            [lsp.servers.python.initialization_options]
+            logLevel = 2
+            "#,
+        )
+        .expect("parse servers");
+
+        assert_eq!(
+            servers.rust.settings_json(),
+            serde_json::json!({
+                "rust-analyzer": {
+                    "cargo": { "features": "all" },
+                    "check": { "command": "clippy" }
+                }
+            })
+        );
+        assert_eq!(servers.rust.initialization_options_json(), None);
+        assert_eq!(
+            servers.python.initialization_options_json(),
+            Some(serde_json::json!({ "logLevel": 2 }))
+        );
+        assert_eq!(servers.python.settings_json(), serde_json::json!({}));
+        assert_eq!(servers.rust.command, "rust-analyzer");
+    }
+
+    #[test]
+    fn lsp_server_reload_reports_invalid_config() {
+        let error = parse_lsp_servers("[lsp.servers.rust\n").unwrap_err();
+
+        assert!(error.starts_with("Failed to parse config file"));
+    }
+
+    #[test]
+    fn misspelled_or_incomplete_additional_servers_are_skipped() {
+        let (servers, problems) = parse_lsp_servers(
+            r#"
+            [lsp.servers.rust]
+            priority = 2
+
+            [lsp.servers.rsut.settings.rust-analyzer]
+            check.command = "clippy"
+
+            [lsp.servers.pyton]
+            enabled = false
+
+            [lsp.servers.zls]
+            command = "zls"
+
+            # Complete servers are fine, even when named like a built-in one.
+            [lsp.servers.ruff]
+            command = "ruff"
+            filetypes = ["python"]
+
+            [lsp.servers.eslint]
+            preset = "eslint"
+            filetypes = ["typescript"]
+            "#,
+        )
+        .expect("parse servers");
+
+        assert_eq!(
+            problems,
+            vec![
+                "[lsp.servers.pyton] is not a built-in server and has no `command` \
+                 (did you mean [lsp.servers.python]?)",
+                "[lsp.servers.rsut] is not a built-in server and has no `command` \
+                 (did you mean [lsp.servers.rust]?)",
+                "[lsp.servers.zls] is not a built-in server and has no `filetypes` or \
+                 `file_extensions`",
+            ]
+        );
+        // Only the bad tables are left out.
+        assert_eq!(
+            servers.additional.keys().collect::<Vec<_>>(),
+            vec!["eslint", "ruff"]
+        );
+        assert_eq!(servers.rust.priority, 2);
+    }
+
     #[test]
     fn lsp_server_partial_config_keeps_default_command_and_args() {
         let mut settings: Settings = toml::from_str(
@@ -1746,6 +2183,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn additional_lsp_servers_are_configured_by_name() {
+        let mut settings: Settings = toml::from_str(
+            r#"
+            [lsp.servers.python]
+            priority = -1
+
+            [lsp.servers.ruff]
+            command = "ruff"
+            args = ["server"]
+            filetypes = ["python"]
+            priority = 1
+            "#,
+        )
+        .expect("parse settings");
+
+        settings.lsp.servers = merge_lsp_servers_with_defaults(settings.lsp.servers);
+
+        assert_eq!(settings.lsp.servers.python.priority, -1);
+        assert_eq!(
+            settings.lsp.servers.python.effective_command(),
+            "pyright-langserver"
+        );
+        let ruff = &settings.lsp.servers.additional["ruff"];
+        assert_eq!(ruff.effective_command(), "ruff");
+        assert_eq!(ruff.effective_args(), vec!["server".to_string()]);
+        assert_eq!(ruff.filetypes, vec!["python".to_string()]);
+        assert_eq!(ruff.priority, 1);
+        assert!(ruff.enabled);
+        assert_eq!(settings.lsp.servers.additional.len(), 1);
+    }
+
     #[test]
     fn lsp_preset_can_be_configured_with_documented_rust_analyzer_name() {
         let settings: Settings = toml::from_str(
@@ -4,13 +4,34 @@ use std::fs::{self, File, OpenOptions};
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
+use tree_sitter::{InputEdit, Point};
+
+use super::options::{BufferOptions, FileFormat};
+use crate::lsp::ContentChange;
 
 /// Unicode scalar values taken from the first line for shebang detection.
 const FIRST_LINE_PREFIX_CHARS: usize = 256;
 
+/// Pending syntax edits kept before giving up and asking for a full reparse.
+const MAX_PENDING_SYNTAX_EDITS: usize = 4096;
+
+/// Pending `textDocument/didChange` changes kept before giving up and
+/// sending the whole text.
+const MAX_PENDING_LSP_CHANGES: usize = 4096;
+
+/// Edits since the syntax tree last caught up with the buffer.
+#[derive(Debug, Clone, Default)]
+pub struct SyntaxEdits {
+    /// Buffer version the edits apply on top of
+    pub base_version: u64,
+    /// `None` when the text changed in a way that was not tracked
+    pub edits: Option<Vec<InputEdit>>,
+}
+
 /// A text buffer backed by a rope data structure.
 /// Ropes provide O(log n) insertions and deletions, making them
 /// ideal for text editors.
+#[derive(Clone)]
 pub struct Buffer {
     /// The text content
     text: Rope,
@@ -23,6 +44,22 @@ pub struct Buffer {
     /// Last known modification time of the file on disk (for autoread)
     last_mtime: Option<SystemTime>,
     kind: BufferKind,
+    /// Buffer-local options (`:setlocal`)
+    pub options: BufferOptions,
+    /// Edits not yet taken by the syntax tree
+    syntax_edits: SyntaxEdits,
+    /// Edits not yet sent to the language server; `None` when the text
+    /// changed in a way that was not tracked
+    lsp_changes: Option<Vec<ContentChange>>,
+}
+
+/// Where an edit starts or ends, taken before the text changes
+#[derive(Debug, Clone, Copy)]
+struct EditPosition {
+    byte: usize,
+    point: Point,
+    /// Column in UTF-16 code units, as language servers count them
+    utf16_col: u32,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -48,6 +85,9 @@ impl Buffer {
             version: 0,
             last_mtime: None,
             kind: BufferKind::Untitled,
+            options: BufferOptions::default(),
+            syntax_edits: SyntaxEdits::default(),
+            lsp_changes: Some(Vec::new()),
         }
     }
 
@@ -62,13 +102,13 @@ impl Buffer {
     }
 
     fn from_file_with_read_only(path: PathBuf, read_only: bool) -> anyhow::Result<Self> {
-        let (text, last_mtime) = if path.exists() {
+        let (text, file_format, last_mtime) = if path.exists() {
             let mtime = std::fs::metadata(&path)?.modified().ok();
-            let rope = Rope::from_reader(std::fs::File::open(&path)?)?;
-            (rope, mtime)
+            let (rope, file_format) = read_file_text(&path)?;
+            (rope, file_format, mtime)
         } else {
             // New file that doesn't exist yet
-            (Rope::new(), None)
+            (Rope::new(), FileFormat::default(), None)
         };
 
         Ok(Self {
@@ -78,6 +118,12 @@ impl Buffer {
             version: 0,
             last_mtime,
             kind: BufferKind::File { read_only },
+            options: BufferOptions {
+                file_format,
+                ..BufferOptions::default()
+            },
+            syntax_edits: SyntaxEdits::default(),
+            lsp_changes: Some(Vec::new()),
         })
     }
 
@@ -98,6 +144,9 @@ impl Buffer {
                 read_only: true,
                 syntax_hint_path,
             },
+            options: BufferOptions::default(),
+            syntax_edits: SyntaxEdits::default(),
+            lsp_changes: Some(Vec::new()),
         }
     }
 
@@ -155,8 +204,16 @@ impl Buffer {
             .as_ref()
             .ok_or_else(|| anyhow::anyhow!("No file path set"))?;
 
+        let file_format = self.options.file_format;
         write_file_atomically(path, |writer| {
-            self.text.write_to(writer)?;
+            match file_format {
+                FileFormat::Unix => self.text.write_to(writer)?,
+                FileFormat::Dos => {
+                    for chunk in self.text.chunks() {
+                        writer.write_all(chunk.replace('\n', "\r\n").as_bytes())?;
+                    }
+                }
+            }
             Ok(())
         })?;
         self.dirty = false;
@@ -193,7 +250,11 @@ impl Buffer {
             .ok_or_else(|| anyhow::anyhow!("No file path set"))?;
 
         if path.exists() {
-            self.text = Rope::from_reader(std::fs::File::open(path)?)?;
+            let (text, file_format) = read_file_text(path)?;
+            self.text = text;
+            self.options.file_format = file_format;
+            self.syntax_edits.edits = None;
+            self.lsp_changes = None;
             self.last_mtime = std::fs::metadata(path).ok().and_then(|m| m.modified().ok());
             self.dirty = false;
             self.version = self.version.wrapping_add(1);
@@ -273,10 +334,103 @@ impl Buffer {
             return;
         }
         self.text = Rope::from_str(content);
+        self.syntax_edits.edits = None;
+        self.lsp_changes = None;
         self.dirty = true;
         self.version = self.version.wrapping_add(1);
     }
 
+    /// Byte offset, tree-sitter position and UTF-16 column of a char index.
+    fn edit_position(&self, char_idx: usize) -> EditPosition {
+        let byte = self.text.char_to_byte(char_idx);
+        let row = self.text.char_to_line(char_idx);
+        let line_start = self.text.line_to_char(row);
+        let utf16_col =
+            self.text.char_to_utf16_cu(char_idx) - self.text.char_to_utf16_cu(line_start);
+        EditPosition {
+            byte,
+            point: Point {
+                row,
+                column: byte - self.text.line_to_byte(row),
+            },
+            utf16_col: utf16_col as u32,
+        }
+    }
+
+    /// Note that the chars from `start` up to `old_end` were just replaced by
+    /// the chars from `start` up to `new_end`. Positions before the edit are
+    /// taken before the text changes.
+    fn record_edit(&mut self, start: EditPosition, old_end: EditPosition, new_end: usize) {
+        if let Some(changes) = self.lsp_changes.as_mut() {
+            if changes.len() >= MAX_PENDING_LSP_CHANGES {
+                self.lsp_changes = None;
+            } else {
+                let start_char = self.text.byte_to_char(start.byte);
+                changes.push(ContentChange {
+                    range: Some((
+                        (start.point.row as u32, start.utf16_col),
+                        (old_end.point.row as u32, old_end.utf16_col),
+                    )),
+                    text: self.text.slice(start_char..new_end).to_string(),
+                });
+            }
+        }
+
+        let new_end = self.edit_position(new_end);
+        let Some(edits) = self.syntax_edits.edits.as_mut() else {
+            return;
+        };
+        if edits.len() >= MAX_PENDING_SYNTAX_EDITS {
+            self.syntax_edits.edits = None;
+            return;
+        }
+        edits.push(InputEdit {
+            start_byte: start.byte,
+            old_end_byte: old_end.byte,
+            new_end_byte: new_end.byte,
+            start_position: start.point,
+            old_end_position: old_end.point,
+            new_end_position: new_end.point,
+        });
+    }
+
+    /// Take the edits made since the last call, for an incremental reparse.
+    pub fn take_syntax_edits(&mut self) -> SyntaxEdits {
+        std::mem::replace(
+            &mut self.syntax_edits,
+            SyntaxEdits {
+                base_version: self.version,
+                edits: Some(Vec::new()),
+            },
+        )
+    }
+
+    /// Take the edits made since the last call, for an incremental
+    /// `textDocument/didChange`. `None` when the text changed in a way that
+    /// was not tracked, and the whole text has to be sent.
+    pub fn take_lsp_changes(&mut self) -> Option<Vec<ContentChange>> {
+        self.lsp_changes.replace(Vec::new())
+    }
+
+    /// The rest of the rope chunk holding `byte`, empty at the end of the text.
+    pub fn chunk_from_byte(&self, byte: usize) -> &str {
+        if byte >= self.text.len_bytes() {
+            return "";
+        }
+        let (chunk, chunk_start, _, _) = self.text.chunk_at_byte(byte);
+        &chunk[byte - chunk_start..]
+    }
+
+    /// Text between two byte offsets.
+    pub fn text_between_bytes(&self, start: usize, end: usize) -> String {
+        self.text.byte_slice(start..end).to_string()
+    }
+
+    /// Iterate over the text in rope-sized chunks.
+    pub fn chunks(&self) -> impl Iterator<Item = &str> {
+        self.text.chunks()
+    }
+
     /// Get the char index for a given line and column
     pub fn line_col_to_char(&self, line: usize, col: usize) -> usize {
         if line >= self.text.len_lines() {
@@ -294,7 +448,9 @@ impl Buffer {
             return;
         }
         let idx = self.line_col_to_char(line, col);
+        let start = self.edit_position(idx);
         self.text.insert_char(idx, ch);
+        self.record_edit(start, start, idx + 1);
         self.dirty = true;
         self.version = self.version.wrapping_add(1);
     }
@@ -305,7 +461,9 @@ impl Buffer {
             return;
         }
         let idx = self.line_col_to_char(line, col);
+        let start = self.edit_position(idx);
         self.text.insert(idx, s);
+        self.record_edit(start, start, idx + s.chars().count());
         self.dirty = true;
         self.version = self.version.wrapping_add(1);
     }
@@ -317,7 +475,10 @@ impl Buffer {
         }
         let idx = self.line_col_to_char(line, col);
         if idx < self.text.len_chars() {
+            let start = self.edit_position(idx);
+            let old_end = self.edit_position(idx + 1);
             self.text.remove(idx..idx + 1);
+            self.record_edit(start, old_end, idx);
             self.dirty = true;
             self.version = self.version.wrapping_add(1);
         }
@@ -337,7 +498,10 @@ impl Buffer {
         let start = self.line_col_to_char(start_line, start_col);
         let end = self.line_col_to_char(end_line, end_col);
         if start < end && end <= self.text.len_chars() {
+            let start_position = self.edit_position(start);
+            let old_end = self.edit_position(end);
             self.text.remove(start..end);
+            self.record_edit(start_position, old_end, start);
             self.dirty = true;
             self.version = self.version.wrapping_add(1);
         }
@@ -360,6 +524,9 @@ impl Buffer {
             self.text.len_chars()
         };
 
+        let start = self.edit_position(start_idx);
+        let old_end = self.edit_position(end_idx);
+
         // Remove the old line content
         if start_idx < end_idx {
             self.text.remove(start_idx..end_idx);
@@ -376,6 +543,11 @@ impl Buffer {
         };
 
         self.text.insert(start_idx, &content_to_insert);
+        self.record_edit(
+            start,
+            old_end,
+            start_idx + content_to_insert.chars().count(),
+        );
         self.dirty = true;
         self.version = self.version.wrapping_add(1);
     }
@@ -477,18 +649,22 @@ impl Buffer {
     /// Deletes old_text at position and inserts new_text
     pub fn apply_change(&mut self, line: usize, col: usize, old_text: &str, new_text: &str) {
         let idx = self.line_col_to_char(line, col);
+        let start = self.edit_position(idx);
 
         // Delete old text if any
         if !old_text.is_empty() {
             let end_idx = idx + old_text.chars().count();
             if end_idx <= self.text.len_chars() {
+                let old_end = self.edit_position(end_idx);
                 self.text.remove(idx..end_idx);
+                self.record_edit(start, old_end, idx);
             }
         }
 
         // Insert new text if any
         if !new_text.is_empty() {
             self.text.insert(idx, new_text);
+            self.record_edit(start, start, idx + new_text.chars().count());
         }
 
         self.dirty = true;
@@ -502,6 +678,17 @@ impl Default for Buffer {
     }
 }
 
+/// Read a file, converting CRLF line endings when every line has one.
+fn read_file_text(path: &Path) -> io::Result<(Rope, FileFormat)> {
+    let content = fs::read_to_string(path)?;
+    let file_format = FileFormat::detect(&content);
+    let text = match file_format {
+        FileFormat::Unix => Rope::from_str(&content),
+        FileFormat::Dos => Rope::from_str(&content.replace("\r\n", "\n")),
+    };
+    Ok((text, file_format))
+}
+
 fn write_file_atomically(
     path: &Path,
     write_contents: impl FnOnce(&mut dyn Write) -> anyhow::Result<()>,
@@ -0,0 +1,222 @@
+//! LSP code lenses (`textDocument/codeLens`): commands such as "▶ Run test"
+//! or "3 references" drawn on a virtual row above the line they apply to,
+//! and run through `workspace/executeCommand` with `:CodeLens`.
+
+use std::path::PathBuf;
+
+use super::{Editor, LspAction};
+use crate::lsp::types::{CodeLens, LspCommand};
+
+/// The lenses of one buffer version, in the order the server sent them so
+/// resolves can refer to them by index
+#[derive(Debug, Clone, Default)]
+pub(super) struct CodeLensCache {
+    version: u64,
+    /// The server asked for the lenses to be requested again
+    stale: bool,
+    lenses: Vec<CodeLens>,
+}
+
+/// A request sent for the current buffer, answered or not
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct CodeLensRequest {
+    uri: String,
+    version: u64,
+}
+
+impl Editor {
+    /// `:set codelens`
+    pub fn code_lens_enabled(&self) -> bool {
+        self.settings.editor.code_lens
+    }
+
+    /// The current buffer's code lens request, as `(path, buffer_version)`,
+    /// when its lenses are not for this version and none are on the way.
+    /// Records the request as sent.
+    pub fn code_lens_request(&mut self) -> Option<(PathBuf, u64)> {
+        if !self.code_lens_enabled() {
+            return None;
+        }
+        let path = self.buffer().path.clone()?;
+        let request = CodeLensRequest {
+            uri: crate::lsp::path_to_uri(&path),
+            version: self.buffer().version(),
+        };
+        if self
+            .code_lenses
+            .get(&request.uri)
+            .is_some_and(|cache| cache.version == request.version && !cache.stale)
+            || self.code_lens_request.as_ref() == Some(&request)
+        {
+            return None;
+        }
+        let version = request.version;
+        self.code_lens_request = Some(request);
+        Some((path, version))
+    }
+
+    /// Store the lenses of the buffer at `uri`, replacing those of an older
+    /// version. Returns the lenses still to resolve, as `(index, lens)`.
+    pub fn set_code_lenses(
+        &mut self,
+        uri: &str,
+        version: u64,
+        lenses: Vec<CodeLens>,
+    ) -> Vec<(usize, serde_json::Value)> {
+        if self
+            .code_lens_request
+            .as_ref()
+            .is_some_and(|request| request.uri == uri && request.version == version)
+        {
+            self.code_lens_request = None;
+        }
+        let Some(buffer) = self.buffers.iter().find(|buffer| {
+            buffer.path.as_ref().map(crate::lsp::path_to_uri).as_deref() == Some(uri)
+        }) else {
+            return Vec::new();
+        };
+        if buffer.version() != version {
+            return Vec::new();
+        }
+
+        let unresolved = lenses
+            .iter()
+            .enumerate()
+            .filter(|(_, lens)| lens.command.is_none())
+            .map(|(index, lens)| (index, lens.raw.clone()))
+            .collect();
+        self.code_lenses.insert(
+            uri.to_string(),
+            CodeLensCache {
+                version,
+                stale: false,
+                lenses,
+            },
+        );
+        self.render_damage.mark_full();
+        unresolved
+    }
+
+    /// Fill in the command of lens `index` of the lenses for `version`.
+    /// Returns true when a shown lens changed.
+    pub fn resolve_code_lens(
+        &mut self,
+        uri: &str,
+        version: u64,
+        index: usize,
+        lens: CodeLens,
+    ) -> bool {
+        let Some(cached) = self
+            .code_lenses
+            .get_mut(uri)
+            .filter(|cache| cache.version == version)
+            .and_then(|cache| cache.lenses.get_mut(index))
+        else {
+            return false;
+        };
+        cached.command = lens.command;
+        self.render_damage.mark_full();
+        true
+    }
+
+    /// Request every buffer's lenses again, showing the old ones until the
+    /// new ones arrive (`workspace/codeLens/refresh`)
+    pub fn refresh_code_lenses(&mut self) {
+        for cache in self.code_lenses.values_mut() {
+            cache.stale = true;
+        }
+        self.code_lens_request = None;
+    }
+
+    /// Drop every lens (`:set nocodelens`)
+    pub fn clear_code_lenses(&mut self) {
+        self.code_lenses.clear();
+        self.code_lens_request = None;
+        self.render_damage.mark_full();
+    }
+
+    /// Whether any buffer has lenses to draw
+    pub fn has_code_lenses(&self) -> bool {
+        self.code_lens_enabled()
+            && self
+                .code_lenses
+                .values()
+                .any(|cache| cache.lenses.iter().any(|lens| lens.command.is_some()))
+    }
+
+    /// Resolved lenses on `line` of buffer `buffer_idx`, in server order.
+    /// Lenses of an older version stay until the new ones arrive, so lines
+    /// do not jump while typing.
+    fn code_lenses_on_line(&self, buffer_idx: usize, line: usize) -> Vec<&LspCommand> {
+        if !self.code_lens_enabled() {
+            return Vec::new();
+        }
+        let Some(cache) = self
+            .buffers
+            .get(buffer_idx)
+            .and_then(|buffer| buffer.path.as_ref())
+            .and_then(|path| self.code_lenses.get(&crate::lsp::path_to_uri(path)))
+        else {
+            return Vec::new();
+        };
+        cache
+            .lenses
+            .iter()
+            .filter(|lens| lens.line == line)
+            .filter_map(|lens| lens.command.as_ref())
+            .filter(|command| !command.title.is_empty())
+            .collect()
+    }
+
+    /// The text of the virtual row above `line` of buffer `buffer_idx`
+    pub fn code_lens_label(&self, buffer_idx: usize, line: usize) -> Option<String> {
+        let commands = self.code_lenses_on_line(buffer_idx, line);
+        (!commands.is_empty()).then(|| {
+            commands
+                .iter()
+                .map(|command| command.title.as_str())
+                .collect::<Vec<_>>()
+                .join(" | ")
+        })
+    }
+
+    /// Virtual rows the lenses of lines `start_line..end_line` take in a
+    /// pane showing `start_line` at the top. The top line's lenses are only
+    /// drawn while its first row is on screen (`top_segment` 0).
+    pub fn code_lens_rows(
+        &self,
+        buffer_idx: usize,
+        start_line: usize,
+        end_line: usize,
+        top_segment: usize,
+    ) -> usize {
+        if !self.has_code_lenses() {
+            return 0;
+        }
+        (start_line..end_line)
+            .filter(|&line| line != start_line || top_segment == 0)
+            .filter(|&line| !self.code_lenses_on_line(buffer_idx, line).is_empty())
+            .count()
+    }
+
+    /// Run lens `number` (1-based) of the cursor line, or its first lens
+    pub fn run_code_lens(&mut self, number: Option<usize>) {
+        let commands = self.code_lenses_on_line(self.current_buffer_idx, self.cursor.line);
+        if commands.is_empty() {
+            self.set_status("No code lens on this line");
+            return;
+        }
+        let Some(command) = commands
+            .get(number.unwrap_or(1).saturating_sub(1))
+            .map(|command| (*command).clone())
+        else {
+            self.set_status(format!(
+                "Code lens {} out of range (1-{})",
+                number.unwrap_or(1),
+                commands.len()
+            ));
+            return;
+        };
+        self.pending_lsp_action = Some(LspAction::ExecuteCommand(command));
+    }
+}
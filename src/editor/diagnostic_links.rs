@@ -0,0 +1,53 @@
+//! What a diagnostic points at besides its own range: the related locations
+//! listed in the diagnostic float, jumped to by number, and the page that
+//! explains its code, opened by `gx`.
+
+use super::Editor;
+use crate::lsp::types::DiagnosticRelatedInformation;
+
+impl Editor {
+    /// Related locations of the diagnostics on the cursor line, in the
+    /// order the diagnostic float numbers them
+    pub fn diagnostic_related_for_float(&self) -> Vec<&DiagnosticRelatedInformation> {
+        self.diagnostics_for_line(self.cursor.line)
+            .into_iter()
+            .flat_map(|diag| diag.related.iter())
+            .collect()
+    }
+
+    /// Jump to related location `number` (1-based) of the diagnostic float
+    pub fn goto_diagnostic_related(&mut self, number: usize) -> Result<(), String> {
+        let related = number
+            .checked_sub(1)
+            .and_then(|idx| self.diagnostic_related_for_float().get(idx).copied())
+            .cloned()
+            .ok_or_else(|| format!("No related location {}", number))?;
+        let path = crate::lsp::uri_to_path(&related.uri)
+            .ok_or_else(|| format!("Cannot open {}", related.uri))?;
+
+        self.record_jump();
+        self.open_file(path)
+            .map_err(|err| format!("Error opening file: {}", err))?;
+        self.goto_line(related.line + 1);
+        self.cursor.col = self.lsp_utf16_col_to_buffer_col(self.cursor.line, related.col);
+        self.clamp_cursor();
+        self.scroll_to_cursor();
+        Ok(())
+    }
+
+    /// The `codeDescription.href` of the diagnostic under the cursor, or of
+    /// the first diagnostic on the cursor line that has one. Like URLs in the
+    /// text, only http(s) links are opened; servers may send any URI.
+    pub fn diagnostic_href_at_cursor(&self) -> Option<String> {
+        let line_diagnostics: Vec<_> = self
+            .diagnostics_for_line(self.cursor.line)
+            .into_iter()
+            .filter(|diag| diag.href.as_deref().is_some_and(Self::is_supported_url))
+            .collect();
+        line_diagnostics
+            .iter()
+            .find(|diag| diag.col_start <= self.cursor.col && self.cursor.col <= diag.col_end)
+            .or_else(|| line_diagnostics.first())
+            .and_then(|diag| diag.href.clone())
+    }
+}
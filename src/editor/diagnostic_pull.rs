@@ -0,0 +1,37 @@
+//! Pull diagnostics (`textDocument/diagnostic`): the current buffer's
+//! diagnostics are asked for once per version, for servers that answer
+//! pulls instead of publishing.
+
+use std::path::PathBuf;
+
+use super::Editor;
+
+/// The buffer version diagnostics were last pulled for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct DiagnosticPullRequest {
+    uri: String,
+    version: u64,
+}
+
+impl Editor {
+    /// The current buffer's path when its diagnostics were not pulled for
+    /// this version yet. Records the pull as sent.
+    pub fn diagnostic_pull_request(&mut self) -> Option<PathBuf> {
+        let path = self.buffer().path.clone()?;
+        let request = DiagnosticPullRequest {
+            uri: crate::lsp::path_to_uri(&path),
+            version: self.buffer().version(),
+        };
+        if self.diagnostic_pull_request.as_ref() == Some(&request) {
+            return None;
+        }
+        self.diagnostic_pull_request = Some(request);
+        Some(path)
+    }
+
+    /// Pull the current buffer's diagnostics again even though it did not
+    /// change (`workspace/diagnostic/refresh`)
+    pub fn refresh_pulled_diagnostics(&mut self) {
+        self.diagnostic_pull_request = None;
+    }
+}
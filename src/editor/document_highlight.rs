@@ -0,0 +1,203 @@
+//! LSP document highlights (`textDocument/documentHighlight`): the reads and
+//! writes of the symbol under the cursor, requested once the cursor rests
+//! and drawn behind the text while it stays on one of them.
+
+use std::path::PathBuf;
+
+use super::{Editor, Mode};
+use crate::lsp::types::{DocumentHighlight, DocumentHighlightKind};
+
+/// A highlighted run of one line, in buffer (char) columns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DocumentHighlightSpan {
+    pub line: usize,
+    pub start_col: usize,
+    /// Exclusive
+    pub end_col: usize,
+    pub kind: DocumentHighlightKind,
+}
+
+/// Highlights the server returned for one buffer version
+#[derive(Debug, Clone)]
+pub(super) struct DocumentHighlights {
+    uri: String,
+    version: u64,
+    /// Sorted by position
+    spans: Vec<DocumentHighlightSpan>,
+}
+
+impl DocumentHighlights {
+    /// Whether the cursor at `line`/`col` is on (or just past) a highlight
+    fn contains(&self, line: usize, col: usize) -> bool {
+        self.spans
+            .iter()
+            .any(|span| span.line == line && span.start_col <= col && col <= span.end_col)
+    }
+}
+
+/// The position highlights were last requested for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct DocumentHighlightRequest {
+    uri: String,
+    version: u64,
+    line: u32,
+    character: u32,
+}
+
+impl Editor {
+    /// `:set documenthighlight`
+    pub fn document_highlight_enabled(&self) -> bool {
+        self.settings.editor.document_highlight
+    }
+
+    /// Whether the stored highlights still belong to the cursor position
+    fn document_highlights_current(&self) -> bool {
+        let Some(highlights) = &self.document_highlights else {
+            return false;
+        };
+        let buffer = self.buffer();
+        buffer.path.as_ref().map(crate::lsp::path_to_uri).as_deref()
+            == Some(highlights.uri.as_str())
+            && buffer.version() == highlights.version
+            && highlights.contains(self.cursor.line, self.cursor.col)
+    }
+
+    /// The cursor position to request highlights for, as `(path, line,
+    /// character, buffer_version)`, when the cursor rests in Normal mode
+    /// off the current highlights and that position was not asked about
+    /// yet. Drops highlights the cursor has left and records the request as
+    /// sent.
+    pub fn document_highlight_request(&mut self) -> Option<(PathBuf, u32, u32, u64)> {
+        if self.document_highlights.is_some() && !self.document_highlights_current() {
+            self.document_highlights = None;
+            self.document_highlight_request = None;
+            self.render_damage.mark_full();
+        }
+        if !self.document_highlight_enabled()
+            || self.mode != Mode::Normal
+            || self.document_highlights.is_some()
+        {
+            return None;
+        }
+        let path = self.buffer().path.clone()?;
+        let line_text = self
+            .buffer()
+            .line(self.cursor.line)
+            .map(|line| line.to_string())
+            .unwrap_or_default();
+        let line_text = line_text.trim_end_matches('\n');
+        let character = crate::copilot::utf8_to_utf16_col(
+            line_text,
+            self.cursor.col.min(line_text.chars().count()),
+        );
+        let request = DocumentHighlightRequest {
+            uri: crate::lsp::path_to_uri(&path),
+            version: self.buffer().version(),
+            line: self.cursor.line as u32,
+            character,
+        };
+        if self.document_highlight_request.as_ref() == Some(&request) {
+            return None;
+        }
+        let version = request.version;
+        self.document_highlight_request = Some(request);
+        Some((path, self.cursor.line as u32, character, version))
+    }
+
+    /// Store the highlights answering the request for `line`/`character`
+    /// of the buffer at `uri`. Answers to older requests are dropped.
+    /// Returns true when the highlights changed.
+    pub fn set_document_highlights(
+        &mut self,
+        uri: &str,
+        version: u64,
+        line: u32,
+        character: u32,
+        highlights: Vec<DocumentHighlight>,
+    ) -> bool {
+        let answered = DocumentHighlightRequest {
+            uri: uri.to_string(),
+            version,
+            line,
+            character,
+        };
+        if self.document_highlight_request.as_ref() != Some(&answered) {
+            return false;
+        }
+        let Some(buffer) = self.buffers.iter().find(|buffer| {
+            buffer.path.as_ref().map(crate::lsp::path_to_uri).as_deref() == Some(uri)
+        }) else {
+            return false;
+        };
+        if buffer.version() != version {
+            return false;
+        }
+
+        let last_line = buffer.len_lines().saturating_sub(1);
+        let mut spans = Vec::new();
+        for highlight in highlights {
+            let range = highlight.range;
+            for line in range.start_line..=range.end_line.min(last_line) {
+                let start_col = if line == range.start_line {
+                    Self::lsp_utf16_col_to_buffer_col_in_buffer(buffer, line, range.start_col)
+                } else {
+                    0
+                };
+                let end_col = if line == range.end_line {
+                    Self::lsp_utf16_col_to_buffer_col_in_buffer(buffer, line, range.end_col)
+                } else {
+                    buffer.line_len(line)
+                };
+                if start_col < end_col {
+                    spans.push(DocumentHighlightSpan {
+                        line,
+                        start_col,
+                        end_col,
+                        kind: highlight.kind,
+                    });
+                }
+            }
+        }
+        spans.sort_by_key(|span| (span.line, span.start_col));
+
+        let had_highlights = self.has_document_highlights();
+        self.document_highlights = (!spans.is_empty()).then(|| DocumentHighlights {
+            uri: uri.to_string(),
+            version,
+            spans,
+        });
+        if !had_highlights && !self.has_document_highlights() {
+            return false;
+        }
+        self.render_damage.mark_full();
+        true
+    }
+
+    /// Drop the highlights shown and forget the last request
+    /// (`:set documenthighlight`)
+    pub fn clear_document_highlights(&mut self) {
+        self.document_highlights = None;
+        self.document_highlight_request = None;
+        self.render_damage.mark_full();
+    }
+
+    /// Whether any highlights are stored; the renderer redraws whole panes
+    /// while they are, since moving the cursor off them hides them.
+    pub fn has_document_highlights(&self) -> bool {
+        self.document_highlights.is_some()
+    }
+
+    /// Highlights to draw for buffer `buffer_idx`: those of the current
+    /// buffer while the cursor is on one of them.
+    pub fn document_highlights_for_buffer(&self, buffer_idx: usize) -> &[DocumentHighlightSpan] {
+        if buffer_idx != self.current_buffer_idx
+            || !self.document_highlight_enabled()
+            || !self.document_highlights_current()
+        {
+            return &[];
+        }
+        self.document_highlights
+            .as_ref()
+            .map_or(&[], |highlights| highlights.spans.as_slice())
+    }
+}
@@ -0,0 +1,27 @@
+//! Document sync for `textDocument/didOpen` and `textDocument/didChange`:
+//! the text a server opens a buffer with, and the edits made after it as
+//! ranged content changes.
+
+use std::path::Path;
+
+use super::Editor;
+use crate::lsp::ContentChange;
+
+impl Editor {
+    /// Text of the open buffer for `path`, for `textDocument/didOpen`. The
+    /// edits made so far are part of it, so they are not sent again.
+    pub fn lsp_open_text(&mut self, path: &Path) -> Option<String> {
+        let idx = self.buffer_index_for_path(path)?;
+        self.buffers[idx].take_lsp_changes();
+        Some(self.buffers[idx].content())
+    }
+
+    /// Edits made to the open buffer for `path` since the last call or
+    /// [`Editor::lsp_open_text`], in order, for `textDocument/didChange`.
+    /// `None` when they were not tracked, such as after a reload, and the
+    /// whole text has to be sent.
+    pub fn take_lsp_changes(&mut self, path: &Path) -> Option<Vec<ContentChange>> {
+        let idx = self.buffer_index_for_path(path)?;
+        self.buffers[idx].take_lsp_changes()
+    }
+}
@@ -0,0 +1,117 @@
+use super::{Editor, SearchDirection, SearchPattern};
+use crate::commands::{AddressBase, ExRange, LineAddress};
+
+impl Editor {
+    /// Resolve an Ex range to inclusive, 0-indexed `(first, last)` lines.
+    /// `None` addresses the cursor line, as a command without a range does.
+    pub fn resolve_ex_range(&self, range: Option<&ExRange>) -> Result<(usize, usize), String> {
+        let Some(range) = range else {
+            return Ok((self.cursor.line, self.cursor.line));
+        };
+
+        let start = self.resolve_line_address(&range.start, self.cursor.line)?;
+        let end = match &range.end {
+            Some(end) => {
+                let from = if range.end_from_start {
+                    start
+                } else {
+                    self.cursor.line
+                };
+                self.resolve_line_address(end, from)?
+            }
+            None => start,
+        };
+
+        // Vim asks before swapping a backwards range; we always accept it.
+        Ok((start.min(end), start.max(end)))
+    }
+
+    /// Resolve one Ex address relative to `from_line` (0-indexed).
+    pub fn resolve_line_address(
+        &self,
+        address: &LineAddress,
+        from_line: usize,
+    ) -> Result<usize, String> {
+        let last_line = self.buffer().addressable_line_count().saturating_sub(1) as isize;
+
+        let base = match &address.base {
+            AddressBase::CurrentLine => from_line as isize,
+            AddressBase::LastLine => last_line,
+            // `:0` addresses "before the first line"; clamp it to the first line.
+            AddressBase::Line(line) => line.saturating_sub(1) as isize,
+            AddressBase::Mark(name) => self.mark_line(*name)? as isize,
+            AddressBase::SearchForward(pattern) => {
+                self.pattern_address_line(pattern, from_line, SearchDirection::Forward)? as isize
+            }
+            AddressBase::SearchBackward(pattern) => {
+                self.pattern_address_line(pattern, from_line, SearchDirection::Backward)? as isize
+            }
+        };
+
+        let line = base.saturating_add(address.offset);
+        if line < 0 || line > last_line {
+            return Err("Invalid range".to_string());
+        }
+        Ok(line as usize)
+    }
+
+    fn mark_line(&self, name: char) -> Result<usize, String> {
+        let not_set = || format!("Mark not set: {}", name);
+        match name {
+            '<' | '>' => {
+                let selection = self.last_visual_selection.as_ref().ok_or_else(not_set)?;
+                let first = selection.anchor_line.min(selection.cursor_line);
+                let last = selection.anchor_line.max(selection.cursor_line);
+                Ok(if name == '<' { first } else { last })
+            }
+            '\'' | '`' => match &self.previous_jump_position {
+                Some((path, line, _)) if path == &self.buffer().path => Ok(*line),
+                _ => Err(not_set()),
+            },
+            _ => {
+                let mark = self
+                    .marks
+                    .get(&self.buffer_key(), name)
+                    .ok_or_else(not_set)?;
+                if mark
+                    .path
+                    .as_ref()
+                    .is_some_and(|path| Some(path) != self.buffer().path.as_ref())
+                {
+                    return Err(not_set());
+                }
+                Ok(mark.line)
+            }
+        }
+    }
+
+    /// Line of the next (or previous) match of `pattern` after `from_line`,
+    /// wrapping around the buffer. An empty pattern reuses the last search.
+    fn pattern_address_line(
+        &self,
+        pattern: &str,
+        from_line: usize,
+        direction: SearchDirection,
+    ) -> Result<usize, String> {
+        let (source, skip_smartcase) = if pattern.is_empty() {
+            let source = self
+                .search
+                .last_pattern
+                .clone()
+                .ok_or_else(|| "No previous regular expression".to_string())?;
+            (source, self.search.last_skips_smartcase)
+        } else {
+            (pattern.to_string(), false)
+        };
+
+        let compiled = SearchPattern::new(&source, self.search_case_options(skip_smartcase))?;
+        let col = match direction {
+            SearchDirection::Forward => usize::MAX,
+            SearchDirection::Backward => 0,
+        };
+        compiled
+            .find_in_buffer(self.buffer(), from_line, col, direction, true)
+            .map(|(found, _)| found.start_line)
+            .ok_or_else(|| format!("Pattern not found: {}", source))
+    }
+}
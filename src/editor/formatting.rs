@@ -0,0 +1,120 @@
+//! LSP range formatting (`gq{motion}`, `gqq`, Visual `gq`) and applying
+//! the edits of any formatting request, whole-document, range or on-type,
+//! without losing the cursor.
+
+use super::{Editor, LspAction, Mode};
+use crate::input::{Motion, TextObject, apply_motion};
+use crate::lsp::types::{TextEdit, TextRange};
+
+impl Editor {
+    /// Format with motion (gq{motion}), whole lines like `=`
+    pub fn format_motion(&mut self, motion: Motion, count: usize) {
+        let start_line = self.cursor.line;
+        let text_rows = self.text_rows().max(1);
+        let Some((target_line, _)) = apply_motion(
+            &self.buffers[self.current_buffer_idx],
+            motion,
+            self.cursor.line,
+            self.cursor.col,
+            count,
+            text_rows,
+        ) else {
+            return;
+        };
+        self.format_lines(start_line.min(target_line), start_line.max(target_line));
+    }
+
+    /// Format the current line and count-1 lines below (gqq)
+    pub fn format_line(&mut self, count: usize) {
+        let start_line = self.cursor.line;
+        self.format_lines(start_line, start_line + count.saturating_sub(1));
+    }
+
+    /// Format the lines of a text object (gqip, gqaf, ...)
+    pub fn format_text_object(&mut self, text_object: TextObject) {
+        if let Some((start_line, _, end_line, _)) = self.find_text_object_range(text_object) {
+            self.format_lines(start_line, end_line);
+        }
+    }
+
+    /// Visual `gq`: the exact selection in Visual mode, whole lines in
+    /// Visual Line and Visual Block
+    pub fn format_visual_selection(&mut self) {
+        let (start_line, start_col, end_line, end_col) = self.get_visual_range();
+        if self.mode != Mode::Visual {
+            self.format_lines(start_line, end_line);
+            return;
+        }
+        let end_col = (end_col + 1).min(self.buffer().line_len(end_line));
+        self.request_range_formatting(start_line, start_col, end_line, end_col);
+    }
+
+    /// Queue a range formatting request for lines `start_line..=end_line`
+    pub fn format_lines(&mut self, start_line: usize, end_line: usize) {
+        let last_line = self.buffer().len_lines().saturating_sub(1);
+        let end_line = end_line.min(last_line);
+        let end_col = self.buffer().line_len(end_line);
+        self.request_range_formatting(start_line.min(end_line), 0, end_line, end_col);
+    }
+
+    /// Hand the main loop a range formatting request for the buffer range
+    /// (char columns, end exclusive)
+    fn request_range_formatting(
+        &mut self,
+        start_line: usize,
+        start_col: usize,
+        end_line: usize,
+        end_col: usize,
+    ) {
+        if self.buffer().path.is_none() {
+            self.set_status("Range formatting needs a file with a language server");
+            return;
+        }
+        self.pending_lsp_action = Some(LspAction::RangeFormatting(TextRange {
+            start_line,
+            start_col: self.lsp_col(start_line, start_col),
+            end_line,
+            end_col: self.lsp_col(end_line, end_col),
+        }));
+    }
+
+    /// The UTF-16 column of char column `col` on `line`
+    fn lsp_col(&self, line: usize, col: usize) -> usize {
+        let line_text = self
+            .buffer()
+            .line(line)
+            .map(|line| line.to_string())
+            .unwrap_or_default();
+        let line_text = line_text.trim_end_matches('\n');
+        crate::copilot::utf8_to_utf16_col(line_text, col.min(line_text.chars().count())) as usize
+    }
+
+    /// Apply formatting edits as one undo step, keeping the cursor on the
+    /// text it was on. Text inserted right at the cursor lands after it, so
+    /// on-type formatting that completes what was typed leaves the cursor
+    /// where the typing stopped.
+    pub fn apply_formatting_edits(&mut self, edits: &[TextEdit]) {
+        if edits.is_empty() {
+            return;
+        }
+
+        // Edits never overlap, so walking them from the last to the first
+        // maps the cursor through each in the coordinates it sees.
+        let mut sorted: Vec<&TextEdit> = edits.iter().collect();
+        sorted.sort_by_key(|edit| std::cmp::Reverse((edit.start_line, edit.start_col)));
+        let mut cursor = (self.cursor.line, self.cursor.col);
+        for edit in sorted {
+            let (start, end) = self.text_edit_buffer_range(edit);
+            if start == end && start == cursor {
+                continue;
+            }
+            cursor = self.position_after_text_edit(cursor, edit);
+        }
+
+        self.apply_text_edits(edits);
+        self.cursor.line = cursor.0;
+        self.cursor.col = cursor.1;
+        self.clamp_cursor();
+        self.scroll_to_cursor();
+    }
+}
@@ -0,0 +1,171 @@
+//! `:global` and `:vglobal`: run an Ex command on every line that matches.
+//!
+//! All lines are marked before the first command runs. Each command's edits
+//! are read back from the open undo group, so marks follow lines as others are
+//! inserted or deleted, and a marked line that gets deleted is skipped.
+
+use std::collections::VecDeque;
+
+use super::{Change, Editor, SearchOffset, SearchPattern};
+use crate::commands::ExRange;
+
+/// An in-progress `:g` run.
+pub struct GlobalRun {
+    /// Marked lines still to visit, in ascending order.
+    lines: VecDeque<usize>,
+    /// Changes in the open undo group already applied to `lines`.
+    seen_changes: usize,
+    buffer_idx: usize,
+}
+
+impl Editor {
+    /// Mark the lines in `range` (default: whole file) that match `pattern`,
+    /// or that do not when `invert` is set, and open the undo step the run's
+    /// commands share.
+    pub fn begin_global(
+        &mut self,
+        range: Option<&ExRange>,
+        pattern: &str,
+        invert: bool,
+    ) -> Result<GlobalRun, String> {
+        let whole_file = ExRange::whole_file();
+        let (first_line, last_line) = self.resolve_ex_range(Some(range.unwrap_or(&whole_file)))?;
+
+        let source = if pattern.is_empty() {
+            self.search
+                .last_pattern
+                .clone()
+                .ok_or_else(|| "No previous regular expression".to_string())?
+        } else {
+            pattern.to_string()
+        };
+        let compiled = SearchPattern::new(&source, self.search_case_options(false))?;
+
+        // Like Vim, the pattern becomes the last search pattern, so `:g/x/s//y/`
+        // substitutes what was matched.
+        self.search.last_pattern = Some(source.clone());
+        self.search.last_offset = SearchOffset::None;
+        self.search.last_skips_smartcase = false;
+
+        let buffer = &self.buffers[self.current_buffer_idx];
+        let mut matched = vec![false; last_line + 1 - first_line];
+        for found in compiled.buffer_matches(buffer, first_line..last_line + 1, usize::MAX) {
+            matched[found.start_line - first_line] = true;
+        }
+        let lines: VecDeque<usize> = matched
+            .iter()
+            .enumerate()
+            .filter(|(_, matched)| **matched != invert)
+            .map(|(offset, _)| first_line + offset)
+            .collect();
+
+        if lines.is_empty() {
+            return Err(if invert {
+                format!("Pattern found in every line: {}", source)
+            } else {
+                format!("Pattern not found: {}", source)
+            });
+        }
+
+        self.undo_stack
+            .begin_compound_group(self.cursor.line, self.cursor.col);
+        Ok(GlobalRun {
+            lines,
+            seen_changes: self.undo_stack.pending_changes().len(),
+            buffer_idx: self.current_buffer_idx,
+        })
+    }
+
+    /// Move the cursor to the next marked line that still exists and return it.
+    pub fn next_global_line(&mut self, run: &mut GlobalRun) -> Option<usize> {
+        if self.current_buffer_idx != run.buffer_idx {
+            return None;
+        }
+
+        let changes = self.undo_stack.pending_changes();
+        for change in changes.get(run.seen_changes..).unwrap_or_default() {
+            adjust_marked_lines(&mut run.lines, change);
+        }
+        run.seen_changes = changes.len();
+
+        let line = run.lines.pop_front()?;
+        self.cursor.line = line;
+        self.cursor.col = 0;
+        Some(line)
+    }
+
+    /// Close the run's undo step.
+    pub fn finish_global(&mut self, _run: GlobalRun) {
+        self.clamp_cursor();
+        self.scroll_to_cursor();
+        self.undo_stack
+            .end_compound_group(self.cursor.line, self.cursor.col);
+    }
+}
+
+/// Keep marked lines on the same text after `change`, dropping deleted ones.
+///
+/// A change at column 0 that ends in a newline removes or inserts whole lines
+/// in front of the line it starts on, like `dd` or `O`. Any other change that
+/// removes line breaks joins the following lines into the one it starts on,
+/// like `J`.
+fn adjust_marked_lines(lines: &mut VecDeque<usize>, change: &Change) {
+    let removed = change.old_text.matches('\n').count();
+    let added = change.new_text.matches('\n').count();
+    if removed == 0 && added == 0 {
+        return;
+    }
+
+    let edited = if change.old_text.is_empty() {
+        &change.new_text
+    } else {
+        &change.old_text
+    };
+    let first_removed = if change.start_col == 0 && edited.ends_with('\n') {
+        change.start_line
+    } else {
+        change.start_line + 1
+    };
+
+    lines.retain_mut(|line| {
+        if *line < first_removed {
+            return true;
+        }
+        if *line < first_removed + removed {
+            return false;
+        }
+        *line = *line + added - removed;
+        true
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::adjust_marked_lines;
+    use crate::editor::Change;
+    use std::collections::VecDeque;
+
+    fn adjusted(lines: &[usize], change: Change) -> Vec<usize> {
+        let mut lines: VecDeque<usize> = lines.iter().copied().collect();
+        adjust_marked_lines(&mut lines, &change);
+        lines.into_iter().collect()
+    }
+
+    #[test]
+    fn deleted_lines_lose_their_marks_and_later_lines_move_up() {
+        let change = Change::delete(1, 0, "b\nc\n".to_string());
+        assert_eq!(adjusted(&[0, 1, 2, 3, 5], change), vec![0, 1, 3]);
+    }
+
+    #[test]
+    fn joined_lines_lose_their_marks_but_the_joined_into_line_keeps_it() {
+        let change = Change::new(1, 3, "\n  ".to_string(), " ".to_string());
+        assert_eq!(adjusted(&[1, 2, 3], change), vec![1, 2]);
+    }
+
+    #[test]
+    fn lines_opened_above_push_marks_down() {
+        let change = Change::insert(2, 0, "new\n".to_string());
+        assert_eq!(adjusted(&[1, 2, 3], change), vec![1, 3, 4]);
+    }
+}
@@ -0,0 +1,551 @@
+//! Call and type hierarchy explorer: a tree popup over
+//! `callHierarchy/*` and `typeHierarchy/*` results. Nodes ask the server
+//! for their children the first time they are expanded, so recursive call
+//! graphs stay finite.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use super::Editor;
+use crate::lsp::types::{CompletionKind, HierarchyDirection, HierarchyItem};
+
+/// Lines shown in the preview pane
+const PREVIEW_HEIGHT: usize = 12;
+/// Lines of context kept above the target line in the preview
+const PREVIEW_CONTEXT: usize = 3;
+
+/// A node in the hierarchy tree
+#[derive(Debug, Clone)]
+pub struct HierarchyNode {
+    /// ID children responses are matched by
+    pub id: u64,
+    pub item: HierarchyItem,
+    /// `None` until the server has answered for this node
+    pub children: Option<Vec<HierarchyNode>>,
+    /// Depth in tree (for indentation)
+    pub depth: usize,
+}
+
+/// A row of the flattened tree, as rendered
+#[derive(Debug, Clone)]
+pub struct FlatHierarchyNode {
+    pub id: u64,
+    pub name: String,
+    pub detail: Option<String>,
+    pub kind: CompletionKind,
+    pub uri: String,
+    /// Target line (0-indexed)
+    pub line: usize,
+    pub depth: usize,
+    pub is_expanded: bool,
+    /// Children were requested and have not arrived yet
+    pub is_loading: bool,
+    /// The server answered with no children
+    pub is_leaf: bool,
+}
+
+/// Source lines around the selected node's target
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HierarchyPreview {
+    pub path: PathBuf,
+    /// Line number of `lines[0]` (0-indexed)
+    pub start_line: usize,
+    /// Target line (0-indexed)
+    pub line: usize,
+    pub lines: Vec<String>,
+}
+
+/// A node whose children must be requested from `server`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HierarchyExpand {
+    pub server: String,
+    pub direction: HierarchyDirection,
+    pub node: u64,
+    /// The node's item as the server sent it
+    pub item: serde_json::Value,
+}
+
+/// Call or type hierarchy popup state
+#[derive(Debug, Clone)]
+pub struct HierarchyExplorer {
+    pub direction: HierarchyDirection,
+    /// Server that prepared the hierarchy; nodes are expanded on it too
+    pub server: String,
+    pub roots: Vec<HierarchyNode>,
+    /// Expanded node IDs
+    pub expanded: HashSet<u64>,
+    /// Node IDs whose children were requested but not answered
+    pub loading: HashSet<u64>,
+    /// Currently selected index in the flattened view
+    pub selected: usize,
+    /// Flattened view for rendering
+    pub flat_view: Vec<FlatHierarchyNode>,
+    pub preview: Option<HierarchyPreview>,
+    /// Children requests not yet sent
+    requests: Vec<HierarchyExpand>,
+}
+
+impl HierarchyExplorer {
+    /// Build the tree of `items`, numbering nodes from the counter in
+    /// `last_id` so IDs are never reused by a later popup
+    pub fn new(
+        direction: HierarchyDirection,
+        server: String,
+        items: Vec<HierarchyItem>,
+        last_id: &mut u64,
+    ) -> Self {
+        let mut explorer = Self {
+            direction,
+            server,
+            roots: Vec::new(),
+            expanded: HashSet::new(),
+            loading: HashSet::new(),
+            selected: 0,
+            flat_view: Vec::new(),
+            preview: None,
+            requests: Vec::new(),
+        };
+        explorer.roots = make_nodes(items, 0, last_id);
+        explorer.rebuild_flat_view();
+        explorer
+    }
+
+    pub fn move_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn move_down(&mut self) {
+        if self.selected + 1 < self.flat_view.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn move_to_top(&mut self) {
+        self.selected = 0;
+    }
+
+    pub fn move_to_bottom(&mut self) {
+        self.selected = self.flat_view.len().saturating_sub(1);
+    }
+
+    /// The item of the selected row
+    pub fn selected_item(&self) -> Option<&HierarchyItem> {
+        let id = self.flat_view.get(self.selected)?.id;
+        find_node(&self.roots, id).map(|node| &node.item)
+    }
+
+    /// Expand the selected node, asking for its children if they have not
+    /// been loaded yet
+    pub fn expand(&mut self) {
+        let Some(row) = self.flat_view.get(self.selected) else {
+            return;
+        };
+        if row.is_expanded || row.is_leaf {
+            return;
+        }
+        let id = row.id;
+        self.expanded.insert(id);
+        if let Some(node) = find_node(&self.roots, id) {
+            if node.children.is_none() && self.loading.insert(id) {
+                self.requests.push(HierarchyExpand {
+                    server: self.server.clone(),
+                    direction: self.direction,
+                    node: id,
+                    item: node.item.raw.clone(),
+                });
+            }
+        }
+        self.rebuild_flat_view();
+    }
+
+    /// Collapse the selected node (if expanded) or go to its parent
+    pub fn collapse(&mut self) {
+        let Some(row) = self.flat_view.get(self.selected) else {
+            return;
+        };
+        if row.is_expanded {
+            self.expanded.remove(&row.id);
+            self.rebuild_flat_view();
+        } else {
+            self.go_to_parent();
+        }
+    }
+
+    /// Toggle expand/collapse for the selected node
+    pub fn toggle_expand(&mut self) {
+        match self.flat_view.get(self.selected) {
+            Some(row) if row.is_expanded => self.collapse(),
+            Some(_) => self.expand(),
+            None => {}
+        }
+    }
+
+    /// Select the row the selected row hangs off
+    pub fn go_to_parent(&mut self) {
+        let Some(depth) = self.flat_view.get(self.selected).map(|row| row.depth) else {
+            return;
+        };
+        if let Some(parent) = self.flat_view[..self.selected]
+            .iter()
+            .rposition(|row| row.depth < depth)
+        {
+            self.selected = parent;
+        }
+    }
+
+    /// Attach the children the server sent for `node`, numbered from
+    /// `last_id`. Returns whether the node is still in the tree.
+    pub fn set_children(
+        &mut self,
+        node: u64,
+        items: Vec<HierarchyItem>,
+        last_id: &mut u64,
+    ) -> bool {
+        self.loading.remove(&node);
+        let Some(depth) = find_node(&self.roots, node).map(|node| node.depth) else {
+            return false;
+        };
+        let children = make_nodes(items, depth + 1, last_id);
+        if let Some(target) = find_node_mut(&mut self.roots, node) {
+            target.children = Some(children);
+        }
+        self.rebuild_flat_view();
+        true
+    }
+
+    /// Children requests queued since the last call
+    pub fn take_requests(&mut self) -> Vec<HierarchyExpand> {
+        std::mem::take(&mut self.requests)
+    }
+
+    /// Rebuild the flattened view from the tree
+    fn rebuild_flat_view(&mut self) {
+        self.flat_view.clear();
+        for root in &self.roots {
+            Self::flatten_tree_into(&mut self.flat_view, root, &self.expanded, &self.loading);
+        }
+
+        // Ensure selected is in bounds
+        if self.selected >= self.flat_view.len() {
+            self.selected = self.flat_view.len().saturating_sub(1);
+        }
+    }
+
+    fn flatten_tree_into(
+        flat_view: &mut Vec<FlatHierarchyNode>,
+        node: &HierarchyNode,
+        expanded: &HashSet<u64>,
+        loading: &HashSet<u64>,
+    ) {
+        let is_expanded = expanded.contains(&node.id);
+
+        flat_view.push(FlatHierarchyNode {
+            id: node.id,
+            name: node.item.name.clone(),
+            detail: node.item.detail.clone(),
+            kind: node.item.kind,
+            uri: node.item.uri.clone(),
+            line: node.item.line,
+            depth: node.depth,
+            is_expanded,
+            is_loading: loading.contains(&node.id),
+            is_leaf: node.children.as_ref().is_some_and(Vec::is_empty),
+        });
+
+        // Only add children if expanded
+        if is_expanded {
+            for child in node.children.iter().flatten() {
+                Self::flatten_tree_into(flat_view, child, expanded, loading);
+            }
+        }
+    }
+}
+
+fn make_nodes(items: Vec<HierarchyItem>, depth: usize, last_id: &mut u64) -> Vec<HierarchyNode> {
+    items
+        .into_iter()
+        .map(|item| {
+            *last_id += 1;
+            HierarchyNode {
+                id: *last_id,
+                item,
+                children: None,
+                depth,
+            }
+        })
+        .collect()
+}
+
+fn find_node(nodes: &[HierarchyNode], id: u64) -> Option<&HierarchyNode> {
+    nodes.iter().find_map(|node| {
+        if node.id == id {
+            Some(node)
+        } else {
+            find_node(node.children.as_deref().unwrap_or_default(), id)
+        }
+    })
+}
+
+fn find_node_mut(nodes: &mut [HierarchyNode], id: u64) -> Option<&mut HierarchyNode> {
+    nodes.iter_mut().find_map(|node| {
+        if node.id == id {
+            Some(node)
+        } else {
+            find_node_mut(node.children.as_deref_mut().unwrap_or_default(), id)
+        }
+    })
+}
+
+impl Editor {
+    /// Open the hierarchy popup on the items `server` prepared at the
+    /// cursor. A single item is expanded straight away.
+    pub fn show_hierarchy(
+        &mut self,
+        server: String,
+        direction: HierarchyDirection,
+        items: Vec<HierarchyItem>,
+    ) {
+        if items.is_empty() {
+            let hierarchy = if direction.is_call() { "call" } else { "type" };
+            self.set_status(format!("No {} hierarchy at cursor", hierarchy));
+            return;
+        }
+
+        let mut explorer =
+            HierarchyExplorer::new(direction, server, items, &mut self.last_hierarchy_node_id);
+        if explorer.roots.len() == 1 {
+            explorer.expand();
+        }
+        self.hierarchy_explorer = Some(explorer);
+        self.update_hierarchy_preview();
+        self.set_status(format!(
+            "{} - j/k to navigate, l/h to expand/collapse, Enter to go, Esc to close",
+            direction.title()
+        ));
+    }
+
+    /// Hide the hierarchy popup
+    pub fn hide_hierarchy(&mut self) {
+        self.hierarchy_explorer = None;
+        self.clear_status();
+    }
+
+    /// Attach children that arrived for a node of the open hierarchy
+    pub fn set_hierarchy_children(
+        &mut self,
+        direction: HierarchyDirection,
+        node: u64,
+        items: Vec<HierarchyItem>,
+    ) -> bool {
+        let Some(explorer) = self
+            .hierarchy_explorer
+            .as_mut()
+            .filter(|explorer| explorer.direction == direction)
+        else {
+            return false;
+        };
+        let attached = explorer.set_children(node, items, &mut self.last_hierarchy_node_id);
+        if attached {
+            self.update_hierarchy_preview();
+        }
+        attached
+    }
+
+    /// Children requests the hierarchy popup is waiting to send
+    pub fn take_hierarchy_requests(&mut self) -> Vec<HierarchyExpand> {
+        self.hierarchy_explorer
+            .as_mut()
+            .map(HierarchyExplorer::take_requests)
+            .unwrap_or_default()
+    }
+
+    /// Close the hierarchy popup and jump to the selected node
+    pub fn hierarchy_jump(&mut self) {
+        let Some(explorer) = self.hierarchy_explorer.take() else {
+            return;
+        };
+        self.clear_status();
+        let Some(item) = explorer.selected_item() else {
+            return;
+        };
+        let Some(path) = crate::lsp::uri_to_path(&item.uri) else {
+            return;
+        };
+
+        self.record_jump();
+        let current_path = self.buffer().path.clone();
+        if current_path.as_ref() != Some(&path) {
+            if let Err(e) = self.open_file(path) {
+                self.set_status(format!("Error opening file: {}", e));
+                return;
+            }
+        }
+        self.goto_line(item.line + 1);
+        self.cursor.col = self.lsp_utf16_col_to_buffer_col(item.line, item.col);
+        self.clamp_cursor();
+        self.scroll_to_cursor();
+    }
+
+    /// Load the preview for the selected node, from its buffer when the
+    /// file is open and from disk otherwise
+    pub fn update_hierarchy_preview(&mut self) {
+        let Some((path, line)) = self
+            .hierarchy_explorer
+            .as_ref()
+            .and_then(HierarchyExplorer::selected_item)
+            .and_then(|item| Some((crate::lsp::uri_to_path(&item.uri)?, item.line)))
+        else {
+            return;
+        };
+        let start_line = line.saturating_sub(PREVIEW_CONTEXT);
+
+        let cached = self
+            .hierarchy_explorer
+            .as_ref()
+            .and_then(|explorer| explorer.preview.as_ref())
+            .is_some_and(|preview| preview.path == path && preview.line == line);
+        if cached {
+            return;
+        }
+
+        let lines: Vec<String> = match self.buffer_index_for_path(&path) {
+            Some(idx) => {
+                let buffer = &self.buffers[idx];
+                (start_line..start_line + PREVIEW_HEIGHT)
+                    .map_while(|idx| buffer.line(idx))
+                    .map(|line| line.to_string().trim_end_matches(['\n', '\r']).to_string())
+                    .collect()
+            }
+            None => std::fs::read_to_string(&path)
+                .map(|content| {
+                    content
+                        .lines()
+                        .skip(start_line)
+                        .take(PREVIEW_HEIGHT)
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+        };
+
+        if let Some(explorer) = self.hierarchy_explorer.as_mut() {
+            explorer.preview = Some(HierarchyPreview {
+                path,
+                start_line,
+                line,
+                lines,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn item(name: &str, line: usize) -> HierarchyItem {
+        HierarchyItem {
+            name: name.to_string(),
+            detail: None,
+            kind: CompletionKind::Function,
+            uri: "file:///tmp/lib.rs".to_string(),
+            line,
+            col: 0,
+            raw: json!({ "name": name }),
+        }
+    }
+
+    fn names(explorer: &HierarchyExplorer) -> Vec<(usize, &str)> {
+        explorer
+            .flat_view
+            .iter()
+            .map(|row| (row.depth, row.name.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn expanding_a_node_requests_its_children_once() {
+        let mut explorer = HierarchyExplorer::new(
+            HierarchyDirection::IncomingCalls,
+            "rust".to_string(),
+            vec![item("parse", 3)],
+            &mut 0,
+        );
+
+        explorer.expand();
+        explorer.collapse();
+        explorer.expand();
+
+        let requests = explorer.take_requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].server, "rust");
+        assert_eq!(requests[0].item, json!({ "name": "parse" }));
+        assert!(explorer.flat_view[0].is_loading);
+
+        assert!(explorer.set_children(
+            requests[0].node,
+            vec![item("main", 1), item("run", 9)],
+            &mut 1,
+        ));
+        assert_eq!(
+            names(&explorer),
+            vec![(0, "parse"), (1, "main"), (1, "run")]
+        );
+        assert!(!explorer.flat_view[0].is_loading);
+
+        explorer.move_down();
+        explorer.expand();
+        let requests = explorer.take_requests();
+        assert!(explorer.set_children(requests[0].node, Vec::new(), &mut 3));
+        assert!(explorer.flat_view[1].is_leaf);
+        assert!(explorer.take_requests().is_empty());
+    }
+
+    #[test]
+    fn collapse_on_a_child_selects_its_parent() {
+        let mut explorer = HierarchyExplorer::new(
+            HierarchyDirection::Subtypes,
+            "rust".to_string(),
+            vec![item("Shape", 0), item("Drawable", 5)],
+            &mut 0,
+        );
+        explorer.expand();
+        let node = explorer.take_requests()[0].node;
+        explorer.set_children(node, vec![item("Circle", 10), item("Square", 20)], &mut 2);
+
+        explorer.move_to_bottom();
+        assert_eq!(explorer.selected_item().unwrap().name, "Drawable");
+        explorer.move_up();
+        assert_eq!(explorer.selected_item().unwrap().name, "Square");
+
+        explorer.collapse();
+        assert_eq!(explorer.selected_item().unwrap().name, "Shape");
+        explorer.collapse();
+        assert_eq!(names(&explorer), vec![(0, "Shape"), (0, "Drawable")]);
+    }
+
+    #[test]
+    fn children_for_a_replaced_hierarchy_are_ignored() {
+        let mut editor = Editor::default();
+        let direction = HierarchyDirection::OutgoingCalls;
+        editor.show_hierarchy("rust".to_string(), direction, vec![item("parse", 3)]);
+        let stale = editor.take_hierarchy_requests();
+        assert_eq!(stale[0].node, 1);
+
+        // The popup is replaced before the root's children arrive.
+        editor.show_hierarchy("rust".to_string(), direction, vec![item("render", 7)]);
+        let pending = editor.take_hierarchy_requests();
+        assert_ne!(pending[0].node, stale[0].node);
+
+        assert!(!editor.set_hierarchy_children(direction, stale[0].node, vec![item("main", 1)]));
+        assert!(!editor.set_hierarchy_children(direction, 42, vec![item("main", 1)]));
+        let explorer = editor.hierarchy_explorer.as_ref().unwrap();
+        assert_eq!(names(explorer), vec![(0, "render")]);
+        assert!(explorer.flat_view[0].is_loading);
+
+        assert!(editor.set_hierarchy_children(direction, pending[0].node, vec![item("draw", 9)]));
+        let explorer = editor.hierarchy_explorer.as_ref().unwrap();
+        assert_eq!(names(explorer), vec![(0, "render"), (1, "draw")]);
+    }
+}
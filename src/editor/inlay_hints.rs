@@ -0,0 +1,169 @@
+//! LSP inlay hints (`textDocument/inlayHint`), cached per buffer version
+//! and drawn by the renderer between buffer characters.
+
+use std::path::PathBuf;
+
+use super::Editor;
+use crate::lsp::types::InlayHint;
+
+/// An inlay hint placed at a buffer column, with its padding in the label
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BufferInlayHint {
+    pub line: usize,
+    /// Buffer (char) column the label is drawn before
+    pub col: usize,
+    pub label: String,
+}
+
+/// Hints the server returned for a range of lines of one buffer version
+#[derive(Debug, Clone, Default)]
+pub struct InlayHintCache {
+    version: u64,
+    start_line: usize,
+    /// Exclusive
+    end_line: usize,
+    /// Sorted by position
+    hints: Vec<BufferInlayHint>,
+}
+
+impl InlayHintCache {
+    fn covers(&self, version: u64, start_line: usize, end_line: usize) -> bool {
+        self.version == version && self.start_line <= start_line && end_line <= self.end_line
+    }
+
+    /// Hints on `line`, in column order
+    pub fn hints_on_line(&self, line: usize) -> &[BufferInlayHint] {
+        let start = self.hints.partition_point(|hint| hint.line < line);
+        let end = self.hints.partition_point(|hint| hint.line <= line);
+        &self.hints[start..end]
+    }
+}
+
+/// A request sent for the current buffer, answered or not
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct InlayHintRequest {
+    uri: String,
+    version: u64,
+    start_line: usize,
+    end_line: usize,
+}
+
+impl Editor {
+    /// `:set inlayhints`
+    pub fn inlay_hints_enabled(&self) -> bool {
+        self.settings.editor.inlay_hints
+    }
+
+    /// The lines of the current buffer to request hints for, as
+    /// `(path, start_line, end_line, buffer_version)`, when the cache does
+    /// not cover the active pane and no request for them is outstanding.
+    /// Records the request as sent.
+    pub fn inlay_hint_request(&mut self) -> Option<(PathBuf, u32, u32, u64)> {
+        if !self.inlay_hints_enabled() {
+            return None;
+        }
+        let path = self.buffer().path.clone()?;
+        let uri = crate::lsp::path_to_uri(&path);
+        let version = self.buffer().version();
+        let pane = &self.panes[self.active_pane];
+        let start_line = pane.viewport_offset;
+        let end_line = (start_line + pane.rect.height as usize).min(self.buffer().len_lines());
+
+        if self
+            .inlay_hints
+            .get(&uri)
+            .is_some_and(|cache| cache.covers(version, start_line, end_line))
+        {
+            return None;
+        }
+        let request = InlayHintRequest {
+            uri,
+            version,
+            start_line,
+            end_line,
+        };
+        if self.inlay_hint_request.as_ref() == Some(&request) {
+            return None;
+        }
+        self.inlay_hint_request = Some(request);
+        Some((path, start_line as u32, end_line as u32, version))
+    }
+
+    /// Store hints for lines `start_line..end_line` of the buffer at `uri`.
+    /// Hints for an older version of the buffer are dropped. Returns true
+    /// when the cache changed.
+    pub fn set_inlay_hints(
+        &mut self,
+        uri: &str,
+        version: u64,
+        start_line: usize,
+        end_line: usize,
+        hints: Vec<InlayHint>,
+    ) -> bool {
+        if self
+            .inlay_hint_request
+            .as_ref()
+            .is_some_and(|request| request.uri == uri && request.version == version)
+        {
+            self.inlay_hint_request = None;
+        }
+        let Some(buffer) = self.buffers.iter().find(|buffer| {
+            buffer.path.as_ref().map(crate::lsp::path_to_uri).as_deref() == Some(uri)
+        }) else {
+            return false;
+        };
+        if buffer.version() != version {
+            return false;
+        }
+
+        let hints = hints
+            .into_iter()
+            .map(|hint| {
+                let mut label = hint.label;
+                if hint.padding_left {
+                    label.insert(0, ' ');
+                }
+                if hint.padding_right {
+                    label.push(' ');
+                }
+                BufferInlayHint {
+                    line: hint.line,
+                    col: Self::lsp_utf16_col_to_buffer_col_in_buffer(buffer, hint.line, hint.col),
+                    label,
+                }
+            })
+            .collect();
+        self.inlay_hints.insert(
+            uri.to_string(),
+            InlayHintCache {
+                version,
+                start_line,
+                end_line,
+                hints,
+            },
+        );
+        self.render_damage.mark_full();
+        true
+    }
+
+    /// Drop every cached hint so visible ones are requested again
+    /// (`workspace/inlayHint/refresh`, or `:set inlayhints`)
+    pub fn refresh_inlay_hints(&mut self) {
+        self.inlay_hints.clear();
+        self.inlay_hint_request = None;
+        self.render_damage.mark_full();
+    }
+
+    /// Hints to draw for buffer `buffer_idx`: none when they are turned off
+    /// or were computed for another version of the buffer.
+    pub fn inlay_hints_for_buffer(&self, buffer_idx: usize) -> Option<&InlayHintCache> {
+        if !self.inlay_hints_enabled() {
+            return None;
+        }
+        let buffer = self.buffers.get(buffer_idx)?;
+        let uri = crate::lsp::path_to_uri(buffer.path.as_ref()?);
+        self.inlay_hints
+            .get(&uri)
+            .filter(|cache| cache.version == buffer.version())
+    }
+}
@@ -1,21 +1,57 @@
 mod buffer;
+mod code_lens;
 mod cursor;
+mod diagnostic_links;
+mod diagnostic_pull;
+mod document_highlight;
+mod document_sync;
+mod ex_range;
+mod formatting;
+mod global;
+mod hierarchy;
+mod inlay_hints;
 mod macros;
 mod marks;
+mod options;
+mod pattern;
 mod register;
+mod repeat;
 mod replace;
+mod selection_range;
+mod semantic_tokens;
+mod substitute;
+mod substitute_preview;
 mod undo;
+mod workspace_edit;
 
-pub use buffer::Buffer;
+pub use buffer::{Buffer, SyntaxEdits};
 pub use cursor::Cursor;
+pub use document_highlight::DocumentHighlightSpan;
+pub use global::GlobalRun;
+pub use hierarchy::{
+    FlatHierarchyNode, HierarchyExpand, HierarchyExplorer, HierarchyNode, HierarchyPreview,
+};
+pub use inlay_hints::{BufferInlayHint, InlayHintCache};
 pub use macros::MacroState;
 pub use marks::{Mark, Marks};
+pub use options::{BufferOptions, FileFormat};
+pub use pattern::{BufferMatch, CapturedMatch, CaseOptions, SearchOffset, SearchPattern};
 pub use register::{RegisterContent, Registers};
+pub use repeat::DotRepeat;
 pub use undo::{Change, UndoEntry, UndoStack};
+pub use workspace_edit::{WorkspaceEditSummary, apply_edits_to_file};
 
+use code_lens::{CodeLensCache, CodeLensRequest};
+use diagnostic_pull::DiagnosticPullRequest;
+use document_highlight::{DocumentHighlightRequest, DocumentHighlights};
+use inlay_hints::InlayHintRequest;
 use replace::ReplaceSession;
+use selection_range::{SelectionExpansion, SelectionRangeRequest};
+use semantic_tokens::{SemanticTokensRequest, SemanticTokensState};
+use substitute::SubstituteConfirm;
+use substitute_preview::SubstitutePreview;
 
-use crate::commands::CommandLine;
+use crate::commands::{CommandLine, ExRange};
 use crate::config::{KeymapLookup, LeaderAction, LeaderHint, Settings};
 use crate::explorer::FileExplorer;
 use crate::finder::FuzzyFinder;
@@ -24,7 +60,10 @@ use crate::input::{
     CaseOperator, InputState, Motion, TextObject, TextObjectModifier, TextObjectType, apply_motion,
     motion::last_addressable_line,
 };
-use crate::lsp::types::{CodeActionItem, CompletionItem, Diagnostic, Location, TextEdit};
+use crate::lsp::types::{
+    CodeActionItem, CompletionItem, Diagnostic, HierarchyDirection, Location, LspCommand,
+    SymbolItem, TextEdit, TextRange,
+};
 use crate::syntax::SyntaxManager;
 use crate::theme::ThemeManager;
 use std::collections::HashMap;
@@ -33,6 +72,25 @@ use unicode_width::UnicodeWidthChar;
 
 const MAX_VISIBLE_SEARCH_MATCHES: usize = 2048;
 
+/// Point `syntax` at the runtime grammar `languages.toml` maps the file to
+/// when it loads, otherwise at the built-in language for the path.
+fn set_syntax_language(
+    languages: &crate::config::LanguagesConfig,
+    syntax: &mut SyntaxManager,
+    path: Option<&std::path::Path>,
+    first_line: Option<&str>,
+) {
+    if let Some(name) = languages.runtime_language_for(path, first_line) {
+        if syntax.set_runtime_language(name).is_ok() {
+            return;
+        }
+    }
+    match path {
+        Some(path) => syntax.set_language_from_path_and_first_line(path, first_line),
+        None => syntax.set_language_from_path_option_and_first_line(None, first_line),
+    }
+}
+
 fn comparable_file_path(path: &std::path::Path) -> std::path::PathBuf {
     let absolute = if path.is_absolute() {
         path.to_path_buf()
@@ -140,16 +198,42 @@ pub enum LspAction {
     GotoDeclaration,
     /// Go to implementation (gI)
     GotoImplementation,
+    /// Go to type definition (gy)
+    GotoTypeDefinition,
     /// Show hover documentation (K)
     Hover,
     /// Format document
     Formatting,
+    /// Format a range of the document (gq), UTF-16 columns
+    RangeFormatting(TextRange),
     /// Find references (gr)
     FindReferences,
     /// Show code actions (ga)
     CodeActions,
     /// Rename symbol
     RenameSymbol(String),
+    /// Run the command carried by a picked code action
+    ExecuteCommand(LspCommand),
+    /// Show the current buffer's symbol outline
+    DocumentSymbols,
+    /// Open the workspace symbol search
+    WorkspaceSymbols,
+    /// Open the call or type hierarchy of the symbol at the cursor
+    Hierarchy(HierarchyDirection),
+    /// Open the protocol log of the named server or language, or of all
+    LspLog(Option<String>),
+    /// Open what the running servers were started with and negotiated
+    LspInfo,
+    /// Restart the named server or language, or the current buffer's
+    LspRestart(Option<String>),
+}
+
+impl LspAction {
+    /// Actions about the servers themselves, run without a ready server
+    /// for the current buffer
+    pub fn manages_servers(&self) -> bool {
+        matches!(self, Self::LspLog(_) | Self::LspInfo | Self::LspRestart(_))
+    }
 }
 
 /// Outcome of formatting the current buffer from `languages.toml` or LSP.
@@ -333,6 +417,10 @@ pub struct SearchState {
     pub last_pattern: Option<String>,
     /// Last search direction
     pub last_direction: SearchDirection,
+    /// Cursor offset applied to matches of the last pattern (`/pat/e+1`)
+    pub last_offset: SearchOffset,
+    /// Last pattern came from `*`/`#`, which ignore `smartcase`
+    pub last_skips_smartcase: bool,
     /// Previously executed non-empty search patterns.
     pub history: Vec<String>,
     /// Current index while navigating search history.
@@ -521,18 +609,45 @@ impl SearchState {
         self.input.chars().count()
     }
 
-    /// Execute search and save pattern
-    pub fn execute(&mut self) -> Option<String> {
+    /// Execute search and save pattern.
+    ///
+    /// Text after an unescaped `/` (or `?` for backward search) is parsed as a
+    /// search offset; an empty pattern reuses the last one.
+    pub fn execute(&mut self) -> Result<Option<String>, String> {
         if self.input.is_empty() {
             // Use last pattern if input is empty
-            self.last_pattern.clone()
-        } else {
-            let pattern = self.input.clone();
-            self.record_history(pattern.clone());
-            self.last_pattern = Some(pattern.clone());
+            return Ok(self.last_pattern.clone());
+        }
+
+        let input = self.input.clone();
+        let (pattern, offset) = self.split_input();
+        let offset = match offset {
+            Some(offset) => SearchOffset::parse(&offset)?,
+            None => SearchOffset::None,
+        };
+        self.record_history(input);
+
+        if pattern.is_empty() {
+            self.last_offset = offset;
             self.last_direction = self.direction;
-            Some(pattern)
+            return Ok(self.last_pattern.clone());
         }
+
+        self.last_pattern = Some(pattern.clone());
+        self.last_direction = self.direction;
+        self.last_offset = offset;
+        self.last_skips_smartcase = false;
+        Ok(Some(pattern))
+    }
+
+    /// Split the current input into its pattern and any trailing offset text.
+    pub fn split_input(&self) -> (String, Option<String>) {
+        let delimiter = match self.direction {
+            SearchDirection::Forward => '/',
+            SearchDirection::Backward => '?',
+        };
+        let (pattern, offset) = pattern::split_search_offset(&self.input, delimiter);
+        (pattern, offset.map(str::to_string))
     }
 
     fn record_history(&mut self, pattern: String) {
@@ -998,12 +1113,10 @@ pub struct Editor {
     pub search: SearchState,
     /// Visual selection state
     pub visual: VisualSelection,
-    /// Syntax highlighting manager
-    pub syntax: SyntaxManager,
+    /// Syntax highlighting state for each buffer (parallel to `buffers`)
+    syntaxes: Vec<SyntaxManager>,
     /// Syntax highlighting manager for finder preview
     pub preview_syntax: SyntaxManager,
-    /// Last parsed syntax version for the active buffer
-    last_syntax_version: u64,
     /// Time of the last buffer edit (for syntax debounce)
     last_edit_at: Option<Instant>,
     /// Configuration settings
@@ -1026,6 +1139,31 @@ pub struct Editor {
     pub lsp_status: Option<String>,
     /// LSP diagnostics per file URI
     diagnostics: HashMap<String, Vec<Diagnostic>>,
+    /// Buffer version diagnostics were last pulled for
+    diagnostic_pull_request: Option<DiagnosticPullRequest>,
+    /// LSP inlay hints per file URI
+    inlay_hints: HashMap<String, InlayHintCache>,
+    /// Inlay hint request sent for the current buffer and not yet answered
+    inlay_hint_request: Option<InlayHintRequest>,
+    /// LSP code lenses per file URI
+    code_lenses: HashMap<String, CodeLensCache>,
+    /// Code lens request sent for the current buffer and not yet answered
+    code_lens_request: Option<CodeLensRequest>,
+    /// Last semantic tokens answer per file URI
+    semantic_tokens: HashMap<String, SemanticTokensState>,
+    /// Semantic tokens request sent for the current buffer and not yet answered
+    semantic_tokens_request: Option<SemanticTokensRequest>,
+    /// Last semantic tokens request answered without tokens, not sent again
+    /// until the buffer changes or the server asks for a refresh
+    semantic_tokens_failed: Option<SemanticTokensRequest>,
+    /// References to the symbol under the cursor
+    document_highlights: Option<DocumentHighlights>,
+    /// Position document highlights were last requested for
+    document_highlight_request: Option<DocumentHighlightRequest>,
+    /// Selection range request queued by `an`
+    selection_range_request: Option<SelectionRangeRequest>,
+    /// Selections `an` grew from
+    selection_expansion: SelectionExpansion,
     /// Autocomplete state
     pub completion: CompletionState,
     /// Pending LSP action to execute (handled by main loop)
@@ -1058,6 +1196,11 @@ pub struct Editor {
     pub save_after_format: bool,
     /// References picker state
     pub references_picker: Option<ReferencesPicker>,
+    /// Call/type hierarchy popup state
+    pub hierarchy_explorer: Option<HierarchyExplorer>,
+    /// Last hierarchy node ID handed out. Shared by every popup so that
+    /// children arriving for a closed one match no node of the next.
+    last_hierarchy_node_id: u64,
     /// Code actions picker state
     pub code_actions_picker: Option<CodeActionsPicker>,
     /// Rename prompt input (new name being entered)
@@ -1096,6 +1239,8 @@ pub struct Editor {
     pub pending_visual_block_edit: Option<VisualBlockEdit>,
     /// Macro recording and playback state
     pub macros: MacroState,
+    /// Last change, replayed by `.`
+    pub dot_repeat: DotRepeat,
     /// Last insert position for `gi` command (line, col)
     pub last_insert_position: Option<(usize, usize)>,
     /// Text inserted during the most recently completed insert session.
@@ -1118,6 +1263,12 @@ pub struct Editor {
     expression_register_value: Option<String>,
     /// Insert mode temporarily handed control to one normal-mode command after `<C-o>`.
     pub pending_insert_normal_once: bool,
+    /// Interactive `:s///c` session waiting for y/n/a/q/l.
+    substitute_confirm: Option<SubstituteConfirm>,
+    /// Replacement string of the last `:s`, reused by `~` in the next one.
+    last_substitute_replacement: Option<String>,
+    /// Live preview of the `:s` being typed on the command line.
+    substitute_preview: Option<SubstitutePreview>,
     /// Previous jump position for `''` command (path, line, col)
     /// This is the position from which the last jump was made
     pub previous_jump_position: Option<(Option<std::path::PathBuf>, usize, usize)>,
@@ -1548,9 +1699,8 @@ impl Editor {
             undo_stacks: vec![UndoStack::new()],
             search: SearchState::default(),
             visual: VisualSelection::default(),
-            syntax,
+            syntaxes: vec![syntax],
             preview_syntax,
-            last_syntax_version: 0,
             last_edit_at: None,
             settings,
             keymap,
@@ -1562,6 +1712,18 @@ impl Editor {
             finder,
             lsp_status: None,
             diagnostics: HashMap::new(),
+            diagnostic_pull_request: None,
+            inlay_hints: HashMap::new(),
+            inlay_hint_request: None,
+            code_lenses: HashMap::new(),
+            code_lens_request: None,
+            semantic_tokens: HashMap::new(),
+            semantic_tokens_request: None,
+            semantic_tokens_failed: None,
+            document_highlights: None,
+            document_highlight_request: None,
+            selection_range_request: None,
+            selection_expansion: SelectionExpansion::default(),
             completion: CompletionState::default(),
             pending_lsp_action: None,
             jump_list: JumpList::default(),
@@ -1578,6 +1740,8 @@ impl Editor {
             pending_format: false,
             save_after_format: false,
             references_picker: None,
+            hierarchy_explorer: None,
+            last_hierarchy_node_id: 0,
             code_actions_picker: None,
             rename_input: String::new(),
             rename_original: String::new(),
@@ -1597,6 +1761,7 @@ impl Editor {
             last_visual_selection: None,
             pending_visual_block_edit: None,
             macros: MacroState::new(),
+            dot_repeat: DotRepeat::default(),
             last_insert_position: None,
             last_inserted_text: None,
             current_inserted_text: String::new(),
@@ -1608,6 +1773,9 @@ impl Editor {
             expression_register_input: String::new(),
             expression_register_value: None,
             pending_insert_normal_once: false,
+            substitute_confirm: None,
+            last_substitute_replacement: None,
+            substitute_preview: None,
             previous_jump_position: None,
             languages_config: crate::config::load_languages_config(),
             startup_errors,
@@ -1767,7 +1935,7 @@ impl Editor {
     /// Set theme by name and sync syntax highlighting
     pub fn set_theme(&mut self, name: &str) -> bool {
         if self.theme_manager.set_theme(name) {
-            self.syntax.sync_theme(self.theme_manager.theme());
+            self.sync_syntax_themes();
             true
         } else {
             false
@@ -1786,11 +1954,11 @@ impl Editor {
         if confirm {
             self.theme_manager.confirm_preview();
             // Sync syntax colors with confirmed theme
-            self.syntax.sync_theme(self.theme_manager.theme());
+            self.sync_syntax_themes();
         } else {
             self.theme_manager.cancel_preview();
             // Sync syntax colors with restored theme
-            self.syntax.sync_theme(self.theme_manager.theme());
+            self.sync_syntax_themes();
         }
         self.theme_picker = None;
     }
@@ -1798,7 +1966,14 @@ impl Editor {
     /// Preview a theme in the picker
     pub fn preview_theme(&mut self, name: &str) {
         if self.theme_manager.preview_theme(name) {
-            self.syntax.sync_theme(self.theme_manager.theme());
+            self.sync_syntax_themes();
+        }
+    }
+
+    fn sync_syntax_themes(&mut self) {
+        let theme = self.theme_manager.theme();
+        for syntax in &mut self.syntaxes {
+            syntax.sync_theme(theme);
         }
     }
 
@@ -1854,6 +2029,16 @@ impl Editor {
         );
     }
 
+    /// Open the LSP protocol log (`:LspLog`) in a read-only virtual buffer.
+    pub fn open_lsp_log_report(&mut self, report: &str) {
+        self.open_virtual_read_only_buffer("[lsp-log]", report, Some("lsp-log.jsonc"));
+    }
+
+    /// Open the running servers' details (`:LspInfo`) in a read-only virtual buffer.
+    pub fn open_lsp_info_report(&mut self, report: &str) {
+        self.open_virtual_read_only_buffer("[lsp-info]", report, Some("lsp-info.md"));
+    }
+
     /// Build a project-wide replace preview and open it as a read-only buffer.
     pub fn preview_project_replace(
         &mut self,
@@ -1996,6 +2181,7 @@ impl Editor {
                 self.panes[self.active_pane].buffer_idx = self.current_buffer_idx;
             }
         }
+        self.detect_buffer_syntax(self.current_buffer_idx);
 
         self.cursor = Cursor::default();
         self.viewport_offset = 0;
@@ -2007,8 +2193,6 @@ impl Editor {
             self.panes[self.active_pane].viewport_offset = self.viewport_offset;
             self.panes[self.active_pane].h_offset = self.h_offset;
         }
-
-        self.sync_syntax_to_current_buffer();
     }
 
     /// Open the user's config file, creating the default template first if needed.
@@ -2839,14 +3023,53 @@ impl Editor {
         false
     }
 
-    fn sync_syntax_to_current_buffer(&mut self) {
-        let syntax_hint = self.buffer().syntax_hint_path().cloned();
-        let first_line = self.buffer().first_line_prefix();
-        self.syntax.set_language_from_path_option_and_first_line(
-            syntax_hint.as_ref(),
+    /// Syntax state of the current buffer.
+    pub fn syntax(&self) -> &SyntaxManager {
+        &self.syntaxes[self.current_buffer_idx]
+    }
+
+    /// Syntax state of the buffer at `idx`.
+    pub fn syntax_at(&self, idx: usize) -> Option<&SyntaxManager> {
+        self.syntaxes.get(idx)
+    }
+
+    /// Give the buffer at `idx` fresh syntax state for its name and first
+    /// line. Called whenever a buffer is added or replaced in place.
+    fn detect_buffer_syntax(&mut self, idx: usize) {
+        let mut syntax = SyntaxManager::new();
+        syntax.sync_theme(self.theme_manager.theme());
+        let buffer = &self.buffers[idx];
+        let first_line = buffer.first_line_prefix();
+        set_syntax_language(
+            &self.languages_config,
+            &mut syntax,
+            buffer.syntax_hint_path().map(|path| path.as_path()),
             first_line.as_deref(),
         );
-        self.parse_current_buffer();
+        if idx < self.syntaxes.len() {
+            self.syntaxes[idx] = syntax;
+        } else {
+            self.syntaxes.push(syntax);
+        }
+        self.parse_buffer_syntax(idx);
+    }
+
+    fn parse_buffer_syntax(&mut self, idx: usize) {
+        let edits = self.buffers[idx].take_syntax_edits();
+        self.syntaxes[idx].reparse(&self.buffers[idx], edits);
+    }
+
+    /// Reparse the buffer at `idx` if it changed since its last parse.
+    fn refresh_buffer_syntax(&mut self, idx: usize) -> bool {
+        if self.syntaxes[idx].is_synced_with(&self.buffers[idx]) {
+            return false;
+        }
+        self.parse_buffer_syntax(idx);
+        true
+    }
+
+    fn sync_syntax_to_current_buffer(&mut self) {
+        self.refresh_buffer_syntax(self.current_buffer_idx);
     }
 
     fn save_current_undo_stack(&mut self) {
@@ -2893,9 +3116,6 @@ impl Editor {
         self.cursor.line = cursor_line.min(max_line);
         let max_col = self.buffer().line_len(self.cursor.line);
         self.cursor.col = cursor_col.min(max_col);
-
-        // Increment syntax version to trigger re-parse
-        self.last_syntax_version = 0;
     }
 
     /// Replace the current buffer content as a single undoable change.
@@ -2922,7 +3142,6 @@ impl Editor {
         self.undo_stack
             .end_undo_group(self.cursor.line, self.cursor.col);
         self.save_current_undo_stack();
-        self.last_syntax_version = 0;
     }
 
     /// Get the number of open buffers
@@ -3017,7 +3236,7 @@ impl Editor {
             self.h_offset = self.panes[self.active_pane].h_offset;
             self.current_buffer_idx = self.panes[self.active_pane].buffer_idx;
             self.load_current_undo_stack();
-            // Re-parse syntax for the buffer
+            // Catch up on edits made through another pane
             self.sync_syntax_to_current_buffer();
         }
     }
@@ -3047,6 +3266,7 @@ impl Editor {
             let new_buffer = Buffer::from_file(path)?;
             self.buffers.push(new_buffer);
             self.undo_stacks.push(UndoStack::new());
+            self.detect_buffer_syntax(self.buffers.len() - 1);
             self.buffers.len() - 1
         } else {
             // Same buffer as current pane
@@ -3503,11 +3723,7 @@ impl Editor {
                 self.panes[self.active_pane].viewport_offset = self.viewport_offset;
                 self.panes[self.active_pane].h_offset = self.h_offset;
             }
-            // Re-parse syntax for this buffer
-            let first_line = self.buffer().first_line_prefix();
-            self.syntax
-                .set_language_from_path_and_first_line(&path, first_line.as_deref());
-            self.parse_current_buffer();
+            self.sync_syntax_to_current_buffer();
             // Update git diff for this buffer
             self.update_git_diff();
             return Ok(());
@@ -3552,12 +3768,7 @@ impl Editor {
         }
 
         // Set up syntax highlighting from path, then shebang if needed
-        let first_line = self.buffer().first_line_prefix();
-        self.syntax
-            .set_language_from_path_and_first_line(&path, first_line.as_deref());
-
-        // Parse the buffer for syntax highlighting
-        self.parse_current_buffer();
+        self.detect_buffer_syntax(self.current_buffer_idx);
 
         // Update git diff for the newly opened file
         self.update_git_diff();
@@ -3574,6 +3785,8 @@ impl Editor {
             // If it's the last buffer, just create a new empty one
             self.buffers[0] = Buffer::new();
             self.undo_stacks = vec![UndoStack::new()];
+            self.syntaxes.truncate(1);
+            self.detect_buffer_syntax(0);
             self.current_buffer_idx = 0;
             self.cursor = Cursor::default();
             self.viewport_offset = 0;
@@ -3589,6 +3802,7 @@ impl Editor {
             // Remove the current buffer
             self.buffers.remove(removed_idx);
             self.undo_stacks.remove(removed_idx);
+            self.syntaxes.remove(removed_idx);
 
             // Adjust current_buffer_idx if needed
             if self.current_buffer_idx >= self.buffers.len() {
@@ -3626,10 +3840,7 @@ impl Editor {
     pub fn set_buffer_path(&mut self, path: std::path::PathBuf) {
         self.buffer_mut().set_file_path(path.clone());
         // Update syntax highlighting for new filename
-        let first_line = self.buffer().first_line_prefix();
-        self.syntax
-            .set_language_from_path_and_first_line(&path, first_line.as_deref());
-        self.parse_current_buffer();
+        self.detect_buffer_syntax(self.current_buffer_idx);
     }
 
     /// Set terminal size
@@ -3806,6 +4017,22 @@ impl Editor {
             self.viewport_offset = self.cursor.line + scroll_off + 1 - text_rows;
         }
 
+        // Code lens rows push the lines below them down the pane
+        if !self.settings.editor.wrap {
+            while self.viewport_offset < self.cursor.line
+                && self.cursor.line + scroll_off + 1 - self.viewport_offset
+                    + self.code_lens_rows(
+                        self.current_buffer_idx,
+                        self.viewport_offset,
+                        self.cursor.line + 1,
+                        0,
+                    )
+                    > text_rows
+            {
+                self.viewport_offset += 1;
+            }
+        }
+
         if self.settings.editor.wrap {
             self.scroll_wrapped_view_to_cursor(text_rows, scroll_off);
         }
@@ -4041,6 +4268,13 @@ impl Editor {
             }
         }
 
+        visual_row = visual_row.saturating_add(self.code_lens_rows(
+            self.current_buffer_idx,
+            self.viewport_offset,
+            self.cursor.line + 1,
+            self.h_offset,
+        ));
+
         if self.cursor.line == self.viewport_offset {
             visual_row.saturating_add(cursor_segment_idx.saturating_sub(self.h_offset))
         } else {
@@ -4421,6 +4655,32 @@ impl Editor {
             .delete(register, RegisterContent::Lines(deleted), false);
     }
 
+    /// Delete the lines of an Ex range (`:[range]d [x] [count]`), leaving the
+    /// cursor on the first non-blank of the line after them.
+    pub fn delete_ex_lines(
+        &mut self,
+        range: Option<&ExRange>,
+        register: Option<char>,
+        count: Option<usize>,
+    ) -> Result<(), String> {
+        let (mut first_line, mut last_line) = self.resolve_ex_range(range)?;
+        if let Some(count) = count {
+            let max_line = self.buffers[self.current_buffer_idx]
+                .addressable_line_count()
+                .saturating_sub(1);
+            first_line = last_line;
+            last_line = (last_line + count - 1).min(max_line);
+        }
+        if self.reject_read_only_edit() {
+            return Ok(());
+        }
+
+        self.cursor.line = first_line;
+        self.delete_line(last_line + 1 - first_line, register);
+        self.cursor.col = self.find_first_non_blank(self.cursor.line);
+        Ok(())
+    }
+
     /// Yank from cursor to motion target
     pub fn yank_motion(&mut self, motion: Motion, count: usize, register: Option<char>) {
         if let Some((start_line, start_col, end_line, end_col)) = self.motion_range(motion, count) {
@@ -5297,6 +5557,9 @@ impl Editor {
                 self.cursor.col += 1;
             }
             self.record_inserted_char(ch);
+            if !ch.is_whitespace() {
+                self.wrap_for_text_width();
+            }
         }
         self.scroll_to_cursor();
     }
@@ -5304,71 +5567,79 @@ impl Editor {
     /// Insert newline with smart indentation using tree-sitter when available
     fn insert_newline_with_indent(&mut self) {
         let tab_width = self.settings.editor.tab_width;
-        let language = self.syntax.language_name().map(|s| s.to_string());
+        let language = self.syntax().language_name().map(|s| s.to_string());
 
         // Check if cursor is between matching brackets like {|} or [|] or (|)
         let between_brackets = self.is_cursor_between_brackets();
 
-        // Try tree-sitter based indentation for supported languages
-        if matches!(
-            language.as_deref(),
-            Some("javascript" | "typescript" | "tsx" | "css" | "json" | "toml" | "html")
-        ) {
-            if let Some((tree, source)) = self.syntax.get_tree_and_source() {
-                if let Some(cursor_byte) = self
-                    .syntax
-                    .position_to_byte(self.cursor.line, self.cursor.col)
-                {
-                    let indent_spaces =
-                        crate::indent::calculate_indent(tree, source, cursor_byte, tab_width);
-                    let indent = " ".repeat(indent_spaces);
-
-                    if between_brackets {
-                        // Bracket expansion: insert two newlines
-                        // First line: indented content line (where cursor goes)
-                        // Second line: closing bracket at base indent
-                        let base_indent =
-                            self.buffers[self.current_buffer_idx].get_line_indent(self.cursor.line);
-                        let insert_text = format!("\n{}\n{}", indent, base_indent);
-
-                        self.undo_stack.record_change(Change::insert(
-                            self.cursor.line,
-                            self.cursor.col,
-                            insert_text.clone(),
-                        ));
+        // Try tree-sitter based indentation: a runtime grammar's indents.scm,
+        // or the built-in rules for supported languages
+        let syntax = self.syntax();
+        let indent_spaces = syntax.get_tree_and_source().and_then(|(tree, source)| {
+            let cursor_byte = syntax.position_to_byte(self.cursor.line, self.cursor.col)?;
+            if let Some(query) = syntax.indent_query() {
+                return Some(crate::indent::calculate_indent_with_query(
+                    tree,
+                    query,
+                    source,
+                    cursor_byte,
+                    tab_width,
+                ));
+            }
+            matches!(
+                language.as_deref(),
+                Some("javascript" | "typescript" | "tsx" | "css" | "json" | "toml" | "html")
+            )
+            .then(|| crate::indent::calculate_indent(tree, source, cursor_byte, tab_width))
+        });
 
-                        self.buffers[self.current_buffer_idx].insert_str(
-                            self.cursor.line,
-                            self.cursor.col,
-                            &insert_text,
-                        );
-                        self.record_inserted_text(&insert_text);
+        if let Some(indent_spaces) = indent_spaces {
+            let indent = " ".repeat(indent_spaces);
 
-                        // Move cursor to the indented middle line
-                        self.cursor.line += 1;
-                        self.cursor.col = indent.len();
-                    } else {
-                        // Regular newline with indent
-                        let insert_text = format!("\n{}", indent);
-                        self.undo_stack.record_change(Change::insert(
-                            self.cursor.line,
-                            self.cursor.col,
-                            insert_text.clone(),
-                        ));
+            if between_brackets {
+                // Bracket expansion: insert two newlines
+                // First line: indented content line (where cursor goes)
+                // Second line: closing bracket at base indent
+                let base_indent =
+                    self.buffers[self.current_buffer_idx].get_line_indent(self.cursor.line);
+                let insert_text = format!("\n{}\n{}", indent, base_indent);
 
-                        self.buffers[self.current_buffer_idx].insert_str(
-                            self.cursor.line,
-                            self.cursor.col,
-                            &insert_text,
-                        );
-                        self.record_inserted_text(&insert_text);
+                self.undo_stack.record_change(Change::insert(
+                    self.cursor.line,
+                    self.cursor.col,
+                    insert_text.clone(),
+                ));
 
-                        self.cursor.line += 1;
-                        self.cursor.col = indent.len();
-                    }
-                    return;
-                }
+                self.buffers[self.current_buffer_idx].insert_str(
+                    self.cursor.line,
+                    self.cursor.col,
+                    &insert_text,
+                );
+                self.record_inserted_text(&insert_text);
+
+                // Move cursor to the indented middle line
+                self.cursor.line += 1;
+                self.cursor.col = indent.len();
+            } else {
+                // Regular newline with indent
+                let insert_text = format!("\n{}", indent);
+                self.undo_stack.record_change(Change::insert(
+                    self.cursor.line,
+                    self.cursor.col,
+                    insert_text.clone(),
+                ));
+
+                self.buffers[self.current_buffer_idx].insert_str(
+                    self.cursor.line,
+                    self.cursor.col,
+                    &insert_text,
+                );
+                self.record_inserted_text(&insert_text);
+
+                self.cursor.line += 1;
+                self.cursor.col = indent.len();
             }
+            return;
         }
 
         // Fallback to basic indentation
@@ -5404,13 +5675,12 @@ impl Editor {
         let buffer = &self.buffers[self.current_buffer_idx];
         let base_indent = buffer.get_line_indent(self.cursor.line);
         let ends_with_brace = buffer.line_ends_with(self.cursor.line, '{');
-        let tab_width = self.settings.editor.tab_width;
 
         // Calculate the full indent for the new line
         let mut indent = base_indent.clone();
         if ends_with_brace || between_brackets {
             // Add one level of indentation after { or between brackets
-            indent.push_str(&" ".repeat(tab_width));
+            indent.push_str(&self.indent_unit());
         }
 
         if between_brackets {
@@ -5458,16 +5728,16 @@ impl Editor {
     /// Handles }, ], and ) characters with tree-sitter based dedent detection
     fn insert_closing_bracket(&mut self, bracket: char) {
         let tab_width = self.settings.editor.tab_width;
-        let language = self.syntax.language_name().map(|s| s.to_string());
+        let language = self.syntax().language_name().map(|s| s.to_string());
 
         // Try tree-sitter based dedent for supported languages
         if matches!(
             language.as_deref(),
             Some("javascript" | "typescript" | "tsx" | "css" | "json" | "toml" | "html")
         ) {
-            if let Some((tree, source)) = self.syntax.get_tree_and_source() {
+            if let Some((tree, source)) = self.syntax().get_tree_and_source() {
                 if let Some(cursor_byte) = self
-                    .syntax
+                    .syntax()
                     .position_to_byte(self.cursor.line, self.cursor.col)
                 {
                     let dedent_amount = crate::indent::get_dedent_amount(
@@ -5807,11 +6077,10 @@ impl Editor {
             let buffer = &self.buffers[self.current_buffer_idx];
             let base_indent = buffer.get_line_indent(self.cursor.line);
             let ends_with_brace = buffer.line_ends_with(self.cursor.line, '{');
-            let tab_width = self.settings.editor.tab_width;
 
             let mut indent = base_indent;
             if ends_with_brace {
-                indent.push_str(&" ".repeat(tab_width));
+                indent.push_str(&self.indent_unit());
             }
             indent
         } else {
@@ -5959,6 +6228,7 @@ impl Editor {
     /// Exit command mode back to normal
     pub fn exit_command_mode(&mut self) {
         self.mode = Mode::Normal;
+        self.clear_substitute_preview();
         self.command_line.clear();
         self.render_damage.mark_full();
     }
@@ -6193,7 +6463,7 @@ impl Editor {
             self.viewport_offset = 0;
             self.h_offset = 0;
             self.reset_current_undo_stack();
-            self.parse_current_buffer();
+            self.detect_buffer_syntax(self.current_buffer_idx);
             self.set_status("File reloaded");
             Ok(())
         } else {
@@ -6233,11 +6503,7 @@ impl Editor {
 
         for idx in reloaded_indices {
             self.reset_undo_stack_for_buffer(idx);
-        }
-
-        // Re-parse current buffer if it was reloaded
-        if !reloaded.is_empty() {
-            self.parse_current_buffer();
+            self.refresh_buffer_syntax(idx);
         }
 
         // Build status message
@@ -6439,21 +6705,60 @@ impl Editor {
         }
     }
 
+    /// Case options for compiling search patterns from `ignore_case` / `smart_case`.
+    fn search_case_options(&self, skip_smartcase: bool) -> CaseOptions {
+        CaseOptions {
+            ignore_case: self.ignore_case(),
+            smart_case: self.smart_case() && !skip_smartcase,
+        }
+    }
+
+    /// Compile a search pattern, reporting syntax errors in the status line.
+    fn compile_search_pattern(
+        &mut self,
+        source: &str,
+        skip_smartcase: bool,
+    ) -> Option<SearchPattern> {
+        match SearchPattern::new(source, self.search_case_options(skip_smartcase)) {
+            Ok(pattern) => Some(pattern),
+            Err(err) => {
+                self.set_status(err);
+                None
+            }
+        }
+    }
+
+    /// Compile the last search pattern (for n/N/gn).
+    fn last_search_pattern(&mut self) -> Option<(String, SearchPattern)> {
+        let Some(source) = self.search.last_pattern.clone() else {
+            self.set_status("No previous search pattern");
+            return None;
+        };
+        let pattern = self.compile_search_pattern(&source, self.search.last_skips_smartcase)?;
+        Some((source, pattern))
+    }
+
     /// Update incremental search matches based on current search input.
     /// Search navigation still scans the whole buffer, but highlight storage
     /// is limited to visible rows so rendering stays bounded.
     pub fn update_incremental_search(&mut self) {
-        let pattern = self.search.input.clone();
-        if pattern.is_empty() {
+        let (source, _) = self.search.split_input();
+        if source.is_empty() {
             self.search_matches.clear();
             self.render_damage.mark_full();
             return;
         }
 
-        if let Some((line, col, _)) = self.find_search_target(&pattern, self.search.direction, true)
-        {
-            self.cursor.line = line;
-            self.cursor.col = col;
+        // Half-typed patterns such as `\(` are expected to fail while typing.
+        let Ok(pattern) = SearchPattern::new(&source, self.search_case_options(false)) else {
+            self.search_matches.clear();
+            self.render_damage.mark_full();
+            return;
+        };
+
+        if let Some((found, _)) = self.find_search_target(&pattern, self.search.direction, true) {
+            self.cursor.line = found.start_line;
+            self.cursor.col = found.start_col;
             self.scroll_to_cursor();
         }
 
@@ -6465,114 +6770,78 @@ impl Editor {
     pub fn execute_search(&mut self) {
         self.render_damage.mark_full();
         let direction = self.search.direction;
-        if let Some(pattern) = self.search.execute() {
-            self.mode = Mode::Normal;
-            if self.cursor_starts_search_match(&pattern)
-                || self.do_search(&pattern, direction, true)
-            {
-                self.refresh_visible_search_matches(&pattern);
-            } else {
-                self.search_matches.clear();
-                self.set_status(format!("Pattern not found: {}", pattern));
+        self.mode = Mode::Normal;
+        let source = match self.search.execute() {
+            Ok(Some(source)) => source,
+            Ok(None) => {
+                self.set_status("No previous search pattern");
+                return;
+            }
+            Err(err) => {
+                self.set_status(err);
+                return;
             }
+        };
+
+        let Some(pattern) = self.compile_search_pattern(&source, self.search.last_skips_smartcase)
+        else {
+            self.search_matches.clear();
+            return;
+        };
+
+        let at_cursor = pattern.match_at(
+            &self.buffers[self.current_buffer_idx],
+            self.cursor.line,
+            self.cursor.col,
+        );
+        if let Some(found) = at_cursor {
+            self.jump_to_search_match(found, self.search.last_offset);
+            self.refresh_visible_search_matches(&pattern);
+        } else if self.do_search(&pattern, direction, true) {
+            self.refresh_visible_search_matches(&pattern);
         } else {
-            self.mode = Mode::Normal;
-            self.set_status("No previous search pattern");
+            self.search_matches.clear();
+            self.set_status(format!("Pattern not found: {}", source));
         }
     }
 
     /// Search for next occurrence (n)
     pub fn search_next(&mut self) {
         self.render_damage.mark_full();
-        if let Some(pattern) = self.search.last_pattern.clone() {
-            // Record jump before searching (search is a jump motion)
-            self.record_jump();
-            let direction = self.search.last_direction;
-            if self.do_search(&pattern, direction, true) {
-                self.refresh_visible_search_matches(&pattern);
-            } else {
-                self.search_matches.clear();
-                self.set_status(format!("Pattern not found: {}", pattern));
-            }
-        } else {
-            self.set_status("No previous search pattern");
-        }
+        let direction = self.search.last_direction;
+        self.search_last_pattern(direction);
     }
 
     /// Search for previous occurrence (N)
     pub fn search_prev(&mut self) {
         self.render_damage.mark_full();
-        if let Some(pattern) = self.search.last_pattern.clone() {
-            // Record jump before searching (search is a jump motion)
-            self.record_jump();
-            // Reverse the direction
-            let direction = match self.search.last_direction {
-                SearchDirection::Forward => SearchDirection::Backward,
-                SearchDirection::Backward => SearchDirection::Forward,
-            };
-            if self.do_search(&pattern, direction, true) {
-                self.refresh_visible_search_matches(&pattern);
-            } else {
-                self.search_matches.clear();
-                self.set_status(format!("Pattern not found: {}", pattern));
-            }
-        } else {
-            self.set_status("No previous search pattern");
-        }
+        // Reverse the direction
+        let direction = match self.search.last_direction {
+            SearchDirection::Forward => SearchDirection::Backward,
+            SearchDirection::Backward => SearchDirection::Forward,
+        };
+        self.search_last_pattern(direction);
     }
 
-    /// Update search matches from a pattern string (used for n/N/*/#)
-    fn update_search_matches_from_pattern(&mut self, pattern: &str) {
-        self.search_matches.clear();
-        self.render_damage.mark_full();
-
-        if pattern.is_empty() {
-            return;
-        }
-
-        let total_lines = self.buffers[self.current_buffer_idx].len_lines();
-        if total_lines == 0 {
+    fn search_last_pattern(&mut self, direction: SearchDirection) {
+        let Some((source, pattern)) = self.last_search_pattern() else {
             return;
-        }
-
-        let pattern_len = pattern.chars().count();
-
-        // Find all matches in the buffer
-        for line_idx in 0..total_lines {
-            if let Some(line) = self.buffers[self.current_buffer_idx].line(line_idx) {
-                let line_str: String = line.chars().collect();
-
-                // Find all occurrences in this line
-                let mut search_from = 0;
-                while search_from < line_str.len() {
-                    if let Some(byte_pos) = line_str[search_from..].find(pattern) {
-                        let match_byte_start = search_from + byte_pos;
-                        let match_byte_end = match_byte_start + pattern.len();
-
-                        // Convert byte positions to char positions
-                        let start_col = Self::byte_to_char_idx(&line_str, match_byte_start);
-                        let end_col = start_col + pattern_len;
-
-                        self.search_matches.push((line_idx, start_col, end_col));
+        };
 
-                        // Move past this match to find more
-                        search_from = match_byte_end;
-                    } else {
-                        break;
-                    }
-                }
-            }
+        // Record jump before searching (search is a jump motion)
+        self.record_jump();
+        if self.do_search(&pattern, direction, true) {
+            self.refresh_visible_search_matches(&pattern);
+        } else {
+            self.search_matches.clear();
+            self.set_status(format!("Pattern not found: {}", source));
         }
     }
 
-    fn refresh_visible_search_matches(&mut self, pattern: &str) {
+    fn refresh_visible_search_matches(&mut self, pattern: &SearchPattern) {
         self.search_matches.clear();
         self.render_damage.mark_full();
 
-        if pattern.is_empty() {
-            return;
-        }
-
         let total_lines = self.buffers[self.current_buffer_idx].len_lines();
         if total_lines == 0 {
             return;
@@ -6582,30 +6851,45 @@ impl Editor {
         let end_line = viewport_offset
             .saturating_add(visible_rows)
             .min(total_lines);
-        let pattern_len = pattern.chars().count();
+        let matches = pattern.buffer_matches(
+            &self.buffers[self.current_buffer_idx],
+            viewport_offset..end_line,
+            MAX_VISIBLE_SEARCH_MATCHES,
+        );
+        for found in matches {
+            self.push_search_match_segments(found, end_line);
+            if self.search_matches.len() >= MAX_VISIBLE_SEARCH_MATCHES {
+                self.search_matches.truncate(MAX_VISIBLE_SEARCH_MATCHES);
+                break;
+            }
+        }
+    }
 
-        'lines: for line_idx in viewport_offset..end_line {
-            if let Some(line) = self.buffers[self.current_buffer_idx].line(line_idx) {
-                let line_str: String = line.chars().collect();
-                let mut search_from = 0;
-
-                while search_from < line_str.len() {
-                    if let Some(byte_pos) = line_str[search_from..].find(pattern) {
-                        let match_byte_start = search_from + byte_pos;
-                        let match_byte_end = match_byte_start + pattern.len();
-                        let start_col = Self::byte_to_char_idx(&line_str, match_byte_start);
-                        let end_col = start_col + pattern_len;
-
-                        self.search_matches.push((line_idx, start_col, end_col));
-                        if self.search_matches.len() >= MAX_VISIBLE_SEARCH_MATCHES {
-                            break 'lines;
-                        }
+    /// Store a match as per-line highlight segments, splitting matches that
+    /// span line breaks.
+    fn push_search_match_segments(&mut self, found: BufferMatch, end_line: usize) {
+        if found.start_line == found.end_line {
+            self.search_matches
+                .push((found.start_line, found.start_col, found.end_col));
+            return;
+        }
 
-                        search_from = match_byte_end;
-                    } else {
-                        break;
-                    }
-                }
+        let buffer = &self.buffers[self.current_buffer_idx];
+        let last_line = found.end_line.min(end_line.saturating_sub(1));
+        for line_idx in found.start_line..=last_line {
+            let start_col = if line_idx == found.start_line {
+                found.start_col
+            } else {
+                0
+            };
+            let end_col = if line_idx == found.end_line {
+                found.end_col
+            } else {
+                // Include the line break so matched empty lines stay visible.
+                buffer.line_len(line_idx) + 1
+            };
+            if end_col > start_col {
+                self.search_matches.push((line_idx, start_col, end_col));
             }
         }
     }
@@ -6615,69 +6899,48 @@ impl Editor {
             return;
         }
 
-        if let Some(pattern) = self.search.last_pattern.clone() {
+        let Some(source) = self.search.last_pattern.clone() else {
+            return;
+        };
+        let case = self.search_case_options(self.search.last_skips_smartcase);
+        if let Ok(pattern) = SearchPattern::new(&source, case) {
             self.refresh_visible_search_matches(&pattern);
         }
     }
 
-    fn cursor_starts_search_match(&self, pattern: &str) -> bool {
-        if pattern.is_empty() {
-            return false;
-        }
-
-        let Some(line) = self.buffers[self.current_buffer_idx].line(self.cursor.line) else {
-            return false;
-        };
+    /// Search for word under cursor forward (*)
+    pub fn search_word_forward(&mut self) {
+        self.search_word_under_cursor(SearchDirection::Forward);
+    }
 
-        let pattern_len = pattern.chars().count();
-        let line_len = line.len_chars();
-        if self.cursor.col.saturating_add(pattern_len) > line_len {
-            return false;
-        }
-
-        let mut line_chars = line.slice(self.cursor.col..line_len).chars();
-        for expected in pattern.chars() {
-            if line_chars.next() != Some(expected) {
-                return false;
-            }
-        }
-
-        true
+    /// Search for word under cursor backward (#)
+    pub fn search_word_backward(&mut self) {
+        self.search_word_under_cursor(SearchDirection::Backward);
     }
 
-    /// Search for word under cursor forward (*)
-    pub fn search_word_forward(&mut self) {
+    fn search_word_under_cursor(&mut self, direction: SearchDirection) {
         self.render_damage.mark_full();
-        if let Some(word) = self.get_word_under_cursor() {
-            // Set as search pattern
-            self.search.last_pattern = Some(word.clone());
-            self.search.last_direction = SearchDirection::Forward;
-            if self.do_search(&word, SearchDirection::Forward, true) {
-                self.refresh_visible_search_matches(&word);
-            } else {
-                self.search_matches.clear();
-                self.set_status(format!("Pattern not found: {}", word));
-            }
-        } else {
+        let Some(word) = self.get_word_under_cursor() else {
             self.set_status("No word under cursor");
-        }
-    }
+            return;
+        };
 
-    /// Search for word under cursor backward (#)
-    pub fn search_word_backward(&mut self) {
-        self.render_damage.mark_full();
-        if let Some(word) = self.get_word_under_cursor() {
-            // Set as search pattern
-            self.search.last_pattern = Some(word.clone());
-            self.search.last_direction = SearchDirection::Backward;
-            if self.do_search(&word, SearchDirection::Backward, true) {
-                self.refresh_visible_search_matches(&word);
-            } else {
-                self.search_matches.clear();
-                self.set_status(format!("Pattern not found: {}", word));
-            }
+        // Like Vim, `*` and `#` match whole keywords and ignore `smartcase`.
+        let source = format!(r"\<{}\>", pattern::escape_literal(&word));
+        self.search.last_pattern = Some(source.clone());
+        self.search.last_direction = direction;
+        self.search.last_offset = SearchOffset::None;
+        self.search.last_skips_smartcase = true;
+
+        let Some(pattern) = self.compile_search_pattern(&source, true) else {
+            self.search_matches.clear();
+            return;
+        };
+        if self.do_search(&pattern, direction, true) {
+            self.refresh_visible_search_matches(&pattern);
         } else {
-            self.set_status("No word under cursor");
+            self.search_matches.clear();
+            self.set_status(format!("Pattern not found: {}", word));
         }
     }
 
@@ -6692,78 +6955,105 @@ impl Editor {
     }
 
     fn search_select_match(&mut self, direction: SearchDirection, count: usize) {
-        let Some(pattern) = self.search.last_pattern.clone() else {
-            self.set_status("No previous search pattern");
+        let Some((source, pattern)) = self.last_search_pattern() else {
             return;
         };
 
-        self.update_search_matches_from_pattern(&pattern);
+        let total_lines = self.buffers[self.current_buffer_idx].len_lines();
+        let matches = pattern.buffer_matches(
+            &self.buffers[self.current_buffer_idx],
+            0..total_lines,
+            usize::MAX,
+        );
 
-        let Some((line, start_col, end_col)) =
-            self.search_match_from_cursor(direction, count.max(1))
-        else {
-            self.set_status(format!("Pattern not found: {}", pattern));
+        let Some(found) = self.search_match_from_cursor(&matches, direction, count.max(1)) else {
+            self.search_matches.clear();
+            self.render_damage.mark_full();
+            self.set_status(format!("Pattern not found: {}", source));
             return;
         };
 
         self.record_jump();
         self.mode = Mode::Visual;
-        self.visual = VisualSelection::new(line, start_col);
-        self.cursor.line = line;
-        self.cursor.col = end_col.saturating_sub(1).max(start_col);
+        self.visual = VisualSelection::new(found.start_line, found.start_col);
+        let (end_line, end_col) = self.search_match_last_char(found);
+        self.cursor.line = end_line;
+        self.cursor.col = end_col;
         self.scroll_to_cursor();
+        self.refresh_visible_search_matches(&pattern);
     }
 
     fn search_match_from_cursor(
         &self,
+        matches: &[BufferMatch],
         direction: SearchDirection,
         count: usize,
-    ) -> Option<(usize, usize, usize)> {
-        if self.search_matches.is_empty() {
+    ) -> Option<BufferMatch> {
+        if matches.is_empty() {
             return None;
         }
 
         let cursor_line = self.cursor.line;
         let cursor_col = self.cursor.col;
-        let mut ordered = Vec::with_capacity(self.search_matches.len());
+        let ends_after_cursor = |found: &BufferMatch| {
+            found.end_line > cursor_line
+                || (found.end_line == cursor_line && found.end_col > cursor_col)
+        };
+        let starts_at_or_before_cursor = |found: &BufferMatch| {
+            found.start_line < cursor_line
+                || (found.start_line == cursor_line && found.start_col <= cursor_col)
+        };
+        let mut ordered = Vec::with_capacity(matches.len());
 
         match direction {
             SearchDirection::Forward => {
                 ordered.extend(
-                    self.search_matches
+                    matches
                         .iter()
                         .copied()
-                        .filter(|(line, _, end_col)| {
-                            *line > cursor_line || (*line == cursor_line && *end_col > cursor_col)
-                        }),
+                        .filter(|found| ends_after_cursor(found)),
                 );
                 ordered.extend(
-                    self.search_matches
+                    matches
                         .iter()
                         .copied()
-                        .filter(|(line, _, end_col)| {
-                            !(*line > cursor_line
-                                || (*line == cursor_line && *end_col > cursor_col))
-                        }),
+                        .filter(|found| !ends_after_cursor(found)),
                 );
             }
             SearchDirection::Backward => {
-                ordered.extend(self.search_matches.iter().rev().copied().filter(
-                    |(line, start_col, _)| {
-                        *line < cursor_line || (*line == cursor_line && *start_col <= cursor_col)
-                    },
-                ));
-                ordered.extend(self.search_matches.iter().rev().copied().filter(
-                    |(line, start_col, _)| {
-                        !(*line < cursor_line || (*line == cursor_line && *start_col <= cursor_col))
-                    },
-                ));
+                ordered.extend(
+                    matches
+                        .iter()
+                        .rev()
+                        .copied()
+                        .filter(|found| starts_at_or_before_cursor(found)),
+                );
+                ordered.extend(
+                    matches
+                        .iter()
+                        .rev()
+                        .copied()
+                        .filter(|found| !starts_at_or_before_cursor(found)),
+                );
             }
         }
 
         ordered.get((count - 1) % ordered.len()).copied()
     }
 
+    /// Position of the last character covered by a match (the match start for
+    /// empty matches).
+    fn search_match_last_char(&self, found: BufferMatch) -> (usize, usize) {
+        if (found.end_line, found.end_col) <= (found.start_line, found.start_col) {
+            return (found.start_line, found.start_col);
+        }
+        if found.end_col > 0 {
+            return (found.end_line, found.end_col - 1);
+        }
+        let line = found.end_line - 1;
+        (line, self.buffers[self.current_buffer_idx].line_len(line))
+    }
+
     /// Get the word under the cursor
     pub fn get_word_under_cursor(&self) -> Option<String> {
         let line = self.buffers[self.current_buffer_idx].line(self.cursor.line)?;
@@ -6835,13 +7125,15 @@ impl Editor {
         self.open_url_under_cursor_with(Self::open_url_external)
     }
 
-    /// Open the URL under the cursor with an injected opener.
+    /// Open the URL under the cursor with an injected opener. Without one,
+    /// the page explaining a diagnostic on the cursor line is opened.
     pub fn open_url_under_cursor_with<F>(&mut self, mut opener: F) -> Result<String, String>
     where
         F: FnMut(&str) -> Result<(), String>,
     {
         let url = self
             .url_under_cursor()
+            .or_else(|| self.diagnostic_href_at_cursor())
             .ok_or_else(|| "No URL under cursor".to_string())?;
         opener(&url)?;
         Ok(url)
@@ -6979,106 +7271,41 @@ impl Editor {
         ch.is_alphanumeric() || ch == '_'
     }
 
-    /// Search and replace text
-    /// Returns the number of replacements made
-    pub fn substitute(
+    /// Perform the actual search, applying the last search offset.
+    /// Returns true if found, false otherwise
+    fn do_search(
         &mut self,
-        pattern: &str,
-        replacement: &str,
-        entire_file: bool,
-        global: bool,
-    ) -> usize {
-        if pattern.is_empty() {
-            return 0;
-        }
-
-        // Begin undo group for all replacements
-        self.begin_change();
-
-        let mut total_replacements = 0;
-        let pattern_len = pattern.len();
-
-        // Determine line range
-        let (start_line, end_line) = if entire_file {
-            (0, self.buffers[self.current_buffer_idx].len_lines())
-        } else {
-            (self.cursor.line, self.cursor.line + 1)
+        pattern: &SearchPattern,
+        direction: SearchDirection,
+        wrap: bool,
+    ) -> bool {
+        let offset = self.search.last_offset;
+        let (line, col) = self.search_origin(offset, direction);
+        let buffer = &self.buffers[self.current_buffer_idx];
+        let Some((mut found, mut wrapped)) =
+            pattern.find_in_buffer(buffer, line, col, direction, wrap)
+        else {
+            return false;
         };
 
-        for line_idx in start_line..end_line {
-            if let Some(line) = self.buffers[self.current_buffer_idx].line(line_idx) {
-                let line_str: String = line.chars().collect();
-                let mut new_line = String::new();
-                let mut last_end = 0;
-                let mut search_from = 0;
-                let mut line_replacements = 0;
-
-                // Find all matches in this line
-                while search_from < line_str.len() {
-                    if let Some(byte_pos) = line_str[search_from..].find(pattern) {
-                        let match_start = search_from + byte_pos;
-                        let match_end = match_start + pattern_len;
-
-                        // Copy text before match
-                        new_line.push_str(&line_str[last_end..match_start]);
-                        // Add replacement
-                        new_line.push_str(replacement);
-
-                        last_end = match_end;
-                        search_from = match_end;
-                        line_replacements += 1;
-                        total_replacements += 1;
-
-                        // If not global, only replace first occurrence on line
-                        if !global {
-                            break;
-                        }
-                    } else {
-                        break;
-                    }
-                }
-
-                // If we made replacements, update the line
-                if line_replacements > 0 {
-                    // Copy remaining text after last match
-                    new_line.push_str(&line_str[last_end..]);
-
-                    // Record undo
-                    self.undo_stack
-                        .record_change(crate::editor::undo::Change::replace_line(
-                            line_idx,
-                            line_str.clone(),
-                            new_line.clone(),
-                        ));
-
-                    // Replace the line in buffer
-                    self.buffers[self.current_buffer_idx].replace_line(line_idx, &new_line);
+        // With an end offset the cursor sits inside the current match, so a
+        // backward search would find that same match again.
+        if matches!(offset, SearchOffset::End(_))
+            && direction == SearchDirection::Backward
+            && self.search_match_last_char(found) == (line, col)
+        {
+            let buffer = &self.buffers[self.current_buffer_idx];
+            match pattern.find_in_buffer(buffer, found.start_line, found.start_col, direction, wrap)
+            {
+                Some((previous, previous_wrapped)) => {
+                    found = previous;
+                    wrapped |= previous_wrapped;
                 }
+                None => return false,
             }
         }
 
-        // End undo group
-        self.undo_stack
-            .end_undo_group(self.cursor.line, self.cursor.col);
-
-        // Mark buffer as modified if changes were made
-        if total_replacements > 0 {
-            self.buffers[self.current_buffer_idx].mark_modified();
-        }
-
-        total_replacements
-    }
-
-    /// Perform the actual search
-    /// Returns true if found, false otherwise
-    fn do_search(&mut self, pattern: &str, direction: SearchDirection, wrap: bool) -> bool {
-        let Some((line, col, wrapped)) = self.find_search_target(pattern, direction, wrap) else {
-            return false;
-        };
-
-        self.cursor.line = line;
-        self.cursor.col = col;
-        self.scroll_to_cursor();
+        self.jump_to_search_match(found, offset);
 
         if wrapped {
             match direction {
@@ -7096,140 +7323,63 @@ impl Editor {
 
     fn find_search_target(
         &self,
-        pattern: &str,
+        pattern: &SearchPattern,
         direction: SearchDirection,
         wrap: bool,
-    ) -> Option<(usize, usize, bool)> {
-        let total_lines = self.buffers[self.current_buffer_idx].len_lines();
-        if total_lines == 0 || pattern.is_empty() {
-            return None;
-        }
-        let pattern_len = pattern.chars().count();
-
-        match direction {
-            SearchDirection::Forward => {
-                // Search from current position forward
-                // First check rest of current line after cursor
-                if let Some(line) = self.buffers[self.current_buffer_idx].line(self.cursor.line) {
-                    let line_str: String = line.chars().collect();
-                    let search_start = self.cursor.col + 1;
-                    let search_start_byte = Self::char_to_byte_idx(&line_str, search_start);
-                    if search_start_byte < line_str.len() {
-                        if let Some(pos) = line_str[search_start_byte..].find(pattern) {
-                            let byte_pos = search_start_byte + pos;
-                            return Some((
-                                self.cursor.line,
-                                Self::byte_to_char_idx(&line_str, byte_pos),
-                                false,
-                            ));
-                        }
-                    }
-                }
-
-                // Search subsequent lines
-                for line_idx in (self.cursor.line + 1)..total_lines {
-                    if let Some(line) = self.buffers[self.current_buffer_idx].line(line_idx) {
-                        let line_str: String = line.chars().collect();
-                        if let Some(pos) = line_str.find(pattern) {
-                            return Some((line_idx, Self::byte_to_char_idx(&line_str, pos), false));
-                        }
-                    }
-                }
+    ) -> Option<(BufferMatch, bool)> {
+        pattern.find_in_buffer(
+            &self.buffers[self.current_buffer_idx],
+            self.cursor.line,
+            self.cursor.col,
+            direction,
+            wrap,
+        )
+    }
 
-                // Wrap around if enabled
-                if wrap {
-                    for line_idx in 0..=self.cursor.line {
-                        if let Some(line) = self.buffers[self.current_buffer_idx].line(line_idx) {
-                            let line_str: String = line.chars().collect();
-                            let end_col = if line_idx == self.cursor.line {
-                                self.cursor.col.saturating_add(pattern_len)
-                            } else {
-                                line_str.chars().count()
-                            };
-                            let end_byte = Self::char_to_byte_idx(&line_str, end_col);
-                            if let Some(pos) =
-                                line_str[..end_byte.min(line_str.len())].find(pattern)
-                            {
-                                return Some((
-                                    line_idx,
-                                    Self::byte_to_char_idx(&line_str, pos),
-                                    true,
-                                ));
-                            }
-                        }
-                    }
+    /// Undo the search offset at the cursor so `n`/`N` continue from the match
+    /// itself rather than from where the offset placed the cursor.
+    fn search_origin(&self, offset: SearchOffset, direction: SearchDirection) -> (usize, usize) {
+        let (line, col) = (self.cursor.line, self.cursor.col);
+        match offset {
+            SearchOffset::None => (line, col),
+            SearchOffset::Line(lines) => {
+                let origin = line.saturating_add_signed(-lines);
+                let origin = origin.min(self.buffer().len_lines().saturating_sub(1));
+                match direction {
+                    SearchDirection::Forward => (origin, usize::MAX),
+                    SearchDirection::Backward => (origin, 0),
                 }
             }
-            SearchDirection::Backward => {
-                // Search from current position backward
-                // First check current line before cursor
-                if let Some(line) = self.buffers[self.current_buffer_idx].line(self.cursor.line) {
-                    let line_str: String = line.chars().collect();
-                    if self.cursor.col > 0 {
-                        let end_byte = Self::char_to_byte_idx(&line_str, self.cursor.col);
-                        if let Some(pos) = line_str[..end_byte].rfind(pattern) {
-                            return Some((
-                                self.cursor.line,
-                                Self::byte_to_char_idx(&line_str, pos),
-                                false,
-                            ));
-                        }
-                    }
-                }
-
-                // Search previous lines
-                for line_idx in (0..self.cursor.line).rev() {
-                    if let Some(line) = self.buffers[self.current_buffer_idx].line(line_idx) {
-                        let line_str: String = line.chars().collect();
-                        if let Some(pos) = line_str.rfind(pattern) {
-                            return Some((line_idx, Self::byte_to_char_idx(&line_str, pos), false));
-                        }
-                    }
-                }
-
-                // Wrap around if enabled
-                if wrap {
-                    for line_idx in (self.cursor.line..total_lines).rev() {
-                        if let Some(line) = self.buffers[self.current_buffer_idx].line(line_idx) {
-                            let line_str: String = line.chars().collect();
-                            let start_col = if line_idx == self.cursor.line {
-                                self.cursor.col
-                            } else {
-                                0
-                            };
-                            let start_byte = Self::char_to_byte_idx(&line_str, start_col);
-                            if start_byte < line_str.len() {
-                                if let Some(pos) = line_str[start_byte..].rfind(pattern) {
-                                    return Some((
-                                        line_idx,
-                                        Self::byte_to_char_idx(&line_str, start_byte + pos),
-                                        true,
-                                    ));
-                                }
-                            }
-                        }
-                    }
-                }
+            SearchOffset::Start(chars) | SearchOffset::End(chars) => {
+                (line, col.saturating_add_signed(-chars))
             }
         }
-
-        None
     }
 
-    /// Convert a character index to a byte index in a string
-    fn char_to_byte_idx(s: &str, char_idx: usize) -> usize {
-        if char_idx == 0 {
-            return 0;
-        }
-        s.char_indices()
-            .nth(char_idx)
-            .map(|(idx, _)| idx)
-            .unwrap_or_else(|| s.len())
-    }
+    /// Move the cursor to a search match, honoring a `/pat/e+1`-style offset.
+    fn jump_to_search_match(&mut self, found: BufferMatch, offset: SearchOffset) {
+        let (line, col) = match offset {
+            SearchOffset::None => (found.start_line, found.start_col),
+            SearchOffset::Line(lines) => {
+                let last_line = self.buffer().len_lines().saturating_sub(1);
+                (
+                    found.start_line.saturating_add_signed(lines).min(last_line),
+                    0,
+                )
+            }
+            SearchOffset::Start(chars) => (
+                found.start_line,
+                found.start_col.saturating_add_signed(chars),
+            ),
+            SearchOffset::End(chars) => {
+                let (line, col) = self.search_match_last_char(found);
+                (line, col.saturating_add_signed(chars))
+            }
+        };
 
-    /// Convert a byte index to a character index in a string
-    fn byte_to_char_idx(s: &str, byte_idx: usize) -> usize {
-        s[..byte_idx.min(s.len())].chars().count()
+        self.cursor.line = line;
+        self.cursor.col = col.min(self.buffer().line_len(line));
+        self.scroll_to_cursor();
     }
 
     /// Enter visual mode (character-wise)
@@ -8915,7 +9065,7 @@ impl Editor {
     /// Toggle comment on a range of lines (gc{motion} command)
     /// Uses the vim convention: if any line is uncommented, comment all; otherwise uncomment all
     pub fn toggle_comment_lines(&mut self, start_line: usize, end_line: usize) {
-        let language = self.syntax.language_name();
+        let language = self.syntax().language_name();
         let comment_start = crate::syntax::get_comment_string(language);
         let comment_end = crate::syntax::get_comment_end(language);
         let buffer = &self.buffers[self.current_buffer_idx];
@@ -9073,7 +9223,7 @@ impl Editor {
 
     /// Indent a range of lines by one level
     pub fn indent_lines(&mut self, start_line: usize, end_line: usize) {
-        let indent_str = " ".repeat(self.settings.editor.tab_width);
+        let indent_str = self.indent_unit();
         let buffer = &self.buffers[self.current_buffer_idx];
         let max_line = buffer.len_lines().saturating_sub(1);
         let end_line = end_line.min(max_line);
@@ -9112,7 +9262,7 @@ impl Editor {
 
     /// Dedent a range of lines by one level
     pub fn dedent_lines(&mut self, start_line: usize, end_line: usize) {
-        let tab_width = self.settings.editor.tab_width;
+        let shift_width = self.shift_width();
         let buffer = &self.buffers[self.current_buffer_idx];
         let max_line = buffer.len_lines().saturating_sub(1);
         let end_line = end_line.min(max_line);
@@ -9124,31 +9274,33 @@ impl Editor {
             if let Some(line) = self.buffers[self.current_buffer_idx].line(line_num) {
                 let line_str: String = line.chars().collect();
 
-                // Count leading whitespace
-                let mut spaces_to_remove = 0;
+                // Count leading whitespace covering one indent level
+                let mut chars_to_remove = 0;
+                let mut removed_width = 0;
                 for ch in line_str.chars() {
-                    if ch == ' ' && spaces_to_remove < tab_width {
-                        spaces_to_remove += 1;
-                    } else if ch == '\t' && spaces_to_remove < tab_width {
-                        // Treat tab as filling to tab_width
-                        spaces_to_remove = tab_width;
-                        break;
-                    } else {
+                    if removed_width >= shift_width {
                         break;
                     }
+                    match ch {
+                        ' ' => removed_width += 1,
+                        // Treat tab as filling the rest of the level
+                        '\t' => removed_width = shift_width,
+                        _ => break,
+                    }
+                    chars_to_remove += 1;
                 }
 
-                if spaces_to_remove == 0 {
+                if chars_to_remove == 0 {
                     continue;
                 }
 
                 // Record deletion for undo
-                let deleted_text: String = line_str.chars().take(spaces_to_remove).collect();
+                let deleted_text: String = line_str.chars().take(chars_to_remove).collect();
                 self.undo_stack
                     .record_change(Change::delete(line_num, 0, deleted_text));
 
                 // Delete the leading whitespace
-                for _ in 0..spaces_to_remove {
+                for _ in 0..chars_to_remove {
                     self.buffers[self.current_buffer_idx].delete_char(line_num, 0);
                 }
             }
@@ -9272,17 +9424,17 @@ impl Editor {
 
         let prev_line = line_num.saturating_sub(1);
         let prev_col = self.buffers[self.current_buffer_idx].line_len(prev_line);
-        let Some(cursor_byte) = self.syntax.position_to_byte(prev_line, prev_col) else {
+        let Some(cursor_byte) = self.syntax().position_to_byte(prev_line, prev_col) else {
             return fallback;
         };
-        let Some((tree, source)) = self.syntax.get_tree_and_source() else {
+        let Some((tree, source)) = self.syntax().get_tree_and_source() else {
             return fallback;
         };
 
         let mut indent = crate::indent::calculate_indent(tree, source, cursor_byte, tab_width);
 
         if let Some(bracket) = self.line_start_closing_delimiter(line_num) {
-            if let Some(current_byte) = self.syntax.position_to_byte(line_num, 0) {
+            if let Some(current_byte) = self.syntax().position_to_byte(line_num, 0) {
                 indent = crate::indent::calculate_closing_bracket_indent(
                     tree,
                     source,
@@ -9387,8 +9539,8 @@ impl Editor {
 
     /// Increase indentation of the current line while preserving insert position.
     pub fn indent_current_line_in_insert_mode(&mut self) {
-        let tab_width = self.settings.editor.tab_width;
-        if tab_width == 0 {
+        let indent = self.indent_unit();
+        if indent.is_empty() {
             return;
         }
 
@@ -9397,11 +9549,10 @@ impl Editor {
             return;
         }
 
-        let indent = " ".repeat(tab_width);
         self.undo_stack
             .record_change(Change::insert(line, 0, indent.clone()));
         self.buffers[self.current_buffer_idx].insert_str(line, 0, &indent);
-        self.cursor.col += tab_width;
+        self.cursor.col += indent.chars().count();
         self.scroll_to_cursor();
     }
 
@@ -9414,7 +9565,7 @@ impl Editor {
 
     /// Decrease indentation of the current line while preserving insert position.
     pub fn dedent_current_line_in_insert_mode(&mut self) {
-        let tab_width = self.settings.editor.tab_width;
+        let tab_width = self.shift_width();
         if tab_width == 0 {
             return;
         }
@@ -9990,15 +10141,6 @@ impl Editor {
         }
     }
 
-    /// Repeat last change (. command)
-    /// Note: Full implementation would store last command sequence.
-    /// For now, this is a placeholder that shows a message.
-    pub fn repeat_last_change(&mut self) {
-        // TODO: Implement proper repeat functionality
-        // This requires storing the last change sequence (keys or operations)
-        self.set_status(". (repeat) not fully implemented yet");
-    }
-
     /// Apply motion with screen-relative awareness
     /// This overrides basic motion for H, M, L which need viewport info
     pub fn apply_motion(&mut self, motion: Motion, count: usize) {
@@ -10834,11 +10976,93 @@ impl Editor {
         self.mode = Mode::Finder;
     }
 
+    /// Open the fuzzy finder on the current buffer's symbol outline
+    pub fn open_finder_symbols(&mut self, symbols: Vec<SymbolItem>) {
+        use crate::finder::FinderItem;
+
+        if symbols.is_empty() {
+            self.set_status("No symbols in this buffer");
+            return;
+        }
+
+        let buffer_idx = self.current_buffer_idx;
+        let path = self.buffer().path.clone().unwrap_or_default();
+        let symbol_items = symbols
+            .iter()
+            .map(|symbol| {
+                // Indent by depth so the outline reads as a tree
+                let mut display = format!("{}{}", "  ".repeat(symbol.depth), symbol.name);
+                if let Some(detail) = &symbol.detail {
+                    display.push_str("  ");
+                    display.push_str(detail);
+                }
+                FinderItem::new(display, path.clone())
+                    .with_buffer_idx(buffer_idx)
+                    .with_line(symbol.line + 1)
+                    .with_col(self.lsp_utf16_col_to_buffer_col(symbol.line, symbol.col))
+                    .with_icon(symbol.kind.icon())
+            })
+            .collect();
+
+        self.finder.open_symbols(symbol_items);
+        self.mode = Mode::Finder;
+        self.update_finder_preview();
+    }
+
+    /// Open the fuzzy finder in workspace symbols mode; the server is
+    /// queried again each time the query changes
+    pub fn open_finder_workspace_symbols(&mut self) {
+        self.finder.open_workspace_symbols();
+        self.mode = Mode::Finder;
+    }
+
+    /// Show workspace symbol results if the finder is still waiting for
+    /// `query`. Returns whether the finder changed.
+    pub fn show_workspace_symbols(&mut self, query: &str, symbols: Vec<SymbolItem>) -> bool {
+        use crate::finder::FinderItem;
+
+        let cwd = self.working_directory();
+        let symbol_items = symbols
+            .into_iter()
+            .filter_map(|symbol| {
+                let path = crate::lsp::uri_to_path(&symbol.uri)?;
+                let col = match self.buffer_index_for_path(&path) {
+                    Some(idx) => Self::lsp_utf16_col_to_buffer_col_in_buffer(
+                        &self.buffers[idx],
+                        symbol.line,
+                        symbol.col,
+                    ),
+                    None => symbol.col,
+                };
+                let rel_path = path.strip_prefix(&cwd).unwrap_or(&path).to_string_lossy();
+                let display = match &symbol.container {
+                    Some(container) => format!(
+                        "{} ({}) | {}:{}",
+                        symbol.name,
+                        container,
+                        rel_path,
+                        symbol.line + 1
+                    ),
+                    None => format!("{} | {}:{}", symbol.name, rel_path, symbol.line + 1),
+                };
+                Some(
+                    FinderItem::new(display, path.clone())
+                        .with_line(symbol.line + 1)
+                        .with_col(col)
+                        .with_icon(symbol.kind.icon()),
+                )
+            })
+            .collect();
+
+        self.finder.set_workspace_symbols(query, symbol_items)
+    }
+
     /// Close the finder and return to normal mode
     pub fn close_finder(&mut self) {
         self.mode = Mode::Normal;
         self.clear_status();
         self.finder.cancel_grep_search();
+        self.finder.workspace_symbol_query_pending = false;
         self.finder.clear_preview_cache();
     }
 
@@ -10861,8 +11085,7 @@ impl Editor {
 
         // Check if we need to update (path changed)
         if self.finder.preview_path.as_ref() == Some(&selected_path)
-            && (self.finder.mode != crate::finder::FinderMode::Grep
-                || self.finder.preview_line == selected_line)
+            && (!self.finder.previews_around_line() || self.finder.preview_line == selected_line)
         {
             return;
         }
@@ -10882,8 +11105,12 @@ impl Editor {
 
         // Set up syntax highlighting for the preview file
         let first_line = self.finder.preview_content.first().map(|s| s.as_str());
-        self.preview_syntax
-            .set_language_from_path_and_first_line(&selected_path, first_line);
+        set_syntax_language(
+            &self.languages_config,
+            &mut self.preview_syntax,
+            Some(selected_path.as_path()),
+            first_line,
+        );
 
         // Sync theme
         self.preview_syntax.sync_theme(self.theme_manager.theme());
@@ -11114,21 +11341,33 @@ impl Editor {
     }
 
     fn parse_current_buffer(&mut self) {
-        let buffer_idx = self.current_buffer_idx;
-        let buffer = &self.buffers[buffer_idx];
-        self.syntax.parse(buffer);
-        self.last_syntax_version = buffer.version();
+        self.parse_buffer_syntax(self.current_buffer_idx);
         self.last_edit_at = None;
     }
 
+    /// Reparse every visible buffer that changed since its last parse.
+    fn refresh_visible_syntax(&mut self) -> bool {
+        let mut visible: Vec<usize> = self.panes.iter().map(|pane| pane.buffer_idx).collect();
+        visible.push(self.current_buffer_idx);
+        visible.sort_unstable();
+        visible.dedup();
+
+        let mut reparsed = false;
+        for idx in visible {
+            if idx < self.buffers.len() {
+                reparsed |= self.refresh_buffer_syntax(idx);
+            }
+        }
+        reparsed
+    }
+
     pub fn maybe_update_syntax(&mut self) {
         if self.mode == Mode::Insert {
             return;
         }
 
-        let version = self.buffers[self.current_buffer_idx].version();
-        if version != self.last_syntax_version {
-            self.parse_current_buffer();
+        if self.refresh_visible_syntax() {
+            self.last_edit_at = None;
         }
     }
 
@@ -11144,14 +11383,9 @@ impl Editor {
             return false;
         }
 
-        let version = self.buffers[self.current_buffer_idx].version();
-        if version != self.last_syntax_version {
-            self.parse_current_buffer();
-            return true;
-        }
-
+        let reparsed = self.refresh_visible_syntax();
         self.last_edit_at = None;
-        false
+        reparsed
     }
 
     // ============================================
@@ -11208,6 +11442,15 @@ impl Editor {
             }
         }
 
+        // The command runs after the edits, as the protocol requires.
+        if let Some(command) = &action.command {
+            let mut command = command.clone();
+            if command.title.is_empty() {
+                command.title = title.clone();
+            }
+            self.pending_lsp_action = Some(LspAction::ExecuteCommand(command));
+        }
+
         if total_edits > 0 {
             Some(format!("Applied '{}' ({} edits)", title, total_edits))
         } else if skipped_file_edits > 0 {
@@ -11216,7 +11459,7 @@ impl Editor {
                 title, skipped_file_edits
             ))
         } else if action.command.is_some() {
-            Some(format!("Action '{}' requires server-side command", title))
+            Some(format!("Running '{}'...", title))
         } else {
             Some(format!("Applied '{}'", title))
         }
@@ -11238,17 +11481,33 @@ fn project_replace_display_path(root: &std::path::Path, path: &std::path::Path)
 
 #[cfg(test)]
 mod tests {
+    mod buffer_syntax;
+    mod code_lens;
+    mod diagnostics;
+    mod document_highlight;
+    mod document_sync;
     mod editing_operators;
     mod file_lifecycle;
+    mod formatting;
+    mod global;
+    mod inlay_hints;
     mod insert_entry;
+    mod normal_command;
     mod open_line;
     mod replace;
     mod screen_position;
-
-    use super::{Editor, JumpList, Mode, SearchDirection, SplitLayout};
+    mod selection_range;
+    mod semantic_tokens;
+    mod set_options;
+    mod substitute;
+    mod support;
+    mod workspace_edit;
+
+    use super::{Editor, JumpList, LspAction, Mode, SearchDirection, SplitLayout};
     use crate::input::Motion;
     use crate::lsp::types::{
-        CodeActionItem, CompletionItem, CompletionKind, Diagnostic, DiagnosticSeverity, TextEdit,
+        CodeActionItem, CompletionItem, CompletionKind, Diagnostic, DiagnosticSeverity, LspCommand,
+        SymbolItem, TextEdit,
     };
     use std::path::{Path, PathBuf};
     use std::time::{Duration, SystemTime, UNIX_EPOCH};
@@ -11278,6 +11537,7 @@ mod tests {
                         timeout: 5,
                     }),
                     tab_width: Some(2),
+                    ..Default::default()
                 },
             )]),
         };
@@ -11464,6 +11724,85 @@ mod tests {
         );
     }
 
+    fn run_search(editor: &mut Editor, input: &str) {
+        editor.enter_search_forward();
+        editor.search.input = input.to_string();
+        editor.search.cursor = input.chars().count();
+        editor.execute_search();
+    }
+
+    #[test]
+    fn search_supports_vim_alternation_and_anchors() {
+        let mut editor = Editor::default();
+        editor.set_size(80, 8);
+        editor.replace_buffer_content("let x = 1;\n    fn main() {}\nbar\n");
+
+        run_search(&mut editor, r"^\s*fn");
+        assert_eq!((editor.cursor.line, editor.cursor.col), (1, 0));
+
+        run_search(&mut editor, r"foo\|bar");
+        assert_eq!((editor.cursor.line, editor.cursor.col), (2, 0));
+    }
+
+    #[test]
+    fn search_end_offset_lands_past_match_and_repeats_with_n() {
+        let mut editor = Editor::default();
+        editor.set_size(80, 8);
+        editor.replace_buffer_content("x foo y foo z\n");
+
+        run_search(&mut editor, "foo/e+1");
+        assert_eq!((editor.cursor.line, editor.cursor.col), (0, 5));
+
+        editor.search_next();
+        assert_eq!((editor.cursor.line, editor.cursor.col), (0, 11));
+    }
+
+    #[test]
+    fn search_matches_across_line_breaks() {
+        let mut editor = Editor::default();
+        editor.set_size(80, 8);
+        editor.replace_buffer_content("alpha end\nstart beta\nend\nstart\n");
+
+        run_search(&mut editor, r"end\nstart");
+        assert_eq!((editor.cursor.line, editor.cursor.col), (0, 6));
+        assert!(editor.search_matches.contains(&(0, 6, 10)));
+        assert!(editor.search_matches.contains(&(1, 0, 5)));
+
+        editor.search_next();
+        assert_eq!((editor.cursor.line, editor.cursor.col), (2, 0));
+    }
+
+    #[test]
+    fn search_honors_ignore_case_and_smart_case_settings() {
+        let mut editor = Editor::default();
+        editor.set_size(80, 8);
+        editor.replace_buffer_content("x\nFOO\nfoo\n");
+        editor.settings.editor.ignore_case = true;
+        editor.settings.editor.smart_case = true;
+
+        run_search(&mut editor, "foo");
+        assert_eq!(editor.cursor.line, 1);
+
+        editor.cursor.line = 0;
+        run_search(&mut editor, "fOo");
+        assert_eq!(editor.cursor.line, 0);
+        assert_eq!(
+            editor.status_message.as_deref(),
+            Some("Pattern not found: fOo")
+        );
+    }
+
+    #[test]
+    fn star_search_matches_whole_words_only() {
+        let mut editor = Editor::default();
+        editor.set_size(80, 8);
+        editor.replace_buffer_content("word swordfish word\n");
+
+        editor.search_word_forward();
+        assert_eq!((editor.cursor.line, editor.cursor.col), (0, 15));
+        assert_eq!(editor.search.last_pattern.as_deref(), Some(r"\<word\>"));
+    }
+
     #[test]
     fn search_prev_wraps_to_only_match_on_current_line() {
         let mut editor = Editor::default();
@@ -12244,14 +12583,14 @@ mod tests {
 
         let mut editor = Editor::default();
         editor.open_virtual_read_only_buffer("[scratch]", "# Scratch\n", Some("scratch.md"));
-        assert_eq!(editor.syntax.language_name(), Some("markdown"));
+        assert_eq!(editor.syntax().language_name(), Some("markdown"));
 
         editor.open_file(rust).expect("open rust file");
-        assert_eq!(editor.syntax.language_name(), Some("rust"));
+        assert_eq!(editor.syntax().language_name(), Some("rust"));
 
         assert!(editor.switch_to_buffer(0));
         assert_eq!(editor.buffer().display_name(), "[scratch]");
-        assert_eq!(editor.syntax.language_name(), Some("markdown"));
+        assert_eq!(editor.syntax().language_name(), Some("markdown"));
 
         let _ = std::fs::remove_dir_all(&tmp);
     }
@@ -12267,14 +12606,14 @@ mod tests {
         editor.open_config_defaults_preview();
         assert_eq!(editor.buffer().display_name(), "[config-defaults]");
         assert!(editor.buffer().is_read_only());
-        assert_eq!(editor.syntax.language_name(), Some("toml"));
+        assert_eq!(editor.syntax().language_name(), Some("toml"));
 
         editor.open_file(rust).expect("open rust file");
-        assert_eq!(editor.syntax.language_name(), Some("rust"));
+        assert_eq!(editor.syntax().language_name(), Some("rust"));
 
         assert!(editor.switch_to_buffer(0));
         assert_eq!(editor.buffer().display_name(), "[config-defaults]");
-        assert_eq!(editor.syntax.language_name(), Some("toml"));
+        assert_eq!(editor.syntax().language_name(), Some("toml"));
 
         let _ = std::fs::remove_dir_all(&tmp);
     }
@@ -12903,6 +13242,75 @@ mod tests {
         let _ = std::fs::remove_dir_all(&tmp);
     }
 
+    #[test]
+    fn symbols_finder_lists_the_outline_with_buffer_columns() {
+        let tmp = unique_temp_dir("nevi_symbols_finder");
+        std::fs::create_dir_all(&tmp).expect("create temp dir");
+        let path = tmp.join("lib.rs");
+        std::fs::write(&path, "struct S;\nimpl S {\n    fn 😀x() {}\n}\n").expect("write file");
+
+        let mut editor = Editor::default();
+        editor.open_file(path.clone()).expect("open file");
+        let uri = crate::lsp::path_to_uri(&path);
+        let symbol = |name: &str, depth, line, col| SymbolItem {
+            name: name.to_string(),
+            detail: None,
+            kind: CompletionKind::Function,
+            uri: uri.clone(),
+            line,
+            col,
+            depth,
+            container: None,
+        };
+
+        editor.open_finder_symbols(vec![symbol("impl S", 0, 1, 0), symbol("x", 1, 2, 9)]);
+
+        assert_eq!(editor.mode, Mode::Finder);
+        assert_eq!(editor.finder.mode, crate::finder::FinderMode::Symbols);
+        let item = &editor.finder.items[1];
+        assert_eq!(item.display, "  x");
+        assert_eq!(item.buffer_idx, Some(editor.current_buffer_index()));
+        assert_eq!(item.line, Some(3));
+        // UTF-16 column 9 is past the emoji's two code units: char column 8
+        assert_eq!(item.col, Some(8));
+
+        editor.close_finder();
+        editor.open_finder_symbols(Vec::new());
+        assert_eq!(editor.mode, Mode::Normal);
+
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+
+    #[test]
+    fn code_action_command_runs_after_its_edits() {
+        let mut editor = Editor::default();
+        editor.replace_buffer_content("abc\n");
+        let command = LspCommand {
+            title: String::new(),
+            command: "rust-analyzer.runSingle".to_string(),
+            arguments: vec![serde_json::json!({ "label": "test it" })],
+            server: None,
+        };
+
+        editor.show_code_actions_picker(vec![CodeActionItem {
+            title: "Run test".to_string(),
+            kind: None,
+            is_preferred: false,
+            edits: Vec::new(),
+            command: Some(command.clone()),
+        }]);
+        let status = editor.apply_selected_code_action();
+
+        assert_eq!(status.as_deref(), Some("Running 'Run test'..."));
+        assert_eq!(
+            editor.pending_lsp_action,
+            Some(LspAction::ExecuteCommand(LspCommand {
+                title: "Run test".to_string(),
+                ..command
+            }))
+        );
+    }
+
     #[test]
     fn diagnostics_mark_visible_editor_rows_for_current_buffer() {
         let tmp = unique_temp_dir("nevi_diagnostic_render_damage");
@@ -12927,6 +13335,9 @@ mod tests {
                 message: "problem".to_string(),
                 source: None,
                 code: None,
+                tags: Vec::new(),
+                related: Vec::new(),
+                href: None,
             }],
         );
 
@@ -12958,6 +13369,9 @@ mod tests {
                 message: "problem".to_string(),
                 source: None,
                 code: None,
+                tags: Vec::new(),
+                related: Vec::new(),
+                href: None,
             }],
         );
         editor.cursor.line = 0;
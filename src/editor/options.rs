@@ -0,0 +1,480 @@
+//! `:set` and `:setlocal`.
+//!
+//! Global options live in `settings.editor`. Buffer-local options have a
+//! global default there too, which a buffer can override with `:setlocal`;
+//! `:set` changes the default and drops the current buffer's override.
+
+use super::{Change, Editor};
+use crate::commands::{OPTION_SPECS, OptionKind, OptionScope, OptionSpec, SetAction, SetArgument};
+
+/// Line endings a buffer is written with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FileFormat {
+    #[default]
+    Unix,
+    Dos,
+}
+
+impl FileFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            FileFormat::Unix => "unix",
+            FileFormat::Dos => "dos",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "unix" => Some(FileFormat::Unix),
+            "dos" => Some(FileFormat::Dos),
+            _ => None,
+        }
+    }
+
+    /// Guess the format of file contents: `dos` when every line ends in CRLF.
+    pub fn detect(text: &str) -> Self {
+        let mut lines = 0;
+        for (idx, _) in text.match_indices('\n') {
+            if idx == 0 || text.as_bytes()[idx - 1] != b'\r' {
+                return FileFormat::Unix;
+            }
+            lines += 1;
+        }
+        if lines > 0 {
+            FileFormat::Dos
+        } else {
+            FileFormat::Unix
+        }
+    }
+}
+
+/// Buffer-local option values. `None` uses the default from `settings.editor`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BufferOptions {
+    pub expand_tab: Option<bool>,
+    pub shift_width: Option<usize>,
+    pub list: Option<bool>,
+    pub ignore_case: Option<bool>,
+    pub smart_case: Option<bool>,
+    pub text_width: Option<usize>,
+    /// Always local: set from the file when it is read
+    pub file_format: FileFormat,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum OptionValue {
+    Bool(bool),
+    Number(usize),
+    String(String),
+}
+
+impl Editor {
+    /// Apply `:set` (or `:setlocal` when `local`) arguments. Returns the text
+    /// to show for queried options. No arguments, or `all`, opens the option
+    /// report instead.
+    pub fn set_options(
+        &mut self,
+        args: &[SetArgument],
+        local: bool,
+    ) -> Result<Option<String>, String> {
+        let show_all = match args {
+            [] => true,
+            [arg] => arg.name == "all" && arg.action == SetAction::Plain,
+            _ => false,
+        };
+        if show_all {
+            self.open_option_report();
+            return Ok(None);
+        }
+
+        let mut shown = Vec::new();
+        for arg in args {
+            if let Some(text) = self.apply_set_argument(arg, local)? {
+                shown.push(text);
+            }
+        }
+        Ok((!shown.is_empty()).then(|| shown.join("  ")))
+    }
+
+    fn apply_set_argument(
+        &mut self,
+        arg: &SetArgument,
+        local: bool,
+    ) -> Result<Option<String>, String> {
+        let (spec, prefix) = resolve_option_name(&arg.name)
+            .ok_or_else(|| format!("Unknown option: {}", arg.name))?;
+        let invalid = || Err(format!("Invalid argument: {}", describe_argument(arg)));
+
+        let current = self.option_value(spec);
+        let value = match (&arg.action, current.clone()) {
+            (SetAction::Query, _) if prefix.is_none() => {
+                return Ok(Some(format_option(spec, &current)));
+            }
+            (SetAction::Plain, OptionValue::Bool(on)) => OptionValue::Bool(match prefix {
+                None => true,
+                Some(BoolPrefix::No) => false,
+                Some(BoolPrefix::Inv) => !on,
+            }),
+            (SetAction::Plain, _) if prefix.is_none() => {
+                return Ok(Some(format_option(spec, &current)));
+            }
+            _ if prefix.is_some() => return invalid(),
+            (SetAction::Toggle, OptionValue::Bool(on)) => OptionValue::Bool(!on),
+            (SetAction::Assign(value), OptionValue::Number(_)) => {
+                OptionValue::Number(parse_number(arg, value)?)
+            }
+            (SetAction::Add(value), OptionValue::Number(n)) => {
+                OptionValue::Number(n.saturating_add(parse_number(arg, value)?))
+            }
+            (SetAction::Subtract(value), OptionValue::Number(n)) => {
+                OptionValue::Number(n.saturating_sub(parse_number(arg, value)?))
+            }
+            (SetAction::Assign(value), OptionValue::String(_)) => {
+                OptionValue::String(value.clone())
+            }
+            _ => return invalid(),
+        };
+
+        self.write_option(spec, value, local)
+            .map_err(|reason| format!("{}: {}", reason, describe_argument(arg)))?;
+        Ok(None)
+    }
+
+    /// Current value of `spec` for the current buffer.
+    fn option_value(&self, spec: &OptionSpec) -> OptionValue {
+        let editor = &self.settings.editor;
+        match spec.name {
+            "autoindent" => OptionValue::Bool(editor.auto_indent),
+            "codelens" => OptionValue::Bool(editor.code_lens),
+            "cursorline" => OptionValue::Bool(editor.cursor_line),
+            "documenthighlight" => OptionValue::Bool(editor.document_highlight),
+            "expandtab" => OptionValue::Bool(self.expand_tab()),
+            "fileformat" => {
+                OptionValue::String(self.buffer().options.file_format.name().to_string())
+            }
+            "ignorecase" => OptionValue::Bool(self.ignore_case()),
+            "inlayhints" => OptionValue::Bool(editor.inlay_hints),
+            "list" => OptionValue::Bool(self.list_mode()),
+            "number" => OptionValue::Bool(editor.line_numbers),
+            "readonly" => OptionValue::Bool(self.buffer().is_read_only()),
+            "relativenumber" => OptionValue::Bool(editor.relative_numbers),
+            "scrolloff" => OptionValue::Number(editor.scroll_off),
+            "semantictokens" => OptionValue::Bool(editor.semantic_tokens),
+            "shiftwidth" => OptionValue::Number(
+                self.buffer()
+                    .options
+                    .shift_width
+                    .unwrap_or(editor.shift_width),
+            ),
+            "smartcase" => OptionValue::Bool(self.smart_case()),
+            "tabstop" => OptionValue::Number(editor.tab_width),
+            "textwidth" => OptionValue::Number(self.text_width()),
+            "wrap" => OptionValue::Bool(editor.wrap),
+            other => unreachable!("option {other} has no value"),
+        }
+    }
+
+    fn write_option(
+        &mut self,
+        spec: &OptionSpec,
+        value: OptionValue,
+        local: bool,
+    ) -> Result<(), &'static str> {
+        let local = local && spec.scope == OptionScope::Buffer;
+        let buffer_idx = self.current_buffer_idx;
+        let editor = &mut self.settings.editor;
+        let options = &mut self.buffers[buffer_idx].options;
+
+        match (spec.name, value) {
+            ("autoindent", OptionValue::Bool(on)) => editor.auto_indent = on,
+            ("cursorline", OptionValue::Bool(on)) => editor.cursor_line = on,
+            ("number", OptionValue::Bool(on)) => editor.line_numbers = on,
+            ("relativenumber", OptionValue::Bool(on)) => editor.relative_numbers = on,
+            ("wrap", OptionValue::Bool(on)) => editor.wrap = on,
+            ("inlayhints", OptionValue::Bool(on)) => {
+                editor.inlay_hints = on;
+                self.refresh_inlay_hints();
+            }
+            ("codelens", OptionValue::Bool(on)) => {
+                editor.code_lens = on;
+                self.clear_code_lenses();
+            }
+            ("documenthighlight", OptionValue::Bool(on)) => {
+                editor.document_highlight = on;
+                self.clear_document_highlights();
+            }
+            ("semantictokens", OptionValue::Bool(on)) => {
+                editor.semantic_tokens = on;
+                self.reset_semantic_tokens();
+            }
+            ("scrolloff", OptionValue::Number(n)) => editor.scroll_off = n,
+            ("tabstop", OptionValue::Number(0)) => return Err("Argument must be positive"),
+            ("tabstop", OptionValue::Number(n)) => editor.tab_width = n,
+            ("expandtab", OptionValue::Bool(on)) => {
+                set_buffer_local(local, &mut options.expand_tab, &mut editor.expand_tab, on)
+            }
+            ("shiftwidth", OptionValue::Number(n)) => {
+                set_buffer_local(local, &mut options.shift_width, &mut editor.shift_width, n)
+            }
+            ("list", OptionValue::Bool(on)) => {
+                set_buffer_local(local, &mut options.list, &mut editor.list, on)
+            }
+            ("ignorecase", OptionValue::Bool(on)) => {
+                set_buffer_local(local, &mut options.ignore_case, &mut editor.ignore_case, on)
+            }
+            ("smartcase", OptionValue::Bool(on)) => {
+                set_buffer_local(local, &mut options.smart_case, &mut editor.smart_case, on)
+            }
+            ("textwidth", OptionValue::Number(n)) => {
+                set_buffer_local(local, &mut options.text_width, &mut editor.text_width, n)
+            }
+            ("fileformat", OptionValue::String(name)) => {
+                let format = FileFormat::parse(&name).ok_or("Invalid argument")?;
+                let buffer = &mut self.buffers[buffer_idx];
+                if buffer.options.file_format != format {
+                    buffer.options.file_format = format;
+                    buffer.dirty = true;
+                }
+            }
+            ("readonly", OptionValue::Bool(on)) => {
+                let buffer = &mut self.buffers[buffer_idx];
+                if !buffer.is_file_backed() && !on {
+                    return Err("Cannot make this buffer writable");
+                }
+                buffer.set_read_only(on);
+            }
+            _ => return Err("Invalid argument"),
+        }
+
+        self.scroll_to_cursor();
+        Ok(())
+    }
+
+    /// Whether indentation uses spaces in the current buffer.
+    pub fn expand_tab(&self) -> bool {
+        self.buffer()
+            .options
+            .expand_tab
+            .unwrap_or(self.settings.editor.expand_tab)
+    }
+
+    /// Columns per indent level in the current buffer; `shiftwidth=0` uses
+    /// the tab width.
+    pub fn shift_width(&self) -> usize {
+        match self
+            .buffer()
+            .options
+            .shift_width
+            .unwrap_or(self.settings.editor.shift_width)
+        {
+            0 => self.settings.editor.tab_width,
+            width => width,
+        }
+    }
+
+    /// Whether tabs and non-breaking spaces are drawn visibly.
+    pub fn list_mode(&self) -> bool {
+        self.buffer()
+            .options
+            .list
+            .unwrap_or(self.settings.editor.list)
+    }
+
+    pub fn ignore_case(&self) -> bool {
+        self.buffer()
+            .options
+            .ignore_case
+            .unwrap_or(self.settings.editor.ignore_case)
+    }
+
+    pub fn smart_case(&self) -> bool {
+        self.buffer()
+            .options
+            .smart_case
+            .unwrap_or(self.settings.editor.smart_case)
+    }
+
+    /// Column past which typed text is wrapped; 0 turns wrapping off.
+    pub fn text_width(&self) -> usize {
+        self.buffer()
+            .options
+            .text_width
+            .unwrap_or(self.settings.editor.text_width)
+    }
+
+    /// Whitespace for one indent level: spaces with `expandtab`, otherwise as
+    /// many tabs as fit with spaces for the rest.
+    pub fn indent_unit(&self) -> String {
+        let width = self.shift_width();
+        let tab_width = self.settings.editor.tab_width.max(1);
+        if self.expand_tab() {
+            " ".repeat(width)
+        } else {
+            let mut unit = "\t".repeat(width / tab_width);
+            unit.push_str(&" ".repeat(width % tab_width));
+            unit
+        }
+    }
+
+    /// With `textwidth` set, break the cursor line at the last blank before
+    /// the limit once typing goes past it. Call after a non-blank is inserted.
+    pub(super) fn wrap_for_text_width(&mut self) {
+        let text_width = self.text_width();
+        if text_width == 0 || self.cursor.col <= text_width {
+            return;
+        }
+
+        let line = self.cursor.line;
+        let Some(line_text) = self.buffers[self.current_buffer_idx].line(line) else {
+            return;
+        };
+        let chars: Vec<char> = line_text.chars().take(self.cursor.col).collect();
+        let indent_len = chars
+            .iter()
+            .take_while(|ch| **ch == ' ' || **ch == '\t')
+            .count();
+        let Some(blank) = (indent_len..chars.len().min(text_width + 1))
+            .rev()
+            .find(|&col| chars[col] == ' ' || chars[col] == '\t')
+        else {
+            return;
+        };
+
+        let mut start = blank;
+        while start > indent_len && matches!(chars[start - 1], ' ' | '\t') {
+            start -= 1;
+        }
+        let mut end = blank + 1;
+        while end < chars.len() && matches!(chars[end], ' ' | '\t') {
+            end += 1;
+        }
+        if start == indent_len {
+            return;
+        }
+
+        let indent: String = chars[..indent_len].iter().collect();
+        let blanks: String = chars[start..end].iter().collect();
+        let replacement = format!("\n{}", indent);
+        let buffer = &mut self.buffers[self.current_buffer_idx];
+        self.undo_stack
+            .record_change(Change::delete(line, start, blanks.clone()));
+        buffer.delete_range(line, start, line, end);
+        self.undo_stack
+            .record_change(Change::insert(line, start, replacement.clone()));
+        buffer.insert_str(line, start, &replacement);
+
+        self.cursor.line = line + 1;
+        self.cursor.col = self.cursor.col - end + indent_len;
+        self.scroll_to_cursor();
+    }
+
+    /// Every option and its current value, as shown by `:set all`.
+    pub fn option_report(&self) -> String {
+        let mut report = String::from("# Options\n\n");
+        for spec in OPTION_SPECS {
+            let value = format_option(spec, &self.option_value(spec));
+            let short = spec.short.unwrap_or("");
+            let scope = match spec.scope {
+                OptionScope::Global => "global",
+                OptionScope::Buffer if self.has_local_value(spec) => "local",
+                OptionScope::Buffer => "buffer",
+            };
+            report.push_str(&format!(
+                "  {value:<18} {short:<4} {scope:<7} {}\n",
+                spec.description
+            ));
+        }
+        report
+    }
+
+    /// Show `:set all` in a read-only virtual buffer.
+    pub fn open_option_report(&mut self) {
+        let report = self.option_report();
+        self.open_virtual_read_only_buffer("[options]", &report, Some("options.md"));
+    }
+
+    fn has_local_value(&self, spec: &OptionSpec) -> bool {
+        let options = &self.buffer().options;
+        match spec.name {
+            "expandtab" => options.expand_tab.is_some(),
+            "shiftwidth" => options.shift_width.is_some(),
+            "list" => options.list.is_some(),
+            "ignorecase" => options.ignore_case.is_some(),
+            "smartcase" => options.smart_case.is_some(),
+            "textwidth" => options.text_width.is_some(),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BoolPrefix {
+    No,
+    Inv,
+}
+
+/// Find the option `name` refers to, allowing `no`/`inv` before booleans.
+fn resolve_option_name(name: &str) -> Option<(&'static OptionSpec, Option<BoolPrefix>)> {
+    if let Some(spec) = crate::commands::find_option(name) {
+        return Some((spec, None));
+    }
+    let (rest, prefix) = if let Some(rest) = name.strip_prefix("no") {
+        (rest, BoolPrefix::No)
+    } else {
+        (name.strip_prefix("inv")?, BoolPrefix::Inv)
+    };
+    crate::commands::find_option(rest)
+        .filter(|spec| spec.kind == OptionKind::Bool)
+        .map(|spec| (spec, Some(prefix)))
+}
+
+/// `:set` writes the global default and drops the local value; `:setlocal`
+/// writes only the local value.
+fn set_buffer_local<T>(local: bool, local_value: &mut Option<T>, global_value: &mut T, value: T) {
+    if local {
+        *local_value = Some(value);
+    } else {
+        *global_value = value;
+        *local_value = None;
+    }
+}
+
+fn parse_number(arg: &SetArgument, value: &str) -> Result<usize, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Number required after =: {}", describe_argument(arg)))
+}
+
+fn format_option(spec: &OptionSpec, value: &OptionValue) -> String {
+    match value {
+        OptionValue::Bool(true) => spec.name.to_string(),
+        OptionValue::Bool(false) => format!("no{}", spec.name),
+        OptionValue::Number(n) => format!("{}={}", spec.name, n),
+        OptionValue::String(s) => format!("{}={}", spec.name, s),
+    }
+}
+
+/// The argument as it was typed, for error messages.
+fn describe_argument(arg: &SetArgument) -> String {
+    match &arg.action {
+        SetAction::Plain => arg.name.clone(),
+        SetAction::Query => format!("{}?", arg.name),
+        SetAction::Toggle => format!("{}!", arg.name),
+        SetAction::Assign(value) => format!("{}={}", arg.name, value),
+        SetAction::Add(value) => format!("{}+={}", arg.name, value),
+        SetAction::Subtract(value) => format!("{}-={}", arg.name, value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FileFormat;
+
+    #[test]
+    fn file_format_is_dos_only_when_every_line_ends_in_crlf() {
+        assert_eq!(FileFormat::detect("a\r\nb\r\n"), FileFormat::Dos);
+        assert_eq!(FileFormat::detect("a\r\nb\n"), FileFormat::Unix);
+        assert_eq!(FileFormat::detect("no newline"), FileFormat::Unix);
+        assert_eq!(FileFormat::detect("\n"), FileFormat::Unix);
+    }
+}
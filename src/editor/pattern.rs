@@ -0,0 +1,1187 @@
+//! Vim-compatible search patterns.
+//!
+//! Vim's magic / very-magic regex dialect is translated onto the `regex` crate
+//! so `/`, `?`, `*`, `#`, `n`/`N` and `gn` share a single matcher. Constructs the
+//! `regex` crate cannot express (backreferences, lookaround) are rejected with
+//! a readable error instead of silently matching the wrong text.
+
+use super::{Buffer, SearchDirection};
+use regex::{Regex, RegexBuilder};
+
+/// Empty capture group inserted where `\zs` appears in the pattern.
+const MATCH_START_GROUP: &str = "nevi_zs";
+/// Empty capture group inserted where `\ze` appears in the pattern.
+const MATCH_END_GROUP: &str = "nevi_ze";
+
+/// Cursor offset applied after a search match (`/pat/e+1`, `?pat?s-2`, `/pat/+3`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchOffset {
+    #[default]
+    None,
+    /// Lines below (or above) the match, landing on column 0.
+    Line(isize),
+    /// Characters from the start of the match (`s`/`b`).
+    Start(isize),
+    /// Characters from the last character of the match (`e`).
+    End(isize),
+}
+
+impl SearchOffset {
+    /// Parse the text following the closing delimiter of a search command.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(Self::None);
+        }
+
+        let (kind, amount) = match text.as_bytes()[0] {
+            b'e' => ('e', &text[1..]),
+            b's' | b'b' => ('s', &text[1..]),
+            _ => ('l', text),
+        };
+
+        let amount = if amount.is_empty() {
+            0
+        } else if amount == "+" {
+            1
+        } else if amount == "-" {
+            -1
+        } else {
+            amount
+                .strip_prefix('+')
+                .unwrap_or(amount)
+                .parse::<isize>()
+                .map_err(|_| format!("Invalid search offset: {}", text))?
+        };
+
+        Ok(match kind {
+            'e' => Self::End(amount),
+            's' => Self::Start(amount),
+            _ => Self::Line(amount),
+        })
+    }
+}
+
+/// Split typed search input into the pattern and its trailing offset.
+///
+/// The first unescaped `delimiter` (`/` for forward, `?` for backward search)
+/// ends the pattern; an escaped delimiter is unescaped so it matches literally.
+pub fn split_search_offset(input: &str, delimiter: char) -> (String, Option<&str>) {
+    let mut pattern = String::with_capacity(input.len());
+    let mut chars = input.char_indices();
+
+    while let Some((idx, ch)) = chars.next() {
+        if ch == '\\' {
+            match chars.next() {
+                Some((_, next)) if next == delimiter => pattern.push(next),
+                Some((_, next)) => {
+                    pattern.push('\\');
+                    pattern.push(next);
+                }
+                None => pattern.push('\\'),
+            }
+        } else if ch == delimiter {
+            return (pattern, Some(&input[idx + ch.len_utf8()..]));
+        } else {
+            pattern.push(ch);
+        }
+    }
+
+    (pattern, None)
+}
+
+/// Escape text so it matches literally inside a magic Vim pattern.
+pub fn escape_literal(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if matches!(
+            ch,
+            '\\' | '/' | '?' | '.' | '*' | '$' | '^' | '~' | '[' | ']'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+/// Case handling inherited from the `ignorecase` and `smartcase` options.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CaseOptions {
+    pub ignore_case: bool,
+    pub smart_case: bool,
+}
+
+/// A match in buffer coordinates. The end position is exclusive and may sit on
+/// a later line when the pattern matches across line breaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferMatch {
+    pub start_line: usize,
+    pub start_col: usize,
+    pub end_line: usize,
+    pub end_col: usize,
+}
+
+impl BufferMatch {
+    fn starts_after(&self, line: usize, col: usize) -> bool {
+        self.start_line > line || (self.start_line == line && self.start_col > col)
+    }
+
+    fn starts_before(&self, line: usize, col: usize) -> bool {
+        self.start_line < line || (self.start_line == line && self.start_col < col)
+    }
+}
+
+/// A compiled Vim search pattern.
+#[derive(Debug, Clone)]
+pub struct SearchPattern {
+    regex: Regex,
+    /// Whether `\zs` / `\ze` groups move the reported match bounds.
+    match_bounds: bool,
+    /// Whether the pattern can match a line break, requiring a multi-line haystack.
+    multiline: bool,
+    /// Regex capture group index for each user `\(...\)` group, in order.
+    user_groups: Vec<usize>,
+}
+
+impl SearchPattern {
+    /// Compile a Vim pattern using the given case options.
+    pub fn new(pattern: &str, case: CaseOptions) -> Result<Self, String> {
+        if pattern.is_empty() {
+            return Err("Empty search pattern".to_string());
+        }
+
+        let translated = Translator::new(pattern).translate()?;
+        let ignore_case = match translated.case_override {
+            Some(ignore_case) => ignore_case,
+            None => case.ignore_case && !(case.smart_case && has_uppercase(pattern)),
+        };
+
+        let regex = RegexBuilder::new(&translated.regex)
+            .multi_line(true)
+            .crlf(true)
+            .case_insensitive(ignore_case)
+            .build()
+            .map_err(|err| format!("Invalid pattern: {}", regex_error_summary(&err)))?;
+
+        Ok(Self {
+            regex,
+            match_bounds: translated.match_bounds,
+            multiline: translated.multiline,
+            user_groups: translated.user_groups,
+        })
+    }
+
+    /// The translated regex, for callers that need capture groups.
+    pub fn regex(&self) -> &Regex {
+        &self.regex
+    }
+
+    /// Whether the pattern can match across line boundaries.
+    pub fn is_multiline(&self) -> bool {
+        self.multiline
+    }
+
+    /// Regex capture group index for Vim's `\1`..`\9`, skipping the internal
+    /// `\zs` / `\ze` groups.
+    pub fn group_index(&self, group: usize) -> Option<usize> {
+        group
+            .checked_sub(1)
+            .and_then(|idx| self.user_groups.get(idx))
+            .copied()
+    }
+
+    /// Find the first match whose underlying regex match begins at or after
+    /// `start`, returning the reported byte range (after `\zs` / `\ze`).
+    pub fn find_at(&self, haystack: &str, start: usize) -> Option<(usize, usize)> {
+        self.find_raw_at(haystack, start)
+            .map(|(reported, _)| reported)
+    }
+
+    /// Iterate non-overlapping matches in `haystack`, returning reported byte ranges.
+    pub fn find_iter<'p, 'h>(&'p self, haystack: &'h str) -> PatternMatches<'p, 'h> {
+        PatternMatches {
+            pattern: self,
+            haystack,
+            next: Some(0),
+            last_end: None,
+        }
+    }
+
+    fn find_raw_at(
+        &self,
+        haystack: &str,
+        start: usize,
+    ) -> Option<((usize, usize), (usize, usize))> {
+        if start > haystack.len() {
+            return None;
+        }
+
+        if !self.match_bounds {
+            let found = self.regex.find_at(haystack, start)?;
+            let range = (found.start(), found.end());
+            return Some((range, range));
+        }
+
+        let caps = self.regex.captures_at(haystack, start)?;
+        let whole = caps.get(0)?;
+        let match_start = caps
+            .name(MATCH_START_GROUP)
+            .map(|m| m.start())
+            .unwrap_or(whole.start());
+        let match_end = caps
+            .name(MATCH_END_GROUP)
+            .map(|m| m.start())
+            .unwrap_or(whole.end())
+            .max(match_start);
+        Some(((match_start, match_end), (whole.start(), whole.end())))
+    }
+
+    /// Collect matches that start inside `lines`, up to `limit` entries.
+    pub fn buffer_matches(
+        &self,
+        buffer: &Buffer,
+        lines: std::ops::Range<usize>,
+        limit: usize,
+    ) -> Vec<BufferMatch> {
+        let mut matches = Vec::new();
+        let end_line = lines.end.min(buffer.len_lines());
+        if lines.start >= end_line || limit == 0 {
+            return matches;
+        }
+
+        if self.multiline {
+            let haystack = Haystack::from_lines(buffer, lines.start..buffer.len_lines());
+            for (start, end) in self.find_iter(&haystack.text) {
+                let found = haystack.buffer_match(start, end);
+                if found.start_line >= end_line {
+                    break;
+                }
+                matches.push(found);
+                if matches.len() >= limit {
+                    break;
+                }
+            }
+            return matches;
+        }
+
+        for line_idx in lines.start..end_line {
+            let haystack = Haystack::single_line(buffer, line_idx);
+            for (start, end) in self.find_iter(&haystack.text) {
+                matches.push(haystack.buffer_match(start, end));
+                if matches.len() >= limit {
+                    return matches;
+                }
+            }
+        }
+        matches
+    }
+
+    /// Like `buffer_matches`, but also captures the text of `&` and `\1`..`\9`
+    /// for each match, as `:s` needs for its replacement.
+    pub fn buffer_captures(
+        &self,
+        buffer: &Buffer,
+        lines: std::ops::Range<usize>,
+    ) -> Vec<CapturedMatch> {
+        let mut matches = Vec::new();
+        let end_line = lines.end.min(buffer.len_lines());
+        if lines.start >= end_line {
+            return matches;
+        }
+
+        if self.multiline {
+            let haystack = Haystack::from_lines(buffer, lines.start..buffer.len_lines());
+            let mut iter = self.find_iter(&haystack.text);
+            while let Some(((start, end), caps)) = iter.next_captures() {
+                let found = haystack.buffer_match(start, end);
+                if found.start_line >= end_line {
+                    break;
+                }
+                matches.push(self.captured_match(found, &haystack.text[start..end], &caps));
+            }
+            return matches;
+        }
+
+        for line_idx in lines.start..end_line {
+            let haystack = Haystack::single_line(buffer, line_idx);
+            let mut iter = self.find_iter(&haystack.text);
+            while let Some(((start, end), caps)) = iter.next_captures() {
+                let found = haystack.buffer_match(start, end);
+                matches.push(self.captured_match(found, &haystack.text[start..end], &caps));
+            }
+        }
+        matches
+    }
+
+    fn captured_match(
+        &self,
+        found: BufferMatch,
+        text: &str,
+        caps: &regex::Captures<'_>,
+    ) -> CapturedMatch {
+        let mut groups = vec![Some(text.to_string())];
+        groups.extend(
+            self.user_groups
+                .iter()
+                .map(|&idx| caps.get(idx).map(|m| m.as_str().to_string())),
+        );
+        CapturedMatch { found, groups }
+    }
+
+    /// Find the next match from `(line, col)` in `direction`.
+    ///
+    /// Forward searches accept matches starting after the cursor; backward
+    /// searches accept matches starting before it. The returned flag reports
+    /// whether the search wrapped around the end of the buffer.
+    pub fn find_in_buffer(
+        &self,
+        buffer: &Buffer,
+        line: usize,
+        col: usize,
+        direction: SearchDirection,
+        wrap: bool,
+    ) -> Option<(BufferMatch, bool)> {
+        let total_lines = buffer.len_lines();
+        if total_lines == 0 {
+            return None;
+        }
+        let line = line.min(total_lines - 1);
+
+        if self.multiline {
+            let haystack = Haystack::from_lines(buffer, 0..total_lines);
+            let mut first = None;
+            let mut last = None;
+            let mut before = None;
+            for (start, end) in self.find_iter(&haystack.text) {
+                let found = haystack.buffer_match(start, end);
+                if first.is_none() {
+                    first = Some(found);
+                }
+                match direction {
+                    SearchDirection::Forward if found.starts_after(line, col) => {
+                        return Some((found, false));
+                    }
+                    SearchDirection::Backward if found.starts_before(line, col) => {
+                        before = Some(found);
+                    }
+                    _ => {}
+                }
+                last = Some(found);
+            }
+            return match direction {
+                SearchDirection::Forward => first.filter(|_| wrap).map(|found| (found, true)),
+                SearchDirection::Backward => before
+                    .map(|found| (found, false))
+                    .or_else(|| last.filter(|_| wrap).map(|found| (found, true))),
+            };
+        }
+
+        match direction {
+            SearchDirection::Forward => {
+                for line_idx in line..total_lines {
+                    let haystack = Haystack::single_line(buffer, line_idx);
+                    let found = self
+                        .find_iter(&haystack.text)
+                        .map(|(start, end)| haystack.buffer_match(start, end))
+                        .find(|found| found.starts_after(line, col));
+                    if let Some(found) = found {
+                        return Some((found, false));
+                    }
+                }
+
+                if wrap {
+                    for line_idx in 0..=line {
+                        let haystack = Haystack::single_line(buffer, line_idx);
+                        if let Some((start, end)) = self.find_iter(&haystack.text).next() {
+                            return Some((haystack.buffer_match(start, end), true));
+                        }
+                    }
+                }
+            }
+            SearchDirection::Backward => {
+                for line_idx in (0..=line).rev() {
+                    let haystack = Haystack::single_line(buffer, line_idx);
+                    let found = self
+                        .find_iter(&haystack.text)
+                        .map(|(start, end)| haystack.buffer_match(start, end))
+                        .filter(|found| found.starts_before(line, col))
+                        .last();
+                    if let Some(found) = found {
+                        return Some((found, false));
+                    }
+                }
+
+                if wrap {
+                    for line_idx in (line..total_lines).rev() {
+                        let haystack = Haystack::single_line(buffer, line_idx);
+                        if let Some((start, end)) = self.find_iter(&haystack.text).last() {
+                            return Some((haystack.buffer_match(start, end), true));
+                        }
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// The match starting exactly at `(line, col)`, if any.
+    pub fn match_at(&self, buffer: &Buffer, line: usize, col: usize) -> Option<BufferMatch> {
+        if line >= buffer.len_lines() {
+            return None;
+        }
+
+        if self.multiline {
+            let haystack = Haystack::from_lines(buffer, line..buffer.len_lines());
+            return self
+                .find_iter(&haystack.text)
+                .map(|(start, end)| haystack.buffer_match(start, end))
+                .take_while(|found| found.start_line == line)
+                .find(|found| found.start_col == col);
+        }
+
+        let haystack = Haystack::single_line(buffer, line);
+        self.find_iter(&haystack.text)
+            .map(|(start, end)| haystack.buffer_match(start, end))
+            .find(|found| found.start_col == col)
+    }
+}
+
+/// A match plus its capture group text: `groups[0]` is the reported match
+/// (`&`), `groups[n]` is Vim's `\n`, `None` when that group did not take part.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedMatch {
+    pub found: BufferMatch,
+    pub groups: Vec<Option<String>>,
+}
+
+/// Iterator over non-overlapping pattern matches.
+///
+/// Like Vim, the next attempt starts where the previous match ended, or one
+/// character later for empty matches so `^` and `\<` cannot loop forever.
+/// An empty match right where a non-empty match ended is skipped, so
+/// `a*` finds `-b-c-` rather than `-b--c-` in `baaac`.
+pub struct PatternMatches<'p, 'h> {
+    pattern: &'p SearchPattern,
+    haystack: &'h str,
+    next: Option<usize>,
+    last_end: Option<usize>,
+}
+
+impl Iterator for PatternMatches<'_, '_> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_raw().map(|(reported, _)| reported)
+    }
+}
+
+impl<'h> PatternMatches<'_, 'h> {
+    /// Advance like `next`, also returning the capture groups of the match.
+    pub fn next_captures(&mut self) -> Option<((usize, usize), regex::Captures<'h>)> {
+        let (reported, raw) = self.next_raw()?;
+        let caps = self.pattern.regex.captures_at(self.haystack, raw.0)?;
+        Some((reported, caps))
+    }
+
+    fn next_raw(&mut self) -> Option<((usize, usize), (usize, usize))> {
+        loop {
+            let start = self.next?;
+            let Some((reported, raw)) = self.pattern.find_raw_at(self.haystack, start) else {
+                self.next = None;
+                return None;
+            };
+
+            let empty = raw.1 == raw.0;
+            let resume = if empty {
+                raw.1
+                    + self.haystack[raw.1..]
+                        .chars()
+                        .next()
+                        .map(char::len_utf8)
+                        .unwrap_or(1)
+            } else {
+                raw.1
+            };
+            self.next = (resume <= self.haystack.len()).then_some(resume);
+
+            if empty && self.last_end == Some(raw.0) {
+                continue;
+            }
+            self.last_end = (!empty).then_some(raw.1);
+            return Some((reported, raw));
+        }
+    }
+}
+
+/// Contiguous buffer text plus the bookkeeping to map byte offsets back to
+/// `(line, col)` positions.
+struct Haystack {
+    text: String,
+    first_line: usize,
+    /// Byte offset of each line start within `text`.
+    line_starts: Vec<usize>,
+}
+
+impl Haystack {
+    /// A single line without its trailing newline, so no match can span it.
+    fn single_line(buffer: &Buffer, line: usize) -> Self {
+        let mut text: String = buffer
+            .line(line)
+            .map(|slice| slice.chars().collect())
+            .unwrap_or_default();
+        if text.ends_with('\n') {
+            text.pop();
+        }
+        Self {
+            text,
+            first_line: line,
+            line_starts: vec![0],
+        }
+    }
+
+    fn from_lines(buffer: &Buffer, lines: std::ops::Range<usize>) -> Self {
+        let mut text = String::new();
+        let mut line_starts = Vec::with_capacity(lines.len());
+        for line_idx in lines.clone() {
+            line_starts.push(text.len());
+            if let Some(slice) = buffer.line(line_idx) {
+                for chunk in slice.chunks() {
+                    text.push_str(chunk);
+                }
+            }
+        }
+        if line_starts.is_empty() {
+            line_starts.push(0);
+        }
+        Self {
+            text,
+            first_line: lines.start,
+            line_starts,
+        }
+    }
+
+    fn position(&self, byte: usize) -> (usize, usize) {
+        let idx = match self.line_starts.binary_search(&byte) {
+            Ok(idx) => idx,
+            Err(idx) => idx.saturating_sub(1),
+        };
+        let line_start = self.line_starts[idx];
+        let col = self.text[line_start..byte].chars().count();
+        (self.first_line + idx, col)
+    }
+
+    fn buffer_match(&self, start: usize, end: usize) -> BufferMatch {
+        let (start_line, start_col) = self.position(start);
+        let (end_line, end_col) = self.position(end);
+        BufferMatch {
+            start_line,
+            start_col,
+            end_line,
+            end_col,
+        }
+    }
+}
+
+/// Whether `pattern` contains an uppercase letter outside of backslash escapes,
+/// which is how `smartcase` decides to stay case sensitive.
+fn has_uppercase(pattern: &str) -> bool {
+    let mut chars = pattern.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\\' {
+            chars.next();
+        } else if ch.is_uppercase() {
+            return true;
+        }
+    }
+    false
+}
+
+fn regex_error_summary(err: &regex::Error) -> String {
+    err.to_string()
+        .lines()
+        .last()
+        .unwrap_or_default()
+        .trim()
+        .trim_start_matches("error: ")
+        .to_string()
+}
+
+/// How many characters carry special meaning without a backslash (`\v`, `\m`, `\M`, `\V`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MagicLevel {
+    VeryMagic,
+    Magic,
+    NoMagic,
+    VeryNoMagic,
+}
+
+struct Translated {
+    regex: String,
+    case_override: Option<bool>,
+    match_bounds: bool,
+    multiline: bool,
+    user_groups: Vec<usize>,
+}
+
+struct Translator {
+    chars: Vec<char>,
+    pos: usize,
+    magic: MagicLevel,
+    out: String,
+    case_override: Option<bool>,
+    match_bounds: bool,
+    multiline: bool,
+    /// Number of capturing groups emitted so far, including `\zs` / `\ze`.
+    capture_count: usize,
+    user_groups: Vec<usize>,
+    /// True at the start of the pattern, a group, or an alternative.
+    branch_start: bool,
+}
+
+impl Translator {
+    fn new(pattern: &str) -> Self {
+        Self {
+            chars: pattern.chars().collect(),
+            pos: 0,
+            magic: MagicLevel::Magic,
+            out: String::with_capacity(pattern.len() * 2),
+            case_override: None,
+            match_bounds: false,
+            multiline: false,
+            capture_count: 0,
+            user_groups: Vec::new(),
+            branch_start: true,
+        }
+    }
+
+    fn translate(mut self) -> Result<Translated, String> {
+        while let Some((ch, escaped)) = self.next_atom() {
+            if escaped && (ch.is_ascii_alphanumeric() || ch == '_') {
+                self.escaped_item(ch)?;
+            } else if self.is_special(ch, escaped) {
+                self.special(ch)?;
+            } else {
+                self.literal(ch);
+            }
+        }
+
+        Ok(Translated {
+            regex: self.out,
+            case_override: self.case_override,
+            match_bounds: self.match_bounds,
+            multiline: self.multiline,
+            user_groups: self.user_groups,
+        })
+    }
+
+    fn next_atom(&mut self) -> Option<(char, bool)> {
+        let ch = *self.chars.get(self.pos)?;
+        self.pos += 1;
+        if ch != '\\' {
+            return Some((ch, false));
+        }
+        match self.chars.get(self.pos) {
+            Some(&next) => {
+                self.pos += 1;
+                Some((next, true))
+            }
+            None => Some(('\\', false)),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn is_special(&self, ch: char, escaped: bool) -> bool {
+        match ch {
+            '(' | ')' | '|' | '+' | '?' | '=' | '{' | '@' | '<' | '>' | '%' => {
+                (self.magic == MagicLevel::VeryMagic) != escaped
+            }
+            '.' | '*' | '[' | '~' => {
+                matches!(self.magic, MagicLevel::VeryMagic | MagicLevel::Magic) != escaped
+            }
+            '^' => (!escaped || self.magic == MagicLevel::VeryNoMagic) && self.branch_start,
+            '$' => {
+                let unescaped_special = self.magic != MagicLevel::VeryNoMagic;
+                (unescaped_special != escaped) && self.at_branch_end()
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the remaining pattern ends the current branch, which is where `$` anchors.
+    fn at_branch_end(&self) -> bool {
+        let rest = &self.chars[self.pos..];
+        match rest {
+            [] => true,
+            ['|' | ')', ..] => self.magic == MagicLevel::VeryMagic,
+            ['\\', '|' | ')', ..] => self.magic != MagicLevel::VeryMagic,
+            ['\\', 'n', ..] => true,
+            _ => false,
+        }
+    }
+
+    fn emit(&mut self, fragment: &str) {
+        self.out.push_str(fragment);
+        self.branch_start = false;
+    }
+
+    fn literal(&mut self, ch: char) {
+        if ch == '\n' {
+            self.multiline = true;
+        }
+        let mut buf = [0u8; 4];
+        self.emit(&regex::escape(ch.encode_utf8(&mut buf)));
+    }
+
+    fn special(&mut self, ch: char) -> Result<(), String> {
+        match ch {
+            '(' => {
+                self.capture_count += 1;
+                self.user_groups.push(self.capture_count);
+                self.out.push('(');
+                self.branch_start = true;
+            }
+            '|' => {
+                self.out.push('|');
+                self.branch_start = true;
+            }
+            ')' => self.emit(")"),
+            '*' | '+' | '?' | '=' => {
+                if self.branch_start {
+                    self.literal(ch);
+                } else {
+                    let op = if ch == '=' { '?' } else { ch };
+                    self.emit(&op.to_string());
+                }
+            }
+            '{' => self.brace_quantifier()?,
+            '.' => self.emit("."),
+            '~' => self.literal('~'),
+            '[' => self.bracket(false),
+            '^' => self.emit("^"),
+            '$' => self.emit("$"),
+            '<' => self.emit(r"\b{start}"),
+            '>' => self.emit(r"\b{end}"),
+            '@' => return Err("Lookaround (\\@) is not supported".to_string()),
+            '%' => self.percent_item()?,
+            _ => self.literal(ch),
+        }
+        Ok(())
+    }
+
+    fn escaped_item(&mut self, ch: char) -> Result<(), String> {
+        match ch {
+            'c' => self.case_override = Some(true),
+            'C' => self.case_override = Some(false),
+            'v' => self.magic = MagicLevel::VeryMagic,
+            'm' => self.magic = MagicLevel::Magic,
+            'M' => self.magic = MagicLevel::NoMagic,
+            'V' => self.magic = MagicLevel::VeryNoMagic,
+            'n' => {
+                self.multiline = true;
+                self.emit(r"\n");
+            }
+            't' => self.emit(r"\t"),
+            'e' => self.emit(r"\x1b"),
+            'r' => self.emit(r"\r"),
+            'b' => self.emit(r"\x08"),
+            'z' => match self.peek() {
+                Some('s') => {
+                    self.pos += 1;
+                    self.match_bounds = true;
+                    self.capture_count += 1;
+                    self.out.push_str(&format!("(?P<{}>)", MATCH_START_GROUP));
+                }
+                Some('e') => {
+                    self.pos += 1;
+                    self.match_bounds = true;
+                    self.capture_count += 1;
+                    self.out.push_str(&format!("(?P<{}>)", MATCH_END_GROUP));
+                }
+                _ => return Err("Unsupported pattern item: \\z".to_string()),
+            },
+            '_' => self.newline_item()?,
+            '1'..='9' => {
+                return Err("Backreferences are not supported in search patterns".to_string());
+            }
+            _ => match character_class(ch) {
+                Some(class) => self.emit(class),
+                None => self.literal(ch),
+            },
+        }
+        Ok(())
+    }
+
+    /// `\_x`: a character class, `.`, `^`, `$` or `[]` that also matches end-of-line.
+    fn newline_item(&mut self) -> Result<(), String> {
+        let Some(ch) = self.peek() else {
+            return Err("Unsupported pattern item: \\_".to_string());
+        };
+        self.pos += 1;
+        self.multiline = true;
+        match ch {
+            '.' => self.emit(r"(?s:.)"),
+            '^' => self.emit("^"),
+            '$' => self.emit("$"),
+            '[' => self.bracket(true),
+            _ => match character_class(ch) {
+                Some(class) => self.emit(&format!(r"(?:{}|\n)", class)),
+                None => return Err(format!("Unsupported pattern item: \\_{}", ch)),
+            },
+        }
+        Ok(())
+    }
+
+    /// `\{n,m}`, `\{-n,m}` and friends (`{` in very magic mode).
+    fn brace_quantifier(&mut self) -> Result<(), String> {
+        let mut body = String::new();
+        loop {
+            match self.peek() {
+                Some('}') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some('\\') if self.chars.get(self.pos + 1) == Some(&'}') => {
+                    self.pos += 2;
+                    break;
+                }
+                Some(ch) if ch.is_ascii_digit() || ch == ',' || ch == '-' || ch == ' ' => {
+                    self.pos += 1;
+                    body.push(ch);
+                }
+                _ => return Err("Missing } in pattern".to_string()),
+            }
+        }
+
+        if self.branch_start {
+            return Err("Nothing to repeat before \\{".to_string());
+        }
+
+        let body: String = body.chars().filter(|ch| *ch != ' ').collect();
+        let (lazy, range) = match body.strip_prefix('-') {
+            Some(range) => (true, range),
+            None => (false, body.as_str()),
+        };
+        let quantifier = match range.split_once(',') {
+            _ if range.is_empty() => "*".to_string(),
+            None => format!("{{{}}}", range),
+            Some(("", "")) => "*".to_string(),
+            Some(("", max)) => format!("{{0,{}}}", max),
+            Some((min, max)) => format!("{{{},{}}}", min, max),
+        };
+        self.emit(&quantifier);
+        if lazy {
+            self.out.push('?');
+        }
+        Ok(())
+    }
+
+    /// `\%(`, `\%^`, `\%$` and numeric character codes.
+    fn percent_item(&mut self) -> Result<(), String> {
+        let Some(ch) = self.peek() else {
+            return Err("Unsupported pattern item: \\%".to_string());
+        };
+        self.pos += 1;
+        match ch {
+            '(' => {
+                self.out.push_str("(?:");
+                self.branch_start = true;
+            }
+            '^' => {
+                self.multiline = true;
+                self.emit(r"\A");
+            }
+            '$' => {
+                self.multiline = true;
+                self.emit(r"\z");
+            }
+            'd' => self.char_code(10, usize::MAX)?,
+            'x' => self.char_code(16, 2)?,
+            'u' => self.char_code(16, 4)?,
+            'U' => self.char_code(16, 8)?,
+            'o' => self.char_code(8, 4)?,
+            _ => return Err(format!("Unsupported pattern item: \\%{}", ch)),
+        }
+        Ok(())
+    }
+
+    fn char_code(&mut self, radix: u32, max_digits: usize) -> Result<(), String> {
+        let mut digits = String::new();
+        while digits.len() < max_digits {
+            match self.peek() {
+                Some(ch) if ch.is_digit(radix) => {
+                    digits.push(ch);
+                    self.pos += 1;
+                }
+                _ => break,
+            }
+        }
+        let ch = u32::from_str_radix(&digits, radix)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| "Invalid character code in pattern".to_string())?;
+        self.literal(ch);
+        Ok(())
+    }
+
+    /// Translate a `[...]` collection. Without a closing `]` the `[` is literal.
+    fn bracket(&mut self, with_newline: bool) {
+        let Some((class, end)) = self.parse_bracket(with_newline) else {
+            self.literal('[');
+            return;
+        };
+        self.pos = end;
+        self.emit(&class);
+    }
+
+    fn parse_bracket(&mut self, with_newline: bool) -> Option<(String, usize)> {
+        let mut pos = self.pos;
+        let mut class = String::from("[");
+        let negated = self.chars.get(pos) == Some(&'^');
+        if negated {
+            class.push('^');
+            pos += 1;
+        }
+        if self.chars.get(pos) == Some(&']') {
+            class.push_str(r"\]");
+            pos += 1;
+        }
+
+        loop {
+            let ch = *self.chars.get(pos)?;
+            pos += 1;
+            match ch {
+                ']' => break,
+                '[' if self.chars.get(pos) == Some(&':') => {
+                    let rest: String = self.chars[pos..].iter().collect();
+                    let name_end = rest.find(":]")?;
+                    let name = &rest[1..name_end];
+                    if !is_posix_class(name) {
+                        return None;
+                    }
+                    class.push_str(&format!("[:{}:]", name));
+                    pos += name_end + 2;
+                }
+                '\\' => {
+                    let next = *self.chars.get(pos)?;
+                    pos += 1;
+                    match next {
+                        'e' => class.push_str(r"\x1b"),
+                        't' => class.push_str(r"\t"),
+                        'r' => class.push_str(r"\r"),
+                        'b' => class.push_str(r"\x08"),
+                        'n' => {
+                            self.multiline = true;
+                            class.push_str(r"\n");
+                        }
+                        '\\' | ']' | '^' | '-' => {
+                            class.push('\\');
+                            class.push(next);
+                        }
+                        _ => {
+                            class.push_str(r"\\");
+                            push_class_char(&mut class, next);
+                        }
+                    }
+                }
+                '-' => {
+                    let at_edge = class.ends_with('[')
+                        || class.ends_with("[^")
+                        || self.chars.get(pos) == Some(&']');
+                    class.push_str(if at_edge { r"\-" } else { "-" });
+                }
+                _ => push_class_char(&mut class, ch),
+            }
+        }
+
+        // Vim collections skip end-of-line unless written as `\_[...]`.
+        if with_newline != negated {
+            class.push_str(r"\n");
+        }
+        class.push(']');
+        Some((class, pos))
+    }
+}
+
+fn push_class_char(class: &mut String, ch: char) {
+    if matches!(ch, '[' | ']' | '\\' | '^' | '&' | '~' | '-') {
+        class.push('\\');
+    }
+    class.push(ch);
+}
+
+fn is_posix_class(name: &str) -> bool {
+    matches!(
+        name,
+        "alnum"
+            | "alpha"
+            | "blank"
+            | "cntrl"
+            | "digit"
+            | "graph"
+            | "lower"
+            | "print"
+            | "punct"
+            | "space"
+            | "upper"
+            | "xdigit"
+    )
+}
+
+/// Vim's backslash character classes. Negated classes never match end-of-line,
+/// matching Vim where only the `\_x` forms cross lines.
+fn character_class(ch: char) -> Option<&'static str> {
+    Some(match ch {
+        's' => r"[ \t]",
+        'S' => r"[^ \t\n]",
+        'd' => "[0-9]",
+        'D' => r"[^0-9\n]",
+        'w' => "[0-9A-Za-z_]",
+        'W' => r"[^0-9A-Za-z_\n]",
+        'a' => "[A-Za-z]",
+        'A' => r"[^A-Za-z\n]",
+        'l' => "[a-z]",
+        'L' => r"[^a-z\n]",
+        'u' => "[A-Z]",
+        'U' => r"[^A-Z\n]",
+        'x' => "[0-9A-Fa-f]",
+        'X' => r"[^0-9A-Fa-f\n]",
+        'o' => "[0-7]",
+        'O' => r"[^0-7\n]",
+        'h' => "[A-Za-z_]",
+        'H' => r"[^A-Za-z_\n]",
+        'k' | 'i' => r"\w",
+        'K' | 'I' => r"[\w&&[^0-9]]",
+        'f' => r"[\w./\-~+,#$%]",
+        'F' => r"[\w./\-~+,#$%&&[^0-9]]",
+        'p' => r"[^\x00-\x1f\x7f]",
+        'P' => r"[^\x00-\x1f\x7f0-9]",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CaseOptions, SearchOffset, SearchPattern, split_search_offset};
+
+    fn find(pattern: &str, haystack: &str) -> Option<String> {
+        let compiled = SearchPattern::new(pattern, CaseOptions::default()).expect("pattern");
+        compiled
+            .find_at(haystack, 0)
+            .map(|(start, end)| haystack[start..end].to_string())
+    }
+
+    #[test]
+    fn magic_mode_translates_vim_operators() {
+        assert_eq!(find(r"foo\|bar", "xx bar"), Some("bar".to_string()));
+        assert_eq!(find(r"^\s*fn", "    fn main"), Some("    fn".to_string()));
+        assert_eq!(find(r"\<word\>", "sword word"), Some("word".to_string()));
+        assert_eq!(find(r"a\{2,3}", "caaaa"), Some("aaa".to_string()));
+        assert_eq!(find(r"a\{-1,}", "aaa"), Some("a".to_string()));
+        assert_eq!(find(r"\(ab\)\+", "ababx"), Some("abab".to_string()));
+        assert_eq!(find("a.c", "a.c abc"), Some("a.c".to_string()));
+        assert_eq!(find("(x)", "f(x)"), Some("(x)".to_string()));
+    }
+
+    #[test]
+    fn very_magic_and_very_nomagic_switch_operator_meaning() {
+        assert_eq!(find(r"\v(foo|bar)+", "barfoo"), Some("barfoo".to_string()));
+        assert_eq!(find(r"\v<\d{3}>", "12 345"), Some("345".to_string()));
+        assert_eq!(find(r"\Va.c", "abc a.c"), Some("a.c".to_string()));
+        assert_eq!(find(r"\Mfoo*", "foo*"), Some("foo*".to_string()));
+    }
+
+    #[test]
+    fn dollar_and_caret_are_literal_mid_pattern() {
+        assert_eq!(find("a$b", "a$b"), Some("a$b".to_string()));
+        assert_eq!(find("a^b", "a^b"), Some("a^b".to_string()));
+        assert_eq!(find("end$", "end end"), Some("end".to_string()));
+    }
+
+    #[test]
+    fn case_flags_override_options() {
+        let smart = CaseOptions {
+            ignore_case: true,
+            smart_case: true,
+        };
+        let lower = SearchPattern::new("foo", smart).expect("pattern");
+        assert!(lower.find_at("FOO", 0).is_some());
+        let upper = SearchPattern::new("Foo", smart).expect("pattern");
+        assert!(upper.find_at("FOO", 0).is_none());
+        let forced = SearchPattern::new(r"Foo\c", CaseOptions::default()).expect("pattern");
+        assert!(forced.find_at("FOO", 0).is_some());
+        let exact = SearchPattern::new(r"\Cfoo", smart).expect("pattern");
+        assert!(exact.find_at("FOO", 0).is_none());
+    }
+
+    #[test]
+    fn zs_and_ze_move_reported_bounds() {
+        assert_eq!(find(r"foo\zsbar", "foobar"), Some("bar".to_string()));
+        assert_eq!(find(r"foo\zebar", "foobar"), Some("foo".to_string()));
+    }
+
+    #[test]
+    fn user_groups_skip_internal_match_bound_groups() {
+        let pattern = SearchPattern::new(r"\(a\)\zs\(b\)\%(c\)\(d\)", CaseOptions::default())
+            .expect("pattern");
+        let (reported, caps) = pattern.find_iter("abcd").next_captures().expect("captures");
+        assert_eq!(reported, (1, 4));
+        let group = |n| pattern.group_index(n).and_then(|idx| caps.get(idx));
+        assert_eq!(group(1).map(|m| m.as_str()), Some("a"));
+        assert_eq!(group(2).map(|m| m.as_str()), Some("b"));
+        assert_eq!(group(3).map(|m| m.as_str()), Some("d"));
+        assert_eq!(pattern.group_index(4), None);
+    }
+
+    #[test]
+    fn empty_match_after_a_match_is_skipped() {
+        let pattern = SearchPattern::new("a*", CaseOptions::default()).expect("pattern");
+        let matches: Vec<_> = pattern.find_iter("baaac").collect();
+        assert_eq!(matches, vec![(0, 0), (1, 4), (5, 5)]);
+    }
+
+    #[test]
+    fn newline_items_mark_pattern_multiline() {
+        let pattern = SearchPattern::new(r"foo\nbar", CaseOptions::default()).expect("pattern");
+        assert!(pattern.is_multiline());
+        assert_eq!(find(r"a\_s*b", "a\n  b"), Some("a\n  b".to_string()));
+        assert!(
+            !SearchPattern::new("foo", CaseOptions::default())
+                .expect("pattern")
+                .is_multiline()
+        );
+    }
+
+    #[test]
+    fn unsupported_items_report_errors() {
+        assert!(SearchPattern::new(r"\(a\)\1", CaseOptions::default()).is_err());
+        assert!(SearchPattern::new(r"foo\@=", CaseOptions::default()).is_err());
+    }
+
+    #[test]
+    fn search_offsets_parse_vim_forms() {
+        assert_eq!(SearchOffset::parse("e+1"), Ok(SearchOffset::End(1)));
+        assert_eq!(SearchOffset::parse("s-2"), Ok(SearchOffset::Start(-2)));
+        assert_eq!(SearchOffset::parse("b"), Ok(SearchOffset::Start(0)));
+        assert_eq!(SearchOffset::parse("+"), Ok(SearchOffset::Line(1)));
+        assert_eq!(SearchOffset::parse("-3"), Ok(SearchOffset::Line(-3)));
+        assert!(SearchOffset::parse("x").is_err());
+    }
+
+    #[test]
+    fn split_search_offset_honors_escaped_delimiter() {
+        assert_eq!(
+            split_search_offset("foo/e+1", '/'),
+            ("foo".to_string(), Some("e+1"))
+        );
+        assert_eq!(split_search_offset(r"a\/b", '/'), ("a/b".to_string(), None));
+        assert_eq!(
+            split_search_offset(r"a\/b", '?'),
+            (r"a\/b".to_string(), None)
+        );
+    }
+}
@@ -0,0 +1,169 @@
+//! `.` repeat: the keys of the last Normal mode command that changed the
+//! buffer are kept and fed back through the key handler.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use super::{Editor, Mode};
+
+/// Keys of the last change and of the command currently being typed.
+#[derive(Debug, Clone, Default)]
+pub struct DotRepeat {
+    /// Keys replayed by `.`
+    last: Vec<KeyEvent>,
+    /// The Normal mode command being typed, if one has started
+    pending: Option<PendingChange>,
+    /// Set while `.` replays `last`, so the replayed keys are not recorded
+    replaying: bool,
+}
+
+#[derive(Debug, Clone)]
+struct PendingChange {
+    keys: Vec<KeyEvent>,
+    buffer_idx: usize,
+    version: u64,
+    /// Ex commands are not repeatable, even when they edit the buffer.
+    entered_command_line: bool,
+}
+
+impl DotRepeat {
+    /// Keys of the last change.
+    pub fn last_change(&self) -> &[KeyEvent] {
+        &self.last
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.replaying
+    }
+
+    pub fn set_replaying(&mut self, replaying: bool) {
+        self.replaying = replaying;
+    }
+
+    /// Make `count` the count of the last change, in place of the one it was
+    /// typed with: `3.` after `2dd` deletes three lines, as does `3.` after
+    /// `d2d`.
+    pub fn set_count(&mut self, count: usize) {
+        let mut keys = self.last.iter().copied().peekable();
+        let mut counted = Vec::new();
+        // A register (`"a`) comes before the count.
+        if keys
+            .peek()
+            .is_some_and(|key| key.code == KeyCode::Char('"'))
+        {
+            counted.extend(keys.by_ref().take(2));
+        }
+        skip_count(&mut keys);
+        counted.extend(
+            count
+                .to_string()
+                .chars()
+                .map(|digit| KeyEvent::new(KeyCode::Char(digit), KeyModifiers::NONE)),
+        );
+        // An operator's own count goes between it and the motion.
+        let operator_len = match keys.peek().map(|key| key.code) {
+            Some(KeyCode::Char('d' | 'c' | 'y' | '<' | '>' | '=' | '!')) => 1,
+            Some(KeyCode::Char('g')) => 2,
+            _ => 0,
+        };
+        let operator: Vec<KeyEvent> = keys.by_ref().take(operator_len).collect();
+        let is_operator = match operator.as_slice() {
+            [_] => true,
+            [_, second] => matches!(
+                second.code,
+                KeyCode::Char('~' | 'u' | 'U' | '?' | 'q' | 'w')
+            ),
+            _ => false,
+        };
+        counted.extend(operator);
+        if is_operator {
+            skip_count(&mut keys);
+        }
+        counted.extend(keys);
+        self.last = counted;
+    }
+}
+
+/// Drop the count digits at the front of `keys`. A leading `0` is the motion.
+fn skip_count(keys: &mut std::iter::Peekable<impl Iterator<Item = KeyEvent>>) {
+    if !keys
+        .peek()
+        .is_some_and(|key| matches!(key.code, KeyCode::Char('1'..='9')))
+    {
+        return;
+    }
+    while keys
+        .next_if(|key| matches!(key.code, KeyCode::Char(ch) if ch.is_ascii_digit()))
+        .is_some()
+    {}
+}
+
+impl Editor {
+    /// Note `key` as part of the command being typed, starting a new one when
+    /// Normal mode is idle. Call before the key is handled.
+    pub fn record_repeat_key(&mut self, key: KeyEvent) {
+        if self.dot_repeat.replaying {
+            return;
+        }
+        if self.dot_repeat.pending.is_none() {
+            if !self.is_idle_normal_mode() {
+                return;
+            }
+            self.dot_repeat.pending = Some(PendingChange {
+                keys: Vec::new(),
+                buffer_idx: self.current_buffer_idx,
+                version: self.buffers[self.current_buffer_idx].version(),
+                entered_command_line: false,
+            });
+        }
+        if let Some(pending) = self.dot_repeat.pending.as_mut() {
+            pending.keys.push(key);
+        }
+    }
+
+    /// Once the command being typed is complete, keep its keys for `.` if it
+    /// changed the buffer. Call after the key is handled.
+    pub fn finish_repeat_key(&mut self) {
+        if self.dot_repeat.replaying {
+            return;
+        }
+        let in_command_line = self.mode == Mode::Command;
+        let Some(pending) = self.dot_repeat.pending.as_mut() else {
+            return;
+        };
+        pending.entered_command_line |= in_command_line;
+        if !self.is_idle_normal_mode() {
+            return;
+        }
+
+        let Some(pending) = self.dot_repeat.pending.take() else {
+            return;
+        };
+        let changed = pending.buffer_idx == self.current_buffer_idx
+            && self.buffers[self.current_buffer_idx].version() != pending.version;
+        if changed && !pending.entered_command_line && !is_undo_or_repeat(&pending.keys) {
+            self.dot_repeat.last = pending.keys;
+        }
+    }
+
+    fn is_idle_normal_mode(&self) -> bool {
+        self.mode == Mode::Normal
+            && !self.input_state.has_pending_sequence()
+            && self.leader_sequence.is_none()
+            && !self.pending_insert_normal_once
+    }
+}
+
+/// Whether `keys` are a (counted) `u`, `U`, `Ctrl-r` or `.`, which change the
+/// buffer without being changes of their own.
+fn is_undo_or_repeat(keys: &[KeyEvent]) -> bool {
+    let command = keys
+        .iter()
+        .find(|key| !matches!(key.code, KeyCode::Char(ch) if ch.is_ascii_digit()));
+    matches!(
+        command.map(|key| (key.modifiers, key.code)),
+        Some((
+            KeyModifiers::NONE | KeyModifiers::SHIFT,
+            KeyCode::Char('u' | 'U' | '.')
+        )) | Some((KeyModifiers::CONTROL, KeyCode::Char('r')))
+    )
+}
@@ -0,0 +1,241 @@
+//! Visual-mode `an`/`in`: grow the selection to the enclosing LSP selection
+//! range (`textDocument/selectionRange`), or to the enclosing tree-sitter
+//! node when no server offers ranges, and shrink it back step by step.
+
+use std::path::PathBuf;
+
+use super::{Editor, LastVisualSelection, Mode, VisualSelection};
+use crate::lsp::types::TextRange;
+
+/// A charwise selection as `(start_line, start_col, end_line, end_col)`,
+/// end inclusive
+type SelectionBounds = (usize, usize, usize, usize);
+
+/// Selections `an` grew from, so `in` can walk back through them
+#[derive(Debug, Clone, Default)]
+pub(super) struct SelectionExpansion {
+    /// The selection the last `an` or `in` left
+    current: Option<SelectionBounds>,
+    /// Selections before each expansion, innermost first
+    history: Vec<LastVisualSelection>,
+}
+
+/// A selection range request for the cursor position
+#[derive(Debug, Clone)]
+pub(super) struct SelectionRangeRequest {
+    path: PathBuf,
+    uri: String,
+    version: u64,
+    line: u32,
+    character: u32,
+    /// Handed to the main loop and waiting for an answer
+    sent: bool,
+}
+
+fn encloses(outer: SelectionBounds, inner: SelectionBounds) -> bool {
+    outer != inner
+        && (outer.0, outer.1) <= (inner.0, inner.1)
+        && (outer.2, outer.3) >= (inner.2, inner.3)
+}
+
+impl Editor {
+    /// `an`: ask for the ranges enclosing the cursor. Buffers without a
+    /// file have no server and grow by syntax node right away.
+    pub fn expand_selection(&mut self) {
+        let Some(path) = self.buffer().path.clone() else {
+            self.expand_selection_with_syntax();
+            return;
+        };
+        let line_text = self
+            .buffer()
+            .line(self.cursor.line)
+            .map(|line| line.to_string())
+            .unwrap_or_default();
+        let line_text = line_text.trim_end_matches('\n');
+        let character = crate::copilot::utf8_to_utf16_col(
+            line_text,
+            self.cursor.col.min(line_text.chars().count()),
+        );
+        self.selection_range_request = Some(SelectionRangeRequest {
+            uri: crate::lsp::path_to_uri(&path),
+            path,
+            version: self.buffer().version(),
+            line: self.cursor.line as u32,
+            character,
+            sent: false,
+        });
+    }
+
+    /// The selection range request `an` queued, as `(path, line,
+    /// character, buffer_version)`. The main loop sends it, or expands by
+    /// syntax node when no server offers selection ranges.
+    pub fn take_selection_range_request(&mut self) -> Option<(PathBuf, u32, u32, u64)> {
+        let request = self.selection_range_request.as_mut()?;
+        if request.sent {
+            return None;
+        }
+        request.sent = true;
+        Some((
+            request.path.clone(),
+            request.line,
+            request.character,
+            request.version,
+        ))
+    }
+
+    /// Grow the selection to the innermost of `ranges` (innermost first)
+    /// that encloses it, falling back to syntax nodes when none does.
+    /// Answers for another buffer version are dropped. Returns true when
+    /// the selection changed.
+    pub fn set_selection_ranges(
+        &mut self,
+        uri: &str,
+        version: u64,
+        ranges: Vec<TextRange>,
+    ) -> bool {
+        if !self
+            .selection_range_request
+            .as_ref()
+            .is_some_and(|request| request.sent && request.uri == uri && request.version == version)
+        {
+            return false;
+        }
+        self.selection_range_request = None;
+        if !self.mode.is_visual() || self.buffer().version() != version {
+            return false;
+        }
+
+        let candidates: Vec<SelectionBounds> = ranges
+            .into_iter()
+            .filter_map(|range| {
+                let start_col = self.lsp_utf16_col_to_buffer_col(range.start_line, range.start_col);
+                let end_col = self.lsp_utf16_col_to_buffer_col(range.end_line, range.end_col);
+                self.inclusive_bounds(range.start_line, start_col, range.end_line, end_col)
+            })
+            .collect();
+        self.expand_selection_to(candidates) || self.expand_selection_with_syntax()
+    }
+
+    /// Grow the selection to the smallest named syntax node enclosing it.
+    /// Returns true when the selection changed.
+    pub fn expand_selection_with_syntax(&mut self) -> bool {
+        let candidates = self.syntax_selection_candidates();
+        self.expand_selection_to(candidates)
+    }
+
+    /// `in`: return to the selection before the last `an`. Does nothing
+    /// once the selection was changed by anything else.
+    pub fn shrink_selection(&mut self) {
+        if self.selection_expansion.current != Some(self.get_visual_range()) {
+            self.selection_expansion = Default::default();
+            return;
+        }
+        let Some(previous) = self.selection_expansion.history.pop() else {
+            return;
+        };
+        self.mode = previous.mode;
+        self.visual = VisualSelection::new(previous.anchor_line, previous.anchor_col);
+        self.cursor.line = previous.cursor_line;
+        self.cursor.col = previous.cursor_col;
+        self.selection_expansion.current = Some(self.get_visual_range());
+        self.scroll_to_cursor();
+        self.render_damage.mark_full();
+    }
+
+    /// Select the first of `candidates` (innermost first) enclosing the
+    /// selection, remembering the old selection for `in`
+    fn expand_selection_to(&mut self, candidates: Vec<SelectionBounds>) -> bool {
+        let current = self.get_visual_range();
+        let Some((start_line, start_col, end_line, end_col)) = candidates
+            .into_iter()
+            .find(|candidate| encloses(*candidate, current))
+        else {
+            return false;
+        };
+        if self.selection_expansion.current != Some(current) {
+            self.selection_expansion.history.clear();
+        }
+        self.selection_expansion.history.push(LastVisualSelection {
+            mode: self.mode,
+            anchor_line: self.visual.anchor_line,
+            anchor_col: self.visual.anchor_col,
+            cursor_line: self.cursor.line,
+            cursor_col: self.cursor.col,
+        });
+        self.mode = Mode::Visual;
+        self.visual = VisualSelection::new(start_line, start_col);
+        self.cursor.line = end_line;
+        self.cursor.col = end_col;
+        self.selection_expansion.current = Some(self.get_visual_range());
+        self.scroll_to_cursor();
+        self.render_damage.mark_full();
+        true
+    }
+
+    /// A range with an exclusive end as an inclusive selection, or `None`
+    /// for an empty range. A range ending at the start of a line ends on
+    /// the last character of the line before.
+    fn inclusive_bounds(
+        &self,
+        start_line: usize,
+        start_col: usize,
+        end_line: usize,
+        end_col: usize,
+    ) -> Option<SelectionBounds> {
+        let (end_line, end_col) = if end_col > 0 {
+            (end_line, end_col - 1)
+        } else if end_line > start_line {
+            let line = end_line - 1;
+            (line, self.buffer().line_len(line).saturating_sub(1))
+        } else {
+            return None;
+        };
+        ((start_line, start_col) <= (end_line, end_col))
+            .then_some((start_line, start_col, end_line, end_col))
+    }
+
+    /// The named syntax nodes around the selection, innermost first
+    fn syntax_selection_candidates(&self) -> Vec<SelectionBounds> {
+        let syntax = self.syntax();
+        if !syntax.is_synced_with(self.buffer()) {
+            return Vec::new();
+        }
+        let Some((tree, _)) = syntax.get_tree_and_source() else {
+            return Vec::new();
+        };
+        let (start_line, start_col, end_line, end_col) = self.get_visual_range();
+        let (Some(start_byte), Some(end_byte)) = (
+            syntax.position_to_byte(start_line, start_col),
+            syntax.position_to_byte(end_line, end_col + 1),
+        ) else {
+            return Vec::new();
+        };
+
+        let char_col = |line: usize, byte_col: usize| {
+            self.buffer().line(line).map_or(0, |text| {
+                text.to_string()
+                    .char_indices()
+                    .take_while(|(idx, _)| *idx < byte_col)
+                    .count()
+            })
+        };
+        let mut candidates = Vec::new();
+        let mut node = tree
+            .root_node()
+            .named_descendant_for_byte_range(start_byte, end_byte.max(start_byte));
+        while let Some(current) = node {
+            let start = current.start_position();
+            let end = current.end_position();
+            if let Some(bounds) = self.inclusive_bounds(
+                start.row,
+                char_col(start.row, start.column),
+                end.row,
+                char_col(end.row, end.column),
+            ) {
+                candidates.push(bounds);
+            }
+            node = current.parent();
+        }
+        candidates
+    }
+}
@@ -0,0 +1,212 @@
+//! LSP semantic tokens (`textDocument/semanticTokens/full` and
+//! `/full/delta`), decoded against the server's legend and laid over the
+//! buffer's tree-sitter highlighting.
+
+use std::path::PathBuf;
+
+use super::{Buffer, Editor};
+use crate::lsp::types::{SemanticTokensData, SemanticTokensLegend};
+use crate::syntax::{HighlightGroup, SemanticToken};
+
+/// The last answer for a document, the base for delta requests
+#[derive(Debug, Clone, Default)]
+pub(super) struct SemanticTokensState {
+    result_id: Option<String>,
+    /// Encoded tokens, five integers each
+    data: Vec<u32>,
+}
+
+/// A request sent for the current buffer, answered or not
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct SemanticTokensRequest {
+    uri: String,
+    version: u64,
+}
+
+impl Editor {
+    /// `:set semantictokens`
+    pub fn semantic_tokens_enabled(&self) -> bool {
+        self.settings.editor.semantic_tokens
+    }
+
+    /// The current buffer's semantic tokens request, as
+    /// `(path, previous_result_id, buffer_version)`, when its highlighting
+    /// has no tokens for this version, none are on the way and the server
+    /// hasn't already answered without any. Records the request as sent.
+    pub fn semantic_tokens_request(&mut self) -> Option<(PathBuf, Option<String>, u64)> {
+        if !self.semantic_tokens_enabled() {
+            return None;
+        }
+        let path = self.buffer().path.clone()?;
+        let version = self.buffer().version();
+        let syntax = self.syntax();
+        if syntax.language_name().is_none() || syntax.semantic_tokens_version() == Some(version) {
+            return None;
+        }
+        let request = SemanticTokensRequest {
+            uri: crate::lsp::path_to_uri(&path),
+            version,
+        };
+        if self.semantic_tokens_request.as_ref() == Some(&request)
+            || self.semantic_tokens_failed.as_ref() == Some(&request)
+        {
+            return None;
+        }
+        let previous_result_id = self
+            .semantic_tokens
+            .get(&request.uri)
+            .and_then(|state| state.result_id.clone());
+        self.semantic_tokens_request = Some(request);
+        Some((path, previous_result_id, version))
+    }
+
+    /// Store the answer to a semantic tokens request for the buffer at
+    /// `uri` and, when it is for the buffer's current version, hand the
+    /// decoded tokens to its highlighting. A delta is applied to the result
+    /// it was computed against; one against another result is asked for
+    /// again in full. A request answered with an error or no tokens isn't
+    /// repeated for the same version. Returns true when the highlighting
+    /// changed.
+    pub fn set_semantic_tokens(
+        &mut self,
+        uri: &str,
+        version: u64,
+        previous_result_id: Option<&str>,
+        result_id: Option<String>,
+        tokens: Option<SemanticTokensData>,
+        legend: &SemanticTokensLegend,
+    ) -> bool {
+        if self
+            .semantic_tokens_request
+            .as_ref()
+            .is_some_and(|request| request.uri == uri && request.version == version)
+        {
+            self.semantic_tokens_request = None;
+        }
+        if !self.semantic_tokens_enabled() {
+            return false;
+        }
+
+        let state = self.semantic_tokens.entry(uri.to_string()).or_default();
+        let data = match tokens {
+            Some(SemanticTokensData::Full(data)) => data,
+            Some(SemanticTokensData::Delta(mut edits))
+                if previous_result_id.is_some()
+                    && state.result_id.as_deref() == previous_result_id =>
+            {
+                let mut data = std::mem::take(&mut state.data);
+                // Edits are given against the old array; apply the last first.
+                edits.sort_by_key(|edit| std::cmp::Reverse(edit.start));
+                for edit in edits {
+                    let start = edit.start.min(data.len());
+                    let end = (start + edit.delete_count).min(data.len());
+                    data.splice(start..end, edit.data);
+                }
+                data
+            }
+            // The server no longer has the result the delta is against
+            Some(SemanticTokensData::Delta(_)) if previous_result_id.is_some() => {
+                *state = SemanticTokensState::default();
+                return false;
+            }
+            _ => {
+                *state = SemanticTokensState::default();
+                self.semantic_tokens_failed = Some(SemanticTokensRequest {
+                    uri: uri.to_string(),
+                    version,
+                });
+                return false;
+            }
+        };
+        state.result_id = result_id;
+        state.data = data;
+
+        let Some(idx) = self.buffers.iter().position(|buffer| {
+            buffer.path.as_ref().map(crate::lsp::path_to_uri).as_deref() == Some(uri)
+        }) else {
+            return false;
+        };
+        if self.buffers[idx].version() != version {
+            return false;
+        }
+        let lines =
+            decode_semantic_tokens(&self.buffers[idx], &self.semantic_tokens[uri].data, legend);
+        self.syntaxes[idx].set_semantic_tokens(version, lines);
+        self.render_damage.mark_full();
+        true
+    }
+
+    /// Ask for every buffer's tokens again (`workspace/semanticTokens/refresh`).
+    /// Shown tokens stay until the new ones arrive.
+    pub fn refresh_semantic_tokens(&mut self) {
+        self.semantic_tokens_request = None;
+        self.semantic_tokens_failed = None;
+        for syntax in &mut self.syntaxes {
+            syntax.mark_semantic_tokens_stale();
+        }
+    }
+
+    /// Drop every token and request state (`:set semantictokens`)
+    pub fn reset_semantic_tokens(&mut self) {
+        self.semantic_tokens.clear();
+        self.semantic_tokens_request = None;
+        self.semantic_tokens_failed = None;
+        for syntax in &mut self.syntaxes {
+            syntax.clear_semantic_tokens();
+        }
+        self.render_damage.mark_full();
+    }
+}
+
+/// Decode relative token positions into buffer (char) columns, one list
+/// per buffer line. Tokens of types without a highlight group are left out
+/// so tree-sitter shows through.
+fn decode_semantic_tokens(
+    buffer: &Buffer,
+    data: &[u32],
+    legend: &SemanticTokensLegend,
+) -> Vec<Vec<SemanticToken>> {
+    let mut lines: Vec<Vec<SemanticToken>> = vec![Vec::new(); buffer.len_lines()];
+    let (mut line, mut start) = (0usize, 0usize);
+    for token in data.chunks_exact(5) {
+        let [delta_line, delta_start, length, token_type, modifier_bits] = token else {
+            continue;
+        };
+        if *delta_line > 0 {
+            line += *delta_line as usize;
+            start = *delta_start as usize;
+        } else {
+            start += *delta_start as usize;
+        }
+        if line >= lines.len() {
+            break;
+        }
+        let modifiers: Vec<&str> = legend
+            .token_modifiers
+            .iter()
+            .enumerate()
+            .filter(|(bit, _)| *bit < 32 && modifier_bits & (1 << bit) != 0)
+            .map(|(_, name)| name.as_str())
+            .collect();
+        let Some(group) = legend
+            .token_types
+            .get(*token_type as usize)
+            .and_then(|name| HighlightGroup::from_semantic_token(name, &modifiers))
+        else {
+            continue;
+        };
+        let start_col = Editor::lsp_utf16_col_to_buffer_col_in_buffer(buffer, line, start);
+        let end_col =
+            Editor::lsp_utf16_col_to_buffer_col_in_buffer(buffer, line, start + *length as usize);
+        lines[line].push(SemanticToken {
+            start_col,
+            end_col,
+            group,
+            modifiers: modifiers
+                .into_iter()
+                .filter_map(HighlightGroup::from_semantic_token_modifier)
+                .collect(),
+        });
+    }
+    lines
+}
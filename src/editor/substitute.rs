@@ -0,0 +1,472 @@
+//! `:[range]s/pattern/replacement/[flags] [count]`.
+//!
+//! Matches are collected up front from the original text, then applied in
+//! buffer order. Each applied replacement can change the length or line count
+//! of the text after it, so later match positions are remapped through
+//! [`EditShift`] instead of re-searching the buffer.
+
+use super::undo::Change;
+use super::{Buffer, BufferMatch, CapturedMatch, CaseOptions, Editor, SearchOffset, SearchPattern};
+use crate::commands::{ExRange, SubstituteFlags};
+
+/// Remaps positions from the original text to the buffer after earlier
+/// (non-overlapping, in-order) replacements have been applied.
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct EditShift {
+    /// `(original end line, original end col, new end line, new end col)` of
+    /// the most recently applied replacement.
+    last_end: Option<(usize, usize, usize, usize)>,
+}
+
+impl EditShift {
+    pub(super) fn map(&self, line: usize, col: usize) -> (usize, usize) {
+        match self.last_end {
+            Some((old_line, old_col, new_line, new_col)) if line == old_line => {
+                (new_line, new_col + col.saturating_sub(old_col))
+            }
+            Some((old_line, _, new_line, _)) => ((line + new_line).saturating_sub(old_line), col),
+            None => (line, col),
+        }
+    }
+
+    fn record(&mut self, old_end: (usize, usize), new_end: (usize, usize)) {
+        self.last_end = Some((old_end.0, old_end.1, new_end.0, new_end.1));
+    }
+}
+
+/// A `:s` resolved against the buffer: the pattern source (after an empty
+/// pattern fell back to the last search), the `~`-expanded replacement and
+/// every match to replace.
+pub(super) struct PreparedSubstitute {
+    pub(super) source: String,
+    pub(super) replacement: String,
+    pub(super) matches: Vec<CapturedMatch>,
+}
+
+/// An interactive `:s///c` in progress. Keys are routed here until every
+/// match has been answered or the user quits.
+pub(super) struct SubstituteConfirm {
+    matches: Vec<CapturedMatch>,
+    next: usize,
+    replacement: String,
+    shift: EditShift,
+    substitutions: usize,
+    lines: usize,
+    last_original_line: Option<usize>,
+    /// Line the cursor lands on when the session finishes.
+    last_line: Option<usize>,
+}
+
+impl Editor {
+    /// Run `:s`. Returns the message to show, if any.
+    pub fn substitute(
+        &mut self,
+        range: Option<&ExRange>,
+        pattern: &str,
+        replacement: &str,
+        flags: SubstituteFlags,
+        count: Option<usize>,
+    ) -> Result<Option<String>, String> {
+        let PreparedSubstitute {
+            source,
+            replacement,
+            matches,
+        } = self.prepare_substitute(range, pattern, replacement, flags, count)?;
+
+        // Like Vim, the substitute pattern becomes the last search pattern.
+        self.search.last_pattern = Some(source.clone());
+        self.search.last_offset = SearchOffset::None;
+        self.search.last_skips_smartcase = false;
+        self.last_substitute_replacement = Some(replacement.clone());
+
+        if matches.is_empty() {
+            if flags.suppress_errors {
+                return Ok(None);
+            }
+            return Err(format!("Pattern not found: {}", source));
+        }
+
+        if flags.count_only {
+            let lines = count_lines(&matches);
+            return Ok(Some(format!(
+                "{} on {}",
+                plural(matches.len(), "match", "matches"),
+                plural(lines, "line", "lines")
+            )));
+        }
+
+        if self.reject_read_only_edit() {
+            return Ok(None);
+        }
+
+        if flags.confirm {
+            self.begin_change();
+            self.substitute_confirm = Some(SubstituteConfirm {
+                matches,
+                next: 0,
+                replacement,
+                shift: EditShift::default(),
+                substitutions: 0,
+                lines: 0,
+                last_original_line: None,
+                last_line: None,
+            });
+            self.show_substitute_confirm_prompt();
+            return Ok(None);
+        }
+
+        self.begin_change();
+        let mut shift = EditShift::default();
+        let mut end_line = self.cursor.line;
+        for captured in &matches {
+            let text = expand_replacement(&replacement, &captured.groups);
+            end_line = self.apply_substitute_edit(&mut shift, &captured.found, &text);
+        }
+        let substitutions = matches.len();
+        let lines = count_lines(&matches);
+        self.finish_substitute(Some(end_line));
+
+        Ok(Some(format!(
+            "{} on {}",
+            plural(substitutions, "substitution", "substitutions"),
+            plural(lines, "line", "lines")
+        )))
+    }
+
+    /// Resolve the range, compile the pattern and collect the matches of a
+    /// `:s` without changing any editor state.
+    pub(super) fn prepare_substitute(
+        &self,
+        range: Option<&ExRange>,
+        pattern: &str,
+        replacement: &str,
+        flags: SubstituteFlags,
+        count: Option<usize>,
+    ) -> Result<PreparedSubstitute, String> {
+        let (mut first_line, mut last_line) = self.resolve_ex_range(range)?;
+        if let Some(count) = count {
+            let max_line = self.buffers[self.current_buffer_idx]
+                .addressable_line_count()
+                .saturating_sub(1);
+            first_line = last_line;
+            last_line = (last_line + count - 1).min(max_line);
+        }
+
+        let source = if pattern.is_empty() {
+            self.search
+                .last_pattern
+                .clone()
+                .ok_or_else(|| "No previous regular expression".to_string())?
+        } else {
+            pattern.to_string()
+        };
+        let case = match flags.ignore_case {
+            Some(ignore_case) => CaseOptions {
+                ignore_case,
+                smart_case: false,
+            },
+            None => self.search_case_options(false),
+        };
+        let compiled = SearchPattern::new(&source, case)?;
+
+        let replacement =
+            expand_previous_replacement(replacement, self.last_substitute_replacement.as_deref());
+
+        let mut matches = compiled.buffer_captures(
+            &self.buffers[self.current_buffer_idx],
+            first_line..last_line + 1,
+        );
+        if !flags.global {
+            let mut previous_line = None;
+            matches.retain(|captured| {
+                let keep = previous_line != Some(captured.found.start_line);
+                previous_line = Some(captured.found.start_line);
+                keep
+            });
+        }
+
+        Ok(PreparedSubstitute {
+            source,
+            replacement,
+            matches,
+        })
+    }
+
+    /// Whether an interactive `:s///c` is waiting for an answer.
+    pub fn is_confirming_substitute(&self) -> bool {
+        self.substitute_confirm.is_some()
+    }
+
+    /// Answer the `:s///c` prompt: `y` replaces, `n` skips, `a` replaces all
+    /// remaining matches, `l` replaces this one and stops, `q` / Esc stop.
+    pub fn substitute_confirm_key(&mut self, key: char) {
+        let Some(mut session) = self.substitute_confirm.take() else {
+            return;
+        };
+
+        match key {
+            'y' | 'l' => {
+                self.apply_confirmed_match(&mut session);
+                if key == 'l' {
+                    session.next = session.matches.len();
+                }
+            }
+            'n' => session.next += 1,
+            'a' => {
+                while session.next < session.matches.len() {
+                    self.apply_confirmed_match(&mut session);
+                }
+            }
+            'q' => session.next = session.matches.len(),
+            _ => {
+                self.substitute_confirm = Some(session);
+                return;
+            }
+        }
+
+        if session.next < session.matches.len() {
+            self.substitute_confirm = Some(session);
+            self.show_substitute_confirm_prompt();
+            return;
+        }
+
+        self.finish_substitute(session.last_line);
+        if session.substitutions > 0 {
+            self.set_status(format!(
+                "{} on {}",
+                plural(session.substitutions, "substitution", "substitutions"),
+                plural(session.lines, "line", "lines")
+            ));
+        } else {
+            self.clear_status();
+        }
+    }
+
+    fn apply_confirmed_match(&mut self, session: &mut SubstituteConfirm) {
+        let captured = &session.matches[session.next];
+        let text = expand_replacement(&session.replacement, &captured.groups);
+        let found = captured.found;
+        session.last_line = Some(self.apply_substitute_edit(&mut session.shift, &found, &text));
+        session.substitutions += 1;
+        if session.last_original_line != Some(found.start_line) {
+            session.lines += 1;
+            session.last_original_line = Some(found.start_line);
+        }
+        session.next += 1;
+    }
+
+    /// Move to and highlight the current match, then prompt for an answer.
+    fn show_substitute_confirm_prompt(&mut self) {
+        let Some(session) = self.substitute_confirm.as_ref() else {
+            return;
+        };
+        let found = session.matches[session.next].found;
+        let (start_line, start_col) = session.shift.map(found.start_line, found.start_col);
+        let (end_line, end_col) = session.shift.map(found.end_line, found.end_col);
+        let prompt = format!(
+            "replace with {} (y/n/a/q/l)?",
+            session.replacement.replace('\n', "^M")
+        );
+
+        self.cursor.line = start_line;
+        self.cursor.col = start_col;
+        self.scroll_to_cursor();
+
+        self.search_matches.clear();
+        let end_line_limit = self.buffers[self.current_buffer_idx].len_lines();
+        self.push_search_match_segments(
+            BufferMatch {
+                start_line,
+                start_col,
+                end_line,
+                end_col: end_col.max(start_col + 1),
+            },
+            end_line_limit,
+        );
+        self.render_damage.mark_full();
+        self.set_status(prompt);
+    }
+
+    /// Replace one match, returning the line its replacement ends on.
+    fn apply_substitute_edit(
+        &mut self,
+        shift: &mut EditShift,
+        found: &BufferMatch,
+        replacement: &str,
+    ) -> usize {
+        let buffer = &mut self.buffers[self.current_buffer_idx];
+        let (old_text, start, end) = replace_match(buffer, shift, found, replacement);
+        self.undo_stack.record_change(Change::new(
+            start.0,
+            start.1,
+            old_text,
+            replacement.to_string(),
+        ));
+        end.0
+    }
+
+    /// Close the undo group and leave the cursor on the first non-blank of
+    /// the last substituted line, as Vim does.
+    fn finish_substitute(&mut self, last_line: Option<usize>) {
+        if let Some(line) = last_line {
+            self.cursor.line = line;
+            self.cursor.col = self.find_first_non_blank(line);
+            self.buffers[self.current_buffer_idx].mark_modified();
+        }
+        self.clamp_cursor();
+        self.undo_stack
+            .end_undo_group(self.cursor.line, self.cursor.col);
+        self.search_matches.clear();
+        self.scroll_to_cursor();
+        self.render_damage.mark_full();
+    }
+}
+
+/// Replace one match in `buffer`, remapping it through `shift` first. Returns
+/// the replaced text and the start and end of the inserted replacement.
+pub(super) fn replace_match(
+    buffer: &mut Buffer,
+    shift: &mut EditShift,
+    found: &BufferMatch,
+    replacement: &str,
+) -> (String, (usize, usize), (usize, usize)) {
+    let (start_line, start_col) = shift.map(found.start_line, found.start_col);
+    let (end_line, end_col) = shift.map(found.end_line, found.end_col);
+    let old_text = buffer.get_text_range(start_line, start_col, end_line, end_col);
+    buffer.apply_change(start_line, start_col, &old_text, replacement);
+
+    let new_end = match replacement.rfind('\n') {
+        Some(idx) => (
+            start_line + replacement.matches('\n').count(),
+            replacement[idx + 1..].chars().count(),
+        ),
+        None => (start_line, start_col + replacement.chars().count()),
+    };
+    shift.record((found.end_line, found.end_col), new_end);
+    (old_text, (start_line, start_col), new_end)
+}
+
+fn count_lines(matches: &[CapturedMatch]) -> usize {
+    let mut lines = 0;
+    let mut previous = None;
+    for captured in matches {
+        if previous != Some(captured.found.start_line) {
+            lines += 1;
+            previous = Some(captured.found.start_line);
+        }
+    }
+    lines
+}
+
+fn plural(count: usize, singular: &str, plural: &str) -> String {
+    format!("{} {}", count, if count == 1 { singular } else { plural })
+}
+
+/// Replace unescaped `~` with the previous replacement string.
+fn expand_previous_replacement(replacement: &str, previous: Option<&str>) -> String {
+    let mut expanded = String::with_capacity(replacement.len());
+    let mut chars = replacement.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => {
+                expanded.push('\\');
+                if let Some(next) = chars.next() {
+                    expanded.push(next);
+                }
+            }
+            '~' => expanded.push_str(previous.unwrap_or_default()),
+            _ => expanded.push(ch),
+        }
+    }
+    expanded
+}
+
+/// Case conversion requested by `\u`, `\l`, `\U` and `\L`.
+#[derive(Debug, Clone, Copy, Default)]
+struct ReplacementCase {
+    once: Option<bool>,
+    all: Option<bool>,
+}
+
+impl ReplacementCase {
+    fn push(&mut self, out: &mut String, text: &str) {
+        for ch in text.chars() {
+            let upper = self.once.take().or(self.all);
+            match upper {
+                Some(true) => out.extend(ch.to_uppercase()),
+                Some(false) => out.extend(ch.to_lowercase()),
+                None => out.push(ch),
+            }
+        }
+    }
+}
+
+/// Expand a Vim replacement string for one match. `groups[0]` is the whole
+/// match (`&`, `\0`) and `groups[n]` is `\n`.
+pub(super) fn expand_replacement(template: &str, groups: &[Option<String>]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut case = ReplacementCase::default();
+    let group = |idx: usize| groups.get(idx).cloned().flatten().unwrap_or_default();
+
+    let mut chars = template.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '&' => case.push(&mut out, &group(0)),
+            '\r' => out.push('\n'),
+            '\\' => match chars.next() {
+                Some(digit @ '0'..='9') => {
+                    let idx = digit.to_digit(10).unwrap_or_default() as usize;
+                    case.push(&mut out, &group(idx));
+                }
+                Some('u') => case.once = Some(true),
+                Some('l') => case.once = Some(false),
+                Some('U') => case.all = Some(true),
+                Some('L') => case.all = Some(false),
+                Some('E' | 'e') => case = ReplacementCase::default(),
+                Some('r') => out.push('\n'),
+                // Vim inserts a <NUL> for `\n`; use `\r` to split the line.
+                Some('n') => out.push('\0'),
+                Some('t') => case.push(&mut out, "\t"),
+                Some(other) => {
+                    let mut buf = [0u8; 4];
+                    case.push(&mut out, other.encode_utf8(&mut buf));
+                }
+                None => out.push('\\'),
+            },
+            _ => {
+                let mut buf = [0u8; 4];
+                case.push(&mut out, ch.encode_utf8(&mut buf));
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expand_previous_replacement, expand_replacement};
+
+    fn groups(values: &[&str]) -> Vec<Option<String>> {
+        values.iter().map(|value| Some(value.to_string())).collect()
+    }
+
+    #[test]
+    fn replacement_expands_groups_and_case_items() {
+        let caps = groups(&["foo bar", "foo", "bar"]);
+        assert_eq!(expand_replacement(r"\2 \1", &caps), "bar foo");
+        assert_eq!(expand_replacement(r"[&]", &caps), "[foo bar]");
+        assert_eq!(expand_replacement(r"\&\\", &caps), "&\\");
+        assert_eq!(expand_replacement(r"\u\1", &caps), "Foo");
+        assert_eq!(expand_replacement(r"\U\1\E-\2", &caps), "FOO-bar");
+        assert_eq!(expand_replacement(r"\L\uFOO", &caps), "Foo");
+        assert_eq!(expand_replacement(r"\1\r\2", &caps), "foo\nbar");
+        assert_eq!(expand_replacement(r"\9", &caps), "");
+    }
+
+    #[test]
+    fn tilde_reuses_previous_replacement() {
+        assert_eq!(expand_previous_replacement("a~b", Some("X")), "aXb");
+        assert_eq!(expand_previous_replacement(r"a\~b", Some("X")), r"a\~b");
+        assert_eq!(expand_previous_replacement("~", None), "");
+    }
+}
@@ -0,0 +1,241 @@
+//! Live `:s` preview while the command line is being typed (`inc_command`).
+//!
+//! The preview applies the substitution to a copy of the buffer, which the
+//! renderer draws in place of the real one. The real buffer, its version and
+//! the undo stack are never touched, so cancelling simply drops the copy.
+
+use super::substitute::{EditShift, expand_replacement, replace_match};
+use super::{Buffer, BufferMatch, Editor, Mode};
+use crate::commands::{Command, parse_incremental_substitute};
+use crate::config::IncCommand;
+use crate::syntax::HighlightSpan;
+
+/// Upper bound on affected lines kept for the `split` listing.
+const MAX_PREVIEW_LISTING_LINES: usize = 100;
+
+pub(super) struct SubstitutePreview {
+    /// Command-line text the preview was built from.
+    input: String,
+    buffer_idx: usize,
+    /// Buffer text with every replacement applied, or `None` while only the
+    /// pattern has been typed and matches are merely highlighted.
+    buffer: Option<Buffer>,
+    /// Preview line ranges touched by a replacement, each with the line shift
+    /// (preview minus original) that applies to the lines after it.
+    changed: Vec<(usize, usize, isize)>,
+    /// Affected lines in preview coordinates, in order.
+    lines: Vec<usize>,
+    /// `search_matches` to restore when the preview closes.
+    saved_search_matches: Vec<(usize, usize, usize)>,
+}
+
+impl SubstitutePreview {
+    /// Original line for a preview line, or `None` when a replacement changed it.
+    fn source_line(&self, line: usize) -> Option<usize> {
+        let idx = self.changed.partition_point(|(first, _, _)| *first <= line);
+        let Some(&(_, last, shift)) = idx.checked_sub(1).and_then(|idx| self.changed.get(idx))
+        else {
+            return Some(line);
+        };
+        (line > last).then(|| (line as isize - shift).max(0) as usize)
+    }
+
+    fn mark_changed(&mut self, first: usize, last: usize, shift: isize) {
+        match self.changed.last_mut() {
+            Some(range) if range.1 >= first => {
+                range.1 = range.1.max(last);
+                range.2 = shift;
+            }
+            _ => self.changed.push((first, last, shift)),
+        }
+    }
+
+    fn push_line(&mut self, line: usize) {
+        if self.lines.len() < MAX_PREVIEW_LISTING_LINES && self.lines.last() != Some(&line) {
+            self.lines.push(line);
+        }
+    }
+}
+
+impl Editor {
+    /// Rebuild the live `:s` preview after the command line changed.
+    pub fn refresh_substitute_preview(&mut self) {
+        if self.mode != Mode::Command || self.settings.editor.inc_command == IncCommand::Off {
+            self.clear_substitute_preview();
+            return;
+        }
+        if self
+            .substitute_preview
+            .as_ref()
+            .is_some_and(|preview| preview.input == self.command_line.input)
+        {
+            return;
+        }
+
+        let input = self.command_line.input.clone();
+        self.clear_substitute_preview();
+        if let Some(preview) = self.build_substitute_preview(input) {
+            self.substitute_preview = Some(preview);
+            self.render_damage.mark_full();
+        }
+    }
+
+    /// Drop the preview and restore the search highlights it replaced.
+    pub fn clear_substitute_preview(&mut self) {
+        if let Some(preview) = self.substitute_preview.take() {
+            self.search_matches = preview.saved_search_matches;
+            self.render_damage.mark_full();
+        }
+    }
+
+    /// Text to render for `buffer_idx` while a preview replaces it.
+    pub fn substitute_preview_buffer(&self, buffer_idx: usize) -> Option<&Buffer> {
+        self.substitute_preview
+            .as_ref()
+            .filter(|preview| preview.buffer_idx == buffer_idx)
+            .and_then(|preview| preview.buffer.as_ref())
+    }
+
+    /// Syntax highlights for a rendered line of the buffer at `buffer_idx`.
+    /// While a preview is showing, unchanged lines map back to their original
+    /// line and replaced lines render without syntax colors.
+    pub fn rendered_line_highlights(&self, buffer_idx: usize, line: usize) -> Vec<HighlightSpan> {
+        let Some(syntax) = self.syntax_at(buffer_idx) else {
+            return Vec::new();
+        };
+        let preview = self
+            .substitute_preview
+            .as_ref()
+            .filter(|preview| preview.buffer_idx == buffer_idx && preview.buffer.is_some());
+        match preview.map(|preview| preview.source_line(line)) {
+            Some(None) => Vec::new(),
+            Some(Some(source)) => syntax.get_line_highlights(source),
+            None => syntax.get_line_highlights(line),
+        }
+    }
+
+    /// Affected lines outside the window as `(line number, text)`, for the
+    /// `inc_command = "split"` listing.
+    pub fn substitute_preview_listing(&self) -> Vec<(usize, String)> {
+        if self.settings.editor.inc_command != IncCommand::Split {
+            return Vec::new();
+        }
+        let Some(preview) = self.substitute_preview.as_ref() else {
+            return Vec::new();
+        };
+
+        let buffer = preview
+            .buffer
+            .as_ref()
+            .unwrap_or(&self.buffers[preview.buffer_idx]);
+        let (viewport_offset, visible_rows) = self.visible_text_region();
+        let visible = viewport_offset..viewport_offset.saturating_add(visible_rows);
+        preview
+            .lines
+            .iter()
+            .filter(|&&line| !visible.contains(&line))
+            .map(|&line| {
+                let text = buffer
+                    .line(line)
+                    .map(|slice| slice.chars().filter(|ch| *ch != '\n').collect())
+                    .unwrap_or_default();
+                (line + 1, text)
+            })
+            .collect()
+    }
+
+    fn build_substitute_preview(&mut self, input: String) -> Option<SubstitutePreview> {
+        let (command, has_replacement) = parse_incremental_substitute(&input)?;
+        let Command::Substitute {
+            range,
+            pattern,
+            replacement,
+            flags,
+            count,
+        } = command
+        else {
+            return None;
+        };
+        if pattern.is_empty() {
+            return None;
+        }
+
+        // Half-typed patterns and ranges are expected to fail while typing.
+        let prepared = self
+            .prepare_substitute(range.as_ref(), &pattern, &replacement, flags, count)
+            .ok()?;
+
+        let mut preview = SubstitutePreview {
+            input,
+            buffer_idx: self.current_buffer_idx,
+            buffer: None,
+            changed: Vec::new(),
+            lines: Vec::new(),
+            saved_search_matches: std::mem::take(&mut self.search_matches),
+        };
+
+        let (viewport_offset, visible_rows) = self.visible_text_region();
+        let viewport_end = viewport_offset.saturating_add(visible_rows);
+
+        if !has_replacement || flags.count_only {
+            let line_count = self.buffers[self.current_buffer_idx].len_lines();
+            for captured in &prepared.matches {
+                let found = captured.found;
+                preview.push_line(found.start_line);
+                if found.start_line < viewport_end && found.end_line >= viewport_offset {
+                    self.push_search_match_segments(found, line_count);
+                }
+            }
+            return Some(preview);
+        }
+
+        let mut buffer = self.buffers[self.current_buffer_idx].clone();
+        let mut shift = EditShift::default();
+        let mut replaced = Vec::new();
+        for captured in &prepared.matches {
+            let text = expand_replacement(&prepared.replacement, &captured.groups);
+            let (_, start, end) = replace_match(&mut buffer, &mut shift, &captured.found, &text);
+            preview.mark_changed(
+                start.0,
+                end.0,
+                end.0 as isize - captured.found.end_line as isize,
+            );
+            for line in start.0..=end.0 {
+                preview.push_line(line);
+            }
+            if start != end {
+                replaced.push(BufferMatch {
+                    start_line: start.0,
+                    start_col: start.1,
+                    end_line: end.0,
+                    end_col: end.1,
+                });
+            }
+        }
+
+        // Highlight the inserted text; an empty replacement has nothing to show.
+        for found in replaced {
+            if found.start_line >= viewport_end || found.end_line < viewport_offset {
+                continue;
+            }
+            for line in found.start_line..=found.end_line {
+                let start_col = if line == found.start_line {
+                    found.start_col
+                } else {
+                    0
+                };
+                let end_col = if line == found.end_line {
+                    found.end_col
+                } else {
+                    buffer.line_len(line) + 1
+                };
+                if end_col > start_col {
+                    self.search_matches.push((line, start_col, end_col));
+                }
+            }
+        }
+
+        preview.buffer = Some(buffer);
+        Some(preview)
+    }
+}
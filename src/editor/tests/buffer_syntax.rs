@@ -0,0 +1,128 @@
+use crate::editor::Editor;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn unique_temp_dir(prefix: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time")
+        .as_nanos();
+    std::env::temp_dir().join(format!("{prefix}_{}_{}", std::process::id(), nanos))
+}
+
+fn write_fixtures(prefix: &str) -> (PathBuf, PathBuf, PathBuf) {
+    let temp_dir = unique_temp_dir(prefix);
+    std::fs::create_dir_all(&temp_dir).expect("create temp directory");
+    let rust = temp_dir.join("main.rs");
+    let toml = temp_dir.join("Cargo.toml");
+    std::fs::write(&rust, "fn main() {}\n").expect("write rust");
+    std::fs::write(&toml, "[package]\nname = \"x\"\n").expect("write toml");
+    (temp_dir, rust, toml)
+}
+
+fn root_id(editor: &Editor, idx: usize) -> usize {
+    let (tree, _) = editor
+        .syntax_at(idx)
+        .and_then(|syntax| syntax.get_tree_and_source())
+        .expect("parsed tree");
+    tree.root_node().id()
+}
+
+#[test]
+fn switching_buffers_keeps_each_tree_without_reparsing() {
+    let (temp_dir, rust, toml) = write_fixtures("nevi_buffer_syntax_switch");
+    let mut editor = Editor::default();
+    editor.open_file(rust).expect("open rust");
+    editor.open_file(toml).expect("open toml");
+
+    let rust_root = root_id(&editor, 0);
+    let toml_root = root_id(&editor, 1);
+
+    assert!(editor.switch_to_buffer(0));
+    assert_eq!(editor.syntax().language_name(), Some("rust"));
+    assert_eq!(root_id(&editor, 0), rust_root);
+
+    assert!(editor.switch_to_buffer(1));
+    assert_eq!(editor.syntax().language_name(), Some("toml"));
+    assert_eq!(root_id(&editor, 1), toml_root);
+
+    std::fs::remove_dir_all(temp_dir).expect("remove temp directory");
+}
+
+#[test]
+fn split_panes_highlight_their_own_buffers() {
+    let (temp_dir, rust, toml) = write_fixtures("nevi_buffer_syntax_split");
+    let mut editor = Editor::default();
+    editor.open_file(rust).expect("open rust");
+    editor.vsplit(Some(toml)).expect("split toml");
+
+    assert_eq!(
+        editor.syntax_at(0).and_then(|s| s.language_name()),
+        Some("rust")
+    );
+    assert_eq!(
+        editor.syntax_at(1).and_then(|s| s.language_name()),
+        Some("toml")
+    );
+    assert!(!editor.rendered_line_highlights(0, 0).is_empty());
+    assert!(!editor.rendered_line_highlights(1, 0).is_empty());
+
+    std::fs::remove_dir_all(temp_dir).expect("remove temp directory");
+}
+
+#[test]
+fn edits_through_one_pane_reparse_the_buffer_shown_in_another() {
+    let (temp_dir, rust, _) = write_fixtures("nevi_buffer_syntax_shared");
+    let mut editor = Editor::default();
+    editor.open_file(rust).expect("open rust");
+    editor.vsplit(None).expect("split same buffer");
+
+    editor.replace_buffer_content("// comment\nfn main() {}\n");
+    editor.maybe_update_syntax();
+
+    let buffer_idx = editor.current_buffer_index();
+    let syntax = editor.syntax_at(buffer_idx).expect("syntax");
+    assert!(syntax.is_synced_with(editor.buffer()));
+    assert!(!editor.rendered_line_highlights(buffer_idx, 1).is_empty());
+
+    std::fs::remove_dir_all(temp_dir).expect("remove temp directory");
+}
+
+#[test]
+fn closing_a_buffer_keeps_the_others_syntax_aligned() {
+    let (temp_dir, rust, toml) = write_fixtures("nevi_buffer_syntax_close");
+    let mut editor = Editor::default();
+    editor.open_file(rust).expect("open rust");
+    editor.open_file(toml).expect("open toml");
+
+    assert!(editor.switch_to_buffer(0));
+    editor.close_current_buffer();
+    assert_eq!(editor.buffer_count(), 1);
+    assert_eq!(editor.syntax().language_name(), Some("toml"));
+
+    editor.close_current_buffer();
+    assert_eq!(editor.syntax().language_name(), None);
+
+    std::fs::remove_dir_all(temp_dir).expect("remove temp directory");
+}
+
+#[test]
+fn unloadable_runtime_grammar_falls_back_to_builtin_detection() {
+    let (temp_dir, rust, _) = write_fixtures("nevi_buffer_syntax_runtime");
+    let mut editor = Editor {
+        languages_config: toml::from_str(
+            r#"
+            [nevi-missing-grammar]
+            extensions = ["rs"]
+            "#,
+        )
+        .expect("parse languages.toml"),
+        ..Default::default()
+    };
+    editor.open_file(rust).expect("open rust");
+
+    assert_eq!(editor.syntax().language_name(), Some("rust"));
+    assert!(!editor.rendered_line_highlights(0, 0).is_empty());
+
+    std::fs::remove_dir_all(temp_dir).expect("remove temp directory");
+}
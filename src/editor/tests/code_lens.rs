@@ -0,0 +1,151 @@
+use super::support::{ex, open_temp_file, unique_temp_dir};
+use crate::editor::{Editor, LspAction};
+use crate::lsp::path_to_uri;
+use crate::lsp::types::{CodeLens, LspCommand};
+
+fn command(title: &str) -> LspCommand {
+    LspCommand {
+        title: title.to_string(),
+        command: format!("test.{}", title.len()),
+        arguments: Vec::new(),
+        server: Some("rust".to_string()),
+    }
+}
+
+fn lens(line: usize, title: Option<&str>) -> CodeLens {
+    CodeLens {
+        line,
+        col: 0,
+        command: title.map(command),
+        raw: serde_json::json!({ "line": line }),
+    }
+}
+
+#[test]
+fn code_lenses_are_requested_once_per_version_and_resolved_by_index() {
+    let (tmp, path, mut editor) = open_temp_file(
+        "nevi_code_lens_request",
+        "fn main() {}\n\n#[test]\nfn it_works() {}\n",
+    );
+    let uri = path_to_uri(&path);
+    let version = editor.buffer().version();
+
+    assert_eq!(editor.code_lens_request(), Some((path.clone(), version)));
+    assert_eq!(editor.code_lens_request(), None);
+
+    let unresolved = editor.set_code_lenses(
+        &uri,
+        version,
+        vec![
+            lens(0, Some("▶ Run")),
+            lens(3, Some("▶ Run test")),
+            lens(3, None),
+        ],
+    );
+    assert_eq!(unresolved, vec![(2, serde_json::json!({ "line": 3 }))]);
+    assert_eq!(editor.code_lens_request(), None);
+    assert_eq!(
+        editor
+            .code_lens_label(editor.current_buffer_idx, 3)
+            .as_deref(),
+        Some("▶ Run test")
+    );
+
+    assert!(editor.resolve_code_lens(&uri, version, 2, lens(3, Some("Debug"))));
+    assert_eq!(
+        editor
+            .code_lens_label(editor.current_buffer_idx, 3)
+            .as_deref(),
+        Some("▶ Run test | Debug")
+    );
+    assert_eq!(editor.code_lens_label(editor.current_buffer_idx, 1), None);
+    // A resolve for an older answer is dropped.
+    assert!(!editor.resolve_code_lens(&uri, version + 1, 2, lens(3, Some("Stale"))));
+
+    // Edits and refreshes ask again, keeping the old lenses on screen.
+    editor.refresh_code_lenses();
+    assert_eq!(editor.code_lens_request(), Some((path.clone(), version)));
+    assert!(
+        editor
+            .code_lens_label(editor.current_buffer_idx, 0)
+            .is_some()
+    );
+    editor.insert_char('x');
+    assert_eq!(
+        editor.code_lens_request(),
+        Some((path, editor.buffer().version()))
+    );
+
+    ex(&mut editor, "set nocodelens");
+    assert_eq!(editor.code_lens_label(editor.current_buffer_idx, 0), None);
+    assert_eq!(editor.code_lens_request(), None);
+
+    let _ = std::fs::remove_dir_all(tmp);
+}
+
+#[test]
+fn code_lens_command_runs_a_lens_of_the_cursor_line() {
+    let (tmp, path, mut editor) =
+        open_temp_file("nevi_code_lens_run", "#[test]\nfn it_works() {}\n");
+    let version = editor.buffer().version();
+    editor.set_code_lenses(
+        &path_to_uri(&path),
+        version,
+        vec![lens(1, Some("▶ Run test")), lens(1, Some("Debug"))],
+    );
+
+    ex(&mut editor, "CodeLens");
+    assert_eq!(editor.pending_lsp_action, None);
+    assert_eq!(
+        editor.status_message.as_deref(),
+        Some("No code lens on this line")
+    );
+
+    editor.cursor.line = 1;
+    ex(&mut editor, "CodeLens");
+    assert_eq!(
+        editor.pending_lsp_action.take(),
+        Some(LspAction::ExecuteCommand(command("▶ Run test")))
+    );
+    ex(&mut editor, "CodeLens 2");
+    assert_eq!(
+        editor.pending_lsp_action.take(),
+        Some(LspAction::ExecuteCommand(command("Debug")))
+    );
+    ex(&mut editor, "CodeLens 3");
+    assert_eq!(editor.pending_lsp_action, None);
+
+    let _ = std::fs::remove_dir_all(tmp);
+}
+
+#[test]
+fn lens_rows_keep_the_cursor_line_on_screen() {
+    let tmp = unique_temp_dir("nevi_code_lens_scroll");
+    let path = tmp.join("main.rs");
+    let text: String = (0..40).map(|n| format!("fn f{n}() {{}}\n")).collect();
+    std::fs::write(&path, text).expect("write test file");
+    let mut editor = Editor::default();
+    editor.set_size(80, 12);
+    editor.settings.editor.scroll_off = 0;
+    editor.open_file(path.clone()).expect("open file");
+    let version = editor.buffer().version();
+    editor.set_code_lenses(
+        &path_to_uri(&path),
+        version,
+        (0..40)
+            .map(|line| lens(line, Some("1 reference")))
+            .collect(),
+    );
+    let text_rows = editor.active_pane_text_rows();
+
+    editor.cursor.line = 10;
+    editor.scroll_to_cursor();
+    // Every line takes two rows, its lens and itself.
+    assert_eq!(editor.viewport_offset, 11 - text_rows / 2);
+    assert_eq!(
+        editor.code_lens_rows(editor.current_buffer_idx, editor.viewport_offset, 11, 0),
+        11 - editor.viewport_offset
+    );
+
+    let _ = std::fs::remove_dir_all(tmp);
+}
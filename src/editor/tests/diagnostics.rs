@@ -0,0 +1,134 @@
+use super::support::unique_temp_dir;
+use crate::editor::Editor;
+use crate::lsp::types::{Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity};
+
+fn diagnostic(line: usize, message: &str) -> Diagnostic {
+    Diagnostic {
+        line,
+        end_line: line,
+        col_start: 0,
+        col_end: 2,
+        severity: DiagnosticSeverity::Warning,
+        message: message.to_string(),
+        source: None,
+        code: None,
+        tags: Vec::new(),
+        related: Vec::new(),
+        href: None,
+    }
+}
+
+fn related(uri: &str, line: usize, col: usize) -> DiagnosticRelatedInformation {
+    DiagnosticRelatedInformation {
+        uri: uri.to_string(),
+        line,
+        col,
+        message: "defined here".to_string(),
+    }
+}
+
+#[test]
+fn diagnostics_are_pulled_once_per_buffer_version() {
+    let tmp = unique_temp_dir("nevi_diagnostic_pull");
+    let path = tmp.join("app.py");
+    std::fs::write(&path, "import os\n").expect("write test file");
+    let mut editor = Editor::default();
+    editor.set_size(80, 24);
+    editor.open_file(path.clone()).expect("open file");
+
+    assert_eq!(editor.diagnostic_pull_request(), Some(path.clone()));
+    assert_eq!(editor.diagnostic_pull_request(), None);
+
+    editor.insert_char('x');
+    assert_eq!(editor.diagnostic_pull_request(), Some(path.clone()));
+    assert_eq!(editor.diagnostic_pull_request(), None);
+
+    // The server asked for a new pull of unchanged text.
+    editor.refresh_pulled_diagnostics();
+    assert_eq!(editor.diagnostic_pull_request(), Some(path));
+
+    let _ = std::fs::remove_dir_all(tmp);
+}
+
+#[test]
+fn related_locations_are_numbered_across_the_line_and_jumped_to() {
+    let tmp = unique_temp_dir("nevi_diagnostic_related");
+    let main = tmp.join("app.py");
+    let util = tmp.join("util.py");
+    std::fs::write(&main, "import os\nos.getcwd()\n").expect("write main");
+    std::fs::write(&util, "# util\nx = \"😀\"; os = 1\n").expect("write util");
+    let mut editor = Editor::default();
+    editor.set_size(80, 24);
+    editor.open_file(main.clone()).expect("open file");
+    let main_uri = crate::lsp::path_to_uri(&main);
+    let util_uri = crate::lsp::path_to_uri(&util);
+
+    let mut first = diagnostic(0, "`os` redefined");
+    first.related = vec![related(&util_uri, 1, 11)];
+    let mut second = diagnostic(0, "`os` shadowed");
+    second.related = vec![related(&main_uri, 1, 3)];
+    editor.set_diagnostics(main_uri.clone(), vec![first, second]);
+
+    let numbered: Vec<(usize, usize)> = editor
+        .diagnostic_related_for_float()
+        .iter()
+        .map(|related| (related.line, related.col))
+        .collect();
+    assert_eq!(numbered, vec![(1, 11), (1, 3)]);
+    assert!(editor.goto_diagnostic_related(3).is_err());
+
+    // Columns are UTF-16; the emoji takes two units.
+    editor.goto_diagnostic_related(1).expect("jump to util.py");
+    assert_eq!(editor.buffer().path.as_ref(), Some(&util));
+    assert_eq!((editor.cursor.line, editor.cursor.col), (1, 10));
+
+    // The jump can be undone with the jump list.
+    assert!(editor.jump_to_previous_position_exact());
+    assert_eq!(editor.buffer().path.as_ref(), Some(&main));
+    assert_eq!((editor.cursor.line, editor.cursor.col), (0, 0));
+
+    let _ = std::fs::remove_dir_all(tmp);
+}
+
+#[test]
+fn gx_opens_the_diagnostic_code_description_without_a_url_under_cursor() {
+    let tmp = unique_temp_dir("nevi_diagnostic_href");
+    let path = tmp.join("app.py");
+    std::fs::write(&path, "import os\n").expect("write test file");
+    let mut editor = Editor::default();
+    editor.set_size(80, 24);
+    editor.open_file(path.clone()).expect("open file");
+
+    let mut unused = diagnostic(0, "`os` imported but unused");
+    unused.col_start = 7;
+    unused.col_end = 9;
+    unused.href = Some("https://docs.astral.sh/ruff/rules/F401".to_string());
+    editor.set_diagnostics(crate::lsp::path_to_uri(&path), vec![unused]);
+
+    let mut opened = Vec::new();
+    let url = editor
+        .open_url_under_cursor_with(|url| {
+            opened.push(url.to_string());
+            Ok(())
+        })
+        .expect("open diagnostic href");
+    assert_eq!(url, "https://docs.astral.sh/ruff/rules/F401");
+    assert_eq!(opened, vec![url]);
+
+    editor.set_diagnostics(crate::lsp::path_to_uri(&path), Vec::new());
+    assert_eq!(
+        editor.open_url_under_cursor_with(|_| Ok(())),
+        Err("No URL under cursor".to_string())
+    );
+
+    // Links that aren't http(s) are never handed to the opener.
+    let mut local = diagnostic(0, "see the launcher");
+    local.href = Some("file:///tmp/launcher.desktop".to_string());
+    editor.set_diagnostics(crate::lsp::path_to_uri(&path), vec![local]);
+    assert_eq!(
+        editor.open_url_under_cursor_with(|url| panic!("opened {url}")),
+        Err("No URL under cursor".to_string())
+    );
+
+    let _ = std::fs::remove_dir_all(tmp);
+}
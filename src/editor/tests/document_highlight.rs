@@ -0,0 +1,94 @@
+use super::support::{ex, open_temp_file};
+use crate::editor::DocumentHighlightSpan;
+use crate::lsp::path_to_uri;
+use crate::lsp::types::{DocumentHighlight, DocumentHighlightKind, TextRange};
+
+fn highlight(
+    line: usize,
+    start: usize,
+    end: usize,
+    kind: DocumentHighlightKind,
+) -> DocumentHighlight {
+    DocumentHighlight {
+        range: TextRange {
+            start_line: line,
+            start_col: start,
+            end_line: line,
+            end_col: end,
+        },
+        kind,
+    }
+}
+
+#[test]
+fn document_highlights_follow_the_cursor() {
+    let (tmp, path, mut editor) =
+        open_temp_file("nevi_document_highlight", "let value = 1;\nprint(value);\n");
+    let version = editor.buffer().version();
+    let uri = path_to_uri(&path);
+    editor.cursor.col = 4;
+
+    assert_eq!(
+        editor.document_highlight_request(),
+        Some((path.clone(), 0, 4, version))
+    );
+    // Already asked about this position
+    assert_eq!(editor.document_highlight_request(), None);
+
+    assert!(editor.set_document_highlights(
+        &uri,
+        version,
+        0,
+        4,
+        vec![
+            highlight(1, 6, 11, DocumentHighlightKind::Read),
+            highlight(0, 4, 9, DocumentHighlightKind::Write),
+        ],
+    ));
+    assert_eq!(
+        editor.document_highlights_for_buffer(0),
+        &[
+            DocumentHighlightSpan {
+                line: 0,
+                start_col: 4,
+                end_col: 9,
+                kind: DocumentHighlightKind::Write,
+            },
+            DocumentHighlightSpan {
+                line: 1,
+                start_col: 6,
+                end_col: 11,
+                kind: DocumentHighlightKind::Read,
+            },
+        ]
+    );
+
+    // Moving along the symbol keeps them without asking again
+    editor.cursor.col = 8;
+    assert_eq!(editor.document_highlights_for_buffer(0).len(), 2);
+    assert_eq!(editor.document_highlight_request(), None);
+
+    // Moving off hides them, then asks about the new position
+    editor.cursor.col = 0;
+    assert!(editor.document_highlights_for_buffer(0).is_empty());
+    assert_eq!(
+        editor.document_highlight_request(),
+        Some((path.clone(), 0, 0, version))
+    );
+    assert!(!editor.has_document_highlights());
+
+    // An answer for the old position is stale
+    assert!(!editor.set_document_highlights(
+        &uri,
+        version,
+        0,
+        4,
+        vec![highlight(0, 4, 9, DocumentHighlightKind::Write)],
+    ));
+
+    ex(&mut editor, "set nodocumenthighlight");
+    editor.cursor.col = 4;
+    assert_eq!(editor.document_highlight_request(), None);
+
+    std::fs::remove_dir_all(tmp).ok();
+}
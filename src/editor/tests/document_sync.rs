@@ -0,0 +1,65 @@
+use super::support::{keys, open_temp_file};
+use crate::lsp::ContentChange;
+
+fn change(start: (u32, u32), end: (u32, u32), text: &str) -> ContentChange {
+    ContentChange {
+        range: Some((start, end)),
+        text: text.to_string(),
+    }
+}
+
+#[test]
+fn lsp_changes_are_utf16_ranges_in_the_text_before_each_edit() {
+    let (tmp, path, mut editor) =
+        open_temp_file("nevi_document_sync_ranges", "let 😀x = 1;\nfoo\n");
+    editor.buffer_mut().insert_str(1, 0, "ignored ");
+    assert_eq!(
+        editor.lsp_open_text(&path).as_deref(),
+        Some("let 😀x = 1;\nignored foo\n")
+    );
+    assert_eq!(editor.take_lsp_changes(&path), Some(Vec::new()));
+
+    // "😀" is two UTF-16 units, so "x" starts at character 6.
+    editor.buffer_mut().delete_range(0, 5, 1, 8);
+    editor.buffer_mut().insert_str(0, 5, "y");
+    assert_eq!(
+        editor.take_lsp_changes(&path),
+        Some(vec![
+            change((0, 6), (1, 8), ""),
+            change((0, 6), (0, 6), "y")
+        ])
+    );
+
+    // Normal-mode edits and their undo are tracked too.
+    keys(&mut editor, "ggx");
+    assert_eq!(editor.buffer().content(), "et 😀yfoo\n");
+    keys(&mut editor, "u");
+    assert_eq!(
+        editor.take_lsp_changes(&path),
+        Some(vec![
+            change((0, 0), (0, 1), ""),
+            change((0, 0), (0, 0), "l")
+        ])
+    );
+    assert_eq!(editor.take_lsp_changes(&path), Some(Vec::new()));
+
+    let _ = std::fs::remove_dir_all(&tmp);
+}
+
+#[test]
+fn untracked_text_changes_fall_back_to_the_whole_text() {
+    let (tmp, path, mut editor) = open_temp_file("nevi_document_sync_full", "one\n");
+    editor.lsp_open_text(&path);
+
+    editor.replace_buffer_content("two\n");
+    assert_eq!(editor.take_lsp_changes(&path), None);
+    // Tracking starts again from the text the server was sent.
+    editor.buffer_mut().insert_char(0, 3, '!');
+    assert_eq!(
+        editor.take_lsp_changes(&path),
+        Some(vec![change((0, 3), (0, 3), "!")])
+    );
+    assert_eq!(editor.take_lsp_changes(&tmp.join("other.rs")), None);
+
+    let _ = std::fs::remove_dir_all(&tmp);
+}
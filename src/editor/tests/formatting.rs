@@ -0,0 +1,141 @@
+use super::support::{keys, open_temp_file};
+use crate::editor::{Editor, LspAction, Mode};
+use crate::lsp::types::{TextEdit, TextRange};
+use crate::terminal::handle_key;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+fn edit(start: (usize, usize), end: (usize, usize), new_text: &str) -> TextEdit {
+    TextEdit {
+        start_line: start.0,
+        start_col: start.1,
+        end_line: end.0,
+        end_col: end.1,
+        new_text: new_text.to_string(),
+    }
+}
+
+#[test]
+fn gq_requests_range_formatting_of_whole_lines() {
+    let (tmp, _, mut editor) =
+        open_temp_file("nevi_gq_lines", "fn main() {\nlet a=1;\nlet b=2;\n}\n");
+    editor.cursor.line = 1;
+
+    keys(&mut editor, "gqj");
+    assert_eq!(
+        editor.pending_lsp_action.take(),
+        Some(LspAction::RangeFormatting(TextRange {
+            start_line: 1,
+            start_col: 0,
+            end_line: 2,
+            end_col: 8,
+        }))
+    );
+    assert_eq!(
+        editor.buffer().content(),
+        "fn main() {\nlet a=1;\nlet b=2;\n}\n"
+    );
+
+    keys(&mut editor, "gqq");
+    assert_eq!(
+        editor.pending_lsp_action.take(),
+        Some(LspAction::RangeFormatting(TextRange {
+            start_line: 1,
+            start_col: 0,
+            end_line: 1,
+            end_col: 8,
+        }))
+    );
+
+    handle_key(
+        &mut editor,
+        KeyEvent::new(KeyCode::Char('V'), KeyModifiers::SHIFT),
+    );
+    keys(&mut editor, "jgq");
+    assert_eq!(editor.mode, Mode::Normal);
+    assert!(matches!(
+        editor.pending_lsp_action.take(),
+        Some(LspAction::RangeFormatting(TextRange {
+            start_line: 1,
+            end_line: 2,
+            ..
+        }))
+    ));
+
+    let _ = std::fs::remove_dir_all(tmp);
+}
+
+#[test]
+fn visual_gq_formats_the_exact_selection_in_utf16_columns() {
+    let (tmp, _, mut editor) = open_temp_file("nevi_gq_visual", "let s = \"😀\";  let t=1;\n");
+    // From `let t` to the `1` after the emoji, which takes two UTF-16 units
+    editor.cursor.col = 14;
+    keys(&mut editor, "v");
+    editor.cursor.col = 20;
+
+    keys(&mut editor, "gq");
+    assert_eq!(
+        editor.pending_lsp_action.take(),
+        Some(LspAction::RangeFormatting(TextRange {
+            start_line: 0,
+            start_col: 15,
+            end_line: 0,
+            end_col: 22,
+        }))
+    );
+
+    let _ = std::fs::remove_dir_all(tmp);
+}
+
+#[test]
+fn gq_without_a_file_asks_nothing() {
+    let mut editor = Editor::default();
+    editor.set_size(80, 24);
+    keys(&mut editor, "gqq");
+    assert_eq!(editor.pending_lsp_action, None);
+    assert!(
+        editor
+            .status_message
+            .as_deref()
+            .is_some_and(|msg| msg.contains("language server"))
+    );
+}
+
+#[test]
+fn formatting_edits_keep_the_cursor_on_its_text() {
+    let (tmp, _, mut editor) = open_temp_file(
+        "nevi_format_cursor",
+        "fn main() {\nlet value = 1;\n        }\n",
+    );
+
+    // Reindenting the line before the cursor shifts it with the text.
+    editor.cursor.line = 1;
+    editor.cursor.col = 4;
+    editor.apply_formatting_edits(&[edit((1, 0), (1, 0), "    "), edit((2, 0), (2, 8), "")]);
+    assert_eq!(
+        editor.buffer().content(),
+        "fn main() {\n    let value = 1;\n}\n"
+    );
+    assert_eq!((editor.cursor.line, editor.cursor.col), (1, 8));
+
+    // On-type formatting right after the typed `}`: the cursor stays put
+    // relative to it and text inserted at the cursor lands after it.
+    editor.mode = Mode::Insert;
+    editor.cursor.line = 2;
+    editor.cursor.col = 1;
+    editor.apply_formatting_edits(&[edit((2, 1), (2, 1), " // end")]);
+    assert_eq!(
+        editor.buffer().content(),
+        "fn main() {\n    let value = 1;\n} // end\n"
+    );
+    assert_eq!((editor.cursor.line, editor.cursor.col), (2, 1));
+
+    // The whole batch is one undo step.
+    editor.mode = Mode::Normal;
+    editor.undo();
+    assert_eq!(
+        editor.buffer().content(),
+        "fn main() {\n    let value = 1;\n}\n"
+    );
+
+    let _ = std::fs::remove_dir_all(tmp);
+}
@@ -0,0 +1,116 @@
+use crate::editor::Editor;
+use crate::terminal::handle_key;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+fn char_key(ch: char) -> KeyEvent {
+    let modifiers = if ch.is_ascii_uppercase() {
+        KeyModifiers::SHIFT
+    } else {
+        KeyModifiers::NONE
+    };
+    KeyEvent::new(KeyCode::Char(ch), modifiers)
+}
+
+fn type_chars(editor: &mut Editor, chars: &str) {
+    for ch in chars.chars() {
+        handle_key(editor, char_key(ch));
+    }
+}
+
+fn ex(editor: &mut Editor, command: &str) {
+    type_chars(editor, ":");
+    type_chars(editor, command);
+    handle_key(editor, KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+}
+
+#[test]
+fn global_delete_skips_marked_lines_that_are_already_gone() {
+    let mut editor = Editor::default();
+    editor.replace_buffer_content("a\n\n\nb\n\nc\n");
+
+    ex(&mut editor, "g/^$/d");
+    assert_eq!(editor.buffer().content(), "a\nb\nc\n");
+
+    editor.replace_buffer_content("one\ntwo\nthree\nfour\nfive\n");
+    ex(&mut editor, "g/o/.,+1d");
+    assert_eq!(editor.buffer().content(), "three\n");
+}
+
+#[test]
+fn vglobal_deletes_unmatched_lines_as_one_undo_step() {
+    let mut editor = Editor::default();
+    editor.replace_buffer_content("keep 1\ndrop\nkeep 2\ndrop\ndrop\n");
+
+    ex(&mut editor, "v/keep/d");
+    assert_eq!(editor.buffer().content(), "keep 1\nkeep 2\n");
+    assert_eq!(editor.undo_stack.undo_count(), 1);
+
+    type_chars(&mut editor, "u");
+    assert_eq!(
+        editor.buffer().content(),
+        "keep 1\ndrop\nkeep 2\ndrop\ndrop\n"
+    );
+
+    ex(&mut editor, "g!/keep/d");
+    assert_eq!(editor.buffer().content(), "keep 1\nkeep 2\n");
+}
+
+#[test]
+fn global_normal_replays_keys_on_each_line() {
+    let mut editor = Editor::default();
+    editor.replace_buffer_content("TODO one\ndone\nTODO two\n");
+
+    ex(&mut editor, "g/TODO/normal A;");
+
+    assert_eq!(editor.buffer().content(), "TODO one;\ndone\nTODO two;\n");
+    assert_eq!(editor.mode, crate::editor::Mode::Normal);
+
+    type_chars(&mut editor, "u");
+    assert_eq!(editor.buffer().content(), "TODO one\ndone\nTODO two\n");
+}
+
+#[test]
+fn global_substitute_reuses_the_pattern_and_ignores_lines_without_matches() {
+    let mut editor = Editor::default();
+    editor.replace_buffer_content("foo x\nbar\nfoo y\n");
+
+    ex(&mut editor, "g/foo/s//baz/");
+    assert_eq!(editor.buffer().content(), "baz x\nbar\nbaz y\n");
+
+    ex(&mut editor, "%g/ /s/y/z/");
+    assert_eq!(editor.buffer().content(), "baz x\nbar\nbaz z\n");
+    assert_eq!(editor.status_message, None);
+}
+
+#[test]
+fn global_without_matches_reports_the_pattern() {
+    let mut editor = Editor::default();
+    editor.replace_buffer_content("a\nb\n");
+
+    ex(&mut editor, "g/zzz/d");
+
+    assert_eq!(
+        editor.status_message.as_deref(),
+        Some("E: Pattern not found: zzz")
+    );
+    assert!(!editor.undo_stack.can_undo());
+}
+
+#[test]
+fn global_without_a_command_prints_the_marked_lines() {
+    let mut editor = Editor::default();
+    editor.replace_buffer_content(
+        "alpha
+beta
+alpine
+",
+    );
+
+    ex(&mut editor, "g/beta/");
+    assert_eq!(editor.status_message.as_deref(), Some("beta"));
+
+    ex(&mut editor, "g/^al");
+    assert_eq!(editor.buffer().display_name(), "[global]");
+    assert!(editor.buffer().is_read_only());
+    assert_eq!(editor.buffer().content(), "alpha\nalpine\n");
+}
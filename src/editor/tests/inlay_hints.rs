@@ -0,0 +1,78 @@
+use super::support::{ex, open_temp_file};
+use crate::editor::BufferInlayHint;
+use crate::lsp::path_to_uri;
+use crate::lsp::types::{InlayHint, InlayHintKind};
+
+fn type_hint(line: usize, col: usize, label: &str) -> InlayHint {
+    InlayHint {
+        line,
+        col,
+        label: label.to_string(),
+        kind: Some(InlayHintKind::Type),
+        padding_left: false,
+        padding_right: false,
+    }
+}
+
+#[test]
+fn inlay_hints_are_requested_once_per_version_and_viewport() {
+    let (tmp, path, mut editor) = open_temp_file("nevi_inlay_request", "let a = 1;\nlet b = 2;\n");
+    let version = editor.buffer().version();
+
+    assert_eq!(
+        editor.inlay_hint_request(),
+        Some((path.clone(), 0, 3, version))
+    );
+    // Already in flight
+    assert_eq!(editor.inlay_hint_request(), None);
+
+    let uri = path_to_uri(&path);
+    assert!(editor.set_inlay_hints(&uri, version, 0, 3, vec![type_hint(0, 5, ": i32")]));
+    assert_eq!(editor.inlay_hint_request(), None);
+
+    editor.refresh_inlay_hints();
+    assert_eq!(
+        editor.inlay_hint_request(),
+        Some((path.clone(), 0, 3, version))
+    );
+
+    ex(&mut editor, "set noinlayhints");
+    assert!(!editor.inlay_hints_enabled());
+    assert_eq!(editor.inlay_hint_request(), None);
+
+    let _ = std::fs::remove_dir_all(&tmp);
+}
+
+#[test]
+fn inlay_hints_use_buffer_columns_and_drop_stale_versions() {
+    let (tmp, path, mut editor) = open_temp_file("nevi_inlay_cache", "let 😀x = 1;\n");
+    let uri = path_to_uri(&path);
+    let version = editor.buffer().version();
+
+    // "let 😀x" ends at UTF-16 column 7, char column 6.
+    let mut hint = type_hint(0, 7, ": i32");
+    hint.padding_left = true;
+    assert!(editor.set_inlay_hints(&uri, version, 0, 2, vec![hint]));
+
+    let cache = editor.inlay_hints_for_buffer(0).expect("cached hints");
+    assert_eq!(
+        cache.hints_on_line(0),
+        &[BufferInlayHint {
+            line: 0,
+            col: 6,
+            label: " : i32".to_string(),
+        }]
+    );
+    assert!(cache.hints_on_line(1).is_empty());
+
+    ex(&mut editor, "set noinlayhints");
+    assert!(editor.inlay_hints_for_buffer(0).is_none());
+    ex(&mut editor, "set inlayhints");
+
+    // Answers for an older version are ignored, and edits hide old hints.
+    editor.replace_buffer_content("let y = 2;\n");
+    assert!(editor.inlay_hints_for_buffer(0).is_none());
+    assert!(!editor.set_inlay_hints(&uri, version, 0, 2, vec![type_hint(0, 5, ": u8")]));
+
+    let _ = std::fs::remove_dir_all(&tmp);
+}
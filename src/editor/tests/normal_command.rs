@@ -0,0 +1,128 @@
+use crate::config::{KeymapEntry, KeymapLookup, Settings};
+use crate::editor::Editor;
+use crate::terminal::handle_key;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+fn char_key(ch: char) -> KeyEvent {
+    let modifiers = if ch.is_ascii_uppercase() {
+        KeyModifiers::SHIFT
+    } else {
+        KeyModifiers::NONE
+    };
+    KeyEvent::new(KeyCode::Char(ch), modifiers)
+}
+
+fn type_chars(editor: &mut Editor, chars: &str) {
+    for ch in chars.chars() {
+        handle_key(editor, char_key(ch));
+    }
+}
+
+fn enter(editor: &mut Editor) {
+    handle_key(editor, KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+}
+
+fn ex(editor: &mut Editor, command: &str) {
+    type_chars(editor, ":");
+    type_chars(editor, command);
+    enter(editor);
+}
+
+#[test]
+fn ranged_normal_runs_keys_from_the_start_of_each_line_as_one_undo_step() {
+    let mut editor = Editor::default();
+    editor.replace_buffer_content("a\nb\nc\n");
+
+    ex(&mut editor, "%normal A;");
+
+    assert_eq!(editor.buffer().content(), "a;\nb;\nc;\n");
+    assert_eq!(editor.undo_stack.undo_count(), 1);
+
+    type_chars(&mut editor, "u");
+    assert_eq!(editor.buffer().content(), "a\nb\nc\n");
+}
+
+#[test]
+fn normal_parses_key_notation_and_keeps_a_bare_angle_bracket() {
+    let mut editor = Editor::default();
+    editor.replace_buffer_content("one two\nthree\n");
+
+    ex(&mut editor, "1normal wciw<<Esc>I-<Esc>");
+
+    assert_eq!(editor.buffer().content(), "-one <\nthree\n");
+}
+
+#[test]
+fn visual_range_normal_dot_repeats_the_last_change() {
+    let mut editor = Editor::default();
+    editor.replace_buffer_content("a\nb\nc\nd\n");
+
+    type_chars(&mut editor, "A!");
+    handle_key(&mut editor, KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+    type_chars(&mut editor, "jVj:normal .");
+    enter(&mut editor);
+
+    assert_eq!(editor.buffer().content(), "a!\nb!\nc!\nd\n");
+}
+
+#[test]
+fn normal_bang_ignores_user_mappings() {
+    let mut settings = Settings::default();
+    settings.keymap.normal.push(KeymapEntry {
+        from: "x".to_string(),
+        to: "dd".to_string(),
+    });
+    let mut editor = Editor {
+        keymap: KeymapLookup::from_settings(&settings.keymap).0,
+        ..Default::default()
+    };
+    editor.replace_buffer_content("abc\ndef\n");
+
+    ex(&mut editor, "normal x");
+    assert_eq!(editor.buffer().content(), "def\n");
+
+    ex(&mut editor, "normal! x");
+    assert_eq!(editor.buffer().content(), "ef\n");
+    assert!(editor.keymap.get_normal_mapping(char_key('x')).is_some());
+}
+
+#[test]
+fn dot_repeats_the_last_change_but_not_undo() {
+    let mut editor = Editor::default();
+    editor.replace_buffer_content("one two three\n");
+
+    type_chars(&mut editor, "ciwX");
+    handle_key(&mut editor, KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+    type_chars(&mut editor, "w.");
+    assert_eq!(editor.buffer().content(), "X X three\n");
+
+    type_chars(&mut editor, "uw.");
+    assert_eq!(editor.buffer().content(), "X two X\n");
+}
+
+#[test]
+fn counted_dot_replaces_the_count_of_the_change() {
+    let mut editor = Editor::default();
+    editor.replace_buffer_content("1\n2\n3\n4\n5\n6\n7\n8\n9\n");
+
+    type_chars(&mut editor, "2dd3.");
+    assert_eq!(editor.buffer().content(), "6\n7\n8\n9\n");
+    // The new count sticks for the next repeat.
+    type_chars(&mut editor, ".");
+    assert_eq!(editor.buffer().content(), "9\n");
+
+    editor.replace_buffer_content("a b c d e f g\n");
+    type_chars(&mut editor, "0\"ad2w.1.");
+    assert_eq!(editor.buffer().content(), "f g\n");
+    assert_eq!(
+        editor
+            .registers
+            .get(Some('a'))
+            .map(|content| content.as_str()),
+        Some("e ")
+    );
+
+    editor.replace_buffer_content("abcdef\n");
+    type_chars(&mut editor, "0x4.");
+    assert_eq!(editor.buffer().content(), "f\n");
+}
@@ -0,0 +1,85 @@
+use super::support::{keys, open_temp_file};
+use crate::editor::{Editor, Mode};
+use crate::lsp::path_to_uri;
+use crate::lsp::types::TextRange;
+use std::path::PathBuf;
+
+fn open_fixture(prefix: &str) -> (PathBuf, PathBuf, Editor) {
+    let (tmp, path, mut editor) =
+        open_temp_file(prefix, "fn main() {\n    let value = compute(1, 2);\n}\n");
+    // On the `c` of `compute`
+    editor.cursor.line = 1;
+    editor.cursor.col = 16;
+    (tmp, path, editor)
+}
+
+/// `an` as the main loop runs it when no server offers selection ranges
+fn expand_without_server(editor: &mut Editor) {
+    keys(editor, "an");
+    assert!(editor.take_selection_range_request().is_some());
+    editor.expand_selection_with_syntax();
+}
+
+#[test]
+fn an_and_in_walk_syntax_nodes_without_a_server() {
+    let (tmp, _path, mut editor) = open_fixture("nevi_selection_syntax");
+    keys(&mut editor, "v");
+
+    expand_without_server(&mut editor);
+    assert_eq!(editor.get_visual_range(), (1, 16, 1, 22));
+    expand_without_server(&mut editor);
+    assert_eq!(editor.get_visual_range(), (1, 16, 1, 28));
+    expand_without_server(&mut editor);
+    assert_eq!(editor.get_visual_range(), (1, 4, 1, 29));
+
+    keys(&mut editor, "in");
+    assert_eq!(editor.get_visual_range(), (1, 16, 1, 28));
+    keys(&mut editor, "in");
+    keys(&mut editor, "in");
+    assert_eq!(editor.get_visual_range(), (1, 16, 1, 16));
+    // Nothing left to shrink to
+    keys(&mut editor, "in");
+    assert_eq!(editor.get_visual_range(), (1, 16, 1, 16));
+    assert_eq!(editor.mode, Mode::Visual);
+
+    std::fs::remove_dir_all(tmp).ok();
+}
+
+#[test]
+fn an_grows_to_the_servers_innermost_enclosing_range() {
+    let (tmp, path, mut editor) = open_fixture("nevi_selection_lsp");
+    let uri = path_to_uri(&path);
+    keys(&mut editor, "van");
+    let version = editor.buffer().version();
+    assert_eq!(
+        editor.take_selection_range_request(),
+        Some((path.clone(), 1, 16, version))
+    );
+    // Queued once
+    assert_eq!(editor.take_selection_range_request(), None);
+
+    let range = |start_line, start_col, end_line, end_col| TextRange {
+        start_line,
+        start_col,
+        end_line,
+        end_col,
+    };
+    assert!(editor.set_selection_ranges(
+        &uri,
+        version,
+        vec![range(1, 16, 1, 17), range(1, 16, 1, 29), range(0, 0, 2, 1)],
+    ));
+    // The one-character range is the selection already
+    assert_eq!(editor.get_visual_range(), (1, 16, 1, 28));
+
+    // Ranges that enclose nothing fall back to syntax nodes
+    keys(&mut editor, "an");
+    assert!(editor.take_selection_range_request().is_some());
+    assert!(editor.set_selection_ranges(&uri, version, vec![range(1, 16, 1, 23)]));
+    assert_eq!(editor.get_visual_range(), (1, 4, 1, 29));
+
+    keys(&mut editor, "in");
+    assert_eq!(editor.get_visual_range(), (1, 16, 1, 28));
+
+    std::fs::remove_dir_all(tmp).ok();
+}
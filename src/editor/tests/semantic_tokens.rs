@@ -0,0 +1,191 @@
+use super::support::{ex, open_temp_file};
+use crate::editor::Editor;
+use crate::lsp::path_to_uri;
+use crate::lsp::types::{SemanticTokensData, SemanticTokensEdit, SemanticTokensLegend};
+use crate::syntax::HighlightGroup;
+
+fn legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: vec![
+            "variable".to_string(),
+            "interface".to_string(),
+            "struct".to_string(),
+        ],
+        token_modifiers: vec!["declaration".to_string(), "mutable".to_string()],
+    }
+}
+
+/// `(start_col, end_col, group, modifiers)` of the tokens on `line`
+fn tokens_on_line(
+    editor: &Editor,
+    line: usize,
+) -> Vec<(usize, usize, HighlightGroup, Vec<HighlightGroup>)> {
+    editor
+        .syntax()
+        .semantic_tokens_on_line(line)
+        .iter()
+        .map(|token| {
+            (
+                token.start_col,
+                token.end_col,
+                token.group,
+                token.modifiers.clone(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn semantic_tokens_decode_against_the_legend_in_buffer_columns() {
+    let (tmp, path, mut editor) = open_temp_file(
+        "nevi_semantic_decode",
+        "let 😀x = y;\nimpl Foo for Bar {}\n",
+    );
+    let uri = path_to_uri(&path);
+    let version = editor.buffer().version();
+
+    assert_eq!(
+        editor.semantic_tokens_request(),
+        Some((path.clone(), None, version))
+    );
+    // Already in flight
+    assert_eq!(editor.semantic_tokens_request(), None);
+
+    // "😀x" is three UTF-16 units and two chars; "y" follows at UTF-16 10.
+    let data = vec![
+        0, 4, 3, 0, 0b11, // 😀x: variable, declaration + mutable
+        0, 6, 1, 0, 0, // y: variable
+        1, 5, 3, 1, 0, // Foo: interface
+        0, 8, 3, 2, 0, // Bar: struct
+        0, 4, 2, 7, 0, // unknown type: left to tree-sitter
+    ];
+    assert!(editor.set_semantic_tokens(
+        &uri,
+        version,
+        None,
+        Some("1".to_string()),
+        Some(SemanticTokensData::Full(data)),
+        &legend(),
+    ));
+    assert_eq!(
+        tokens_on_line(&editor, 0),
+        vec![
+            (
+                4,
+                6,
+                HighlightGroup::Variable,
+                vec![HighlightGroup::Mutable]
+            ),
+            (9, 10, HighlightGroup::Variable, vec![]),
+        ]
+    );
+    assert_eq!(
+        tokens_on_line(&editor, 1),
+        vec![
+            (5, 8, HighlightGroup::Interface, vec![]),
+            (13, 16, HighlightGroup::Struct, vec![]),
+        ]
+    );
+    assert_eq!(editor.semantic_tokens_request(), None);
+
+    ex(&mut editor, "set nosemantictokens");
+    assert!(!editor.semantic_tokens_enabled());
+    assert!(tokens_on_line(&editor, 1).is_empty());
+    assert_eq!(editor.semantic_tokens_request(), None);
+
+    let _ = std::fs::remove_dir_all(&tmp);
+}
+
+#[test]
+fn semantic_token_deltas_apply_to_the_result_they_were_computed_against() {
+    let (tmp, path, mut editor) = open_temp_file("nevi_semantic_delta", "struct A;\nstruct B;\n");
+    let uri = path_to_uri(&path);
+    let version = editor.buffer().version();
+    editor.semantic_tokens_request();
+    assert!(editor.set_semantic_tokens(
+        &uri,
+        version,
+        None,
+        Some("1".to_string()),
+        Some(SemanticTokensData::Full(vec![0, 7, 1, 2, 0])),
+        &legend(),
+    ));
+
+    // Adding a line makes the next request a delta against result "1".
+    editor.replace_buffer_content("struct A;\nstruct B;\nstruct C;\n");
+    editor.maybe_update_syntax();
+    let version = editor.buffer().version();
+    assert_eq!(
+        editor.semantic_tokens_request(),
+        Some((path.clone(), Some("1".to_string()), version))
+    );
+    let delta = |start, data| {
+        Some(SemanticTokensData::Delta(vec![SemanticTokensEdit {
+            start,
+            delete_count: 0,
+            data,
+        }]))
+    };
+    assert!(editor.set_semantic_tokens(
+        &uri,
+        version,
+        Some("1"),
+        Some("2".to_string()),
+        delta(5, vec![1, 7, 1, 1, 0]),
+        &legend(),
+    ));
+    assert_eq!(
+        tokens_on_line(&editor, 1),
+        vec![(7, 8, HighlightGroup::Interface, vec![])]
+    );
+
+    // A delta against a result we no longer have is dropped, and so is the
+    // base, so the request is sent again for every token.
+    editor.replace_buffer_content("struct A;\n");
+    editor.maybe_update_syntax();
+    let version = editor.buffer().version();
+    editor.semantic_tokens_request();
+    assert!(!editor.set_semantic_tokens(
+        &uri,
+        version,
+        Some("1"),
+        Some("3".to_string()),
+        delta(0, vec![]),
+        &legend(),
+    ));
+    assert_eq!(
+        editor.semantic_tokens_request(),
+        Some((path.clone(), None, version))
+    );
+
+    let _ = std::fs::remove_dir_all(&tmp);
+}
+
+#[test]
+fn requests_answered_without_tokens_wait_for_a_change_or_refresh() {
+    let (tmp, path, mut editor) = open_temp_file("nevi_semantic_failed", "struct A;\n");
+    let uri = path_to_uri(&path);
+    let version = editor.buffer().version();
+
+    // An error or a `null` result
+    editor.semantic_tokens_request();
+    assert!(!editor.set_semantic_tokens(&uri, version, None, None, None, &legend()));
+    assert_eq!(editor.semantic_tokens_request(), None);
+
+    editor.refresh_semantic_tokens();
+    assert_eq!(
+        editor.semantic_tokens_request(),
+        Some((path.clone(), None, version))
+    );
+    assert!(!editor.set_semantic_tokens(&uri, version, None, None, None, &legend()));
+    assert_eq!(editor.semantic_tokens_request(), None);
+
+    editor.replace_buffer_content("struct B;\n");
+    editor.maybe_update_syntax();
+    assert_eq!(
+        editor.semantic_tokens_request(),
+        Some((path.clone(), None, editor.buffer().version()))
+    );
+
+    let _ = std::fs::remove_dir_all(&tmp);
+}
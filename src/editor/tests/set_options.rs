@@ -0,0 +1,200 @@
+use crate::editor::{Editor, FileFormat};
+use crate::terminal::handle_key;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn char_key(ch: char) -> KeyEvent {
+    let modifiers = if ch.is_ascii_uppercase() {
+        KeyModifiers::SHIFT
+    } else {
+        KeyModifiers::NONE
+    };
+    KeyEvent::new(KeyCode::Char(ch), modifiers)
+}
+
+fn type_chars(editor: &mut Editor, chars: &str) {
+    for ch in chars.chars() {
+        handle_key(editor, char_key(ch));
+    }
+}
+
+fn ex(editor: &mut Editor, command: &str) {
+    type_chars(editor, ":");
+    type_chars(editor, command);
+    handle_key(editor, KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+}
+
+fn unique_temp_dir(prefix: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time")
+        .as_nanos();
+    std::env::temp_dir().join(format!("{prefix}_{}_{}", std::process::id(), nanos))
+}
+
+#[test]
+fn set_toggles_global_options_and_reports_queries() {
+    let mut editor = Editor::default();
+
+    ex(&mut editor, "set nonu rnu ts=2 so+=2");
+    assert!(!editor.settings.editor.line_numbers);
+    assert!(editor.settings.editor.relative_numbers);
+    assert_eq!(editor.settings.editor.tab_width, 2);
+    assert_eq!(editor.settings.editor.scroll_off, 10);
+
+    ex(&mut editor, "set wrap! number? tabstop");
+    assert!(editor.settings.editor.wrap);
+    assert_eq!(
+        editor.status_message.as_deref(),
+        Some("nonumber  tabstop=2")
+    );
+
+    ex(&mut editor, "set invcul noai");
+    assert!(editor.settings.editor.cursor_line);
+    assert!(!editor.settings.editor.auto_indent);
+}
+
+#[test]
+fn set_reports_vim_style_errors() {
+    let mut editor = Editor::default();
+
+    ex(&mut editor, "set bogus");
+    assert_eq!(
+        editor.status_message.as_deref(),
+        Some("E: Unknown option: bogus")
+    );
+
+    ex(&mut editor, "set ts=abc");
+    assert_eq!(
+        editor.status_message.as_deref(),
+        Some("E: Number required after =: ts=abc")
+    );
+
+    ex(&mut editor, "set nots");
+    assert_eq!(
+        editor.status_message.as_deref(),
+        Some("E: Unknown option: nots")
+    );
+
+    ex(&mut editor, "set ff=mac");
+    assert_eq!(
+        editor.status_message.as_deref(),
+        Some("E: Invalid argument: ff=mac")
+    );
+    assert_eq!(editor.settings.editor.tab_width, 4);
+}
+
+#[test]
+fn setlocal_overrides_only_the_current_buffer() {
+    let mut editor = Editor::default();
+    editor.replace_buffer_content("x\n");
+
+    ex(&mut editor, "setlocal sw=2 noet list");
+    assert_eq!(editor.shift_width(), 2);
+    assert!(!editor.expand_tab());
+    assert!(editor.list_mode());
+    assert_eq!(editor.settings.editor.shift_width, 0);
+    assert!(editor.settings.editor.expand_tab);
+
+    editor.open_virtual_read_only_buffer("[other]", "y\n", None);
+    assert_eq!(editor.shift_width(), editor.settings.editor.tab_width);
+    assert!(editor.expand_tab());
+    assert!(!editor.list_mode());
+}
+
+#[test]
+fn indent_follows_shiftwidth_and_expandtab() {
+    let mut editor = Editor::default();
+    editor.replace_buffer_content("a\n");
+
+    ex(&mut editor, "set sw=2");
+    type_chars(&mut editor, ">>");
+    assert_eq!(editor.buffer().content(), "  a\n");
+
+    ex(&mut editor, "set noexpandtab sw=0 ts=4");
+    type_chars(&mut editor, ">>");
+    assert_eq!(editor.buffer().content(), "\t  a\n");
+
+    type_chars(&mut editor, "<<<<");
+    assert_eq!(editor.buffer().content(), "a\n");
+}
+
+#[test]
+fn textwidth_breaks_lines_while_typing() {
+    let mut editor = Editor::default();
+    editor.replace_buffer_content("\n");
+
+    ex(&mut editor, "set tw=10");
+    type_chars(&mut editor, "i  one two three");
+    handle_key(&mut editor, KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+
+    assert_eq!(editor.buffer().content(), "  one two\n  three\n");
+}
+
+#[test]
+fn smartcase_option_applies_to_search() {
+    let mut editor = Editor::default();
+    editor.replace_buffer_content("Foo\nfoo\n");
+
+    ex(&mut editor, "setlocal ic");
+    type_chars(&mut editor, "/foo");
+    handle_key(
+        &mut editor,
+        KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE),
+    );
+    assert_eq!(editor.cursor.line, 1);
+    type_chars(&mut editor, "n");
+    assert_eq!(editor.cursor.line, 0);
+
+    ex(&mut editor, "setl scs");
+    type_chars(&mut editor, "/Foo");
+    handle_key(
+        &mut editor,
+        KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE),
+    );
+    assert_eq!(editor.cursor.line, 0);
+}
+
+#[test]
+fn set_all_opens_the_option_report() {
+    let mut editor = Editor::default();
+
+    ex(&mut editor, "setlocal tw=72");
+    ex(&mut editor, "set all");
+
+    assert_eq!(editor.buffer().display_name(), "[options]");
+    assert!(editor.buffer().is_read_only());
+    let report = editor.buffer().content();
+    assert!(report.contains("textwidth=72"));
+    assert!(report.contains("local"));
+    assert!(report.contains("noreadonly"));
+}
+
+#[test]
+fn fileformat_round_trips_crlf_files() {
+    let temp_dir = unique_temp_dir("nevi_fileformat");
+    std::fs::create_dir_all(&temp_dir).expect("create temp directory");
+    let path = temp_dir.join("dos.txt");
+    std::fs::write(&path, "one\r\ntwo\r\n").expect("write fixture");
+
+    let mut editor = Editor::default();
+    editor.open_file(path.clone()).expect("open fixture");
+    assert_eq!(editor.buffer().content(), "one\ntwo\n");
+    assert_eq!(editor.buffer().options.file_format, FileFormat::Dos);
+
+    editor.save().expect("save dos");
+    assert_eq!(std::fs::read(&path).expect("read"), b"one\r\ntwo\r\n");
+
+    ex(&mut editor, "set ff=unix");
+    assert!(editor.buffer().dirty);
+    editor.save().expect("save unix");
+    assert_eq!(std::fs::read(&path).expect("read"), b"one\ntwo\n");
+
+    ex(&mut editor, "set ro");
+    assert!(editor.buffer().is_read_only());
+    ex(&mut editor, "set noro");
+    assert!(!editor.buffer().is_read_only());
+
+    std::fs::remove_dir_all(temp_dir).expect("remove temp directory");
+}
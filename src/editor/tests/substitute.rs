@@ -0,0 +1,175 @@
+use crate::editor::Editor;
+use crate::terminal::handle_key;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+fn char_key(ch: char) -> KeyEvent {
+    let modifiers = if ch.is_ascii_uppercase() {
+        KeyModifiers::SHIFT
+    } else {
+        KeyModifiers::NONE
+    };
+    KeyEvent::new(KeyCode::Char(ch), modifiers)
+}
+
+fn type_chars(editor: &mut Editor, chars: &str) {
+    for ch in chars.chars() {
+        handle_key(editor, char_key(ch));
+    }
+}
+
+fn ex(editor: &mut Editor, command: &str) {
+    type_chars(editor, ":");
+    type_chars(editor, command);
+    handle_key(editor, KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+}
+
+#[test]
+fn numeric_range_substitute_expands_groups_and_undoes_as_one_change() {
+    let mut editor = Editor::default();
+    editor.replace_buffer_content("a1\nb2\nc3\nd4\n");
+
+    ex(&mut editor, r"2,3s/\(\w\)\(\d\)/\2\u\1/");
+
+    assert_eq!(editor.buffer().content(), "a1\n2B\n3C\nd4\n");
+    assert_eq!(editor.cursor.line, 2);
+    assert_eq!(
+        editor.status_message.as_deref(),
+        Some("2 substitutions on 2 lines")
+    );
+
+    type_chars(&mut editor, "u");
+    assert_eq!(editor.buffer().content(), "a1\nb2\nc3\nd4\n");
+}
+
+#[test]
+fn visual_colon_prefills_the_selection_range() {
+    let mut editor = Editor::default();
+    editor.replace_buffer_content("foo\nfoo foo\nfoo foo\nfoo\n");
+
+    type_chars(&mut editor, "jVj:");
+    assert_eq!(editor.command_line.input, "'<,'>");
+    type_chars(&mut editor, "s/foo/bar/g");
+    handle_key(
+        &mut editor,
+        KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE),
+    );
+
+    assert_eq!(editor.buffer().content(), "foo\nbar bar\nbar bar\nfoo\n");
+}
+
+#[test]
+fn pattern_addresses_and_line_breaks_in_replacement() {
+    let mut editor = Editor::default();
+    editor.replace_buffer_content("x\nstart\na, b\nend\na, b\n");
+
+    ex(&mut editor, r"/start/+1,/end/-1s/, /\r/");
+
+    assert_eq!(editor.buffer().content(), "x\nstart\na\nb\nend\na, b\n");
+}
+
+#[test]
+fn count_only_and_error_flags_leave_the_buffer_alone() {
+    let mut editor = Editor::default();
+    editor.replace_buffer_content("Foo foo\nbar\nfoo\n");
+
+    ex(&mut editor, "%s/foo//gn");
+    assert_eq!(
+        editor.status_message.as_deref(),
+        Some("2 matches on 2 lines")
+    );
+
+    ex(&mut editor, "%s/foo//gni");
+    assert_eq!(
+        editor.status_message.as_deref(),
+        Some("3 matches on 2 lines")
+    );
+
+    ex(&mut editor, "%s/missing/x/e");
+    assert_eq!(editor.status_message, None);
+    assert_eq!(editor.buffer().content(), "Foo foo\nbar\nfoo\n");
+}
+
+#[test]
+fn confirm_flag_asks_before_each_replacement() {
+    let mut editor = Editor::default();
+    editor.replace_buffer_content("a a\na\na\n");
+
+    ex(&mut editor, "%s/a/bb/gc");
+    assert!(editor.is_confirming_substitute());
+    assert_eq!(
+        editor.status_message.as_deref(),
+        Some("replace with bb (y/n/a/q/l)?")
+    );
+
+    type_chars(&mut editor, "yn");
+    assert_eq!((editor.cursor.line, editor.cursor.col), (1, 0));
+    type_chars(&mut editor, "a");
+
+    assert!(!editor.is_confirming_substitute());
+    assert_eq!(editor.buffer().content(), "bb a\nbb\nbb\n");
+    assert_eq!(
+        editor.status_message.as_deref(),
+        Some("3 substitutions on 3 lines")
+    );
+
+    type_chars(&mut editor, "u");
+    assert_eq!(editor.buffer().content(), "a a\na\na\n");
+}
+
+#[test]
+fn typing_substitute_previews_replacements_without_editing() {
+    let mut editor = Editor::default();
+    editor.set_size(80, 10);
+    editor.replace_buffer_content("foo one\nbar\nfoo two\n");
+    let version = editor.buffer().version();
+
+    type_chars(&mut editor, ":%s/foo");
+    assert!(editor.substitute_preview_buffer(0).is_none());
+    assert_eq!(editor.search_matches, vec![(0, 0, 3), (2, 0, 3)]);
+
+    type_chars(&mut editor, "/\\U&!/");
+    let preview = editor.substitute_preview_buffer(0).expect("preview");
+    assert_eq!(preview.content(), "FOO! one\nbar\nFOO! two\n");
+    assert_eq!(editor.search_matches, vec![(0, 0, 4), (2, 0, 4)]);
+
+    handle_key(&mut editor, KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+
+    assert!(editor.substitute_preview_buffer(0).is_none());
+    assert!(editor.search_matches.is_empty());
+    assert_eq!(editor.buffer().content(), "foo one\nbar\nfoo two\n");
+    assert_eq!(editor.buffer().version(), version);
+    assert!(!editor.undo_stack.can_undo());
+}
+
+#[test]
+fn substitute_preview_maps_lines_after_inserted_breaks() {
+    let mut editor = Editor::default();
+    editor.set_size(80, 10);
+    editor.replace_buffer_content("a,b\nc\n");
+
+    type_chars(&mut editor, ":1s/,/\\r/");
+    let preview = editor.substitute_preview_buffer(0).expect("preview");
+    assert_eq!(preview.content(), "a\nb\nc\n");
+
+    handle_key(
+        &mut editor,
+        KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE),
+    );
+    assert!(editor.substitute_preview_buffer(0).is_none());
+    assert_eq!(editor.buffer().content(), "a\nb\nc\n");
+}
+
+#[test]
+fn split_preview_lists_affected_lines_outside_the_window() {
+    let mut editor = Editor::default();
+    editor.settings.editor.inc_command = crate::config::IncCommand::Split;
+    editor.set_size(80, 6);
+    let content: String = (1..=30).map(|n| format!("line {n}\n")).collect();
+    editor.replace_buffer_content(&content);
+
+    type_chars(&mut editor, ":%s/line 2\\d/row/");
+
+    let listing = editor.substitute_preview_listing();
+    assert_eq!(listing.len(), 10);
+    assert_eq!(listing[0], (20, "row".to_string()));
+}
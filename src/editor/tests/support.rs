@@ -0,0 +1,47 @@
+//! Helpers shared by the editor tests
+
+use crate::editor::Editor;
+use crate::terminal::handle_key;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Create an empty directory of its own under the system temp directory
+pub(super) fn unique_temp_dir(prefix: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time")
+        .as_nanos();
+    let dir = std::env::temp_dir().join(format!("{prefix}_{}_{}", std::process::id(), nanos));
+    std::fs::create_dir_all(&dir).expect("create temp directory");
+    dir
+}
+
+/// Write `text` to `main.rs` in a new temp directory and open it in an
+/// 80x24 editor. Returns the directory, the file and the editor.
+pub(super) fn open_temp_file(prefix: &str, text: &str) -> (PathBuf, PathBuf, Editor) {
+    let tmp = unique_temp_dir(prefix);
+    let path = tmp.join("main.rs");
+    std::fs::write(&path, text).expect("write temp file");
+    let mut editor = Editor::default();
+    editor.set_size(80, 24);
+    editor.open_file(path.clone()).expect("open temp file");
+    (tmp, path, editor)
+}
+
+/// Type `keys` into the editor, `\x1b` being Escape
+pub(super) fn keys(editor: &mut Editor, keys: &str) {
+    for ch in keys.chars() {
+        let key = match ch {
+            '\x1b' => KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE),
+            _ => KeyEvent::new(KeyCode::Char(ch), KeyModifiers::NONE),
+        };
+        handle_key(editor, key);
+    }
+}
+
+/// Run `command` as typed on the `:` command line
+pub(super) fn ex(editor: &mut Editor, command: &str) {
+    keys(editor, &format!(":{command}"));
+    handle_key(editor, KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+}
@@ -0,0 +1,202 @@
+use super::support::unique_temp_dir;
+use crate::editor::{Editor, apply_edits_to_file};
+use crate::lsp::path_to_uri;
+use crate::lsp::types::{TextEdit, WorkspaceEdit, WorkspaceEditChange};
+use std::path::Path;
+
+fn insert(line: usize, col: usize, text: &str) -> TextEdit {
+    TextEdit {
+        start_line: line,
+        start_col: col,
+        end_line: line,
+        end_col: col,
+        new_text: text.to_string(),
+    }
+}
+
+fn edit(path: &Path, version: Option<i32>, edits: Vec<TextEdit>) -> WorkspaceEditChange {
+    WorkspaceEditChange::Edit {
+        uri: path_to_uri(&path.to_path_buf()),
+        version,
+        edits,
+    }
+}
+
+#[test]
+fn apply_edits_to_file_treats_columns_as_utf16_offsets() {
+    let tmp = unique_temp_dir("nevi_disk_lsp_edit");
+    let path = tmp.join("unicode.txt");
+    std::fs::write(&path, "a😀b\n").expect("write file");
+
+    let count = apply_edits_to_file(
+        &path,
+        &[TextEdit {
+            start_line: 0,
+            start_col: 3,
+            end_line: 0,
+            end_col: 4,
+            new_text: "X".to_string(),
+        }],
+    )
+    .expect("apply edits");
+
+    assert_eq!(count, 1);
+    assert_eq!(std::fs::read_to_string(&path).expect("read file"), "a😀X\n");
+
+    let _ = std::fs::remove_dir_all(&tmp);
+}
+
+#[test]
+fn workspace_edit_updates_open_buffers_and_files_on_disk() {
+    let tmp = unique_temp_dir("nevi_workspace_edit");
+    let open = tmp.join("open.rs");
+    let closed = tmp.join("closed.rs");
+    let current = tmp.join("current.rs");
+    std::fs::write(&open, "fn a() {}\n").expect("write open");
+    std::fs::write(&closed, "fn b() {}\n").expect("write closed");
+    std::fs::write(&current, "fn c() {}\n").expect("write current");
+
+    let mut editor = Editor::default();
+    editor.open_file(open.clone()).expect("open buffer");
+    editor.open_file(current.clone()).expect("open current");
+
+    let summary = editor
+        .apply_workspace_edit(
+            &WorkspaceEdit {
+                changes: vec![
+                    edit(&open, Some(1), vec![insert(0, 0, "pub ")]),
+                    edit(&closed, None, vec![insert(0, 0, "pub ")]),
+                ],
+            },
+            |_| Some(1),
+        )
+        .expect("apply edit");
+
+    assert_eq!(summary.edits, 2);
+    assert_eq!(summary.files, 2);
+    assert_eq!(summary.changed_buffers, vec![open.clone()]);
+    assert_eq!(editor.buffer().content(), "fn c() {}\n");
+    assert_eq!(
+        std::fs::read_to_string(&closed).expect("read closed"),
+        "pub fn b() {}\n"
+    );
+    // The open buffer changed in memory only, with its own undo history.
+    assert_eq!(
+        std::fs::read_to_string(&open).expect("read open"),
+        "fn a() {}\n"
+    );
+    assert_eq!(
+        editor.open_buffer_content(&open).as_deref(),
+        Some("pub fn a() {}\n")
+    );
+
+    editor.switch_to_buffer(0);
+    assert!(editor.buffer().dirty);
+    editor.undo();
+    assert_eq!(editor.buffer().content(), "fn a() {}\n");
+
+    let _ = std::fs::remove_dir_all(&tmp);
+}
+
+#[test]
+fn workspace_edit_against_a_stale_version_changes_nothing() {
+    let tmp = unique_temp_dir("nevi_workspace_edit_stale");
+    let first = tmp.join("first.rs");
+    let second = tmp.join("second.rs");
+    std::fs::write(&first, "one\n").expect("write first");
+    std::fs::write(&second, "two\n").expect("write second");
+
+    let mut editor = Editor::default();
+    editor.open_file(second.clone()).expect("open buffer");
+
+    let err = editor
+        .apply_workspace_edit(
+            &WorkspaceEdit {
+                changes: vec![
+                    edit(&first, None, vec![insert(0, 0, "x")]),
+                    edit(&second, Some(3), vec![insert(0, 0, "y")]),
+                ],
+            },
+            |path| (path == second.as_path()).then_some(4),
+        )
+        .unwrap_err();
+
+    assert!(err.contains("version 4 != 3"), "{err}");
+    assert_eq!(
+        std::fs::read_to_string(&first).expect("read first"),
+        "one\n"
+    );
+    assert_eq!(editor.buffer().content(), "two\n");
+
+    let _ = std::fs::remove_dir_all(&tmp);
+}
+
+#[test]
+fn workspace_edit_creates_renames_and_deletes_files() {
+    let tmp = unique_temp_dir("nevi_workspace_edit_files");
+    let old = tmp.join("old.rs");
+    let new = tmp.join("nested/new.rs");
+    let created = tmp.join("created.rs");
+    let doomed = tmp.join("doomed.rs");
+    std::fs::write(&old, "fn main() {}\n").expect("write old");
+    std::fs::write(&doomed, "").expect("write doomed");
+
+    let mut editor = Editor::default();
+    editor.open_file(old.clone()).expect("open buffer");
+
+    let summary = editor
+        .apply_workspace_edit(
+            &WorkspaceEdit {
+                changes: vec![
+                    WorkspaceEditChange::CreateFile {
+                        uri: path_to_uri(&created),
+                        overwrite: false,
+                        ignore_if_exists: false,
+                    },
+                    edit(&created, None, vec![insert(0, 0, "mod old;\n")]),
+                    WorkspaceEditChange::RenameFile {
+                        old_uri: path_to_uri(&old),
+                        new_uri: path_to_uri(&new),
+                        overwrite: false,
+                        ignore_if_exists: false,
+                    },
+                    WorkspaceEditChange::DeleteFile {
+                        uri: path_to_uri(&doomed),
+                        recursive: false,
+                        ignore_if_not_exists: false,
+                    },
+                ],
+            },
+            |_| None,
+        )
+        .expect("apply edit");
+
+    assert_eq!(
+        std::fs::read_to_string(&created).expect("read created"),
+        "mod old;\n"
+    );
+    assert!(!old.exists());
+    assert_eq!(
+        std::fs::read_to_string(&new).expect("read new"),
+        "fn main() {}\n"
+    );
+    assert!(!doomed.exists());
+    assert_eq!(summary.renamed_buffers, vec![(old.clone(), new.clone())]);
+    assert_eq!(editor.buffer().path.as_deref(), Some(new.as_path()));
+
+    let err = editor
+        .apply_workspace_edit(
+            &WorkspaceEdit {
+                changes: vec![WorkspaceEditChange::CreateFile {
+                    uri: path_to_uri(&created),
+                    overwrite: false,
+                    ignore_if_exists: false,
+                }],
+            },
+            |_| None,
+        )
+        .unwrap_err();
+    assert!(err.ends_with("already exists"), "{err}");
+
+    let _ = std::fs::remove_dir_all(&tmp);
+}
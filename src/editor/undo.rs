@@ -230,6 +230,13 @@ impl UndoStack {
         }
     }
 
+    /// Changes recorded so far in the open undo group.
+    pub fn pending_changes(&self) -> &[Change] {
+        self.current_entry
+            .as_ref()
+            .map_or(&[], |entry| entry.changes.as_slice())
+    }
+
     /// Keep group finalization from replacing an operation-specific redo cursor.
     pub fn prefer_current_cursor_after(&mut self, line: usize, col: usize) {
         if let Some(entry) = self.current_entry.as_mut() {
@@ -0,0 +1,271 @@
+//! Applying language-server workspace edits (`workspace/applyEdit`) across
+//! open buffers and files on disk.
+
+use std::path::{Path, PathBuf};
+
+use super::{Cursor, Editor, comparable_file_path};
+use crate::lsp::types::{TextEdit, WorkspaceEdit, WorkspaceEditChange};
+
+/// What a workspace edit changed
+#[derive(Debug, Default)]
+pub struct WorkspaceEditSummary {
+    /// Text edits applied
+    pub edits: usize,
+    /// Files touched by text edits or file operations
+    pub files: usize,
+    /// Open buffers whose text changed
+    pub changed_buffers: Vec<PathBuf>,
+    /// Open buffers that followed a file rename, as (old, new) paths
+    pub renamed_buffers: Vec<(PathBuf, PathBuf)>,
+}
+
+impl Editor {
+    /// Apply a workspace edit in order. Text edits to open buffers go through
+    /// `apply_text_edits`, so each buffer gets one undo group; other files are
+    /// edited on disk. `document_version` gives the version last sent to the
+    /// server for an open document: versioned edits made against any other
+    /// version are refused before anything changes. Otherwise the first
+    /// failing change stops the edit, leaving earlier changes applied.
+    pub fn apply_workspace_edit(
+        &mut self,
+        edit: &WorkspaceEdit,
+        document_version: impl Fn(&Path) -> Option<i32>,
+    ) -> Result<WorkspaceEditSummary, String> {
+        for change in &edit.changes {
+            if let WorkspaceEditChange::Edit {
+                uri,
+                version: Some(version),
+                ..
+            } = change
+            {
+                let path = uri_path(uri)?;
+                if let Some(current) = document_version(&path) {
+                    if current != *version {
+                        return Err(format!(
+                            "{} changed since the edit was made (version {} != {})",
+                            path.display(),
+                            current,
+                            version
+                        ));
+                    }
+                }
+            }
+        }
+
+        let mut summary = WorkspaceEditSummary::default();
+        for change in &edit.changes {
+            match change {
+                WorkspaceEditChange::Edit { uri, edits, .. } => {
+                    if edits.is_empty() {
+                        continue;
+                    }
+                    let path = uri_path(uri)?;
+                    match self.buffer_index_for_path(&path) {
+                        Some(idx) => {
+                            self.apply_text_edits_to_buffer(idx, edits);
+                            if !summary.changed_buffers.contains(&path) {
+                                summary.changed_buffers.push(path);
+                            }
+                        }
+                        None => {
+                            apply_edits_to_file(&path, edits)
+                                .map_err(|e| format!("{}: {}", path.display(), e))?;
+                        }
+                    }
+                    summary.edits += edits.len();
+                    summary.files += 1;
+                }
+                WorkspaceEditChange::CreateFile {
+                    uri,
+                    overwrite,
+                    ignore_if_exists,
+                } => {
+                    let path = uri_path(uri)?;
+                    if path.exists() && !overwrite {
+                        if *ignore_if_exists {
+                            continue;
+                        }
+                        return Err(format!("{} already exists", path.display()));
+                    }
+                    if let Some(parent) = path.parent() {
+                        std::fs::create_dir_all(parent)
+                            .map_err(|e| format!("{}: {}", parent.display(), e))?;
+                    }
+                    std::fs::write(&path, "").map_err(|e| format!("{}: {}", path.display(), e))?;
+                    summary.files += 1;
+                }
+                WorkspaceEditChange::RenameFile {
+                    old_uri,
+                    new_uri,
+                    overwrite,
+                    ignore_if_exists,
+                } => {
+                    let old_path = uri_path(old_uri)?;
+                    let new_path = uri_path(new_uri)?;
+                    if new_path.exists() && !overwrite {
+                        if *ignore_if_exists {
+                            continue;
+                        }
+                        return Err(format!("{} already exists", new_path.display()));
+                    }
+
+                    // Resolve buffer paths before the old ones stop existing.
+                    let old_comparable = comparable_file_path(&old_path);
+                    let moved: Vec<(usize, PathBuf, PathBuf)> = self
+                        .buffers
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(idx, buffer)| {
+                            let path = buffer.path.as_ref()?;
+                            let rest = comparable_file_path(path)
+                                .strip_prefix(&old_comparable)
+                                .ok()?
+                                .to_path_buf();
+                            let target = if rest.as_os_str().is_empty() {
+                                new_path.clone()
+                            } else {
+                                new_path.join(rest)
+                            };
+                            Some((idx, path.clone(), target))
+                        })
+                        .collect();
+
+                    if let Some(parent) = new_path.parent() {
+                        std::fs::create_dir_all(parent)
+                            .map_err(|e| format!("{}: {}", parent.display(), e))?;
+                    }
+                    std::fs::rename(&old_path, &new_path)
+                        .map_err(|e| format!("{}: {}", old_path.display(), e))?;
+
+                    for (idx, old, new) in moved {
+                        self.buffers[idx].set_file_path(new.clone());
+                        self.detect_buffer_syntax(idx);
+                        summary.renamed_buffers.push((old, new));
+                    }
+                    summary.files += 1;
+                }
+                WorkspaceEditChange::DeleteFile {
+                    uri,
+                    recursive,
+                    ignore_if_not_exists,
+                } => {
+                    let path = uri_path(uri)?;
+                    if !path.exists() {
+                        if *ignore_if_not_exists {
+                            continue;
+                        }
+                        return Err(format!("{} does not exist", path.display()));
+                    }
+                    let result = if !path.is_dir() {
+                        std::fs::remove_file(&path)
+                    } else if *recursive {
+                        std::fs::remove_dir_all(&path)
+                    } else {
+                        std::fs::remove_dir(&path)
+                    };
+                    result.map_err(|e| format!("{}: {}", path.display(), e))?;
+                    summary.files += 1;
+                }
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Text of the open buffer for `path`, if there is one
+    pub fn open_buffer_content(&self, path: &Path) -> Option<String> {
+        self.buffer_index_for_path(path)
+            .map(|idx| self.buffers[idx].content())
+    }
+
+    pub(super) fn buffer_index_for_path(&self, path: &Path) -> Option<usize> {
+        let comparable = comparable_file_path(path);
+        self.buffers.iter().position(|buffer| {
+            buffer
+                .path
+                .as_deref()
+                .is_some_and(|buffer_path| comparable_file_path(buffer_path) == comparable)
+        })
+    }
+
+    /// `apply_text_edits` on any buffer, recording the undo group in that
+    /// buffer's own history.
+    fn apply_text_edits_to_buffer(&mut self, idx: usize, edits: &[TextEdit]) {
+        if idx == self.current_buffer_idx {
+            self.apply_text_edits(edits);
+            return;
+        }
+
+        let current_idx = self.current_buffer_idx;
+        let cursor = self.cursor;
+        self.save_current_undo_stack();
+        self.current_buffer_idx = idx;
+        self.load_current_undo_stack();
+        // Undo puts the cursor back where the edit starts in that buffer.
+        self.cursor = edits
+            .iter()
+            .map(|edit| Cursor::new(edit.start_line, edit.start_col))
+            .min_by_key(|cursor| (cursor.line, cursor.col))
+            .unwrap_or_default();
+
+        self.apply_text_edits(edits);
+
+        self.save_current_undo_stack();
+        self.current_buffer_idx = current_idx;
+        self.load_current_undo_stack();
+        self.cursor = cursor;
+    }
+}
+
+fn uri_path(uri: &str) -> Result<PathBuf, String> {
+    crate::lsp::uri_to_path(uri).ok_or_else(|| format!("unsupported URI: {}", uri))
+}
+
+/// Apply LSP text edits to a file on disk
+/// Reads the file, applies edits in reverse order, and writes back
+pub fn apply_edits_to_file(path: &Path, edits: &[TextEdit]) -> anyhow::Result<usize> {
+    use std::fs;
+
+    let content = fs::read_to_string(path)?;
+    let mut text = ropey::Rope::from_str(&content);
+
+    // Sort edits by position (reverse order) so we can apply from end to start
+    let mut sorted_edits: Vec<&TextEdit> = edits.iter().collect();
+    sorted_edits.sort_by(|a, b| match b.end_line.cmp(&a.end_line) {
+        std::cmp::Ordering::Equal => b.end_col.cmp(&a.end_col),
+        other => other,
+    });
+
+    // Apply each edit
+    for edit in &sorted_edits {
+        let start = utf16_position_to_rope_char(&text, edit.start_line, edit.start_col);
+        let end = utf16_position_to_rope_char(&text, edit.end_line, edit.end_col);
+
+        if start <= end && end <= text.len_chars() {
+            if start < end {
+                text.remove(start..end);
+            }
+            if !edit.new_text.is_empty() {
+                text.insert(start, &edit.new_text);
+            }
+        }
+    }
+
+    fs::write(path, text.to_string())?;
+
+    Ok(sorted_edits.len())
+}
+
+fn utf16_position_to_rope_char(text: &ropey::Rope, line: usize, utf16_col: usize) -> usize {
+    if line >= text.len_lines() {
+        return text.len_chars();
+    }
+
+    let line_start = text.line_to_char(line);
+    let line_text = text.line(line).to_string();
+    let line_text = line_text.trim_end_matches('\n');
+    let line_len = line_text.chars().count();
+    let col = crate::copilot::utf16_to_utf8_col(line_text, utf16_col as u32).min(line_len);
+
+    line_start + col
+}
@@ -39,6 +39,8 @@ pub enum FinderMode {
     GitChanges,
     Terminals,
     Keymaps,
+    Symbols,
+    WorkspaceSymbols,
 }
 
 /// Input mode for the fuzzy finder (like vim modes)
@@ -250,6 +252,8 @@ pub struct FuzzyFinder {
     grep_search_generation: u64,
     /// Receiver for the currently running async grep search
     grep_search_receiver: Option<Receiver<GrepSearchMessage>>,
+    /// Query changed in workspace symbols mode; the main loop re-queries the server
+    pub workspace_symbol_query_pending: bool,
 }
 
 impl FuzzyFinder {
@@ -279,6 +283,7 @@ impl FuzzyFinder {
             grep_search_running: false,
             grep_search_generation: 0,
             grep_search_receiver: None,
+            workspace_symbol_query_pending: false,
         }
     }
 
@@ -309,6 +314,7 @@ impl FuzzyFinder {
             grep_search_running: false,
             grep_search_generation: 0,
             grep_search_receiver: None,
+            workspace_symbol_query_pending: false,
         }
     }
 
@@ -557,6 +563,63 @@ impl FuzzyFinder {
         self.populated = true;
     }
 
+    /// Open the finder on the current buffer's symbol outline
+    /// Takes symbol items pre-formatted by the editor, in outline order
+    pub fn open_symbols(&mut self, symbol_items: Vec<FinderItem>) {
+        self.mode = FinderMode::Symbols;
+        self.input_mode = FinderInputMode::Insert;
+        self.query.clear();
+        self.cursor = 0;
+        self.selected = 0;
+        self.scroll_offset = 0;
+        self.clear_preview_cache();
+        self.cancel_grep_search();
+        self.preview_enabled = true;
+
+        self.items = symbol_items;
+        self.filtered = (0..self.items.len()).collect();
+        self.populated = true;
+    }
+
+    /// Open the finder in workspace symbols mode. Results arrive from the
+    /// language server through `set_workspace_symbols`.
+    pub fn open_workspace_symbols(&mut self) {
+        self.mode = FinderMode::WorkspaceSymbols;
+        self.input_mode = FinderInputMode::Insert;
+        self.query.clear();
+        self.cursor = 0;
+        self.selected = 0;
+        self.scroll_offset = 0;
+        self.clear_preview_cache();
+        self.cancel_grep_search();
+        self.preview_enabled = true;
+
+        self.items.clear();
+        self.filtered.clear();
+        self.workspace_symbol_query_pending = true;
+        self.populated = true;
+    }
+
+    /// Show server results for `query`. Results for an older query are
+    /// dropped; the server has already filtered and ranked them.
+    pub fn set_workspace_symbols(&mut self, query: &str, items: Vec<FinderItem>) -> bool {
+        if self.mode != FinderMode::WorkspaceSymbols || query != self.query {
+            return false;
+        }
+
+        self.items = items;
+        for item in &mut self.items {
+            item.match_indices = self.matcher.match_indices(query, &item.display);
+        }
+        self.filtered = (0..self.items.len()).collect();
+        self.selected = 0;
+        self.scroll_offset = 0;
+        if self.preview_enabled {
+            self.preview_update_pending = true;
+        }
+        true
+    }
+
     /// Enter normal mode (for j/k navigation)
     pub fn enter_normal_mode(&mut self) {
         self.input_mode = FinderInputMode::Normal;
@@ -765,6 +828,11 @@ impl FuzzyFinder {
             item.match_indices.clear();
         }
 
+        if self.mode == FinderMode::WorkspaceSymbols {
+            // Filter the current results locally until the server answers
+            self.workspace_symbol_query_pending = true;
+        }
+
         match self.mode {
             FinderMode::Grep => {
                 // In grep mode, defer search to debounce mechanism
@@ -971,6 +1039,17 @@ impl FuzzyFinder {
                 | FinderMode::Harpoon
                 | FinderMode::Marks
                 | FinderMode::GitChanges
+                | FinderMode::Symbols
+                | FinderMode::WorkspaceSymbols
+        )
+    }
+
+    /// Whether the preview follows the selected item's line rather than
+    /// showing the top of the file
+    pub fn previews_around_line(&self) -> bool {
+        matches!(
+            self.mode,
+            FinderMode::Grep | FinderMode::Symbols | FinderMode::WorkspaceSymbols
         )
     }
 
@@ -999,7 +1078,7 @@ impl FuzzyFinder {
         let selected_line = selected_item.line;
 
         let should_reload = self.preview_path.as_ref() != Some(&selected_path)
-            || (self.mode == FinderMode::Grep && self.preview_line != selected_line);
+            || (self.previews_around_line() && self.preview_line != selected_line);
 
         // Check if we need to load new content
         if should_reload {
@@ -1026,7 +1105,7 @@ impl FuzzyFinder {
             // matches still preview correctly without loading the whole file.
             const MAX_PREVIEW_LINES: usize = 150;
             const GREP_PREVIEW_CONTEXT_BEFORE: usize = 10;
-            let start_line = if self.mode == FinderMode::Grep {
+            let start_line = if self.previews_around_line() {
                 selected_line
                     .map(|line_num| {
                         line_num
@@ -1634,4 +1713,63 @@ mod tests {
                 .all(|idx| *idx >= "src/main.rs:12: ".chars().count())
         );
     }
+
+    #[test]
+    fn workspace_symbols_keep_only_results_for_the_current_query() {
+        let mut finder = FuzzyFinder::new();
+        finder.open_workspace_symbols();
+        assert!(finder.workspace_symbol_query_pending);
+        finder.workspace_symbol_query_pending = false;
+
+        finder.insert_char('p');
+        finder.insert_char('a');
+        assert!(finder.workspace_symbol_query_pending);
+
+        let stale = vec![FinderItem::new(
+            "Parser | src/p.rs:1".to_string(),
+            PathBuf::from("src/p.rs"),
+        )];
+        assert!(!finder.set_workspace_symbols("p", stale));
+        assert!(finder.items.is_empty());
+
+        let fresh = vec![
+            FinderItem::new(
+                "parse | src/lib.rs:4".to_string(),
+                PathBuf::from("src/lib.rs"),
+            )
+            .with_line(4),
+            FinderItem::new(
+                "Span | src/span.rs:1".to_string(),
+                PathBuf::from("src/span.rs"),
+            ),
+        ];
+        assert!(finder.set_workspace_symbols("pa", fresh));
+        // The server already filtered; everything it returned is shown.
+        assert_eq!(finder.filtered, vec![0, 1]);
+        assert_eq!(finder.items[0].match_indices, vec![0, 1]);
+    }
+
+    #[test]
+    fn symbol_preview_loads_window_around_the_symbol() {
+        let root = unique_temp_dir("finder_symbol_preview");
+        fs::create_dir_all(&root).unwrap();
+        let path = root.join("lib.rs");
+        write_numbered_file(&path, &[120]);
+
+        let mut finder = FuzzyFinder::new();
+        finder.open_symbols(vec![
+            FinderItem::new("needle".to_string(), path.clone()).with_line(120),
+        ]);
+        assert!(finder.preview_enabled);
+        finder.update_preview_content();
+
+        let symbol_idx = finder
+            .preview_content
+            .iter()
+            .position(|line| line.contains("needle"))
+            .expect("preview should include the symbol line");
+        assert_eq!(finder.preview_line_offset + symbol_idx + 1, 120);
+
+        let _ = fs::remove_dir_all(root);
+    }
 }
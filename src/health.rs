@@ -39,6 +39,7 @@ pub struct HealthReportInput {
     pub keymap: KeymapHealth,
     pub external_tools: ExternalToolsHealth,
     pub large_file: Option<LargeFileHealth>,
+    pub grammars: GrammarsHealth,
     pub profile_enabled: bool,
     pub profile_log_path: PathBuf,
     pub profile_log_status: ProfileLogStatus,
@@ -54,6 +55,21 @@ pub struct LargeFileHealth {
     pub syntax_degraded: bool,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct GrammarsHealth {
+    pub dir: Option<PathBuf>,
+    pub grammars: Vec<GrammarHealth>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrammarHealth {
+    pub name: String,
+    /// Extensions (`.zig`) and shebangs (`#!lua`) mapped in languages.toml
+    pub file_types: Vec<String>,
+    /// Load details on success, the reason on failure
+    pub status: Result<String, String>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct KeymapHealth {
     pub leader: String,
@@ -76,7 +92,8 @@ pub struct KeymapMappingHealth {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LspServerHealth {
-    pub language: &'static str,
+    /// Language of a built-in server, or the name of a user-defined one
+    pub language: String,
     pub enabled: bool,
     pub command: String,
 }
@@ -295,6 +312,33 @@ pub fn build_health_report(input: &HealthReportInput) -> String {
     );
     report.push('\n');
 
+    report.push_str("## Tree-sitter Grammars\n");
+    report.push_str(&format!(
+        "- Runtime grammars: {}\n",
+        path_label(input.grammars.dir.as_ref())
+    ));
+    if input.grammars.grammars.is_empty() {
+        report.push_str("- Installed: none\n");
+    }
+    for grammar in &input.grammars.grammars {
+        let file_types = if grammar.file_types.is_empty() {
+            "not mapped in languages.toml".to_string()
+        } else {
+            grammar.file_types.join(", ")
+        };
+        match &grammar.status {
+            Ok(details) => report.push_str(&format!(
+                "- {}: loaded ({details}; files: {file_types})\n",
+                grammar.name
+            )),
+            Err(error) => report.push_str(&format!(
+                "- {}: failed ({error}; files: {file_types})\n",
+                grammar.name
+            )),
+        }
+    }
+    report.push('\n');
+
     report.push_str("## LSP\n");
     report.push_str(&format!(
         "- LSP: {}\n",
@@ -376,6 +420,7 @@ pub fn collect_health_report(
             command_exists_on_path,
         ),
         large_file,
+        grammars: grammars_health(languages_config),
         profile_enabled: profile_enabled_from_env(),
         profile_log_status: inspect_profile_log(&profile_log_path),
         profile_log_path,
@@ -384,6 +429,60 @@ pub fn collect_health_report(
     })
 }
 
+/// Every grammar directory plus every languages.toml entry mapping files to
+/// a runtime grammar, with whether it loaded.
+fn grammars_health(languages_config: &crate::config::LanguagesConfig) -> GrammarsHealth {
+    let mut names = crate::syntax::installed_grammars();
+    names.extend(
+        languages_config
+            .runtime_language_names()
+            .into_iter()
+            .map(str::to_string),
+    );
+    names.sort();
+    names.dedup();
+
+    let grammars = names
+        .into_iter()
+        .map(|name| {
+            let mut file_types = Vec::new();
+            if let Some(config) = languages_config.languages.get(&name) {
+                file_types.extend(
+                    config
+                        .extensions
+                        .iter()
+                        .map(|ext| format!(".{}", ext.trim_start_matches('.'))),
+                );
+                file_types.extend(config.shebangs.iter().map(|shebang| format!("#!{shebang}")));
+            }
+            let status = crate::syntax::load_runtime_grammar(&name).map(|language| {
+                let mut queries = vec!["highlights"];
+                if language.injections.is_some() {
+                    queries.push("injections");
+                }
+                if language.indents.is_some() {
+                    queries.push("indents");
+                }
+                format!(
+                    "ABI {}, queries: {}",
+                    language.language.abi_version(),
+                    queries.join(", ")
+                )
+            });
+            GrammarHealth {
+                name,
+                file_types,
+                status,
+            }
+        })
+        .collect();
+
+    GrammarsHealth {
+        dir: crate::syntax::grammars_dir(),
+        grammars,
+    }
+}
+
 pub fn keymap_health_from_settings(settings: &crate::config::KeymapSettings) -> KeymapHealth {
     let normal_mappings = keymap_entry_health(&settings.normal);
     let mut warnings = Vec::new();
@@ -489,24 +588,28 @@ where
     }
 
     let servers = &settings.lsp.servers;
-    let mut commands = vec![
-        command_tool_health_if_enabled("rust", &servers.rust, is_command_available),
-        command_tool_health_if_enabled("typescript", &servers.typescript, is_command_available),
-        command_tool_health_if_enabled("javascript", &servers.javascript, is_command_available),
-        command_tool_health_if_enabled("css", &servers.css, is_command_available),
-        command_tool_health_if_enabled("json", &servers.json, is_command_available),
-        command_tool_health_if_enabled("toml", &servers.toml, is_command_available),
-        command_tool_health_if_enabled("markdown", &servers.markdown, is_command_available),
-        command_tool_health_if_enabled("html", &servers.html, is_command_available),
-        command_tool_health_if_enabled("python", &servers.python, is_command_available),
-        command_tool_health_if_enabled("php", &servers.php, is_command_available),
-        command_tool_health_if_enabled("go", &servers.go, is_command_available),
-        command_tool_health_if_enabled("ruby", &servers.ruby, is_command_available),
-        command_tool_health_if_enabled("shell", &servers.shell, is_command_available),
-    ]
-    .into_iter()
-    .flatten()
-    .collect::<Vec<_>>();
+    let mut commands =
+        vec![
+            command_tool_health_if_enabled("rust", &servers.rust, is_command_available),
+            command_tool_health_if_enabled("typescript", &servers.typescript, is_command_available),
+            command_tool_health_if_enabled("javascript", &servers.javascript, is_command_available),
+            command_tool_health_if_enabled("css", &servers.css, is_command_available),
+            command_tool_health_if_enabled("json", &servers.json, is_command_available),
+            command_tool_health_if_enabled("toml", &servers.toml, is_command_available),
+            command_tool_health_if_enabled("markdown", &servers.markdown, is_command_available),
+            command_tool_health_if_enabled("html", &servers.html, is_command_available),
+            command_tool_health_if_enabled("python", &servers.python, is_command_available),
+            command_tool_health_if_enabled("php", &servers.php, is_command_available),
+            command_tool_health_if_enabled("go", &servers.go, is_command_available),
+            command_tool_health_if_enabled("ruby", &servers.ruby, is_command_available),
+            command_tool_health_if_enabled("shell", &servers.shell, is_command_available),
+        ]
+        .into_iter()
+        .chain(servers.additional.iter().map(|(name, config)| {
+            command_tool_health_if_enabled(name, config, is_command_available)
+        }))
+        .flatten()
+        .collect::<Vec<_>>();
     commands.sort_by(|a, b| a.label.cmp(&b.label).then(a.command.cmp(&b.command)));
     commands
 }
@@ -659,7 +762,7 @@ fn inspect_profile_log(path: &PathBuf) -> ProfileLogStatus {
 
 fn lsp_server_health(settings: &crate::config::Settings) -> Vec<LspServerHealth> {
     let servers = &settings.lsp.servers;
-    vec![
+    let mut health = vec![
         server_health("rust", &servers.rust),
         server_health("typescript", &servers.typescript),
         server_health("javascript", &servers.javascript),
@@ -673,15 +776,19 @@ fn lsp_server_health(settings: &crate::config::Settings) -> Vec<LspServerHealth>
         server_health("go", &servers.go),
         server_health("ruby", &servers.ruby),
         server_health("shell", &servers.shell),
-    ]
+    ];
+    health.extend(
+        servers
+            .additional
+            .iter()
+            .map(|(name, config)| server_health(name, config)),
+    );
+    health
 }
 
-fn server_health(
-    language: &'static str,
-    config: &crate::config::LspServerConfig,
-) -> LspServerHealth {
+fn server_health(language: &str, config: &crate::config::LspServerConfig) -> LspServerHealth {
     LspServerHealth {
-        language,
+        language: language.to_string(),
         enabled: config.enabled,
         command: config.effective_command().to_string(),
     }
@@ -761,12 +868,13 @@ mod tests {
             keymap: default_keymap_health(),
             external_tools: default_external_tools_health(),
             large_file: None,
+            grammars: GrammarsHealth::default(),
             profile_enabled: false,
             profile_log_path: PathBuf::from(PROFILE_LOG_PATH),
             profile_log_status: ProfileLogStatus::Missing,
             lsp_enabled: true,
             lsp_servers: vec![LspServerHealth {
-                language: "rust",
+                language: "rust".to_string(),
                 enabled: true,
                 command: "rust-analyzer".to_string(),
             }],
@@ -784,6 +892,51 @@ mod tests {
         assert!(report.contains("rust: enabled (rust-analyzer)"));
     }
 
+    #[test]
+    fn health_report_lists_runtime_grammars() {
+        let report = build_health_report(&HealthReportInput {
+            config_path: None,
+            config_status: FileCheckStatus::Unavailable,
+            languages_path: None,
+            languages_status: FileCheckStatus::Unavailable,
+            keymap: default_keymap_health(),
+            external_tools: default_external_tools_health(),
+            large_file: None,
+            grammars: GrammarsHealth {
+                dir: Some(PathBuf::from("/home/me/.config/nevi/grammars")),
+                grammars: vec![
+                    GrammarHealth {
+                        name: "lua".to_string(),
+                        file_types: vec![".lua".to_string(), "#!lua".to_string()],
+                        status: Err(
+                            "no .so or .dylib parser in /home/me/.config/nevi/grammars/lua"
+                                .to_string(),
+                        ),
+                    },
+                    GrammarHealth {
+                        name: "zig".to_string(),
+                        file_types: vec![".zig".to_string()],
+                        status: Ok("ABI 14, queries: highlights, indents".to_string()),
+                    },
+                ],
+            },
+            profile_enabled: false,
+            profile_log_path: PathBuf::from(PROFILE_LOG_PATH),
+            profile_log_status: ProfileLogStatus::Missing,
+            lsp_enabled: false,
+            lsp_servers: Vec::new(),
+        });
+
+        assert!(report.contains("## Tree-sitter Grammars"));
+        assert!(report.contains("- Runtime grammars: /home/me/.config/nevi/grammars"));
+        assert!(report.contains(
+            "- lua: failed (no .so or .dylib parser in /home/me/.config/nevi/grammars/lua; files: .lua, #!lua)"
+        ));
+        assert!(
+            report.contains("- zig: loaded (ABI 14, queries: highlights, indents; files: .zig)")
+        );
+    }
+
     #[test]
     fn health_report_lists_profile_summary_metrics() {
         let report = build_health_report(&HealthReportInput {
@@ -794,6 +947,7 @@ mod tests {
             keymap: default_keymap_health(),
             external_tools: default_external_tools_health(),
             large_file: None,
+            grammars: GrammarsHealth::default(),
             profile_enabled: true,
             profile_log_path: PathBuf::from(PROFILE_LOG_PATH),
             profile_log_status: ProfileLogStatus::Summary(vec![ProfileMetricSummary {
@@ -825,6 +979,7 @@ mod tests {
             keymap: default_keymap_health(),
             external_tools: default_external_tools_health(),
             large_file: None,
+            grammars: GrammarsHealth::default(),
             profile_enabled: false,
             profile_log_path: PathBuf::from(PROFILE_LOG_PATH),
             profile_log_status: ProfileLogStatus::Summary(vec![ProfileMetricSummary {
@@ -855,6 +1010,7 @@ mod tests {
             keymap: default_keymap_health(),
             external_tools: default_external_tools_health(),
             large_file: None,
+            grammars: GrammarsHealth::default(),
             profile_enabled: false,
             profile_log_path: PathBuf::from(PROFILE_LOG_PATH),
             profile_log_status: ProfileLogStatus::Missing,
@@ -892,6 +1048,7 @@ mod tests {
             keymap: keymap_health_from_settings(&settings.keymap),
             external_tools: default_external_tools_health(),
             large_file: None,
+            grammars: GrammarsHealth::default(),
             profile_enabled: false,
             profile_log_path: PathBuf::from(PROFILE_LOG_PATH),
             profile_log_status: ProfileLogStatus::Missing,
@@ -921,6 +1078,7 @@ mod tests {
             languages_status: FileCheckStatus::Unavailable,
             keymap: default_keymap_health(),
             large_file: None,
+            grammars: GrammarsHealth::default(),
             profile_enabled: false,
             profile_log_path: PathBuf::from(PROFILE_LOG_PATH),
             profile_log_status: ProfileLogStatus::Missing,
@@ -975,6 +1133,7 @@ mod tests {
                             timeout: 5,
                         }),
                         tab_width: Some(2),
+                        ..Default::default()
                     },
                 ),
                 (
@@ -986,6 +1145,7 @@ mod tests {
                             timeout: 5,
                         }),
                         tab_width: None,
+                        ..Default::default()
                     },
                 ),
             ]),
@@ -3,7 +3,10 @@
 //! This module provides tree-sitter based indentation calculation for
 //! JavaScript, TypeScript, TSX, and JSX files.
 
-use tree_sitter::Tree;
+use std::collections::HashSet;
+
+use streaming_iterator::StreamingIterator;
+use tree_sitter::{Query, QueryCursor, Tree};
 
 /// Node types that increase indentation for their children
 const INDENT_NODES: &[&str] = &[
@@ -116,6 +119,58 @@ pub fn calculate_indent(tree: &Tree, source: &str, cursor_byte: usize, tab_width
     line_indent
 }
 
+/// Calculate the indentation for a new line from an `indents.scm` query, as
+/// shipped with runtime grammars.
+///
+/// Each enclosing node captured as `@indent` or `@indent.begin` adds one
+/// level; nodes starting on the same line count once, so `foo({` indents a
+/// single level. Falls back to the current line's indent when no captured
+/// node encloses the cursor.
+pub fn calculate_indent_with_query(
+    tree: &Tree,
+    query: &Query,
+    source: &str,
+    cursor_byte: usize,
+    tab_width: usize,
+) -> usize {
+    let indent_captures: Vec<u32> = ["indent", "indent.begin"]
+        .iter()
+        .filter_map(|name| query.capture_index_for_name(name))
+        .collect();
+    if indent_captures.is_empty() {
+        return get_line_indent_at_byte(source, cursor_byte);
+    }
+
+    let mut cursor = QueryCursor::new();
+    cursor.set_byte_range(cursor_byte.saturating_sub(1)..cursor_byte + 1);
+    let mut indent_nodes = HashSet::new();
+    let mut captures = cursor.captures(query, tree.root_node(), source.as_bytes());
+    while let Some((query_match, capture_idx)) = captures.next() {
+        let capture = query_match.captures[*capture_idx];
+        if indent_captures.contains(&capture.index) {
+            indent_nodes.insert(capture.node.id());
+        }
+    }
+
+    let mut rows = HashSet::new();
+    let mut current = find_node_at_position(tree.root_node(), cursor_byte);
+    while let Some(node) = current {
+        if indent_nodes.contains(&node.id())
+            && cursor_byte > node.start_byte()
+            && cursor_byte < node.end_byte()
+        {
+            rows.insert(node.start_position().row);
+        }
+        current = node.parent();
+    }
+
+    if rows.is_empty() {
+        get_line_indent_at_byte(source, cursor_byte)
+    } else {
+        rows.len() * tab_width
+    }
+}
+
 /// Calculate the expected indent for a closing bracket based on its matching opener.
 ///
 /// # Arguments
@@ -336,6 +391,45 @@ mod tests {
         parser.parse(source, None).unwrap()
     }
 
+    fn parse_rust(source: &str) -> Tree {
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_rust::LANGUAGE.into())
+            .unwrap();
+        parser.parse(source, None).unwrap()
+    }
+
+    #[test]
+    fn test_indents_query_counts_enclosing_captures_once_per_line() {
+        let query = Query::new(
+            &tree_sitter_rust::LANGUAGE.into(),
+            "(block) @indent.begin\n(arguments) @indent",
+        )
+        .unwrap();
+
+        let source = "fn main() {\n    if x {\n    }\n}\n";
+        let tree = parse_rust(source);
+        let after_outer = source.find('{').unwrap() + 1;
+        let after_inner = source.rfind("x {").unwrap() + 3;
+        assert_eq!(
+            calculate_indent_with_query(&tree, &query, source, after_outer, 4),
+            4
+        );
+        assert_eq!(
+            calculate_indent_with_query(&tree, &query, source, after_inner, 4),
+            8
+        );
+        assert_eq!(calculate_indent_with_query(&tree, &query, source, 0, 4), 0);
+
+        let source = "fn main() { foo(a, b); }\n";
+        let tree = parse_rust(source);
+        let in_args = source.find("(a").unwrap() + 1;
+        assert_eq!(
+            calculate_indent_with_query(&tree, &query, source, in_args, 2),
+            2
+        );
+    }
+
     fn parse_ts(source: &str) -> Tree {
         let mut parser = tree_sitter::Parser::new();
         parser
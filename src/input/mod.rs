@@ -80,6 +80,7 @@ pub enum Operator {
     Indent,     // >
     Dedent,     // <
     AutoIndent, // =
+    Format,     // gq
 }
 
 /// Case transformation operators
@@ -160,8 +161,8 @@ pub enum KeyAction {
     ScrollTop,
     /// Scroll cursor to bottom of screen (zb)
     ScrollBottom,
-    /// Repeat last change (.)
-    RepeatLastChange,
+    /// Repeat last change (.), with the count typed before it, if any
+    RepeatLastChange(Option<usize>),
     /// Paste after cursor
     PasteAfter(usize),
     /// Paste before cursor
@@ -237,6 +238,8 @@ pub enum KeyAction {
     GotoDeclaration,
     /// Go to implementation (gI)
     GotoImplementation,
+    /// Go to type definition (gy)
+    GotoTypeDefinition,
     /// Open file under cursor (gf)
     GotoFile,
     /// Open URL under cursor (gx)
@@ -883,6 +886,14 @@ impl InputState {
             }
 
             // Macros
+            (KeyModifiers::NONE, KeyCode::Char('q'))
+                if self.pending_operator == Some(Operator::Format) =>
+            {
+                // gqq - format line
+                let final_count = self.combined_count();
+                self.reset();
+                KeyAction::OperatorLine(Operator::Format, final_count)
+            }
             (KeyModifiers::NONE, KeyCode::Char('q')) => {
                 // 'q' starts recording (waiting for register name)
                 // Note: stopping recording (q while recording) is handled in terminal/mod.rs
@@ -1116,8 +1127,9 @@ impl InputState {
             }
             (KeyModifiers::NONE, KeyCode::Char('.')) => {
                 // . - repeat last change
+                let count = self.count;
                 self.reset();
-                KeyAction::RepeatLastChange
+                KeyAction::RepeatLastChange(count)
             }
             (KeyModifiers::SHIFT, KeyCode::Char('~'))
             | (KeyModifiers::NONE, KeyCode::Char('~')) => {
@@ -1314,6 +1326,11 @@ impl InputState {
                 self.reset();
                 KeyAction::GotoImplementation
             }
+            // gy - go to type definition (LSP)
+            ('g', KeyModifiers::NONE, KeyCode::Char('y')) => {
+                self.reset();
+                KeyAction::GotoTypeDefinition
+            }
             // gf - open file under cursor
             ('g', KeyModifiers::NONE, KeyCode::Char('f')) => {
                 self.reset();
@@ -1389,6 +1406,17 @@ impl InputState {
                 self.pending_comment = true;
                 KeyAction::Pending
             }
+            // gq - LSP range format (waits for motion), gqgq - format line
+            ('g', KeyModifiers::NONE, KeyCode::Char('q')) => {
+                if self.pending_operator == Some(Operator::Format) {
+                    let final_count = self.combined_count();
+                    self.reset();
+                    KeyAction::OperatorLine(Operator::Format, final_count)
+                } else {
+                    self.set_operator(Operator::Format);
+                    KeyAction::Pending
+                }
+            }
             // gu - lowercase (waits for motion)
             ('g', KeyModifiers::NONE, KeyCode::Char('u')) => {
                 self.pending_case_operator = Some(CaseOperator::Lowercase);
@@ -2324,6 +2352,23 @@ mod tests {
         assert_operator_line(&[key('='), key('=')], Operator::AutoIndent, 1);
         assert_operator_motion(&[key('='), key('j')], Operator::AutoIndent, Motion::Down, 1);
         assert_operator_line(&[key('2'), key('='), key('=')], Operator::AutoIndent, 2);
+        assert_operator_line(&[key('g'), key('q'), key('q')], Operator::Format, 1);
+        assert_operator_line(
+            &[key('g'), key('q'), key('g'), key('q')],
+            Operator::Format,
+            1,
+        );
+        assert_operator_motion(
+            &[key('g'), key('q'), key('}')],
+            Operator::Format,
+            Motion::ParagraphForward,
+            1,
+        );
+        assert_operator_line(
+            &[key('3'), key('g'), key('q'), key('q')],
+            Operator::Format,
+            3,
+        );
 
         match run(&[key('x')]) {
             KeyAction::DeleteChar(1) => {}
@@ -2367,9 +2412,13 @@ mod tests {
             other => panic!("expected counted newline ReplaceChar, got {:?}", other),
         }
         match run(&[key('.')]) {
-            KeyAction::RepeatLastChange => {}
+            KeyAction::RepeatLastChange(None) => {}
             other => panic!("expected RepeatLastChange, got {:?}", other),
         }
+        match run(&[key('1'), key('2'), key('.')]) {
+            KeyAction::RepeatLastChange(Some(12)) => {}
+            other => panic!("expected counted RepeatLastChange, got {:?}", other),
+        }
         match run(&[key('u')]) {
             KeyAction::Undo => {}
             other => panic!("expected Undo, got {:?}", other),
@@ -2535,6 +2584,10 @@ mod tests {
             format!("{:?}", run(&[key('g'), shift('I')])),
             "GotoImplementation"
         );
+        assert_eq!(
+            format!("{:?}", run(&[key('g'), key('y')])),
+            "GotoTypeDefinition"
+        );
         assert_eq!(format!("{:?}", run(&[key('g'), key('x')])), "OpenUrl");
         match run(&[key('g'), key('f')]) {
             KeyAction::GotoFile => {}
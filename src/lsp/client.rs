@@ -11,7 +11,9 @@ use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use anyhow::{Result, anyhow};
-use lsp_types::request::{GotoDeclarationParams, GotoImplementationParams};
+use lsp_types::request::{
+    GotoDeclarationParams, GotoImplementationParams, GotoTypeDefinitionParams,
+};
 use lsp_types::{
     ClientCapabilities, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
     DidOpenTextDocumentParams, GotoDefinitionParams, HoverParams, InitializeParams,
@@ -21,10 +23,15 @@ use lsp_types::{
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 
+use super::protocol_log::{LogDirection, LoggedStdin, ProtocolLog};
 use super::types::{
-    CodeActionItem, CompletionItem, CompletionKind, Diagnostic, DiagnosticSeverity, Location,
-    LspNavigationTargetKind, LspNotification, ParameterInfo, RequestKind, SignatureHelpResult,
-    SignatureInfo, TextEdit,
+    CodeActionItem, CodeLens, CompletionItem, CompletionKind, ContentChange, Diagnostic,
+    DiagnosticRelatedInformation, DiagnosticReport, DiagnosticSeverity, DiagnosticTag,
+    DocumentHighlight, DocumentHighlightKind, HierarchyDirection, HierarchyItem, InlayHint,
+    InlayHintKind, Location, LspCommand, LspNavigationTargetKind, LspNotification, ParameterInfo,
+    RequestKind, SemanticTokensCapability, SemanticTokensData, SemanticTokensEdit,
+    SemanticTokensLegend, ServerCapabilities, SignatureHelpResult, SignatureInfo, SymbolItem,
+    TextEdit, TextRange, WorkspaceEdit, WorkspaceEditChange,
 };
 #[cfg(test)]
 use super::watched_files::WATCHED_FILES_METHOD;
@@ -35,7 +42,10 @@ use super::watched_files::{
 /// Shared pending requests map - maps request ID to request kind
 /// This is shared between the request sender and response reader threads
 pub type PendingRequests = Arc<Mutex<HashMap<u64, RequestKind>>>;
-pub type SharedStdin = Arc<Mutex<ChildStdin>>;
+pub type SharedStdin = Arc<Mutex<LoggedStdin<ChildStdin>>>;
+/// The server's `settings` from the config, read by the response reader to
+/// answer `workspace/configuration` and replaced when the config changes
+pub type SharedSettings = Arc<Mutex<Value>>;
 
 #[cfg(not(test))]
 const WATCHER_COMMAND_REPLY_TIMEOUT: Duration = Duration::from_secs(2);
@@ -160,10 +170,42 @@ fn client_capabilities() -> ClientCapabilities {
                 link_support: Some(false),
                 ..Default::default()
             }),
+            type_definition: Some(lsp_types::GotoCapability {
+                link_support: Some(false),
+                ..Default::default()
+            }),
+            document_symbol: Some(lsp_types::DocumentSymbolClientCapabilities {
+                hierarchical_document_symbol_support: Some(true),
+                ..Default::default()
+            }),
+            call_hierarchy: Some(lsp_types::CallHierarchyClientCapabilities::default()),
+            type_hierarchy: Some(lsp_types::TypeHierarchyClientCapabilities::default()),
+            inlay_hint: Some(lsp_types::InlayHintClientCapabilities::default()),
+            document_highlight: Some(lsp_types::DocumentHighlightClientCapabilities::default()),
+            selection_range: Some(lsp_types::SelectionRangeClientCapabilities::default()),
+            code_lens: Some(lsp_types::CodeLensClientCapabilities {
+                dynamic_registration: Some(false),
+            }),
+            range_formatting: Some(lsp_types::DocumentRangeFormattingClientCapabilities::default()),
+            on_type_formatting: Some(
+                lsp_types::DocumentOnTypeFormattingClientCapabilities::default(),
+            ),
+            semantic_tokens: Some(semantic_tokens_client_capabilities()),
             publish_diagnostics: Some(lsp_types::PublishDiagnosticsClientCapabilities {
                 related_information: Some(true),
+                tag_support: Some(lsp_types::TagSupport {
+                    value_set: vec![
+                        lsp_types::DiagnosticTag::UNNECESSARY,
+                        lsp_types::DiagnosticTag::DEPRECATED,
+                    ],
+                }),
+                code_description_support: Some(true),
                 ..Default::default()
             }),
+            diagnostic: Some(lsp_types::DiagnosticClientCapabilities {
+                dynamic_registration: Some(false),
+                related_document_support: Some(false),
+            }),
             code_action: Some(lsp_types::CodeActionClientCapabilities {
                 code_action_literal_support: Some(lsp_types::CodeActionLiteralSupport {
                     code_action_kind: lsp_types::CodeActionKindLiteralSupport {
@@ -194,10 +236,37 @@ fn client_capabilities() -> ClientCapabilities {
             ..Default::default()
         }),
         workspace: Some(lsp_types::WorkspaceClientCapabilities {
+            apply_edit: Some(true),
+            workspace_edit: Some(lsp_types::WorkspaceEditClientCapabilities {
+                document_changes: Some(true),
+                resource_operations: Some(vec![
+                    lsp_types::ResourceOperationKind::Create,
+                    lsp_types::ResourceOperationKind::Rename,
+                    lsp_types::ResourceOperationKind::Delete,
+                ]),
+                failure_handling: Some(lsp_types::FailureHandlingKind::Abort),
+                ..Default::default()
+            }),
             did_change_watched_files: Some(lsp_types::DidChangeWatchedFilesClientCapabilities {
                 dynamic_registration: Some(true),
                 relative_pattern_support: Some(true),
             }),
+            symbol: Some(lsp_types::WorkspaceSymbolClientCapabilities::default()),
+            inlay_hint: Some(lsp_types::InlayHintWorkspaceClientCapabilities {
+                refresh_support: Some(true),
+            }),
+            semantic_tokens: Some(lsp_types::SemanticTokensWorkspaceClientCapabilities {
+                refresh_support: Some(true),
+            }),
+            diagnostic: Some(lsp_types::DiagnosticWorkspaceClientCapabilities {
+                refresh_support: Some(true),
+            }),
+            code_lens: Some(lsp_types::CodeLensWorkspaceClientCapabilities {
+                refresh_support: Some(true),
+            }),
+            execute_command: Some(lsp_types::ExecuteCommandClientCapabilities {
+                dynamic_registration: Some(false),
+            }),
             ..Default::default()
         }),
         window: Some(lsp_types::WindowClientCapabilities {
@@ -213,9 +282,70 @@ fn client_capabilities() -> ClientCapabilities {
     }
 }
 
+/// Whole-document tokens (plain or as a delta), one line each, with the
+/// standard token types plus the `mutable` modifier rust-analyzer reports.
+fn semantic_tokens_client_capabilities() -> lsp_types::SemanticTokensClientCapabilities {
+    use lsp_types::{SemanticTokenModifier, SemanticTokenType};
+
+    lsp_types::SemanticTokensClientCapabilities {
+        dynamic_registration: Some(false),
+        requests: lsp_types::SemanticTokensClientCapabilitiesRequests {
+            range: Some(false),
+            full: Some(lsp_types::SemanticTokensFullOptions::Delta { delta: Some(true) }),
+        },
+        token_types: vec![
+            SemanticTokenType::NAMESPACE,
+            SemanticTokenType::TYPE,
+            SemanticTokenType::CLASS,
+            SemanticTokenType::ENUM,
+            SemanticTokenType::INTERFACE,
+            SemanticTokenType::STRUCT,
+            SemanticTokenType::TYPE_PARAMETER,
+            SemanticTokenType::PARAMETER,
+            SemanticTokenType::VARIABLE,
+            SemanticTokenType::PROPERTY,
+            SemanticTokenType::ENUM_MEMBER,
+            SemanticTokenType::EVENT,
+            SemanticTokenType::FUNCTION,
+            SemanticTokenType::METHOD,
+            SemanticTokenType::MACRO,
+            SemanticTokenType::KEYWORD,
+            SemanticTokenType::MODIFIER,
+            SemanticTokenType::COMMENT,
+            SemanticTokenType::STRING,
+            SemanticTokenType::NUMBER,
+            SemanticTokenType::REGEXP,
+            SemanticTokenType::OPERATOR,
+            SemanticTokenType::DECORATOR,
+        ],
+        token_modifiers: vec![
+            SemanticTokenModifier::DECLARATION,
+            SemanticTokenModifier::DEFINITION,
+            SemanticTokenModifier::READONLY,
+            SemanticTokenModifier::STATIC,
+            SemanticTokenModifier::DEPRECATED,
+            SemanticTokenModifier::ABSTRACT,
+            SemanticTokenModifier::ASYNC,
+            SemanticTokenModifier::MODIFICATION,
+            SemanticTokenModifier::DOCUMENTATION,
+            SemanticTokenModifier::DEFAULT_LIBRARY,
+            SemanticTokenModifier::new("mutable"),
+        ],
+        formats: vec![lsp_types::TokenFormat::RELATIVE],
+        overlapping_token_support: Some(false),
+        multiline_token_support: Some(false),
+        server_cancel_support: Some(true),
+        augments_syntax_tokens: Some(true),
+    }
+}
+
 impl LspClient {
-    /// Spawn a new LSP server process
-    pub fn spawn(command: &str, args: &[String]) -> Result<(Self, PendingRequests, SharedStdin)> {
+    /// Spawn a new LSP server process, recording what is written to it in `log`
+    pub fn spawn(
+        command: &str,
+        args: &[String],
+        log: ProtocolLog,
+    ) -> Result<(Self, PendingRequests, SharedStdin)> {
         let resolved_command = crate::command_resolver::resolve_command(command);
         let mut process = Command::new(&resolved_command)
             .args(args)
@@ -229,7 +359,8 @@ impl LspClient {
             .stdin
             .take()
             .ok_or_else(|| anyhow!("Failed to get stdin"))?;
-        let stdin = Arc::new(Mutex::new(stdin));
+        log.set_pid(process.id());
+        let stdin = Arc::new(Mutex::new(LoggedStdin::new(stdin, log)));
         let stdin_clone = stdin.clone();
 
         // Create the shared pending requests map
@@ -259,8 +390,13 @@ impl LspClient {
         self.process.stderr.take()
     }
 
-    /// Send initialize request
-    pub fn initialize(&mut self, root_path: &std::path::Path) -> Result<u64> {
+    /// Send initialize request, with the configured `initialization_options`
+    /// merged over the built-in ones
+    pub fn initialize(
+        &mut self,
+        root_path: &std::path::Path,
+        initialization_options: Option<Value>,
+    ) -> Result<u64> {
         let root_uri = lsp_types::Url::from_file_path(root_path).map_err(|_| {
             anyhow!(
                 "Failed to convert root path to URI: {}",
@@ -272,7 +408,10 @@ impl LspClient {
             process_id: Some(std::process::id()),
             root_path: Some(root_path.to_string_lossy().to_string()),
             root_uri: Some(root_uri.clone()),
-            initialization_options: initialization_options_for_command(&self.command),
+            initialization_options: merge_initialization_options(
+                initialization_options_for_command(&self.command),
+                initialization_options,
+            ),
             capabilities: client_capabilities(),
             trace: None,
             workspace_folders: Some(vec![WorkspaceFolder {
@@ -302,6 +441,14 @@ impl LspClient {
         self.send_notification("initialized", json!({}))
     }
 
+    /// Push changed settings (`workspace/didChangeConfiguration`)
+    pub fn did_change_configuration(&mut self, settings: &Value) -> Result<()> {
+        self.send_notification(
+            "workspace/didChangeConfiguration",
+            json!({ "settings": settings }),
+        )
+    }
+
     /// Send shutdown request
     pub fn shutdown(&mut self) -> Result<u64> {
         self.send_request("shutdown", Value::Null, RequestKind::Shutdown)
@@ -332,17 +479,18 @@ impl LspClient {
     }
 
     /// Notify server that a document changed
-    pub fn did_change(&mut self, uri: &str, version: i32, text: &str) -> Result<()> {
+    pub fn did_change(
+        &mut self,
+        uri: &str,
+        version: i32,
+        changes: Vec<ContentChange>,
+    ) -> Result<()> {
         let params = DidChangeTextDocumentParams {
             text_document: VersionedTextDocumentIdentifier {
                 uri: lsp_types::Url::parse(uri)?,
                 version,
             },
-            content_changes: vec![TextDocumentContentChangeEvent {
-                range: None,
-                range_length: None,
-                text: text.to_string(),
-            }],
+            content_changes: changes.into_iter().map(content_change_event).collect(),
         };
         self.send_notification("textDocument/didChange", serde_json::to_value(params)?)
     }
@@ -457,6 +605,29 @@ impl LspClient {
         )
     }
 
+    /// Request go-to-type-definition
+    pub fn goto_type_definition(&mut self, uri: &str, line: u32, character: u32) -> Result<u64> {
+        let params = GotoTypeDefinitionParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: lsp_types::Url::parse(uri)?,
+                },
+                position: lsp_types::Position { line, character },
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+        self.send_request(
+            "textDocument/typeDefinition",
+            serde_json::to_value(params)?,
+            RequestKind::TypeDefinition {
+                uri: uri.to_string(),
+                line,
+                character,
+            },
+        )
+    }
+
     /// Request hover information
     pub fn hover(&mut self, uri: &str, line: u32, character: u32) -> Result<u64> {
         let params = HoverParams {
@@ -525,6 +696,81 @@ impl LspClient {
         )
     }
 
+    /// Request formatting of `range` (UTF-16 columns)
+    pub fn range_formatting(
+        &mut self,
+        uri: &str,
+        range: TextRange,
+        tab_size: u32,
+        insert_spaces: bool,
+        buffer_version: u64,
+    ) -> Result<u64> {
+        let position = |line: usize, character: usize| lsp_types::Position {
+            line: line as u32,
+            character: character as u32,
+        };
+        let params = lsp_types::DocumentRangeFormattingParams {
+            text_document: TextDocumentIdentifier {
+                uri: lsp_types::Url::parse(uri)?,
+            },
+            range: lsp_types::Range {
+                start: position(range.start_line, range.start_col),
+                end: position(range.end_line, range.end_col),
+            },
+            options: lsp_types::FormattingOptions {
+                tab_size,
+                insert_spaces,
+                ..Default::default()
+            },
+            work_done_progress_params: Default::default(),
+        };
+        self.send_request(
+            "textDocument/rangeFormatting",
+            serde_json::to_value(params)?,
+            RequestKind::RangeFormatting {
+                uri: uri.to_string(),
+                buffer_version,
+            },
+        )
+    }
+
+    /// Request formatting after `ch` was typed, with the cursor at
+    /// `line`/`character`
+    #[allow(clippy::too_many_arguments)]
+    pub fn on_type_formatting(
+        &mut self,
+        uri: &str,
+        line: u32,
+        character: u32,
+        ch: char,
+        tab_size: u32,
+        insert_spaces: bool,
+        buffer_version: u64,
+    ) -> Result<u64> {
+        let params = lsp_types::DocumentOnTypeFormattingParams {
+            text_document_position: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: lsp_types::Url::parse(uri)?,
+                },
+                position: lsp_types::Position { line, character },
+            },
+            ch: ch.to_string(),
+            options: lsp_types::FormattingOptions {
+                tab_size,
+                insert_spaces,
+                ..Default::default()
+            },
+        };
+        self.send_request(
+            "textDocument/onTypeFormatting",
+            serde_json::to_value(params)?,
+            RequestKind::OnTypeFormatting {
+                uri: uri.to_string(),
+                buffer_version,
+            },
+        )
+    }
+
     /// Request find references
     pub fn references(&mut self, uri: &str, line: u32, character: u32) -> Result<u64> {
         let params = lsp_types::ReferenceParams {
@@ -673,95 +919,479 @@ impl LspClient {
         Ok(id)
     }
 
-    /// Send a JSON-RPC notification
-    fn send_notification(&mut self, method: &str, params: Value) -> Result<()> {
-        let notification = JsonRpcNotification {
-            jsonrpc: "2.0",
-            method: method.to_string(),
-            params: if params.is_null() { None } else { Some(params) },
+    /// Request the symbol outline of a document
+    pub fn document_symbols(&mut self, uri: &str) -> Result<u64> {
+        let params = lsp_types::DocumentSymbolParams {
+            text_document: TextDocumentIdentifier {
+                uri: lsp_types::Url::parse(uri)?,
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
         };
-
-        self.send_message(&serde_json::to_string(&notification)?)
+        self.send_request(
+            "textDocument/documentSymbol",
+            serde_json::to_value(params)?,
+            RequestKind::DocumentSymbols {
+                uri: uri.to_string(),
+            },
+        )
     }
 
-    /// Send a raw message with Content-Length header
-    fn send_message(&mut self, content: &str) -> Result<()> {
-        let message = format!("Content-Length: {}\r\n\r\n{}", content.len(), content);
-        let mut stdin = self
-            .stdin
-            .lock()
-            .map_err(|_| anyhow!("Failed to lock stdin"))?;
-        stdin.write_all(message.as_bytes())?;
-        stdin.flush()?;
-        Ok(())
+    /// Search symbols across the workspace
+    pub fn workspace_symbols(&mut self, query: &str) -> Result<u64> {
+        let params = lsp_types::WorkspaceSymbolParams {
+            query: query.to_string(),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+        self.send_request(
+            "workspace/symbol",
+            serde_json::to_value(params)?,
+            RequestKind::WorkspaceSymbols {
+                query: query.to_string(),
+            },
+        )
     }
-}
 
-fn initialization_options_for_command(command: &str) -> Option<Value> {
-    let command_name = std::path::Path::new(command)
-        .file_name()
-        .and_then(|name| name.to_str())
-        .unwrap_or(command);
+    /// Prepare a call or type hierarchy for the symbol at a position
+    pub fn prepare_hierarchy(
+        &mut self,
+        uri: &str,
+        line: u32,
+        character: u32,
+        direction: HierarchyDirection,
+    ) -> Result<u64> {
+        let params = TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier {
+                uri: lsp_types::Url::parse(uri)?,
+            },
+            position: lsp_types::Position { line, character },
+        };
+        let method = if direction.is_call() {
+            "textDocument/prepareCallHierarchy"
+        } else {
+            "textDocument/prepareTypeHierarchy"
+        };
+        self.send_request(
+            method,
+            serde_json::to_value(params)?,
+            RequestKind::PrepareHierarchy {
+                uri: uri.to_string(),
+                direction,
+            },
+        )
+    }
 
-    if command_name != "typescript-language-server"
-        && command_name != "typescript-language-server.cmd"
-    {
-        return None;
+    /// Request the callers, callees, supertypes or subtypes of an item
+    /// returned by `prepare_hierarchy`
+    pub fn hierarchy_children(
+        &mut self,
+        direction: HierarchyDirection,
+        node: u64,
+        item: Value,
+    ) -> Result<u64> {
+        let method = match direction {
+            HierarchyDirection::IncomingCalls => "callHierarchy/incomingCalls",
+            HierarchyDirection::OutgoingCalls => "callHierarchy/outgoingCalls",
+            HierarchyDirection::Supertypes => "typeHierarchy/supertypes",
+            HierarchyDirection::Subtypes => "typeHierarchy/subtypes",
+        };
+        self.send_request(
+            method,
+            json!({ "item": item }),
+            RequestKind::HierarchyChildren { direction, node },
+        )
     }
 
-    Some(json!({
-        "preferences": {
-            "includeCompletionsForModuleExports": true,
-            "includeCompletionsForImportStatements": true,
-            "includeCompletionsWithSnippetText": false,
-            "includeAutomaticOptionalChainCompletions": true
-        }
-    }))
-}
+    /// Request inlay hints for lines `start_line..end_line`
+    pub fn inlay_hints(
+        &mut self,
+        uri: &str,
+        start_line: u32,
+        end_line: u32,
+        buffer_version: u64,
+    ) -> Result<u64> {
+        let params = lsp_types::InlayHintParams {
+            work_done_progress_params: Default::default(),
+            text_document: TextDocumentIdentifier {
+                uri: lsp_types::Url::parse(uri)?,
+            },
+            range: lsp_types::Range {
+                start: lsp_types::Position::new(start_line, 0),
+                end: lsp_types::Position::new(end_line, 0),
+            },
+        };
+        self.send_request(
+            "textDocument/inlayHint",
+            serde_json::to_value(params)?,
+            RequestKind::InlayHints {
+                uri: uri.to_string(),
+                start_line,
+                end_line,
+                buffer_version,
+            },
+        )
+    }
 
-fn diagnostic_to_lsp_diagnostic(diagnostic: &Diagnostic) -> lsp_types::Diagnostic {
-    lsp_types::Diagnostic {
-        range: lsp_types::Range {
-            start: lsp_types::Position {
-                line: diagnostic.line as u32,
-                character: diagnostic.col_start as u32,
+    /// Request the occurrences of the symbol at a position
+    pub fn document_highlight(
+        &mut self,
+        uri: &str,
+        line: u32,
+        character: u32,
+        buffer_version: u64,
+    ) -> Result<u64> {
+        let params = lsp_types::DocumentHighlightParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: lsp_types::Url::parse(uri)?,
+                },
+                position: lsp_types::Position { line, character },
             },
-            end: lsp_types::Position {
-                line: diagnostic.end_line as u32,
-                character: diagnostic.col_end as u32,
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+        self.send_request(
+            "textDocument/documentHighlight",
+            serde_json::to_value(params)?,
+            RequestKind::DocumentHighlight {
+                uri: uri.to_string(),
+                line,
+                character,
+                buffer_version,
             },
-        },
-        severity: Some(match diagnostic.severity {
-            super::types::DiagnosticSeverity::Error => lsp_types::DiagnosticSeverity::ERROR,
-            super::types::DiagnosticSeverity::Warning => lsp_types::DiagnosticSeverity::WARNING,
-            super::types::DiagnosticSeverity::Information => {
-                lsp_types::DiagnosticSeverity::INFORMATION
-            }
-            super::types::DiagnosticSeverity::Hint => lsp_types::DiagnosticSeverity::HINT,
-        }),
-        code: diagnostic.code.as_ref().map(|code| match code {
-            super::types::DiagnosticCode::Number(n) => lsp_types::NumberOrString::Number(*n as i32),
-            super::types::DiagnosticCode::String(s) => lsp_types::NumberOrString::String(s.clone()),
-        }),
-        message: diagnostic.message.clone(),
-        source: diagnostic.source.clone(),
-        ..Default::default()
+        )
     }
-}
 
-impl Drop for LspClient {
-    fn drop(&mut self) {
-        // Try to gracefully shutdown, but don't block if stdin is locked
-        // This prevents deadlock if another thread holds the lock
-        if let Ok(mut stdin) = self.stdin.try_lock() {
-            let notification = serde_json::json!({
-                "jsonrpc": "2.0",
-                "method": "exit",
-                "params": null
-            });
-            if let Ok(content) = serde_json::to_string(&notification) {
-                let message = format!("Content-Length: {}\r\n\r\n{}", content.len(), content);
-                let _ = stdin.write_all(message.as_bytes());
+    /// Request the ranges enclosing a position
+    pub fn selection_range(
+        &mut self,
+        uri: &str,
+        line: u32,
+        character: u32,
+        buffer_version: u64,
+    ) -> Result<u64> {
+        let params = lsp_types::SelectionRangeParams {
+            text_document: TextDocumentIdentifier {
+                uri: lsp_types::Url::parse(uri)?,
+            },
+            positions: vec![lsp_types::Position { line, character }],
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+        self.send_request(
+            "textDocument/selectionRange",
+            serde_json::to_value(params)?,
+            RequestKind::SelectionRange {
+                uri: uri.to_string(),
+                buffer_version,
+            },
+        )
+    }
+
+    /// Request the code lenses of a document
+    pub fn code_lens(&mut self, uri: &str, buffer_version: u64) -> Result<u64> {
+        let params = lsp_types::CodeLensParams {
+            text_document: TextDocumentIdentifier {
+                uri: lsp_types::Url::parse(uri)?,
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+        self.send_request(
+            "textDocument/codeLens",
+            serde_json::to_value(params)?,
+            RequestKind::CodeLens {
+                uri: uri.to_string(),
+                buffer_version,
+            },
+        )
+    }
+
+    /// Resolve the command of a code lens, sent back as the server gave it
+    pub fn code_lens_resolve(
+        &mut self,
+        uri: &str,
+        buffer_version: u64,
+        index: usize,
+        lens: Value,
+    ) -> Result<u64> {
+        self.send_request(
+            "codeLens/resolve",
+            lens,
+            RequestKind::CodeLensResolve {
+                uri: uri.to_string(),
+                buffer_version,
+                index,
+            },
+        )
+    }
+
+    /// Request semantic tokens for the whole document, as a delta against
+    /// `previous_result_id` when given
+    pub fn semantic_tokens(
+        &mut self,
+        uri: &str,
+        previous_result_id: Option<&str>,
+        buffer_version: u64,
+    ) -> Result<u64> {
+        let text_document = TextDocumentIdentifier {
+            uri: lsp_types::Url::parse(uri)?,
+        };
+        let kind = RequestKind::SemanticTokens {
+            uri: uri.to_string(),
+            previous_result_id: previous_result_id.map(str::to_string),
+            buffer_version,
+        };
+        match previous_result_id {
+            Some(previous_result_id) => {
+                let params = lsp_types::SemanticTokensDeltaParams {
+                    work_done_progress_params: Default::default(),
+                    partial_result_params: Default::default(),
+                    text_document,
+                    previous_result_id: previous_result_id.to_string(),
+                };
+                self.send_request(
+                    "textDocument/semanticTokens/full/delta",
+                    serde_json::to_value(params)?,
+                    kind,
+                )
+            }
+            None => {
+                let params = lsp_types::SemanticTokensParams {
+                    work_done_progress_params: Default::default(),
+                    partial_result_params: Default::default(),
+                    text_document,
+                };
+                self.send_request(
+                    "textDocument/semanticTokens/full",
+                    serde_json::to_value(params)?,
+                    kind,
+                )
+            }
+        }
+    }
+
+    /// Pull the diagnostics of a document, as unchanged when they still
+    /// match `previous_result_id`
+    pub fn document_diagnostic(
+        &mut self,
+        uri: &str,
+        previous_result_id: Option<String>,
+    ) -> Result<u64> {
+        let params = lsp_types::DocumentDiagnosticParams {
+            text_document: TextDocumentIdentifier {
+                uri: lsp_types::Url::parse(uri)?,
+            },
+            identifier: None,
+            previous_result_id,
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+        self.send_request(
+            "textDocument/diagnostic",
+            serde_json::to_value(params)?,
+            RequestKind::DocumentDiagnostic {
+                uri: uri.to_string(),
+            },
+        )
+    }
+
+    /// Pull the diagnostics of every document in the workspace, given the
+    /// `(uri, result_id)` of the reports already held
+    pub fn workspace_diagnostic(
+        &mut self,
+        previous_result_ids: &[(String, String)],
+    ) -> Result<u64> {
+        let params = lsp_types::WorkspaceDiagnosticParams {
+            identifier: None,
+            previous_result_ids: previous_result_ids
+                .iter()
+                .filter_map(|(uri, value)| {
+                    Some(lsp_types::PreviousResultId {
+                        uri: lsp_types::Url::parse(uri).ok()?,
+                        value: value.clone(),
+                    })
+                })
+                .collect(),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+        self.send_request(
+            "workspace/diagnostic",
+            serde_json::to_value(params)?,
+            RequestKind::WorkspaceDiagnostic,
+        )
+    }
+
+    /// Run a server-side command. Edits the command makes come back as
+    /// `workspace/applyEdit` requests before the response.
+    pub fn execute_command(&mut self, command: &LspCommand) -> Result<u64> {
+        let params = lsp_types::ExecuteCommandParams {
+            command: command.command.clone(),
+            arguments: command.arguments.clone(),
+            work_done_progress_params: Default::default(),
+        };
+        self.send_request(
+            "workspace/executeCommand",
+            serde_json::to_value(params)?,
+            RequestKind::ExecuteCommand {
+                title: command.title.clone(),
+            },
+        )
+    }
+
+    /// Send a JSON-RPC notification
+    fn send_notification(&mut self, method: &str, params: Value) -> Result<()> {
+        let notification = JsonRpcNotification {
+            jsonrpc: "2.0",
+            method: method.to_string(),
+            params: if params.is_null() { None } else { Some(params) },
+        };
+
+        self.send_message(&serde_json::to_string(&notification)?)
+    }
+
+    /// Answer a `workspace/applyEdit` request once the editor has applied
+    /// (or refused) the edit
+    pub fn respond_apply_edit(
+        &mut self,
+        request_id: Value,
+        applied: bool,
+        failure_reason: Option<String>,
+    ) -> Result<()> {
+        let id: JsonRpcId = serde_json::from_value(request_id)?;
+        let mut result = json!({ "applied": applied });
+        if let Some(reason) = failure_reason {
+            result["failureReason"] = Value::String(reason);
+        }
+        let response = JsonRpcResponseOut {
+            jsonrpc: "2.0",
+            id,
+            result: Some(result),
+            error: None,
+        };
+        self.send_message(&serde_json::to_string(&response)?)
+    }
+
+    /// Send a raw message with Content-Length header
+    fn send_message(&mut self, content: &str) -> Result<()> {
+        let message = format!("Content-Length: {}\r\n\r\n{}", content.len(), content);
+        let mut stdin = self
+            .stdin
+            .lock()
+            .map_err(|_| anyhow!("Failed to lock stdin"))?;
+        stdin.write_all(message.as_bytes())?;
+        stdin.flush()?;
+        Ok(())
+    }
+}
+
+fn initialization_options_for_command(command: &str) -> Option<Value> {
+    let command_name = std::path::Path::new(command)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(command);
+
+    if command_name != "typescript-language-server"
+        && command_name != "typescript-language-server.cmd"
+    {
+        return None;
+    }
+
+    Some(json!({
+        "preferences": {
+            "includeCompletionsForModuleExports": true,
+            "includeCompletionsForImportStatements": true,
+            "includeCompletionsWithSnippetText": false,
+            "includeAutomaticOptionalChainCompletions": true
+        }
+    }))
+}
+
+/// Configured options win over built-in ones; objects are merged key by key
+fn merge_initialization_options(
+    builtin: Option<Value>,
+    configured: Option<Value>,
+) -> Option<Value> {
+    match (builtin, configured) {
+        (Some(mut builtin), Some(configured)) => {
+            merge_json(&mut builtin, configured);
+            Some(builtin)
+        }
+        (builtin, configured) => configured.or(builtin),
+    }
+}
+
+fn merge_json(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+fn diagnostic_to_lsp_diagnostic(diagnostic: &Diagnostic) -> lsp_types::Diagnostic {
+    lsp_types::Diagnostic {
+        range: lsp_types::Range {
+            start: lsp_types::Position {
+                line: diagnostic.line as u32,
+                character: diagnostic.col_start as u32,
+            },
+            end: lsp_types::Position {
+                line: diagnostic.end_line as u32,
+                character: diagnostic.col_end as u32,
+            },
+        },
+        severity: Some(match diagnostic.severity {
+            super::types::DiagnosticSeverity::Error => lsp_types::DiagnosticSeverity::ERROR,
+            super::types::DiagnosticSeverity::Warning => lsp_types::DiagnosticSeverity::WARNING,
+            super::types::DiagnosticSeverity::Information => {
+                lsp_types::DiagnosticSeverity::INFORMATION
+            }
+            super::types::DiagnosticSeverity::Hint => lsp_types::DiagnosticSeverity::HINT,
+        }),
+        code: diagnostic.code.as_ref().map(|code| match code {
+            super::types::DiagnosticCode::Number(n) => lsp_types::NumberOrString::Number(*n as i32),
+            super::types::DiagnosticCode::String(s) => lsp_types::NumberOrString::String(s.clone()),
+        }),
+        message: diagnostic.message.clone(),
+        source: diagnostic.source.clone(),
+        tags: (!diagnostic.tags.is_empty()).then(|| {
+            diagnostic
+                .tags
+                .iter()
+                .map(|tag| match tag {
+                    DiagnosticTag::Unnecessary => lsp_types::DiagnosticTag::UNNECESSARY,
+                    DiagnosticTag::Deprecated => lsp_types::DiagnosticTag::DEPRECATED,
+                })
+                .collect()
+        }),
+        ..Default::default()
+    }
+}
+
+impl Drop for LspClient {
+    fn drop(&mut self) {
+        // Try to gracefully shutdown, but don't block if stdin is locked
+        // This prevents deadlock if another thread holds the lock
+        if let Ok(mut stdin) = self.stdin.try_lock() {
+            let notification = serde_json::json!({
+                "jsonrpc": "2.0",
+                "method": "exit",
+                "params": null
+            });
+            if let Ok(content) = serde_json::to_string(&notification) {
+                let message = format!("Content-Length: {}\r\n\r\n{}", content.len(), content);
+                let _ = stdin.write_all(message.as_bytes());
                 let _ = stdin.flush();
             }
         }
@@ -771,11 +1401,11 @@ impl Drop for LspClient {
 
 /// Send initialized notification using shared stdin
 /// This is called from the reader thread immediately after receiving initialize response
-fn send_initialized_notification(stdin: &SharedStdin) -> Result<()> {
+fn send_notification_to(stdin: &SharedStdin, method: &str, params: Value) -> Result<()> {
     let notification = json!({
         "jsonrpc": "2.0",
-        "method": "initialized",
-        "params": {}
+        "method": method,
+        "params": params
     });
     let content = serde_json::to_string(&notification)?;
     let message = format!("Content-Length: {}\r\n\r\n{}", content.len(), content);
@@ -796,6 +1426,8 @@ pub fn read_messages(
     pending: PendingRequests,
     stdin: SharedStdin,
     watcher_tx: Sender<WatcherCommand>,
+    settings: SharedSettings,
+    log: ProtocolLog,
 ) {
     let mut reader = BufReader::new(stdout);
     let mut headers = String::new();
@@ -838,6 +1470,7 @@ pub fn read_messages(
             Ok(s) => s,
             Err(_) => continue,
         };
+        log.record(LogDirection::Received, &content_str);
 
         let response: JsonRpcResponse = match serde_json::from_str(&content_str) {
             Ok(r) => r,
@@ -846,7 +1479,7 @@ pub fn read_messages(
 
         // Handle the message using the shared pending map
         let (notification, response_to_server) =
-            handle_message(response, &pending, Some(&watcher_tx));
+            handle_message(response, &pending, Some(&watcher_tx), &settings);
 
         // Send response to server if needed (for server-initiated requests)
         if let Some(response_msg) = response_to_server {
@@ -858,12 +1491,30 @@ pub fn read_messages(
 
         // If this is the Initialize response, send 'initialized' notification immediately
         // This must happen before any other requests are sent to the server
-        if let Some(LspNotification::Initialized) = &notification {
-            if let Err(e) = send_initialized_notification(&stdin) {
+        if let Some(LspNotification::Initialized { .. }) = &notification {
+            if let Err(e) = send_notification_to(&stdin, "initialized", json!({})) {
                 let _ = tx.send(LspNotification::Error {
                     message: format!("Failed to send initialized: {}", e),
                 });
             }
+            // Servers that read settings only from this notification get
+            // them right away; the rest ask with workspace/configuration.
+            let settings = settings
+                .lock()
+                .ok()
+                .map(|settings| settings.clone())
+                .filter(|settings| settings.as_object().is_some_and(|map| !map.is_empty()));
+            if let Some(settings) = settings {
+                if let Err(e) = send_notification_to(
+                    &stdin,
+                    "workspace/didChangeConfiguration",
+                    json!({ "settings": settings }),
+                ) {
+                    let _ = tx.send(LspNotification::Error {
+                        message: format!("Failed to send didChangeConfiguration: {}", e),
+                    });
+                }
+            }
         }
 
         // Send notification to editor if we have one
@@ -881,6 +1532,7 @@ fn handle_message(
     msg: JsonRpcResponse,
     pending: &PendingRequests,
     watcher_tx: Option<&Sender<WatcherCommand>>,
+    settings: &SharedSettings,
 ) -> (Option<LspNotification>, Option<String>) {
     // Check if it's a notification (no id) - these are server-initiated notifications
     if msg.id.is_none() {
@@ -895,6 +1547,34 @@ fn handle_message(
     // Check if it's a server-initiated REQUEST (has both id AND method)
     // These require us to send a response back
     if let Some(method) = &msg.method {
+        // Workspace edits are applied by the editor, which answers later.
+        if method == "workspace/applyEdit" {
+            return handle_apply_edit_request(id, msg.params);
+        }
+        if method == "workspace/inlayHint/refresh" {
+            return (
+                Some(LspNotification::InlayHintRefresh),
+                success_response(id),
+            );
+        }
+        if method == "workspace/configuration" {
+            return (None, configuration_response(id, msg.params, settings));
+        }
+        if method == "workspace/semanticTokens/refresh" {
+            return (
+                Some(LspNotification::SemanticTokensRefresh),
+                success_response(id),
+            );
+        }
+        if method == "workspace/diagnostic/refresh" {
+            return (
+                Some(LspNotification::DiagnosticRefresh),
+                success_response(id),
+            );
+        }
+        if method == "workspace/codeLens/refresh" {
+            return (Some(LspNotification::CodeLensRefresh), success_response(id));
+        }
         let response = handle_server_request(id, method, msg.params, watcher_tx);
         return (None, response);
     }
@@ -910,45 +1590,197 @@ fn handle_message(
 
     // Handle JSON-RPC errors - remove from pending map
     if let Some(error) = msg.error {
-        if let Ok(mut pending_map) = pending.lock() {
-            pending_map.remove(&id_num);
+        let kind = match pending.lock() {
+            Ok(mut pending_map) => pending_map.remove(&id_num),
+            Err(_) => None,
+        };
+        // Hints are requested on every scroll and edit; a server without
+        // them (or one that cancelled a stale request) shows none rather
+        // than an error.
+        if let Some(RequestKind::InlayHints {
+            uri,
+            start_line,
+            end_line,
+            buffer_version,
+        }) = kind
+        {
+            return (
+                Some(LspNotification::InlayHints {
+                    hints: Vec::new(),
+                    request_uri: uri,
+                    request_start_line: start_line,
+                    request_end_line: end_line,
+                    request_version: buffer_version,
+                }),
+                None,
+            );
         }
-        return (
-            Some(LspNotification::Error {
-                message: format!("LSP error ({}): {}", error.code, error.message),
-            }),
-            None,
-        );
-    }
-
-    // Look up what kind of request this was (and remove it from pending)
-    let kind = match pending.lock() {
-        Ok(mut pending_map) => pending_map.remove(&id_num),
-        Err(_) => None,
-    };
-
-    let kind = match kind {
-        Some(k) => k,
-        None => {
-            // Unknown response ID - could be a server request we didn't handle
-            // or a timing issue. Log and ignore.
+        // Pulls run after every edit; a failed one (often a cancelled,
+        // outdated request) keeps the diagnostics shown.
+        if matches!(
+            kind,
+            Some(RequestKind::DocumentDiagnostic { .. } | RequestKind::WorkspaceDiagnostic)
+        ) {
             return (None, None);
         }
-    };
-
-    // Dispatch based on request kind
-    let notification = match kind {
-        RequestKind::Initialize => Some(LspNotification::Initialized),
-        RequestKind::Shutdown => {
-            // Shutdown response - nothing to notify
-            None
+        // On-type formatting runs while typing; a failure leaves the text
+        // as typed.
+        if let Some(RequestKind::OnTypeFormatting {
+            uri,
+            buffer_version,
+        }) = kind
+        {
+            return (
+                Some(LspNotification::Formatting {
+                    edits: Vec::new(),
+                    request_uri: uri,
+                    request_version: buffer_version,
+                }),
+                None,
+            );
         }
-        RequestKind::Completion {
+        // Highlights follow the cursor; a failed request just shows none.
+        if let Some(RequestKind::DocumentHighlight {
             uri,
             line,
             character,
             buffer_version,
-        } => match msg.result {
+        }) = kind
+        {
+            return (
+                Some(LspNotification::DocumentHighlights {
+                    highlights: Vec::new(),
+                    request_uri: uri,
+                    request_line: line,
+                    request_character: character,
+                    request_version: buffer_version,
+                }),
+                None,
+            );
+        }
+        // Selection expansion falls back to the syntax tree.
+        if let Some(RequestKind::SelectionRange {
+            uri,
+            buffer_version,
+        }) = kind
+        {
+            return (
+                Some(LspNotification::SelectionRanges {
+                    ranges: Vec::new(),
+                    request_uri: uri,
+                    request_version: buffer_version,
+                }),
+                None,
+            );
+        }
+        // Lenses that failed to load or resolve are simply not shown.
+        if let Some(RequestKind::CodeLens {
+            uri,
+            buffer_version,
+        }) = kind
+        {
+            return (
+                Some(LspNotification::CodeLenses {
+                    lenses: Vec::new(),
+                    request_uri: uri,
+                    request_version: buffer_version,
+                }),
+                None,
+            );
+        }
+        if matches!(kind, Some(RequestKind::CodeLensResolve { .. })) {
+            return (None, None);
+        }
+        // A node whose children failed to load shows as a leaf instead of
+        // loading forever.
+        if let Some(RequestKind::HierarchyChildren { direction, node }) = kind {
+            return (
+                Some(LspNotification::HierarchyChildren {
+                    direction,
+                    node,
+                    items: Vec::new(),
+                }),
+                None,
+            );
+        }
+        // Code actions are gathered from every server for the buffer; one
+        // that fails offers none.
+        if let Some(RequestKind::CodeAction {
+            uri,
+            buffer_version,
+            ..
+        }) = kind
+        {
+            return (
+                Some(LspNotification::CodeActions {
+                    actions: Vec::new(),
+                    request_uri: uri,
+                    request_version: buffer_version,
+                }),
+                None,
+            );
+        }
+        // Same for semantic tokens; the editor falls back to a full request.
+        if let Some(RequestKind::SemanticTokens {
+            uri,
+            previous_result_id,
+            buffer_version,
+        }) = kind
+        {
+            return (
+                Some(LspNotification::SemanticTokens {
+                    tokens: None,
+                    result_id: None,
+                    request_uri: uri,
+                    request_previous_result_id: previous_result_id,
+                    request_version: buffer_version,
+                }),
+                None,
+            );
+        }
+        return (
+            Some(LspNotification::Error {
+                message: format!("LSP error ({}): {}", error.code, error.message),
+            }),
+            None,
+        );
+    }
+
+    // Look up what kind of request this was (and remove it from pending)
+    let kind = match pending.lock() {
+        Ok(mut pending_map) => pending_map.remove(&id_num),
+        Err(_) => None,
+    };
+
+    let kind = match kind {
+        Some(k) => k,
+        None => {
+            // Unknown response ID - could be a server request we didn't handle
+            // or a timing issue. Log and ignore.
+            return (None, None);
+        }
+    };
+
+    // Dispatch based on request kind
+    let notification = match kind {
+        RequestKind::Initialize => Some(LspNotification::Initialized {
+            capabilities: msg
+                .result
+                .as_ref()
+                .and_then(|result| result.get("capabilities"))
+                .map(parse_server_capabilities)
+                .unwrap_or_default(),
+        }),
+        RequestKind::Shutdown => {
+            // Shutdown response - nothing to notify
+            None
+        }
+        RequestKind::Completion {
+            uri,
+            line,
+            character,
+            buffer_version,
+        } => match msg.result {
             Some(result) if !result.is_null() => {
                 handle_completion_response(result, uri, line, character, buffer_version)
             }
@@ -1003,6 +1835,20 @@ fn handle_message(
                 request_uri: uri,
             }),
         },
+        RequestKind::TypeDefinition {
+            uri,
+            line: _,
+            character: _,
+        } => match msg.result {
+            Some(result) if !result.is_null() => {
+                handle_definition_response(result, uri, LspNavigationTargetKind::TypeDefinition)
+            }
+            _ => Some(LspNotification::Definition {
+                locations: vec![],
+                target_kind: LspNavigationTargetKind::TypeDefinition,
+                request_uri: uri,
+            }),
+        },
         RequestKind::Hover {
             uri,
             line,
@@ -1036,6 +1882,14 @@ fn handle_message(
         RequestKind::Formatting {
             uri,
             buffer_version,
+        }
+        | RequestKind::RangeFormatting {
+            uri,
+            buffer_version,
+        }
+        | RequestKind::OnTypeFormatting {
+            uri,
+            buffer_version,
         } => match msg.result {
             Some(result) if !result.is_null() => {
                 handle_formatting_response(result, uri, buffer_version)
@@ -1098,6 +1952,139 @@ fn handle_message(
                 additional_text_edits: Vec::new(),
             }),
         },
+        RequestKind::DocumentSymbols { uri } => Some(LspNotification::DocumentSymbols {
+            symbols: msg
+                .result
+                .map(|result| parse_document_symbols(&result, &uri))
+                .unwrap_or_default(),
+            request_uri: uri,
+        }),
+        RequestKind::WorkspaceSymbols { query } => Some(LspNotification::WorkspaceSymbols {
+            symbols: msg
+                .result
+                .map(|result| parse_workspace_symbols(&result))
+                .unwrap_or_default(),
+            query,
+        }),
+        RequestKind::PrepareHierarchy { uri, direction } => {
+            Some(LspNotification::HierarchyPrepared {
+                direction,
+                items: msg
+                    .result
+                    .as_ref()
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(parse_hierarchy_item)
+                    .collect(),
+                request_uri: uri,
+            })
+        }
+        RequestKind::HierarchyChildren { direction, node } => {
+            Some(LspNotification::HierarchyChildren {
+                direction,
+                node,
+                items: msg
+                    .result
+                    .map(|result| parse_hierarchy_children(&result, direction))
+                    .unwrap_or_default(),
+            })
+        }
+        RequestKind::InlayHints {
+            uri,
+            start_line,
+            end_line,
+            buffer_version,
+        } => Some(LspNotification::InlayHints {
+            hints: msg
+                .result
+                .map(|result| parse_inlay_hints(&result))
+                .unwrap_or_default(),
+            request_uri: uri,
+            request_start_line: start_line,
+            request_end_line: end_line,
+            request_version: buffer_version,
+        }),
+        RequestKind::DocumentHighlight {
+            uri,
+            line,
+            character,
+            buffer_version,
+        } => Some(LspNotification::DocumentHighlights {
+            highlights: msg
+                .result
+                .map(|result| parse_document_highlights(&result))
+                .unwrap_or_default(),
+            request_uri: uri,
+            request_line: line,
+            request_character: character,
+            request_version: buffer_version,
+        }),
+        RequestKind::SelectionRange {
+            uri,
+            buffer_version,
+        } => Some(LspNotification::SelectionRanges {
+            ranges: msg
+                .result
+                .map(|result| parse_selection_ranges(&result))
+                .unwrap_or_default(),
+            request_uri: uri,
+            request_version: buffer_version,
+        }),
+        RequestKind::CodeLens {
+            uri,
+            buffer_version,
+        } => Some(LspNotification::CodeLenses {
+            lenses: msg
+                .result
+                .map(|result| parse_code_lenses(&result))
+                .unwrap_or_default(),
+            request_uri: uri,
+            request_version: buffer_version,
+        }),
+        RequestKind::CodeLensResolve {
+            uri,
+            buffer_version,
+            index,
+        } => msg
+            .result
+            .and_then(|result| parse_code_lens(&result))
+            .map(|lens| LspNotification::CodeLensResolved {
+                lens,
+                index,
+                request_uri: uri,
+                request_version: buffer_version,
+            }),
+        RequestKind::DocumentDiagnostic { uri } => msg
+            .result
+            .and_then(|result| parse_diagnostic_report(&result, uri))
+            .map(|report| LspNotification::PulledDiagnostics {
+                reports: vec![report],
+            }),
+        RequestKind::WorkspaceDiagnostic => Some(LspNotification::PulledDiagnostics {
+            reports: msg
+                .result
+                .map(|result| parse_workspace_diagnostic_reports(&result))
+                .unwrap_or_default(),
+        }),
+        RequestKind::SemanticTokens {
+            uri,
+            previous_result_id,
+            buffer_version,
+        } => {
+            let result = msg.result.unwrap_or(Value::Null);
+            Some(LspNotification::SemanticTokens {
+                tokens: parse_semantic_tokens(&result),
+                result_id: result
+                    .get("resultId")
+                    .and_then(|id| id.as_str())
+                    .map(str::to_string),
+                request_uri: uri,
+                request_previous_result_id: previous_result_id,
+                request_version: buffer_version,
+            })
+        }
+        RequestKind::ExecuteCommand { title } => Some(LspNotification::CommandExecuted { title }),
     };
 
     (notification, None)
@@ -1126,6 +2113,63 @@ fn error_response(id: JsonRpcId, code: i64, message: impl Into<String>) -> Optio
     })
 }
 
+/// Answer `workspace/configuration` from the configured settings: each item
+/// gets the value under its `section` (`rust-analyzer`, `python.analysis`),
+/// or all settings when it names none.
+fn configuration_response(
+    id: JsonRpcId,
+    params: Option<Value>,
+    settings: &SharedSettings,
+) -> Option<String> {
+    let settings = settings
+        .lock()
+        .map(|settings| settings.clone())
+        .unwrap_or_else(|_| json!({}));
+    let sections: Vec<Option<String>> = params
+        .as_ref()
+        .and_then(|params| params.get("items"))
+        .and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .map(|item| {
+                    item.get("section")
+                        .and_then(Value::as_str)
+                        .map(str::to_string)
+                })
+                .collect()
+        })
+        .unwrap_or_else(|| vec![None]);
+
+    let result = sections
+        .iter()
+        .map(|section| configuration_section(&settings, section.as_deref()))
+        .collect();
+    build_response(JsonRpcResponseOut {
+        jsonrpc: "2.0",
+        id,
+        result: Some(Value::Array(result)),
+        error: None,
+    })
+}
+
+fn configuration_section(settings: &Value, section: Option<&str>) -> Value {
+    let Some(section) = section.filter(|section| !section.is_empty()) else {
+        return settings.clone();
+    };
+    settings
+        .get(section)
+        .or_else(|| {
+            section
+                .split('.')
+                .try_fold(settings, |value, key| value.get(key))
+        })
+        .cloned()
+        // Sections nobody configured get an empty object, which servers
+        // read as "use your defaults".
+        .unwrap_or_else(|| json!({}))
+}
+
 fn send_watcher_command(
     watcher_tx: Option<&Sender<WatcherCommand>>,
     build: impl FnOnce(SyncSender<std::result::Result<(), WatcherRequestError>>) -> WatcherCommand,
@@ -1154,26 +2198,6 @@ fn handle_server_request(
     watcher_tx: Option<&Sender<WatcherCommand>>,
 ) -> Option<String> {
     match method {
-        "workspace/configuration" => {
-            // Server is asking for configuration
-            // Return empty configs for each requested item
-            let items_count = params
-                .as_ref()
-                .and_then(|p| p.get("items"))
-                .and_then(|items| items.as_array())
-                .map(|arr| arr.len())
-                .unwrap_or(1);
-
-            // Return an array of empty objects (one per requested item)
-            let result: Vec<Value> = (0..items_count).map(|_| serde_json::json!({})).collect();
-
-            build_response(JsonRpcResponseOut {
-                jsonrpc: "2.0",
-                id,
-                result: Some(Value::Array(result)),
-                error: None,
-            })
-        }
         "client/registerCapability" => {
             let registrations = match parse_register_params(params) {
                 Ok(registrations) => registrations,
@@ -1252,19 +2276,6 @@ fn handle_server_request(
                 Err(WatcherRequestError::Setup(message)) => error_response(id, -32603, message),
             }
         }
-        "workspace/applyEdit" => {
-            // We don't support workspace edits yet; explicitly reject.
-            let result = serde_json::json!({
-                "applied": false,
-                "failureReason": "workspace edits not supported",
-            });
-            build_response(JsonRpcResponseOut {
-                jsonrpc: "2.0",
-                id,
-                result: Some(result),
-                error: None,
-            })
-        }
         _ => {
             // Unknown server request - return method not found error
             build_response(JsonRpcResponseOut {
@@ -1280,6 +2291,96 @@ fn handle_server_request(
     }
 }
 
+/// Forward a `workspace/applyEdit` request to the editor, keeping its id so
+/// the editor's answer can be matched to it.
+fn handle_apply_edit_request(
+    id: JsonRpcId,
+    params: Option<Value>,
+) -> (Option<LspNotification>, Option<String>) {
+    let Some(edit) = params.as_ref().and_then(|params| params.get("edit")) else {
+        return (None, error_response(id, -32602, "missing edit"));
+    };
+    let Ok(request_id) = serde_json::to_value(&id) else {
+        return (None, error_response(id, -32603, "invalid request id"));
+    };
+    let label = params
+        .as_ref()
+        .and_then(|params| params.get("label"))
+        .and_then(Value::as_str)
+        .map(str::to_string);
+
+    let notification = LspNotification::ApplyEdit {
+        request_id,
+        label,
+        edit: parse_workspace_edit(edit),
+    };
+    (Some(notification), None)
+}
+
+/// Parse a `WorkspaceEdit`. `documentChanges` wins over `changes` when a
+/// server sends both, as the spec asks.
+fn parse_workspace_edit(edit: &Value) -> WorkspaceEdit {
+    let mut changes = Vec::new();
+
+    if let Some(document_changes) = edit.get("documentChanges").and_then(Value::as_array) {
+        for change in document_changes {
+            let string = |key: &str| change.get(key).and_then(Value::as_str).map(str::to_string);
+            let option = |key: &str| {
+                change
+                    .get("options")
+                    .and_then(|options| options.get(key))
+                    .and_then(Value::as_bool)
+                    .unwrap_or(false)
+            };
+            let parsed = match change.get("kind").and_then(Value::as_str) {
+                Some("create") => string("uri").map(|uri| WorkspaceEditChange::CreateFile {
+                    uri,
+                    overwrite: option("overwrite"),
+                    ignore_if_exists: option("ignoreIfExists"),
+                }),
+                Some("rename") => {
+                    string("oldUri")
+                        .zip(string("newUri"))
+                        .map(|(old_uri, new_uri)| WorkspaceEditChange::RenameFile {
+                            old_uri,
+                            new_uri,
+                            overwrite: option("overwrite"),
+                            ignore_if_exists: option("ignoreIfExists"),
+                        })
+                }
+                Some("delete") => string("uri").map(|uri| WorkspaceEditChange::DeleteFile {
+                    uri,
+                    recursive: option("recursive"),
+                    ignore_if_not_exists: option("ignoreIfNotExists"),
+                }),
+                _ => change.get("textDocument").and_then(|text_document| {
+                    let uri = text_document.get("uri")?.as_str()?.to_string();
+                    let version = text_document
+                        .get("version")
+                        .and_then(Value::as_i64)
+                        .map(|version| version as i32);
+                    Some(WorkspaceEditChange::Edit {
+                        uri,
+                        version,
+                        edits: parse_lsp_text_edits(change.get("edits")),
+                    })
+                }),
+            };
+            changes.extend(parsed);
+        }
+    } else if let Some(map) = edit.get("changes").and_then(Value::as_object) {
+        for (uri, edits) in map {
+            changes.push(WorkspaceEditChange::Edit {
+                uri: uri.clone(),
+                version: None,
+                edits: parse_lsp_text_edits(Some(edits)),
+            });
+        }
+    }
+
+    WorkspaceEdit { changes }
+}
+
 fn build_response(response: JsonRpcResponseOut) -> Option<String> {
     let body = serde_json::to_string(&response).ok()?;
     Some(format!("Content-Length: {}\r\n\r\n{}", body.len(), body))
@@ -1295,79 +2396,7 @@ fn handle_notification(method: &str, params: Option<Value>) -> Option<LspNotific
 
             let diagnostics: Vec<Diagnostic> = diagnostics_json
                 .iter()
-                .filter_map(|d| {
-                    let range = d.get("range")?;
-                    let start = range.get("start")?;
-                    let end = range.get("end")?;
-
-                    // Extract diagnostic code for severity override
-                    let code = d.get("code").and_then(|c| {
-                        c.as_u64()
-                            .or_else(|| c.as_i64().map(|n| n as u64))
-                            .or_else(|| c.as_str().and_then(|s| s.parse().ok()))
-                    });
-
-                    // TypeScript codes that should be hints (unused vars/imports)
-                    let is_hint_code = matches!(
-                        code,
-                        Some(6133)  // Variable declared but never used
-                            | Some(6138)  // Property declared but never used
-                            | Some(6192)  // All destructured elements are unused
-                            | Some(6196)  // All imports in import declaration are unused
-                            | Some(6198)  // All variables are unused
-                            | Some(6199)  // All imports only used as types
-                            | Some(6205)  // All type parameters are unused
-                            | Some(80001) // Suggestion: requires await
-                            | Some(80005) // Suggestion: require -> import
-                    );
-
-                    let mut severity = d
-                        .get("severity")
-                        .and_then(|s| {
-                            s.as_u64()
-                                .or_else(|| s.as_i64().map(|n| n as u64))
-                                .or_else(|| s.as_f64().map(|n| n as u64))
-                        })
-                        .map(|s| match s {
-                            1 => DiagnosticSeverity::Error,
-                            2 => DiagnosticSeverity::Warning,
-                            3 => DiagnosticSeverity::Information,
-                            4 => DiagnosticSeverity::Hint,
-                            _ => DiagnosticSeverity::Hint,
-                        })
-                        .unwrap_or(DiagnosticSeverity::Warning);
-
-                    // Override: TypeScript sends hint-level diagnostics as errors
-                    // when noUnusedLocals/noUnusedParameters is enabled in tsconfig
-                    if is_hint_code {
-                        severity = DiagnosticSeverity::Hint;
-                    }
-
-                    // Extract code as DiagnosticCode for code actions
-                    let diagnostic_code = d.get("code").and_then(|c| {
-                        if let Some(n) = c.as_i64() {
-                            Some(super::types::DiagnosticCode::Number(n))
-                        } else if let Some(s) = c.as_str() {
-                            Some(super::types::DiagnosticCode::String(s.to_string()))
-                        } else {
-                            None
-                        }
-                    });
-
-                    Some(Diagnostic {
-                        line: start.get("line")?.as_u64()? as usize,
-                        end_line: end.get("line")?.as_u64()? as usize,
-                        col_start: start.get("character")?.as_u64()? as usize,
-                        col_end: end.get("character")?.as_u64()? as usize,
-                        severity,
-                        message: d.get("message")?.as_str()?.to_string(),
-                        source: d
-                            .get("source")
-                            .and_then(|s| s.as_str())
-                            .map(|s| s.to_string()),
-                        code: diagnostic_code,
-                    })
-                })
+                .filter_map(parse_diagnostic)
                 .collect();
 
             Some(LspNotification::Diagnostics { uri, diagnostics })
@@ -1419,6 +2448,167 @@ fn handle_notification(method: &str, params: Option<Value>) -> Option<LspNotific
     }
 }
 
+/// Parse one LSP `Diagnostic`, pushed or pulled
+fn parse_diagnostic(d: &Value) -> Option<Diagnostic> {
+    let range = d.get("range")?;
+    let start = range.get("start")?;
+    let end = range.get("end")?;
+
+    // Extract diagnostic code for severity override
+    let code = d.get("code").and_then(|c| {
+        c.as_u64()
+            .or_else(|| c.as_i64().map(|n| n as u64))
+            .or_else(|| c.as_str().and_then(|s| s.parse().ok()))
+    });
+
+    // TypeScript codes that should be hints (unused vars/imports)
+    let is_hint_code = matches!(
+        code,
+        Some(6133)  // Variable declared but never used
+            | Some(6138)  // Property declared but never used
+            | Some(6192)  // All destructured elements are unused
+            | Some(6196)  // All imports in import declaration are unused
+            | Some(6198)  // All variables are unused
+            | Some(6199)  // All imports only used as types
+            | Some(6205)  // All type parameters are unused
+            | Some(80001) // Suggestion: requires await
+            | Some(80005) // Suggestion: require -> import
+    );
+
+    let mut severity = d
+        .get("severity")
+        .and_then(|s| {
+            s.as_u64()
+                .or_else(|| s.as_i64().map(|n| n as u64))
+                .or_else(|| s.as_f64().map(|n| n as u64))
+        })
+        .map(|s| match s {
+            1 => DiagnosticSeverity::Error,
+            2 => DiagnosticSeverity::Warning,
+            3 => DiagnosticSeverity::Information,
+            4 => DiagnosticSeverity::Hint,
+            _ => DiagnosticSeverity::Hint,
+        })
+        .unwrap_or(DiagnosticSeverity::Warning);
+
+    // Override: TypeScript sends hint-level diagnostics as errors
+    // when noUnusedLocals/noUnusedParameters is enabled in tsconfig
+    if is_hint_code {
+        severity = DiagnosticSeverity::Hint;
+    }
+
+    // Extract code as DiagnosticCode for code actions
+    let diagnostic_code = d.get("code").and_then(|c| {
+        if let Some(n) = c.as_i64() {
+            Some(super::types::DiagnosticCode::Number(n))
+        } else if let Some(s) = c.as_str() {
+            Some(super::types::DiagnosticCode::String(s.to_string()))
+        } else {
+            None
+        }
+    });
+
+    Some(Diagnostic {
+        line: start.get("line")?.as_u64()? as usize,
+        end_line: end.get("line")?.as_u64()? as usize,
+        col_start: start.get("character")?.as_u64()? as usize,
+        col_end: end.get("character")?.as_u64()? as usize,
+        severity,
+        message: d.get("message")?.as_str()?.to_string(),
+        source: d
+            .get("source")
+            .and_then(|s| s.as_str())
+            .map(|s| s.to_string()),
+        code: diagnostic_code,
+        tags: parse_diagnostic_tags(d),
+        related: parse_diagnostic_related(d),
+        href: d
+            .get("codeDescription")
+            .and_then(|description| description.get("href"))
+            .and_then(|href| href.as_str())
+            .map(|href| href.to_string()),
+    })
+}
+
+/// The `tags` of a diagnostic nevi draws: 1 is unnecessary, 2 deprecated
+fn parse_diagnostic_tags(d: &Value) -> Vec<DiagnosticTag> {
+    d.get("tags")
+        .and_then(|tags| tags.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|tag| match tag.as_u64()? {
+            1 => Some(DiagnosticTag::Unnecessary),
+            2 => Some(DiagnosticTag::Deprecated),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The `relatedInformation` of a diagnostic
+fn parse_diagnostic_related(d: &Value) -> Vec<DiagnosticRelatedInformation> {
+    d.get("relatedInformation")
+        .and_then(|related| related.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|related| {
+            let location = related.get("location")?;
+            let start = location.get("range")?.get("start")?;
+            Some(DiagnosticRelatedInformation {
+                uri: location.get("uri")?.as_str()?.to_string(),
+                line: start.get("line")?.as_u64()? as usize,
+                col: start.get("character")?.as_u64()? as usize,
+                message: related
+                    .get("message")
+                    .and_then(|message| message.as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Parse a document diagnostic report (`textDocument/diagnostic`, or an
+/// item of `workspace/diagnostic`). `kind: "unchanged"` reports keep the
+/// diagnostics of the previous result.
+fn parse_diagnostic_report(report: &Value, uri: String) -> Option<DiagnosticReport> {
+    let result_id = report
+        .get("resultId")
+        .and_then(|id| id.as_str())
+        .map(|id| id.to_string());
+    let diagnostics = match report.get("kind")?.as_str()? {
+        "full" => Some(
+            report
+                .get("items")
+                .and_then(|items| items.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(parse_diagnostic)
+                .collect(),
+        ),
+        "unchanged" => None,
+        _ => return None,
+    };
+    Some(DiagnosticReport {
+        uri,
+        result_id,
+        diagnostics,
+    })
+}
+
+/// Parse a `workspace/diagnostic` answer into its document reports
+fn parse_workspace_diagnostic_reports(result: &Value) -> Vec<DiagnosticReport> {
+    result
+        .get("items")
+        .and_then(|items| items.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|item| {
+            let uri = item.get("uri")?.as_str()?.to_string();
+            parse_diagnostic_report(item, uri)
+        })
+        .collect()
+}
+
 fn parse_lsp_text_edit(edit: &Value) -> Option<TextEdit> {
     let range = edit
         .get("range")
@@ -1915,11 +3105,12 @@ fn handle_code_action_response(
                 }
             }
 
-            let command = action
-                .get("command")
-                .and_then(|c| c.get("command"))
-                .and_then(|t| t.as_str())
-                .map(|s| s.to_string());
+            // A bare Command has a string `command`; a CodeAction nests one.
+            let command = match action.get("command") {
+                Some(Value::String(_)) => parse_lsp_command(action),
+                Some(command) => parse_lsp_command(command),
+                None => None,
+            };
 
             Some(CodeActionItem {
                 title,
@@ -1938,31 +3129,471 @@ fn handle_code_action_response(
     })
 }
 
-/// Handle rename response
-fn handle_rename_response(
-    result: Value,
-    request_uri: String,
-    request_version: u64,
-) -> Option<LspNotification> {
-    // Result is a WorkspaceEdit
-    let mut edits: Vec<(String, Vec<TextEdit>)> = Vec::new();
+fn parse_lsp_command(command: &Value) -> Option<LspCommand> {
+    Some(LspCommand {
+        title: command
+            .get("title")
+            .and_then(|t| t.as_str())
+            .unwrap_or_default()
+            .to_string(),
+        command: command.get("command")?.as_str()?.to_string(),
+        arguments: command
+            .get("arguments")
+            .and_then(|a| a.as_array())
+            .cloned()
+            .unwrap_or_default(),
+        server: None,
+    })
+}
 
-    // Handle "changes" format
-    if let Some(changes) = result.get("changes").and_then(|c| c.as_object()) {
-        for (uri, file_edits) in changes {
-            if let Some(file_edits_arr) = file_edits.as_array() {
-                let text_edits: Vec<TextEdit> = file_edits_arr
-                    .iter()
-                    .filter_map(parse_lsp_text_edit)
-                    .collect();
-                if !text_edits.is_empty() {
-                    edits.push((uri.clone(), text_edits));
-                }
+/// Flatten a `textDocument/documentSymbol` result into outline order.
+/// Accepts both hierarchical `DocumentSymbol[]` and flat
+/// `SymbolInformation[]` results.
+fn parse_document_symbols(result: &Value, uri: &str) -> Vec<SymbolItem> {
+    fn push_symbol(symbol: &Value, uri: &str, depth: usize, out: &mut Vec<SymbolItem>) {
+        let Some(name) = symbol.get("name").and_then(|n| n.as_str()) else {
+            return;
+        };
+        // DocumentSymbol points at the name through selectionRange;
+        // SymbolInformation only has a location.
+        let (symbol_uri, range) = match symbol.get("selectionRange") {
+            Some(range) => (uri.to_string(), range),
+            None => {
+                let Some(location) = symbol.get("location") else {
+                    return;
+                };
+                let Some(range) = location.get("range") else {
+                    return;
+                };
+                let symbol_uri = location
+                    .get("uri")
+                    .and_then(|u| u.as_str())
+                    .unwrap_or(uri)
+                    .to_string();
+                (symbol_uri, range)
             }
-        }
-    }
-
-    // Handle "documentChanges" format
+        };
+        let (line, col) = range_start(range);
+        out.push(SymbolItem {
+            name: name.to_string(),
+            detail: symbol
+                .get("detail")
+                .and_then(|d| d.as_str())
+                .filter(|d| !d.is_empty())
+                .map(str::to_string),
+            kind: symbol_kind(symbol.get("kind")),
+            uri: symbol_uri,
+            line,
+            col,
+            depth,
+            container: symbol
+                .get("containerName")
+                .and_then(|c| c.as_str())
+                .filter(|c| !c.is_empty())
+                .map(str::to_string),
+        });
+        if let Some(children) = symbol.get("children").and_then(|c| c.as_array()) {
+            for child in children {
+                push_symbol(child, uri, depth + 1, out);
+            }
+        }
+    }
+
+    let mut symbols = Vec::new();
+    for symbol in result.as_array().into_iter().flatten() {
+        push_symbol(symbol, uri, 0, &mut symbols);
+    }
+    // Flat results come in no particular order.
+    if symbols.iter().all(|symbol| symbol.depth == 0) {
+        symbols.sort_by_key(|symbol| (symbol.line, symbol.col));
+    }
+    symbols
+}
+
+/// Parse a `workspace/symbol` result (`SymbolInformation[]` or
+/// `WorkspaceSymbol[]`, whose location may omit the range).
+fn parse_workspace_symbols(result: &Value) -> Vec<SymbolItem> {
+    result
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|symbol| {
+            let location = symbol.get("location")?;
+            let (line, col) = location.get("range").map(range_start).unwrap_or((0, 0));
+            Some(SymbolItem {
+                name: symbol.get("name")?.as_str()?.to_string(),
+                detail: None,
+                kind: symbol_kind(symbol.get("kind")),
+                uri: location.get("uri")?.as_str()?.to_string(),
+                line,
+                col,
+                depth: 0,
+                container: symbol
+                    .get("containerName")
+                    .and_then(|c| c.as_str())
+                    .filter(|c| !c.is_empty())
+                    .map(str::to_string),
+            })
+        })
+        .collect()
+}
+
+/// Parse a `CallHierarchyItem` or `TypeHierarchyItem`, pointing at its name
+fn parse_hierarchy_item(item: &Value) -> Option<HierarchyItem> {
+    let range = item.get("selectionRange").or_else(|| item.get("range"))?;
+    let (line, col) = range_start(range);
+    Some(HierarchyItem {
+        name: item.get("name")?.as_str()?.to_string(),
+        detail: item
+            .get("detail")
+            .and_then(|d| d.as_str())
+            .filter(|d| !d.is_empty())
+            .map(str::to_string),
+        kind: symbol_kind(item.get("kind")),
+        uri: item.get("uri")?.as_str()?.to_string(),
+        line,
+        col,
+        raw: item.clone(),
+    })
+}
+
+/// Parse the result of a hierarchy follow-up request. Incoming calls point
+/// at the first call site inside the caller rather than the caller's name.
+fn parse_hierarchy_children(result: &Value, direction: HierarchyDirection) -> Vec<HierarchyItem> {
+    result
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| match direction {
+            HierarchyDirection::IncomingCalls => {
+                let mut item = parse_hierarchy_item(entry.get("from")?)?;
+                if let Some(call_site) = entry
+                    .get("fromRanges")
+                    .and_then(Value::as_array)
+                    .and_then(|ranges| ranges.first())
+                {
+                    (item.line, item.col) = range_start(call_site);
+                }
+                Some(item)
+            }
+            HierarchyDirection::OutgoingCalls => parse_hierarchy_item(entry.get("to")?),
+            HierarchyDirection::Supertypes | HierarchyDirection::Subtypes => {
+                parse_hierarchy_item(entry)
+            }
+        })
+        .collect()
+}
+
+/// Parse a `textDocument/inlayHint` result, joining label parts into one
+/// string and dropping hints without a usable position or label.
+fn parse_inlay_hints(result: &Value) -> Vec<InlayHint> {
+    let mut hints: Vec<InlayHint> = result
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|hint| {
+            let position = hint.get("position")?;
+            let label = match hint.get("label")? {
+                Value::String(label) => label.clone(),
+                Value::Array(parts) => parts
+                    .iter()
+                    .filter_map(|part| part.get("value").and_then(|v| v.as_str()))
+                    .collect(),
+                _ => return None,
+            };
+            // Labels are drawn on one row.
+            let label = label.replace(['\n', '\r'], " ");
+            if label.trim().is_empty() {
+                return None;
+            }
+            let flag = |name: &str| hint.get(name).and_then(|v| v.as_bool()).unwrap_or(false);
+            Some(InlayHint {
+                line: position.get("line")?.as_u64()? as usize,
+                col: position.get("character")?.as_u64()? as usize,
+                label,
+                kind: match hint.get("kind").and_then(|k| k.as_u64()) {
+                    Some(1) => Some(InlayHintKind::Type),
+                    Some(2) => Some(InlayHintKind::Parameter),
+                    _ => None,
+                },
+                padding_left: flag("paddingLeft"),
+                padding_right: flag("paddingRight"),
+            })
+        })
+        .collect();
+    hints.sort_by_key(|hint| (hint.line, hint.col));
+    hints
+}
+
+fn parse_text_range(range: &Value) -> Option<TextRange> {
+    let start = range.get("start")?;
+    let end = range.get("end")?;
+    Some(TextRange {
+        start_line: start.get("line")?.as_u64()? as usize,
+        start_col: start.get("character")?.as_u64()? as usize,
+        end_line: end.get("line")?.as_u64()? as usize,
+        end_col: end.get("character")?.as_u64()? as usize,
+    })
+}
+
+/// Parse a `textDocument/documentHighlight` result in document order. A
+/// highlight without a kind is a textual match.
+fn parse_document_highlights(result: &Value) -> Vec<DocumentHighlight> {
+    let mut highlights: Vec<DocumentHighlight> = result
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|highlight| {
+            Some(DocumentHighlight {
+                range: parse_text_range(highlight.get("range")?)?,
+                kind: match highlight.get("kind").and_then(|k| k.as_u64()) {
+                    Some(2) => DocumentHighlightKind::Read,
+                    Some(3) => DocumentHighlightKind::Write,
+                    _ => DocumentHighlightKind::Text,
+                },
+            })
+        })
+        .collect();
+    highlights.sort_by_key(|highlight| (highlight.range.start_line, highlight.range.start_col));
+    highlights
+}
+
+/// Flatten the `parent` chain of the first `textDocument/selectionRange`
+/// result into ranges, innermost first.
+fn parse_selection_ranges(result: &Value) -> Vec<TextRange> {
+    let mut ranges = Vec::new();
+    let mut next = result.as_array().and_then(|results| results.first());
+    while let Some(selection) = next {
+        let Some(range) = selection.get("range").and_then(parse_text_range) else {
+            break;
+        };
+        ranges.push(range);
+        next = selection.get("parent");
+    }
+    ranges
+}
+
+/// Parse a `textDocument/codeLens` result, keeping the server's order so
+/// resolves can refer to lenses by index
+fn parse_code_lenses(result: &Value) -> Vec<CodeLens> {
+    result
+        .as_array()
+        .map(|lenses| lenses.iter().filter_map(parse_code_lens).collect())
+        .unwrap_or_default()
+}
+
+fn parse_code_lens(lens: &Value) -> Option<CodeLens> {
+    let start = lens.get("range")?.get("start")?;
+    Some(CodeLens {
+        line: start.get("line")?.as_u64()? as usize,
+        col: start.get("character")?.as_u64()? as usize,
+        command: lens.get("command").and_then(parse_lsp_command),
+        raw: lens.clone(),
+    })
+}
+
+/// The parts of an initialize response's `capabilities` the editor uses
+fn parse_server_capabilities(capabilities: &Value) -> ServerCapabilities {
+    let mut providers: Vec<String> = capabilities
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter(|(_, value)| !matches!(value, Value::Null | Value::Bool(false)))
+        .map(|(key, _)| key.clone())
+        .collect();
+    providers.sort();
+    ServerCapabilities {
+        providers,
+        semantic_tokens: capabilities
+            .get("semanticTokensProvider")
+            .and_then(parse_semantic_tokens_capability),
+        incremental_sync: capabilities
+            .get("textDocumentSync")
+            .is_some_and(parse_incremental_sync),
+        on_type_formatting_triggers: capabilities
+            .get("documentOnTypeFormattingProvider")
+            .map(parse_on_type_formatting_triggers)
+            .unwrap_or_default(),
+        workspace_diagnostics: capabilities
+            .get("diagnosticProvider")
+            .and_then(|provider| provider.get("workspaceDiagnostics"))
+            .and_then(|workspace| workspace.as_bool())
+            .unwrap_or(false),
+        code_lens_resolve: capabilities
+            .get("codeLensProvider")
+            .and_then(|provider| provider.get("resolveProvider"))
+            .and_then(|resolve| resolve.as_bool())
+            .unwrap_or(false),
+    }
+}
+
+/// The trigger characters of a `documentOnTypeFormattingProvider`: its
+/// `firstTriggerCharacter` followed by any `moreTriggerCharacter`
+fn parse_on_type_formatting_triggers(provider: &Value) -> Vec<char> {
+    let first = provider.get("firstTriggerCharacter").into_iter();
+    let more = provider
+        .get("moreTriggerCharacter")
+        .and_then(|more| more.as_array())
+        .into_iter()
+        .flatten();
+    let mut triggers: Vec<char> = first
+        .chain(more)
+        .filter_map(|trigger| {
+            let mut chars = trigger.as_str()?.chars();
+            // Triggers are single characters; anything else never matches
+            let ch = chars.next()?;
+            chars.next().is_none().then_some(ch)
+        })
+        .collect();
+    triggers.sort_unstable();
+    triggers.dedup();
+    triggers
+}
+
+/// Whether a `textDocumentSync` capability, a kind or options with a
+/// `change` kind, asks for incremental changes
+fn parse_incremental_sync(sync: &Value) -> bool {
+    let kind = sync.get("change").unwrap_or(sync);
+    kind.as_u64() == Some(2)
+}
+
+fn content_change_event(change: ContentChange) -> TextDocumentContentChangeEvent {
+    let position = |(line, character)| lsp_types::Position { line, character };
+    TextDocumentContentChangeEvent {
+        range: change.range.map(|(start, end)| lsp_types::Range {
+            start: position(start),
+            end: position(end),
+        }),
+        range_length: None,
+        text: change.text,
+    }
+}
+
+/// Parse `capabilities.semanticTokensProvider`. Servers that only answer
+/// range requests are left out, since only whole documents are requested.
+fn parse_semantic_tokens_capability(provider: &Value) -> Option<SemanticTokensCapability> {
+    let names = |field: &str| -> Vec<String> {
+        provider
+            .get("legend")
+            .and_then(|legend| legend.get(field))
+            .and_then(|names| names.as_array())
+            .into_iter()
+            .flatten()
+            .map(|name| name.as_str().unwrap_or_default().to_string())
+            .collect()
+    };
+    let (full, delta) = match provider.get("full") {
+        Some(Value::Bool(full)) => (*full, false),
+        Some(options @ Value::Object(_)) => (
+            true,
+            options
+                .get("delta")
+                .and_then(|delta| delta.as_bool())
+                .unwrap_or(false),
+        ),
+        _ => (false, false),
+    };
+    full.then(|| SemanticTokensCapability {
+        legend: SemanticTokensLegend {
+            token_types: names("tokenTypes"),
+            token_modifiers: names("tokenModifiers"),
+        },
+        delta,
+    })
+}
+
+/// Parse a `textDocument/semanticTokens/full` or `/full/delta` result.
+/// A delta request may be answered with a full result.
+fn parse_semantic_tokens(result: &Value) -> Option<SemanticTokensData> {
+    let integers = |value: Option<&Value>| -> Vec<u32> {
+        value
+            .and_then(|data| data.as_array())
+            .into_iter()
+            .flatten()
+            .map(|n| n.as_u64().unwrap_or(0) as u32)
+            .collect()
+    };
+    if let Some(data) = result.get("data") {
+        return Some(SemanticTokensData::Full(integers(Some(data))));
+    }
+    let edits = result.get("edits")?.as_array()?;
+    Some(SemanticTokensData::Delta(
+        edits
+            .iter()
+            .map(|edit| SemanticTokensEdit {
+                start: edit.get("start").and_then(|n| n.as_u64()).unwrap_or(0) as usize,
+                delete_count: edit
+                    .get("deleteCount")
+                    .and_then(|n| n.as_u64())
+                    .unwrap_or(0) as usize,
+                data: integers(edit.get("data")),
+            })
+            .collect(),
+    ))
+}
+
+fn range_start(range: &Value) -> (usize, usize) {
+    let start = range.get("start");
+    let field = |name: &str| {
+        start
+            .and_then(|s| s.get(name))
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0) as usize
+    };
+    (field("line"), field("character"))
+}
+
+/// Map an LSP `SymbolKind` onto the closest completion kind, so symbols
+/// share the completion icons.
+fn symbol_kind(kind: Option<&Value>) -> CompletionKind {
+    match kind.and_then(|k| k.as_u64()).unwrap_or(0) {
+        1 => CompletionKind::File,
+        2..=4 => CompletionKind::Module,
+        5 => CompletionKind::Class,
+        6 => CompletionKind::Method,
+        7 | 20 => CompletionKind::Property,
+        8 => CompletionKind::Field,
+        9 => CompletionKind::Constructor,
+        10 => CompletionKind::Enum,
+        11 => CompletionKind::Interface,
+        12 => CompletionKind::Function,
+        13 => CompletionKind::Variable,
+        14 => CompletionKind::Constant,
+        15..=18 | 21 => CompletionKind::Value,
+        19 | 23 => CompletionKind::Struct,
+        22 => CompletionKind::EnumMember,
+        24 => CompletionKind::Event,
+        25 => CompletionKind::Operator,
+        26 => CompletionKind::TypeParameter,
+        _ => CompletionKind::Text,
+    }
+}
+
+/// Handle rename response
+fn handle_rename_response(
+    result: Value,
+    request_uri: String,
+    request_version: u64,
+) -> Option<LspNotification> {
+    // Result is a WorkspaceEdit
+    let mut edits: Vec<(String, Vec<TextEdit>)> = Vec::new();
+
+    // Handle "changes" format
+    if let Some(changes) = result.get("changes").and_then(|c| c.as_object()) {
+        for (uri, file_edits) in changes {
+            if let Some(file_edits_arr) = file_edits.as_array() {
+                let text_edits: Vec<TextEdit> = file_edits_arr
+                    .iter()
+                    .filter_map(parse_lsp_text_edit)
+                    .collect();
+                if !text_edits.is_empty() {
+                    edits.push((uri.clone(), text_edits));
+                }
+            }
+        }
+    }
+
+    // Handle "documentChanges" format
     if let Some(doc_changes) = result.get("documentChanges").and_then(|c| c.as_array()) {
         for doc_change in doc_changes {
             if let (Some(text_document), Some(file_edits_arr)) = (
@@ -2072,122 +3703,999 @@ mod tests {
             message: "multi-line problem".to_string(),
             source: Some("test".to_string()),
             code: Some(DiagnosticCode::Number(123)),
+            tags: Vec::new(),
+            related: Vec::new(),
+            href: None,
+        };
+
+        let converted = diagnostic_to_lsp_diagnostic(&diagnostic);
+
+        assert_eq!(converted.range.start.line, 2);
+        assert_eq!(converted.range.end.line, 4);
+        assert_eq!(converted.range.end.character, 7);
+    }
+
+    #[test]
+    fn typescript_server_gets_auto_import_completion_preferences() {
+        let options =
+            initialization_options_for_command("/usr/local/bin/typescript-language-server")
+                .expect("typescript init options");
+
+        let preferences = options
+            .get("preferences")
+            .and_then(|value| value.as_object())
+            .expect("preferences object");
+
+        assert_eq!(
+            preferences
+                .get("includeCompletionsForModuleExports")
+                .and_then(|value| value.as_bool()),
+            Some(true)
+        );
+        assert_eq!(
+            preferences
+                .get("includeCompletionsForImportStatements")
+                .and_then(|value| value.as_bool()),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn non_typescript_servers_do_not_get_typescript_initialization_options() {
+        assert!(initialization_options_for_command("rust-analyzer").is_none());
+    }
+
+    #[test]
+    fn configured_initialization_options_merge_over_builtin_ones() {
+        let merged = merge_initialization_options(
+            initialization_options_for_command("typescript-language-server"),
+            Some(json!({
+                "preferences": { "includeCompletionsWithSnippetText": true },
+                "tsserver": { "logVerbosity": "verbose" }
+            })),
+        )
+        .unwrap();
+
+        assert_eq!(
+            merged["preferences"]["includeCompletionsWithSnippetText"],
+            true
+        );
+        assert_eq!(
+            merged["preferences"]["includeCompletionsForModuleExports"],
+            true
+        );
+        assert_eq!(merged["tsserver"]["logVerbosity"], "verbose");
+        assert_eq!(
+            merge_initialization_options(None, Some(json!({ "a": 1 }))),
+            Some(json!({ "a": 1 }))
+        );
+    }
+
+    #[test]
+    fn workspace_configuration_answers_each_requested_section() {
+        let pending = Arc::new(Mutex::new(HashMap::new()));
+        let settings: SharedSettings = Arc::new(Mutex::new(json!({
+            "rust-analyzer": { "check": { "command": "clippy" } },
+            "python": { "analysis": { "typeCheckingMode": "strict" } }
+        })));
+        let request = JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(JsonRpcId::Num(7)),
+            result: None,
+            error: None,
+            method: Some("workspace/configuration".to_string()),
+            params: Some(json!({
+                "items": [
+                    { "section": "rust-analyzer" },
+                    { "section": "python.analysis" },
+                    { "section": "gopls" },
+                    {}
+                ]
+            })),
+        };
+
+        let (notification, response) = handle_message(request, &pending, None, &settings);
+
+        assert!(notification.is_none());
+        let response = response.unwrap();
+        let body = response.split("\r\n\r\n").nth(1).unwrap();
+        let response: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(response["id"], 7);
+        assert_eq!(
+            response["result"],
+            json!([
+                { "check": { "command": "clippy" } },
+                { "typeCheckingMode": "strict" },
+                {},
+                {
+                    "rust-analyzer": { "check": { "command": "clippy" } },
+                    "python": { "analysis": { "typeCheckingMode": "strict" } }
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn client_capabilities_advertise_work_done_progress() {
+        let capabilities = client_capabilities();
+
+        assert_eq!(
+            capabilities
+                .window
+                .and_then(|window| window.work_done_progress),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn client_capabilities_delegate_file_watching_to_the_client() {
+        let capabilities = client_capabilities();
+
+        let watched_files = capabilities
+            .workspace
+            .and_then(|workspace| workspace.did_change_watched_files)
+            .expect("watched-file capabilities");
+
+        assert_eq!(watched_files.dynamic_registration, Some(true));
+        assert_eq!(watched_files.relative_pattern_support, Some(true));
+    }
+
+    #[test]
+    fn watched_file_registration_is_forwarded_before_success_response() {
+        let root = std::env::temp_dir();
+        let (command_tx, command_rx) = mpsc::channel();
+        let worker = thread::spawn(move || match command_rx.recv().expect("watcher command") {
+            WatcherCommand::Register {
+                registrations,
+                reply,
+            } => {
+                assert_eq!(registrations.len(), 1);
+                assert_eq!(registrations[0].id, "rust-files");
+                reply.send(Ok(())).unwrap();
+            }
+            other => panic!("unexpected command: {other:?}"),
+        });
+
+        let response = handle_server_request(
+            JsonRpcId::Num(7),
+            "client/registerCapability",
+            watched_file_register_params(&root),
+            Some(&command_tx),
+        )
+        .expect("response");
+
+        worker.join().unwrap();
+        assert!(response.contains("\"result\":null"));
+    }
+
+    #[test]
+    fn watched_file_unregistration_is_forwarded_before_success_response() {
+        let (command_tx, command_rx) = mpsc::channel();
+        let worker = thread::spawn(move || match command_rx.recv().expect("watcher command") {
+            WatcherCommand::Unregister {
+                registration_ids,
+                reply,
+            } => {
+                assert_eq!(registration_ids, vec!["rust-files".to_string()]);
+                reply.send(Ok(())).unwrap();
+            }
+            other => panic!("unexpected command: {other:?}"),
+        });
+
+        let response = handle_server_request(
+            JsonRpcId::Num(9),
+            "client/unregisterCapability",
+            watched_file_unregister_params(),
+            Some(&command_tx),
+        )
+        .expect("response");
+
+        worker.join().unwrap();
+        assert!(response.contains("\"result\":null"));
+    }
+
+    #[test]
+    fn apply_edit_request_is_forwarded_to_the_editor_unanswered() {
+        let pending = Arc::new(Mutex::new(HashMap::new()));
+        let request = JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(JsonRpcId::Str("edit-1".to_string())),
+            result: None,
+            error: None,
+            method: Some("workspace/applyEdit".to_string()),
+            params: Some(json!({
+                "label": "Organize imports",
+                "edit": {
+                    "documentChanges": [
+                        { "kind": "create", "uri": "file:///tmp/new.rs", "options": { "ignoreIfExists": true } },
+                        {
+                            "textDocument": { "uri": "file:///tmp/lib.rs", "version": 4 },
+                            "edits": [{
+                                "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 0 } },
+                                "newText": "mod new;\n"
+                            }]
+                        },
+                        { "kind": "rename", "oldUri": "file:///tmp/a.rs", "newUri": "file:///tmp/b.rs" },
+                        { "kind": "delete", "uri": "file:///tmp/old", "options": { "recursive": true } }
+                    ],
+                    "changes": { "file:///tmp/ignored.rs": [] }
+                }
+            })),
+        };
+
+        let (notification, response) =
+            handle_message(request, &pending, None, &SharedSettings::default());
+
+        assert!(response.is_none());
+        let Some(LspNotification::ApplyEdit {
+            request_id,
+            label,
+            edit,
+        }) = notification
+        else {
+            panic!("expected ApplyEdit notification");
+        };
+        assert_eq!(request_id, json!("edit-1"));
+        assert_eq!(label.as_deref(), Some("Organize imports"));
+        assert_eq!(edit.changes.len(), 4);
+        assert!(matches!(
+            &edit.changes[0],
+            WorkspaceEditChange::CreateFile { uri, overwrite: false, ignore_if_exists: true }
+                if uri == "file:///tmp/new.rs"
+        ));
+        assert!(matches!(
+            &edit.changes[1],
+            WorkspaceEditChange::Edit { uri, version: Some(4), edits }
+                if uri == "file:///tmp/lib.rs" && edits[0].new_text == "mod new;\n"
+        ));
+        assert!(matches!(
+            &edit.changes[2],
+            WorkspaceEditChange::RenameFile { old_uri, new_uri, .. }
+                if old_uri == "file:///tmp/a.rs" && new_uri == "file:///tmp/b.rs"
+        ));
+        assert!(matches!(
+            &edit.changes[3],
+            WorkspaceEditChange::DeleteFile {
+                recursive: true,
+                ignore_if_not_exists: false,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn apply_edit_without_document_changes_uses_the_changes_map() {
+        let edit = parse_workspace_edit(&json!({
+            "changes": {
+                "file:///tmp/main.rs": [{
+                    "range": { "start": { "line": 1, "character": 2 }, "end": { "line": 1, "character": 5 } },
+                    "newText": "foo"
+                }]
+            }
+        }));
+
+        assert_eq!(edit.changes.len(), 1);
+        assert!(matches!(
+            &edit.changes[0],
+            WorkspaceEditChange::Edit { uri, version: None, edits }
+                if uri == "file:///tmp/main.rs" && edits[0].end_col == 5
+        ));
+    }
+
+    #[test]
+    fn client_capabilities_advertise_workspace_edits() {
+        let workspace = client_capabilities()
+            .workspace
+            .expect("workspace capabilities");
+        assert_eq!(workspace.apply_edit, Some(true));
+        let workspace_edit = workspace
+            .workspace_edit
+            .expect("workspace edit capabilities");
+        assert_eq!(workspace_edit.document_changes, Some(true));
+        assert_eq!(
+            workspace_edit.resource_operations.map(|ops| ops.len()),
+            Some(3)
+        );
+        assert!(workspace.execute_command.is_some());
+    }
+
+    #[test]
+    fn code_action_commands_keep_their_arguments() {
+        let Some(LspNotification::CodeActions { actions, .. }) = handle_code_action_response(
+            json!([
+                {
+                    "title": "Run test",
+                    "command": "rust-analyzer.runSingle",
+                    "arguments": [{ "label": "test it" }]
+                },
+                {
+                    "title": "Fix all",
+                    "kind": "source.fixAll",
+                    "command": { "title": "Fix all", "command": "eslint.applyAllFixes" }
+                }
+            ]),
+            "file:///tmp/main.rs".to_string(),
+            1,
+        ) else {
+            panic!("expected CodeActions notification");
+        };
+
+        assert_eq!(
+            actions[0].command,
+            Some(LspCommand {
+                title: "Run test".to_string(),
+                command: "rust-analyzer.runSingle".to_string(),
+                arguments: vec![json!({ "label": "test it" })],
+                server: None,
+            })
+        );
+        let fix_all = actions[1].command.as_ref().expect("nested command");
+        assert_eq!(fix_all.command, "eslint.applyAllFixes");
+        assert!(fix_all.arguments.is_empty());
+    }
+
+    #[test]
+    fn hierarchy_children_point_incoming_calls_at_the_call_site() {
+        let item = |name: &str, line: u64| {
+            json!({
+                "name": name,
+                "kind": 12,
+                "detail": "fn()",
+                "uri": "file:///tmp/lib.rs",
+                "range": { "start": { "line": line, "character": 0 }, "end": { "line": line + 4, "character": 1 } },
+                "selectionRange": { "start": { "line": line, "character": 3 }, "end": { "line": line, "character": 8 } },
+                "data": { "id": name }
+            })
+        };
+        let call_site = json!({ "start": { "line": 12, "character": 4 }, "end": { "line": 12, "character": 9 } });
+
+        let incoming = parse_hierarchy_children(
+            &json!([{ "from": item("main", 10), "fromRanges": [call_site] }]),
+            HierarchyDirection::IncomingCalls,
+        );
+        let outgoing = parse_hierarchy_children(
+            &json!([{ "to": item("parse", 30), "fromRanges": [call_site] }]),
+            HierarchyDirection::OutgoingCalls,
+        );
+        let subtypes =
+            parse_hierarchy_children(&json!([item("Circle", 50)]), HierarchyDirection::Subtypes);
+
+        assert_eq!((incoming[0].line, incoming[0].col), (12, 4));
+        assert_eq!(incoming[0].name, "main");
+        assert_eq!(incoming[0].kind, CompletionKind::Function);
+        assert_eq!(incoming[0].detail.as_deref(), Some("fn()"));
+        assert_eq!(incoming[0].raw["data"], json!({ "id": "main" }));
+        assert_eq!((outgoing[0].line, outgoing[0].col), (30, 3));
+        assert_eq!(subtypes[0].name, "Circle");
+    }
+
+    #[test]
+    fn failed_hierarchy_expansion_yields_no_children() {
+        let pending = Arc::new(Mutex::new(HashMap::new()));
+        pending.lock().unwrap().insert(
+            4,
+            RequestKind::HierarchyChildren {
+                direction: HierarchyDirection::Supertypes,
+                node: 9,
+            },
+        );
+        let response = JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(JsonRpcId::Num(4)),
+            result: None,
+            error: Some(JsonRpcError {
+                code: -32603,
+                message: "no type hierarchy".to_string(),
+            }),
+            method: None,
+            params: None,
+        };
+
+        let (notification, _) =
+            handle_message(response, &pending, None, &SharedSettings::default());
+
+        assert!(matches!(
+            notification,
+            Some(LspNotification::HierarchyChildren { node: 9, items, .. }) if items.is_empty()
+        ));
+    }
+
+    #[test]
+    fn document_symbols_flatten_in_outline_order() {
+        let range = |line: u64, character: u64| {
+            json!({
+                "start": { "line": line, "character": character },
+                "end": { "line": line, "character": character + 3 }
+            })
+        };
+        let symbols = parse_document_symbols(
+            &json!([{
+                "name": "Parser",
+                "detail": "struct",
+                "kind": 23,
+                "range": range(0, 0),
+                "selectionRange": range(0, 11),
+                "children": [{
+                    "name": "parse",
+                    "kind": 6,
+                    "range": range(2, 4),
+                    "selectionRange": range(2, 11)
+                }]
+            }]),
+            "file:///tmp/lib.rs",
+        );
+
+        let outline: Vec<_> = symbols
+            .iter()
+            .map(|s| (s.name.as_str(), s.depth, s.line, s.col, s.kind))
+            .collect();
+        assert_eq!(
+            outline,
+            vec![
+                ("Parser", 0, 0, 11, CompletionKind::Struct),
+                ("parse", 1, 2, 11, CompletionKind::Method),
+            ]
+        );
+        assert_eq!(symbols[0].detail.as_deref(), Some("struct"));
+        assert_eq!(symbols[1].uri, "file:///tmp/lib.rs");
+    }
+
+    #[test]
+    fn flat_symbol_information_is_sorted_by_position() {
+        let symbols = parse_document_symbols(
+            &json!([
+                {
+                    "name": "second",
+                    "kind": 12,
+                    "location": {
+                        "uri": "file:///tmp/lib.rs",
+                        "range": { "start": { "line": 9, "character": 3 }, "end": { "line": 9, "character": 9 } }
+                    },
+                    "containerName": "lib"
+                },
+                {
+                    "name": "first",
+                    "kind": 13,
+                    "location": {
+                        "uri": "file:///tmp/lib.rs",
+                        "range": { "start": { "line": 1, "character": 0 }, "end": { "line": 1, "character": 5 } }
+                    }
+                }
+            ]),
+            "file:///tmp/lib.rs",
+        );
+
+        let names: Vec<_> = symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["first", "second"]);
+        assert_eq!(symbols[1].container.as_deref(), Some("lib"));
+    }
+
+    #[test]
+    fn workspace_symbols_accept_locations_without_ranges() {
+        let symbols = parse_workspace_symbols(&json!([
+            {
+                "name": "Config",
+                "kind": 5,
+                "location": { "uri": "file:///tmp/config.rs" }
+            },
+            { "name": "broken", "kind": 12 }
+        ]));
+
+        assert_eq!(symbols.len(), 1);
+        assert_eq!(symbols[0].name, "Config");
+        assert_eq!(symbols[0].kind, CompletionKind::Class);
+        assert_eq!((symbols[0].line, symbols[0].col), (0, 0));
+    }
+
+    #[test]
+    fn execute_command_response_reports_completion() {
+        let pending = Arc::new(Mutex::new(HashMap::new()));
+        pending.lock().unwrap().insert(
+            7,
+            RequestKind::ExecuteCommand {
+                title: "Fix all".to_string(),
+            },
+        );
+        let response = JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(JsonRpcId::Num(7)),
+            result: Some(Value::Null),
+            error: None,
+            method: None,
+            params: None,
+        };
+
+        let (notification, reply) =
+            handle_message(response, &pending, None, &SharedSettings::default());
+
+        assert!(reply.is_none());
+        assert!(matches!(
+            notification,
+            Some(LspNotification::CommandExecuted { title }) if title == "Fix all"
+        ));
+        assert!(pending.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn inlay_hints_join_label_parts_in_position_order() {
+        let hints = parse_inlay_hints(&json!([
+            {
+                "position": { "line": 3, "character": 12 },
+                "label": [{ "value": "x" }, { "value": ": " }],
+                "kind": 2,
+                "paddingRight": true
+            },
+            {
+                "position": { "line": 1, "character": 9 },
+                "label": ": Vec<u8>",
+                "kind": 1
+            },
+            { "position": { "line": 2, "character": 0 }, "label": "" }
+        ]));
+
+        assert_eq!(
+            hints,
+            vec![
+                InlayHint {
+                    line: 1,
+                    col: 9,
+                    label: ": Vec<u8>".to_string(),
+                    kind: Some(InlayHintKind::Type),
+                    padding_left: false,
+                    padding_right: false,
+                },
+                InlayHint {
+                    line: 3,
+                    col: 12,
+                    label: "x: ".to_string(),
+                    kind: Some(InlayHintKind::Parameter),
+                    padding_left: false,
+                    padding_right: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn document_highlights_keep_kinds_in_document_order() {
+        let highlights = parse_document_highlights(&json!([
+            {
+                "range": {
+                    "start": { "line": 4, "character": 2 },
+                    "end": { "line": 4, "character": 7 }
+                },
+                "kind": 3
+            },
+            {
+                "range": {
+                    "start": { "line": 1, "character": 8 },
+                    "end": { "line": 1, "character": 13 }
+                }
+            },
+            { "kind": 2 }
+        ]));
+
+        assert_eq!(
+            highlights,
+            vec![
+                DocumentHighlight {
+                    range: TextRange {
+                        start_line: 1,
+                        start_col: 8,
+                        end_line: 1,
+                        end_col: 13,
+                    },
+                    kind: DocumentHighlightKind::Text,
+                },
+                DocumentHighlight {
+                    range: TextRange {
+                        start_line: 4,
+                        start_col: 2,
+                        end_line: 4,
+                        end_col: 7,
+                    },
+                    kind: DocumentHighlightKind::Write,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn selection_ranges_flatten_parents_innermost_first() {
+        let ranges = parse_selection_ranges(&json!([{
+            "range": {
+                "start": { "line": 2, "character": 8 },
+                "end": { "line": 2, "character": 11 }
+            },
+            "parent": {
+                "range": {
+                    "start": { "line": 2, "character": 4 },
+                    "end": { "line": 2, "character": 16 }
+                },
+                "parent": {
+                    "range": {
+                        "start": { "line": 0, "character": 0 },
+                        "end": { "line": 3, "character": 1 }
+                    }
+                }
+            }
+        }]));
+
+        assert_eq!(
+            ranges
+                .iter()
+                .map(|r| (r.start_line, r.start_col, r.end_line, r.end_col))
+                .collect::<Vec<_>>(),
+            vec![(2, 8, 2, 11), (2, 4, 2, 16), (0, 0, 3, 1)]
+        );
+        assert!(parse_selection_ranges(&Value::Null).is_empty());
+    }
+
+    #[test]
+    fn selection_range_errors_answer_with_no_ranges() {
+        let pending = Arc::new(Mutex::new(HashMap::new()));
+        pending.lock().unwrap().insert(
+            4,
+            RequestKind::SelectionRange {
+                uri: "file:///tmp/main.rs".to_string(),
+                buffer_version: 2,
+            },
+        );
+        let response = JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(JsonRpcId::Num(4)),
+            result: None,
+            error: Some(JsonRpcError {
+                code: -32601,
+                message: "method not found".to_string(),
+            }),
+            method: None,
+            params: None,
         };
 
-        let converted = diagnostic_to_lsp_diagnostic(&diagnostic);
-
-        assert_eq!(converted.range.start.line, 2);
-        assert_eq!(converted.range.end.line, 4);
-        assert_eq!(converted.range.end.character, 7);
+        let (notification, _) =
+            handle_message(response, &pending, None, &SharedSettings::default());
+        assert!(matches!(
+            notification,
+            Some(LspNotification::SelectionRanges { ranges, request_version: 2, .. }) if ranges.is_empty()
+        ));
     }
 
     #[test]
-    fn typescript_server_gets_auto_import_completion_preferences() {
-        let options =
-            initialization_options_for_command("/usr/local/bin/typescript-language-server")
-                .expect("typescript init options");
+    fn inlay_hint_errors_and_refreshes_do_not_surface_as_errors() {
+        let pending = Arc::new(Mutex::new(HashMap::new()));
+        pending.lock().unwrap().insert(
+            3,
+            RequestKind::InlayHints {
+                uri: "file:///tmp/main.rs".to_string(),
+                start_line: 0,
+                end_line: 40,
+                buffer_version: 5,
+            },
+        );
+        let response = JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(JsonRpcId::Num(3)),
+            result: None,
+            error: Some(JsonRpcError {
+                code: -32801,
+                message: "content modified".to_string(),
+            }),
+            method: None,
+            params: None,
+        };
 
-        let preferences = options
-            .get("preferences")
-            .and_then(|value| value.as_object())
-            .expect("preferences object");
+        let (notification, _) =
+            handle_message(response, &pending, None, &SharedSettings::default());
+        assert!(matches!(
+            notification,
+            Some(LspNotification::InlayHints { hints, request_version: 5, .. }) if hints.is_empty()
+        ));
+        assert!(pending.lock().unwrap().is_empty());
 
-        assert_eq!(
-            preferences
-                .get("includeCompletionsForModuleExports")
-                .and_then(|value| value.as_bool()),
-            Some(true)
-        );
-        assert_eq!(
-            preferences
-                .get("includeCompletionsForImportStatements")
-                .and_then(|value| value.as_bool()),
-            Some(true)
-        );
+        let refresh = JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(JsonRpcId::Num(8)),
+            result: None,
+            error: None,
+            method: Some("workspace/inlayHint/refresh".to_string()),
+            params: None,
+        };
+        let (notification, reply) =
+            handle_message(refresh, &pending, None, &SharedSettings::default());
+        assert!(matches!(
+            notification,
+            Some(LspNotification::InlayHintRefresh)
+        ));
+        assert!(reply.expect("refresh reply").contains("\"result\":null"));
     }
 
     #[test]
-    fn non_typescript_servers_do_not_get_typescript_initialization_options() {
-        assert!(initialization_options_for_command("rust-analyzer").is_none());
+    fn code_lenses_keep_server_order_and_resolve_by_index() {
+        let lenses = parse_code_lenses(&json!([
+            {
+                "range": { "start": { "line": 4, "character": 0 }, "end": { "line": 4, "character": 9 } },
+                "command": { "title": "▶ Run test", "command": "rust-analyzer.runSingle", "arguments": [{ "label": "test it_works" }] }
+            },
+            {
+                "range": { "start": { "line": 1, "character": 3 }, "end": { "line": 1, "character": 7 } },
+                "data": { "id": 7 }
+            }
+        ]));
+        assert_eq!(lenses.len(), 2);
+        assert_eq!((lenses[0].line, lenses[0].col), (4, 0));
+        let run = lenses[0].command.as_ref().expect("resolved lens");
+        assert_eq!(run.title, "▶ Run test");
+        assert_eq!(run.command, "rust-analyzer.runSingle");
+        assert_eq!(run.arguments.len(), 1);
+        assert_eq!(lenses[1].command, None);
+        assert_eq!(lenses[1].raw["data"]["id"], 7);
+
+        let pending = Arc::new(Mutex::new(HashMap::new()));
+        pending.lock().unwrap().insert(
+            6,
+            RequestKind::CodeLensResolve {
+                uri: "file:///tmp/main.rs".to_string(),
+                buffer_version: 3,
+                index: 1,
+            },
+        );
+        let response = JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(JsonRpcId::Num(6)),
+            result: Some(json!({
+                "range": { "start": { "line": 1, "character": 3 }, "end": { "line": 1, "character": 7 } },
+                "command": { "title": "3 references", "command": "rust-analyzer.showReferences" }
+            })),
+            error: None,
+            method: None,
+            params: None,
+        };
+        let (notification, _) =
+            handle_message(response, &pending, None, &SharedSettings::default());
+        assert!(matches!(
+            notification,
+            Some(LspNotification::CodeLensResolved { lens, index: 1, request_version: 3, .. })
+                if lens.command.as_ref().is_some_and(|command| command.title == "3 references")
+        ));
+
+        let refresh = JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(JsonRpcId::Num(9)),
+            result: None,
+            error: None,
+            method: Some("workspace/codeLens/refresh".to_string()),
+            params: None,
+        };
+        let (notification, reply) =
+            handle_message(refresh, &pending, None, &SharedSettings::default());
+        assert!(matches!(
+            notification,
+            Some(LspNotification::CodeLensRefresh)
+        ));
+        assert!(reply.expect("refresh reply").contains("\"result\":null"));
     }
 
     #[test]
-    fn client_capabilities_advertise_work_done_progress() {
-        let capabilities = client_capabilities();
+    fn initialize_response_carries_the_semantic_tokens_legend() {
+        let pending = Arc::new(Mutex::new(HashMap::new()));
+        pending.lock().unwrap().insert(1, RequestKind::Initialize);
+        let response = JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(JsonRpcId::Num(1)),
+            result: Some(json!({
+                "capabilities": {
+                    "semanticTokensProvider": {
+                        "legend": {
+                            "tokenTypes": ["variable", "interface"],
+                            "tokenModifiers": ["mutable"]
+                        },
+                        "full": { "delta": true }
+                    },
+                    "textDocumentSync": { "openClose": true, "change": 2 }
+                }
+            })),
+            error: None,
+            method: None,
+            params: None,
+        };
 
+        let (notification, _) =
+            handle_message(response, &pending, None, &SharedSettings::default());
+        let Some(LspNotification::Initialized { capabilities }) = notification else {
+            panic!("expected Initialized");
+        };
         assert_eq!(
-            capabilities
-                .window
-                .and_then(|window| window.work_done_progress),
-            Some(true)
+            capabilities.semantic_tokens,
+            Some(SemanticTokensCapability {
+                legend: SemanticTokensLegend {
+                    token_types: vec!["variable".to_string(), "interface".to_string()],
+                    token_modifiers: vec!["mutable".to_string()],
+                },
+                delta: true,
+            })
         );
+        // Range-only providers are not used.
+        assert_eq!(
+            parse_semantic_tokens_capability(&json!({ "legend": {}, "range": true })),
+            None
+        );
+        assert!(capabilities.incremental_sync);
+        assert_eq!(
+            capabilities.providers,
+            vec!["semanticTokensProvider", "textDocumentSync"]
+        );
+        assert!(capabilities.supports("semanticTokensProvider"));
+        assert!(!capabilities.supports("hoverProvider"));
     }
 
     #[test]
-    fn client_capabilities_delegate_file_watching_to_the_client() {
-        let capabilities = client_capabilities();
-
-        let watched_files = capabilities
-            .workspace
-            .and_then(|workspace| workspace.did_change_watched_files)
-            .expect("watched-file capabilities");
-
-        assert_eq!(watched_files.dynamic_registration, Some(true));
-        assert_eq!(watched_files.relative_pattern_support, Some(true));
+    fn on_type_formatting_triggers_combine_first_and_more_characters() {
+        let capabilities = parse_server_capabilities(&json!({
+            "documentOnTypeFormattingProvider": {
+                "firstTriggerCharacter": "}",
+                "moreTriggerCharacter": [";", "\n", "}", "=>"]
+            }
+        }));
+        assert_eq!(
+            capabilities.on_type_formatting_triggers,
+            vec!['\n', ';', '}']
+        );
+        assert!(capabilities.supports("documentOnTypeFormattingProvider"));
+        assert!(
+            parse_server_capabilities(&json!({}))
+                .on_type_formatting_triggers
+                .is_empty()
+        );
     }
 
     #[test]
-    fn watched_file_registration_is_forwarded_before_success_response() {
-        let root = std::env::temp_dir();
-        let (command_tx, command_rx) = mpsc::channel();
-        let worker = thread::spawn(move || match command_rx.recv().expect("watcher command") {
-            WatcherCommand::Register {
-                registrations,
-                reply,
-            } => {
-                assert_eq!(registrations.len(), 1);
-                assert_eq!(registrations[0].id, "rust-files");
-                reply.send(Ok(())).unwrap();
-            }
-            other => panic!("unexpected command: {other:?}"),
-        });
+    fn range_and_on_type_formatting_answer_as_formatting_edits() {
+        let pending = Arc::new(Mutex::new(HashMap::new()));
+        pending.lock().unwrap().insert(
+            7,
+            RequestKind::RangeFormatting {
+                uri: "file:///tmp/main.rs".to_string(),
+                buffer_version: 3,
+            },
+        );
+        pending.lock().unwrap().insert(
+            8,
+            RequestKind::OnTypeFormatting {
+                uri: "file:///tmp/main.rs".to_string(),
+                buffer_version: 4,
+            },
+        );
 
-        let response = handle_server_request(
-            JsonRpcId::Num(7),
-            "client/registerCapability",
-            watched_file_register_params(&root),
-            Some(&command_tx),
-        )
-        .expect("response");
+        let response = JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(JsonRpcId::Num(7)),
+            result: Some(json!([{
+                "range": {
+                    "start": { "line": 2, "character": 0 },
+                    "end": { "line": 2, "character": 2 }
+                },
+                "newText": "    "
+            }])),
+            error: None,
+            method: None,
+            params: None,
+        };
+        match handle_message(response, &pending, None, &SharedSettings::default()).0 {
+            Some(LspNotification::Formatting {
+                edits,
+                request_uri,
+                request_version,
+            }) => {
+                assert_eq!(request_uri, "file:///tmp/main.rs");
+                assert_eq!(request_version, 3);
+                assert_eq!(edits.len(), 1);
+                assert_eq!(edits[0].new_text, "    ");
+            }
+            other => panic!("expected formatting edits, got {other:?}"),
+        }
 
-        worker.join().unwrap();
-        assert!(response.contains("\"result\":null"));
+        // A failed on-type request keeps the text as typed, without an error.
+        let response = JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(JsonRpcId::Num(8)),
+            result: None,
+            error: Some(JsonRpcError {
+                code: -32603,
+                message: "no formatting here".to_string(),
+            }),
+            method: None,
+            params: None,
+        };
+        match handle_message(response, &pending, None, &SharedSettings::default()).0 {
+            Some(LspNotification::Formatting {
+                edits,
+                request_version: 4,
+                ..
+            }) => assert!(edits.is_empty()),
+            other => panic!("expected empty formatting edits, got {other:?}"),
+        }
     }
 
     #[test]
-    fn watched_file_unregistration_is_forwarded_before_success_response() {
-        let (command_tx, command_rx) = mpsc::channel();
-        let worker = thread::spawn(move || match command_rx.recv().expect("watcher command") {
-            WatcherCommand::Unregister {
-                registration_ids,
-                reply,
-            } => {
-                assert_eq!(registration_ids, vec!["rust-files".to_string()]);
-                reply.send(Ok(())).unwrap();
-            }
-            other => panic!("unexpected command: {other:?}"),
+    fn did_change_sends_ranges_only_to_incremental_servers() {
+        assert!(parse_incremental_sync(&json!(2)));
+        assert!(!parse_incremental_sync(&json!(1)));
+        assert!(!parse_incremental_sync(&json!({ "openClose": true })));
+
+        let event = content_change_event(ContentChange {
+            range: Some(((1, 3), (2, 0))),
+            text: "x".to_string(),
+        });
+        assert_eq!(
+            serde_json::to_value(event).unwrap(),
+            json!({
+                "range": {
+                    "start": { "line": 1, "character": 3 },
+                    "end": { "line": 2, "character": 0 }
+                },
+                "text": "x"
+            })
+        );
+        let event = content_change_event(ContentChange {
+            range: None,
+            text: "all".to_string(),
         });
+        assert_eq!(
+            serde_json::to_value(event).unwrap(),
+            json!({ "text": "all" })
+        );
+    }
 
-        let response = handle_server_request(
-            JsonRpcId::Num(9),
-            "client/unregisterCapability",
-            watched_file_unregister_params(),
-            Some(&command_tx),
-        )
-        .expect("response");
+    #[test]
+    fn semantic_tokens_parse_full_and_delta_results() {
+        assert_eq!(
+            parse_semantic_tokens(&json!({ "resultId": "1", "data": [0, 4, 1, 0, 1] })),
+            Some(SemanticTokensData::Full(vec![0, 4, 1, 0, 1]))
+        );
+        assert_eq!(
+            parse_semantic_tokens(&json!({
+                "resultId": "2",
+                "edits": [{ "start": 5, "deleteCount": 0, "data": [1, 0, 2, 1, 0] }]
+            })),
+            Some(SemanticTokensData::Delta(vec![SemanticTokensEdit {
+                start: 5,
+                delete_count: 0,
+                data: vec![1, 0, 2, 1, 0],
+            }]))
+        );
+        assert_eq!(parse_semantic_tokens(&Value::Null), None);
 
-        worker.join().unwrap();
-        assert!(response.contains("\"result\":null"));
+        let pending = Arc::new(Mutex::new(HashMap::new()));
+        pending.lock().unwrap().insert(
+            4,
+            RequestKind::SemanticTokens {
+                uri: "file:///tmp/main.rs".to_string(),
+                previous_result_id: Some("1".to_string()),
+                buffer_version: 2,
+            },
+        );
+        let response = JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(JsonRpcId::Num(4)),
+            result: None,
+            error: Some(JsonRpcError {
+                code: -32802,
+                message: "server cancelled".to_string(),
+            }),
+            method: None,
+            params: None,
+        };
+        let (notification, _) =
+            handle_message(response, &pending, None, &SharedSettings::default());
+        assert!(matches!(
+            notification,
+            Some(LspNotification::SemanticTokens {
+                tokens: None,
+                request_version: 2,
+                ..
+            })
+        ));
     }
 
     #[test]
@@ -2252,7 +4760,8 @@ mod tests {
             params: None,
         };
 
-        let (notification, server_response) = handle_message(response, &pending, None);
+        let (notification, server_response) =
+            handle_message(response, &pending, None, &SharedSettings::default());
 
         assert!(server_response.is_none());
         let notification = notification.expect("declaration notification");
@@ -2289,7 +4798,8 @@ mod tests {
             params: None,
         };
 
-        let (notification, server_response) = handle_message(response, &pending, None);
+        let (notification, server_response) =
+            handle_message(response, &pending, None, &SharedSettings::default());
 
         assert!(server_response.is_none());
         let notification = notification.expect("implementation notification");
@@ -2474,4 +4984,174 @@ mod tests {
             other => panic!("expected server status notification, got {other:?}"),
         }
     }
+
+    #[test]
+    fn published_diagnostics_carry_tags_related_locations_and_href() {
+        let notification = handle_notification(
+            "textDocument/publishDiagnostics",
+            Some(json!({
+                "uri": "file:///tmp/app.py",
+                "diagnostics": [{
+                    "range": {
+                        "start": { "line": 0, "character": 7 },
+                        "end": { "line": 0, "character": 9 }
+                    },
+                    "severity": 2,
+                    "code": "F401",
+                    "codeDescription": { "href": "https://docs.astral.sh/ruff/rules/F401" },
+                    "message": "`os` imported but unused",
+                    "tags": [1, 2, 99],
+                    "relatedInformation": [{
+                        "location": {
+                            "uri": "file:///tmp/util.py",
+                            "range": {
+                                "start": { "line": 4, "character": 2 },
+                                "end": { "line": 4, "character": 6 }
+                            }
+                        },
+                        "message": "first imported here"
+                    }]
+                }]
+            })),
+        );
+        let Some(LspNotification::Diagnostics { diagnostics, .. }) = notification else {
+            panic!("expected diagnostics, got {notification:?}");
+        };
+        let diagnostic = &diagnostics[0];
+        assert_eq!(
+            diagnostic.tags,
+            vec![DiagnosticTag::Unnecessary, DiagnosticTag::Deprecated]
+        );
+        assert_eq!(
+            diagnostic.related,
+            vec![DiagnosticRelatedInformation {
+                uri: "file:///tmp/util.py".to_string(),
+                line: 4,
+                col: 2,
+                message: "first imported here".to_string(),
+            }]
+        );
+        assert_eq!(
+            diagnostic.href.as_deref(),
+            Some("https://docs.astral.sh/ruff/rules/F401")
+        );
+        assert_eq!(
+            diagnostic_to_lsp_diagnostic(diagnostic).tags,
+            Some(vec![
+                lsp_types::DiagnosticTag::UNNECESSARY,
+                lsp_types::DiagnosticTag::DEPRECATED
+            ])
+        );
+    }
+
+    #[test]
+    fn pulled_diagnostic_reports_are_full_or_unchanged() {
+        let pending = Arc::new(Mutex::new(HashMap::new()));
+        pending.lock().unwrap().insert(
+            6,
+            RequestKind::DocumentDiagnostic {
+                uri: "file:///tmp/app.py".to_string(),
+            },
+        );
+        let response = JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(JsonRpcId::Num(6)),
+            result: Some(json!({
+                "kind": "full",
+                "resultId": "r1",
+                "items": [{
+                    "range": {
+                        "start": { "line": 1, "character": 0 },
+                        "end": { "line": 1, "character": 3 }
+                    },
+                    "message": "undefined name"
+                }]
+            })),
+            error: None,
+            method: None,
+            params: None,
+        };
+        let (notification, _) =
+            handle_message(response, &pending, None, &SharedSettings::default());
+        let Some(LspNotification::PulledDiagnostics { reports }) = notification else {
+            panic!("expected pulled diagnostics, got {notification:?}");
+        };
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].uri, "file:///tmp/app.py");
+        assert_eq!(reports[0].result_id.as_deref(), Some("r1"));
+        assert_eq!(
+            reports[0]
+                .diagnostics
+                .as_ref()
+                .map(|items| items[0].message.as_str()),
+            Some("undefined name")
+        );
+
+        let reports = parse_workspace_diagnostic_reports(&json!({
+            "items": [
+                { "uri": "file:///tmp/app.py", "kind": "unchanged", "resultId": "r1" },
+                { "uri": "file:///tmp/util.py", "kind": "full", "items": [] },
+                { "kind": "full", "items": [] }
+            ]
+        }));
+        assert_eq!(reports.len(), 2);
+        assert!(reports[0].diagnostics.is_none());
+        assert_eq!(reports[0].result_id.as_deref(), Some("r1"));
+        assert_eq!(reports[1].diagnostics.as_ref().map(Vec::len), Some(0));
+    }
+
+    #[test]
+    fn diagnostic_pull_errors_are_silent_and_refreshes_are_answered() {
+        let pending = Arc::new(Mutex::new(HashMap::new()));
+        pending
+            .lock()
+            .unwrap()
+            .insert(7, RequestKind::WorkspaceDiagnostic);
+        let response = JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(JsonRpcId::Num(7)),
+            result: None,
+            error: Some(JsonRpcError {
+                code: -32800,
+                message: "request cancelled".to_string(),
+            }),
+            method: None,
+            params: None,
+        };
+        let (notification, reply) =
+            handle_message(response, &pending, None, &SharedSettings::default());
+        assert!(notification.is_none());
+        assert!(reply.is_none());
+
+        let refresh = JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(JsonRpcId::Num(9)),
+            result: None,
+            error: None,
+            method: Some("workspace/diagnostic/refresh".to_string()),
+            params: None,
+        };
+        let (notification, reply) =
+            handle_message(refresh, &pending, None, &SharedSettings::default());
+        assert!(matches!(
+            notification,
+            Some(LspNotification::DiagnosticRefresh)
+        ));
+        assert!(reply.expect("refresh reply").contains("\"result\":null"));
+
+        let capabilities = parse_server_capabilities(&json!({
+            "diagnosticProvider": {
+                "interFileDependencies": true,
+                "workspaceDiagnostics": true
+            }
+        }));
+        assert!(capabilities.supports("diagnosticProvider"));
+        assert!(capabilities.workspace_diagnostics);
+
+        let capabilities = parse_server_capabilities(&json!({
+            "codeLensProvider": { "resolveProvider": true }
+        }));
+        assert!(capabilities.supports("codeLensProvider"));
+        assert!(capabilities.code_lens_resolve);
+    }
 }
@@ -8,18 +8,19 @@
 
 mod client;
 pub mod multi;
+mod protocol_log;
 pub mod types;
 mod watched_files;
 
 use std::path::PathBuf;
 use std::sync::mpsc::{self, Receiver, Sender};
 use std::thread::{self, JoinHandle};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use lsp_types::Url;
 
 pub use client::LspClient;
-pub use multi::{LanguageId, MultiLspManager};
+pub use multi::{Filetype, LanguageId, MultiLspManager};
 pub use types::*;
 
 const READER_JOIN_TIMEOUT: Duration = Duration::from_millis(300);
@@ -34,19 +35,42 @@ pub struct LspManager {
     thread_handle: Option<JoinHandle<()>>,
     /// Current status
     status: LspStatus,
+    /// Messages exchanged with the server and its stderr
+    log: protocol_log::ProtocolLog,
+    /// Workspace root the server was initialized with
+    root_path: PathBuf,
+    started_at: Instant,
 }
 
 impl LspManager {
     /// Start the LSP manager with the given server command
-    pub fn start(command: &str, args: &[String], root_path: PathBuf) -> anyhow::Result<Self> {
+    pub fn start(
+        command: &str,
+        args: &[String],
+        root_path: PathBuf,
+        initialization_options: Option<serde_json::Value>,
+        settings: serde_json::Value,
+    ) -> anyhow::Result<Self> {
         let (request_tx, request_rx) = mpsc::channel::<LspRequest>();
         let (notification_tx, notification_rx) = mpsc::channel::<LspNotification>();
 
         let command = command.to_string();
         let args = args.to_vec();
+        let log = protocol_log::ProtocolLog::default();
+        let thread_log = log.clone();
+        let thread_root = root_path.clone();
 
         let thread_handle = thread::spawn(move || {
-            run_lsp_thread(&command, &args, root_path, request_rx, notification_tx);
+            run_lsp_thread(
+                &command,
+                &args,
+                thread_root,
+                initialization_options,
+                settings,
+                request_rx,
+                notification_tx,
+                thread_log,
+            );
         });
 
         Ok(Self {
@@ -54,6 +78,9 @@ impl LspManager {
             notification_rx,
             thread_handle: Some(thread_handle),
             status: LspStatus::Starting,
+            log,
+            root_path,
+            started_at: Instant::now(),
         })
     }
 
@@ -63,7 +90,7 @@ impl LspManager {
             Ok(notification) => {
                 // Update status based on notification
                 match &notification {
-                    LspNotification::Initialized => self.status = LspStatus::Ready,
+                    LspNotification::Initialized { .. } => self.status = LspStatus::Ready,
                     LspNotification::Error { .. } => self.status = LspStatus::Error,
                     _ => {}
                 }
@@ -90,6 +117,25 @@ impl LspManager {
         self.status == LspStatus::Ready
     }
 
+    /// Messages exchanged with the server so far, oldest first
+    pub fn protocol_log(&self) -> Vec<protocol_log::LogEntry> {
+        self.log.entries()
+    }
+
+    /// Process id of the server, once spawned
+    pub fn pid(&self) -> Option<u32> {
+        self.log.pid()
+    }
+
+    pub fn root_path(&self) -> &std::path::Path {
+        &self.root_path
+    }
+
+    /// Time since the server was started
+    pub fn uptime(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
     /// Shutdown the LSP manager.
     ///
     /// Sends a graceful shutdown, then waits only *briefly* for the worker thread to
@@ -115,10 +161,18 @@ impl LspManager {
 
     // Helper methods for common operations
 
-    /// Notify that a document was opened
-    pub fn did_open(&self, path: &PathBuf, text: &str) -> anyhow::Result<()> {
+    /// Notify that a document was opened, as `language_id` or else the id
+    /// detected from its path and first line
+    pub fn did_open(
+        &self,
+        path: &PathBuf,
+        language_id: Option<&str>,
+        text: &str,
+    ) -> anyhow::Result<()> {
         let uri = path_to_uri(path);
-        let language_id = detect_language_for_document(path, text);
+        let language_id = language_id
+            .map(str::to_string)
+            .unwrap_or_else(|| detect_language_for_document(path, text));
         self.send(LspRequest::DidOpen {
             uri,
             language_id,
@@ -128,12 +182,17 @@ impl LspManager {
     }
 
     /// Notify that a document changed
-    pub fn did_change(&self, path: &PathBuf, version: i32, text: &str) -> anyhow::Result<()> {
+    pub fn did_change(
+        &self,
+        path: &PathBuf,
+        version: i32,
+        changes: Vec<ContentChange>,
+    ) -> anyhow::Result<()> {
         let uri = path_to_uri(path);
         self.send(LspRequest::DidChange {
             uri,
             version,
-            text: text.to_string(),
+            changes,
         })
     }
 
@@ -214,6 +273,21 @@ impl LspManager {
         })
     }
 
+    /// Request go-to-type-definition
+    pub fn goto_type_definition(
+        &self,
+        path: &PathBuf,
+        line: u32,
+        character: u32,
+    ) -> anyhow::Result<()> {
+        let uri = path_to_uri(path);
+        self.send(LspRequest::GotoTypeDefinition {
+            uri,
+            line,
+            character,
+        })
+    }
+
     /// Request hover
     pub fn hover(&self, path: &PathBuf, line: u32, character: u32) -> anyhow::Result<()> {
         let uri = path_to_uri(path);
@@ -249,6 +323,49 @@ impl LspManager {
         })
     }
 
+    /// Request formatting of `range` (UTF-16 columns)
+    pub fn range_formatting(
+        &self,
+        path: &PathBuf,
+        range: TextRange,
+        tab_size: u32,
+        insert_spaces: bool,
+        buffer_version: u64,
+    ) -> anyhow::Result<()> {
+        let uri = path_to_uri(path);
+        self.send(LspRequest::RangeFormatting {
+            uri,
+            range,
+            tab_size,
+            insert_spaces,
+            buffer_version,
+        })
+    }
+
+    /// Request formatting after `ch` was typed at `line`/`character`
+    #[allow(clippy::too_many_arguments)]
+    pub fn on_type_formatting(
+        &self,
+        path: &PathBuf,
+        line: u32,
+        character: u32,
+        ch: char,
+        tab_size: u32,
+        insert_spaces: bool,
+        buffer_version: u64,
+    ) -> anyhow::Result<()> {
+        let uri = path_to_uri(path);
+        self.send(LspRequest::OnTypeFormatting {
+            uri,
+            line,
+            character,
+            ch,
+            tab_size,
+            insert_spaces,
+            buffer_version,
+        })
+    }
+
     /// Request find references
     pub fn references(&self, path: &PathBuf, line: u32, character: u32) -> anyhow::Result<()> {
         let uri = path_to_uri(path);
@@ -300,6 +417,187 @@ impl LspManager {
             buffer_version,
         })
     }
+
+    /// Request the symbol outline of a document
+    pub fn document_symbols(&self, path: &PathBuf) -> anyhow::Result<()> {
+        let uri = path_to_uri(path);
+        self.send(LspRequest::DocumentSymbols { uri })
+    }
+
+    /// Search symbols across the workspace
+    pub fn workspace_symbols(&self, query: String) -> anyhow::Result<()> {
+        self.send(LspRequest::WorkspaceSymbols { query })
+    }
+
+    /// Prepare a call or type hierarchy at a position
+    pub fn prepare_hierarchy(
+        &self,
+        path: &PathBuf,
+        line: u32,
+        character: u32,
+        direction: HierarchyDirection,
+    ) -> anyhow::Result<()> {
+        let uri = path_to_uri(path);
+        self.send(LspRequest::PrepareHierarchy {
+            uri,
+            line,
+            character,
+            direction,
+        })
+    }
+
+    /// Request the children of a hierarchy item for tree node `node`
+    pub fn hierarchy_children(
+        &self,
+        direction: HierarchyDirection,
+        node: u64,
+        item: serde_json::Value,
+    ) -> anyhow::Result<()> {
+        self.send(LspRequest::HierarchyChildren {
+            direction,
+            node,
+            item,
+        })
+    }
+
+    /// Replace the server's settings and push them to it
+    pub fn did_change_configuration(&self, settings: serde_json::Value) -> anyhow::Result<()> {
+        self.send(LspRequest::DidChangeConfiguration { settings })
+    }
+
+    /// Request inlay hints for lines `start_line..end_line`
+    pub fn inlay_hints(
+        &self,
+        path: &PathBuf,
+        start_line: u32,
+        end_line: u32,
+        buffer_version: u64,
+    ) -> anyhow::Result<()> {
+        let uri = path_to_uri(path);
+        self.send(LspRequest::InlayHints {
+            uri,
+            start_line,
+            end_line,
+            buffer_version,
+        })
+    }
+
+    /// Request the occurrences of the symbol at a position
+    pub fn document_highlight(
+        &self,
+        path: &PathBuf,
+        line: u32,
+        character: u32,
+        buffer_version: u64,
+    ) -> anyhow::Result<()> {
+        let uri = path_to_uri(path);
+        self.send(LspRequest::DocumentHighlight {
+            uri,
+            line,
+            character,
+            buffer_version,
+        })
+    }
+
+    /// Request the ranges enclosing a position
+    pub fn selection_range(
+        &self,
+        path: &PathBuf,
+        line: u32,
+        character: u32,
+        buffer_version: u64,
+    ) -> anyhow::Result<()> {
+        let uri = path_to_uri(path);
+        self.send(LspRequest::SelectionRange {
+            uri,
+            line,
+            character,
+            buffer_version,
+        })
+    }
+
+    /// Request the code lenses of a document
+    pub fn code_lens(&self, path: &PathBuf, buffer_version: u64) -> anyhow::Result<()> {
+        let uri = path_to_uri(path);
+        self.send(LspRequest::CodeLens {
+            uri,
+            buffer_version,
+        })
+    }
+
+    /// Resolve the command of lens `index` of the answer for `buffer_version`
+    pub fn code_lens_resolve(
+        &self,
+        path: &PathBuf,
+        buffer_version: u64,
+        index: usize,
+        lens: serde_json::Value,
+    ) -> anyhow::Result<()> {
+        let uri = path_to_uri(path);
+        self.send(LspRequest::CodeLensResolve {
+            uri,
+            buffer_version,
+            index,
+            lens,
+        })
+    }
+
+    /// Request semantic tokens for the whole document
+    pub fn semantic_tokens(
+        &self,
+        path: &PathBuf,
+        previous_result_id: Option<String>,
+        buffer_version: u64,
+    ) -> anyhow::Result<()> {
+        let uri = path_to_uri(path);
+        self.send(LspRequest::SemanticTokens {
+            uri,
+            previous_result_id,
+            buffer_version,
+        })
+    }
+
+    /// Pull the diagnostics of a document
+    pub fn document_diagnostic(
+        &self,
+        path: &PathBuf,
+        previous_result_id: Option<String>,
+    ) -> anyhow::Result<()> {
+        let uri = path_to_uri(path);
+        self.send(LspRequest::DocumentDiagnostic {
+            uri,
+            previous_result_id,
+        })
+    }
+
+    /// Pull the diagnostics of the whole workspace
+    pub fn workspace_diagnostic(
+        &self,
+        previous_result_ids: Vec<(String, String)>,
+    ) -> anyhow::Result<()> {
+        self.send(LspRequest::WorkspaceDiagnostic {
+            previous_result_ids,
+        })
+    }
+
+    /// Run a server-side command
+    pub fn execute_command(&self, command: LspCommand) -> anyhow::Result<()> {
+        self.send(LspRequest::ExecuteCommand { command })
+    }
+
+    /// Answer a `workspace/applyEdit` request from the server
+    pub fn apply_edit_response(
+        &self,
+        request_id: serde_json::Value,
+        applied: bool,
+        failure_reason: Option<String>,
+    ) -> anyhow::Result<()> {
+        self.send(LspRequest::ApplyEditResponse {
+            request_id,
+            applied,
+            failure_reason,
+        })
+    }
 }
 
 impl Drop for LspManager {
@@ -318,15 +616,19 @@ fn join_thread_with_timeout(handle: JoinHandle<()>, timeout: Duration) -> bool {
 }
 
 /// Run the LSP client thread
+#[allow(clippy::too_many_arguments)]
 fn run_lsp_thread(
     command: &str,
     args: &[String],
     root_path: PathBuf,
+    initialization_options: Option<serde_json::Value>,
+    settings: serde_json::Value,
     request_rx: Receiver<LspRequest>,
     notification_tx: Sender<LspNotification>,
+    log: protocol_log::ProtocolLog,
 ) {
     // Try to spawn the LSP server - returns client, shared pending map, and shared stdin
-    let (mut client, pending, stdin) = match LspClient::spawn(command, args) {
+    let (mut client, pending, stdin) = match LspClient::spawn(command, args, log.clone()) {
         Ok(result) => result,
         Err(e) => {
             let _ = notification_tx.send(LspNotification::Error {
@@ -366,8 +668,19 @@ fn run_lsp_thread(
     // The pending map is populated by client methods BEFORE sending requests,
     // so responses are guaranteed to find their request kinds
     let notification_tx_clone = notification_tx.clone();
+    let settings = client::SharedSettings::new(std::sync::Mutex::new(settings));
+    let reader_settings = settings.clone();
+    let reader_log = log.clone();
     let reader_handle = thread::spawn(move || {
-        client::read_messages(stdout, notification_tx_clone, pending, stdin, watcher_tx);
+        client::read_messages(
+            stdout,
+            notification_tx_clone,
+            pending,
+            stdin,
+            watcher_tx,
+            reader_settings,
+            reader_log,
+        );
     });
 
     // Spawn stderr reader thread to capture LSP server errors
@@ -379,6 +692,7 @@ fn run_lsp_thread(
             for line in reader.lines() {
                 if let Ok(line) = line {
                     if !line.trim().is_empty() {
+                        log.record(protocol_log::LogDirection::Stderr, &line);
                         let _ = notification_tx_stderr.send(LspNotification::Error {
                             message: format!("LSP stderr: {}", line),
                         });
@@ -389,7 +703,7 @@ fn run_lsp_thread(
     }
 
     // Send initialize request (automatically tracked in pending map)
-    if let Err(e) = client.initialize(&root_path) {
+    if let Err(e) = client.initialize(&root_path, initialization_options) {
         let _ = notification_tx.send(LspNotification::Error {
             message: format!("Failed to initialize LSP: {}", e),
         });
@@ -423,8 +737,12 @@ fn run_lsp_thread(
                             });
                         }
                     }
-                    LspRequest::DidChange { uri, version, text } => {
-                        if let Err(e) = client.did_change(&uri, version, &text) {
+                    LspRequest::DidChange {
+                        uri,
+                        version,
+                        changes,
+                    } => {
+                        if let Err(e) = client.did_change(&uri, version, changes) {
                             let _ = notification_tx.send(LspNotification::Error {
                                 message: format!("Failed to send didChange: {}", e),
                             });
@@ -494,6 +812,17 @@ fn run_lsp_thread(
                             });
                         }
                     }
+                    LspRequest::GotoTypeDefinition {
+                        uri,
+                        line,
+                        character,
+                    } => {
+                        if let Err(e) = client.goto_type_definition(&uri, line, character) {
+                            let _ = notification_tx.send(LspNotification::Error {
+                                message: format!("Failed to request type definition: {}", e),
+                            });
+                        }
+                    }
                     LspRequest::Hover {
                         uri,
                         line,
@@ -527,6 +856,48 @@ fn run_lsp_thread(
                             });
                         }
                     }
+                    LspRequest::RangeFormatting {
+                        uri,
+                        range,
+                        tab_size,
+                        insert_spaces,
+                        buffer_version,
+                    } => {
+                        if let Err(e) = client.range_formatting(
+                            &uri,
+                            range,
+                            tab_size,
+                            insert_spaces,
+                            buffer_version,
+                        ) {
+                            let _ = notification_tx.send(LspNotification::Error {
+                                message: format!("Failed to request range formatting: {}", e),
+                            });
+                        }
+                    }
+                    LspRequest::OnTypeFormatting {
+                        uri,
+                        line,
+                        character,
+                        ch,
+                        tab_size,
+                        insert_spaces,
+                        buffer_version,
+                    } => {
+                        if let Err(e) = client.on_type_formatting(
+                            &uri,
+                            line,
+                            character,
+                            ch,
+                            tab_size,
+                            insert_spaces,
+                            buffer_version,
+                        ) {
+                            let _ = notification_tx.send(LspNotification::Error {
+                                message: format!("Failed to request on-type formatting: {}", e),
+                            });
+                        }
+                    }
                     LspRequest::References {
                         uri,
                         line,
@@ -576,6 +947,172 @@ fn run_lsp_thread(
                             });
                         }
                     }
+                    LspRequest::DocumentSymbols { uri } => {
+                        if let Err(e) = client.document_symbols(&uri) {
+                            let _ = notification_tx.send(LspNotification::Error {
+                                message: format!("Failed to request document symbols: {}", e),
+                            });
+                        }
+                    }
+                    LspRequest::DidChangeConfiguration { settings: changed } => {
+                        if let Ok(mut settings) = settings.lock() {
+                            *settings = changed.clone();
+                        }
+                        if let Err(e) = client.did_change_configuration(&changed) {
+                            let _ = notification_tx.send(LspNotification::Error {
+                                message: format!("Failed to send didChangeConfiguration: {}", e),
+                            });
+                        }
+                    }
+                    LspRequest::WorkspaceSymbols { query } => {
+                        if let Err(e) = client.workspace_symbols(&query) {
+                            let _ = notification_tx.send(LspNotification::Error {
+                                message: format!("Failed to request workspace symbols: {}", e),
+                            });
+                        }
+                    }
+                    LspRequest::PrepareHierarchy {
+                        uri,
+                        line,
+                        character,
+                        direction,
+                    } => {
+                        if let Err(e) = client.prepare_hierarchy(&uri, line, character, direction) {
+                            let _ = notification_tx.send(LspNotification::Error {
+                                message: format!("Failed to prepare hierarchy: {}", e),
+                            });
+                        }
+                    }
+                    LspRequest::HierarchyChildren {
+                        direction,
+                        node,
+                        item,
+                    } => {
+                        if let Err(e) = client.hierarchy_children(direction, node, item) {
+                            let _ = notification_tx.send(LspNotification::Error {
+                                message: format!("Failed to expand hierarchy: {}", e),
+                            });
+                        }
+                    }
+                    LspRequest::InlayHints {
+                        uri,
+                        start_line,
+                        end_line,
+                        buffer_version,
+                    } => {
+                        if let Err(e) =
+                            client.inlay_hints(&uri, start_line, end_line, buffer_version)
+                        {
+                            let _ = notification_tx.send(LspNotification::Error {
+                                message: format!("Failed to request inlay hints: {}", e),
+                            });
+                        }
+                    }
+                    LspRequest::DocumentHighlight {
+                        uri,
+                        line,
+                        character,
+                        buffer_version,
+                    } => {
+                        if let Err(e) =
+                            client.document_highlight(&uri, line, character, buffer_version)
+                        {
+                            let _ = notification_tx.send(LspNotification::Error {
+                                message: format!("Failed to request document highlights: {}", e),
+                            });
+                        }
+                    }
+                    LspRequest::SelectionRange {
+                        uri,
+                        line,
+                        character,
+                        buffer_version,
+                    } => {
+                        if let Err(e) =
+                            client.selection_range(&uri, line, character, buffer_version)
+                        {
+                            let _ = notification_tx.send(LspNotification::Error {
+                                message: format!("Failed to request selection ranges: {}", e),
+                            });
+                        }
+                    }
+                    LspRequest::CodeLens {
+                        uri,
+                        buffer_version,
+                    } => {
+                        if let Err(e) = client.code_lens(&uri, buffer_version) {
+                            let _ = notification_tx.send(LspNotification::Error {
+                                message: format!("Failed to request code lenses: {}", e),
+                            });
+                        }
+                    }
+                    LspRequest::CodeLensResolve {
+                        uri,
+                        buffer_version,
+                        index,
+                        lens,
+                    } => {
+                        if let Err(e) = client.code_lens_resolve(&uri, buffer_version, index, lens)
+                        {
+                            let _ = notification_tx.send(LspNotification::Error {
+                                message: format!("Failed to resolve code lens: {}", e),
+                            });
+                        }
+                    }
+                    LspRequest::SemanticTokens {
+                        uri,
+                        previous_result_id,
+                        buffer_version,
+                    } => {
+                        if let Err(e) = client.semantic_tokens(
+                            &uri,
+                            previous_result_id.as_deref(),
+                            buffer_version,
+                        ) {
+                            let _ = notification_tx.send(LspNotification::Error {
+                                message: format!("Failed to request semantic tokens: {}", e),
+                            });
+                        }
+                    }
+                    LspRequest::DocumentDiagnostic {
+                        uri,
+                        previous_result_id,
+                    } => {
+                        if let Err(e) = client.document_diagnostic(&uri, previous_result_id) {
+                            let _ = notification_tx.send(LspNotification::Error {
+                                message: format!("Failed to pull diagnostics: {}", e),
+                            });
+                        }
+                    }
+                    LspRequest::WorkspaceDiagnostic {
+                        previous_result_ids,
+                    } => {
+                        if let Err(e) = client.workspace_diagnostic(&previous_result_ids) {
+                            let _ = notification_tx.send(LspNotification::Error {
+                                message: format!("Failed to pull workspace diagnostics: {}", e),
+                            });
+                        }
+                    }
+                    LspRequest::ExecuteCommand { command } => {
+                        if let Err(e) = client.execute_command(&command) {
+                            let _ = notification_tx.send(LspNotification::Error {
+                                message: format!("Failed to execute command: {}", e),
+                            });
+                        }
+                    }
+                    LspRequest::ApplyEditResponse {
+                        request_id,
+                        applied,
+                        failure_reason,
+                    } => {
+                        if let Err(e) =
+                            client.respond_apply_edit(request_id, applied, failure_reason)
+                        {
+                            let _ = notification_tx.send(LspNotification::Error {
+                                message: format!("Failed to answer applyEdit: {}", e),
+                            });
+                        }
+                    }
                 }
             }
             Err(_) => {
@@ -3,15 +3,39 @@
 //! Manages multiple language servers simultaneously, routing requests
 //! to the appropriate server based on file type.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
-use crate::config::LspServerConfig;
-use crate::lsp::{LspManager, LspNotification};
+use crate::config::{LspServerConfig, LspServers};
+use crate::lsp::{
+    CodeActionItem, CompletionItem, ContentChange, Diagnostic, DiagnosticReport,
+    HierarchyDirection, LspManager, LspNotification, SemanticTokensCapability, ServerCapabilities,
+    TextRange,
+};
+
+use super::protocol_log::{LogEntry, render_protocol_logs};
 
 const PROGRESS_DISPLAY_DELAY: Duration = Duration::from_millis(250);
 
+/// How long gathered code actions wait for a slow server before showing
+/// what the others offered
+const CODE_ACTION_TIMEOUT: Duration = Duration::from_millis(1500);
+
+/// Filetypes of extensions not named after their language, for servers
+/// that list several new filetypes
+const KNOWN_EXTENSION_FILETYPES: &[(&str, &str)] = &[
+    ("h", "c"),
+    ("cc", "cpp"),
+    ("cxx", "cpp"),
+    ("c++", "cpp"),
+    ("hh", "cpp"),
+    ("hpp", "cpp"),
+    ("hxx", "cpp"),
+    ("h++", "cpp"),
+    ("inl", "cpp"),
+];
+
 /// Language identifier for routing LSP requests
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum LanguageId {
@@ -31,6 +55,23 @@ pub enum LanguageId {
 }
 
 impl LanguageId {
+    /// Every language with a built-in server configuration
+    pub const ALL: [Self; 13] = [
+        Self::Rust,
+        Self::TypeScript,
+        Self::JavaScript,
+        Self::Css,
+        Self::Json,
+        Self::Toml,
+        Self::Markdown,
+        Self::Html,
+        Self::Python,
+        Self::Php,
+        Self::Go,
+        Self::Ruby,
+        Self::Shell,
+    ];
+
     /// Detect language from file extension
     pub fn from_extension(ext: &str) -> Option<Self> {
         match ext.to_lowercase().as_str() {
@@ -112,6 +153,41 @@ impl LanguageId {
             Self::Shell => "shellscript",
         }
     }
+
+    /// Key of the language's own server under `[lsp.servers]`
+    pub fn server_name(&self) -> &'static str {
+        match self {
+            Self::Shell => "shell",
+            _ => self.as_lsp_id(),
+        }
+    }
+
+    /// Language named in a user-defined server's `filetypes`, by its server
+    /// key or LSP language id
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|lang| lang.server_name() == name || lang.as_lsp_id() == name)
+    }
+}
+
+/// What a file is to the language servers: a built-in language, or a
+/// filetype of user-defined servers, which has no tree-sitter grammar
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Filetype {
+    Language(LanguageId),
+    Custom(String),
+}
+
+impl Filetype {
+    /// Name shown in the status line, and the `languageId` of custom
+    /// filetypes' documents
+    pub fn as_lsp_id(&self) -> &str {
+        match self {
+            Self::Language(lang) => lang.as_lsp_id(),
+            Self::Custom(name) => name,
+        }
+    }
 }
 
 /// State for a single language server
@@ -126,7 +202,19 @@ struct LspInstance {
     last_error: Option<String>,
     progress: Option<LspProgressState>,
     current_file: Option<PathBuf>,
-    document_version: i32,
+    /// Version last sent for each open document, to check versioned workspace edits
+    document_versions: HashMap<PathBuf, i32>,
+    /// What the server offered in its initialize response
+    capabilities: ServerCapabilities,
+    /// Configured settings the server was given
+    settings: serde_json::Value,
+    /// Settings changed before the server was ready; pushed once it is
+    settings_pending: bool,
+    /// Result id of the last pulled diagnostic report, by document URI
+    diagnostic_result_ids: HashMap<String, String>,
+    /// Documents the server had open before `:LspRestart`, opened again
+    /// once the new one is ready
+    reattach: Vec<PathBuf>,
 }
 
 struct LspProgressState {
@@ -134,16 +222,47 @@ struct LspProgressState {
     started_at: Instant,
 }
 
+/// Completions merged from every server asked at one position
+struct CompletionMerge {
+    /// `(uri, line, character, buffer_version)` of the request
+    key: (String, u32, u32, u64),
+    items: Vec<CompletionItem>,
+    is_incomplete: bool,
+}
+
+/// Code actions gathered for one request until every server answered
+struct CodeActionGather {
+    /// `(uri, buffer_version)` of the request
+    key: (String, u64),
+    /// Servers asked, in priority order
+    servers: Vec<String>,
+    /// Servers that have not answered yet
+    waiting: Vec<String>,
+    /// Answers so far, by server
+    actions: HashMap<String, Vec<CodeActionItem>>,
+    started_at: Instant,
+}
+
 /// Manages multiple language servers
 pub struct MultiLspManager {
-    /// Active language server instances
-    instances: HashMap<LanguageId, LspInstance>,
-    /// Server configurations
+    /// Running language servers, by server name (`rust`, `eslint`, ...)
+    instances: HashMap<String, LspInstance>,
+    /// Each language's own server configuration
     configs: HashMap<LanguageId, LspServerConfig>,
+    /// Additional servers by name, attached to the languages they list
+    additional: BTreeMap<String, LspServerConfig>,
     /// Workspace root for all servers
     workspace_root: PathBuf,
     /// Shebang-detected languages for extensionless files (cleared on did_close)
     shebang_languages: HashMap<PathBuf, LanguageId>,
+    /// Latest diagnostics of each server, by document URI
+    diagnostics: HashMap<String, BTreeMap<String, Vec<Diagnostic>>>,
+    /// Completions of the latest request
+    completions: Option<CompletionMerge>,
+    /// Server that offered each merged completion item, to resolve it on
+    completion_servers: HashMap<u64, String>,
+    /// Code action request waiting on servers
+    code_actions: Option<CodeActionGather>,
 }
 
 impl MultiLspManager {
@@ -166,23 +285,7 @@ impl MultiLspManager {
 
         let ext = path.extension().and_then(|ext| ext.to_str())?;
 
-        [
-            LanguageId::Rust,
-            LanguageId::TypeScript,
-            LanguageId::JavaScript,
-            LanguageId::Css,
-            LanguageId::Json,
-            LanguageId::Toml,
-            LanguageId::Markdown,
-            LanguageId::Html,
-            LanguageId::Python,
-            LanguageId::Php,
-            LanguageId::Go,
-            LanguageId::Ruby,
-            LanguageId::Shell,
-        ]
-        .into_iter()
-        .find(|lang| {
+        LanguageId::ALL.into_iter().find(|lang| {
             self.configs
                 .get(lang)
                 .map(|config| {
@@ -195,6 +298,66 @@ impl MultiLspManager {
         })
     }
 
+    pub fn filetype_for_path(&self, path: &Path) -> Option<Filetype> {
+        self.filetype_for_path_and_first_line(path, None)
+    }
+
+    /// The file's built-in language, or else the filetype of the highest
+    /// priority user-defined server whose `file_extensions` match it. That
+    /// is the server's filetype the extension maps to (by the server's
+    /// `extension_filetypes`, then [`KNOWN_EXTENSION_FILETYPES`]) or is
+    /// named like, its first new filetype, or the server's own name.
+    pub fn filetype_for_path_and_first_line(
+        &self,
+        path: &Path,
+        first_line: Option<&str>,
+    ) -> Option<Filetype> {
+        if let Some(lang) = self.language_for_path_and_first_line(path, first_line) {
+            return Some(Filetype::Language(lang));
+        }
+
+        let ext = path.extension().and_then(|ext| ext.to_str())?;
+        let mut custom: Vec<(&String, &LspServerConfig)> = self
+            .additional
+            .iter()
+            .filter(|(_, config)| config.enabled)
+            .collect();
+        custom.sort_by_key(|(_, config)| std::cmp::Reverse(config.priority));
+        custom.into_iter().find_map(|(name, config)| {
+            if !config
+                .file_extensions
+                .iter()
+                .any(|configured| configured.eq_ignore_ascii_case(ext))
+            {
+                return None;
+            }
+            let mapped = config
+                .extension_filetypes
+                .iter()
+                .find(|(configured, _)| configured.eq_ignore_ascii_case(ext))
+                .map(|(_, filetype)| filetype.as_str())
+                .or_else(|| {
+                    KNOWN_EXTENSION_FILETYPES
+                        .iter()
+                        .find(|(known, _)| known.eq_ignore_ascii_case(ext))
+                        .map(|(_, filetype)| *filetype)
+                })
+                .unwrap_or(ext);
+            let filetype = config
+                .filetypes
+                .iter()
+                .find(|filetype| filetype.eq_ignore_ascii_case(mapped))
+                .or_else(|| {
+                    config
+                        .filetypes
+                        .iter()
+                        .find(|filetype| LanguageId::from_name(filetype).is_none())
+                })
+                .unwrap_or(name);
+            Some(Filetype::Custom(filetype.clone()))
+        })
+    }
+
     fn remember_shebang_language(&mut self, path: &Path, first_line: Option<&str>) {
         if LanguageId::from_path(path).is_some() {
             return;
@@ -205,12 +368,111 @@ impl MultiLspManager {
         }
     }
 
-    fn resolve_server_root(&self, lang: LanguageId, file_path: Option<&Path>) -> PathBuf {
+    /// Configuration of the server named `server`
+    fn server_config(&self, server: &str) -> Option<&LspServerConfig> {
+        LanguageId::ALL
+            .into_iter()
+            .find(|lang| lang.server_name() == server)
+            .and_then(|lang| self.configs.get(&lang))
+            .or_else(|| self.additional.get(server))
+    }
+
+    /// Enabled servers attached to `filetype`, highest priority first. A
+    /// language's own server wins ties.
+    pub fn servers_for_filetype(&self, filetype: &Filetype) -> Vec<String> {
+        let mut servers: Vec<(i32, &str)> = Vec::new();
+        if let Filetype::Language(lang) = filetype {
+            if let Some(config) = self.configs.get(lang).filter(|config| config.enabled) {
+                servers.push((config.priority, lang.server_name()));
+            }
+        }
+        for (name, config) in &self.additional {
+            let attached = match filetype {
+                Filetype::Language(lang) => config
+                    .filetypes
+                    .iter()
+                    .any(|configured| LanguageId::from_name(configured) == Some(*lang)),
+                Filetype::Custom(custom) => {
+                    config
+                        .filetypes
+                        .iter()
+                        .any(|configured| configured == custom)
+                        || (config.filetypes.is_empty() && name == custom)
+                }
+            };
+            if config.enabled && attached {
+                servers.push((config.priority, name));
+            }
+        }
+        servers.sort_by_key(|(priority, _)| std::cmp::Reverse(*priority));
+        servers
+            .into_iter()
+            .map(|(_, name)| name.to_string())
+            .collect()
+    }
+
+    /// Whether `server` attaches to files like `path`
+    pub fn serves_file(&self, server: &str, path: &Path) -> bool {
+        self.filetype_for_path(path).is_some_and(|filetype| {
+            self.servers_for_filetype(&filetype)
+                .iter()
+                .any(|s| s == server)
+        })
+    }
+
+    /// Ready servers attached to `path`, highest priority first
+    fn ready_servers(&self, path: &Path) -> Vec<String> {
+        let Some(filetype) = self.filetype_for_path(path) else {
+            return Vec::new();
+        };
+        self.servers_for_filetype(&filetype)
+            .into_iter()
+            .filter(|server| self.instances.get(server).is_some_and(|i| i.ready))
+            .collect()
+    }
+
+    /// Ready servers for `path` that turned on `provider`, or the highest
+    /// priority ready server when none did
+    fn servers_supporting(&self, path: &Path, provider: &str) -> Vec<String> {
+        let ready = self.ready_servers(path);
+        let supporting: Vec<String> = ready
+            .iter()
+            .filter(|server| self.instances[*server].capabilities.supports(provider))
+            .cloned()
+            .collect();
+        if supporting.is_empty() {
+            ready.into_iter().take(1).collect()
+        } else {
+            supporting
+        }
+    }
+
+    /// Whether a ready server for `path` turned on `provider`
+    pub fn supports(&self, path: &Path, provider: &str) -> bool {
+        self.ready_servers(path)
+            .iter()
+            .any(|server| self.instances[server].capabilities.supports(provider))
+    }
+
+    /// The instance that answers a `provider` request for `path`: the
+    /// highest priority ready server that supports it
+    fn instance_for(
+        &mut self,
+        path: &Path,
+        provider: &str,
+    ) -> anyhow::Result<Option<&mut LspInstance>> {
+        self.filetype_for_path(path)
+            .ok_or_else(|| anyhow::anyhow!("Unknown language for {:?}", path))?;
+        let server = self.servers_supporting(path, provider).into_iter().next();
+        Ok(server.and_then(|server| self.instances.get_mut(&server)))
+    }
+
+    fn resolve_server_root(&self, server: &str, file_path: Option<&Path>) -> PathBuf {
         let Some(path) = file_path else {
             return self.workspace_root.clone();
         };
 
-        let Some(config) = self.configs.get(&lang) else {
+        let Some(config) = self.server_config(server) else {
             return self.workspace_root.clone();
         };
 
@@ -351,79 +613,108 @@ impl MultiLspManager {
     /// Statusline label once the handshake is done: "ready" when the server is
     /// quiescent, "indexing…" while it is still analyzing (rust-analyzer reports
     /// this via `experimental/serverStatus`).
-    fn lifecycle_label(server_name: &str, lang: LanguageId, indexing: bool) -> String {
+    fn lifecycle_label(server_name: &str, language: &str, indexing: bool) -> String {
         if indexing {
-            format!("LSP: {} indexing… ({})", server_name, lang.as_lsp_id())
+            format!("LSP: {} indexing… ({})", server_name, language)
         } else {
-            format!("LSP: {} ready ({})", server_name, lang.as_lsp_id())
+            format!("LSP: {} ready ({})", server_name, language)
         }
     }
 
     /// Create a new multi-LSP manager with the given configurations
-    pub fn new(
-        workspace_root: PathBuf,
-        rust_config: LspServerConfig,
-        typescript_config: LspServerConfig,
-        javascript_config: LspServerConfig,
-        css_config: LspServerConfig,
-        json_config: LspServerConfig,
-        toml_config: LspServerConfig,
-        markdown_config: LspServerConfig,
-        html_config: LspServerConfig,
-        python_config: LspServerConfig,
-        php_config: LspServerConfig,
-        go_config: LspServerConfig,
-        ruby_config: LspServerConfig,
-        shell_config: LspServerConfig,
-    ) -> Self {
-        let mut configs = HashMap::new();
-        configs.insert(LanguageId::Rust, rust_config);
-        configs.insert(LanguageId::TypeScript, typescript_config);
-        configs.insert(LanguageId::JavaScript, javascript_config);
-        configs.insert(LanguageId::Css, css_config);
-        configs.insert(LanguageId::Json, json_config);
-        configs.insert(LanguageId::Toml, toml_config);
-        configs.insert(LanguageId::Markdown, markdown_config);
-        configs.insert(LanguageId::Html, html_config);
-        configs.insert(LanguageId::Python, python_config);
-        configs.insert(LanguageId::Php, php_config);
-        configs.insert(LanguageId::Go, go_config);
-        configs.insert(LanguageId::Ruby, ruby_config);
-        configs.insert(LanguageId::Shell, shell_config);
+    pub fn new(workspace_root: PathBuf, servers: LspServers) -> Self {
+        let (configs, additional) = Self::split_servers(servers);
 
         Self {
             instances: HashMap::new(),
             configs,
+            additional,
             workspace_root,
             shebang_languages: HashMap::new(),
+            diagnostics: HashMap::new(),
+            completions: None,
+            completion_servers: HashMap::new(),
+            code_actions: None,
         }
     }
 
-    /// Start a language server for the given language (if not already running)
-    pub fn ensure_server_for_language(&mut self, lang: LanguageId) -> anyhow::Result<bool> {
-        self.ensure_server_for_language_with_file(lang, None)
+    /// Built-in servers by language, and user-defined ones by name
+    fn split_servers(
+        servers: LspServers,
+    ) -> (
+        HashMap<LanguageId, LspServerConfig>,
+        BTreeMap<String, LspServerConfig>,
+    ) {
+        let mut configs = HashMap::new();
+        configs.insert(LanguageId::Rust, servers.rust);
+        configs.insert(LanguageId::TypeScript, servers.typescript);
+        configs.insert(LanguageId::JavaScript, servers.javascript);
+        configs.insert(LanguageId::Css, servers.css);
+        configs.insert(LanguageId::Json, servers.json);
+        configs.insert(LanguageId::Toml, servers.toml);
+        configs.insert(LanguageId::Markdown, servers.markdown);
+        configs.insert(LanguageId::Html, servers.html);
+        configs.insert(LanguageId::Python, servers.python);
+        configs.insert(LanguageId::Php, servers.php);
+        configs.insert(LanguageId::Go, servers.go);
+        configs.insert(LanguageId::Ruby, servers.ruby);
+        configs.insert(LanguageId::Shell, servers.shell);
+        (configs, servers.additional)
+    }
+
+    /// Take edited server configurations. Running servers whose `settings`
+    /// changed are sent them (`workspace/didChangeConfiguration`); returns
+    /// their names. Other changes apply to servers started from now on.
+    pub fn update_servers(&mut self, servers: LspServers) -> Vec<String> {
+        let (configs, additional) = Self::split_servers(servers);
+        self.configs = configs;
+        self.additional = additional;
+
+        let mut changed: Vec<(String, serde_json::Value)> = self
+            .instances
+            .iter()
+            .filter_map(|(server, instance)| {
+                let settings = self
+                    .server_config(server)
+                    .map(LspServerConfig::settings_json)?;
+                (settings != instance.settings).then(|| (server.clone(), settings))
+            })
+            .collect();
+        changed.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut updated = Vec::new();
+        for (server, settings) in changed {
+            let instance = self.instances.get_mut(&server).expect("running server");
+            instance.settings = settings.clone();
+            // Settings may only be pushed after the handshake.
+            if !instance.ready {
+                instance.settings_pending = true;
+            } else if instance.manager.did_change_configuration(settings).is_err() {
+                continue;
+            }
+            updated.push(server);
+        }
+        updated
     }
 
-    fn ensure_server_for_language_with_file(
-        &mut self,
-        lang: LanguageId,
-        file_path: Option<&Path>,
-    ) -> anyhow::Result<bool> {
+    /// Start the server named `server` (if not already running)
+    fn ensure_server(&mut self, server: &str, file_path: Option<&Path>) -> anyhow::Result<bool> {
         // Already running?
-        if self.instances.contains_key(&lang) {
+        if self.instances.contains_key(server) {
             return Ok(false);
         }
 
         // Get config data without holding the borrow across server startup.
-        let (enabled, command, args) = {
+        let (enabled, command, args, initialization_options, settings) = {
             let config = self
-                .configs
-                .get(&lang)
-                .ok_or_else(|| anyhow::anyhow!("No config for language {:?}", lang))?;
+                .server_config(server)
+                .ok_or_else(|| anyhow::anyhow!("No config for server {}", server))?;
             (
                 config.enabled,
                 config.effective_command().to_string(),
                 config.effective_args(),
+                config.initialization_options_json(),
+                config.settings_json(),
             )
         };
 
@@ -432,13 +723,19 @@ impl MultiLspManager {
             return Ok(false);
         }
 
-        let root_path = self.resolve_server_root(lang, file_path);
+        let root_path = self.resolve_server_root(server, file_path);
 
         // Try to start the server (using effective command/args which resolve presets)
-        match LspManager::start(&command, &args, root_path) {
+        match LspManager::start(
+            &command,
+            &args,
+            root_path,
+            initialization_options,
+            settings.clone(),
+        ) {
             Ok(manager) => {
                 self.instances.insert(
-                    lang,
+                    server.to_string(),
                     LspInstance {
                         manager,
                         ready: false,
@@ -446,80 +743,92 @@ impl MultiLspManager {
                         last_error: None,
                         progress: None,
                         current_file: None,
-                        document_version: 1,
+                        document_versions: HashMap::new(),
+                        capabilities: ServerCapabilities::default(),
+                        settings,
+                        settings_pending: false,
+                        diagnostic_result_ids: HashMap::new(),
+                        reattach: Vec::new(),
                     },
                 );
                 Ok(true)
             }
-            Err(e) => Err(e),
+            Err(e) => Err(anyhow::anyhow!(Self::format_error_for_display(
+                &command,
+                &e.to_string()
+            ))),
         }
     }
 
-    /// Start a server for a file if needed
-    pub fn ensure_server_for_file(&mut self, path: &Path) -> anyhow::Result<Option<LanguageId>> {
+    /// Start the servers for a file if needed
+    pub fn ensure_server_for_file(&mut self, path: &Path) -> anyhow::Result<Option<Filetype>> {
         self.ensure_server_for_file_with_first_line(path, None)
     }
 
+    /// Start every server attached to a file. Errors are ready for display;
+    /// the first is returned after trying the rest.
     pub fn ensure_server_for_file_with_first_line(
         &mut self,
         path: &Path,
         first_line: Option<&str>,
-    ) -> anyhow::Result<Option<LanguageId>> {
+    ) -> anyhow::Result<Option<Filetype>> {
         self.remember_shebang_language(path, first_line);
-        if let Some(lang) = self.language_for_path_and_first_line(path, first_line) {
-            let Some(config) = self.configs.get(&lang) else {
-                return Ok(None);
-            };
-            if !config.enabled {
-                return Ok(None);
+        let Some(filetype) = self.filetype_for_path_and_first_line(path, first_line) else {
+            return Ok(None);
+        };
+        let servers = self.servers_for_filetype(&filetype);
+        if servers.is_empty() {
+            return Ok(None);
+        }
+        let mut first_error = None;
+        for server in servers {
+            if let Err(e) = self.ensure_server(&server, Some(path)) {
+                first_error.get_or_insert(e);
             }
-            self.ensure_server_for_language_with_file(lang, Some(path))?;
-            Ok(Some(lang))
-        } else {
-            Ok(None)
         }
-    }
-
-    /// Check if a server is ready for the given language
-    pub fn is_ready(&self, lang: LanguageId) -> bool {
-        self.instances.get(&lang).map_or(false, |i| i.ready)
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(Some(filetype)),
+        }
     }
 
     /// Check if any server is ready for the given file
     pub fn is_ready_for_file(&self, path: &Path) -> bool {
-        self.language_for_path(path)
-            .map_or(false, |lang| self.is_ready(lang))
-    }
-
-    /// Get a mutable reference to the instance for a language
-    fn get_instance_mut(&mut self, lang: LanguageId) -> Option<&mut LspInstance> {
-        self.instances.get_mut(&lang)
+        !self.ready_servers(path).is_empty()
     }
 
     /// Poll all servers for notifications
-    pub fn poll_notifications(&mut self) -> Vec<(LanguageId, LspNotification)> {
+    pub fn poll_notifications(&mut self) -> Vec<(String, LspNotification)> {
         self.poll_notifications_limited(None)
     }
 
     /// Poll all servers for notifications, optionally limiting total work.
+    /// Each comes with the name of the server that sent it. Diagnostics,
+    /// completions and code actions are merged across the servers of a file.
     ///
     /// The editor uses a small limit while keyboard input is active so LSP progress
     /// and hover responses keep moving without draining a large notification burst.
     pub fn poll_notifications_limited(
         &mut self,
         limit: Option<usize>,
-    ) -> Vec<(LanguageId, LspNotification)> {
-        let mut notifications = Vec::new();
+    ) -> Vec<(String, LspNotification)> {
+        let mut received = Vec::new();
 
-        for (&lang, instance) in &mut self.instances {
-            if limit.map_or(false, |limit| notifications.len() >= limit) {
+        for (server, instance) in &mut self.instances {
+            if limit.map_or(false, |limit| received.len() >= limit) {
                 break;
             }
 
             while let Some(notification) = instance.manager.try_recv() {
                 // Update ready state
-                if let LspNotification::Initialized = &notification {
+                if let LspNotification::Initialized { capabilities } = &notification {
                     instance.ready = true;
+                    instance.capabilities = capabilities.clone();
+                    if std::mem::take(&mut instance.settings_pending) {
+                        let _ = instance
+                            .manager
+                            .did_change_configuration(instance.settings.clone());
+                    }
                     instance.last_error = None;
                     // Handshake done, but analysis hasn't started yet. Wait for a
                     // serverStatus notification (if the server sends them) before
@@ -559,80 +868,312 @@ impl MultiLspManager {
                     if Self::is_fatal_error(message) {
                         instance.ready = false;
                         instance.analysis_ready = None;
-                        let command = self
-                            .configs
-                            .get(&lang)
+                        let command = LanguageId::ALL
+                            .into_iter()
+                            .find(|lang| lang.server_name() == server)
+                            .and_then(|lang| self.configs.get(&lang))
+                            .or_else(|| self.additional.get(server))
                             .map(|config| config.effective_command())
-                            .unwrap_or(lang.as_lsp_id());
+                            .unwrap_or(server.as_str());
                         instance.last_error =
                             Some(Self::format_error_for_display(command, message));
                     }
                 }
-                notifications.push((lang, notification));
+                received.push((server.clone(), notification));
 
-                if limit.map_or(false, |limit| notifications.len() >= limit) {
+                if limit.map_or(false, |limit| received.len() >= limit) {
                     break;
                 }
             }
         }
 
+        let mut notifications: Vec<(String, LspNotification)> = received
+            .into_iter()
+            .filter_map(|(server, notification)| {
+                let notification = self.merge_notification(&server, notification)?;
+                Some((server, notification))
+            })
+            .collect();
+        if let Some(gathered) = self.take_timed_out_code_actions(Instant::now()) {
+            notifications.push(gathered);
+        }
         notifications
     }
 
-    /// Send did_open notification to appropriate server
+    /// Fold one server's answer into those of the other servers of the
+    /// same file. `None` while code actions still wait on other servers.
+    fn merge_notification(
+        &mut self,
+        server: &str,
+        notification: LspNotification,
+    ) -> Option<LspNotification> {
+        match notification {
+            LspNotification::Diagnostics { uri, diagnostics } => {
+                let by_server = self.diagnostics.entry(uri.clone()).or_default();
+                by_server.insert(server.to_string(), diagnostics);
+                Some(LspNotification::Diagnostics {
+                    uri,
+                    diagnostics: by_server.values().flatten().cloned().collect(),
+                })
+            }
+            LspNotification::PulledDiagnostics { reports } => {
+                let mut merged = Vec::new();
+                for report in reports {
+                    if let Some(instance) = self.instances.get_mut(server) {
+                        match &report.result_id {
+                            Some(result_id) => instance
+                                .diagnostic_result_ids
+                                .insert(report.uri.clone(), result_id.clone()),
+                            None => instance.diagnostic_result_ids.remove(&report.uri),
+                        };
+                    }
+                    // Unchanged reports leave the diagnostics shown as they are.
+                    let Some(diagnostics) = report.diagnostics else {
+                        continue;
+                    };
+                    let by_server = self.diagnostics.entry(report.uri.clone()).or_default();
+                    by_server.insert(server.to_string(), diagnostics);
+                    merged.push(DiagnosticReport {
+                        diagnostics: Some(by_server.values().flatten().cloned().collect()),
+                        ..report
+                    });
+                }
+                (!merged.is_empty())
+                    .then_some(LspNotification::PulledDiagnostics { reports: merged })
+            }
+            LspNotification::Completions {
+                items,
+                is_incomplete,
+                request_uri,
+                request_line,
+                request_character,
+                request_version,
+            } => {
+                let key = (
+                    request_uri.clone(),
+                    request_line,
+                    request_character,
+                    request_version,
+                );
+                let merge = match &mut self.completions {
+                    Some(merge) if merge.key == key => merge,
+                    slot => {
+                        self.completion_servers.clear();
+                        slot.insert(CompletionMerge {
+                            key,
+                            items: Vec::new(),
+                            is_incomplete: false,
+                        })
+                    }
+                };
+                merge.is_incomplete |= is_incomplete;
+                for item in items {
+                    if merge
+                        .items
+                        .iter()
+                        .any(|merged| same_completion(merged, &item))
+                    {
+                        continue;
+                    }
+                    self.completion_servers
+                        .insert(item.item_id, server.to_string());
+                    merge.items.push(item);
+                }
+                Some(LspNotification::Completions {
+                    items: merge.items.clone(),
+                    is_incomplete: merge.is_incomplete,
+                    request_uri,
+                    request_line,
+                    request_character,
+                    request_version,
+                })
+            }
+            // Lens commands run on the server that sent them.
+            LspNotification::CodeLenses {
+                mut lenses,
+                request_uri,
+                request_version,
+            } => {
+                for command in lenses.iter_mut().filter_map(|lens| lens.command.as_mut()) {
+                    command.server = Some(server.to_string());
+                }
+                Some(LspNotification::CodeLenses {
+                    lenses,
+                    request_uri,
+                    request_version,
+                })
+            }
+            LspNotification::CodeLensResolved {
+                mut lens,
+                index,
+                request_uri,
+                request_version,
+            } => {
+                if let Some(command) = &mut lens.command {
+                    command.server = Some(server.to_string());
+                }
+                Some(LspNotification::CodeLensResolved {
+                    lens,
+                    index,
+                    request_uri,
+                    request_version,
+                })
+            }
+            LspNotification::CodeActions {
+                mut actions,
+                request_uri,
+                request_version,
+            } => {
+                for action in &mut actions {
+                    if let Some(command) = &mut action.command {
+                        command.server = Some(server.to_string());
+                    }
+                }
+                let Some(gather) = self.code_actions.as_mut().filter(|gather| {
+                    gather.key == (request_uri.clone(), request_version)
+                        && gather.waiting.iter().any(|waiting| waiting == server)
+                }) else {
+                    return Some(LspNotification::CodeActions {
+                        actions,
+                        request_uri,
+                        request_version,
+                    });
+                };
+                gather.waiting.retain(|waiting| waiting != server);
+                gather.actions.insert(server.to_string(), actions);
+                if !gather.waiting.is_empty() {
+                    return None;
+                }
+                self.take_code_actions()
+                    .map(|(_, notification)| notification)
+            }
+            notification => Some(notification),
+        }
+    }
+
+    /// The gathered code actions, in server priority order, as one answer
+    fn take_code_actions(&mut self) -> Option<(String, LspNotification)> {
+        let mut gather = self.code_actions.take()?;
+        let actions = gather
+            .servers
+            .iter()
+            .filter_map(|server| gather.actions.remove(server))
+            .flatten()
+            .collect();
+        let (request_uri, request_version) = gather.key;
+        Some((
+            gather.servers.first().cloned().unwrap_or_default(),
+            LspNotification::CodeActions {
+                actions,
+                request_uri,
+                request_version,
+            },
+        ))
+    }
+
+    /// Code actions from the servers that answered, once the rest took too long
+    fn take_timed_out_code_actions(&mut self, now: Instant) -> Option<(String, LspNotification)> {
+        let gather = self.code_actions.as_ref()?;
+        if now.duration_since(gather.started_at) < CODE_ACTION_TIMEOUT {
+            return None;
+        }
+        self.take_code_actions()
+    }
+
+    /// Send did_open notification to the ready servers for `path` that do
+    /// not have it open yet
     pub fn did_open(&mut self, path: &PathBuf, text: &str) -> anyhow::Result<()> {
         let first_line = text.lines().next();
         self.remember_shebang_language(path, first_line);
-        let lang = self
-            .language_for_path_and_first_line(path, first_line)
+        let filetype = self
+            .filetype_for_path_and_first_line(path, first_line)
             .ok_or_else(|| anyhow::anyhow!("Unknown language for {:?}", path))?;
+        // Built-in languages keep the id detected from the file itself.
+        let language_id = match &filetype {
+            Filetype::Language(_) => None,
+            Filetype::Custom(name) => Some(name.as_str()),
+        };
 
-        if let Some(instance) = self.get_instance_mut(lang) {
-            if instance.ready {
-                instance.document_version = 1;
-                instance.manager.did_open(path, text)?;
-                instance.current_file = Some(path.clone());
+        for server in self.ready_servers(path) {
+            let instance = self.instances.get_mut(&server).expect("ready server");
+            if instance.document_versions.contains_key(path) {
+                continue;
             }
+            instance.manager.did_open(path, language_id, text)?;
+            instance.document_versions.insert(path.clone(), 1);
+            instance.current_file = Some(path.clone());
         }
         Ok(())
     }
 
-    /// Send did_change notification to appropriate server
-    pub fn did_change(&mut self, path: &PathBuf, text: &str) -> anyhow::Result<()> {
-        let lang = self
-            .language_for_path(path)
+    /// Send did_change notification to the servers that have `path` open.
+    /// `changes` are the edits since they last saw the document, sent as
+    /// ranges to servers that sync incrementally; the others, or all of them
+    /// when the edits were not tracked (`None`), get the whole text from `text`.
+    pub fn did_change(
+        &mut self,
+        path: &PathBuf,
+        changes: Option<Vec<ContentChange>>,
+        text: impl FnOnce() -> String,
+    ) -> anyhow::Result<()> {
+        self.filetype_for_path(path)
             .ok_or_else(|| anyhow::anyhow!("Unknown language for {:?}", path))?;
 
-        if let Some(instance) = self.get_instance_mut(lang) {
-            if instance.ready {
-                instance.document_version += 1;
-                instance
-                    .manager
-                    .did_change(path, instance.document_version, text)?;
-            }
+        let mut text = Some(text);
+        let mut full_text: Option<String> = None;
+        for server in self.ready_servers(path) {
+            let instance = self.instances.get_mut(&server).expect("ready server");
+            let Some(version) = instance.document_versions.get_mut(path) else {
+                continue;
+            };
+            let changes = match &changes {
+                Some(changes) if instance.capabilities.incremental_sync => {
+                    if changes.is_empty() {
+                        continue;
+                    }
+                    changes.clone()
+                }
+                _ => {
+                    if let Some(text) = text.take() {
+                        full_text = Some(text());
+                    }
+                    vec![ContentChange {
+                        range: None,
+                        text: full_text.clone().unwrap_or_default(),
+                    }]
+                }
+            };
+            // Versions count up per document, whatever else the server has open.
+            *version += 1;
+            instance.manager.did_change(path, *version, changes)?;
         }
         Ok(())
     }
 
-    /// Send did_close notification to appropriate server
+    /// Send did_close notification to the servers that have `path` open
     pub fn did_close(&mut self, path: &PathBuf) -> anyhow::Result<()> {
-        let lang = self
-            .language_for_path(path)
+        self.filetype_for_path(path)
             .ok_or_else(|| anyhow::anyhow!("Unknown language for {:?}", path))?;
 
-        if let Some(instance) = self.get_instance_mut(lang) {
-            if instance.ready {
-                instance.manager.did_close(path)?;
-                if instance.current_file.as_ref() == Some(path) {
-                    instance.current_file = None;
-                }
+        for server in self.ready_servers(path) {
+            let instance = self.instances.get_mut(&server).expect("ready server");
+            if instance.document_versions.remove(path).is_none() {
+                continue;
+            }
+            instance.manager.did_close(path)?;
+            if instance.current_file.as_ref() == Some(path) {
+                instance.current_file = None;
             }
+            instance
+                .diagnostic_result_ids
+                .remove(&crate::lsp::path_to_uri(path));
         }
+        self.diagnostics.remove(&crate::lsp::path_to_uri(path));
         self.shebang_languages.remove(path);
         Ok(())
     }
 
-    /// Request completions for a file
+    /// Request completions from every server for the file that offers them
     pub fn completion(
         &mut self,
         path: &PathBuf,
@@ -640,50 +1181,45 @@ impl MultiLspManager {
         character: u32,
         buffer_version: u64,
     ) -> anyhow::Result<()> {
-        let lang = self
-            .language_for_path(path)
+        self.filetype_for_path(path)
             .ok_or_else(|| anyhow::anyhow!("Unknown language for {:?}", path))?;
 
-        if let Some(instance) = self.get_instance_mut(lang) {
-            if instance.ready {
-                instance
-                    .manager
-                    .completion(path, line, character, buffer_version)?;
-            }
+        for server in self.servers_supporting(path, "completionProvider") {
+            self.instances[&server]
+                .manager
+                .completion(path, line, character, buffer_version)?;
         }
         Ok(())
     }
 
-    /// Resolve a completion item to get full documentation
+    /// Resolve a completion item to get full documentation, on the server
+    /// that offered it
     pub fn completion_resolve(
         &mut self,
-        path: &PathBuf,
+        path: &Path,
         item: serde_json::Value,
         item_id: u64,
         label: String,
     ) -> anyhow::Result<()> {
-        let lang = self
-            .language_for_path(path)
-            .ok_or_else(|| anyhow::anyhow!("Unknown language for {:?}", path))?;
-
-        if let Some(instance) = self.get_instance_mut(lang) {
-            if instance.ready {
-                instance.manager.completion_resolve(item, item_id, label)?;
-            }
+        let origin = self
+            .completion_servers
+            .get(&item_id)
+            .filter(|server| self.instances.get(*server).is_some_and(|i| i.ready))
+            .cloned();
+        let instance = match origin {
+            Some(server) => self.instances.get_mut(&server),
+            None => self.instance_for(path, "completionProvider")?,
+        };
+        if let Some(instance) = instance {
+            instance.manager.completion_resolve(item, item_id, label)?;
         }
         Ok(())
     }
 
     /// Request hover information for a file
     pub fn hover(&mut self, path: &PathBuf, line: u32, character: u32) -> anyhow::Result<()> {
-        let lang = self
-            .language_for_path(path)
-            .ok_or_else(|| anyhow::anyhow!("Unknown language for {:?}", path))?;
-
-        if let Some(instance) = self.get_instance_mut(lang) {
-            if instance.ready {
-                instance.manager.hover(path, line, character)?;
-            }
+        if let Some(instance) = self.instance_for(path, "hoverProvider")? {
+            instance.manager.hover(path, line, character)?;
         }
         Ok(())
     }
@@ -695,14 +1231,8 @@ impl MultiLspManager {
         line: u32,
         character: u32,
     ) -> anyhow::Result<()> {
-        let lang = self
-            .language_for_path(path)
-            .ok_or_else(|| anyhow::anyhow!("Unknown language for {:?}", path))?;
-
-        if let Some(instance) = self.get_instance_mut(lang) {
-            if instance.ready {
-                instance.manager.goto_definition(path, line, character)?;
-            }
+        if let Some(instance) = self.instance_for(path, "definitionProvider")? {
+            instance.manager.goto_definition(path, line, character)?;
         }
         Ok(())
     }
@@ -714,14 +1244,8 @@ impl MultiLspManager {
         line: u32,
         character: u32,
     ) -> anyhow::Result<()> {
-        let lang = self
-            .language_for_path(path)
-            .ok_or_else(|| anyhow::anyhow!("Unknown language for {:?}", path))?;
-
-        if let Some(instance) = self.get_instance_mut(lang) {
-            if instance.ready {
-                instance.manager.goto_declaration(path, line, character)?;
-            }
+        if let Some(instance) = self.instance_for(path, "declarationProvider")? {
+            instance.manager.goto_declaration(path, line, character)?;
         }
         Ok(())
     }
@@ -733,30 +1257,33 @@ impl MultiLspManager {
         line: u32,
         character: u32,
     ) -> anyhow::Result<()> {
-        let lang = self
-            .language_for_path(path)
-            .ok_or_else(|| anyhow::anyhow!("Unknown language for {:?}", path))?;
+        if let Some(instance) = self.instance_for(path, "implementationProvider")? {
+            instance
+                .manager
+                .goto_implementation(path, line, character)?;
+        }
+        Ok(())
+    }
 
-        if let Some(instance) = self.get_instance_mut(lang) {
-            if instance.ready {
-                instance
-                    .manager
-                    .goto_implementation(path, line, character)?;
-            }
+    /// Request go-to-type-definition for a file
+    pub fn goto_type_definition(
+        &mut self,
+        path: &PathBuf,
+        line: u32,
+        character: u32,
+    ) -> anyhow::Result<()> {
+        if let Some(instance) = self.instance_for(path, "typeDefinitionProvider")? {
+            instance
+                .manager
+                .goto_type_definition(path, line, character)?;
         }
         Ok(())
     }
 
     /// Request references for a symbol
     pub fn references(&mut self, path: &PathBuf, line: u32, character: u32) -> anyhow::Result<()> {
-        let lang = self
-            .language_for_path(path)
-            .ok_or_else(|| anyhow::anyhow!("Unknown language for {:?}", path))?;
-
-        if let Some(instance) = self.get_instance_mut(lang) {
-            if instance.ready {
-                instance.manager.references(path, line, character)?;
-            }
+        if let Some(instance) = self.instance_for(path, "referencesProvider")? {
+            instance.manager.references(path, line, character)?;
         }
         Ok(())
     }
@@ -768,40 +1295,91 @@ impl MultiLspManager {
         line: u32,
         character: u32,
     ) -> anyhow::Result<()> {
-        let lang = self
-            .language_for_path(path)
-            .ok_or_else(|| anyhow::anyhow!("Unknown language for {:?}", path))?;
-
-        if let Some(instance) = self.get_instance_mut(lang) {
-            if instance.ready {
-                instance.manager.signature_help(path, line, character)?;
-            }
+        if let Some(instance) = self.instance_for(path, "signatureHelpProvider")? {
+            instance.manager.signature_help(path, line, character)?;
         }
         Ok(())
     }
 
-    /// Request document formatting
+    /// Request document formatting from the highest priority server that
+    /// formats
     pub fn formatting(
         &mut self,
         path: &PathBuf,
         tab_size: u32,
         buffer_version: u64,
     ) -> anyhow::Result<()> {
-        let lang = self
-            .language_for_path(path)
-            .ok_or_else(|| anyhow::anyhow!("Unknown language for {:?}", path))?;
-
-        if let Some(instance) = self.get_instance_mut(lang) {
-            if instance.ready {
-                instance
-                    .manager
-                    .formatting(path, tab_size, buffer_version)?;
-            }
+        if let Some(instance) = self.instance_for(path, "documentFormattingProvider")? {
+            instance
+                .manager
+                .formatting(path, tab_size, buffer_version)?;
         }
         Ok(())
     }
 
-    /// Request code actions
+    /// Request formatting of `range` from the highest priority server that
+    /// formats ranges. Returns false, without sending anything, when no
+    /// ready server for `path` does.
+    pub fn range_formatting(
+        &mut self,
+        path: &PathBuf,
+        range: TextRange,
+        tab_size: u32,
+        insert_spaces: bool,
+        buffer_version: u64,
+    ) -> anyhow::Result<bool> {
+        if !self.supports(path, "documentRangeFormattingProvider") {
+            return Ok(false);
+        }
+        if let Some(instance) = self.instance_for(path, "documentRangeFormattingProvider")? {
+            instance.manager.range_formatting(
+                path,
+                range,
+                tab_size,
+                insert_spaces,
+                buffer_version,
+            )?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Request formatting after `ch` was typed from the highest priority
+    /// server that lists it as a trigger character. Returns false, without
+    /// sending anything, when none does.
+    #[allow(clippy::too_many_arguments)]
+    pub fn on_type_formatting(
+        &mut self,
+        path: &PathBuf,
+        line: u32,
+        character: u32,
+        ch: char,
+        tab_size: u32,
+        insert_spaces: bool,
+        buffer_version: u64,
+    ) -> anyhow::Result<bool> {
+        let Some(server) = self.ready_servers(path).into_iter().find(|server| {
+            self.instances[server]
+                .capabilities
+                .on_type_formatting_triggers
+                .contains(&ch)
+        }) else {
+            return Ok(false);
+        };
+        self.instances[&server].manager.on_type_formatting(
+            path,
+            line,
+            character,
+            ch,
+            tab_size,
+            insert_spaces,
+            buffer_version,
+        )?;
+        Ok(true)
+    }
+
+    /// Request code actions from every server for the file that offers
+    /// them. They are answered together once all have replied.
     pub fn code_action(
         &mut self,
         path: &PathBuf,
@@ -812,22 +1390,29 @@ impl MultiLspManager {
         buffer_version: u64,
         diagnostics: Vec<crate::lsp::types::Diagnostic>,
     ) -> anyhow::Result<()> {
-        let lang = self
-            .language_for_path(path)
+        self.filetype_for_path(path)
             .ok_or_else(|| anyhow::anyhow!("Unknown language for {:?}", path))?;
 
-        if let Some(instance) = self.get_instance_mut(lang) {
-            if instance.ready {
-                instance.manager.code_action(
-                    path,
-                    start_line,
-                    start_character,
-                    end_line,
-                    end_character,
-                    buffer_version,
-                    diagnostics,
-                )?;
-            }
+        let servers = self.servers_supporting(path, "codeActionProvider");
+        for server in &servers {
+            self.instances[server].manager.code_action(
+                path,
+                start_line,
+                start_character,
+                end_line,
+                end_character,
+                buffer_version,
+                diagnostics.clone(),
+            )?;
+        }
+        if !servers.is_empty() {
+            self.code_actions = Some(CodeActionGather {
+                key: (crate::lsp::path_to_uri(path), buffer_version),
+                waiting: servers.clone(),
+                servers,
+                actions: HashMap::new(),
+                started_at: Instant::now(),
+            });
         }
         Ok(())
     }
@@ -841,132 +1426,592 @@ impl MultiLspManager {
         new_name: String,
         buffer_version: u64,
     ) -> anyhow::Result<()> {
-        let lang = self
-            .language_for_path(path)
-            .ok_or_else(|| anyhow::anyhow!("Unknown language for {:?}", path))?;
-
-        if let Some(instance) = self.get_instance_mut(lang) {
-            if instance.ready {
-                instance
-                    .manager
-                    .rename(path, line, character, new_name, buffer_version)?;
-            }
+        if let Some(instance) = self.instance_for(path, "renameProvider")? {
+            instance
+                .manager
+                .rename(path, line, character, new_name, buffer_version)?;
         }
         Ok(())
     }
 
-    /// Shutdown all servers
-    pub fn shutdown(&mut self) {
-        for (_, instance) in &mut self.instances {
-            instance.manager.shutdown();
+    /// Request the symbol outline of `path`
+    pub fn document_symbols(&mut self, path: &PathBuf) -> anyhow::Result<()> {
+        if let Some(instance) = self.instance_for(path, "documentSymbolProvider")? {
+            instance.manager.document_symbols(path)?;
         }
-        self.instances.clear();
+        Ok(())
     }
 
-    /// Get status string for display
-    pub fn status(&self, path: Option<&Path>) -> String {
-        if let Some(p) = path {
-            if let Some(lang) = self.language_for_path(p) {
-                if let Some(instance) = self.instances.get(&lang) {
-                    // Get the server name from config
-                    let server_name = self
-                        .configs
-                        .get(&lang)
-                        .map(|c| c.effective_command())
-                        .unwrap_or("unknown");
-
-                    // `ready` (handshake) gates requests; `analysis_ready == Some(false)`
-                    // means the server is up but still indexing, so don't claim "ready".
-                    let indexing = instance.analysis_ready == Some(false);
-
-                    if let Some(error) = &instance.last_error {
-                        return format!("LSP: {error}");
-                    } else if let Some(progress) = &instance.progress {
-                        if Self::should_show_progress(progress.started_at, Instant::now()) {
-                            return format!(
-                                "LSP: {} loading: {} ({})",
-                                server_name,
-                                progress.label,
-                                lang.as_lsp_id()
-                            );
-                        }
-                    } else if instance.ready {
-                        return Self::lifecycle_label(server_name, lang, indexing);
-                    }
-
-                    if instance.ready {
-                        return Self::lifecycle_label(server_name, lang, indexing);
-                    }
-
-                    return format!("LSP: starting {} ({})...", server_name, lang.as_lsp_id());
-                } else {
-                    // Check if config exists and is enabled
-                    if let Some(config) = self.configs.get(&lang) {
-                        if config.enabled {
-                            return format!(
-                                "LSP: {} not started ({})",
-                                config.effective_command(),
-                                lang.as_lsp_id()
-                            );
-                        } else {
-                            return format!("LSP: {} (disabled)", lang.as_lsp_id());
-                        }
-                    }
-                }
-            }
+    /// Search workspace symbols on the server for `path`'s language
+    pub fn workspace_symbols(&mut self, path: &Path, query: String) -> anyhow::Result<()> {
+        if let Some(instance) = self.instance_for(path, "workspaceSymbolProvider")? {
+            instance.manager.workspace_symbols(query)?;
         }
+        Ok(())
+    }
 
-        // Count active servers
-        let active = self.instances.len();
-        let ready = self.instances.values().filter(|i| i.ready).count();
-        if active > 0 {
-            format!("LSP: {}/{} servers", ready, active)
+    /// Prepare a call or type hierarchy at a position in `path`
+    pub fn prepare_hierarchy(
+        &mut self,
+        path: &PathBuf,
+        line: u32,
+        character: u32,
+        direction: HierarchyDirection,
+    ) -> anyhow::Result<()> {
+        let provider = if direction.is_call() {
+            "callHierarchyProvider"
         } else {
-            "LSP: (no server)".to_string()
+            "typeHierarchyProvider"
+        };
+        if let Some(instance) = self.instance_for(path, provider)? {
+            instance
+                .manager
+                .prepare_hierarchy(path, line, character, direction)?;
         }
+        Ok(())
     }
 
-    pub fn user_facing_error(&self, lang: LanguageId, message: &str) -> String {
-        let command = self
-            .configs
-            .get(&lang)
-            .map(|config| config.effective_command())
-            .unwrap_or(lang.as_lsp_id());
-        Self::format_error_for_display(command, message)
+    /// Expand a hierarchy node on the server that prepared the hierarchy
+    pub fn hierarchy_children(
+        &mut self,
+        server: &str,
+        direction: HierarchyDirection,
+        node: u64,
+        item: serde_json::Value,
+    ) -> anyhow::Result<()> {
+        if let Some(instance) = self.instances.get_mut(server) {
+            instance.manager.hierarchy_children(direction, node, item)?;
+        }
+        Ok(())
     }
-}
 
-impl Drop for MultiLspManager {
-    fn drop(&mut self) {
-        self.shutdown();
+    /// Request inlay hints for lines `start_line..end_line` of `path`
+    pub fn inlay_hints(
+        &mut self,
+        path: &PathBuf,
+        start_line: u32,
+        end_line: u32,
+        buffer_version: u64,
+    ) -> anyhow::Result<()> {
+        if let Some(instance) = self.instance_for(path, "inlayHintProvider")? {
+            instance
+                .manager
+                .inlay_hints(path, start_line, end_line, buffer_version)?;
+        }
+        Ok(())
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs;
-    use std::sync::mpsc;
-    use std::time::{SystemTime, UNIX_EPOCH};
+
+    /// Request the occurrences of the symbol at a position. Only servers
+    /// that offer document highlights are asked.
+    pub fn document_highlight(
+        &mut self,
+        path: &PathBuf,
+        line: u32,
+        character: u32,
+        buffer_version: u64,
+    ) -> anyhow::Result<()> {
+        if !self.supports(path, "documentHighlightProvider") {
+            return Ok(());
+        }
+        if let Some(instance) = self.instance_for(path, "documentHighlightProvider")? {
+            instance
+                .manager
+                .document_highlight(path, line, character, buffer_version)?;
+        }
+        Ok(())
+    }
+
+    /// Request the ranges enclosing a position. Returns false, without
+    /// sending anything, when no ready server for `path` offers them.
+    pub fn selection_range(
+        &mut self,
+        path: &PathBuf,
+        line: u32,
+        character: u32,
+        buffer_version: u64,
+    ) -> anyhow::Result<bool> {
+        if !self.supports(path, "selectionRangeProvider") {
+            return Ok(false);
+        }
+        if let Some(instance) = self.instance_for(path, "selectionRangeProvider")? {
+            instance
+                .manager
+                .selection_range(path, line, character, buffer_version)?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Request the code lenses of `path` from the server that offers them
+    pub fn code_lens(&mut self, path: &PathBuf, buffer_version: u64) -> anyhow::Result<()> {
+        if !self.supports(path, "codeLensProvider") {
+            return Ok(());
+        }
+        if let Some(instance) = self.instance_for(path, "codeLensProvider")? {
+            instance.manager.code_lens(path, buffer_version)?;
+        }
+        Ok(())
+    }
+
+    /// Resolve a lens of the answer to `code_lens`. The server that sent
+    /// the lens is the one asked, and only when it resolves lenses.
+    pub fn resolve_code_lens(
+        &mut self,
+        path: &PathBuf,
+        buffer_version: u64,
+        index: usize,
+        lens: serde_json::Value,
+    ) -> anyhow::Result<()> {
+        if !self.supports(path, "codeLensProvider") {
+            return Ok(());
+        }
+        if let Some(instance) = self.instance_for(path, "codeLensProvider")?
+            && instance.capabilities.code_lens_resolve
+        {
+            instance
+                .manager
+                .code_lens_resolve(path, buffer_version, index, lens)?;
+        }
+        Ok(())
+    }
+
+    /// Pull the diagnostics of `path` from every ready server that offers
+    /// pull diagnostics, as unchanged against the last report each sent
+    pub fn pull_diagnostics(&mut self, path: &PathBuf) -> anyhow::Result<()> {
+        let uri = crate::lsp::path_to_uri(path);
+        for server in self.ready_servers(path) {
+            let instance = &self.instances[&server];
+            if !instance.capabilities.supports("diagnosticProvider") {
+                continue;
+            }
+            let previous_result_id = instance.diagnostic_result_ids.get(&uri).cloned();
+            instance
+                .manager
+                .document_diagnostic(path, previous_result_id)?;
+        }
+        Ok(())
+    }
+
+    /// Pull the diagnostics of the whole workspace from every ready server
+    /// that offers workspace diagnostics
+    pub fn pull_workspace_diagnostics(&mut self) -> anyhow::Result<()> {
+        for instance in self.instances.values() {
+            if !instance.ready || !instance.capabilities.workspace_diagnostics {
+                continue;
+            }
+            let previous_result_ids = instance
+                .diagnostic_result_ids
+                .iter()
+                .map(|(uri, result_id)| (uri.clone(), result_id.clone()))
+                .collect();
+            instance.manager.workspace_diagnostic(previous_result_ids)?;
+        }
+        Ok(())
+    }
+
+    /// Semantic token support of the server named `server`
+    pub fn semantic_tokens_capability(&self, server: &str) -> Option<&SemanticTokensCapability> {
+        self.instances
+            .get(server)
+            .and_then(|instance| instance.capabilities.semantic_tokens.as_ref())
+    }
+
+    /// The ready server for `path` that semantic tokens are requested from
+    fn semantic_tokens_server(&self, path: &Path) -> Option<String> {
+        self.ready_servers(path)
+            .into_iter()
+            .find(|server| self.semantic_tokens_capability(server).is_some())
+    }
+
+    /// Whether a ready server for `path` offers semantic tokens
+    pub fn has_semantic_tokens(&self, path: &Path) -> bool {
+        self.semantic_tokens_server(path).is_some()
+    }
+
+    /// Request semantic tokens for `path`, as a delta against
+    /// `previous_result_id` when the server supports deltas. Does nothing
+    /// for files without a semantic tokens server.
+    pub fn semantic_tokens(
+        &mut self,
+        path: &PathBuf,
+        previous_result_id: Option<String>,
+        buffer_version: u64,
+    ) -> anyhow::Result<()> {
+        self.filetype_for_path(path)
+            .ok_or_else(|| anyhow::anyhow!("Unknown language for {:?}", path))?;
+
+        if let Some(server) = self.semantic_tokens_server(path) {
+            let instance = &self.instances[&server];
+            let delta = instance
+                .capabilities
+                .semantic_tokens
+                .as_ref()
+                .is_some_and(|capability| capability.delta);
+            let previous_result_id = previous_result_id.filter(|_| delta);
+            instance
+                .manager
+                .semantic_tokens(path, previous_result_id, buffer_version)?;
+        }
+        Ok(())
+    }
+
+    /// Run a server-side command on the server that offered it, or the
+    /// server for `path` that runs commands
+    pub fn execute_command(
+        &mut self,
+        path: &Path,
+        command: crate::lsp::types::LspCommand,
+    ) -> anyhow::Result<()> {
+        let origin = command
+            .server
+            .clone()
+            .filter(|server| self.instances.get(server).is_some_and(|i| i.ready));
+        let instance = match origin {
+            Some(server) => self.instances.get_mut(&server),
+            None => self.instance_for(path, "executeCommandProvider")?,
+        };
+        if let Some(instance) = instance {
+            instance.manager.execute_command(command)?;
+        }
+        Ok(())
+    }
+
+    /// Version last sent to `server` for an open document
+    pub fn document_version(&self, server: &str, path: &Path) -> Option<i32> {
+        self.instances
+            .get(server)?
+            .document_versions
+            .get(path)
+            .copied()
+    }
+
+    /// Answer a `workspace/applyEdit` request from `server`
+    pub fn apply_edit_response(
+        &mut self,
+        server: &str,
+        request_id: serde_json::Value,
+        applied: bool,
+        failure_reason: Option<String>,
+    ) -> anyhow::Result<()> {
+        if let Some(instance) = self.instances.get_mut(server) {
+            instance
+                .manager
+                .apply_edit_response(request_id, applied, failure_reason)?;
+        }
+        Ok(())
+    }
+
+    /// Shutdown all servers
+    pub fn shutdown(&mut self) {
+        for (_, instance) in &mut self.instances {
+            instance.manager.shutdown();
+        }
+        self.instances.clear();
+    }
+
+    /// Get status string for display: the language's own server (or the
+    /// first attached one), followed by the other ready servers
+    pub fn status(&self, path: Option<&Path>) -> String {
+        if let Some(filetype) = path.and_then(|p| self.filetype_for_path(p)) {
+            let servers = self.servers_for_filetype(&filetype);
+            let own = match &filetype {
+                Filetype::Language(lang) => lang.server_name(),
+                Filetype::Custom(_) => "",
+            };
+            let Some(main) = servers
+                .iter()
+                .find(|server| *server == own)
+                .or(servers.first())
+            else {
+                return format!("LSP: {} (disabled)", filetype.as_lsp_id());
+            };
+            let status = self.server_status(main, filetype.as_lsp_id());
+            let others: Vec<&str> = servers
+                .iter()
+                .filter(|server| *server != main)
+                .filter(|server| self.instances.get(*server).is_some_and(|i| i.ready))
+                .map(String::as_str)
+                .collect();
+            if others.is_empty() {
+                return status;
+            }
+            return format!("{} +{}", status, others.join(" +"));
+        }
+
+        // Count active servers
+        let active = self.instances.len();
+        let ready = self.instances.values().filter(|i| i.ready).count();
+        if active > 0 {
+            format!("LSP: {}/{} servers", ready, active)
+        } else {
+            "LSP: (no server)".to_string()
+        }
+    }
+
+    /// Status of one server, for a file of `language`
+    fn server_status(&self, server: &str, language: &str) -> String {
+        // Get the server name from config
+        let server_name = self
+            .server_config(server)
+            .map(|c| c.effective_command())
+            .unwrap_or("unknown");
+
+        let Some(instance) = self.instances.get(server) else {
+            return format!("LSP: {} not started ({})", server_name, language);
+        };
+
+        // `ready` (handshake) gates requests; `analysis_ready == Some(false)`
+        // means the server is up but still indexing, so don't claim "ready".
+        let indexing = instance.analysis_ready == Some(false);
+
+        if let Some(error) = &instance.last_error {
+            return format!("LSP: {error}");
+        } else if let Some(progress) = &instance.progress {
+            if Self::should_show_progress(progress.started_at, Instant::now()) {
+                return format!(
+                    "LSP: {} loading: {} ({})",
+                    server_name, progress.label, language
+                );
+            }
+        } else if instance.ready {
+            return Self::lifecycle_label(server_name, language, indexing);
+        }
+
+        if instance.ready {
+            return Self::lifecycle_label(server_name, language, indexing);
+        }
+
+        format!("LSP: starting {} ({})...", server_name, language)
+    }
+
+    /// Running servers attached to the language `name`, or else the
+    /// running server named `name`
+    fn running_servers_named(&self, name: &str) -> Vec<String> {
+        let filetype = LanguageId::from_name(name)
+            .map(Filetype::Language)
+            .unwrap_or_else(|| Filetype::Custom(name.to_string()));
+        let servers: Vec<String> = self
+            .servers_for_filetype(&filetype)
+            .into_iter()
+            .filter(|server| self.instances.contains_key(server))
+            .collect();
+        if servers.is_empty() && self.instances.contains_key(name) {
+            return vec![name.to_string()];
+        }
+        servers
+    }
+
+    /// Restart the servers named or attached to the language `name`, or
+    /// those attached to `path` when no name is given. The documents they
+    /// had open are opened again once the new servers are ready (see
+    /// [`Self::take_reattach`]). Returns the restarted servers.
+    pub fn restart(
+        &mut self,
+        name: Option<&str>,
+        path: Option<&Path>,
+    ) -> anyhow::Result<Vec<String>> {
+        let servers = match (name, path) {
+            (Some(name), _) => self.running_servers_named(name),
+            (None, Some(path)) => self
+                .filetype_for_path(path)
+                .map(|filetype| self.servers_for_filetype(&filetype))
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|server| self.instances.contains_key(server))
+                .collect(),
+            (None, None) => Vec::new(),
+        };
+        if servers.is_empty() {
+            return Err(match name {
+                Some(name) => anyhow::anyhow!("No language server running for {}", name),
+                None => anyhow::anyhow!("No language server running for this buffer"),
+            });
+        }
+
+        let mut first_error = None;
+        for server in &servers {
+            let Some(mut old) = self.instances.remove(server) else {
+                continue;
+            };
+            old.manager.shutdown();
+            for by_server in self.diagnostics.values_mut() {
+                by_server.remove(server);
+            }
+            let mut reattach: Vec<PathBuf> = old.document_versions.into_keys().collect();
+            reattach.sort();
+            let root_file = reattach.first().cloned().or(old.current_file);
+            if let Err(e) = self.ensure_server(server, root_file.as_deref()) {
+                first_error.get_or_insert(e);
+                continue;
+            }
+            if let Some(instance) = self.instances.get_mut(server) {
+                instance.reattach = reattach;
+            }
+        }
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(servers),
+        }
+    }
+
+    /// Documents to open again on `server`, which just became ready after
+    /// a restart
+    pub fn take_reattach(&mut self, server: &str) -> Vec<PathBuf> {
+        self.instances
+            .get_mut(server)
+            .map(|instance| std::mem::take(&mut instance.reattach))
+            .unwrap_or_default()
+    }
+
+    /// What each running server was started with and negotiated, for
+    /// `:LspInfo`
+    pub fn info_report(&self) -> String {
+        let mut out = String::from("# LSP info\n\n");
+        out.push_str(&format!(
+            "Workspace root: {}\n",
+            self.workspace_root.display()
+        ));
+        if self.instances.is_empty() {
+            out.push_str("\nNo language server is running.\n");
+            return out;
+        }
+
+        let mut servers: Vec<&String> = self.instances.keys().collect();
+        servers.sort();
+        for server in servers {
+            let instance = &self.instances[server];
+            let manager = &instance.manager;
+            out.push_str(&format!("\n## {}\n\n", server));
+            if let Some(config) = self.server_config(server) {
+                let mut command = config.effective_command().to_string();
+                for arg in config.effective_args() {
+                    command.push(' ');
+                    command.push_str(&arg);
+                }
+                out.push_str(&format!("- Command: `{}`\n", command));
+            }
+            out.push_str(&format!("- Root: {}\n", manager.root_path().display()));
+            out.push_str(&format!(
+                "- PID: {}\n",
+                manager
+                    .pid()
+                    .map(|pid| pid.to_string())
+                    .unwrap_or_else(|| "-".to_string())
+            ));
+            out.push_str(&format!("- Uptime: {}\n", format_uptime(manager.uptime())));
+            let status = match (&instance.last_error, instance.ready) {
+                (Some(error), _) => format!("error: {}", error),
+                (None, false) => "starting".to_string(),
+                (None, true) if instance.analysis_ready == Some(false) => "indexing".to_string(),
+                (None, true) => "ready".to_string(),
+            };
+            out.push_str(&format!("- Status: {}\n", status));
+            out.push_str(&format!(
+                "- Text sync: {}\n",
+                if instance.capabilities.incremental_sync {
+                    "incremental"
+                } else {
+                    "full"
+                }
+            ));
+
+            out.push_str("\n### Attached buffers\n\n");
+            let mut documents: Vec<(&PathBuf, &i32)> = instance.document_versions.iter().collect();
+            documents.sort();
+            if documents.is_empty() {
+                out.push_str("(none)\n");
+            }
+            for (path, version) in documents {
+                out.push_str(&format!("- {} (version {})\n", path.display(), version));
+            }
+
+            out.push_str("\n### Capabilities\n\n");
+            if instance.capabilities.providers.is_empty() {
+                out.push_str("(none)\n");
+            }
+            for provider in &instance.capabilities.providers {
+                out.push_str(&format!("- {}\n", provider));
+            }
+        }
+        out
+    }
+
+    /// The protocol log of the server (or the language's servers) `name`,
+    /// or of every running server, for `:LspLog`
+    pub fn log_report(&self, name: Option<&str>) -> anyhow::Result<String> {
+        let servers = match name {
+            Some(name) => self.running_servers_named(name),
+            None => {
+                let mut servers: Vec<String> = self.instances.keys().cloned().collect();
+                servers.sort();
+                servers
+            }
+        };
+        if servers.is_empty() {
+            return Err(match name {
+                Some(name) => anyhow::anyhow!("No language server running for {}", name),
+                None => anyhow::anyhow!("No language server is running"),
+            });
+        }
+        let logs: Vec<(&str, Vec<LogEntry>)> = servers
+            .iter()
+            .map(|server| {
+                (
+                    server.as_str(),
+                    self.instances[server].manager.protocol_log(),
+                )
+            })
+            .collect();
+        Ok(render_protocol_logs(&logs))
+    }
+
+    pub fn user_facing_error(&self, server: &str, message: &str) -> String {
+        let command = self
+            .server_config(server)
+            .map(|config| config.effective_command())
+            .unwrap_or(server);
+        Self::format_error_for_display(command, message)
+    }
+}
+
+/// `1h 02m 03s`, `2m 03s` or `3s`
+fn format_uptime(uptime: Duration) -> String {
+    let secs = uptime.as_secs();
+    match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m {:02}s", m, s),
+        (h, m, s) => format!("{}h {:02}m {:02}s", h, m, s),
+    }
+}
+
+/// Whether two servers offered the same completion
+fn same_completion(a: &CompletionItem, b: &CompletionItem) -> bool {
+    fn text(item: &CompletionItem) -> &str {
+        item.text_edit
+            .as_ref()
+            .map(|edit| edit.new_text.as_str())
+            .or(item.insert_text.as_deref())
+            .unwrap_or(&item.label)
+    }
+    a.label == b.label && text(a) == text(b)
+}
+
+impl Drop for MultiLspManager {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lsp::types::{CodeLens, LspCommand};
+    use std::fs;
+    use std::sync::mpsc;
+    use std::time::{SystemTime, UNIX_EPOCH};
 
     fn make_manager(workspace_root: PathBuf) -> MultiLspManager {
-        let servers = crate::config::LspServers::default();
-        MultiLspManager::new(
-            workspace_root,
-            servers.rust,
-            servers.typescript,
-            servers.javascript,
-            servers.css,
-            servers.json,
-            servers.toml,
-            servers.markdown,
-            servers.html,
-            servers.python,
-            servers.php,
-            servers.go,
-            servers.ruby,
-            servers.shell,
-        )
+        MultiLspManager::new(workspace_root, LspServers::default())
     }
 
     fn unique_temp_dir(prefix: &str) -> PathBuf {
@@ -977,9 +2022,15 @@ mod tests {
         std::env::temp_dir().join(format!("{}_{}_{}", prefix, std::process::id(), nanos))
     }
 
-    fn manager_with_rust_notifications(notifications: Vec<LspNotification>) -> MultiLspManager {
-        let mut manager = make_manager(PathBuf::from("/tmp/nevi_lsp_test"));
-        let (request_tx, _request_rx) = mpsc::channel::<crate::lsp::LspRequest>();
+    /// Attach a ready server named `name` that offers `providers` and has
+    /// sent `notifications`. Returns the requests it is sent.
+    fn add_ready_server(
+        manager: &mut MultiLspManager,
+        name: &str,
+        providers: &[&str],
+        notifications: Vec<LspNotification>,
+    ) -> mpsc::Receiver<crate::lsp::LspRequest> {
+        let (request_tx, request_rx) = mpsc::channel::<crate::lsp::LspRequest>();
         let (notification_tx, notification_rx) = mpsc::channel();
 
         for notification in notifications {
@@ -989,26 +2040,78 @@ mod tests {
         }
         drop(notification_tx);
 
+        let mut providers: Vec<String> = providers.iter().map(|p| p.to_string()).collect();
+        providers.sort();
         manager.instances.insert(
-            LanguageId::Rust,
+            name.to_string(),
             LspInstance {
                 manager: LspManager {
                     request_tx,
                     notification_rx,
                     thread_handle: None,
                     status: crate::lsp::LspStatus::Ready,
+                    log: Default::default(),
+                    root_path: manager.workspace_root.clone(),
+                    started_at: std::time::Instant::now(),
                 },
                 ready: true,
                 analysis_ready: Some(true),
                 last_error: None,
                 progress: None,
                 current_file: None,
-                document_version: 0,
+                document_versions: HashMap::new(),
+                capabilities: ServerCapabilities {
+                    providers,
+                    ..ServerCapabilities::default()
+                },
+                settings: serde_json::json!({}),
+                settings_pending: false,
+                diagnostic_result_ids: HashMap::new(),
+                reattach: Vec::new(),
             },
         );
+        request_rx
+    }
+
+    fn manager_with_rust_notifications(notifications: Vec<LspNotification>) -> MultiLspManager {
+        let mut manager = make_manager(PathBuf::from("/tmp/nevi_lsp_test"));
+        add_ready_server(&mut manager, "rust", &[], notifications);
         manager
     }
 
+    /// A manager with ruff attached to Python files next to pyright
+    fn python_manager(ruff_priority: i32) -> MultiLspManager {
+        let mut servers = LspServers::default();
+        servers.additional.insert(
+            "ruff".to_string(),
+            LspServerConfig {
+                preset: None,
+                command: "ruff".to_string(),
+                args: vec!["server".to_string()],
+                filetypes: vec!["python".to_string()],
+                priority: ruff_priority,
+                ..servers.python.clone()
+            },
+        );
+        MultiLspManager::new(PathBuf::from("/tmp/nevi_lsp_test"), servers)
+    }
+
+    fn diagnostic(message: &str) -> Diagnostic {
+        Diagnostic {
+            line: 0,
+            end_line: 0,
+            col_start: 0,
+            col_end: 1,
+            severity: crate::lsp::types::DiagnosticSeverity::Error,
+            message: message.to_string(),
+            source: None,
+            code: None,
+            tags: Vec::new(),
+            related: Vec::new(),
+            href: None,
+        }
+    }
+
     #[test]
     fn resolve_server_root_uses_language_root_markers() {
         let tmp = unique_temp_dir("nevi_lsp_root");
@@ -1024,7 +2127,7 @@ mod tests {
 
         let manager = make_manager(workspace_root.clone());
         let file_path = nested.join("main.rs");
-        let resolved = manager.resolve_server_root(LanguageId::Rust, Some(file_path.as_path()));
+        let resolved = manager.resolve_server_root("rust", Some(file_path.as_path()));
         assert_eq!(resolved, project_root);
 
         let _ = fs::remove_dir_all(&tmp);
@@ -1039,7 +2142,7 @@ mod tests {
 
         let manager = make_manager(workspace_root.clone());
         let file_path = nested.join("main.rs");
-        let resolved = manager.resolve_server_root(LanguageId::Rust, Some(file_path.as_path()));
+        let resolved = manager.resolve_server_root("rust", Some(file_path.as_path()));
         assert_eq!(resolved, workspace_root);
 
         let _ = fs::remove_dir_all(&tmp);
@@ -1098,7 +2201,7 @@ mod tests {
         let notifications = manager.poll_notifications();
 
         assert_eq!(notifications.len(), 1);
-        assert_eq!(notifications[0].0, LanguageId::Rust);
+        assert_eq!(notifications[0].0, "rust");
         assert!(matches!(
             &notifications[0].1,
             LspNotification::Error { message }
@@ -1261,7 +2364,7 @@ mod tests {
 
         let manager = make_manager(workspace_root.clone());
         let file_path = nested.join("user.rb");
-        let resolved = manager.resolve_server_root(LanguageId::Ruby, Some(file_path.as_path()));
+        let resolved = manager.resolve_server_root("ruby", Some(file_path.as_path()));
         assert_eq!(resolved, project_root);
 
         let _ = fs::remove_dir_all(&tmp);
@@ -1278,7 +2381,7 @@ mod tests {
 
         let manager = make_manager(workspace_root.clone());
         let file_path = nested.join("HomeController.php");
-        let resolved = manager.resolve_server_root(LanguageId::Php, Some(file_path.as_path()));
+        let resolved = manager.resolve_server_root("php", Some(file_path.as_path()));
         assert_eq!(resolved, project_root);
 
         let _ = fs::remove_dir_all(&tmp);
@@ -1374,12 +2477,693 @@ mod tests {
 
     #[test]
     fn lifecycle_label_distinguishes_indexing_from_ready() {
-        let ready = MultiLspManager::lifecycle_label("rust-analyzer", LanguageId::Rust, false);
+        let ready = MultiLspManager::lifecycle_label("rust-analyzer", "rust", false);
         assert!(ready.contains("ready"), "got: {ready}");
         assert!(!ready.contains("indexing"), "got: {ready}");
 
-        let indexing = MultiLspManager::lifecycle_label("rust-analyzer", LanguageId::Rust, true);
+        let indexing = MultiLspManager::lifecycle_label("rust-analyzer", "rust", true);
         assert!(indexing.contains("indexing"), "got: {indexing}");
         assert!(!indexing.contains("ready"), "got: {indexing}");
     }
+
+    #[test]
+    fn additional_servers_attach_in_priority_order() {
+        let manager = python_manager(0);
+        // The language's own server wins ties.
+        assert_eq!(
+            manager.servers_for_filetype(&Filetype::Language(LanguageId::Python)),
+            vec!["python", "ruff"]
+        );
+        assert_eq!(
+            manager.servers_for_filetype(&Filetype::Language(LanguageId::Rust)),
+            vec!["rust"]
+        );
+        assert!(manager.serves_file("ruff", Path::new("app.py")));
+        assert!(!manager.serves_file("ruff", Path::new("main.rs")));
+
+        let manager = python_manager(1);
+        assert_eq!(
+            manager.servers_for_filetype(&Filetype::Language(LanguageId::Python)),
+            vec!["ruff", "python"]
+        );
+    }
+
+    #[test]
+    fn user_defined_servers_route_new_filetypes_by_extension() {
+        let mut servers = LspServers::default();
+        servers.additional.insert(
+            "clangd".to_string(),
+            LspServerConfig {
+                preset: None,
+                command: "clangd".to_string(),
+                args: Vec::new(),
+                filetypes: vec!["c".to_string(), "cpp".to_string()],
+                file_extensions: vec!["c".to_string(), "h".to_string(), "CPP".to_string()],
+                root_patterns: vec!["compile_commands.json".to_string()],
+                ..servers.python.clone()
+            },
+        );
+        servers.additional.insert(
+            "zls".to_string(),
+            LspServerConfig {
+                enabled: false,
+                preset: None,
+                command: "zls".to_string(),
+                args: Vec::new(),
+                filetypes: Vec::new(),
+                file_extensions: vec!["zig".to_string()],
+                ..servers.python.clone()
+            },
+        );
+        let mut manager = MultiLspManager::new(PathBuf::from("/tmp/nevi_lsp_test"), servers);
+
+        let custom = |name: &str| Some(Filetype::Custom(name.to_string()));
+        assert_eq!(
+            manager.filetype_for_path(Path::new("src/main.c")),
+            custom("c")
+        );
+        assert_eq!(
+            manager.filetype_for_path(Path::new("src/util.h")),
+            custom("c")
+        );
+        assert_eq!(
+            manager.filetype_for_path(Path::new("src/app.cpp")),
+            custom("cpp")
+        );
+        assert_eq!(
+            manager.filetype_for_path(Path::new("src/main.rs")),
+            Some(Filetype::Language(LanguageId::Rust))
+        );
+        // Disabled servers claim no files.
+        assert_eq!(manager.filetype_for_path(Path::new("build.zig")), None);
+
+        assert_eq!(
+            manager.servers_for_filetype(&Filetype::Custom("cpp".to_string())),
+            vec!["clangd"]
+        );
+        assert_eq!(
+            manager.status(Some(Path::new("src/main.c"))),
+            "LSP: clangd not started (c)"
+        );
+
+        // Documents are opened with the filetype as their languageId.
+        let clangd = add_ready_server(&mut manager, "clangd", &[], vec![]);
+        let path = PathBuf::from("/tmp/nevi_lsp_test/src/util.h");
+        manager.did_open(&path, "int f(void);\n").expect("did_open");
+        assert!(matches!(
+            clangd.try_recv(),
+            Ok(crate::lsp::LspRequest::DidOpen { language_id, .. }) if language_id == "c"
+        ));
+        assert_eq!(
+            manager.status(Some(path.as_path())),
+            "LSP: clangd ready (c)"
+        );
+    }
+
+    #[test]
+    fn extensions_pick_the_filetype_they_belong_to() {
+        // The clangd example of the config template and README
+        let clangd = |extension_filetypes: &[(&str, &str)]| {
+            let mut servers = LspServers::default();
+            servers.additional.insert(
+                "clangd".to_string(),
+                LspServerConfig {
+                    preset: None,
+                    command: "clangd".to_string(),
+                    args: Vec::new(),
+                    filetypes: vec!["c".to_string(), "cpp".to_string()],
+                    file_extensions: ["c", "h", "cpp", "hpp", "cc"].map(str::to_string).to_vec(),
+                    extension_filetypes: extension_filetypes
+                        .iter()
+                        .map(|(ext, filetype)| (ext.to_string(), filetype.to_string()))
+                        .collect(),
+                    root_patterns: vec!["compile_commands.json".to_string()],
+                    ..servers.python.clone()
+                },
+            );
+            MultiLspManager::new(PathBuf::from("/tmp/nevi_lsp_test"), servers)
+        };
+        let filetype = |manager: &MultiLspManager, file: &str| match manager
+            .filetype_for_path(Path::new(file))
+        {
+            Some(Filetype::Custom(filetype)) => filetype,
+            other => panic!("expected a custom filetype for {file}, got {other:?}"),
+        };
+
+        let manager = clangd(&[]);
+        for (file, expected) in [
+            ("main.c", "c"),
+            ("util.h", "c"),
+            ("app.cpp", "cpp"),
+            ("app.cc", "cpp"),
+            ("app.HPP", "cpp"),
+        ] {
+            assert_eq!(filetype(&manager, file), expected, "{file}");
+        }
+
+        let manager = clangd(&[("h", "cpp"), ("c", "cpp")]);
+        assert_eq!(filetype(&manager, "util.h"), "cpp");
+        assert_eq!(filetype(&manager, "main.c"), "cpp");
+        assert_eq!(filetype(&manager, "app.cc"), "cpp");
+    }
+
+    #[test]
+    fn document_versions_count_up_per_document() {
+        let mut manager = python_manager(1);
+        let pyright = add_ready_server(&mut manager, "python", &[], vec![]);
+        let app = PathBuf::from("/tmp/nevi_lsp_test/app.py");
+        let util = PathBuf::from("/tmp/nevi_lsp_test/util.py");
+        let sent_versions = || -> Vec<i32> {
+            pyright
+                .try_iter()
+                .filter_map(|request| match request {
+                    crate::lsp::LspRequest::DidChange { version, .. } => Some(version),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        manager.did_open(&app, "a = 1\n").expect("did_open app");
+        manager
+            .did_change(&app, None, || "a = 2\n".to_string())
+            .expect("did_change app");
+        manager
+            .did_change(&app, None, || "a = 3\n".to_string())
+            .expect("did_change app");
+        manager.did_open(&util, "b = 1\n").expect("did_open util");
+        manager
+            .did_change(&util, None, || "b = 2\n".to_string())
+            .expect("did_change util");
+        manager
+            .did_change(&app, None, || "a = 4\n".to_string())
+            .expect("did_change app");
+
+        assert_eq!(sent_versions(), vec![2, 3, 2, 4]);
+        assert_eq!(manager.document_version("python", &app), Some(4));
+        assert_eq!(manager.document_version("python", &util), Some(2));
+    }
+
+    #[test]
+    fn range_and_on_type_formatting_go_only_to_servers_that_offer_them() {
+        let path = PathBuf::from("/tmp/nevi_lsp_test/app.py");
+        let range = TextRange {
+            start_line: 1,
+            start_col: 0,
+            end_line: 3,
+            end_col: 0,
+        };
+
+        let mut manager = python_manager(1);
+        let pyright = add_ready_server(&mut manager, "python", &["hoverProvider"], vec![]);
+        let ruff = add_ready_server(
+            &mut manager,
+            "ruff",
+            &[
+                "documentOnTypeFormattingProvider",
+                "documentRangeFormattingProvider",
+            ],
+            vec![],
+        );
+        manager
+            .instances
+            .get_mut("ruff")
+            .expect("ruff instance")
+            .capabilities
+            .on_type_formatting_triggers = vec!['\n', ':'];
+
+        assert!(
+            manager
+                .range_formatting(&path, range, 4, true, 1)
+                .expect("range formatting")
+        );
+        assert!(matches!(
+            ruff.try_recv(),
+            Ok(crate::lsp::LspRequest::RangeFormatting { .. })
+        ));
+
+        assert!(
+            manager
+                .on_type_formatting(&path, 2, 4, ':', 2, false, 2)
+                .expect("on-type formatting")
+        );
+        assert!(matches!(
+            ruff.try_recv(),
+            Ok(crate::lsp::LspRequest::OnTypeFormatting {
+                ch: ':',
+                tab_size: 2,
+                insert_spaces: false,
+                ..
+            })
+        ));
+        assert!(
+            !manager
+                .on_type_formatting(&path, 2, 5, 'x', 4, true, 3)
+                .expect("on-type formatting")
+        );
+        assert!(ruff.try_recv().is_err());
+        assert!(pyright.try_recv().is_err());
+
+        let mut manager = python_manager(1);
+        let pyright = add_ready_server(&mut manager, "python", &["hoverProvider"], vec![]);
+        assert!(
+            !manager
+                .range_formatting(&path, range, 4, true, 1)
+                .expect("range formatting")
+        );
+        assert!(pyright.try_recv().is_err());
+    }
+
+    #[test]
+    fn formatting_goes_to_the_highest_priority_server_that_formats() {
+        let path = PathBuf::from("/tmp/nevi_lsp_test/app.py");
+
+        let mut manager = python_manager(1);
+        let pyright = add_ready_server(&mut manager, "python", &["hoverProvider"], vec![]);
+        let ruff = add_ready_server(
+            &mut manager,
+            "ruff",
+            &["documentFormattingProvider", "hoverProvider"],
+            vec![],
+        );
+        manager.formatting(&path, 4, 1).expect("formatting");
+        assert!(matches!(
+            ruff.try_recv(),
+            Ok(crate::lsp::LspRequest::Formatting { .. })
+        ));
+        assert!(pyright.try_recv().is_err());
+
+        // Priority picks among capable servers only.
+        let mut manager = python_manager(1);
+        let pyright = add_ready_server(
+            &mut manager,
+            "python",
+            &["documentFormattingProvider"],
+            vec![],
+        );
+        let ruff = add_ready_server(&mut manager, "ruff", &["hoverProvider"], vec![]);
+        manager.formatting(&path, 4, 1).expect("formatting");
+        assert!(pyright.try_recv().is_ok());
+        assert!(ruff.try_recv().is_err());
+        assert_eq!(
+            manager.status(Some(path.as_path())),
+            "LSP: pyright-langserver ready (python) +ruff"
+        );
+    }
+
+    #[test]
+    fn diagnostics_are_merged_per_server() {
+        let uri = "file:///tmp/nevi_lsp_test/app.py".to_string();
+        let mut manager = python_manager(0);
+        add_ready_server(
+            &mut manager,
+            "python",
+            &[],
+            vec![LspNotification::Diagnostics {
+                uri: uri.clone(),
+                diagnostics: vec![diagnostic("type error")],
+            }],
+        );
+        add_ready_server(
+            &mut manager,
+            "ruff",
+            &[],
+            vec![LspNotification::Diagnostics {
+                uri: uri.clone(),
+                diagnostics: vec![diagnostic("unused import")],
+            }],
+        );
+        manager.poll_notifications();
+
+        // A server's new list replaces only its own diagnostics.
+        let merged = manager.merge_notification(
+            "ruff",
+            LspNotification::Diagnostics {
+                uri: uri.clone(),
+                diagnostics: vec![diagnostic("line too long")],
+            },
+        );
+        let Some(LspNotification::Diagnostics { diagnostics, .. }) = merged else {
+            panic!("expected diagnostics, got {merged:?}");
+        };
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, vec!["type error", "line too long"]);
+    }
+
+    #[test]
+    fn pulled_diagnostics_merge_per_server_and_keep_result_ids() {
+        let path = PathBuf::from("/tmp/nevi_lsp_test/app.py");
+        let uri = crate::lsp::path_to_uri(&path);
+        let mut manager = python_manager(0);
+        add_ready_server(
+            &mut manager,
+            "python",
+            &[],
+            vec![LspNotification::Diagnostics {
+                uri: uri.clone(),
+                diagnostics: vec![diagnostic("type error")],
+            }],
+        );
+        let ruff_requests = add_ready_server(&mut manager, "ruff", &["diagnosticProvider"], vec![]);
+        manager.poll_notifications();
+        let report = |result_id: &str, diagnostics| DiagnosticReport {
+            uri: uri.clone(),
+            result_id: Some(result_id.to_string()),
+            diagnostics,
+        };
+
+        let merged = manager.merge_notification(
+            "ruff",
+            LspNotification::PulledDiagnostics {
+                reports: vec![report("r1", Some(vec![diagnostic("unused import")]))],
+            },
+        );
+        let Some(LspNotification::PulledDiagnostics { reports }) = merged else {
+            panic!("expected pulled diagnostics, got {merged:?}");
+        };
+        let messages: Vec<&str> = reports[0]
+            .diagnostics
+            .iter()
+            .flatten()
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(messages, vec!["type error", "unused import"]);
+
+        // An unchanged report leaves nothing to show.
+        let merged = manager.merge_notification(
+            "ruff",
+            LspNotification::PulledDiagnostics {
+                reports: vec![report("r2", None)],
+            },
+        );
+        assert!(merged.is_none());
+
+        // Only ruff pulls, against the last result it sent.
+        manager.pull_diagnostics(&path).expect("pull diagnostics");
+        let request = ruff_requests.try_recv().expect("ruff pull request");
+        assert!(matches!(
+            request,
+            crate::lsp::LspRequest::DocumentDiagnostic { previous_result_id: Some(id), .. }
+                if id == "r2"
+        ));
+    }
+
+    #[test]
+    fn server_info_log_and_restart_cover_the_named_servers() {
+        let path = PathBuf::from("/tmp/nevi_lsp_test/app.py");
+        let mut manager = python_manager(0);
+        if let Some(ruff) = manager.additional.get_mut("ruff") {
+            ruff.command = "nevi-test-missing-server".to_string();
+        }
+        add_ready_server(&mut manager, "python", &["hoverProvider"], vec![]);
+        add_ready_server(&mut manager, "ruff", &["codeLensProvider"], vec![]);
+        let ruff = manager.instances.get_mut("ruff").expect("ruff instance");
+        ruff.document_versions.insert(path.clone(), 3);
+        ruff.manager.log.record(
+            super::super::protocol_log::LogDirection::Sent,
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize"}"#,
+        );
+
+        let info = manager.info_report();
+        assert!(info.contains("## python\n"));
+        assert!(info.contains("## ruff\n"));
+        assert!(info.contains("- Command: `nevi-test-missing-server server`\n"));
+        assert!(info.contains("- /tmp/nevi_lsp_test/app.py (version 3)\n"));
+        assert!(info.contains("- codeLensProvider\n"));
+        assert!(info.contains("- Status: ready\n"));
+
+        let log = manager.log_report(Some("ruff")).expect("ruff log");
+        assert!(log.contains(" ruff → initialize #1\n"));
+        // A language name covers every server attached to it.
+        assert_eq!(
+            manager.running_servers_named("python"),
+            vec!["python", "ruff"]
+        );
+        assert!(manager.log_report(Some("go")).is_err());
+
+        assert_eq!(
+            manager.restart(Some("ruff"), None).expect("restart ruff"),
+            vec!["ruff"]
+        );
+        assert!(!manager.instances["ruff"].ready);
+        assert!(manager.instances["python"].ready);
+        assert_eq!(manager.take_reattach("ruff"), vec![path]);
+        assert!(manager.take_reattach("ruff").is_empty());
+        assert!(manager.restart(Some("go"), None).is_err());
+    }
+
+    #[test]
+    fn code_lenses_run_and_resolve_on_the_server_that_sent_them() {
+        let path = PathBuf::from("/tmp/nevi_lsp_test/app.py");
+        let uri = crate::lsp::path_to_uri(&path);
+        let mut manager = python_manager(0);
+        add_ready_server(&mut manager, "python", &[], vec![]);
+        let ruff_requests = add_ready_server(&mut manager, "ruff", &["codeLensProvider"], vec![]);
+        let lens = |command: Option<LspCommand>| CodeLens {
+            line: 2,
+            col: 0,
+            command,
+            raw: serde_json::json!({ "data": 1 }),
+        };
+        let run = LspCommand {
+            title: "▶ Run".to_string(),
+            command: "ruff.run".to_string(),
+            arguments: Vec::new(),
+            server: None,
+        };
+
+        let merged = manager.merge_notification(
+            "ruff",
+            LspNotification::CodeLenses {
+                lenses: vec![lens(Some(run.clone())), lens(None)],
+                request_uri: uri.clone(),
+                request_version: 4,
+            },
+        );
+        let Some(LspNotification::CodeLenses { lenses, .. }) = merged else {
+            panic!("expected code lenses, got {merged:?}");
+        };
+        assert_eq!(
+            lenses[0].command.as_ref().and_then(|c| c.server.as_deref()),
+            Some("ruff")
+        );
+        assert_eq!(lenses[1].command, None);
+
+        // Without resolve support, unresolved lenses stay as they are.
+        manager
+            .resolve_code_lens(&path, 4, 1, serde_json::json!({}))
+            .expect("resolve code lens");
+        manager.code_lens(&path, 4).expect("request code lenses");
+        assert!(matches!(
+            ruff_requests.try_recv(),
+            Ok(crate::lsp::LspRequest::CodeLens {
+                buffer_version: 4,
+                ..
+            })
+        ));
+        assert!(ruff_requests.try_recv().is_err());
+
+        manager
+            .instances
+            .get_mut("ruff")
+            .expect("ruff instance")
+            .capabilities
+            .code_lens_resolve = true;
+        manager
+            .resolve_code_lens(&path, 4, 1, serde_json::json!({ "data": 1 }))
+            .expect("resolve code lens");
+        assert!(matches!(
+            ruff_requests.try_recv(),
+            Ok(crate::lsp::LspRequest::CodeLensResolve { index: 1, .. })
+        ));
+
+        let resolved = manager.merge_notification(
+            "ruff",
+            LspNotification::CodeLensResolved {
+                lens: lens(Some(run)),
+                index: 1,
+                request_uri: uri,
+                request_version: 4,
+            },
+        );
+        assert!(matches!(
+            resolved,
+            Some(LspNotification::CodeLensResolved { lens, .. })
+                if lens.command.as_ref().and_then(|c| c.server.as_deref()) == Some("ruff")
+        ));
+    }
+
+    #[test]
+    fn completions_are_merged_and_deduplicated() {
+        let path = PathBuf::from("/tmp/nevi_lsp_test/app.py");
+        let uri = crate::lsp::path_to_uri(&path);
+        let item = |item_id, label: &str| CompletionItem {
+            item_id,
+            label: label.to_string(),
+            kind: crate::lsp::types::CompletionKind::Function,
+            detail: None,
+            documentation: None,
+            insert_text: None,
+            filter_text: None,
+            sort_text: None,
+            text_edit: None,
+            additional_text_edits: Vec::new(),
+            raw_data: None,
+        };
+        let completions = |items, is_incomplete| LspNotification::Completions {
+            items,
+            is_incomplete,
+            request_uri: uri.clone(),
+            request_line: 3,
+            request_character: 4,
+            request_version: 7,
+        };
+
+        let mut manager = python_manager(0);
+        let pyright = add_ready_server(&mut manager, "python", &["completionProvider"], vec![]);
+        let ruff = add_ready_server(&mut manager, "ruff", &["completionProvider"], vec![]);
+        manager.completion(&path, 3, 4, 7).expect("completion");
+        assert!(pyright.try_recv().is_ok());
+        assert!(ruff.try_recv().is_ok());
+
+        manager.merge_notification("python", completions(vec![item(1, "print")], false));
+        let merged = manager.merge_notification(
+            "ruff",
+            completions(vec![item(2, "print"), item(3, "pprint")], true),
+        );
+        let Some(LspNotification::Completions {
+            items,
+            is_incomplete,
+            ..
+        }) = merged
+        else {
+            panic!("expected completions, got {merged:?}");
+        };
+        let labels: Vec<&str> = items.iter().map(|item| item.label.as_str()).collect();
+        assert_eq!(labels, vec!["print", "pprint"]);
+        assert!(is_incomplete);
+
+        // Items are resolved by the server that offered them.
+        manager
+            .completion_resolve(&path, serde_json::Value::Null, 3, "pprint".to_string())
+            .expect("resolve");
+        assert!(matches!(
+            ruff.try_recv(),
+            Ok(crate::lsp::LspRequest::CompletionResolve { item_id: 3, .. })
+        ));
+        assert!(pyright.try_recv().is_err());
+    }
+
+    #[test]
+    fn code_actions_are_gathered_from_every_server() {
+        let path = PathBuf::from("/tmp/nevi_lsp_test/app.py");
+        let uri = crate::lsp::path_to_uri(&path);
+        let action = |title: &str| CodeActionItem {
+            title: title.to_string(),
+            kind: Some("quickfix".to_string()),
+            is_preferred: false,
+            edits: Vec::new(),
+            command: Some(crate::lsp::types::LspCommand {
+                title: title.to_string(),
+                command: "fix".to_string(),
+                arguments: Vec::new(),
+                server: None,
+            }),
+        };
+        let code_actions = |actions| LspNotification::CodeActions {
+            actions,
+            request_uri: uri.clone(),
+            request_version: 2,
+        };
+
+        let mut manager = python_manager(1);
+        let _pyright = add_ready_server(&mut manager, "python", &["codeActionProvider"], vec![]);
+        let _ruff = add_ready_server(&mut manager, "ruff", &["codeActionProvider"], vec![]);
+        manager
+            .code_action(&path, 0, 0, 0, 1, 2, Vec::new())
+            .expect("code action");
+
+        assert!(
+            manager
+                .merge_notification("python", code_actions(vec![action("Add type")]))
+                .is_none()
+        );
+        let merged = manager.merge_notification("ruff", code_actions(vec![action("Fix import")]));
+        let Some(LspNotification::CodeActions { actions, .. }) = merged else {
+            panic!("expected code actions, got {merged:?}");
+        };
+        let offered: Vec<(&str, Option<&str>)> = actions
+            .iter()
+            .map(|action| {
+                (
+                    action.title.as_str(),
+                    action.command.as_ref().and_then(|c| c.server.as_deref()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            offered,
+            vec![("Fix import", Some("ruff")), ("Add type", Some("python"))]
+        );
+
+        // A server that never answers does not hold back the others for long.
+        manager
+            .code_action(&path, 0, 0, 0, 1, 2, Vec::new())
+            .expect("code action");
+        assert!(
+            manager
+                .merge_notification("python", code_actions(vec![action("Add type")]))
+                .is_none()
+        );
+        let started_at = manager.code_actions.as_ref().expect("gathering").started_at;
+        assert!(manager.take_timed_out_code_actions(started_at).is_none());
+        let timed_out = manager.take_timed_out_code_actions(started_at + CODE_ACTION_TIMEOUT);
+        assert!(matches!(
+            timed_out,
+            Some((_, LspNotification::CodeActions { actions, .. })) if actions.len() == 1
+        ));
+    }
+
+    #[test]
+    fn update_servers_pushes_changed_settings_once_servers_are_ready() {
+        let mut manager = make_manager(PathBuf::from("/tmp/nevi_lsp_test"));
+        let rust_rx = add_ready_server(&mut manager, "rust", &[], Vec::new());
+        let python_rx = add_ready_server(
+            &mut manager,
+            "python",
+            &[],
+            vec![LspNotification::Initialized {
+                capabilities: ServerCapabilities::default(),
+            }],
+        );
+        let shell_rx = add_ready_server(&mut manager, "shell", &[], Vec::new());
+        manager.instances.get_mut("python").unwrap().ready = false;
+
+        let mut servers = LspServers::default();
+        let analysis: toml::Table = toml::from_str("typeCheckingMode = \"strict\"").unwrap();
+        servers.rust.settings.insert(
+            "rust-analyzer".to_string(),
+            toml::Value::Table(toml::Table::new()),
+        );
+        servers
+            .python
+            .settings
+            .insert("python.analysis".to_string(), toml::Value::Table(analysis));
+
+        assert_eq!(manager.update_servers(servers), vec!["python", "rust"]);
+        assert!(matches!(
+            rust_rx.try_recv(),
+            Ok(crate::lsp::LspRequest::DidChangeConfiguration { settings })
+                if settings == serde_json::json!({ "rust-analyzer": {} })
+        ));
+        assert!(python_rx.try_recv().is_err());
+        assert!(shell_rx.try_recv().is_err());
+
+        manager.poll_notifications();
+
+        assert!(matches!(
+            python_rx.try_recv(),
+            Ok(crate::lsp::LspRequest::DidChangeConfiguration { settings })
+                if settings["python.analysis"]["typeCheckingMode"] == "strict"
+        ));
+    }
 }
@@ -0,0 +1,282 @@
+//! A record of what passed between nevi and one language server: every
+//! JSON-RPC message in and out and each line of its stderr, kept in a ring
+//! buffer for `:LspLog`.
+
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Entries kept per server; older ones are dropped first
+pub const PROTOCOL_LOG_CAPACITY: usize = 1000;
+/// Longest entry kept whole. `didOpen` and `didChange` carry entire
+/// documents, which would crowd out everything else.
+const MAX_ENTRY_LEN: usize = 16 * 1024;
+
+/// Which way an entry went
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogDirection {
+    /// Written to the server's stdin
+    Sent,
+    /// Read from the server's stdout
+    Received,
+    /// A line of the server's stderr
+    Stderr,
+}
+
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub at: SystemTime,
+    pub direction: LogDirection,
+    pub text: String,
+}
+
+#[derive(Debug, Default)]
+struct LogState {
+    entries: VecDeque<LogEntry>,
+    pid: Option<u32>,
+}
+
+/// The log of one server, shared by the threads that talk to it
+#[derive(Debug, Clone, Default)]
+pub struct ProtocolLog(Arc<Mutex<LogState>>);
+
+impl ProtocolLog {
+    pub fn record(&self, direction: LogDirection, text: &str) {
+        let text = if text.len() > MAX_ENTRY_LEN {
+            let mut end = MAX_ENTRY_LEN;
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            format!("{}… ({} bytes in all)", &text[..end], text.len())
+        } else {
+            text.to_string()
+        };
+        let Ok(mut state) = self.0.lock() else {
+            return;
+        };
+        if state.entries.len() == PROTOCOL_LOG_CAPACITY {
+            state.entries.pop_front();
+        }
+        state.entries.push_back(LogEntry {
+            at: SystemTime::now(),
+            direction,
+            text,
+        });
+    }
+
+    /// The entries still in the buffer, oldest first
+    pub fn entries(&self) -> Vec<LogEntry> {
+        self.0
+            .lock()
+            .map(|state| state.entries.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn set_pid(&self, pid: u32) {
+        if let Ok(mut state) = self.0.lock() {
+            state.pid = Some(pid);
+        }
+    }
+
+    /// Process id of the server, once spawned
+    pub fn pid(&self) -> Option<u32> {
+        self.0.lock().ok().and_then(|state| state.pid)
+    }
+}
+
+/// A server's stdin that records every message written to it. Writers send
+/// whole `Content-Length` framed messages and flush after each, so the
+/// bytes since the last flush are parsed back into message bodies.
+pub struct LoggedStdin<W: Write> {
+    inner: W,
+    log: ProtocolLog,
+    unflushed: Vec<u8>,
+}
+
+impl<W: Write> LoggedStdin<W> {
+    pub fn new(inner: W, log: ProtocolLog) -> Self {
+        Self {
+            inner,
+            log,
+            unflushed: Vec::new(),
+        }
+    }
+
+    fn record_unflushed(&mut self) {
+        let mut rest = self.unflushed.as_slice();
+        while let Some((body, next)) = split_frame(rest) {
+            self.log
+                .record(LogDirection::Sent, &String::from_utf8_lossy(body));
+            rest = next;
+        }
+        self.unflushed.clear();
+    }
+}
+
+impl<W: Write> Write for LoggedStdin<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.unflushed.extend_from_slice(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.record_unflushed();
+        self.inner.flush()
+    }
+}
+
+/// Split one framed message off the front of `bytes`, as `(body, rest)`
+fn split_frame(bytes: &[u8]) -> Option<(&[u8], &[u8])> {
+    let header_end = bytes.windows(4).position(|window| window == b"\r\n\r\n")?;
+    let length = std::str::from_utf8(&bytes[..header_end])
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("Content-Length: "))?
+        .trim()
+        .parse::<usize>()
+        .ok()?;
+    let body = bytes.get(header_end + 4..header_end + 4 + length)?;
+    Some((body, &bytes[header_end + 4 + length..]))
+}
+
+/// The logs of several servers merged by time, as JSON with a `//` comment
+/// heading each message: `// 14:03:07.215 rust → initialize`
+pub fn render_protocol_logs(logs: &[(&str, Vec<LogEntry>)]) -> String {
+    let mut entries: Vec<(&str, &LogEntry)> = logs
+        .iter()
+        .flat_map(|(server, entries)| entries.iter().map(move |entry| (*server, entry)))
+        .collect();
+    entries.sort_by_key(|(_, entry)| entry.at);
+
+    let mut out = String::from("// LSP protocol log, oldest first (times are UTC)\n");
+    for (server, entry) in entries {
+        let time = clock_time(entry.at);
+        match entry.direction {
+            LogDirection::Stderr => {
+                out.push_str(&format!("// {time} {server} stderr: {}\n", entry.text));
+            }
+            direction => {
+                let arrow = if direction == LogDirection::Sent {
+                    "→"
+                } else {
+                    "←"
+                };
+                let message = serde_json::from_str::<serde_json::Value>(&entry.text).ok();
+                let summary = message.as_ref().map(message_summary).unwrap_or_default();
+                out.push_str(&format!("\n// {time} {server} {arrow} {summary}\n"));
+                let body = message
+                    .and_then(|message| serde_json::to_string_pretty(&message).ok())
+                    .unwrap_or_else(|| entry.text.clone());
+                out.push_str(&body);
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+/// `method` for requests and notifications, `response #id` for responses
+fn message_summary(message: &serde_json::Value) -> String {
+    let id = message.get("id").map(|id| match id {
+        serde_json::Value::String(id) => id.clone(),
+        id => id.to_string(),
+    });
+    match (message.get("method").and_then(|method| method.as_str()), id) {
+        (Some(method), Some(id)) => format!("{method} #{id}"),
+        (Some(method), None) => method.to_string(),
+        (None, Some(id)) if message.get("error").is_some() => format!("error #{id}"),
+        (None, Some(id)) => format!("response #{id}"),
+        (None, None) => String::new(),
+    }
+}
+
+/// `HH:MM:SS.mmm` of the UTC day
+fn clock_time(at: SystemTime) -> String {
+    let since_epoch = at.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs() % 86_400;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(body: &str) -> String {
+        format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+    }
+
+    #[test]
+    fn written_messages_are_recorded_on_flush() {
+        let log = ProtocolLog::default();
+        let mut stdin = LoggedStdin::new(Vec::new(), log.clone());
+        let initialize = r#"{"jsonrpc":"2.0","id":1,"method":"initialize"}"#;
+        let initialized = r#"{"jsonrpc":"2.0","method":"initialized","params":{}}"#;
+
+        stdin.write_all(frame(initialize).as_bytes()).unwrap();
+        assert!(log.entries().is_empty());
+        stdin.flush().unwrap();
+        stdin
+            .write_all(format!("{}{}", frame(initialized), frame("{}")).as_bytes())
+            .unwrap();
+        stdin.flush().unwrap();
+
+        let texts: Vec<String> = log.entries().into_iter().map(|entry| entry.text).collect();
+        assert_eq!(texts, vec![initialize, initialized, "{}"]);
+        assert_eq!(
+            stdin.inner,
+            format!("{}{}{}", frame(initialize), frame(initialized), frame("{}")).into_bytes()
+        );
+    }
+
+    #[test]
+    fn the_log_keeps_the_newest_entries_and_cuts_long_ones() {
+        let log = ProtocolLog::default();
+        for n in 0..PROTOCOL_LOG_CAPACITY + 5 {
+            log.record(LogDirection::Stderr, &n.to_string());
+        }
+        let entries = log.entries();
+        assert_eq!(entries.len(), PROTOCOL_LOG_CAPACITY);
+        assert_eq!(entries[0].text, "5");
+
+        log.record(LogDirection::Sent, &"é".repeat(MAX_ENTRY_LEN));
+        let long = log.entries().pop().unwrap().text;
+        assert!(long.len() < MAX_ENTRY_LEN + 32);
+        assert!(long.ends_with(&format!("… ({} bytes in all)", 2 * MAX_ENTRY_LEN)));
+    }
+
+    #[test]
+    fn rendered_logs_merge_servers_and_pretty_print_messages() {
+        let at = UNIX_EPOCH + std::time::Duration::from_millis(49_387_215);
+        let entry = |secs: u64, direction, text: &str| LogEntry {
+            at: at + std::time::Duration::from_secs(secs),
+            direction,
+            text: text.to_string(),
+        };
+        let rendered = render_protocol_logs(&[
+            (
+                "rust",
+                vec![
+                    entry(0, LogDirection::Sent, r#"{"id":1,"method":"initialize"}"#),
+                    entry(2, LogDirection::Received, r#"{"id":1,"result":null}"#),
+                ],
+            ),
+            ("eslint", vec![entry(1, LogDirection::Stderr, "warming up")]),
+        ]);
+
+        let expected = "// LSP protocol log, oldest first (times are UTC)\n\
+            \n// 13:43:07.215 rust → initialize #1\n\
+            {\n  \"id\": 1,\n  \"method\": \"initialize\"\n}\n\
+            // 13:43:08.215 eslint stderr: warming up\n\
+            \n// 13:43:09.215 rust ← response #1\n\
+            {\n  \"id\": 1,\n  \"result\": null\n}\n";
+        assert_eq!(rendered, expected);
+    }
+}
@@ -17,6 +17,51 @@ pub struct TextEdit {
     pub new_text: String,
 }
 
+/// One change of a `textDocument/didChange` notification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentChange {
+    /// `(line, character)` of the start and end of the replaced text, in
+    /// the document as it was before the change, with UTF-16 columns.
+    /// `None` replaces the whole document.
+    pub range: Option<((u32, u32), (u32, u32))>,
+    pub text: String,
+}
+
+/// A `WorkspaceEdit` from the server, flattened into the order its changes
+/// must be applied in
+#[derive(Debug, Clone, Default)]
+pub struct WorkspaceEdit {
+    pub changes: Vec<WorkspaceEditChange>,
+}
+
+/// One step of a workspace edit
+#[derive(Debug, Clone)]
+pub enum WorkspaceEditChange {
+    /// Text edits for one document. `version` is the document version the
+    /// server computed them against, when it sent one
+    Edit {
+        uri: String,
+        version: Option<i32>,
+        edits: Vec<TextEdit>,
+    },
+    CreateFile {
+        uri: String,
+        overwrite: bool,
+        ignore_if_exists: bool,
+    },
+    RenameFile {
+        old_uri: String,
+        new_uri: String,
+        overwrite: bool,
+        ignore_if_exists: bool,
+    },
+    DeleteFile {
+        uri: String,
+        recursive: bool,
+        ignore_if_not_exists: bool,
+    },
+}
+
 /// Kind of LSP request - used for tracking responses
 /// Includes request context so responses can be validated against current state
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -44,6 +89,11 @@ pub enum RequestKind {
         line: u32,
         character: u32,
     },
+    TypeDefinition {
+        uri: String,
+        line: u32,
+        character: u32,
+    },
     Hover {
         uri: String,
         line: u32,
@@ -58,6 +108,14 @@ pub enum RequestKind {
         uri: String,
         buffer_version: u64,
     },
+    RangeFormatting {
+        uri: String,
+        buffer_version: u64,
+    },
+    OnTypeFormatting {
+        uri: String,
+        buffer_version: u64,
+    },
     References {
         uri: String,
         line: u32,
@@ -78,12 +136,66 @@ pub enum RequestKind {
         new_name: String,
         buffer_version: u64,
     },
+    DocumentSymbols {
+        uri: String,
+    },
+    WorkspaceSymbols {
+        query: String,
+    },
+    PrepareHierarchy {
+        uri: String,
+        direction: HierarchyDirection,
+    },
+    HierarchyChildren {
+        direction: HierarchyDirection,
+        /// Tree node the children belong to
+        node: u64,
+    },
+    InlayHints {
+        uri: String,
+        start_line: u32,
+        end_line: u32,
+        buffer_version: u64,
+    },
+    DocumentHighlight {
+        uri: String,
+        line: u32,
+        character: u32,
+        buffer_version: u64,
+    },
+    SelectionRange {
+        uri: String,
+        buffer_version: u64,
+    },
+    CodeLens {
+        uri: String,
+        buffer_version: u64,
+    },
+    CodeLensResolve {
+        uri: String,
+        buffer_version: u64,
+        /// Position of the lens in the answer to `textDocument/codeLens`
+        index: usize,
+    },
+    SemanticTokens {
+        uri: String,
+        previous_result_id: Option<String>,
+        buffer_version: u64,
+    },
+    DocumentDiagnostic {
+        uri: String,
+    },
+    WorkspaceDiagnostic,
     CompletionResolve {
         /// Stable ID of the item being resolved.
         item_id: u64,
         /// Label of the item being resolved (for matching response to item)
         label: String,
     },
+    ExecuteCommand {
+        /// Title shown to the user for the command
+        title: String,
+    },
 }
 
 /// Requests sent from the editor to the LSP client thread
@@ -107,7 +219,7 @@ pub enum LspRequest {
     DidChange {
         uri: String,
         version: i32,
-        text: String,
+        changes: Vec<ContentChange>,
     },
 
     /// Document was closed
@@ -149,6 +261,12 @@ pub enum LspRequest {
         line: u32,
         character: u32,
     },
+    /// Request go-to-type-definition
+    GotoTypeDefinition {
+        uri: String,
+        line: u32,
+        character: u32,
+    },
 
     /// Request hover information
     Hover {
@@ -171,6 +289,26 @@ pub enum LspRequest {
         buffer_version: u64,
     },
 
+    /// Request formatting of part of a document (`gq`)
+    RangeFormatting {
+        uri: String,
+        range: TextRange,
+        tab_size: u32,
+        insert_spaces: bool,
+        buffer_version: u64,
+    },
+
+    /// Request formatting after typing a trigger character
+    OnTypeFormatting {
+        uri: String,
+        line: u32,
+        character: u32,
+        ch: char,
+        tab_size: u32,
+        insert_spaces: bool,
+        buffer_version: u64,
+    },
+
     /// Request find references
     References {
         uri: String,
@@ -197,13 +335,106 @@ pub enum LspRequest {
         new_name: String,
         buffer_version: u64,
     },
+
+    /// Request the symbol outline of a document
+    DocumentSymbols { uri: String },
+
+    /// Search symbols across the workspace
+    WorkspaceSymbols { query: String },
+
+    /// Prepare a call or type hierarchy at a position
+    PrepareHierarchy {
+        uri: String,
+        line: u32,
+        character: u32,
+        direction: HierarchyDirection,
+    },
+
+    /// Request the children of a hierarchy item in `direction`
+    HierarchyChildren {
+        direction: HierarchyDirection,
+        node: u64,
+        /// The item as the server sent it
+        item: serde_json::Value,
+    },
+
+    /// Request inlay hints for a range of lines (end exclusive)
+    InlayHints {
+        uri: String,
+        start_line: u32,
+        end_line: u32,
+        buffer_version: u64,
+    },
+
+    /// Request the ranges that read or write the symbol at a position
+    DocumentHighlight {
+        uri: String,
+        line: u32,
+        character: u32,
+        buffer_version: u64,
+    },
+
+    /// Request the ranges enclosing a position, innermost first
+    SelectionRange {
+        uri: String,
+        line: u32,
+        character: u32,
+        buffer_version: u64,
+    },
+
+    /// Request the code lenses of a whole document
+    CodeLens { uri: String, buffer_version: u64 },
+
+    /// Fill in the command of lens `index` of the answer for `buffer_version`
+    CodeLensResolve {
+        uri: String,
+        buffer_version: u64,
+        index: usize,
+        /// The lens as the server sent it
+        lens: serde_json::Value,
+    },
+
+    /// Request semantic tokens for a whole document: a delta against
+    /// `previous_result_id` when there is one, the full set otherwise
+    SemanticTokens {
+        uri: String,
+        previous_result_id: Option<String>,
+        buffer_version: u64,
+    },
+
+    /// Pull the diagnostics of a document (`textDocument/diagnostic`)
+    DocumentDiagnostic {
+        uri: String,
+        previous_result_id: Option<String>,
+    },
+
+    /// Pull the diagnostics of the whole workspace (`workspace/diagnostic`),
+    /// as `(uri, result_id)` of the reports already held
+    WorkspaceDiagnostic {
+        previous_result_ids: Vec<(String, String)>,
+    },
+
+    /// Run a server-side command (`workspace/executeCommand`)
+    ExecuteCommand { command: LspCommand },
+
+    /// Answer a server's `workspace/applyEdit` request
+    ApplyEditResponse {
+        /// JSON-RPC id of the server request
+        request_id: serde_json::Value,
+        applied: bool,
+        failure_reason: Option<String>,
+    },
+
+    /// Replace the server's settings and push them
+    /// (`workspace/didChangeConfiguration`)
+    DidChangeConfiguration { settings: serde_json::Value },
 }
 
 /// Notifications sent from the LSP client thread to the editor
 #[derive(Debug, Clone)]
 pub enum LspNotification {
     /// Server initialization complete
-    Initialized,
+    Initialized { capabilities: ServerCapabilities },
 
     /// Server failed to start or crashed
     Error { message: String },
@@ -263,7 +494,8 @@ pub enum LspNotification {
         message: Option<String>,
     },
 
-    /// Formatting result with text edits to apply
+    /// Formatting result (whole document, range or on-type) with text
+    /// edits to apply
     Formatting {
         edits: Vec<TextEdit>,
         /// Request context for validation
@@ -286,6 +518,113 @@ pub enum LspNotification {
         request_version: u64,
     },
 
+    /// Document symbols result, flattened in outline order
+    DocumentSymbols {
+        symbols: Vec<SymbolItem>,
+        /// Request context for validation
+        request_uri: String,
+    },
+
+    /// Workspace symbols result
+    WorkspaceSymbols {
+        symbols: Vec<SymbolItem>,
+        /// Query the results answer
+        query: String,
+    },
+
+    /// Items a call or type hierarchy starts from
+    HierarchyPrepared {
+        direction: HierarchyDirection,
+        items: Vec<HierarchyItem>,
+        /// Request context for validation
+        request_uri: String,
+    },
+
+    /// Children of a hierarchy tree node
+    HierarchyChildren {
+        direction: HierarchyDirection,
+        node: u64,
+        items: Vec<HierarchyItem>,
+    },
+
+    /// Inlay hints for a range of lines
+    InlayHints {
+        hints: Vec<InlayHint>,
+        /// Request context for validation
+        request_uri: String,
+        request_start_line: u32,
+        request_end_line: u32,
+        request_version: u64,
+    },
+
+    /// The server asked for inlay hints to be requested again
+    /// (`workspace/inlayHint/refresh`)
+    InlayHintRefresh,
+
+    /// Occurrences of the symbol under the cursor
+    DocumentHighlights {
+        highlights: Vec<DocumentHighlight>,
+        /// Request context for validation
+        request_uri: String,
+        request_line: u32,
+        request_character: u32,
+        request_version: u64,
+    },
+
+    /// Ranges enclosing a position, innermost first. Empty when the server
+    /// had none or the request failed.
+    SelectionRanges {
+        ranges: Vec<TextRange>,
+        /// Request context for validation
+        request_uri: String,
+        request_version: u64,
+    },
+
+    /// Code lenses of a document, some waiting to be resolved
+    CodeLenses {
+        lenses: Vec<CodeLens>,
+        /// Request context for validation
+        request_uri: String,
+        request_version: u64,
+    },
+
+    /// Lens `index` of the lenses for `request_version`, with its command
+    CodeLensResolved {
+        lens: CodeLens,
+        index: usize,
+        /// Request context for validation
+        request_uri: String,
+        request_version: u64,
+    },
+
+    /// The server asked for code lenses to be requested again
+    /// (`workspace/codeLens/refresh`)
+    CodeLensRefresh,
+
+    /// Semantic tokens for a document, still encoded against the legend.
+    /// `tokens` is `None` when the request failed.
+    SemanticTokens {
+        tokens: Option<SemanticTokensData>,
+        result_id: Option<String>,
+        /// Request context for validation
+        request_uri: String,
+        /// Result a delta applies to
+        request_previous_result_id: Option<String>,
+        request_version: u64,
+    },
+
+    /// The server asked for semantic tokens to be requested again
+    /// (`workspace/semanticTokens/refresh`)
+    SemanticTokensRefresh,
+
+    /// Pulled diagnostic reports, one per document. Answers to
+    /// `textDocument/diagnostic` hold one, `workspace/diagnostic` any number.
+    PulledDiagnostics { reports: Vec<DiagnosticReport> },
+
+    /// The server asked for diagnostics to be pulled again
+    /// (`workspace/diagnostic/refresh`)
+    DiagnosticRefresh,
+
     /// Rename result with workspace edits
     RenameResult {
         /// Edits grouped by file URI
@@ -311,6 +650,21 @@ pub enum LspNotification {
         additional_text_edits: Vec<TextEdit>,
     },
 
+    /// A `workspace/executeCommand` request completed
+    CommandExecuted {
+        /// Title of the command that ran
+        title: String,
+    },
+
+    /// The server asked the editor to apply a workspace edit; answer with
+    /// `LspRequest::ApplyEditResponse`
+    ApplyEdit {
+        /// JSON-RPC id of the server request
+        request_id: serde_json::Value,
+        label: Option<String>,
+        edit: WorkspaceEdit,
+    },
+
     /// Work-done progress from a language server.
     Progress {
         title: String,
@@ -325,6 +679,7 @@ pub enum LspNavigationTargetKind {
     Definition,
     Declaration,
     Implementation,
+    TypeDefinition,
 }
 
 impl LspNavigationTargetKind {
@@ -333,6 +688,7 @@ impl LspNavigationTargetKind {
             Self::Definition => "definition",
             Self::Declaration => "declaration",
             Self::Implementation => "implementation",
+            Self::TypeDefinition => "type definition",
         }
     }
 }
@@ -348,8 +704,21 @@ pub struct CodeActionItem {
     pub is_preferred: bool,
     /// Edits to apply (if any)
     pub edits: Vec<(String, Vec<TextEdit>)>,
-    /// Command to execute (if any)
-    pub command: Option<String>,
+    /// Command to execute (if any), after the edits are applied
+    pub command: Option<LspCommand>,
+}
+
+/// A server-side command, as carried by code actions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LspCommand {
+    /// Display title for the command
+    pub title: String,
+    /// Command identifier understood by the server
+    pub command: String,
+    /// Arguments passed back to the server unchanged
+    pub arguments: Vec<serde_json::Value>,
+    /// Name of the server that offered the command, which runs it
+    pub server: Option<String>,
 }
 
 /// Signature help information
@@ -397,6 +766,41 @@ pub struct Diagnostic {
     pub source: Option<String>,
     /// The diagnostic code (e.g., TypeScript error number) - needed for code actions
     pub code: Option<DiagnosticCode>,
+    /// Unused or deprecated code the diagnostic marks
+    pub tags: Vec<DiagnosticTag>,
+    /// Other places that take part in the diagnostic
+    pub related: Vec<DiagnosticRelatedInformation>,
+    /// Where the code is explained (`codeDescription.href`)
+    pub href: Option<String>,
+}
+
+/// How a diagnostic's range is drawn on top of its severity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticTag {
+    /// Unused code, drawn dimmed
+    Unnecessary,
+    /// Deprecated code, drawn struck through
+    Deprecated,
+}
+
+/// A location related to a diagnostic (0-indexed line, UTF-16 column)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiagnosticRelatedInformation {
+    pub uri: String,
+    pub line: usize,
+    pub col: usize,
+    pub message: String,
+}
+
+/// One document's answer to a diagnostic pull
+#[derive(Debug, Clone)]
+pub struct DiagnosticReport {
+    pub uri: String,
+    /// Sent back with the next pull so the server can answer "unchanged"
+    pub result_id: Option<String>,
+    /// The document's diagnostics, or `None` when they are unchanged since
+    /// the previous result
+    pub diagnostics: Option<Vec<Diagnostic>>,
 }
 
 /// Diagnostic code can be either an integer or a string
@@ -544,6 +948,187 @@ pub struct Location {
     pub col: usize,
 }
 
+/// A symbol from `textDocument/documentSymbol` or `workspace/symbol`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolItem {
+    pub name: String,
+    /// Extra detail such as a signature
+    pub detail: Option<String>,
+    pub kind: CompletionKind,
+    pub uri: String,
+    /// Position of the symbol's name (0-indexed line, UTF-16 column)
+    pub line: usize,
+    pub col: usize,
+    /// Nesting depth in the document outline (0 for top-level symbols)
+    pub depth: usize,
+    /// Name of the enclosing symbol, when the server reports one
+    pub container: Option<String>,
+}
+
+/// The relation a call or type hierarchy explores
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HierarchyDirection {
+    /// Functions that call the item (`callHierarchy/incomingCalls`)
+    IncomingCalls,
+    /// Functions the item calls (`callHierarchy/outgoingCalls`)
+    OutgoingCalls,
+    /// Types the item extends or implements (`typeHierarchy/supertypes`)
+    Supertypes,
+    /// Types that extend or implement the item (`typeHierarchy/subtypes`)
+    Subtypes,
+}
+
+impl HierarchyDirection {
+    /// Whether this direction belongs to the call hierarchy
+    pub fn is_call(self) -> bool {
+        matches!(self, Self::IncomingCalls | Self::OutgoingCalls)
+    }
+
+    /// Title shown on the hierarchy popup
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::IncomingCalls => "Incoming Calls",
+            Self::OutgoingCalls => "Outgoing Calls",
+            Self::Supertypes => "Supertypes",
+            Self::Subtypes => "Subtypes",
+        }
+    }
+}
+
+/// An item of a call or type hierarchy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HierarchyItem {
+    pub name: String,
+    /// Extra detail such as a signature or container
+    pub detail: Option<String>,
+    pub kind: CompletionKind,
+    pub uri: String,
+    /// Where jumping to the item lands: the first call site for incoming
+    /// calls, the item's name otherwise (0-indexed line, UTF-16 column)
+    pub line: usize,
+    pub col: usize,
+    /// The item as the server sent it, passed back to expand it
+    pub raw: serde_json::Value,
+}
+
+/// An inlay hint: a label shown between buffer characters
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InlayHint {
+    /// Position the label goes before (0-indexed line, UTF-16 column)
+    pub line: usize,
+    pub col: usize,
+    /// Label parts joined together
+    pub label: String,
+    pub kind: Option<InlayHintKind>,
+    pub padding_left: bool,
+    pub padding_right: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InlayHintKind {
+    Type,
+    Parameter,
+}
+
+/// A code lens: a command shown above the line it applies to
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeLens {
+    /// Start of the range the lens applies to (0-indexed line, UTF-16 column)
+    pub line: usize,
+    pub col: usize,
+    /// What the lens shows and runs; `None` until resolved
+    pub command: Option<LspCommand>,
+    /// The lens as the server sent it, passed back to resolve it
+    pub raw: serde_json::Value,
+}
+
+/// A range of a document (0-indexed lines, UTF-16 columns, end exclusive)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextRange {
+    pub start_line: usize,
+    pub start_col: usize,
+    pub end_line: usize,
+    pub end_col: usize,
+}
+
+/// An occurrence of the symbol under the cursor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DocumentHighlight {
+    pub range: TextRange,
+    pub kind: DocumentHighlightKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentHighlightKind {
+    /// A textual match, neither a read nor a write
+    Text,
+    Read,
+    Write,
+}
+
+/// What a server offered in its initialize response
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServerCapabilities {
+    /// Capability keys the server turned on (`hoverProvider`,
+    /// `documentFormattingProvider`, ...), sorted
+    pub providers: Vec<String>,
+    /// Semantic token support, when the server offers it
+    pub semantic_tokens: Option<SemanticTokensCapability>,
+    /// The server takes `textDocument/didChange` ranges instead of the
+    /// whole text (`TextDocumentSyncKind::Incremental`)
+    pub incremental_sync: bool,
+    /// Characters that trigger `textDocument/onTypeFormatting`
+    /// (`documentOnTypeFormattingProvider`)
+    pub on_type_formatting_triggers: Vec<char>,
+    /// The server answers `workspace/diagnostic`
+    /// (`diagnosticProvider.workspaceDiagnostics`)
+    pub workspace_diagnostics: bool,
+    /// The server fills in lens commands with `codeLens/resolve`
+    /// (`codeLensProvider.resolveProvider`)
+    pub code_lens_resolve: bool,
+}
+
+impl ServerCapabilities {
+    /// Whether the server turned on `provider` (e.g. `hoverProvider`)
+    pub fn supports(&self, provider: &str) -> bool {
+        self.providers
+            .binary_search_by(|key| key.as_str().cmp(provider))
+            .is_ok()
+    }
+}
+
+/// The server's `semanticTokensProvider`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SemanticTokensCapability {
+    pub legend: SemanticTokensLegend,
+    /// Answers `textDocument/semanticTokens/full/delta`
+    pub delta: bool,
+}
+
+/// Token type and modifier names that encoded tokens index into
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SemanticTokensLegend {
+    pub token_types: Vec<String>,
+    pub token_modifiers: Vec<String>,
+}
+
+/// A semantic tokens response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SemanticTokensData {
+    /// Every token, five integers each, positions relative to the previous
+    Full(Vec<u32>),
+    /// Edits to the integers of the previous result
+    Delta(Vec<SemanticTokensEdit>),
+}
+
+/// Replace `delete_count` integers at `start` with `data`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SemanticTokensEdit {
+    pub start: usize,
+    pub delete_count: usize,
+    pub data: Vec<u32>,
+}
+
 /// LSP server status
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LspStatus {
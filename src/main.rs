@@ -8,7 +8,7 @@ use nevi::copilot::{
     AuthStatus, CopilotCompletion, CopilotManager, CopilotNotification, CopilotStatus,
     utf8_to_utf16_col, utf16_to_utf8_col,
 };
-use nevi::editor::{CopilotAction, CopilotGhostText, LspAction};
+use nevi::editor::{CopilotAction, CopilotGhostText, LspAction, apply_edits_to_file};
 use nevi::lsp;
 use nevi::perf::PerfStats;
 use nevi::terminal::{EditorEvent, execute_leader_action, handle_key};
@@ -180,6 +180,23 @@ fn diagnostic_to_lsp_offsets(
     diagnostic
 }
 
+/// The LSP status line for a buffer's diagnostics
+fn diagnostic_status(diagnostics: &[lsp::types::Diagnostic]) -> String {
+    let count = |severity: lsp::types::DiagnosticSeverity| {
+        diagnostics
+            .iter()
+            .filter(|d| d.severity == severity)
+            .count()
+    };
+    let errors = count(lsp::types::DiagnosticSeverity::Error);
+    let warnings = count(lsp::types::DiagnosticSeverity::Warning);
+    if errors > 0 || warnings > 0 {
+        format!("LSP: {}E {}W", errors, warnings)
+    } else {
+        "LSP: ✓".to_string()
+    }
+}
+
 fn lsp_response_matches_current_buffer(
     editor: &Editor,
     request_uri: &str,
@@ -233,6 +250,36 @@ fn request_selected_completion_resolve(
     let _ = mlsp.completion_resolve(&path, raw_data, item_id, label);
 }
 
+/// Run `:LspLog`, `:LspInfo` or `:LspRestart`
+fn run_lsp_server_action(
+    editor: &mut Editor,
+    mlsp: Option<&mut MultiLspManager>,
+    action: LspAction,
+) {
+    let Some(mlsp) = mlsp else {
+        editor.set_status("LSP not available");
+        return;
+    };
+    match action {
+        LspAction::LspLog(name) => match mlsp.log_report(name.as_deref()) {
+            Ok(report) => editor.open_lsp_log_report(&report),
+            Err(e) => editor.set_status(format!("LSP: {}", e)),
+        },
+        LspAction::LspInfo => editor.open_lsp_info_report(&mlsp.info_report()),
+        LspAction::LspRestart(name) => {
+            let path = editor.buffer().path.clone();
+            match mlsp.restart(name.as_deref(), path.as_deref()) {
+                Ok(servers) => {
+                    editor.set_lsp_status(mlsp.status(path.as_deref()));
+                    editor.set_status(format!("LSP: restarting {}", servers.join(", ")));
+                }
+                Err(e) => editor.set_status(format!("LSP: {}", e)),
+            }
+        }
+        _ => {}
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum CliStartupAction {
     PrintVersion,
@@ -505,6 +552,10 @@ fn main() -> anyhow::Result<()> {
     // Servers are started lazily when files of that type are opened
     let mut multi_lsp: Option<MultiLspManager> = None;
     let mut lsp_current_file: Option<PathBuf> = None; // Track which file LSP knows about
+    // Config file changes are checked now and then to reload server settings
+    let config_check_interval = Duration::from_secs(1);
+    let mut last_config_check = Instant::now();
+    let mut config_mtime = config_file_mtime();
 
     if lsp_enabled {
         // Collect configured root markers so workspace detection is not Cargo-only.
@@ -523,7 +574,10 @@ fn main() -> anyhow::Result<()> {
             &lsp_servers.go,
             &lsp_servers.ruby,
             &lsp_servers.shell,
-        ] {
+        ]
+        .into_iter()
+        .chain(lsp_servers.additional.values())
+        {
             for marker in &cfg.root_patterns {
                 if marker.trim().is_empty() {
                     continue;
@@ -547,22 +601,7 @@ fn main() -> anyhow::Result<()> {
         };
 
         // Create MultiLspManager with all server configs
-        let mgr = MultiLspManager::new(
-            workspace_root,
-            lsp_servers.rust,
-            lsp_servers.typescript,
-            lsp_servers.javascript,
-            lsp_servers.css,
-            lsp_servers.json,
-            lsp_servers.toml,
-            lsp_servers.markdown,
-            lsp_servers.html,
-            lsp_servers.python,
-            lsp_servers.php,
-            lsp_servers.go,
-            lsp_servers.ruby,
-            lsp_servers.shell,
-        );
+        let mgr = MultiLspManager::new(workspace_root, lsp_servers);
         multi_lsp = Some(mgr);
         editor.set_lsp_status("LSP: (no server)");
     }
@@ -602,6 +641,8 @@ fn main() -> anyhow::Result<()> {
     let mut redraw_from_input = false;
     let mut terminal_redraw_pending = false;
     let typing_pause = Duration::from_millis(50);
+    let document_highlight_delay = Duration::from_millis(250);
+    let diagnostic_pull_delay = Duration::from_millis(300);
     let mut last_input_at: Option<Instant> = None;
 
     // Autosave state: track when the last edit occurred
@@ -732,7 +773,7 @@ fn main() -> anyhow::Result<()> {
                     // Dismiss hover popup on any key press
                     editor.hover_content = None;
                     // Dismiss diagnostic float on any key press (it can be reopened with gl)
-                    editor.show_diagnostic_float = false;
+                    let diagnostic_float_open = std::mem::take(&mut editor.show_diagnostic_float);
 
                     if pick_mode {
                         match pick_mode_action_for_key(&editor, key) {
@@ -804,6 +845,20 @@ fn main() -> anyhow::Result<()> {
                         && key.modifiers == KeyModifiers::CONTROL
                         && key.code == KeyCode::Char(' ');
 
+                    // A digit in the diagnostic float jumps to that related location
+                    let related_number = match key.code {
+                        KeyCode::Char(ch @ '1'..='9')
+                            if diagnostic_float_open
+                                && editor.mode == Mode::Normal
+                                && key.modifiers == KeyModifiers::NONE =>
+                        {
+                            let number = ch as usize - '0' as usize;
+                            (number <= editor.diagnostic_related_for_float().len())
+                                .then_some(number)
+                        }
+                        _ => None,
+                    };
+
                     let mut key_went_to_terminal = false;
                     if manual_completion {
                         // Request completion from LSP (only if ready for this file type)
@@ -819,6 +874,10 @@ fn main() -> anyhow::Result<()> {
                                 }
                             }
                         }
+                    } else if let Some(number) = related_number {
+                        if let Err(err) = editor.goto_diagnostic_related(number) {
+                            editor.set_status(err);
+                        }
                     } else {
                         let t_handle_key = Instant::now();
                         let mode_before = editor.mode;
@@ -866,6 +925,14 @@ fn main() -> anyhow::Result<()> {
                         last_resolved_completion = None;
                     }
 
+                    // Server commands work whether or not a server is ready
+                    if let Some(action) = editor
+                        .pending_lsp_action
+                        .take_if(|action| action.manages_servers())
+                    {
+                        run_lsp_server_action(&mut editor, multi_lsp.as_mut(), action);
+                    }
+
                     // Handle pending LSP actions (gd, K) - only if LSP is ready
                     if let Some(action) = editor.pending_lsp_action.take() {
                         if let Some(ref mut mlsp) = multi_lsp {
@@ -877,13 +944,7 @@ fn main() -> anyhow::Result<()> {
                                         &path,
                                         first_line.as_deref(),
                                     ) {
-                                        let message = mlsp
-                                            .language_for_path(&path)
-                                            .map(|lang| {
-                                                mlsp.user_facing_error(lang, &e.to_string())
-                                            })
-                                            .unwrap_or_else(|| e.to_string());
-                                        editor.set_status(format!("LSP: {}", message));
+                                        editor.set_status(format!("LSP: {}", e));
                                     } else {
                                         let status = mlsp.status(Some(path.as_path()));
                                         editor.set_lsp_status(status.clone());
@@ -902,6 +963,9 @@ fn main() -> anyhow::Result<()> {
                                         LspAction::GotoImplementation => {
                                             let _ = mlsp.goto_implementation(&path, line, col);
                                         }
+                                        LspAction::GotoTypeDefinition => {
+                                            let _ = mlsp.goto_type_definition(&path, line, col);
+                                        }
                                         LspAction::Hover => {
                                             let _ = mlsp.hover(&path, line, col);
                                         }
@@ -913,6 +977,21 @@ fn main() -> anyhow::Result<()> {
                                                 editor.buffer().version(),
                                             );
                                         }
+                                        LspAction::RangeFormatting(range) => {
+                                            match mlsp.range_formatting(
+                                                &path,
+                                                range,
+                                                editor.shift_width() as u32,
+                                                editor.expand_tab(),
+                                                editor.buffer().version(),
+                                            ) {
+                                                Ok(true) => editor.pending_format = true,
+                                                Ok(false) => editor.set_status(
+                                                    "No language server formats ranges",
+                                                ),
+                                                Err(_) => {}
+                                            }
+                                        }
                                         LspAction::FindReferences => {
                                             let _ = mlsp.references(&path, line, col);
                                         }
@@ -947,6 +1026,34 @@ fn main() -> anyhow::Result<()> {
                                                 editor.buffer().version(),
                                             );
                                         }
+                                        LspAction::DocumentSymbols => {
+                                            let _ = mlsp.document_symbols(&path);
+                                        }
+                                        LspAction::WorkspaceSymbols => {
+                                            editor.open_finder_workspace_symbols();
+                                        }
+                                        LspAction::Hierarchy(direction) => {
+                                            if let Err(e) =
+                                                mlsp.prepare_hierarchy(&path, line, col, direction)
+                                            {
+                                                editor.set_status(format!("LSP: {}", e));
+                                            }
+                                        }
+                                        LspAction::ExecuteCommand(command) => {
+                                            // The server must see edits the action just
+                                            // made before the command runs against them.
+                                            let changes = editor.take_lsp_changes(&path);
+                                            let _ = mlsp.did_change(&path, changes, || {
+                                                editor.buffer().content()
+                                            });
+                                            if let Err(e) = mlsp.execute_command(&path, command) {
+                                                editor.set_status(format!("LSP: {}", e));
+                                            }
+                                        }
+                                        // Run above
+                                        LspAction::LspLog(_)
+                                        | LspAction::LspInfo
+                                        | LspAction::LspRestart(_) => {}
                                     }
                                 }
                             } else {
@@ -957,6 +1064,20 @@ fn main() -> anyhow::Result<()> {
                         }
                     }
 
+                    // Grow the selection (`an`) from the server's selection
+                    // ranges, or by syntax node when no server offers them
+                    if let Some((path, line, character, version)) =
+                        editor.take_selection_range_request()
+                    {
+                        let sent = multi_lsp.as_mut().is_some_and(|mlsp| {
+                            mlsp.selection_range(&path, line, character, version)
+                                .unwrap_or(false)
+                        });
+                        if !sent {
+                            editor.expand_selection_with_syntax();
+                        }
+                    }
+
                     // Handle pending Copilot actions
                     if let Some(action) = editor.pending_copilot_action.take() {
                         if let Some(ref mut cop) = copilot {
@@ -1041,19 +1162,13 @@ fn main() -> anyhow::Result<()> {
                                             .set_lsp_status(mlsp.status(Some(new_path.as_path())));
                                     }
                                     Err(e) => {
-                                        let message = mlsp
-                                            .language_for_path(new_path)
-                                            .map(|lang| {
-                                                mlsp.user_facing_error(lang, &e.to_string())
-                                            })
-                                            .unwrap_or_else(|| e.to_string());
-                                        editor.set_lsp_status(format!("LSP: {}", message));
+                                        editor.set_lsp_status(format!("LSP: {}", e));
                                     }
                                 }
 
                                 // If server is ready, send did_open
                                 if mlsp.is_ready_for_file(new_path) {
-                                    let text = editor.buffer().content();
+                                    let text = editor.lsp_open_text(new_path).unwrap_or_default();
                                     if let Err(e) = mlsp.did_open(new_path, &text) {
                                         editor.set_lsp_status(format!("LSP: open error: {}", e));
                                     } else {
@@ -1114,15 +1229,18 @@ fn main() -> anyhow::Result<()> {
                         if let Some(ref mut mlsp) = multi_lsp {
                             if let Some(ref path) = current_buffer_path {
                                 if mlsp.is_ready_for_file(path) {
-                                    let t_lsp = Instant::now();
-                                    let text = editor.buffer().content();
-                                    profile!(
-                                        profile_file,
-                                        "buffer.content() for LSP: {:?}",
-                                        t_lsp.elapsed()
-                                    );
                                     let t_send = Instant::now();
-                                    let _ = mlsp.did_change(path, &text);
+                                    let changes = editor.take_lsp_changes(path);
+                                    let _ = mlsp.did_change(path, changes, || {
+                                        let t_lsp = Instant::now();
+                                        let text = editor.buffer().content();
+                                        profile!(
+                                            profile_file,
+                                            "buffer.content() for LSP: {:?}",
+                                            t_lsp.elapsed()
+                                        );
+                                        text
+                                    });
                                     profile!(
                                         profile_file,
                                         "mlsp.did_change: {:?}",
@@ -1179,6 +1297,22 @@ fn main() -> anyhow::Result<()> {
                                             editor_lsp_cursor_col(&editor),
                                         );
                                     }
+
+                                    // Let the server fix up the text after one
+                                    // of its trigger characters (}, ;, newline)
+                                    if editor.mode == Mode::Insert {
+                                        if let Some(ch) = typed_format_trigger(&editor, &key) {
+                                            let _ = mlsp.on_type_formatting(
+                                                path,
+                                                editor.cursor.line as u32,
+                                                editor_lsp_cursor_col(&editor),
+                                                ch,
+                                                editor.shift_width() as u32,
+                                                editor.expand_tab(),
+                                                editor.buffer().version(),
+                                            );
+                                        }
+                                    }
                                 }
                             }
                         }
@@ -1348,35 +1482,208 @@ fn main() -> anyhow::Result<()> {
             if let Some(ref mut mlsp) = multi_lsp {
                 let t_lsp_poll = Instant::now();
                 let mut lsp_notification_count = 0;
-                for (lang, notification) in mlsp.poll_notifications_limited(lsp_notification_limit)
+                for (server, notification) in
+                    mlsp.poll_notifications_limited(lsp_notification_limit)
                 {
                     lsp_notification_count += 1;
                     match notification {
-                        LspNotification::Initialized => {
+                        LspNotification::Initialized { .. } => {
                             // Update status - server is now ready
                             let current_path = editor.buffer().path.clone();
                             editor.set_lsp_status(
                                 mlsp.status(current_path.as_ref().map(|p| p.as_path())),
                             );
+                            // Documents the server had open before `:LspRestart`
+                            let reattach = mlsp.take_reattach(&server);
+                            let restarted = !reattach.is_empty();
+                            for path in reattach {
+                                if Some(&path) == current_path.as_ref() {
+                                    continue;
+                                }
+                                let changes = editor.take_lsp_changes(&path);
+                                let Some(text) = editor.lsp_open_text(&path) else {
+                                    continue;
+                                };
+                                let _ = mlsp.did_change(&path, changes, || text.clone());
+                                let _ = mlsp.did_open(&path, &text);
+                            }
+                            if restarted {
+                                // Requests the old server took are never answered.
+                                editor.refresh_inlay_hints();
+                                editor.refresh_code_lenses();
+                                editor.reset_semantic_tokens();
+                            }
 
                             // Now that this server is ready, send did_open for current file if it matches
                             if let Some(path) = current_path {
-                                if mlsp.language_for_path(&path) == Some(lang) {
-                                    let text = editor.buffer().content();
+                                if mlsp.serves_file(&server, &path) {
+                                    // Servers already attached get the pending
+                                    // edits before the change log restarts.
+                                    let changes = editor.take_lsp_changes(&path);
+                                    let _ = mlsp
+                                        .did_change(&path, changes, || editor.buffer().content());
+                                    let text = editor.lsp_open_text(&path).unwrap_or_default();
                                     if let Err(e) = mlsp.did_open(&path, &text) {
                                         editor.set_lsp_status(format!("LSP: open error: {}", e));
                                     } else {
                                         lsp_current_file = Some(path);
+                                        editor.refresh_pulled_diagnostics();
                                     }
                                 }
                             }
+                            let _ = mlsp.pull_workspace_diagnostics();
+                            needs_redraw = true;
+                        }
+                        LspNotification::DocumentSymbols {
+                            symbols,
+                            request_uri,
+                        } => {
+                            // Ignore outlines for a buffer the user has left
+                            if editor.current_buffer_uri().as_deref() == Some(request_uri.as_str())
+                            {
+                                editor.open_finder_symbols(symbols);
+                                needs_redraw = true;
+                            }
+                        }
+                        LspNotification::WorkspaceSymbols { symbols, query } => {
+                            if editor.show_workspace_symbols(&query, symbols) {
+                                needs_redraw = true;
+                            }
+                        }
+                        LspNotification::HierarchyPrepared {
+                            direction,
+                            items,
+                            request_uri,
+                        } => {
+                            // Ignore hierarchies for a buffer the user has left
+                            if editor.current_buffer_uri().as_deref() == Some(request_uri.as_str())
+                            {
+                                editor.show_hierarchy(server.clone(), direction, items);
+                                needs_redraw = true;
+                            }
+                        }
+                        LspNotification::HierarchyChildren {
+                            direction,
+                            node,
+                            items,
+                        } => {
+                            if editor.set_hierarchy_children(direction, node, items) {
+                                needs_redraw = true;
+                            }
+                        }
+                        LspNotification::InlayHints {
+                            hints,
+                            request_uri,
+                            request_start_line,
+                            request_end_line,
+                            request_version,
+                        } => {
+                            if editor.set_inlay_hints(
+                                &request_uri,
+                                request_version,
+                                request_start_line as usize,
+                                request_end_line as usize,
+                                hints,
+                            ) {
+                                needs_redraw = true;
+                            }
+                        }
+                        LspNotification::InlayHintRefresh => {
+                            editor.refresh_inlay_hints();
+                            needs_redraw = true;
+                        }
+                        LspNotification::DocumentHighlights {
+                            highlights,
+                            request_uri,
+                            request_line,
+                            request_character,
+                            request_version,
+                        } => {
+                            if editor.set_document_highlights(
+                                &request_uri,
+                                request_version,
+                                request_line,
+                                request_character,
+                                highlights,
+                            ) {
+                                needs_redraw = true;
+                            }
+                        }
+                        LspNotification::SelectionRanges {
+                            ranges,
+                            request_uri,
+                            request_version,
+                        } => {
+                            if editor.set_selection_ranges(&request_uri, request_version, ranges) {
+                                needs_redraw = true;
+                            }
+                        }
+                        LspNotification::SemanticTokens {
+                            tokens,
+                            result_id,
+                            request_uri,
+                            request_previous_result_id,
+                            request_version,
+                        } => {
+                            if let Some(capability) = mlsp.semantic_tokens_capability(&server) {
+                                if editor.set_semantic_tokens(
+                                    &request_uri,
+                                    request_version,
+                                    request_previous_result_id.as_deref(),
+                                    result_id,
+                                    tokens,
+                                    &capability.legend,
+                                ) {
+                                    needs_redraw = true;
+                                }
+                            }
+                        }
+                        LspNotification::SemanticTokensRefresh => {
+                            editor.refresh_semantic_tokens();
+                        }
+                        LspNotification::CodeLenses {
+                            lenses,
+                            request_uri,
+                            request_version,
+                        } => {
+                            let unresolved =
+                                editor.set_code_lenses(&request_uri, request_version, lenses);
+                            if let Some(path) = lsp::uri_to_path(&request_uri) {
+                                for (index, lens) in unresolved {
+                                    let _ =
+                                        mlsp.resolve_code_lens(&path, request_version, index, lens);
+                                }
+                            }
+                            needs_redraw = true;
+                        }
+                        LspNotification::CodeLensResolved {
+                            lens,
+                            index,
+                            request_uri,
+                            request_version,
+                        } => {
+                            if editor.resolve_code_lens(&request_uri, request_version, index, lens)
+                            {
+                                needs_redraw = true;
+                            }
+                        }
+                        LspNotification::CodeLensRefresh => {
+                            editor.refresh_code_lenses();
+                        }
+                        LspNotification::CommandExecuted { title } => {
+                            // Edits made by the command were already applied
+                            // through workspace/applyEdit; keep their status.
+                            let running = format!("Running '{}'...", title);
+                            if editor.status_message.as_deref() == Some(running.as_str()) {
+                                editor.set_status(format!("Ran '{}'", title));
+                            }
                             needs_redraw = true;
                         }
                         LspNotification::Error { message } => {
                             // Update status with error
                             editor.set_lsp_status(format!(
                                 "LSP: {}",
-                                mlsp.user_facing_error(lang, &message)
+                                mlsp.user_facing_error(&server, &message)
                             ));
                             needs_redraw = true;
                         }
@@ -1384,26 +1691,8 @@ fn main() -> anyhow::Result<()> {
                             let t_diag = Instant::now();
                             let diag_count = diagnostics.len();
                             // Store diagnostics for rendering
-                            let errors = diagnostics
-                                .iter()
-                                .filter(|d| {
-                                    matches!(d.severity, lsp::types::DiagnosticSeverity::Error)
-                                })
-                                .count();
-                            let warnings = diagnostics
-                                .iter()
-                                .filter(|d| {
-                                    matches!(d.severity, lsp::types::DiagnosticSeverity::Warning)
-                                })
-                                .count();
-
+                            editor.set_lsp_status(diagnostic_status(&diagnostics));
                             editor.set_diagnostics(uri, diagnostics);
-
-                            if errors > 0 || warnings > 0 {
-                                editor.set_lsp_status(format!("LSP: {}E {}W", errors, warnings));
-                            } else {
-                                editor.set_lsp_status("LSP: ✓");
-                            }
                             profile!(
                                 profile_file,
                                 "diagnostics: {} items in {:?}",
@@ -1412,6 +1701,23 @@ fn main() -> anyhow::Result<()> {
                             );
                             needs_redraw = true;
                         }
+                        LspNotification::PulledDiagnostics { reports } => {
+                            let current_uri = editor.current_buffer_uri();
+                            for report in reports {
+                                let Some(diagnostics) = report.diagnostics else {
+                                    continue;
+                                };
+                                if current_uri.as_deref() == Some(report.uri.as_str()) {
+                                    editor.set_lsp_status(diagnostic_status(&diagnostics));
+                                }
+                                editor.set_diagnostics(report.uri, diagnostics);
+                            }
+                            needs_redraw = true;
+                        }
+                        LspNotification::DiagnosticRefresh => {
+                            editor.refresh_pulled_diagnostics();
+                            let _ = mlsp.pull_workspace_diagnostics();
+                        }
                         LspNotification::Completions {
                             items,
                             is_incomplete,
@@ -1657,16 +1963,21 @@ fn main() -> anyhow::Result<()> {
 
                             // Apply formatting edits to the buffer
                             if !edits.is_empty() {
-                                editor.apply_text_edits(&edits);
-                                editor.set_status(format!(
-                                    "Applied {} formatting edits",
-                                    edits.len()
-                                ));
+                                editor.apply_formatting_edits(&edits);
+                                // On-type edits land while typing; keep the
+                                // status line quiet for them.
+                                if editor.mode != Mode::Insert {
+                                    editor.set_status(format!(
+                                        "Applied {} formatting edits",
+                                        edits.len()
+                                    ));
+                                }
 
                                 // Send didChange to LSP so it knows about the formatted content
                                 if let Some(path) = editor.buffer().path.clone() {
-                                    let text = editor.buffer().content();
-                                    let _ = mlsp.did_change(&path, &text);
+                                    let changes = editor.take_lsp_changes(&path);
+                                    let _ = mlsp
+                                        .did_change(&path, changes, || editor.buffer().content());
                                 }
                             }
                             // Clear the pending format flag
@@ -1810,13 +2121,57 @@ fn main() -> anyhow::Result<()> {
                                     ));
                                     // Send didChange to LSP for current file
                                     if let Some(path) = editor.buffer().path.clone() {
-                                        let text = editor.buffer().content();
-                                        let _ = mlsp.did_change(&path, &text);
+                                        let changes = editor.take_lsp_changes(&path);
+                                        let _ = mlsp.did_change(&path, changes, || {
+                                            editor.buffer().content()
+                                        });
                                     }
                                 }
                             }
                             needs_redraw = true;
                         }
+                        LspNotification::ApplyEdit {
+                            request_id,
+                            label,
+                            edit,
+                        } => {
+                            let label = label.unwrap_or_else(|| "workspace edit".to_string());
+                            let result = editor.apply_workspace_edit(&edit, |path| {
+                                mlsp.document_version(&server, path)
+                            });
+                            let (applied, failure_reason) = match result {
+                                Ok(summary) => {
+                                    for (old_path, new_path) in &summary.renamed_buffers {
+                                        let _ = mlsp.did_close(old_path);
+                                        if let Some(text) = editor.lsp_open_text(new_path) {
+                                            let _ = mlsp.did_open(new_path, &text);
+                                        }
+                                    }
+                                    for path in &summary.changed_buffers {
+                                        let changes = editor.take_lsp_changes(path);
+                                        let _ = mlsp.did_change(path, changes, || {
+                                            editor.open_buffer_content(path).unwrap_or_default()
+                                        });
+                                    }
+                                    editor.set_status(format!(
+                                        "Applied {}: {} edits in {} file(s)",
+                                        label, summary.edits, summary.files
+                                    ));
+                                    (true, None)
+                                }
+                                Err(reason) => {
+                                    editor.set_status(format!("{} failed: {}", label, reason));
+                                    (false, Some(reason))
+                                }
+                            };
+                            let _ = mlsp.apply_edit_response(
+                                &server,
+                                request_id,
+                                applied,
+                                failure_reason,
+                            );
+                            needs_redraw = true;
+                        }
                         LspNotification::CompletionResolved {
                             item_id,
                             label,
@@ -2064,6 +2419,157 @@ fn main() -> anyhow::Result<()> {
             preview_pending_since = None;
         }
 
+        // Request inlay hints for the visible lines once typing pauses
+        if !input_pending {
+            if let Some(mlsp) = multi_lsp.as_mut() {
+                let ready = editor
+                    .buffer()
+                    .path
+                    .as_ref()
+                    .is_some_and(|path| mlsp.is_ready_for_file(path));
+                if ready {
+                    if let Some((path, start_line, end_line, version)) = editor.inlay_hint_request()
+                    {
+                        let _ = mlsp.inlay_hints(&path, start_line, end_line, version);
+                    }
+                }
+            }
+        }
+
+        // Request references to the symbol under the cursor once it rests
+        let cursor_resting = last_input_at.is_none_or(|t| t.elapsed() >= document_highlight_delay);
+        if !input_pending && cursor_resting {
+            if let Some(mlsp) = multi_lsp.as_mut() {
+                let ready = editor
+                    .buffer()
+                    .path
+                    .as_ref()
+                    .is_some_and(|path| mlsp.is_ready_for_file(path));
+                if ready {
+                    if let Some((path, line, character, version)) =
+                        editor.document_highlight_request()
+                    {
+                        let _ = mlsp.document_highlight(&path, line, character, version);
+                    }
+                }
+            }
+        }
+
+        // Request code lenses for the current buffer once typing pauses
+        let typing_paused = last_input_at.is_none_or(|t| t.elapsed() >= diagnostic_pull_delay);
+        if !input_pending && typing_paused {
+            if let Some(mlsp) = multi_lsp.as_mut() {
+                let ready = editor
+                    .buffer()
+                    .path
+                    .as_ref()
+                    .is_some_and(|path| mlsp.is_ready_for_file(path));
+                if ready {
+                    if let Some((path, version)) = editor.code_lens_request() {
+                        let _ = mlsp.code_lens(&path, version);
+                    }
+                }
+            }
+        }
+
+        // Request semantic tokens for the current buffer once typing pauses
+        if !input_pending && typing_paused {
+            if let Some(mlsp) = multi_lsp.as_mut() {
+                let supported = editor
+                    .buffer()
+                    .path
+                    .as_ref()
+                    .is_some_and(|path| mlsp.has_semantic_tokens(path));
+                if supported {
+                    if let Some((path, previous_result_id, version)) =
+                        editor.semantic_tokens_request()
+                    {
+                        let _ = mlsp.semantic_tokens(&path, previous_result_id, version);
+                    }
+                }
+            }
+        }
+
+        // Pull the current buffer's diagnostics once typing pauses
+        if !input_pending && typing_paused {
+            if let Some(mlsp) = multi_lsp.as_mut() {
+                let supported = editor
+                    .buffer()
+                    .path
+                    .as_ref()
+                    .is_some_and(|path| mlsp.supports(path, "diagnosticProvider"));
+                if supported {
+                    if let Some(path) = editor.diagnostic_pull_request() {
+                        let _ = mlsp.pull_diagnostics(&path);
+                    }
+                }
+            }
+        }
+
+        // Push edited LSP server settings to running servers
+        if !input_pending && last_config_check.elapsed() >= config_check_interval {
+            last_config_check = Instant::now();
+            let mtime = config_file_mtime();
+            if mtime != config_mtime {
+                config_mtime = mtime;
+                if let Some(mlsp) = multi_lsp.as_mut() {
+                    match nevi::config::load_lsp_servers() {
+                        Ok((servers, problems)) => {
+                            let updated = mlsp.update_servers(servers);
+                            if let Some(problem) = problems.first() {
+                                editor.set_status(format!("Ignoring LSP server: {}", problem));
+                                needs_redraw = true;
+                            } else if !updated.is_empty() {
+                                editor.set_status(format!(
+                                    "LSP settings reloaded: {}",
+                                    updated.join(", ")
+                                ));
+                                needs_redraw = true;
+                            }
+                        }
+                        Err(e) => {
+                            editor.set_status(format!("LSP settings not reloaded: {}", e));
+                            needs_redraw = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        // Expand hierarchy nodes opened since the last frame
+        let hierarchy_requests = editor.take_hierarchy_requests();
+        if let Some(mlsp) = multi_lsp.as_mut() {
+            for request in hierarchy_requests {
+                if let Err(e) = mlsp.hierarchy_children(
+                    &request.server,
+                    request.direction,
+                    request.node,
+                    request.item,
+                ) {
+                    editor.set_status(format!("LSP: {}", e));
+                    needs_redraw = true;
+                }
+            }
+        }
+
+        // Re-query workspace symbols once typing pauses
+        if editor.finder.workspace_symbol_query_pending && !input_pending {
+            editor.finder.workspace_symbol_query_pending = false;
+            let query = editor.finder.query.clone();
+            match (multi_lsp.as_mut(), editor.buffer().path.clone()) {
+                (Some(mlsp), Some(path)) => {
+                    if let Err(e) = mlsp.workspace_symbols(&path, query) {
+                        editor.set_status(format!("LSP: {}", e));
+                        needs_redraw = true;
+                    }
+                }
+                _ => {
+                    editor.set_status("LSP not available");
+                    needs_redraw = true;
+                }
+            }
+        }
+
         // Check for pending grep searches (debounced)
         // This avoids running expensive grep on every keystroke
         if editor.finder.grep_search_pending {
@@ -2300,58 +2806,6 @@ fn calculate_word_start(editor: &Editor, line_idx: usize, col: usize) -> usize {
     col
 }
 
-/// Apply LSP text edits to a file on disk
-/// Reads the file, applies edits in reverse order, and writes back
-fn apply_edits_to_file(
-    path: &std::path::Path,
-    edits: &[lsp::types::TextEdit],
-) -> anyhow::Result<usize> {
-    use std::fs;
-
-    let content = fs::read_to_string(path)?;
-    let mut text = ropey::Rope::from_str(&content);
-
-    // Sort edits by position (reverse order) so we can apply from end to start
-    let mut sorted_edits: Vec<&lsp::types::TextEdit> = edits.iter().collect();
-    sorted_edits.sort_by(|a, b| match b.end_line.cmp(&a.end_line) {
-        std::cmp::Ordering::Equal => b.end_col.cmp(&a.end_col),
-        other => other,
-    });
-
-    // Apply each edit
-    for edit in &sorted_edits {
-        let start = utf16_position_to_rope_char(&text, edit.start_line, edit.start_col);
-        let end = utf16_position_to_rope_char(&text, edit.end_line, edit.end_col);
-
-        if start <= end && end <= text.len_chars() {
-            if start < end {
-                text.remove(start..end);
-            }
-            if !edit.new_text.is_empty() {
-                text.insert(start, &edit.new_text);
-            }
-        }
-    }
-
-    fs::write(path, text.to_string())?;
-
-    Ok(sorted_edits.len())
-}
-
-fn utf16_position_to_rope_char(text: &ropey::Rope, line: usize, utf16_col: usize) -> usize {
-    if line >= text.len_lines() {
-        return text.len_chars();
-    }
-
-    let line_start = text.line_to_char(line);
-    let line_text = text.line(line).to_string();
-    let line_text = line_text.trim_end_matches('\n');
-    let line_len = line_text.chars().count();
-    let col = utf16_to_utf8_col(line_text, utf16_col as u32).min(line_len);
-
-    line_start + col
-}
-
 /// Check if we should auto-trigger signature help based on the character just typed
 fn should_trigger_signature_help(editor: &Editor) -> bool {
     let col = editor.cursor.col;
@@ -2376,6 +2830,27 @@ fn should_trigger_signature_help(editor: &Editor) -> bool {
     false
 }
 
+/// The character `key` typed into the buffer in Insert mode, as sent with
+/// `textDocument/onTypeFormatting`: the character now before the cursor, or
+/// a newline when Enter opened the cursor's line
+fn typed_format_trigger(editor: &Editor, key: &KeyEvent) -> Option<char> {
+    let line: Vec<char> = editor.buffer().line(editor.cursor.line)?.chars().collect();
+    let before_cursor = line.get(..editor.cursor.col)?;
+    match key.code {
+        KeyCode::Char(c)
+            if !key
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+        {
+            (before_cursor.last() == Some(&c)).then_some(c)
+        }
+        KeyCode::Enter => (editor.cursor.line > 0
+            && before_cursor.iter().all(|ch| ch.is_whitespace()))
+        .then_some('\n'),
+        _ => None,
+    }
+}
+
 /// Check if signature help should be dismissed (cursor moved out of function call)
 fn should_dismiss_signature_help(editor: &Editor) -> bool {
     let col = editor.cursor.col;
@@ -2575,6 +3050,12 @@ fn apply_copilot_completion(editor: &mut Editor, completion: &CopilotCompletion)
 }
 
 /// Find workspace root by walking up the tree and checking root markers.
+/// Modification time of the config file, when it exists
+fn config_file_mtime() -> Option<std::time::SystemTime> {
+    let path = nevi::config::config_path()?;
+    std::fs::metadata(path).ok()?.modified().ok()
+}
+
 fn find_workspace_root(file_path: &Path, root_markers: &[String]) -> PathBuf {
     let mut current = if file_path.is_dir() {
         Some(file_path.to_path_buf())
@@ -2608,14 +3089,13 @@ fn find_workspace_root(file_path: &Path, root_markers: &[String]) -> PathBuf {
 #[cfg(test)]
 mod tests {
     use super::{
-        CliStartupAction, PickModeAction, TerminalOutputTarget, apply_edits_to_file,
-        diagnostic_to_lsp_offsets, editor_lsp_cursor_col, editor_lsp_line_len,
-        lsp_completion_response_matches_current_cursor, lsp_response_matches_current_buffer,
-        pick_mode_action_for_key, profile_enabled_from_value, startup_action_from_args,
-        terminal_output_target_for_pick_mode,
+        CliStartupAction, PickModeAction, TerminalOutputTarget, diagnostic_to_lsp_offsets,
+        editor_lsp_cursor_col, editor_lsp_line_len, lsp_completion_response_matches_current_cursor,
+        lsp_response_matches_current_buffer, pick_mode_action_for_key, profile_enabled_from_value,
+        startup_action_from_args, terminal_output_target_for_pick_mode,
     };
     use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
-    use nevi::lsp::types::{Diagnostic, DiagnosticSeverity, TextEdit};
+    use nevi::lsp::types::{Diagnostic, DiagnosticSeverity};
     use nevi::{Editor, Mode};
     use std::path::PathBuf;
     use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
@@ -2628,31 +3108,6 @@ mod tests {
         std::env::temp_dir().join(format!("{}_{}_{}", prefix, std::process::id(), nanos))
     }
 
-    #[test]
-    fn apply_edits_to_file_treats_columns_as_utf16_offsets() {
-        let tmp = unique_temp_dir("nevi_disk_lsp_edit");
-        std::fs::create_dir_all(&tmp).expect("create temp dir");
-        let path = tmp.join("unicode.txt");
-        std::fs::write(&path, "a😀b\n").expect("write file");
-
-        let count = apply_edits_to_file(
-            &path,
-            &[TextEdit {
-                start_line: 0,
-                start_col: 3,
-                end_line: 0,
-                end_col: 4,
-                new_text: "X".to_string(),
-            }],
-        )
-        .expect("apply edits");
-
-        assert_eq!(count, 1);
-        assert_eq!(std::fs::read_to_string(&path).expect("read file"), "a😀X\n");
-
-        let _ = std::fs::remove_dir_all(&tmp);
-    }
-
     #[test]
     fn profiling_is_disabled_unless_env_value_opts_in() {
         assert!(!profile_enabled_from_value(None));
@@ -2942,6 +3397,9 @@ mod tests {
                 message: "problem".to_string(),
                 source: None,
                 code: None,
+                tags: Vec::new(),
+                related: Vec::new(),
+                href: None,
             },
         );
 
@@ -1,8 +1,8 @@
 use crossterm::style::Color;
 use streaming_iterator::StreamingIterator;
-use tree_sitter::{Query, QueryCursor, Tree};
+use tree_sitter::{Query, QueryCursor, Range, Tree};
 
-use super::theme::{SyntaxStyle, Theme};
+use super::theme::{HighlightGroup, SyntaxStyle, Theme};
 
 /// Maximum query byte range to prevent freezing on minified files
 /// (e.g., minified JavaScript with 100KB+ single lines)
@@ -21,20 +21,49 @@ pub struct HighlightSpan {
     pub style: SyntaxStyle,
 }
 
+/// A semantic token from the language server, on one line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SemanticToken {
+    /// Start column (character index, 0-based)
+    pub start_col: usize,
+    /// End column (exclusive)
+    pub end_col: usize,
+    pub group: HighlightGroup,
+    /// Modifier groups (`Mutable`, `Deprecated`, `Async`)
+    pub modifiers: Vec<HighlightGroup>,
+}
+
 /// Internal span with priority for sorting
 #[derive(Debug, Clone, Copy)]
 struct PrioritySpan {
     start_col: usize,
     end_col: usize,
     style: SyntaxStyle,
-    /// Capture index - higher = later in query = higher priority
+    /// Capture index - higher = later in query = higher priority.
+    /// Injected layers add `depth * LAYER_PRIORITY_STRIDE`.
     priority: u32,
 }
 
-/// Get highlights for a specific line from the parsed tree
+/// Priority gap between injection depths, larger than any query's capture count
+const LAYER_PRIORITY_STRIDE: u32 = 1 << 16;
+
+/// One parsed tree to highlight a line from
+pub struct HighlightLayer<'a> {
+    pub tree: &'a Tree,
+    pub query: &'a Query,
+    /// Byte ranges the layer covers; empty for the host document
+    pub ranges: &'a [Range],
+    /// Injection depth (0 for the host); deeper layers win overlaps
+    pub depth: u32,
+    /// Keep the parent's highlights under this layer instead of replacing them
+    pub overlay: bool,
+}
+
+/// Get highlights for a line from the host tree and any injected layers.
+/// Spans from deeper layers win, and a non-overlay layer clears its parents'
+/// spans over the ranges it covers.
 pub fn get_line_highlights(
-    tree: &Tree,
-    query: &Query,
+    layers: &[HighlightLayer],
     source: &str,
     line_start_bytes: &[usize],
     line: usize,
@@ -71,52 +100,81 @@ pub fn get_line_highlights(
     // Build a byte-to-char mapping for this line
     // This converts tree-sitter byte offsets to character indices for rendering
     let byte_to_char = build_byte_to_char_map(line_content);
+    let to_col = |byte: usize| byte_offset_to_char_index(&byte_to_char, byte - line_start_byte);
 
-    let root = tree.root_node();
-
-    // Query only the nodes that intersect with this line
-    cursor.set_byte_range(line_start_byte..line_end_byte);
+    // Column ranges where a layer replaces the highlights of shallower ones
+    let mut masks: Vec<(usize, usize, u32)> = Vec::new();
 
-    let mut matches = cursor.matches(query, root, source.as_bytes());
+    for layer in layers {
+        // Byte ranges of this layer on the line; the host covers all of it
+        let line_ranges: Vec<(usize, usize)> = if layer.ranges.is_empty() {
+            vec![(line_start_byte, line_end_byte)]
+        } else {
+            layer
+                .ranges
+                .iter()
+                .filter(|range| {
+                    range.start_byte < line_end_byte && range.end_byte > line_start_byte
+                })
+                .map(|range| {
+                    (
+                        range.start_byte.max(line_start_byte),
+                        range.end_byte.min(line_end_byte),
+                    )
+                })
+                .collect()
+        };
+        if line_ranges.is_empty() {
+            continue;
+        }
+        if !layer.overlay && layer.depth > 0 {
+            for &(start, end) in &line_ranges {
+                masks.push((to_col(start), to_col(end), layer.depth));
+            }
+        }
 
-    while let Some(m) = matches.next() {
-        for capture in m.captures {
-            let node = capture.node;
-            let capture_name = query.capture_names()[capture.index as usize];
+        // Query only the nodes that intersect with this line
+        cursor.set_byte_range(line_start_byte..line_end_byte);
+        let query = layer.query;
+        let mut matches = cursor.matches(query, layer.tree.root_node(), source.as_bytes());
 
-            // Get the style for this capture
-            if let Some(style) = theme.get_style_for_capture(capture_name) {
-                let node_start = node.start_byte();
-                let node_end = node.end_byte();
+        while let Some(m) = matches.next() {
+            for capture in m.captures {
+                let node = capture.node;
+                let capture_name = query.capture_names()[capture.index as usize];
 
-                // Skip if node doesn't intersect with this line
-                if node_end <= line_start_byte || node_start >= line_end_byte {
+                // Get the style for this capture
+                let Some(style) = theme.get_style_for_capture(capture_name) else {
                     continue;
-                }
-
-                // Clamp to line boundaries (in bytes)
-                let start_byte = node_start.max(line_start_byte);
-                let end_byte = node_end.min(line_end_byte);
-
-                // Convert byte offsets (relative to line start) to char indices
-                let start_byte_rel = start_byte - line_start_byte;
-                let end_byte_rel = end_byte - line_start_byte;
+                };
 
-                let start_col = byte_offset_to_char_index(&byte_to_char, start_byte_rel);
-                let end_col = byte_offset_to_char_index(&byte_to_char, end_byte_rel);
+                // Clamp to the parts of the line this layer covers (in bytes)
+                for &(range_start, range_end) in &line_ranges {
+                    let start_byte = node.start_byte().max(range_start);
+                    let end_byte = node.end_byte().min(range_end);
+                    if start_byte >= end_byte {
+                        continue;
+                    }
 
-                if start_col < end_col {
-                    spans.push(PrioritySpan {
-                        start_col,
-                        end_col,
-                        style,
-                        priority: capture.index,
-                    });
+                    let start_col = to_col(start_byte);
+                    let end_col = to_col(end_byte);
+                    if start_col < end_col {
+                        spans.push(PrioritySpan {
+                            start_col,
+                            end_col,
+                            style,
+                            priority: layer.depth * LAYER_PRIORITY_STRIDE + capture.index,
+                        });
+                    }
                 }
             }
         }
     }
 
+    if !masks.is_empty() {
+        spans = mask_spans(spans, &masks);
+    }
+
     // Sort spans by start column, then by priority (higher priority = later in query = wins)
     spans.sort_by(|a, b| {
         a.start_col
@@ -128,6 +186,92 @@ pub fn get_line_highlights(
     resolve_overlapping_spans_with_priority(spans)
 }
 
+/// Cut spans out of the columns a deeper, non-overlay layer covers.
+fn mask_spans(spans: Vec<PrioritySpan>, masks: &[(usize, usize, u32)]) -> Vec<PrioritySpan> {
+    let mut result = Vec::with_capacity(spans.len());
+    for span in spans {
+        let depth = span.priority / LAYER_PRIORITY_STRIDE;
+        let mut pieces = vec![span];
+        for &(mask_start, mask_end, mask_depth) in masks {
+            if mask_depth <= depth {
+                continue;
+            }
+            pieces = pieces
+                .into_iter()
+                .flat_map(|piece| {
+                    let before = PrioritySpan {
+                        end_col: piece.end_col.min(mask_start),
+                        ..piece
+                    };
+                    let after = PrioritySpan {
+                        start_col: piece.start_col.max(mask_end),
+                        ..piece
+                    };
+                    [before, after]
+                        .into_iter()
+                        .filter(|part| part.start_col < part.end_col)
+                })
+                .collect();
+        }
+        result.extend(pieces);
+    }
+    result
+}
+
+/// Lay semantic tokens over a line's tree-sitter spans: tokens replace the
+/// spans under them, and tree-sitter keeps the columns tokens don't cover.
+pub fn overlay_semantic_tokens(
+    spans: Vec<HighlightSpan>,
+    tokens: &[SemanticToken],
+    theme: &Theme,
+) -> Vec<HighlightSpan> {
+    let overlay: Vec<HighlightSpan> = tokens
+        .iter()
+        .filter(|token| token.start_col < token.end_col)
+        .filter_map(|token| {
+            let style = theme.get_semantic_style(token.group, &token.modifiers)?;
+            Some(HighlightSpan {
+                start_col: token.start_col,
+                end_col: token.end_col,
+                fg: style.fg,
+                style,
+            })
+        })
+        .collect();
+    if overlay.is_empty() {
+        return spans;
+    }
+
+    let mut result: Vec<HighlightSpan> = spans
+        .into_iter()
+        .flat_map(|span| {
+            let mut pieces = vec![span];
+            for token in &overlay {
+                pieces = pieces
+                    .into_iter()
+                    .flat_map(|piece| {
+                        let before = HighlightSpan {
+                            end_col: piece.end_col.min(token.start_col),
+                            ..piece
+                        };
+                        let after = HighlightSpan {
+                            start_col: piece.start_col.max(token.end_col),
+                            ..piece
+                        };
+                        [before, after]
+                            .into_iter()
+                            .filter(|part| part.start_col < part.end_col)
+                    })
+                    .collect();
+            }
+            pieces
+        })
+        .collect();
+    result.extend(overlay);
+    result.sort_by_key(|span| span.start_col);
+    result
+}
+
 /// Get highlights for a YAML line using lightweight tokenization.
 /// This is used when tree-sitter YAML grammar is not available.
 pub fn get_line_highlights_yaml(
@@ -1372,11 +1516,152 @@ pub fn shell_highlight_query() -> &'static str {
     tree_sitter_bash::HIGHLIGHT_QUERY
 }
 
+/// Get the highlight query for Markdown inline content (emphasis, code spans,
+/// links), injected into the block grammar's `inline` nodes
+pub fn markdown_inline_highlight_query() -> &'static str {
+    r##"
+; Code spans
+(code_span) @string
+(code_span_delimiter) @punctuation
+
+; Emphasis markers
+(emphasis_delimiter) @punctuation
+
+; Links and images
+(link_text) @label
+(link_label) @label
+(image_description) @label
+(link_destination) @string
+(link_title) @string
+(uri_autolink) @string
+(email_autolink) @string
+(image ["!" "[" "]" "(" ")"] @punctuation)
+(inline_link ["[" "]" "(" ")"] @punctuation)
+(shortcut_link ["[" "]"] @punctuation)
+
+; Escapes and references
+(backslash_escape) @string
+(entity_reference) @constant
+(numeric_character_reference) @constant
+"##
+}
+
+/// Get the injection query for a language, if it embeds others.
+///
+/// Besides the usual `injection.language`, `injection.content` and
+/// `injection.combined`, `injection.overlay` keeps the host's highlights under
+/// the injected layer instead of replacing them.
+pub fn injection_query(language: &str) -> Option<&'static str> {
+    match language {
+        "markdown" => Some(markdown_injection_query()),
+        "markdown_inline" => Some(markdown_inline_injection_query()),
+        "html" => Some(html_injection_query()),
+        "javascript" | "typescript" | "tsx" => Some(javascript_injection_query()),
+        _ => None,
+    }
+}
+
+/// Get the injection query for Markdown
+pub fn markdown_injection_query() -> &'static str {
+    r##"
+; Fenced code blocks with a language (```rust)
+(fenced_code_block
+  (info_string (language) @injection.language)
+  (code_fence_content) @injection.content)
+
+; Raw HTML blocks
+((html_block) @injection.content
+  (#set! injection.language "html"))
+
+; Inline content (emphasis, code spans, links) layers over block highlights
+((inline) @injection.content
+  (#set! injection.language "markdown_inline")
+  (#set! injection.combined)
+  (#set! injection.overlay))
+"##
+}
+
+/// Get the injection query for Markdown inline content
+pub fn markdown_inline_injection_query() -> &'static str {
+    r##"
+((html_tag) @injection.content
+  (#set! injection.language "html")
+  (#set! injection.combined))
+"##
+}
+
+/// Get the injection query for HTML
+pub fn html_injection_query() -> &'static str {
+    r##"
+(script_element
+  (raw_text) @injection.content
+  (#set! injection.language "javascript"))
+
+(style_element
+  (raw_text) @injection.content
+  (#set! injection.language "css"))
+"##
+}
+
+/// Get the injection query for JavaScript and TypeScript tagged templates
+pub fn javascript_injection_query() -> &'static str {
+    r##"
+; css`...`, keyframes`...`, createGlobalStyle`...`
+(call_expression
+  function: (identifier) @_tag
+  arguments: (template_string (string_fragment) @injection.content)
+  (#any-of? @_tag "css" "keyframes" "injectGlobal" "createGlobalStyle")
+  (#set! injection.language "css")
+  (#set! injection.combined))
+
+; styled.div`...`
+(call_expression
+  function: (member_expression object: (identifier) @_tag)
+  arguments: (template_string (string_fragment) @injection.content)
+  (#eq? @_tag "styled")
+  (#set! injection.language "css")
+  (#set! injection.combined))
+
+; styled(Component)`...`
+(call_expression
+  function: (call_expression function: (identifier) @_tag)
+  arguments: (template_string (string_fragment) @injection.content)
+  (#eq? @_tag "styled")
+  (#set! injection.language "css")
+  (#set! injection.combined))
+
+; html`...`, sql`...`, graphql`...`
+(call_expression
+  function: (identifier) @injection.language
+  arguments: (template_string (string_fragment) @injection.content)
+  (#any-of? @injection.language "html" "sql" "graphql" "gql")
+  (#set! injection.combined))
+"##
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use tree_sitter::Parser;
 
+    fn host_line_highlights(
+        tree: &Tree,
+        query: &Query,
+        source: &str,
+        line_start_bytes: &[usize],
+        line: usize,
+        theme: &Theme,
+    ) -> Vec<HighlightSpan> {
+        let host = HighlightLayer {
+            tree,
+            query,
+            ranges: &[],
+            depth: 0,
+            overlay: false,
+        };
+        get_line_highlights(&[host], source, line_start_bytes, line, theme)
+    }
+
     fn assert_query_compiles(language: tree_sitter::Language, query_source: &str, name: &str) {
         let query = Query::new(&language, query_source);
         assert!(
@@ -1649,7 +1934,7 @@ mod tests {
         let source = "self.name.upper()\n";
         let tree = parser.parse(source, None).expect("parse python source");
         let theme = Theme::default();
-        let spans = get_line_highlights(&tree, &query, source, &[0, source.len()], 0, &theme);
+        let spans = host_line_highlights(&tree, &query, source, &[0, source.len()], 0, &theme);
         let upper_start = source.find("upper").expect("upper call");
         let function = theme
             .get_color_for_capture("function")
@@ -1790,7 +2075,7 @@ mod tests {
         let source = "// comment\n";
         let tree = parser.parse(source, None).expect("parse rust source");
         let theme = Theme::default();
-        let spans = get_line_highlights(&tree, &query, source, &[0, source.len()], 0, &theme);
+        let spans = host_line_highlights(&tree, &query, source, &[0, source.len()], 0, &theme);
 
         assert!(
             spans.iter().any(|span| span.style.italic),
@@ -0,0 +1,537 @@
+//! Language injections: nested parsers for code embedded in another
+//! language, such as fenced blocks in Markdown or `<script>` in HTML.
+//!
+//! Each injected language is parsed over just its ranges of the host source,
+//! so layer trees use the same byte offsets as the host and can be queried
+//! line by line alongside it.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use streaming_iterator::StreamingIterator;
+use tree_sitter::{Language, Parser, Point, Query, QueryCursor, QueryMatch, Range, Tree};
+
+use super::highlighter::{self, HighlightLayer};
+use super::runtime;
+
+/// Deepest chain of injections followed (e.g. Markdown → HTML → JavaScript)
+const MAX_INJECTION_DEPTH: u32 = 3;
+/// Cap on layers per document, so a file with thousands of fences stays cheap
+const MAX_INJECTION_LAYERS: usize = 256;
+
+/// A grammar that can be injected, with its compiled queries.
+pub struct LayerLanguage {
+    pub name: String,
+    pub language: Language,
+    pub highlights: Query,
+    pub injections: Option<Query>,
+    pub indents: Option<Query>,
+    /// Shared library a runtime grammar was loaded from; `None` when built in
+    pub library: Option<PathBuf>,
+}
+
+/// A parsed injection over part of the host document.
+pub struct InjectionLayer {
+    pub language: Arc<LayerLanguage>,
+    pub tree: Tree,
+    /// Host byte ranges the layer was parsed over, sorted and disjoint
+    pub ranges: Vec<Range>,
+    pub depth: u32,
+    /// Parsed from every match for its language as one document
+    pub combined: bool,
+    pub overlay: bool,
+}
+
+impl InjectionLayer {
+    pub fn highlight_layer(&self) -> HighlightLayer<'_> {
+        HighlightLayer {
+            tree: &self.tree,
+            query: &self.language.highlights,
+            ranges: &self.ranges,
+            depth: self.depth,
+            overlay: self.overlay,
+        }
+    }
+}
+
+/// Map an injection or fence name such as `rs` or `javascript` to a built-in
+/// grammar and its highlight query.
+fn builtin_language(name: &str) -> Option<(&'static str, Language, &'static str)> {
+    let entry: (&'static str, Language, &'static str) = match name {
+        "rust" | "rs" => (
+            "rust",
+            tree_sitter_rust::LANGUAGE.into(),
+            highlighter::rust_highlight_query(),
+        ),
+        "javascript" | "js" | "jsx" | "mjs" | "cjs" | "node" => (
+            "javascript",
+            tree_sitter_javascript::LANGUAGE.into(),
+            highlighter::javascript_highlight_query(),
+        ),
+        "typescript" | "ts" => (
+            "typescript",
+            tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            highlighter::typescript_highlight_query(),
+        ),
+        "tsx" => (
+            "tsx",
+            tree_sitter_typescript::LANGUAGE_TSX.into(),
+            highlighter::tsx_highlight_query(),
+        ),
+        "css" => (
+            "css",
+            tree_sitter_css::LANGUAGE.into(),
+            highlighter::css_highlight_query(),
+        ),
+        "scss" | "sass" => (
+            "scss",
+            tree_sitter_css::LANGUAGE.into(),
+            highlighter::scss_highlight_query(),
+        ),
+        "json" | "jsonc" => (
+            "json",
+            tree_sitter_json::LANGUAGE.into(),
+            highlighter::json_highlight_query(),
+        ),
+        "markdown" | "md" => (
+            "markdown",
+            tree_sitter_md::LANGUAGE.into(),
+            highlighter::markdown_highlight_query(),
+        ),
+        "markdown_inline" => (
+            "markdown_inline",
+            tree_sitter_md::INLINE_LANGUAGE.into(),
+            highlighter::markdown_inline_highlight_query(),
+        ),
+        "toml" => (
+            "toml",
+            tree_sitter_toml_ng::LANGUAGE.into(),
+            highlighter::toml_highlight_query(),
+        ),
+        "html" | "htm" | "xhtml" => (
+            "html",
+            tree_sitter_html::LANGUAGE.into(),
+            highlighter::html_highlight_query(),
+        ),
+        "python" | "py" | "python3" => (
+            "python",
+            tree_sitter_python::LANGUAGE.into(),
+            highlighter::python_highlight_query(),
+        ),
+        "php" => (
+            "php",
+            tree_sitter_php::LANGUAGE_PHP.into(),
+            highlighter::php_highlight_query(),
+        ),
+        "go" | "golang" => (
+            "go",
+            tree_sitter_go::LANGUAGE.into(),
+            highlighter::go_highlight_query(),
+        ),
+        "ruby" | "rb" => (
+            "ruby",
+            tree_sitter_ruby::LANGUAGE.into(),
+            highlighter::ruby_highlight_query(),
+        ),
+        "shell" | "bash" | "sh" | "zsh" => (
+            "shell",
+            tree_sitter_bash::LANGUAGE.into(),
+            highlighter::shell_highlight_query(),
+        ),
+        _ => return None,
+    };
+    Some(entry)
+}
+
+/// Look up an injectable language by name, compiling its queries on first use.
+/// Names without a built-in grammar fall back to runtime grammars. Results are
+/// shared by every buffer.
+pub fn layer_language(name: &str) -> Option<Arc<LayerLanguage>> {
+    static LANGUAGES: OnceLock<Mutex<HashMap<String, Option<Arc<LayerLanguage>>>>> =
+        OnceLock::new();
+
+    let name = name.to_ascii_lowercase();
+    let mut languages = LANGUAGES
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(cached) = languages.get(&name) {
+        return cached
+            .clone()
+            .or_else(|| runtime::load_runtime_grammar(&name).ok());
+    }
+
+    let loaded = builtin_language(&name).and_then(|(canonical, language, highlights)| {
+        let highlights = Query::new(&language, highlights).ok()?;
+        let injections = highlighter::injection_query(canonical)
+            .and_then(|source| Query::new(&language, source).ok());
+        Some(Arc::new(LayerLanguage {
+            name: canonical.to_string(),
+            language,
+            highlights,
+            injections,
+            indents: None,
+            library: None,
+        }))
+    });
+    languages.insert(name.clone(), loaded.clone());
+    loaded.or_else(|| runtime::load_runtime_grammar(&name).ok())
+}
+
+/// Parsed-layer identity used to tell whether an edit changed which layers
+/// exist: language, depth and range count.
+pub fn layer_shape(layers: &[InjectionLayer]) -> Vec<(&str, u32, usize)> {
+    layers
+        .iter()
+        .map(|layer| {
+            (
+                layer.language.name.as_str(),
+                layer.depth,
+                layer.ranges.len(),
+            )
+        })
+        .collect()
+}
+
+/// Parse every injection the host tree's injection query finds, following
+/// nested injections down to `MAX_INJECTION_DEPTH`.
+pub fn collect_injections(
+    source: &str,
+    line_start_bytes: &[usize],
+    tree: &Tree,
+    injections: &Query,
+) -> Vec<InjectionLayer> {
+    let mut layers = Vec::new();
+    collect_layer_injections(
+        source,
+        line_start_bytes,
+        tree,
+        injections,
+        None,
+        1,
+        &mut layers,
+    );
+    layers
+}
+
+/// One match of an injection query.
+struct Injection {
+    language: String,
+    ranges: Vec<Range>,
+    combined: bool,
+    overlay: bool,
+}
+
+fn collect_layer_injections(
+    source: &str,
+    line_start_bytes: &[usize],
+    tree: &Tree,
+    query: &Query,
+    parent_ranges: Option<&[Range]>,
+    depth: u32,
+    layers: &mut Vec<InjectionLayer>,
+) {
+    if depth > MAX_INJECTION_DEPTH {
+        return;
+    }
+
+    // Separate injections get a layer each; combined ones share one layer per
+    // language, parsed as a single document.
+    let mut pending: Vec<(Arc<LayerLanguage>, Injection)> = Vec::new();
+    let mut combined: Vec<(Arc<LayerLanguage>, Injection)> = Vec::new();
+
+    let mut cursor = QueryCursor::new();
+    let mut matches = cursor.matches(query, tree.root_node(), source.as_bytes());
+    while let Some(m) = matches.next() {
+        let Some(mut injection) = injection_for_match(query, m, source) else {
+            continue;
+        };
+        let Some(language) = layer_language(&injection.language) else {
+            continue;
+        };
+        if let Some(parent) = parent_ranges {
+            injection.ranges = injection
+                .ranges
+                .iter()
+                .flat_map(|range| clip_range(range, parent, line_start_bytes))
+                .collect();
+        }
+        if injection.ranges.is_empty() {
+            continue;
+        }
+
+        if injection.combined {
+            match combined
+                .iter_mut()
+                .find(|(existing, _)| existing.name == language.name)
+            {
+                Some((_, existing)) => existing.ranges.extend(injection.ranges),
+                None => combined.push((language, injection)),
+            }
+        } else {
+            pending.push((language, injection));
+        }
+    }
+    pending.extend(combined);
+
+    for (language, mut injection) in pending {
+        if layers.len() >= MAX_INJECTION_LAYERS {
+            return;
+        }
+        normalize_ranges(&mut injection.ranges);
+        let Some(tree) = parse_layer(&language.language, source, &injection.ranges) else {
+            continue;
+        };
+
+        let layer_idx = layers.len();
+        layers.push(InjectionLayer {
+            language: language.clone(),
+            tree,
+            ranges: injection.ranges,
+            depth,
+            combined: injection.combined,
+            overlay: injection.overlay,
+        });
+
+        if let Some(nested) = &language.injections {
+            let layer = &layers[layer_idx];
+            let (tree, ranges) = (layer.tree.clone(), layer.ranges.clone());
+            collect_layer_injections(
+                source,
+                line_start_bytes,
+                &tree,
+                nested,
+                Some(&ranges),
+                depth + 1,
+                layers,
+            );
+        }
+    }
+}
+
+fn injection_for_match(query: &Query, m: &QueryMatch, source: &str) -> Option<Injection> {
+    let content_idx = query.capture_index_for_name("injection.content")?;
+    let language_idx = query.capture_index_for_name("injection.language");
+
+    let mut language = None;
+    let mut combined = false;
+    let mut overlay = false;
+    for property in query.property_settings(m.pattern_index) {
+        match property.key.as_ref() {
+            "injection.language" => language = property.value.as_deref().map(str::to_string),
+            "injection.combined" => combined = true,
+            "injection.overlay" => overlay = true,
+            _ => {}
+        }
+    }
+
+    let mut ranges = Vec::new();
+    for capture in m.captures {
+        if Some(capture.index) == language_idx {
+            let text = capture.node.utf8_text(source.as_bytes()).ok()?;
+            language = Some(fence_language_name(text).to_string());
+        } else if capture.index == content_idx {
+            ranges.push(capture.node.range());
+        }
+    }
+
+    let language = language.filter(|name| !name.is_empty())?;
+    Some(Injection {
+        language,
+        ranges,
+        combined,
+        overlay,
+    })
+}
+
+/// The language word of a fence info string: `rust,ignore` and `{.python}`
+/// both name their language first.
+fn fence_language_name(info: &str) -> &str {
+    let info = info.trim().trim_start_matches(['{', '.']);
+    let end = info
+        .find(|ch: char| !(ch.is_alphanumeric() || matches!(ch, '_' | '-' | '+' | '#')))
+        .unwrap_or(info.len());
+    &info[..end]
+}
+
+/// Sort ranges and drop overlaps, as `Parser::set_included_ranges` requires.
+fn normalize_ranges(ranges: &mut Vec<Range>) {
+    ranges.sort_by_key(|range| (range.start_byte, range.end_byte));
+    let mut last_end = 0;
+    ranges.retain(|range| {
+        if range.start_byte < last_end || range.start_byte >= range.end_byte {
+            return false;
+        }
+        last_end = range.end_byte;
+        true
+    });
+}
+
+/// Intersect `range` with a parent layer's ranges, so nested injections stay
+/// inside the text their parent actually parsed.
+fn clip_range(range: &Range, parent: &[Range], line_start_bytes: &[usize]) -> Vec<Range> {
+    parent
+        .iter()
+        .filter_map(|outer| {
+            let start = range.start_byte.max(outer.start_byte);
+            let end = range.end_byte.min(outer.end_byte);
+            (start < end).then(|| Range {
+                start_byte: start,
+                end_byte: end,
+                start_point: byte_to_point(line_start_bytes, start),
+                end_point: byte_to_point(line_start_bytes, end),
+            })
+        })
+        .collect()
+}
+
+fn byte_to_point(line_start_bytes: &[usize], byte: usize) -> Point {
+    let row = line_start_bytes
+        .partition_point(|&start| start <= byte)
+        .saturating_sub(1);
+    let column = byte - line_start_bytes.get(row).copied().unwrap_or(0);
+    Point { row, column }
+}
+
+fn parse_layer(language: &Language, source: &str, ranges: &[Range]) -> Option<Tree> {
+    let mut parser = Parser::new();
+    parser.set_language(language).ok()?;
+    parser.set_included_ranges(ranges).ok()?;
+    parser.parse(source, None)
+}
+
+/// Rows touched by layers that an edit over `start..end` (host bytes) may
+/// have re-highlighted. A separate layer can change anywhere in itself (an
+/// opened block comment), a combined one only in the ranges the edit touched.
+pub fn rows_affected_by_edit(
+    layers: &[InjectionLayer],
+    line_start_bytes: &[usize],
+    start: usize,
+    end: usize,
+) -> Vec<(usize, usize)> {
+    let touches = |range: &Range| range.start_byte <= end && range.end_byte >= start;
+    let mut rows = Vec::new();
+    for layer in layers {
+        if !layer.ranges.iter().any(touches) {
+            continue;
+        }
+        let row = |byte| byte_to_point(line_start_bytes, byte).row;
+        if !layer.combined {
+            let first = layer.ranges.first().map_or(0, |range| range.start_byte);
+            let last = layer.ranges.last().map_or(0, |range| range.end_byte);
+            rows.push((row(first), row(last)));
+        } else {
+            rows.extend(
+                layer
+                    .ranges
+                    .iter()
+                    .filter(|range| touches(range))
+                    .map(|range| (row(range.start_byte), row(range.end_byte))),
+            );
+        }
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(language: &str, source: &str) -> (Tree, Vec<InjectionLayer>) {
+        let host = layer_language(language).expect("built-in language");
+        let tree = parse_layer(&host.language, source, &[]).expect("parse host");
+        let line_starts: Vec<usize> = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+        let layers = collect_injections(
+            source,
+            &line_starts,
+            &tree,
+            host.injections.as_ref().expect("injection query"),
+        );
+        (tree, layers)
+    }
+
+    fn layer_names(layers: &[InjectionLayer]) -> Vec<&str> {
+        layers
+            .iter()
+            .map(|layer| layer.language.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn injection_queries_compile() {
+        for name in [
+            "markdown",
+            "markdown_inline",
+            "html",
+            "javascript",
+            "typescript",
+            "tsx",
+        ] {
+            let language = layer_language(name).expect("built-in language");
+            assert!(language.injections.is_some(), "{name} injections");
+        }
+    }
+
+    #[test]
+    fn markdown_fences_inject_by_info_string() {
+        let source = "# Title\n\n```rust,ignore\nfn main() {}\n```\n\n```nope\nx\n```\n";
+        let (_, layers) = parse("markdown", source);
+
+        let names = layer_names(&layers);
+        assert!(names.contains(&"rust"), "{names:?}");
+        assert!(names.contains(&"markdown_inline"), "{names:?}");
+        assert_eq!(names.len(), 2, "unknown fence languages are skipped");
+
+        let rust = layers
+            .iter()
+            .find(|layer| layer.language.name == "rust")
+            .unwrap();
+        assert_eq!(
+            &source[rust.ranges[0].start_byte..rust.ranges[0].end_byte],
+            "fn main() {}\n"
+        );
+        assert!(!rust.overlay);
+    }
+
+    #[test]
+    fn html_script_and_style_nest_inside_markdown() {
+        let source =
+            "<div>\n<script>let x = 1;</script>\n<style>a { color: red; }</style>\n</div>\n";
+        let (_, layers) = parse("markdown", source);
+
+        let html = layers
+            .iter()
+            .find(|layer| layer.language.name == "html")
+            .unwrap();
+        assert_eq!(html.depth, 1);
+        let script = layers
+            .iter()
+            .find(|layer| layer.language.name == "javascript")
+            .expect("script layer");
+        assert_eq!(script.depth, 2);
+        assert_eq!(
+            &source[script.ranges[0].start_byte..script.ranges[0].end_byte],
+            "let x = 1;"
+        );
+        assert!(layer_names(&layers).contains(&"css"));
+    }
+
+    #[test]
+    fn tagged_templates_combine_into_one_css_layer() {
+        let source = "const a = css`color: red;`;\nconst B = styled.div`margin: ${m}px;`;\n";
+        let (_, layers) = parse("javascript", source);
+
+        assert_eq!(layer_names(&layers), vec!["css"]);
+        assert_eq!(layers[0].ranges.len(), 3);
+    }
+
+    #[test]
+    fn fence_language_name_takes_the_first_word() {
+        assert_eq!(fence_language_name("rust,ignore"), "rust");
+        assert_eq!(fence_language_name("{.python}"), "python");
+        assert_eq!(fence_language_name("c++ linenums"), "c++");
+    }
+}
@@ -1,14 +1,18 @@
 mod highlighter;
+mod injection;
+mod runtime;
 mod theme;
 
-pub use highlighter::HighlightSpan;
+pub use highlighter::{HighlightSpan, SemanticToken};
+pub use runtime::{grammars_dir, installed_grammars, load_runtime_grammar};
 pub use theme::{HighlightGroup, SyntaxStyle, Theme};
 
 use std::cell::{Cell, RefCell};
 use std::path::Path;
-use tree_sitter::{Parser, Query, Tree};
+use std::sync::Arc;
+use tree_sitter::{InputEdit, Parser, Query, Tree};
 
-use crate::editor::Buffer;
+use crate::editor::{Buffer, SyntaxEdits};
 
 pub const MAX_HIGHLIGHT_LINES: usize = 200_000;
 pub const MAX_HIGHLIGHT_CHARS: usize = 2_000_000;
@@ -25,6 +29,9 @@ pub struct SyntaxManager {
     tree: Option<Tree>,
     /// Highlight query for the current language
     query: Option<Query>,
+    /// Grammar loaded from the grammars directory, when one is in use; its
+    /// queries stand in for `query`
+    runtime_language: Option<Arc<injection::LayerLanguage>>,
     /// Current language name
     language: Option<String>,
     /// Color theme
@@ -39,6 +46,21 @@ pub struct SyntaxManager {
     cache_version: Cell<u64>,
     /// Version of the buffer last parsed
     parse_version: u64,
+    /// Buffer version last handed to `reparse`; `None` until the first parse
+    /// and after the language changes
+    synced_version: Option<u64>,
+    /// The current language's injection query, when it embeds other languages
+    injection_language: Option<Arc<injection::LayerLanguage>>,
+    /// Parsed injected layers (fenced code, `<script>`, inline Markdown, ...)
+    injections: Vec<injection::InjectionLayer>,
+    /// LSP semantic tokens per line, laid over the tree-sitter highlights.
+    /// Edits shift them along with the cached highlights and drop them from
+    /// the lines they touch.
+    semantic_tokens: Vec<Vec<SemanticToken>>,
+    /// Buffer version `semantic_tokens` were computed for
+    semantic_version: Option<u64>,
+    /// Tokens for a buffer version not parsed yet, installed once it is
+    pending_semantic_tokens: Option<(u64, Vec<Vec<SemanticToken>>)>,
 }
 
 impl SyntaxManager {
@@ -48,6 +70,7 @@ impl SyntaxManager {
             parser: Parser::new(),
             tree: None,
             query: None,
+            runtime_language: None,
             language: None,
             theme: Theme::default(),
             source_cache: String::new(),
@@ -55,6 +78,12 @@ impl SyntaxManager {
             highlight_cache: RefCell::new(Vec::new()),
             cache_version: Cell::new(0),
             parse_version: 0,
+            synced_version: None,
+            injection_language: None,
+            injections: Vec::new(),
+            semantic_tokens: Vec::new(),
+            semantic_version: None,
+            pending_semantic_tokens: None,
         }
     }
 
@@ -65,6 +94,44 @@ impl SyntaxManager {
 
     /// Detect language from path, then the first line (shebang) if the path is unknown.
     pub fn set_language_from_path_and_first_line(&mut self, path: &Path, first_line: Option<&str>) {
+        // The tree belongs to whatever buffer was parsed before.
+        self.tree = None;
+        self.synced_version = None;
+        self.runtime_language = None;
+        self.detect_language(path, first_line);
+        self.load_injection_language();
+    }
+
+    /// Use the runtime grammar `name` from the grammars directory. On error
+    /// the current language is left untouched.
+    pub fn set_runtime_language(&mut self, name: &str) -> Result<(), String> {
+        let language = runtime::load_runtime_grammar(name)?;
+        self.parser
+            .set_language(&language.language)
+            .map_err(|e| format!("{}: {}", name, e))?;
+        self.tree = None;
+        self.synced_version = None;
+        self.query = None;
+        self.language = Some(language.name.clone());
+        self.runtime_language = Some(language);
+        self.load_injection_language();
+        Ok(())
+    }
+
+    fn highlight_query(&self) -> Option<&Query> {
+        self.query
+            .as_ref()
+            .or_else(|| self.runtime_language.as_ref().map(|l| &l.highlights))
+    }
+
+    /// The current language's `indents.scm` query, for runtime grammars that ship one.
+    pub fn indent_query(&self) -> Option<&Query> {
+        self.runtime_language
+            .as_ref()
+            .and_then(|language| language.indents.as_ref())
+    }
+
+    fn detect_language(&mut self, path: &Path, first_line: Option<&str>) {
         let extension = path.extension().and_then(|e| e.to_str());
 
         if is_ruby_path(path, extension) {
@@ -113,24 +180,46 @@ impl SyntaxManager {
         path: Option<&std::path::PathBuf>,
         first_line: Option<&str>,
     ) {
+        self.tree = None;
+        self.synced_version = None;
+        self.runtime_language = None;
         if let Some(p) = path {
-            self.set_language_from_path_and_first_line(p, first_line);
+            self.detect_language(p, first_line);
         } else if first_line.is_some_and(shebang_is_shell) {
             self.set_shell_language();
         } else {
             self.clear_language();
         }
+        self.load_injection_language();
+    }
+
+    /// Look up the injection query for the language just detected.
+    fn load_injection_language(&mut self) {
+        self.injections.clear();
+        if let Some(language) = &self.runtime_language {
+            self.injection_language = language.injections.is_some().then(|| Arc::clone(language));
+            return;
+        }
+        self.injection_language = match self.language.as_deref() {
+            Some(name) if self.query.is_some() => highlighter::injection_query(name)
+                .and_then(|_| injection::layer_language(name))
+                .filter(|language| language.injections.is_some()),
+            _ => None,
+        };
     }
 
     fn clear_language(&mut self) {
         self.language = None;
         self.query = None;
+        self.runtime_language = None;
         self.tree = None;
         self.source_cache.clear();
         self.line_start_bytes.clear();
         self.highlight_cache.borrow_mut().clear();
         self.cache_version.set(0);
         self.parse_version = 0;
+        self.synced_version = None;
+        self.injections.clear();
     }
 
     /// Set up Rust language parser
@@ -509,55 +598,196 @@ impl SyntaxManager {
 
     /// Parse the entire buffer
     pub fn parse(&mut self, buffer: &Buffer) {
+        self.reparse(buffer, SyntaxEdits::default());
+    }
+
+    /// Bring the tree up to date with `buffer`. When `edits` cover every
+    /// change since the last parse, the old tree is edited and reused and only
+    /// the lines whose syntax changed lose their cached highlights.
+    pub fn reparse(&mut self, buffer: &Buffer, edits: SyntaxEdits) {
+        self.synced_version = Some(buffer.version());
         if self.language.is_none() {
             return;
         }
 
         if self.language.as_deref() == Some("yaml") {
             self.source_cache = buffer_to_string(buffer);
-            self.line_start_bytes.clear();
-            self.line_start_bytes.push(0);
-            for (idx, b) in self.source_cache.bytes().enumerate() {
-                if b == b'\n' {
-                    self.line_start_bytes.push(idx + 1);
-                }
-            }
+            self.line_start_bytes = line_start_bytes(&self.source_cache);
             self.tree = None;
             self.query = None;
             self.parse_version = buffer.version();
             self.cache_version.set(self.parse_version);
             self.highlight_cache
                 .replace(vec![None; self.line_start_bytes.len()]);
+            self.clear_semantic_tokens();
             return;
         }
 
         if exceeds_highlight_limits(buffer.len_lines(), buffer.len_chars()) {
             self.tree = None;
+            self.injections.clear();
             self.source_cache.clear();
             self.line_start_bytes.clear();
             self.highlight_cache.borrow_mut().clear();
             self.cache_version.set(0);
             self.parse_version = buffer.version();
+            self.clear_semantic_tokens();
             return;
         }
 
-        // Convert buffer to string for parsing
-        self.source_cache = buffer_to_string(buffer);
-        self.line_start_bytes.clear();
-        self.line_start_bytes.push(0);
-        for (idx, b) in self.source_cache.bytes().enumerate() {
-            if b == b'\n' {
-                self.line_start_bytes.push(idx + 1);
+        let tracked = edits.base_version == self.parse_version && !self.source_cache.is_empty();
+        let edit = match (edits.edits, self.tree.take()) {
+            (Some(edits), Some(old_tree)) if tracked => {
+                self.parse_incremental(buffer, &edits, old_tree)
             }
-        }
-        // Note: Incremental parsing requires calling tree.edit() before parse()
-        // to inform tree-sitter of document changes. Without proper edit tracking,
-        // passing the old tree causes highlighting corruption. Full reparse for now.
-        self.tree = self.parser.parse(&self.source_cache, None);
+            _ => {
+                self.parse_full(buffer);
+                None
+            }
+        };
+        self.refresh_injections(edit);
         self.parse_version = buffer.version();
         self.cache_version.set(self.parse_version);
+        if let Some((version, tokens)) = self.pending_semantic_tokens.take() {
+            if version == self.parse_version {
+                self.install_semantic_tokens(version, tokens);
+            } else if version > self.parse_version {
+                self.pending_semantic_tokens = Some((version, tokens));
+            }
+        }
+    }
+
+    fn parse_full(&mut self, buffer: &Buffer) {
+        self.source_cache = buffer_to_string(buffer);
+        self.line_start_bytes = line_start_bytes(&self.source_cache);
+        self.tree = parse_rope(&mut self.parser, buffer, None);
         self.highlight_cache
             .replace(vec![None; self.line_start_bytes.len()]);
+        // Without the edits there is no telling where old tokens moved.
+        self.semantic_tokens.clear();
+        self.semantic_version = None;
+    }
+
+    fn parse_incremental(
+        &mut self,
+        buffer: &Buffer,
+        edits: &[InputEdit],
+        mut old_tree: Tree,
+    ) -> Option<MergedEdit> {
+        for edit in edits {
+            old_tree.edit(edit);
+        }
+        let merged = merge_edits(edits);
+        if let Some(edit) = merged {
+            self.splice_source(buffer, edit);
+        }
+
+        self.tree = parse_rope(&mut self.parser, buffer, Some(&old_tree));
+        if let Some(tree) = &self.tree {
+            let mut cache = self.highlight_cache.borrow_mut();
+            for range in old_tree.changed_ranges(tree) {
+                let last_row = range.end_point.row.min(cache.len().saturating_sub(1));
+                for entry in cache
+                    .iter_mut()
+                    .take(last_row + 1)
+                    .skip(range.start_point.row)
+                {
+                    *entry = None;
+                }
+            }
+        }
+        merged
+    }
+
+    /// Re-run the injection query over the new tree. After an incremental
+    /// parse, rows of layers the edit touched lose their cached highlights;
+    /// if layers appeared or vanished, every row does.
+    fn refresh_injections(&mut self, edit: Option<MergedEdit>) {
+        let query = self
+            .injection_language
+            .as_ref()
+            .and_then(|language| language.injections.as_ref());
+        let (Some(tree), Some(query)) = (&self.tree, query) else {
+            self.injections.clear();
+            return;
+        };
+
+        let layers =
+            injection::collect_injections(&self.source_cache, &self.line_start_bytes, tree, query);
+        if let Some(edit) = edit {
+            let mut cache = self.highlight_cache.borrow_mut();
+            if injection::layer_shape(&self.injections) != injection::layer_shape(&layers) {
+                cache.iter_mut().for_each(|entry| *entry = None);
+            } else {
+                let rows = injection::rows_affected_by_edit(
+                    &layers,
+                    &self.line_start_bytes,
+                    edit.start,
+                    edit.new_end,
+                );
+                for (first, last) in rows {
+                    for entry in cache.iter_mut().take(last + 1).skip(first) {
+                        *entry = None;
+                    }
+                }
+            }
+        }
+        self.injections = layers;
+    }
+
+    /// Replace the bytes `edit` changed in `source_cache` with the buffer's
+    /// new text, shifting line starts and cached highlights after it.
+    fn splice_source(&mut self, buffer: &Buffer, edit: MergedEdit) {
+        let old_line_count = self.line_start_bytes.len();
+        let start_row = self
+            .line_start_bytes
+            .partition_point(|&byte| byte <= edit.start)
+            - 1;
+        let old_end_row = self
+            .line_start_bytes
+            .partition_point(|&byte| byte <= edit.old_end)
+            - 1;
+
+        let new_text = buffer.text_between_bytes(edit.start, edit.new_end);
+        self.source_cache
+            .replace_range(edit.start..edit.old_end, &new_text);
+
+        let tail: Vec<usize> = self.line_start_bytes[old_end_row + 1..]
+            .iter()
+            .map(|byte| byte - edit.old_end + edit.new_end)
+            .collect();
+        self.line_start_bytes.truncate(start_row + 1);
+        self.line_start_bytes.extend(
+            new_text
+                .match_indices('\n')
+                .map(|(offset, _)| edit.start + offset + 1),
+        );
+        let new_end_row = self.line_start_bytes.len() - 1;
+        self.line_start_bytes.extend(tail);
+
+        let mut cache = self.highlight_cache.borrow_mut();
+        if cache.len() == old_line_count {
+            cache.splice(
+                start_row..=old_end_row,
+                std::iter::repeat_n(None, new_end_row - start_row + 1),
+            );
+        } else {
+            *cache = vec![None; self.line_start_bytes.len()];
+        }
+        if self.semantic_tokens.len() == old_line_count {
+            self.semantic_tokens.splice(
+                start_row..=old_end_row,
+                std::iter::repeat_n(Vec::new(), new_end_row - start_row + 1),
+            );
+        } else {
+            self.semantic_tokens.clear();
+            self.semantic_version = None;
+        }
+    }
+
+    /// Whether the last parse saw the current version of `buffer`.
+    pub fn is_synced_with(&self, buffer: &Buffer) -> bool {
+        self.synced_version == Some(buffer.version())
     }
 
     /// Parse string content directly (for preview panels, etc.)
@@ -611,6 +841,7 @@ impl SyntaxManager {
             }
         }
         self.tree = self.parser.parse(&self.source_cache, None);
+        self.refresh_injections(None);
         self.parse_version = self.parse_version.wrapping_add(1);
         self.cache_version.set(self.parse_version);
         self.highlight_cache
@@ -619,7 +850,8 @@ impl SyntaxManager {
 
     /// Check if syntax highlighting is available
     pub fn has_highlighting(&self) -> bool {
-        self.language.as_deref() == Some("yaml") || (self.tree.is_some() && self.query.is_some())
+        self.language.as_deref() == Some("yaml")
+            || (self.tree.is_some() && self.highlight_query().is_some())
     }
 
     /// Get highlights for a specific line
@@ -656,7 +888,7 @@ impl SyntaxManager {
             return spans;
         }
 
-        match (&self.tree, &self.query) {
+        match (&self.tree, self.highlight_query()) {
             (Some(tree), Some(query)) => {
                 if self.cache_version.get() != self.parse_version {
                     self.highlight_cache
@@ -677,14 +909,26 @@ impl SyntaxManager {
                     return cached.clone();
                 }
 
-                let spans = highlighter::get_line_highlights(
+                let host = highlighter::HighlightLayer {
                     tree,
                     query,
+                    ranges: &[],
+                    depth: 0,
+                    overlay: false,
+                };
+                let layers: Vec<_> = std::iter::once(host)
+                    .chain(self.injections.iter().map(|layer| layer.highlight_layer()))
+                    .collect();
+                let mut spans = highlighter::get_line_highlights(
+                    &layers,
                     &self.source_cache,
                     &self.line_start_bytes,
                     line,
                     &self.theme,
                 );
+                if let Some(tokens) = self.semantic_tokens.get(line) {
+                    spans = highlighter::overlay_semantic_tokens(spans, tokens, &self.theme);
+                }
                 if let Some(entry) = self.highlight_cache.borrow_mut().get_mut(line) {
                     *entry = Some(spans.clone());
                 }
@@ -694,6 +938,57 @@ impl SyntaxManager {
         }
     }
 
+    /// Lay LSP semantic tokens (one list per line) for buffer `version`
+    /// over the tree-sitter highlights. Tokens for a version not parsed yet
+    /// wait for its parse; older ones are dropped.
+    pub fn set_semantic_tokens(&mut self, version: u64, tokens: Vec<Vec<SemanticToken>>) {
+        if self.synced_version == Some(version) && self.parse_version == version {
+            self.pending_semantic_tokens = None;
+            self.install_semantic_tokens(version, tokens);
+        } else if self.synced_version.is_none_or(|synced| synced < version) {
+            self.pending_semantic_tokens = Some((version, tokens));
+        }
+    }
+
+    /// Drop semantic tokens, leaving the tree-sitter highlights
+    pub fn clear_semantic_tokens(&mut self) {
+        self.pending_semantic_tokens = None;
+        self.semantic_version = None;
+        if !self.semantic_tokens.is_empty() {
+            self.semantic_tokens.clear();
+            self.highlight_cache.borrow_mut().clear();
+        }
+    }
+
+    /// Buffer version of the newest semantic tokens handed over, shown or
+    /// waiting for their parse. Tokens shifted by later edits keep the
+    /// version they were computed for.
+    pub fn semantic_tokens_version(&self) -> Option<u64> {
+        self.pending_semantic_tokens
+            .as_ref()
+            .map(|(version, _)| *version)
+            .or(self.semantic_version)
+    }
+
+    /// Keep the shown semantic tokens but treat them as out of date, so
+    /// new ones are requested
+    pub fn mark_semantic_tokens_stale(&mut self) {
+        self.semantic_version = None;
+        self.pending_semantic_tokens = None;
+    }
+
+    /// Semantic tokens on `line`, in column order
+    pub fn semantic_tokens_on_line(&self, line: usize) -> &[SemanticToken] {
+        self.semantic_tokens.get(line).map_or(&[], Vec::as_slice)
+    }
+
+    fn install_semantic_tokens(&mut self, version: u64, mut tokens: Vec<Vec<SemanticToken>>) {
+        tokens.resize(self.line_start_bytes.len(), Vec::new());
+        self.semantic_tokens = tokens;
+        self.semantic_version = Some(version);
+        self.highlight_cache.borrow_mut().clear();
+    }
+
     /// Get the current language name
     pub fn language_name(&self) -> Option<&str> {
         self.language.as_deref()
@@ -764,15 +1059,51 @@ impl Default for SyntaxManager {
 
 /// Convert a buffer to a string for tree-sitter parsing
 fn buffer_to_string(buffer: &Buffer) -> String {
-    let mut result = String::new();
-    for i in 0..buffer.len_lines() {
-        if let Some(line) = buffer.line(i) {
-            for ch in line.chars() {
-                result.push(ch);
-            }
-        }
-    }
-    result
+    buffer.chunks().collect()
+}
+
+fn line_start_bytes(source: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(source.match_indices('\n').map(|(idx, _)| idx + 1))
+        .collect()
+}
+
+/// Parse straight from the buffer's rope chunks.
+fn parse_rope(parser: &mut Parser, buffer: &Buffer, old_tree: Option<&Tree>) -> Option<Tree> {
+    parser.parse_with_options(
+        &mut |byte, _| buffer.chunk_from_byte(byte).as_bytes(),
+        old_tree,
+        None,
+    )
+}
+
+/// Byte range covering a run of edits: `start..old_end` in the text before
+/// the first edit became `start..new_end` after the last.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct MergedEdit {
+    start: usize,
+    old_end: usize,
+    new_end: usize,
+}
+
+/// Merge edits, each given in the coordinates left by the ones before it.
+fn merge_edits(edits: &[InputEdit]) -> Option<MergedEdit> {
+    edits.iter().fold(None, |merged, edit| {
+        let Some(merged) = merged else {
+            return Some(MergedEdit {
+                start: edit.start_byte,
+                old_end: edit.old_end_byte,
+                new_end: edit.new_end_byte,
+            });
+        };
+        // End of the covered range in current coordinates, before this edit.
+        let end = merged.new_end.max(edit.old_end_byte);
+        Some(MergedEdit {
+            start: merged.start.min(edit.start_byte),
+            old_end: merged.old_end + (end - merged.new_end),
+            new_end: end - edit.old_end_byte + edit.new_end_byte,
+        })
+    })
 }
 
 fn is_ruby_path(path: &Path, extension: Option<&str>) -> bool {
@@ -862,7 +1193,8 @@ pub fn shebang_is_shell(first_line: &str) -> bool {
     )
 }
 
-fn shebang_interpreter(first_line: &str) -> Option<&str> {
+/// Interpreter named by a `#!` line, looking through `/usr/bin/env`.
+pub fn shebang_interpreter(first_line: &str) -> Option<&str> {
     let line = first_line.trim_end_matches(['\r', '\n']);
     let rest = line.strip_prefix("#!")?.trim();
     let mut parts = rest.split_whitespace();
@@ -1127,4 +1459,296 @@ mod tests {
         assert!(!shebang_is_shell("#!/usr/bin/env fish"));
         assert!(!shebang_is_shell("echo hi"));
     }
+
+    fn rust_syntax(buffer: &mut Buffer) -> SyntaxManager {
+        let mut syntax = SyntaxManager::new();
+        syntax.set_language_from_path(Path::new("lib.rs"));
+        let edits = buffer.take_syntax_edits();
+        syntax.reparse(buffer, edits);
+        syntax
+    }
+
+    fn assert_matches_full_parse(syntax: &SyntaxManager, buffer: &Buffer) {
+        assert_matches_full_parse_as(syntax, buffer, "lib.rs");
+    }
+
+    fn assert_matches_full_parse_as(syntax: &SyntaxManager, buffer: &Buffer, path: &str) {
+        let mut fresh = SyntaxManager::new();
+        fresh.set_language_from_path(Path::new(path));
+        fresh.parse(buffer);
+
+        assert_eq!(syntax.source_cache, buffer.content());
+        assert_eq!(syntax.line_start_bytes, fresh.line_start_bytes);
+        assert_eq!(
+            syntax.tree.as_ref().map(|tree| tree.root_node().to_sexp()),
+            fresh.tree.as_ref().map(|tree| tree.root_node().to_sexp())
+        );
+        for line in 0..buffer.len_lines() {
+            assert_eq!(
+                format!("{:?}", syntax.get_line_highlights(line)),
+                format!("{:?}", fresh.get_line_highlights(line)),
+                "highlights differ on line {line}"
+            );
+        }
+    }
+
+    #[test]
+    fn incremental_reparse_matches_a_full_parse() {
+        let mut buffer = Buffer::new();
+        buffer.set_content("fn one() {\n    let a = 1;\n}\n\nfn two() {}\n");
+        let mut syntax = rust_syntax(&mut buffer);
+        for line in 0..buffer.len_lines() {
+            syntax.get_line_highlights(line);
+        }
+
+        buffer.insert_str(1, 14, "\n    let é = \"/*\";");
+        buffer.delete_range(0, 3, 0, 6);
+        buffer.insert_char(0, 3, 'x');
+        buffer.replace_line(4, "/* block\n");
+        buffer.apply_change(5, 3, "two", "three");
+        let edits = buffer.take_syntax_edits();
+        assert_eq!(edits.edits.as_ref().map(Vec::len), Some(6));
+        syntax.reparse(&buffer, edits);
+        assert_matches_full_parse(&syntax, &buffer);
+
+        buffer.delete_range(0, 0, 3, 0);
+        let edits = buffer.take_syntax_edits();
+        syntax.reparse(&buffer, edits);
+        assert_matches_full_parse(&syntax, &buffer);
+    }
+
+    #[test]
+    fn incremental_reparse_keeps_highlights_of_untouched_lines() {
+        let mut buffer = Buffer::new();
+        buffer.set_content("fn a() {}\nfn b() {}\nfn c() {}\n");
+        let mut syntax = rust_syntax(&mut buffer);
+        for line in 0..buffer.len_lines() {
+            syntax.get_line_highlights(line);
+        }
+
+        buffer.insert_str(1, 4, "bb");
+        let edits = buffer.take_syntax_edits();
+        syntax.reparse(&buffer, edits);
+
+        let cache = syntax.highlight_cache.borrow();
+        assert!(cache[0].is_some());
+        assert!(cache[1].is_none());
+        assert!(cache[2].is_some());
+    }
+
+    #[test]
+    fn untracked_changes_fall_back_to_a_full_parse() {
+        let mut buffer = Buffer::new();
+        buffer.set_content("fn a() {}\n");
+        let mut syntax = rust_syntax(&mut buffer);
+
+        buffer.set_content("struct S;\n");
+        let edits = buffer.take_syntax_edits();
+        assert!(edits.edits.is_none());
+        syntax.reparse(&buffer, edits);
+        assert_matches_full_parse(&syntax, &buffer);
+    }
+
+    #[test]
+    fn merged_edits_cover_every_edit() {
+        let edit = |start_byte, old_end_byte, new_end_byte| InputEdit {
+            start_byte,
+            old_end_byte,
+            new_end_byte,
+            start_position: tree_sitter::Point::default(),
+            old_end_position: tree_sitter::Point::default(),
+            new_end_position: tree_sitter::Point::default(),
+        };
+
+        // Insert 3 bytes at 10, then delete 2 bytes at 4.
+        assert_eq!(
+            merge_edits(&[edit(10, 10, 13), edit(4, 6, 4)]),
+            Some(MergedEdit {
+                start: 4,
+                old_end: 10,
+                new_end: 11,
+            })
+        );
+        // Replace 5..8 with 1 byte, then delete across its end.
+        assert_eq!(
+            merge_edits(&[edit(5, 8, 6), edit(5, 10, 5)]),
+            Some(MergedEdit {
+                start: 5,
+                old_end: 12,
+                new_end: 5,
+            })
+        );
+        assert_eq!(merge_edits(&[]), None);
+    }
+
+    fn highlights_of(path: &str, content: &str, line: usize) -> String {
+        let mut syntax = SyntaxManager::new();
+        syntax.set_language_from_path(Path::new(path));
+        let mut buffer = Buffer::new();
+        buffer.set_content(content);
+        syntax.parse(&buffer);
+        format!("{:?}", syntax.get_line_highlights(line))
+    }
+
+    #[test]
+    fn markdown_fences_highlight_with_the_fence_language() {
+        assert_eq!(
+            highlights_of("notes.md", "# Notes\n\n```rust\nfn main() {}\n```\n", 3),
+            highlights_of("main.rs", "fn main() {}\n", 0)
+        );
+        // Unknown languages keep the plain code block style.
+        assert_eq!(
+            highlights_of("notes.md", "```nope\nfn main() {}\n```\n", 1),
+            highlights_of("notes.md", "```\nfn main() {}\n```\n", 1)
+        );
+    }
+
+    #[test]
+    fn html_script_and_style_highlight_as_javascript_and_css() {
+        let html = "<script>\nconst x = 1;\n</script>\n<style>\na { color: red; }\n</style>\n";
+        assert_eq!(
+            highlights_of("index.html", html, 1),
+            highlights_of("main.js", "const x = 1;\n", 0)
+        );
+        assert_eq!(
+            highlights_of("index.html", html, 4),
+            highlights_of("main.css", "a { color: red; }\n", 0)
+        );
+    }
+
+    #[test]
+    fn markdown_inline_layers_over_block_highlights() {
+        let theme = Theme::default();
+        let mut syntax = SyntaxManager::new();
+        syntax.set_language_from_path(Path::new("notes.md"));
+        let mut buffer = Buffer::new();
+        buffer.set_content("# Title `code`\n");
+        syntax.parse(&buffer);
+
+        let spans = syntax.get_line_highlights(0);
+        let style_at = |col: usize| {
+            spans
+                .iter()
+                .find(|span| span.start_col <= col && col < span.end_col)
+                .map(|span| span.style)
+        };
+        assert_eq!(style_at(2), theme.get_style(HighlightGroup::Type));
+        assert_eq!(style_at(9), theme.get_style(HighlightGroup::String));
+    }
+
+    #[test]
+    fn editing_a_fence_language_rehighlights_the_block() {
+        let mut buffer = Buffer::new();
+        buffer.set_content("```rust\nfn main() {}\n```\n\ntext\n");
+        let mut syntax = SyntaxManager::new();
+        syntax.set_language_from_path(Path::new("notes.md"));
+        syntax.parse(&buffer);
+        for line in 0..buffer.len_lines() {
+            syntax.get_line_highlights(line);
+        }
+
+        buffer.insert_char(0, 7, 'y');
+        let edits = buffer.take_syntax_edits();
+        syntax.reparse(&buffer, edits);
+        assert_matches_full_parse_as(&syntax, &buffer, "notes.md");
+
+        buffer.delete_char(0, 7);
+        buffer.insert_str(1, 0, "/* ");
+        let edits = buffer.take_syntax_edits();
+        syntax.reparse(&buffer, edits);
+        assert_matches_full_parse_as(&syntax, &buffer, "notes.md");
+    }
+
+    fn style_at(spans: &[HighlightSpan], col: usize) -> Option<SyntaxStyle> {
+        spans
+            .iter()
+            .find(|span| span.start_col <= col && col < span.end_col)
+            .map(|span| span.style)
+    }
+
+    #[test]
+    fn semantic_tokens_override_tree_sitter_where_they_cover() {
+        let theme = Theme::default();
+        let mut buffer = Buffer::new();
+        buffer.set_content("let mut counter = Foo::new();\n");
+        let mut syntax = rust_syntax(&mut buffer);
+        let keyword = style_at(&syntax.get_line_highlights(0), 0);
+
+        let token = |start_col, end_col, group| SemanticToken {
+            start_col,
+            end_col,
+            group,
+            modifiers: Vec::new(),
+        };
+        syntax.set_semantic_tokens(
+            buffer.version(),
+            vec![vec![
+                token(8, 15, HighlightGroup::Parameter),
+                token(18, 21, HighlightGroup::Interface),
+            ]],
+        );
+
+        let spans = syntax.get_line_highlights(0);
+        assert_eq!(style_at(&spans, 0), keyword);
+        assert_eq!(
+            style_at(&spans, 8),
+            theme.get_style(HighlightGroup::Parameter)
+        );
+        assert_eq!(
+            style_at(&spans, 18),
+            theme.get_style(HighlightGroup::Interface)
+        );
+        assert_eq!(syntax.semantic_tokens_version(), Some(buffer.version()));
+    }
+
+    #[test]
+    fn semantic_tokens_follow_edits_and_wait_for_their_parse() {
+        let mut buffer = Buffer::new();
+        buffer.set_content("a\nb\nc\n");
+        let mut syntax = rust_syntax(&mut buffer);
+        let token = |group| SemanticToken {
+            start_col: 0,
+            end_col: 1,
+            group,
+            modifiers: Vec::new(),
+        };
+        syntax.set_semantic_tokens(
+            buffer.version(),
+            vec![
+                vec![token(HighlightGroup::Struct)],
+                vec![token(HighlightGroup::Enum)],
+                vec![token(HighlightGroup::Parameter)],
+            ],
+        );
+
+        // Edited lines lose their tokens and the ones below them move down.
+        buffer.insert_str(0, 1, "\nx");
+        let edits = buffer.take_syntax_edits();
+        syntax.reparse(&buffer, edits);
+        let groups = |syntax: &SyntaxManager, line| {
+            syntax
+                .semantic_tokens_on_line(line)
+                .iter()
+                .map(|token| token.group)
+                .collect::<Vec<_>>()
+        };
+        assert!(groups(&syntax, 0).is_empty());
+        assert!(groups(&syntax, 1).is_empty());
+        assert_eq!(groups(&syntax, 2), vec![HighlightGroup::Enum]);
+        assert_eq!(groups(&syntax, 3), vec![HighlightGroup::Parameter]);
+
+        // Tokens for a version the debounced parse has not seen yet wait for it.
+        buffer.insert_char(0, 1, 'a');
+        syntax.set_semantic_tokens(buffer.version(), vec![vec![token(HighlightGroup::Enum)]]);
+        assert_eq!(groups(&syntax, 2), vec![HighlightGroup::Enum]);
+        assert_eq!(syntax.semantic_tokens_version(), Some(buffer.version()));
+        let edits = buffer.take_syntax_edits();
+        syntax.reparse(&buffer, edits);
+        assert_eq!(groups(&syntax, 0), vec![HighlightGroup::Enum]);
+        assert!(groups(&syntax, 2).is_empty());
+
+        syntax.clear_semantic_tokens();
+        assert!(groups(&syntax, 0).is_empty());
+        assert_eq!(syntax.semantic_tokens_version(), None);
+    }
 }
@@ -0,0 +1,280 @@
+//! Grammars loaded at runtime from `~/.config/nevi/grammars/<name>/`.
+//!
+//! A grammar directory holds a compiled parser (`.so` on Linux, `.dylib` on
+//! macOS) exporting `tree_sitter_<name>`, a required `highlights.scm` and
+//! optional `injections.scm` and `indents.scm`. Files are mapped to grammars
+//! by the `extensions` and `shebangs` keys in `languages.toml`.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use tree_sitter::{Language, Query};
+
+use super::injection::LayerLanguage;
+
+/// Directory searched for runtime grammars
+pub fn grammars_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".config/nevi/grammars"))
+}
+
+/// Names of the grammar directories present under `grammars_dir()`, sorted.
+pub fn installed_grammars() -> Vec<String> {
+    let Some(dir) = grammars_dir() else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+        .collect();
+    names.sort();
+    names
+}
+
+/// Load the runtime grammar `name`, compiling its queries on first use.
+/// Failures are cached too, so a broken grammar is reported once rather
+/// than retried on every buffer.
+pub fn load_runtime_grammar(name: &str) -> Result<Arc<LayerLanguage>, String> {
+    type LoadResult = Result<Arc<LayerLanguage>, String>;
+    static GRAMMARS: OnceLock<Mutex<HashMap<String, LoadResult>>> = OnceLock::new();
+
+    let mut grammars = GRAMMARS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(cached) = grammars.get(name) {
+        return cached.clone();
+    }
+
+    let loaded = match grammars_dir() {
+        Some(dir) => load_grammar_from(name, &dir.join(name)).map(Arc::new),
+        None => Err("home directory not found".to_string()),
+    };
+    grammars.insert(name.to_string(), loaded.clone());
+    loaded
+}
+
+/// Load the grammar in `dir` without caching.
+pub(super) fn load_grammar_from(name: &str, dir: &Path) -> Result<LayerLanguage, String> {
+    if !dir.is_dir() {
+        return Err(format!("{} not found", dir.display()));
+    }
+    let library = find_library(dir)?;
+    let language = open_language(&library, &symbol_name(name))?;
+
+    let abi = language.abi_version();
+    if !(tree_sitter::MIN_COMPATIBLE_LANGUAGE_VERSION..=tree_sitter::LANGUAGE_VERSION)
+        .contains(&abi)
+    {
+        return Err(format!(
+            "{}: ABI {} is not supported (expected {}-{})",
+            library.display(),
+            abi,
+            tree_sitter::MIN_COMPATIBLE_LANGUAGE_VERSION,
+            tree_sitter::LANGUAGE_VERSION
+        ));
+    }
+
+    let highlights = read_query(&language, dir, "highlights.scm")?
+        .ok_or_else(|| format!("{} is missing highlights.scm", dir.display()))?;
+    let injections = read_query(&language, dir, "injections.scm")?;
+    let indents = read_query(&language, dir, "indents.scm")?;
+
+    Ok(LayerLanguage {
+        name: name.to_string(),
+        language,
+        highlights,
+        injections,
+        indents,
+        library: Some(library),
+    })
+}
+
+/// The parser library in `dir`: `<dir name>.so`/`.dylib` if present,
+/// otherwise the only shared library there.
+fn find_library(dir: &Path) -> Result<PathBuf, String> {
+    let mut libraries: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map_err(|e| format!("{}: {}", dir.display(), e))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| matches!(ext, "so" | "dylib"))
+        })
+        .collect();
+    libraries.sort();
+
+    let stem = dir.file_name().and_then(|name| name.to_str());
+    if let Some(named) = libraries
+        .iter()
+        .find(|path| path.file_stem().and_then(|s| s.to_str()) == stem)
+    {
+        return Ok(named.clone());
+    }
+    match libraries.len() {
+        0 => Err(format!("no .so or .dylib parser in {}", dir.display())),
+        1 => Ok(libraries.remove(0)),
+        _ => Err(format!(
+            "several parser libraries in {}; name one {}.so",
+            dir.display(),
+            stem.unwrap_or("<grammar>")
+        )),
+    }
+}
+
+/// `tree_sitter_<name>`, the entry point `tree-sitter generate` emits.
+fn symbol_name(name: &str) -> String {
+    format!("tree_sitter_{}", name.replace('-', "_"))
+}
+
+fn read_query(language: &Language, dir: &Path, file: &str) -> Result<Option<Query>, String> {
+    let path = dir.join(file);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let source = std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", file, e))?;
+    Query::new(language, &source)
+        .map(Some)
+        .map_err(|e| format!("{}: {}", file, e))
+}
+
+#[cfg(unix)]
+fn open_language(library: &Path, symbol: &str) -> Result<Language, String> {
+    use std::ffi::{CStr, CString};
+    use std::os::unix::ffi::OsStrExt;
+
+    fn last_error() -> String {
+        // SAFETY: dlerror returns null or a NUL-terminated thread-local string.
+        let message = unsafe { libc::dlerror() };
+        if message.is_null() {
+            "unknown error".to_string()
+        } else {
+            unsafe { CStr::from_ptr(message) }
+                .to_string_lossy()
+                .into_owned()
+        }
+    }
+
+    let path = CString::new(library.as_os_str().as_bytes())
+        .map_err(|_| format!("{}: invalid path", library.display()))?;
+    let c_symbol = CString::new(symbol).map_err(|_| format!("{}: invalid symbol", symbol))?;
+
+    // The library is never closed: trees and queries keep pointers into it
+    // for as long as the editor runs.
+    // SAFETY: loading a parser runs no code beyond the library's initializers.
+    let handle = unsafe { libc::dlopen(path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+    if handle.is_null() {
+        return Err(last_error());
+    }
+    // SAFETY: `handle` came from a successful dlopen.
+    let entry = unsafe { libc::dlsym(handle, c_symbol.as_ptr()) };
+    if entry.is_null() {
+        return Err(format!(
+            "{}: symbol {} not found",
+            library.display(),
+            symbol
+        ));
+    }
+
+    // SAFETY: tree-sitter parsers export `const TSLanguage *tree_sitter_<name>(void)`.
+    let entry: unsafe extern "C" fn() -> *const () = unsafe { std::mem::transmute(entry) };
+    let language_fn = unsafe { tree_sitter_language::LanguageFn::from_raw(entry) };
+    Ok(Language::new(language_fn))
+}
+
+#[cfg(not(unix))]
+fn open_language(library: &Path, _symbol: &str) -> Result<Language, String> {
+    Err(format!(
+        "{}: runtime grammars are only supported on Unix",
+        library.display()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn grammar_dir(name: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time")
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!(
+            "nevi_runtime_grammar_{}_{}",
+            std::process::id(),
+            nanos
+        ));
+        let dir = root.join(name);
+        std::fs::create_dir_all(&dir).expect("create grammar dir");
+        dir
+    }
+
+    fn load_error(name: &str, dir: &Path) -> String {
+        match load_grammar_from(name, dir) {
+            Ok(_) => panic!("{name} should not load"),
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn missing_grammar_directory_is_reported() {
+        let dir = grammar_dir("zig");
+        let err = load_error("lua", &dir.with_file_name("lua"));
+        assert!(err.ends_with("lua not found"), "{err}");
+        std::fs::remove_dir_all(dir.parent().unwrap()).expect("cleanup");
+    }
+
+    #[test]
+    fn grammar_without_a_library_is_reported() {
+        let dir = grammar_dir("zig");
+        std::fs::write(dir.join("highlights.scm"), "(identifier) @variable\n").unwrap();
+        let err = load_error("zig", &dir);
+        assert!(err.starts_with("no .so or .dylib parser"), "{err}");
+        std::fs::remove_dir_all(dir.parent().unwrap()).expect("cleanup");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unloadable_library_reports_the_loader_error() {
+        let dir = grammar_dir("zig");
+        std::fs::write(dir.join("zig.so"), b"not an elf file").unwrap();
+        let err = load_error("zig", &dir);
+        assert!(err.contains("zig.so"), "{err}");
+        std::fs::remove_dir_all(dir.parent().unwrap()).expect("cleanup");
+    }
+
+    #[test]
+    fn library_is_picked_by_directory_name() {
+        let dir = grammar_dir("c-sharp");
+        std::fs::write(dir.join("c-sharp.so"), b"").unwrap();
+        std::fs::write(dir.join("scanner.so"), b"").unwrap();
+        assert_eq!(find_library(&dir).unwrap(), dir.join("c-sharp.so"));
+        assert_eq!(symbol_name("c-sharp"), "tree_sitter_c_sharp");
+
+        std::fs::remove_file(dir.join("c-sharp.so")).unwrap();
+        std::fs::write(dir.join("other.so"), b"").unwrap();
+        assert!(find_library(&dir).unwrap_err().starts_with("several"));
+        std::fs::remove_dir_all(dir.parent().unwrap()).expect("cleanup");
+    }
+
+    #[test]
+    fn invalid_queries_name_the_file() {
+        let dir = grammar_dir("json");
+        std::fs::write(dir.join("highlights.scm"), "(not_a_node) @x\n").unwrap();
+        let language: Language = tree_sitter_json::LANGUAGE.into();
+        let err = read_query(&language, &dir, "highlights.scm").unwrap_err();
+        assert!(err.starts_with("highlights.scm:"), "{err}");
+        assert!(
+            read_query(&language, &dir, "indents.scm")
+                .unwrap()
+                .is_none()
+        );
+        std::fs::remove_dir_all(dir.parent().unwrap()).expect("cleanup");
+    }
+}
@@ -29,7 +29,26 @@ impl SyntaxStyle {
     }
 }
 
-/// Highlight group names used by tree-sitter queries
+/// Change a semantic token modifier makes to a style
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ModifierStyle {
+    /// Replaces the token's color when set
+    pub fg: Option<Color>,
+    pub bold: bool,
+    pub italic: bool,
+}
+
+impl ModifierStyle {
+    pub fn apply(&self, style: SyntaxStyle) -> SyntaxStyle {
+        SyntaxStyle {
+            fg: self.fg.unwrap_or(style.fg),
+            bold: style.bold || self.bold,
+            italic: style.italic || self.italic,
+        }
+    }
+}
+
+/// Highlight group names used by tree-sitter queries and LSP semantic tokens
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum HighlightGroup {
     Keyword,
@@ -53,6 +72,17 @@ pub enum HighlightGroup {
     Method,      // .clone(), .ok()
     Constructor, // Some, None, Ok, Err
     Boolean,     // true, false
+    // Only produced by semantic tokens
+    Interface,     // traits, interfaces
+    Struct,        // structs, classes
+    Enum,          // enums
+    EnumMember,    // enum variants
+    TypeParameter, // T in Vec<T>
+    Parameter,     // function parameters
+    // Semantic token modifiers, styled on top of the token's group
+    Mutable,
+    Deprecated,
+    Async,
 }
 
 impl HighlightGroup {
@@ -92,6 +122,55 @@ impl HighlightGroup {
             _ => None,
         }
     }
+
+    /// Map an LSP semantic token type (standard, or one of rust-analyzer's
+    /// extensions) onto a highlight group. Constant and static variables
+    /// keep the constant color tree-sitter gives them.
+    pub fn from_semantic_token(token_type: &str, modifiers: &[&str]) -> Option<Self> {
+        let group = match token_type {
+            "namespace" | "toolModule" => Self::Namespace,
+            "type" | "builtinType" | "typeAlias" => Self::Type,
+            "class" | "struct" | "union" => Self::Struct,
+            "enum" => Self::Enum,
+            "interface" | "trait" => Self::Interface,
+            "typeParameter" => Self::TypeParameter,
+            "parameter" => Self::Parameter,
+            "variable" => Self::Variable,
+            "property" | "event" => Self::Property,
+            "enumMember" => Self::EnumMember,
+            "function" => Self::Function,
+            "method" => Self::Method,
+            "macro" => Self::Macro,
+            "keyword" | "modifier" | "selfKeyword" | "selfTypeKeyword" => Self::Keyword,
+            "comment" => Self::Comment,
+            "string" | "regexp" | "character" => Self::String,
+            "number" => Self::Number,
+            "boolean" => Self::Boolean,
+            "operator" => Self::Operator,
+            "decorator" | "attribute" | "builtinAttribute" | "derive" => Self::Attribute,
+            "lifetime" | "label" => Self::Label,
+            "constParameter" => Self::Constant,
+            _ => return None,
+        };
+        if group == Self::Variable
+            && modifiers
+                .iter()
+                .any(|modifier| matches!(*modifier, "constant" | "static"))
+        {
+            return Some(Self::Constant);
+        }
+        Some(group)
+    }
+
+    /// Map an LSP semantic token modifier onto the group that styles it
+    pub fn from_semantic_token_modifier(modifier: &str) -> Option<Self> {
+        match modifier {
+            "mutable" => Some(Self::Mutable),
+            "deprecated" => Some(Self::Deprecated),
+            "async" => Some(Self::Async),
+            _ => None,
+        }
+    }
 }
 
 /// A syntax highlighting theme
@@ -99,6 +178,8 @@ impl HighlightGroup {
 pub struct Theme {
     pub name: String,
     styles: HashMap<HighlightGroup, SyntaxStyle>,
+    /// Styles of the modifier groups
+    modifiers: HashMap<HighlightGroup, ModifierStyle>,
 }
 
 impl Theme {
@@ -269,10 +350,41 @@ impl Theme {
                 b: 102,
             }),
         ); // Orange
+        // Semantic token groups
+        let yellow = Color::Rgb {
+            r: 229,
+            g: 192,
+            b: 123,
+        };
+        styles.insert(
+            HighlightGroup::Interface,
+            SyntaxStyle::new(yellow).with_italic(),
+        );
+        styles.insert(HighlightGroup::Struct, SyntaxStyle::new(yellow));
+        styles.insert(HighlightGroup::Enum, SyntaxStyle::new(yellow));
+        styles.insert(
+            HighlightGroup::EnumMember,
+            SyntaxStyle::new(Color::Rgb {
+                r: 86,
+                g: 182,
+                b: 194,
+            }),
+        ); // Cyan
+        styles.insert(HighlightGroup::TypeParameter, SyntaxStyle::new(yellow));
+        styles.insert(
+            HighlightGroup::Parameter,
+            SyntaxStyle::new(Color::Rgb {
+                r: 224,
+                g: 108,
+                b: 117,
+            })
+            .with_italic(),
+        ); // Red, italic
 
         Self {
             name: "default".to_string(),
             styles,
+            modifiers: HashMap::new(),
         }
     }
 
@@ -281,6 +393,22 @@ impl Theme {
         self.styles.get(&group).copied()
     }
 
+    /// Style of a semantic token: its group's style with the styles of its
+    /// modifier groups applied in order
+    pub fn get_semantic_style(
+        &self,
+        group: HighlightGroup,
+        modifiers: &[HighlightGroup],
+    ) -> Option<SyntaxStyle> {
+        let style = self.get_style(group)?;
+        Some(
+            modifiers
+                .iter()
+                .filter_map(|modifier| self.modifiers.get(modifier))
+                .fold(style, |style, modifier| modifier.apply(style)),
+        )
+    }
+
     /// Get the color for a highlight group (for backwards compatibility)
     pub fn get_color(&self, group: HighlightGroup) -> Option<Color> {
         self.styles.get(&group).map(|s| s.fg)
@@ -348,10 +476,49 @@ impl Theme {
             convert(&ui_theme.syntax.constructor),
         );
         styles.insert(HighlightGroup::Boolean, convert(&ui_theme.syntax.boolean));
+        // Semantic token groups
+        styles.insert(
+            HighlightGroup::Interface,
+            convert(&ui_theme.syntax.interface),
+        );
+        styles.insert(HighlightGroup::Struct, convert(&ui_theme.syntax.struct_));
+        styles.insert(HighlightGroup::Enum, convert(&ui_theme.syntax.enum_));
+        styles.insert(
+            HighlightGroup::EnumMember,
+            convert(&ui_theme.syntax.enum_member),
+        );
+        styles.insert(
+            HighlightGroup::TypeParameter,
+            convert(&ui_theme.syntax.type_parameter),
+        );
+        styles.insert(
+            HighlightGroup::Parameter,
+            convert(&ui_theme.syntax.parameter),
+        );
+
+        let convert_modifier = |def: &crate::theme::ModifierDef| ModifierStyle {
+            fg: def.fg,
+            bold: def.bold,
+            italic: def.italic,
+        };
+        let mut modifiers = HashMap::new();
+        modifiers.insert(
+            HighlightGroup::Mutable,
+            convert_modifier(&ui_theme.syntax.mutable),
+        );
+        modifiers.insert(
+            HighlightGroup::Deprecated,
+            convert_modifier(&ui_theme.syntax.deprecated),
+        );
+        modifiers.insert(
+            HighlightGroup::Async,
+            convert_modifier(&ui_theme.syntax.async_),
+        );
 
         Self {
             name: ui_theme.name.clone(),
             styles,
+            modifiers,
         }
     }
 }
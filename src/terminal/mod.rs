@@ -61,16 +61,22 @@ fn finder_preview_match_ranges(line: &str, query: &str) -> Vec<(usize, usize)> {
     ranges
 }
 
-use crate::commands::{Command, CommandPopupMode, CommandResult, PendingDigraph, parse_command};
+use crate::commands::{
+    Command, CommandPopupMode, CommandResult, ExRange, PendingDigraph, parse_command,
+};
+use crate::config::keymap::parse_key_sequence;
 use crate::config::{CommandModeAction, LeaderAction};
 use crate::editor::{
-    BufferFormatOutcome, Editor, ExpressionRegisterTarget, LspAction, Mode, Pane, PaneDirection,
-    SplitLayout,
+    BufferFormatOutcome, BufferInlayHint, DocumentHighlightSpan, Editor, ExpressionRegisterTarget,
+    LspAction, Mode, Pane, PaneDirection, SplitLayout,
 };
 use crate::input::{
     InsertPosition, KeyAction, Operator, TextObject, TextObjectModifier, TextObjectType,
 };
-use crate::lsp::types::{CompletionKind, Diagnostic, DiagnosticSeverity};
+use crate::lsp::types::{
+    CompletionKind, Diagnostic, DiagnosticSeverity, DiagnosticTag, DocumentHighlightKind,
+    HierarchyDirection,
+};
 use crate::syntax::{HighlightSpan, SyntaxStyle};
 
 /// Events from the terminal that the editor cares about
@@ -196,6 +202,8 @@ struct RenderLineColors {
     selection_bg: Color,
     search_match_bg: Color,
     search_match_fg: Color,
+    document_highlight_bg: Color,
+    document_highlight_write_bg: Color,
     jump_label_bg: Color,
     jump_label_fg: Color,
     diagnostic_error_color: Color,
@@ -212,6 +220,8 @@ impl RenderLineColors {
             selection_bg: theme.ui.selection,
             search_match_bg: theme.ui.search_match_bg,
             search_match_fg: theme.ui.search_match_fg,
+            document_highlight_bg: theme.ui.document_highlight,
+            document_highlight_write_bg: theme.ui.document_highlight_write,
             jump_label_bg: theme.ui.finder_match,
             jump_label_fg: Color::Black,
             diagnostic_error_color: theme.diagnostic.error,
@@ -226,6 +236,10 @@ struct RenderLineCellStyle {
     bg: Color,
     bold: bool,
     italic: bool,
+    /// Unnecessary code (`DiagnosticTag::Unnecessary`)
+    dim: bool,
+    /// Deprecated code (`DiagnosticTag::Deprecated`)
+    strikethrough: bool,
     underline_color: Option<Color>,
     jump_label: Option<char>,
 }
@@ -239,23 +253,29 @@ struct RenderLineContext<'a> {
     mode: &'a Mode,
     is_cursor_line: bool,
     search_matches: &'a [(usize, usize, usize)],
+    document_highlights: &'a [DocumentHighlightSpan],
     jump_labels: &'a [(usize, char)],
     diagnostics: &'a [&'a Diagnostic],
     colors: RenderLineColors,
     tab_width: usize,
+    /// `:set list`
+    list: bool,
 }
 
 struct RenderLineContextFactory<'a> {
     visual_range: Option<(usize, usize, usize, usize)>,
     mode: &'a Mode,
     search_matches: &'a [(usize, usize, usize)],
+    document_highlights: &'a [DocumentHighlightSpan],
     colors: RenderLineColors,
     tab_width: usize,
+    list: bool,
 }
 
 impl<'a> RenderLineContextFactory<'a> {
     fn new(
         editor: &'a Editor,
+        buffer_idx: usize,
         visual_range: Option<(usize, usize, usize, usize)>,
         tab_width: usize,
     ) -> Self {
@@ -263,8 +283,10 @@ impl<'a> RenderLineContextFactory<'a> {
             visual_range,
             mode: &editor.mode,
             search_matches: &editor.search_matches,
+            document_highlights: editor.document_highlights_for_buffer(buffer_idx),
             colors: RenderLineColors::from_editor(editor),
             tab_width,
+            list: editor.list_mode(),
         }
     }
 
@@ -287,10 +309,12 @@ impl<'a> RenderLineContextFactory<'a> {
             mode: self.mode,
             is_cursor_line,
             search_matches: self.search_matches,
+            document_highlights: self.document_highlights,
             jump_labels,
             diagnostics,
             colors: self.colors,
             tab_width: self.tab_width,
+            list: self.list,
         }
     }
 }
@@ -325,6 +349,20 @@ impl RenderLineContext<'_> {
         source_col_for_wrap_segment_position(self.col_offset, self.virtual_prefix_chars, visual_idx)
     }
 
+    /// Context for the text from `visual_idx` on
+    fn starting_at(&self, visual_idx: usize) -> Self {
+        let (col_offset, virtual_prefix_chars) = if visual_idx < self.virtual_prefix_chars {
+            (self.col_offset, self.virtual_prefix_chars - visual_idx)
+        } else {
+            (self.col_offset + visual_idx - self.virtual_prefix_chars, 0)
+        };
+        Self {
+            col_offset,
+            virtual_prefix_chars,
+            ..*self
+        }
+    }
+
     #[cfg(test)]
     fn style_for_source_col(&self, source_col: usize) -> RenderLineCellStyle {
         self.style_for_source_col_with_syntax(source_col, self.syntax_style_at(source_col))
@@ -339,6 +377,7 @@ impl RenderLineContext<'_> {
         let syntax_color = syntax_style.map(|style| style.fg);
         let in_visual = self.is_visual_col(source_col);
         let is_search = self.is_search_match(source_col);
+        let document_highlight = self.document_highlight_at(source_col);
         let jump_label = self.jump_label_at(source_col);
         let diag_at_col = diagnostic_at_col(self.diagnostics, self.line_idx, source_col);
         let is_hint_diagnostic =
@@ -353,6 +392,13 @@ impl RenderLineContext<'_> {
             )
         } else if is_search {
             (self.colors.search_match_bg, self.colors.search_match_fg)
+        } else if let Some(kind) = document_highlight {
+            let bg = if kind == DocumentHighlightKind::Write {
+                self.colors.document_highlight_write_bg
+            } else {
+                self.colors.document_highlight_bg
+            };
+            (bg, syntax_color.unwrap_or(self.colors.editor_fg))
         } else if is_hint_diagnostic {
             (base_bg, self.colors.diagnostic_hint_color)
         } else {
@@ -372,11 +418,18 @@ impl RenderLineContext<'_> {
             })
         };
 
+        let tagged = |tag| {
+            jump_label.is_none()
+                && diagnostic_tagged_at_col(self.diagnostics, self.line_idx, source_col, tag)
+        };
+
         RenderLineCellStyle {
             fg,
             bg,
             bold: jump_label.is_some() || desired_style.map_or(false, |style| style.bold),
             italic: desired_style.map_or(false, |style| style.italic),
+            dim: tagged(DiagnosticTag::Unnecessary),
+            strikethrough: tagged(DiagnosticTag::Deprecated),
             underline_color,
             jump_label,
         }
@@ -424,6 +477,17 @@ impl RenderLineContext<'_> {
         })
     }
 
+    fn document_highlight_at(&self, source_col: usize) -> Option<DocumentHighlightKind> {
+        self.document_highlights
+            .iter()
+            .find(|span| {
+                span.line == self.line_idx
+                    && source_col >= span.start_col
+                    && source_col < span.end_col
+            })
+            .map(|span| span.kind)
+    }
+
     fn jump_label_at(&self, source_col: usize) -> Option<char> {
         self.jump_labels
             .iter()
@@ -508,23 +572,37 @@ fn diagnostic_at_col<'a>(
     diagnostics
         .iter()
         .copied()
-        .filter(|d| {
-            if line_num < d.line || line_num > d.end_line {
-                return false;
-            }
-            if d.line == d.end_line {
-                col >= d.col_start && col < d.col_end
-            } else if line_num == d.line {
-                col >= d.col_start
-            } else if line_num == d.end_line {
-                col < d.col_end
-            } else {
-                true
-            }
-        })
+        .filter(|d| diagnostic_covers(d, line_num, col))
         .min_by_key(|d| diagnostic_severity_priority(d.severity))
 }
 
+fn diagnostic_covers(d: &Diagnostic, line_num: usize, col: usize) -> bool {
+    if line_num < d.line || line_num > d.end_line {
+        return false;
+    }
+    if d.line == d.end_line {
+        col >= d.col_start && col < d.col_end
+    } else if line_num == d.line {
+        col >= d.col_start
+    } else if line_num == d.end_line {
+        col < d.col_end
+    } else {
+        true
+    }
+}
+
+/// Whether a diagnostic with `tag` covers the column, whatever its severity
+fn diagnostic_tagged_at_col(
+    diagnostics: &[&Diagnostic],
+    line_num: usize,
+    col: usize,
+    tag: DiagnosticTag,
+) -> bool {
+    diagnostics
+        .iter()
+        .any(|d| d.tags.contains(&tag) && diagnostic_covers(d, line_num, col))
+}
+
 fn diagnostic_underline_color(
     diagnostic: &Diagnostic,
     diagnostic_error_color: Color,
@@ -622,6 +700,14 @@ fn text_display_width(text: &str, tab_width: usize) -> usize {
         .sum()
 }
 
+/// Display width of the whitespace a line starts with
+fn leading_whitespace_width(text: &str, tab_width: usize) -> usize {
+    text.chars()
+        .take_while(|ch| *ch != '\n' && ch.is_whitespace())
+        .map(|ch| editor_char_display_width(ch, tab_width))
+        .sum()
+}
+
 fn display_width_between_char_cols(
     text: &str,
     start_col: usize,
@@ -770,6 +856,23 @@ fn write_editor_char<W: Write>(writer: &mut W, ch: char, tab_width: usize) -> io
     Ok(width)
 }
 
+/// Like `write_editor_char`, but shows a tab as `>` plus padding and a
+/// non-breaking space as `+`.
+fn write_list_char<W: Write>(writer: &mut W, ch: char, tab_width: usize) -> io::Result<usize> {
+    match ch {
+        '\t' => {
+            let width = editor_char_display_width(ch, tab_width);
+            write!(writer, ">{}", " ".repeat(width.saturating_sub(1)))?;
+            Ok(width)
+        }
+        '\u{a0}' => {
+            write!(writer, "+")?;
+            Ok(1)
+        }
+        _ => write_editor_char(writer, ch, tab_width),
+    }
+}
+
 /// Dim a color by reducing its brightness (for hidden files, etc.)
 fn dim_color(color: Color) -> Color {
     match color {
@@ -928,6 +1031,8 @@ fn render_line_text_with_context<W: Write>(
     let mut current_bg: Option<Color> = None;
     let mut current_bold = false;
     let mut current_italic = false;
+    let mut current_dim = false;
+    let mut current_strikethrough = false;
     let mut current_underline_color: Option<Color> = None;
     let mut rendered_cols = 0;
 
@@ -942,6 +1047,8 @@ fn render_line_text_with_context<W: Write>(
                 bg: base_bg,
                 bold: false,
                 italic: false,
+                dim: false,
+                strikethrough: false,
                 underline_color: None,
                 jump_label: None,
             }
@@ -959,6 +1066,8 @@ fn render_line_text_with_context<W: Write>(
             current_bg = Some(base_bg);
             current_bold = false;
             current_italic = false;
+            current_dim = false;
+            current_strikethrough = false;
             current_underline_color = None;
             continue;
         }
@@ -971,6 +1080,16 @@ fn render_line_text_with_context<W: Write>(
             execute!(writer, SetForegroundColor(cell_style.fg))?;
             current_fg = Some(cell_style.fg);
         }
+        if cell_style.dim != current_dim {
+            if cell_style.dim {
+                execute!(writer, SetAttribute(Attribute::Dim))?;
+            } else {
+                // Normal intensity ends bold too; it is set again below.
+                execute!(writer, SetAttribute(Attribute::NormalIntensity))?;
+                current_bold = false;
+            }
+            current_dim = cell_style.dim;
+        }
         if cell_style.bold != current_bold {
             execute!(
                 writer,
@@ -993,12 +1112,27 @@ fn render_line_text_with_context<W: Write>(
             )?;
             current_italic = cell_style.italic;
         }
+        if cell_style.strikethrough != current_strikethrough {
+            execute!(
+                writer,
+                SetAttribute(if cell_style.strikethrough {
+                    Attribute::CrossedOut
+                } else {
+                    Attribute::NotCrossedOut
+                })
+            )?;
+            current_strikethrough = cell_style.strikethrough;
+        }
         if cell_style.underline_color != current_underline_color {
             apply_diagnostic_underline(writer, cell_style.underline_color, cell_style.fg)?;
             current_underline_color = cell_style.underline_color;
         }
 
-        rendered_cols += write_editor_char(writer, *ch, context.tab_width)?;
+        rendered_cols += if context.list {
+            write_list_char(writer, *ch, context.tab_width)?
+        } else {
+            write_editor_char(writer, *ch, context.tab_width)?
+        };
     }
 
     if selection_end.is_some_and(|end| end > line_len) {
@@ -1017,6 +1151,72 @@ fn render_line_text_with_context<W: Write>(
     Ok(rendered_cols)
 }
 
+/// Where inlay hints go in rendered line text: the text index each label is
+/// drawn before, with the label as drawn. Text index `prefix` shows buffer
+/// column `first_col` (a wrapped row starts with `prefix` indent chars).
+/// Hints at the end of the text only belong to it when it `ends_line`.
+///
+/// With `clip_text`, labels push text past `max_width` off the row as a long
+/// line would be. Otherwise labels that do not fit are left out, so all of
+/// the text stays visible.
+#[allow(clippy::too_many_arguments)]
+fn place_inlay_hints(
+    text: &str,
+    first_col: usize,
+    prefix: usize,
+    hints: &[BufferInlayHint],
+    ends_line: bool,
+    max_width: usize,
+    clip_text: bool,
+    tab_width: usize,
+) -> Vec<(usize, String)> {
+    let text_len = text.chars().count();
+    let mut labels_width = 0;
+    let mut used = text_display_width(text, tab_width);
+    let mut placed = Vec::new();
+
+    for hint in hints {
+        let Some(offset) = hint.col.checked_sub(first_col) else {
+            continue;
+        };
+        let idx = prefix + offset;
+        if idx > text_len || (idx == text_len && !ends_line) {
+            continue;
+        }
+        let label_width = text_display_width(&hint.label, tab_width);
+        if clip_text {
+            let start = display_width_between_char_cols(text, 0, idx, tab_width) + labels_width;
+            if start >= max_width {
+                break;
+            }
+            let label = take_display_width(&hint.label, 0, max_width - start, tab_width);
+            labels_width += text_display_width(&label, tab_width);
+            placed.push((idx, label));
+        } else if used + label_width <= max_width {
+            used += label_width;
+            placed.push((idx, hint.label.clone()));
+        }
+    }
+
+    placed
+}
+
+/// Display columns inlay hints add before the char at `col` (the cursor
+/// draws after a hint placed at its own column).
+fn inlay_hint_width_before(
+    hints: &[(usize, String)],
+    prefix: usize,
+    first_col: usize,
+    col: usize,
+    tab_width: usize,
+) -> usize {
+    hints
+        .iter()
+        .filter(|(idx, _)| *idx <= prefix + col.saturating_sub(first_col))
+        .map(|(_, label)| text_display_width(label, tab_width))
+        .sum()
+}
+
 #[cfg(test)]
 fn source_col_for_wrap_segment_char(segment: &WrapSegment, visual_idx: usize) -> Option<usize> {
     source_col_for_wrap_segment_position(
@@ -1259,6 +1459,11 @@ impl Terminal {
             self.render_references_picker(editor)?;
         }
 
+        // Render call/type hierarchy popup if active
+        if editor.hierarchy_explorer.is_some() {
+            self.render_hierarchy_explorer(editor)?;
+        }
+
         // Render code actions picker if active
         if editor.code_actions_picker.is_some() {
             self.render_code_actions_picker(editor)?;
@@ -1327,6 +1532,7 @@ impl Terminal {
             || editor.signature_help.is_some()
             || editor.show_diagnostic_float
             || editor.references_picker.is_some()
+            || editor.hierarchy_explorer.is_some()
             || editor.code_actions_picker.is_some()
             || editor.markdown_preview.is_some()
             || editor.theme_picker.is_some()
@@ -1334,6 +1540,8 @@ impl Terminal {
             || !editor.leader_popup_items().is_empty()
             || editor.command_line.popup_mode != CommandPopupMode::None
             || !editor.search_matches.is_empty()
+            || editor.has_document_highlights()
+            || editor.has_code_lenses()
             || editor.mode.is_visual()
     }
 
@@ -1577,7 +1785,10 @@ impl Terminal {
 
     /// Render a single pane's content
     fn render_pane(&mut self, editor: &Editor, pane: &Pane, is_active: bool) -> anyhow::Result<()> {
-        let buffer = editor.buffer_at(pane.buffer_idx).unwrap();
+        let buffer = editor
+            .substitute_preview_buffer(pane.buffer_idx)
+            .or_else(|| editor.buffer_at(pane.buffer_idx))
+            .unwrap();
         let buffer_path = buffer.path.clone();
         let rect = &pane.rect;
 
@@ -1686,7 +1897,16 @@ impl Terminal {
         let editor_fg = theme.ui.foreground;
         let tab_width = editor.get_effective_tab_width();
         let line_count = buffer.addressable_line_count();
-        let line_context_factory = RenderLineContextFactory::new(editor, visual_range, tab_width);
+        let line_context_factory =
+            RenderLineContextFactory::new(editor, pane.buffer_idx, visual_range, tab_width);
+        let inlay_hints = editor.inlay_hints_for_buffer(pane.buffer_idx);
+        let text_area_width = pane_width.saturating_sub(
+            2 + if show_line_numbers {
+                line_num_width + 1
+            } else {
+                0
+            },
+        );
 
         // Pre-compute URI for diagnostic lookups (avoids repeated string allocations)
         let cached_uri = if is_active {
@@ -1711,11 +1931,7 @@ impl Terminal {
             let segments = calculate_wrap_segments(&line_content, wrap_width, true, tab_width);
 
             // Get syntax highlights for this line
-            let highlights = if is_active {
-                editor.syntax.get_line_highlights(file_line)
-            } else {
-                Vec::new()
-            };
+            let highlights = editor.rendered_line_highlights(pane.buffer_idx, file_line);
 
             // Get diagnostics for line number coloring
             let line_diagnostics = match &cached_uri {
@@ -1745,8 +1961,25 @@ impl Terminal {
                 0
             };
 
+            // Code lenses take a row of their own above the line's first row.
+            if first_visible_segment == 0
+                && let Some(label) = editor.code_lens_label(pane.buffer_idx, file_line)
+            {
+                let indent = leading_whitespace_width(&line_content, tab_width);
+                self.render_code_lens_row(
+                    editor,
+                    rect.x,
+                    rect.y + current_row as u16,
+                    pane_width.saturating_sub(text_area_width),
+                    indent,
+                    &label,
+                    pane_width,
+                )?;
+                current_row += 1;
+            }
+
             // Render each segment
-            for segment in segments.iter().skip(first_visible_segment) {
+            for (seg_idx, segment) in segments.iter().enumerate().skip(first_visible_segment) {
                 if current_row >= pane_height {
                     break;
                 }
@@ -1912,10 +2145,29 @@ impl Terminal {
                     &line_diagnostics,
                 );
 
-                let rendered_cols = self.render_line_text(
+                // Hints fit in beside the row's text or are left out, so
+                // rows wrap where they would without them.
+                let hints = inlay_hints
+                    .map(|cache| {
+                        place_inlay_hints(
+                            segment_text,
+                            segment.start_col,
+                            segment.virtual_prefix_chars,
+                            cache.hints_on_line(file_line),
+                            seg_idx + 1 == segments.len(),
+                            text_area_width,
+                            false,
+                            tab_width,
+                        )
+                    })
+                    .unwrap_or_default();
+                let rendered_cols = self.render_line_text_with_inlay_hints(
                     segment_text,
                     &context,
                     RenderLineTextScope::WrappedSegment,
+                    &hints,
+                    theme.ui.inlay_hint,
+                    text_area_width,
                 )?;
 
                 // Fill remaining space (sign column = 2)
@@ -2040,7 +2292,9 @@ impl Terminal {
         let editor_fg = theme.ui.foreground;
         let tab_width = editor.get_effective_tab_width();
         let line_count = buffer.addressable_line_count();
-        let line_context_factory = RenderLineContextFactory::new(editor, visual_range, tab_width);
+        let line_context_factory =
+            RenderLineContextFactory::new(editor, pane.buffer_idx, visual_range, tab_width);
+        let inlay_hints = editor.inlay_hints_for_buffer(pane.buffer_idx);
 
         // Pre-compute URI for diagnostic lookups (avoids repeated string allocations)
         let cached_uri = if is_active {
@@ -2049,10 +2303,37 @@ impl Terminal {
             None
         };
 
-        // Render each row in this pane
+        // Render each row in this pane. Code lenses take a row of their own
+        // above their line, so rows and lines part ways after the first.
+        let gutter_width = pane_width.saturating_sub(effective_width);
+        let mut file_line = pane.viewport_offset;
+        let mut lens_row_drawn = false;
         for row in 0..pane_height {
             let screen_y = rect.y + row as u16;
-            let file_line = pane.viewport_offset + row;
+            if !lens_row_drawn
+                && file_line < line_count
+                && let Some(label) = editor.code_lens_label(pane.buffer_idx, file_line)
+            {
+                let indent = buffer
+                    .line(file_line)
+                    .map(|line| leading_whitespace_width(&line.to_string(), tab_width))
+                    .unwrap_or(0)
+                    .saturating_sub(pane.h_offset);
+                self.render_code_lens_row(
+                    editor,
+                    rect.x,
+                    screen_y,
+                    gutter_width,
+                    indent,
+                    &label,
+                    pane_width,
+                )?;
+                lens_row_drawn = true;
+                continue;
+            }
+            lens_row_drawn = false;
+            file_line += 1;
+            let file_line = file_line - 1;
             let is_cursor_line = is_active && file_line == pane.cursor.line;
 
             // Move to start of this row in the pane
@@ -2220,12 +2501,8 @@ impl Terminal {
                     let line_str =
                         take_rope_display_width(line, h_offset, effective_width, tab_width);
 
-                    // Get syntax highlights for this line (only for active pane)
-                    let highlights = if is_active {
-                        editor.syntax.get_line_highlights(file_line)
-                    } else {
-                        Vec::new()
-                    };
+                    // Get syntax highlights for this line
+                    let highlights = editor.rendered_line_highlights(pane.buffer_idx, file_line);
 
                     let jump_labels = if is_active {
                         editor.labeled_jump_labels_for_line(file_line)
@@ -2242,10 +2519,27 @@ impl Terminal {
                         &line_diagnostics,
                     );
 
-                    let rendered_cols = self.render_line_text(
+                    let hints = inlay_hints
+                        .map(|cache| {
+                            place_inlay_hints(
+                                &line_str,
+                                h_offset,
+                                0,
+                                cache.hints_on_line(file_line),
+                                true,
+                                effective_width,
+                                true,
+                                tab_width,
+                            )
+                        })
+                        .unwrap_or_default();
+                    let rendered_cols = self.render_line_text_with_inlay_hints(
                         &line_str,
                         &context,
                         RenderLineTextScope::LogicalLine,
+                        &hints,
+                        theme.ui.inlay_hint,
+                        effective_width,
                     )?;
 
                     // Track characters printed for fill calculation (sign column = 2)
@@ -2436,6 +2730,47 @@ impl Terminal {
         Ok(())
     }
 
+    /// Draw a row of code lens titles, indented like the line below it
+    #[allow(clippy::too_many_arguments)]
+    fn render_code_lens_row(
+        &mut self,
+        editor: &Editor,
+        x: u16,
+        y: u16,
+        gutter_width: usize,
+        indent: usize,
+        label: &str,
+        pane_width: usize,
+    ) -> anyhow::Result<()> {
+        let theme = editor.theme();
+        let indent = indent.min(pane_width.saturating_sub(gutter_width));
+        let label: String = label
+            .chars()
+            .take(pane_width.saturating_sub(gutter_width + indent))
+            .collect();
+        let printed = gutter_width + indent + label.chars().count();
+        execute!(
+            self.stdout,
+            cursor::MoveTo(x, y),
+            SetAttribute(Attribute::Reset),
+            SetBackgroundColor(theme.ui.background),
+            SetForegroundColor(theme.ui.inlay_hint)
+        )?;
+        terminal_print!(
+            self,
+            "{:gutter$}{:indent$}{}{:fill$}",
+            "",
+            "",
+            label,
+            "",
+            gutter = gutter_width,
+            indent = indent,
+            fill = pane_width.saturating_sub(printed)
+        );
+        execute!(self.stdout, SetForegroundColor(theme.ui.foreground))?;
+        Ok(())
+    }
+
     fn render_line_text(
         &mut self,
         text: &str,
@@ -2453,6 +2788,57 @@ impl Terminal {
         )
     }
 
+    /// `render_line_text` with inlay hint labels (from `place_inlay_hints`)
+    /// drawn between the chars, keeping the row within `max_width` columns.
+    fn render_line_text_with_inlay_hints(
+        &mut self,
+        text: &str,
+        context: &RenderLineContext<'_>,
+        scope: RenderLineTextScope,
+        hints: &[(usize, String)],
+        hint_color: Color,
+        max_width: usize,
+    ) -> anyhow::Result<usize> {
+        if hints.is_empty() {
+            return self.render_line_text(text, context, scope);
+        }
+
+        let chars: Vec<char> = text.chars().collect();
+        let mut rendered_cols = 0;
+        let mut start = 0;
+        for (idx, label) in hints {
+            let chunk: String = chars[start..*idx].iter().collect();
+            rendered_cols += self.render_line_text(
+                &chunk,
+                &context.starting_at(start),
+                RenderLineTextScope::WrappedSegment,
+            )?;
+            execute!(
+                self.stdout,
+                SetForegroundColor(hint_color),
+                SetBackgroundColor(context.base_bg())
+            )?;
+            terminal_print!(self, "{}", label);
+            rendered_cols += text_display_width(label, context.tab_width);
+            start = *idx;
+        }
+
+        // Labels pushed the rest of the text right; drop what no longer fits.
+        let rest: String = chars[start..].iter().collect();
+        let rest = take_display_width(
+            &rest,
+            0,
+            max_width.saturating_sub(rendered_cols),
+            context.tab_width,
+        );
+        rendered_cols += self.render_line_text(
+            &rest,
+            &context.starting_at(start),
+            RenderLineTextScope::WrappedSegment,
+        )?;
+        Ok(rendered_cols)
+    }
+
     /// Draw separator lines between panes
     /// Render the file explorer sidebar
     fn render_explorer(&mut self, editor: &Editor) -> anyhow::Result<()> {
@@ -2972,8 +3358,17 @@ impl Terminal {
                         tab_width,
                     );
 
+                    // Code lens rows above the lines, the cursor line's included
+                    visual_row += editor.code_lens_rows(
+                        active_pane.buffer_idx,
+                        active_pane.viewport_offset,
+                        editor.cursor.line + 1,
+                        active_pane.h_offset,
+                    );
+
                     let mut cursor_visual_row = visual_row;
                     let mut cursor_visual_col = editor.cursor.col;
+                    let mut cursor_segment = 0;
 
                     for (seg_idx, segment) in segments.iter().enumerate() {
                         let segment_end = if seg_idx + 1 < segments.len() {
@@ -3000,6 +3395,7 @@ impl Terminal {
                                     .sum::<usize>();
                                 cursor_visual_col += indent_len;
                             }
+                            cursor_segment = seg_idx;
                             break;
                         }
                         cursor_visual_row += 1;
@@ -3017,12 +3413,35 @@ impl Terminal {
                         let last_segment = segments.last().unwrap();
                         cursor_visual_col =
                             text_display_width(last_segment.text.trim_end_matches('\n'), tab_width);
+                        cursor_segment = segments.len() - 1;
                         if editor.cursor.line == active_pane.viewport_offset {
                             cursor_visual_row =
                                 cursor_visual_row.saturating_sub(active_pane.h_offset);
                         }
                     }
 
+                    // Step over inlay hints drawn before the cursor
+                    if let Some(cache) = editor.inlay_hints_for_buffer(active_pane.buffer_idx) {
+                        let segment = &segments[cursor_segment];
+                        let hints = place_inlay_hints(
+                            segment.text.trim_end_matches('\n'),
+                            segment.start_col,
+                            segment.virtual_prefix_chars,
+                            cache.hints_on_line(editor.cursor.line),
+                            cursor_segment + 1 == segments.len(),
+                            text_area_width,
+                            false,
+                            tab_width,
+                        );
+                        cursor_visual_col += inlay_hint_width_before(
+                            &hints,
+                            segment.virtual_prefix_chars,
+                            segment.start_col,
+                            editor.cursor.col,
+                            tab_width,
+                        );
+                    }
+
                     // Sign column (2) + line numbers + cursor position
                     let col = 2 + if show_line_numbers {
                         line_num_width + 1 + cursor_visual_col
@@ -3036,18 +3455,61 @@ impl Terminal {
                     let cursor_row = editor
                         .cursor
                         .line
-                        .saturating_sub(active_pane.viewport_offset);
+                        .saturating_sub(active_pane.viewport_offset)
+                        + editor.code_lens_rows(
+                            active_pane.buffer_idx,
+                            active_pane.viewport_offset,
+                            editor.cursor.line + 1,
+                            0,
+                        );
                     // Sign column (2) + line numbers + cursor position (adjusted for horizontal scroll)
+                    let text_area_width = active_pane.rect.width as usize
+                        - 2
+                        - if show_line_numbers {
+                            line_num_width + 1
+                        } else {
+                            0
+                        };
+                    let inlay_hints = editor.inlay_hints_for_buffer(active_pane.buffer_idx);
                     let display_col = editor
                         .buffer()
                         .line(editor.cursor.line)
                         .map(|line| {
-                            display_width_between_rope_char_cols(
+                            let mut col = display_width_between_rope_char_cols(
                                 line,
                                 active_pane.h_offset,
                                 editor.cursor.col,
                                 tab_width,
-                            )
+                            );
+                            // Step over inlay hints drawn before the cursor
+                            if let Some(cache) = inlay_hints {
+                                let line_str = take_rope_display_width(
+                                    line,
+                                    active_pane.h_offset,
+                                    text_area_width,
+                                    tab_width,
+                                );
+                                let hints = place_inlay_hints(
+                                    &line_str,
+                                    active_pane.h_offset,
+                                    0,
+                                    cache.hints_on_line(editor.cursor.line),
+                                    true,
+                                    text_area_width,
+                                    true,
+                                    tab_width,
+                                );
+                                let hint_width = inlay_hint_width_before(
+                                    &hints,
+                                    0,
+                                    active_pane.h_offset,
+                                    editor.cursor.col,
+                                    tab_width,
+                                );
+                                // Hints can push the cursor's char off the row.
+                                col = (col + hint_width).min(text_area_width.max(col));
+                            }
+                            col
                         })
                         .unwrap_or(0);
                     let cursor_col = 2 + if show_line_numbers {
@@ -3126,13 +3588,14 @@ impl Terminal {
                 s.push_str(&count.to_string());
             }
             if let Some(op) = editor.input_state.pending_operator {
-                s.push(match op {
-                    Operator::Delete => 'd',
-                    Operator::Change => 'c',
-                    Operator::Yank => 'y',
-                    Operator::Indent => '>',
-                    Operator::Dedent => '<',
-                    Operator::AutoIndent => '=',
+                s.push_str(match op {
+                    Operator::Delete => "d",
+                    Operator::Change => "c",
+                    Operator::Yank => "y",
+                    Operator::Indent => ">",
+                    Operator::Dedent => "<",
+                    Operator::AutoIndent => "=",
+                    Operator::Format => "gq",
                 });
             }
             if !s.is_empty() {
@@ -3181,7 +3644,7 @@ impl Terminal {
 
         // Right side: LSP status, language and position
         let lsp_status = editor.lsp_status.as_deref().unwrap_or("");
-        let lang = editor.syntax.language_name().unwrap_or("plain");
+        let lang = editor.syntax().language_name().unwrap_or("plain");
         let right = if lsp_status.is_empty() {
             format!(
                 " {} | {}:{} ",
@@ -3250,6 +3713,65 @@ impl Terminal {
         out
     }
 
+    /// List the lines outside the window that the `:s` being typed would
+    /// change, above the status line (`inc_command = "split"`).
+    fn render_substitute_preview_listing(&mut self, editor: &Editor) -> anyhow::Result<()> {
+        let listing = editor.substitute_preview_listing();
+        if listing.is_empty() {
+            return Ok(());
+        }
+
+        let status_row = editor.term_height.saturating_sub(2);
+        let term_width = editor.term_width as usize;
+        let available_rows = status_row as usize;
+        if available_rows < 2 || term_width < 8 {
+            return Ok(());
+        }
+        let item_rows = listing.len().min(8).min(available_rows - 1);
+        let popup_top_row = status_row.saturating_sub((item_rows + 1) as u16);
+        let number_width = listing
+            .iter()
+            .map(|(line, _)| line.to_string().len())
+            .max()
+            .unwrap_or(1);
+
+        let theme = editor.theme();
+        let mut header = format!("[SUBSTITUTE] {} line(s) outside the window", listing.len());
+        header = Self::truncate_inline(&header, term_width);
+        let header_len = header.chars().count();
+        header.push_str(&" ".repeat(term_width.saturating_sub(header_len)));
+        execute!(
+            self.stdout,
+            cursor::MoveTo(0, popup_top_row),
+            terminal::Clear(ClearType::CurrentLine),
+            SetForegroundColor(theme.ui.statusline_mode_command),
+            SetBackgroundColor(theme.ui.statusline_bg)
+        )?;
+        terminal_print!(self, "{}", header);
+
+        for (row_offset, (line, text)) in listing.iter().take(item_rows).enumerate() {
+            let row = popup_top_row + row_offset as u16 + 1;
+            let text = text.replace('\t', " ");
+            let mut row_text = Self::truncate_inline(
+                &format!("{:>width$} {}", line, text, width = number_width),
+                term_width,
+            );
+            let printed = row_text.chars().count();
+            row_text.push_str(&" ".repeat(term_width.saturating_sub(printed)));
+            execute!(
+                self.stdout,
+                cursor::MoveTo(0, row),
+                terminal::Clear(ClearType::CurrentLine),
+                SetForegroundColor(theme.ui.foreground),
+                SetBackgroundColor(theme.ui.popup_bg)
+            )?;
+            terminal_print!(self, "{}", row_text);
+        }
+        execute!(self.stdout, ResetColor)?;
+
+        Ok(())
+    }
+
     /// Render command suggestions/history window above the status line.
     fn render_command_popup(&mut self, editor: &Editor) -> anyhow::Result<()> {
         if editor.mode != Mode::Command {
@@ -3405,7 +3927,11 @@ impl Terminal {
 
     fn render_command_line(&mut self, editor: &Editor) -> anyhow::Result<()> {
         if editor.mode == Mode::Command {
-            self.render_command_popup(editor)?;
+            if editor.command_line.popup_mode == CommandPopupMode::None {
+                self.render_substitute_preview_listing(editor)?;
+            } else {
+                self.render_command_popup(editor)?;
+            }
         }
 
         // Position at the command line row (last row)
@@ -4430,6 +4956,9 @@ impl Terminal {
         // Prepare diagnostic lines with numbers
         let mut lines: Vec<(Color, String)> = Vec::new();
         lines.push((theme.ui.foreground, "Diagnostics:".to_string()));
+        // Related locations are numbered across diagnostics; typing the
+        // number jumps there.
+        let mut related_number = 0;
 
         for (idx, diag) in diagnostics.iter().enumerate() {
             let (color, prefix) = match diag.severity {
@@ -4448,6 +4977,29 @@ impl Terminal {
                     lines.push((color, format!("   {}", msg_line)));
                 }
             }
+            for related in &diag.related {
+                related_number += 1;
+                let file = crate::lsp::uri_to_path(&related.uri)
+                    .and_then(|path| {
+                        path.file_name()
+                            .map(|name| name.to_string_lossy().to_string())
+                    })
+                    .unwrap_or_else(|| related.uri.clone());
+                lines.push((
+                    theme.ui.line_number,
+                    format!(
+                        "   [{}] {}:{}:{} {}",
+                        related_number,
+                        file,
+                        related.line + 1,
+                        related.col + 1,
+                        related.message.lines().next().unwrap_or_default()
+                    ),
+                ));
+            }
+            if let Some(href) = &diag.href {
+                lines.push((theme.ui.line_number, format!("   gx: {}", href)));
+            }
         }
 
         // Calculate popup dimensions
@@ -4881,6 +5433,220 @@ impl Terminal {
         Ok(())
     }
 
+    /// Render the call/type hierarchy popup: the tree on top and a preview
+    /// of the selected node's target below it
+    fn render_hierarchy_explorer(&mut self, editor: &Editor) -> anyhow::Result<()> {
+        let explorer = match &editor.hierarchy_explorer {
+            Some(e) => e,
+            None => return Ok(()),
+        };
+
+        if explorer.flat_view.is_empty() {
+            return Ok(());
+        }
+
+        let theme = editor.theme();
+        let border_color = theme.ui.popup_border;
+        let bg_color = theme.ui.popup_bg;
+        let selected_bg = theme.ui.popup_selection;
+        let text_color = theme.ui.foreground;
+        let detail_color = theme.ui.completion_detail;
+        let file_color = theme.ui.explorer_directory;
+        let line_num_color = theme.ui.line_number;
+        let target_bg = theme.ui.cursor_line;
+        let use_nerd_fonts = editor.settings.editor.use_nerd_font_icons;
+
+        // Calculate popup dimensions
+        let popup_width = 100u16.min(editor.term_width.saturating_sub(4));
+        let max_height = editor.term_height.saturating_sub(4);
+        let preview_lines = explorer
+            .preview
+            .as_ref()
+            .map_or(0, |preview| preview.lines.len()) as u16;
+        let tree_height = (explorer.flat_view.len() as u16)
+            .min(10)
+            .min(max_height.saturating_sub(preview_lines + 3).max(1));
+        let preview_height = preview_lines.min(max_height.saturating_sub(tree_height + 3));
+        let popup_height = tree_height + preview_height + if preview_height > 0 { 3 } else { 2 };
+        if popup_width < 20 || popup_height > editor.term_height {
+            return Ok(());
+        }
+
+        // Center the popup
+        let popup_x = (editor.term_width.saturating_sub(popup_width)) / 2;
+        let popup_y = (editor.term_height.saturating_sub(popup_height)) / 2;
+        let content_width = (popup_width - 2) as usize;
+
+        // Draw top border with the direction as title
+        execute!(
+            self.stdout,
+            cursor::MoveTo(popup_x, popup_y),
+            SetForegroundColor(border_color),
+            SetBackgroundColor(bg_color)
+        )?;
+        let title = format!(" {} ", explorer.direction.title());
+        let fill = content_width.saturating_sub(title.chars().count());
+        terminal_print!(
+            self,
+            "╭{}{}{}╮",
+            "─".repeat(fill / 2),
+            title,
+            "─".repeat(fill - fill / 2)
+        );
+
+        // Calculate visible rows
+        let visible_count = tree_height as usize;
+        let scroll_offset = explorer.selected.saturating_sub(visible_count - 1);
+
+        let visible_end = (scroll_offset + visible_count).min(explorer.flat_view.len());
+        for (i, idx) in (scroll_offset..visible_end).enumerate() {
+            let row = &explorer.flat_view[idx];
+            let row_bg = if idx == explorer.selected {
+                selected_bg
+            } else {
+                bg_color
+            };
+
+            let marker = if row.is_loading {
+                "… "
+            } else if row.is_leaf {
+                "  "
+            } else if row.is_expanded {
+                "▾ "
+            } else {
+                "▸ "
+            };
+            let icon = if use_nerd_fonts {
+                format!("{} ", row.kind.icon())
+            } else {
+                String::new()
+            };
+            let name = format!(" {}{}{}{}", "  ".repeat(row.depth), marker, icon, row.name);
+            let location = crate::lsp::uri_to_path(&row.uri)
+                .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+                .map(|file| format!("{}:{} ", file, row.line + 1))
+                .unwrap_or_default();
+            let detail = row
+                .detail
+                .as_deref()
+                .map(|detail| format!("  {}", detail))
+                .unwrap_or_default();
+
+            let name: String = name.chars().take(content_width).collect();
+            let name_len = name.chars().count();
+            let location_len = location.chars().count();
+            let location = if name_len + location_len < content_width {
+                location
+            } else {
+                String::new()
+            };
+            let detail_room = content_width.saturating_sub(name_len + location.chars().count() + 1);
+            let detail: String = detail.chars().take(detail_room).collect();
+            let padding =
+                content_width - name_len - detail.chars().count() - location.chars().count();
+
+            execute!(
+                self.stdout,
+                cursor::MoveTo(popup_x, popup_y + 1 + i as u16),
+                SetForegroundColor(border_color),
+                SetBackgroundColor(bg_color)
+            )?;
+            terminal_print!(self, "│");
+            execute!(
+                self.stdout,
+                SetBackgroundColor(row_bg),
+                SetForegroundColor(text_color)
+            )?;
+            terminal_print!(self, "{}", name);
+            execute!(self.stdout, SetForegroundColor(detail_color))?;
+            terminal_print!(self, "{}{:padding$}", detail, "", padding = padding);
+            execute!(self.stdout, SetForegroundColor(file_color))?;
+            terminal_print!(self, "{}", location);
+            execute!(
+                self.stdout,
+                SetForegroundColor(border_color),
+                SetBackgroundColor(bg_color)
+            )?;
+            terminal_print!(self, "│");
+        }
+
+        // Fill remaining tree rows
+        for i in (visible_end - scroll_offset)..visible_count {
+            execute!(
+                self.stdout,
+                cursor::MoveTo(popup_x, popup_y + 1 + i as u16),
+                SetForegroundColor(border_color),
+                SetBackgroundColor(bg_color)
+            )?;
+            terminal_print!(self, "│{:width$}│", "", width = content_width);
+        }
+
+        // Preview of the selected node's target
+        if let Some(preview) = explorer.preview.as_ref().filter(|_| preview_height > 0) {
+            let separator_row = popup_y + 1 + tree_height;
+            execute!(
+                self.stdout,
+                cursor::MoveTo(popup_x, separator_row),
+                SetForegroundColor(border_color),
+                SetBackgroundColor(bg_color)
+            )?;
+            terminal_print!(self, "├{}┤", "─".repeat(content_width));
+
+            let gutter_width = (preview.start_line + preview.lines.len()).to_string().len();
+            let tab = " ".repeat(editor.settings.editor.tab_width.max(1));
+            for (i, line) in preview
+                .lines
+                .iter()
+                .take(preview_height as usize)
+                .enumerate()
+            {
+                let line_idx = preview.start_line + i;
+                let line_bg = if line_idx == preview.line {
+                    target_bg
+                } else {
+                    bg_color
+                };
+                let text_width = content_width.saturating_sub(gutter_width + 2);
+                let text: String = line.replace('\t', &tab).chars().take(text_width).collect();
+                let padding = text_width - text.chars().count();
+
+                execute!(
+                    self.stdout,
+                    cursor::MoveTo(popup_x, separator_row + 1 + i as u16),
+                    SetForegroundColor(border_color),
+                    SetBackgroundColor(bg_color)
+                )?;
+                terminal_print!(self, "│");
+                execute!(
+                    self.stdout,
+                    SetBackgroundColor(line_bg),
+                    SetForegroundColor(line_num_color)
+                )?;
+                terminal_print!(self, "{:>width$} ", line_idx + 1, width = gutter_width);
+                execute!(self.stdout, SetForegroundColor(text_color))?;
+                terminal_print!(self, " {}{:padding$}", text, "", padding = padding);
+                execute!(
+                    self.stdout,
+                    SetForegroundColor(border_color),
+                    SetBackgroundColor(bg_color)
+                )?;
+                terminal_print!(self, "│");
+            }
+        }
+
+        // Draw bottom border
+        execute!(
+            self.stdout,
+            cursor::MoveTo(popup_x, popup_y + popup_height - 1),
+            SetForegroundColor(border_color),
+            SetBackgroundColor(bg_color)
+        )?;
+        terminal_print!(self, "╰{}╯", "─".repeat(content_width));
+
+        execute!(self.stdout, ResetColor)?;
+        Ok(())
+    }
+
     /// Render the code actions picker as a floating popup
     fn render_code_actions_picker(&mut self, editor: &Editor) -> anyhow::Result<()> {
         let picker = match &editor.code_actions_picker {
@@ -5588,6 +6354,8 @@ impl Terminal {
             crate::finder::FinderMode::GitChanges => " Git Changes ",
             crate::finder::FinderMode::Terminals => " Terminals ",
             crate::finder::FinderMode::Keymaps => " Key Maps ",
+            crate::finder::FinderMode::Symbols => " Symbols ",
+            crate::finder::FinderMode::WorkspaceSymbols => " Workspace Symbols ",
         };
 
         if preview_enabled {
@@ -6402,6 +7170,53 @@ fn handle_references_picker_key(editor: &mut Editor, key: KeyEvent) {
 }
 
 /// Handle key input for the code actions picker
+fn handle_hierarchy_explorer_key(editor: &mut Editor, key: KeyEvent) {
+    match (key.modifiers, key.code) {
+        // Close popup
+        (KeyModifiers::NONE, KeyCode::Esc)
+        | (KeyModifiers::NONE, KeyCode::Char('q'))
+        | (KeyModifiers::CONTROL, KeyCode::Char('[')) => {
+            editor.hide_hierarchy();
+            return;
+        }
+
+        // Select and jump
+        (KeyModifiers::NONE, KeyCode::Enter) | (KeyModifiers::NONE, KeyCode::Char('o')) => {
+            editor.hierarchy_jump();
+            return;
+        }
+
+        _ => {}
+    }
+
+    let Some(explorer) = editor.hierarchy_explorer.as_mut() else {
+        return;
+    };
+    match (key.modifiers, key.code) {
+        (KeyModifiers::NONE, KeyCode::Up) | (KeyModifiers::NONE, KeyCode::Char('k')) => {
+            explorer.move_up();
+        }
+        (KeyModifiers::NONE, KeyCode::Down) | (KeyModifiers::NONE, KeyCode::Char('j')) => {
+            explorer.move_down();
+        }
+        (KeyModifiers::NONE, KeyCode::Char('g')) => explorer.move_to_top(),
+        (KeyModifiers::SHIFT, KeyCode::Char('G')) | (KeyModifiers::NONE, KeyCode::Char('G')) => {
+            explorer.move_to_bottom();
+        }
+        (KeyModifiers::NONE, KeyCode::Right) | (KeyModifiers::NONE, KeyCode::Char('l')) => {
+            explorer.expand();
+        }
+        (KeyModifiers::NONE, KeyCode::Left) | (KeyModifiers::NONE, KeyCode::Char('h')) => {
+            explorer.collapse();
+        }
+        (KeyModifiers::NONE, KeyCode::Tab) | (KeyModifiers::NONE, KeyCode::Char(' ')) => {
+            explorer.toggle_expand();
+        }
+        _ => return,
+    }
+    editor.update_hierarchy_preview();
+}
+
 fn handle_code_actions_picker_key(editor: &mut Editor, key: KeyEvent) {
     match (key.modifiers, key.code) {
         // Close picker
@@ -6961,6 +7776,17 @@ pub fn handle_key(editor: &mut Editor, key: KeyEvent) {
         return;
     }
 
+    if editor.is_confirming_substitute() {
+        match (key.modifiers, key.code) {
+            (KeyModifiers::NONE, KeyCode::Esc) | (KeyModifiers::CONTROL, KeyCode::Char('[')) => {
+                editor.substitute_confirm_key('q');
+            }
+            (_, KeyCode::Char(ch)) => editor.substitute_confirm_key(ch),
+            _ => {}
+        }
+        return;
+    }
+
     if editor.pending_expression_register.is_some() {
         match (key.modifiers, key.code) {
             (KeyModifiers::NONE, KeyCode::Esc) | (KeyModifiers::CONTROL, KeyCode::Char('[')) => {
@@ -6986,6 +7812,12 @@ pub fn handle_key(editor: &mut Editor, key: KeyEvent) {
         return;
     }
 
+    // Handle call/type hierarchy popup if active
+    if editor.hierarchy_explorer.is_some() {
+        handle_hierarchy_explorer_key(editor, key);
+        return;
+    }
+
     // Handle code actions picker if active
     if editor.code_actions_picker.is_some() {
         handle_code_actions_picker_key(editor, key);
@@ -7014,8 +7846,8 @@ pub fn handle_key(editor: &mut Editor, key: KeyEvent) {
         editor.clear_status();
     }
 
-    // Handle macro recording
-    if editor.macros.is_recording() {
+    // Handle macro recording (keys replayed by `.` were recorded when first typed)
+    if editor.macros.is_recording() && !editor.dot_repeat.is_replaying() {
         // Check if 'q' is pressed in Normal mode to stop recording
         if editor.mode == Mode::Normal
             && key.code == KeyCode::Char('q')
@@ -7028,6 +7860,7 @@ pub fn handle_key(editor: &mut Editor, key: KeyEvent) {
         // Record the key (we record before processing so all keys including motions are captured)
         editor.macros.record_key(key);
     }
+    editor.record_repeat_key(key);
 
     if editor.pending_insert_normal_once && editor.mode == Mode::Normal {
         handle_normal_mode(editor, key);
@@ -7045,13 +7878,17 @@ pub fn handle_key(editor: &mut Editor, key: KeyEvent) {
         Mode::Normal => handle_normal_mode(editor, key),
         Mode::Insert => handle_insert_mode(editor, key),
         Mode::Replace => handle_replace_mode(editor, key),
-        Mode::Command => handle_command_mode(editor, key),
+        Mode::Command => {
+            handle_command_mode(editor, key);
+            editor.refresh_substitute_preview();
+        }
         Mode::Search => handle_search_mode(editor, key),
         Mode::Visual | Mode::VisualLine | Mode::VisualBlock => handle_visual_mode(editor, key),
         Mode::Finder => handle_finder_mode(editor, key),
         Mode::Explorer => handle_explorer_mode(editor, key),
         Mode::RenamePrompt => handle_rename_prompt_mode(editor, key),
     }
+    editor.finish_repeat_key();
 
     if let Some(cursor_row_damage) = cursor_row_damage {
         cursor_row_damage.apply(editor);
@@ -7238,6 +8075,7 @@ fn handle_normal_mode(editor: &mut Editor, key: KeyEvent) {
                 Operator::Indent => editor.indent_motion(motion, count),
                 Operator::Dedent => editor.dedent_motion(motion, count),
                 Operator::AutoIndent => editor.auto_indent_motion(motion, count),
+                Operator::Format => editor.format_motion(motion, count),
             }
         }
 
@@ -7253,6 +8091,7 @@ fn handle_normal_mode(editor: &mut Editor, key: KeyEvent) {
                 Operator::Indent => editor.indent_line(count),
                 Operator::Dedent => editor.dedent_line(count),
                 Operator::AutoIndent => editor.auto_indent_line(count),
+                Operator::Format => editor.format_line(count),
             }
         }
 
@@ -7268,6 +8107,7 @@ fn handle_normal_mode(editor: &mut Editor, key: KeyEvent) {
                 Operator::Indent => editor.indent_text_object(text_object),
                 Operator::Dedent => editor.dedent_text_object(text_object),
                 Operator::AutoIndent => editor.auto_indent_text_object(text_object),
+                Operator::Format => editor.format_text_object(text_object),
             }
         }
 
@@ -7449,8 +8289,8 @@ fn handle_normal_mode(editor: &mut Editor, key: KeyEvent) {
             editor.scroll_cursor_bottom();
         }
 
-        KeyAction::RepeatLastChange => {
-            editor.repeat_last_change();
+        KeyAction::RepeatLastChange(count) => {
+            repeat_last_change(editor, count);
         }
 
         KeyAction::EnterCommand => {
@@ -7635,6 +8475,10 @@ fn handle_normal_mode(editor: &mut Editor, key: KeyEvent) {
             editor.pending_lsp_action = Some(crate::editor::LspAction::GotoImplementation);
         }
 
+        KeyAction::GotoTypeDefinition => {
+            editor.pending_lsp_action = Some(crate::editor::LspAction::GotoTypeDefinition);
+        }
+
         KeyAction::GotoFile => match editor.open_file_under_cursor() {
             Ok(path) => editor.set_status(format!("Opened {}", path.display())),
             Err(err) => editor.set_status(err),
@@ -8166,9 +9010,13 @@ fn handle_insert_mode(editor: &mut Editor, key: KeyEvent) {
 
         // Tab
         (KeyModifiers::NONE, KeyCode::Tab) => {
-            // Insert spaces based on configured tab width
-            for _ in 0..editor.settings.editor.tab_width {
-                editor.insert_char(' ');
+            // Spaces up to the configured tab width, or a real tab with noexpandtab
+            if editor.expand_tab() {
+                for _ in 0..editor.settings.editor.tab_width {
+                    editor.insert_char(' ');
+                }
+            } else {
+                editor.insert_char('\t');
             }
         }
 
@@ -8490,6 +9338,7 @@ fn handle_command_mode(editor: &mut Editor, key: KeyEvent) {
             } else {
                 let cmd = editor.command_line.execute();
                 editor.mode = Mode::Normal;
+                editor.clear_substitute_preview();
                 execute_command(editor, cmd);
             }
         }
@@ -8823,6 +9672,12 @@ fn handle_visual_mode(editor: &mut Editor, key: KeyEvent) {
             editor.enter_normal_mode();
             return;
         }
+        // gq in visual mode - LSP range format the selection
+        if matches!(key.code, KeyCode::Char('q')) {
+            editor.format_visual_selection();
+            editor.enter_normal_mode();
+            return;
+        }
         // If not 'c', fall through to normal handling (e.g., gg)
         if matches!(key.code, KeyCode::Char('g')) {
             editor.apply_motion(Motion::FileStart, 1);
@@ -8832,6 +9687,14 @@ fn handle_visual_mode(editor: &mut Editor, key: KeyEvent) {
 
     // Handle text object selection (after i or a was pressed)
     if let Some(modifier) = editor.input_state.pending_text_object.take() {
+        // an/in - grow or shrink the selection by syntax node
+        if (key.modifiers, key.code) == (KeyModifiers::NONE, KeyCode::Char('n')) {
+            match modifier {
+                TextObjectModifier::Around => editor.expand_selection(),
+                TextObjectModifier::Inner => editor.shrink_selection(),
+            }
+            return;
+        }
         let object_type = match (key.modifiers, key.code) {
             (KeyModifiers::NONE, KeyCode::Char('w')) => Some(TextObjectType::Word),
             (KeyModifiers::SHIFT, KeyCode::Char('W')) => Some(TextObjectType::BigWord),
@@ -8910,6 +9773,12 @@ fn handle_visual_mode(editor: &mut Editor, key: KeyEvent) {
             let register = editor.input_state.take_register();
             editor.visual_paste(register);
         }
+
+        // Ex command over the selected lines
+        (_, KeyCode::Char(':')) => {
+            editor.exit_visual_mode();
+            editor.enter_command_mode_with_input("'<,'>");
+        }
         (KeyModifiers::SHIFT, KeyCode::Char('I')) if editor.mode == Mode::VisualBlock => {
             editor.enter_visual_block_insert_mode(false);
         }
@@ -9965,7 +10834,12 @@ fn rename_file_impl(
 }
 
 fn execute_command(editor: &mut Editor, cmd: Command) {
-    let result = match cmd {
+    let result = run_command(editor, cmd);
+    report_command_result(editor, result);
+}
+
+fn run_command(editor: &mut Editor, cmd: Command) -> CommandResult {
+    match cmd {
         Command::Write(path) => {
             if let Some(p) = path {
                 // Save as: skip format_on_save for explicit path
@@ -10193,6 +11067,14 @@ fn execute_command(editor: &mut Editor, cmd: Command) {
             CommandResult::Ok
         }
 
+        Command::GotoRange(range) => match editor.resolve_ex_range(Some(&range)) {
+            Ok((_, last)) => {
+                editor.goto_line(last + 1);
+                CommandResult::Ok
+            }
+            Err(message) => CommandResult::Error(message),
+        },
+
         Command::Next => {
             if editor.buffer_count() > 1 {
                 editor.next_buffer();
@@ -10228,7 +11110,11 @@ fn execute_command(editor: &mut Editor, cmd: Command) {
             }
         }
 
-        Command::Set(option, _value) => CommandResult::Error(format!("Unknown option: {}", option)),
+        Command::Set { args, local } => match editor.set_options(&args, local) {
+            Ok(Some(shown)) => CommandResult::Message(shown),
+            Ok(None) => CommandResult::Ok,
+            Err(err) => CommandResult::Error(err),
+        },
 
         Command::LazyGit => CommandResult::RunExternal("lazygit".to_string()),
 
@@ -10311,18 +11197,38 @@ fn execute_command(editor: &mut Editor, cmd: Command) {
         }
 
         Command::Substitute {
-            entire_file,
+            range,
+            pattern,
+            replacement,
+            flags,
+            count,
+        } => match editor.substitute(range.as_ref(), &pattern, &replacement, flags, count) {
+            Ok(Some(message)) => CommandResult::Message(message),
+            Ok(None) => CommandResult::Ok,
+            Err(message) => CommandResult::Error(message),
+        },
+
+        Command::Global {
+            range,
             pattern,
-            replacement,
-            global,
-        } => {
-            let count = editor.substitute(&pattern, &replacement, entire_file, global);
-            if count > 0 {
-                CommandResult::Message(format!("{} substitution(s)", count))
-            } else {
-                CommandResult::Message(format!("Pattern not found: {}", pattern))
-            }
-        }
+            command,
+            invert,
+        } => run_global_command(editor, range.as_ref(), &pattern, &command, invert),
+
+        Command::DeleteLines {
+            range,
+            register,
+            count,
+        } => match editor.delete_ex_lines(range.as_ref(), register, count) {
+            Ok(()) => CommandResult::Ok,
+            Err(message) => CommandResult::Error(message),
+        },
+
+        Command::Normal {
+            range,
+            keys,
+            noremap,
+        } => run_normal_command(editor, range.as_ref(), &keys, noremap),
 
         Command::ProjectReplace {
             pattern,
@@ -10466,6 +11372,56 @@ fn execute_command(editor: &mut Editor, cmd: Command) {
             CommandResult::Ok
         }
 
+        Command::DocumentSymbols => {
+            editor.pending_lsp_action = Some(LspAction::DocumentSymbols);
+            CommandResult::Ok
+        }
+
+        Command::WorkspaceSymbols => {
+            editor.pending_lsp_action = Some(LspAction::WorkspaceSymbols);
+            CommandResult::Ok
+        }
+
+        Command::CallHierarchy { outgoing } => {
+            let direction = if outgoing {
+                HierarchyDirection::OutgoingCalls
+            } else {
+                HierarchyDirection::IncomingCalls
+            };
+            editor.pending_lsp_action = Some(LspAction::Hierarchy(direction));
+            CommandResult::Ok
+        }
+
+        Command::TypeHierarchy { supertypes } => {
+            let direction = if supertypes {
+                HierarchyDirection::Supertypes
+            } else {
+                HierarchyDirection::Subtypes
+            };
+            editor.pending_lsp_action = Some(LspAction::Hierarchy(direction));
+            CommandResult::Ok
+        }
+
+        Command::CodeLens(number) => {
+            editor.run_code_lens(number);
+            CommandResult::Ok
+        }
+
+        Command::LspLog(server) => {
+            editor.pending_lsp_action = Some(LspAction::LspLog(server));
+            CommandResult::Ok
+        }
+
+        Command::LspInfo => {
+            editor.pending_lsp_action = Some(LspAction::LspInfo);
+            CommandResult::Ok
+        }
+
+        Command::LspRestart(name) => {
+            editor.pending_lsp_action = Some(LspAction::LspRestart(name));
+            CommandResult::Ok
+        }
+
         Command::Rename(new_name) => {
             // Trigger LSP rename
             editor.pending_lsp_action = Some(LspAction::RenameSymbol(new_name.clone()));
@@ -10681,9 +11637,10 @@ fn execute_command(editor: &mut Editor, cmd: Command) {
                 CommandResult::Error(format!("Not an editor command: {}", cmd))
             }
         }
-    };
+    }
+}
 
-    // Handle the result
+fn report_command_result(editor: &mut Editor, result: CommandResult) {
     match result {
         CommandResult::Ok => {}
         CommandResult::Message(msg) => {
@@ -10707,6 +11664,153 @@ fn execute_command(editor: &mut Editor, cmd: Command) {
     }
 }
 
+/// Run `command` on every line `:g` marks, as one undo step. The first
+/// error stops the run. `p` lists the lines instead, in the status line or,
+/// for several, a read-only buffer.
+fn run_global_command(
+    editor: &mut Editor,
+    range: Option<&ExRange>,
+    pattern: &str,
+    command: &str,
+    invert: bool,
+) -> CommandResult {
+    let mut run = match editor.begin_global(range, pattern, invert) {
+        Ok(run) => run,
+        Err(message) => return CommandResult::Error(message),
+    };
+
+    let print = matches!(command, "p" | "print");
+    let mut printed = Vec::new();
+    let mut result = CommandResult::Ok;
+    while let Some(line) = editor.next_global_line(&mut run) {
+        if print {
+            let text = editor.buffer().line(line).map(|text| text.to_string());
+            let text = text.unwrap_or_default();
+            printed.push(text.trim_end_matches(['\r', '\n']).to_string());
+            continue;
+        }
+        let cmd = match parse_command(command) {
+            Command::Global { .. } => {
+                result = CommandResult::Error("Cannot do :global recursive".to_string());
+                break;
+            }
+            // Lines without a match are expected, so `:s` stays quiet about them.
+            Command::Substitute {
+                range,
+                pattern,
+                replacement,
+                mut flags,
+                count,
+            } => {
+                flags.suppress_errors = true;
+                Command::Substitute {
+                    range,
+                    pattern,
+                    replacement,
+                    flags,
+                    count,
+                }
+            }
+            cmd => cmd,
+        };
+        match run_command(editor, cmd) {
+            CommandResult::Error(message) => {
+                result = CommandResult::Error(message);
+                break;
+            }
+            CommandResult::Quit => {
+                result = CommandResult::Quit;
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    editor.finish_global(run);
+    match printed.len() {
+        0 => result,
+        1 => CommandResult::Message(printed.remove(0)),
+        _ => {
+            editor.open_virtual_read_only_buffer("[global]", &(printed.join("\n") + "\n"), None);
+            result
+        }
+    }
+}
+
+/// Replay the keys of the last change (`.`). A count replaces the change's
+/// own, for this and later repeats.
+fn repeat_last_change(editor: &mut Editor, count: Option<usize>) {
+    if let Some(count) = count {
+        editor.dot_repeat.set_count(count);
+    }
+    let keys = editor.dot_repeat.last_change().to_vec();
+    editor.dot_repeat.set_replaying(true);
+    for key in keys {
+        handle_key(editor, key);
+    }
+    editor.dot_repeat.set_replaying(false);
+}
+
+/// Replay `keys` once at the cursor, or from column 0 of each line in
+/// `range`. The whole run is one undo step.
+fn run_normal_command(
+    editor: &mut Editor,
+    range: Option<&ExRange>,
+    keys: &str,
+    noremap: bool,
+) -> CommandResult {
+    let lines = match range {
+        Some(range) => match editor.resolve_ex_range(Some(range)) {
+            Ok((first, last)) => Some(first..=last),
+            Err(message) => return CommandResult::Error(message),
+        },
+        None => None,
+    };
+    let keys = parse_key_sequence(keys);
+    // `:normal!` runs the built-in commands, ignoring the user's mappings.
+    let user_keymap = noremap.then(|| std::mem::take(&mut editor.keymap));
+
+    editor
+        .undo_stack
+        .begin_compound_group(editor.cursor.line, editor.cursor.col);
+    match lines {
+        Some(lines) => {
+            for line in lines {
+                if line >= editor.buffer().addressable_line_count() {
+                    break;
+                }
+                editor.cursor.line = line;
+                editor.cursor.col = 0;
+                replay_normal_keys(editor, &keys);
+            }
+        }
+        None => replay_normal_keys(editor, &keys),
+    }
+    editor
+        .undo_stack
+        .end_compound_group(editor.cursor.line, editor.cursor.col);
+
+    if let Some(keymap) = user_keymap {
+        editor.keymap = keymap;
+    }
+    CommandResult::Ok
+}
+
+/// Feed `keys` through `handle_key` as if typed in Normal mode, then leave
+/// whatever mode they ended in, as `:normal` does with an incomplete command.
+fn replay_normal_keys(editor: &mut Editor, keys: &[KeyEvent]) {
+    for key in keys {
+        handle_key(editor, *key);
+    }
+    let esc = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
+    for _ in 0..3 {
+        if editor.mode == Mode::Normal {
+            break;
+        }
+        handle_key(editor, esc);
+    }
+}
+
 /// Execute a leader key action
 pub fn execute_leader_action(editor: &mut Editor, action: &LeaderAction) {
     editor.leader_sequence = None;
@@ -10742,12 +11846,15 @@ mod tests {
     };
     use crate::commands::{Command, CommandPopupMode};
     use crate::config::{FormatterConfig, KeymapEntry, LanguageConfig, LanguagesConfig, Settings};
-    use crate::editor::{Editor, LspAction, Mode, RegisterContent};
+    use crate::editor::{DocumentHighlightSpan, Editor, LspAction, Mode, RegisterContent};
     use crate::explorer::{ExplorerAction, FlatNode};
     use crate::finder::FinderMode;
     use crate::floating_terminal::TerminalCursorShape;
     use crate::input::Motion;
-    use crate::lsp::types::{CompletionItem, CompletionKind, Diagnostic, DiagnosticSeverity};
+    use crate::lsp::types::{
+        CompletionItem, CompletionKind, Diagnostic, DiagnosticSeverity, DiagnosticTag,
+        DocumentHighlightKind,
+    };
     use crate::syntax::{HighlightSpan, SyntaxStyle};
     use alacritty_terminal::event::VoidListener;
     use alacritty_terminal::grid::Dimensions;
@@ -11425,6 +12532,80 @@ mod tests {
         std::fs::remove_dir_all(root).expect("remove temp dir");
     }
 
+    #[test]
+    fn diagnostic_float_numbers_related_locations_and_shows_href() {
+        let root = unique_temp_dir("nevi_diagnostic_float_related");
+        std::fs::create_dir_all(&root).expect("create temp dir");
+        let path = root.join("app.py");
+        std::fs::write(&path, "import os\n").expect("write diagnostic fixture");
+
+        let mut editor = Editor::default();
+        editor.set_size(120, 30);
+        editor.open_file(path.clone()).expect("open fixture");
+        let mut redefined = diagnostic(0, 7, 9, DiagnosticSeverity::Warning);
+        redefined.related = vec![crate::lsp::types::DiagnosticRelatedInformation {
+            uri: crate::lsp::path_to_uri(&root.join("util.py")),
+            line: 4,
+            col: 2,
+            message: "first defined here".to_string(),
+        }];
+        redefined.href = Some("https://docs.astral.sh/ruff/rules/F811".to_string());
+        editor.set_diagnostics(crate::lsp::path_to_uri(&path), vec![redefined]);
+        editor.show_diagnostic_float = true;
+
+        let rendered = render_diagnostic_float_to_string(&editor);
+        assert!(rendered.contains("[1] util.py:5:3 first defined here"));
+        assert!(rendered.contains("gx: https://docs.astral.sh/ruff/rules/F811"));
+
+        std::fs::remove_dir_all(root).expect("remove temp dir");
+    }
+
+    #[test]
+    fn code_lenses_render_on_a_row_above_their_line() {
+        let root = unique_temp_dir("nevi_code_lens_render");
+        std::fs::create_dir_all(&root).expect("create temp dir");
+        let path = root.join("main.rs");
+        std::fs::write(&path, "mod tests {\n    fn it_works() {}\n}\n")
+            .expect("write code lens fixture");
+
+        let mut editor = Editor::default();
+        editor.set_size(80, 12);
+        editor.open_file(path.clone()).expect("open fixture");
+        let version = editor.buffer().version();
+        editor.set_code_lenses(
+            &crate::lsp::path_to_uri(&path),
+            version,
+            vec![crate::lsp::types::CodeLens {
+                line: 1,
+                col: 4,
+                command: Some(crate::lsp::types::LspCommand {
+                    title: "▶ Run test".to_string(),
+                    command: "rust-analyzer.runSingle".to_string(),
+                    arguments: Vec::new(),
+                    server: None,
+                }),
+                raw: serde_json::Value::Null,
+            }],
+        );
+
+        for wrap in [false, true] {
+            editor.settings.editor.wrap = wrap;
+            let rendered = render_editor_to_string(&editor);
+            // Sign column, line numbers, then the line's indentation
+            let lens = rendered
+                .find(&format!("{}▶ Run test", " ".repeat(2 + 4 + 4)))
+                .expect("lens row");
+            let above = rendered.find("tests").expect("line 1");
+            let below = rendered.find("it_works").expect("line 2");
+            assert!(above < lens && lens < below, "wrap={wrap}");
+        }
+
+        editor.settings.editor.code_lens = false;
+        assert!(!render_editor_to_string(&editor).contains("Run test"));
+
+        std::fs::remove_dir_all(root).expect("remove temp dir");
+    }
+
     #[test]
     fn finder_first_frame_sets_active_theme_background_before_top_border() {
         let mut editor = Editor::default();
@@ -13611,6 +14792,57 @@ mod tests {
         let _ = std::fs::remove_dir_all(&root);
     }
 
+    #[test]
+    fn inlay_hints_clip_text_on_unwrapped_rows() {
+        let hints = vec![
+            crate::editor::BufferInlayHint {
+                line: 0,
+                col: 6,
+                label: ": i32".to_string(),
+            },
+            crate::editor::BufferInlayHint {
+                line: 0,
+                col: 11,
+                label: ": String".to_string(),
+            },
+        ];
+
+        // "let x = 1; y" scrolled one column right: "et x = 1; y"
+        let placed = super::place_inlay_hints("et x = 1; y", 1, 0, &hints, true, 17, true, 4);
+        assert_eq!(
+            placed,
+            vec![(5, ": i32".to_string()), (10, ": ".to_string())]
+        );
+        // The cursor on "=" (column 6) draws after the first label.
+        assert_eq!(super::inlay_hint_width_before(&placed, 0, 1, 6, 4), 5);
+        assert_eq!(super::inlay_hint_width_before(&placed, 0, 1, 5, 4), 0);
+    }
+
+    #[test]
+    fn inlay_hints_that_do_not_fit_a_wrapped_row_are_left_out() {
+        let hint = |col: usize, label: &str| crate::editor::BufferInlayHint {
+            line: 0,
+            col,
+            label: label.to_string(),
+        };
+        let hints = vec![hint(2, "a: "), hint(4, "b: "), hint(8, ": T")];
+
+        // Continuation row "  cdef" (two indent chars) shows columns 4..8.
+        assert_eq!(
+            super::place_inlay_hints("  cdef", 4, 2, &hints, false, 10, false, 4),
+            vec![(2, "b: ".to_string())]
+        );
+        // The end-of-line hint belongs to the last row, if it fits.
+        assert_eq!(
+            super::place_inlay_hints("  cdef", 4, 2, &hints, true, 10, false, 4),
+            vec![(2, "b: ".to_string())]
+        );
+        assert_eq!(
+            super::place_inlay_hints("  cdef", 4, 2, &hints, true, 12, false, 4),
+            vec![(2, "b: ".to_string()), (6, ": T".to_string())]
+        );
+    }
+
     #[test]
     fn wrap_segments_measure_tabs_by_display_width() {
         let segments = super::calculate_wrap_segments("ab\tcd", 5, true, 4);
@@ -13739,6 +14971,9 @@ mod tests {
             severity,
             source: None,
             code: None,
+            tags: Vec::new(),
+            related: Vec::new(),
+            href: None,
         }
     }
 
@@ -13789,6 +15024,8 @@ mod tests {
             selection_bg: Color::DarkBlue,
             search_match_bg: Color::Yellow,
             search_match_fg: Color::Black,
+            document_highlight_bg: Color::DarkCyan,
+            document_highlight_write_bg: Color::DarkMagenta,
             jump_label_bg: Color::Green,
             jump_label_fg: Color::Black,
             diagnostic_error_color: Color::Red,
@@ -13803,10 +15040,12 @@ mod tests {
             mode: &Mode::Normal,
             is_cursor_line: false,
             search_matches: &search_matches,
+            document_highlights: &[],
             jump_labels: &jump_labels,
             diagnostics: &diagnostics,
             colors,
             tab_width: 4,
+            list: false,
         };
 
         let cell = context.style_for_source_col(2);
@@ -13817,6 +15056,74 @@ mod tests {
         assert_eq!(cell.underline_color, None);
     }
 
+    #[test]
+    fn render_line_context_draws_document_highlights_behind_syntax() {
+        let diagnostics = Vec::<&Diagnostic>::new();
+        let highlights = vec![HighlightSpan {
+            start_col: 0,
+            end_col: 3,
+            fg: Color::Blue,
+            style: SyntaxStyle {
+                fg: Color::Blue,
+                bold: true,
+                italic: false,
+            },
+        }];
+        let search_matches = Vec::<(usize, usize, usize)>::new();
+        let document_highlights = vec![
+            DocumentHighlightSpan {
+                line: 1,
+                start_col: 0,
+                end_col: 3,
+                kind: DocumentHighlightKind::Write,
+            },
+            DocumentHighlightSpan {
+                line: 1,
+                start_col: 6,
+                end_col: 9,
+                kind: DocumentHighlightKind::Read,
+            },
+        ];
+        let jump_labels = Vec::<(usize, char)>::new();
+        let colors = RenderLineColors {
+            editor_bg: Color::Black,
+            editor_fg: Color::White,
+            cursor_line_bg: Color::DarkGrey,
+            selection_bg: Color::DarkBlue,
+            search_match_bg: Color::Yellow,
+            search_match_fg: Color::Black,
+            document_highlight_bg: Color::DarkCyan,
+            document_highlight_write_bg: Color::DarkMagenta,
+            jump_label_bg: Color::Green,
+            jump_label_fg: Color::Black,
+            diagnostic_error_color: Color::Red,
+            diagnostic_hint_color: Color::DarkGrey,
+        };
+        let context = RenderLineContext {
+            line_idx: 1,
+            col_offset: 0,
+            virtual_prefix_chars: 0,
+            highlights: &highlights,
+            visual_range: None,
+            mode: &Mode::Normal,
+            is_cursor_line: false,
+            search_matches: &search_matches,
+            document_highlights: &document_highlights,
+            jump_labels: &jump_labels,
+            diagnostics: &diagnostics,
+            colors,
+            tab_width: 4,
+            list: false,
+        };
+
+        let write = context.style_for_source_col(1);
+        assert_eq!(write.bg, Color::DarkMagenta);
+        assert_eq!(write.fg, Color::Blue);
+        assert!(write.bold, "highlighted text keeps its syntax style");
+        assert_eq!(context.style_for_source_col(7).bg, Color::DarkCyan);
+        assert_eq!(context.style_for_source_col(4).bg, Color::Black);
+    }
+
     #[test]
     fn render_line_context_factory_applies_editor_render_defaults() {
         let mut editor = Editor::default();
@@ -13827,7 +15134,7 @@ mod tests {
         let diagnostics = Vec::<&Diagnostic>::new();
         let jump_labels = Vec::<(usize, char)>::new();
 
-        let factory = RenderLineContextFactory::new(&editor, Some((1, 0, 1, 3)), 8);
+        let factory = RenderLineContextFactory::new(&editor, 0, Some((1, 0, 1, 3)), 8);
         let context = factory.context(1, 2, 1, &highlights, true, &jump_labels, &diagnostics);
 
         assert_eq!(context.line_idx, 1);
@@ -13865,6 +15172,8 @@ mod tests {
             selection_bg: Color::DarkBlue,
             search_match_bg: Color::Yellow,
             search_match_fg: Color::Black,
+            document_highlight_bg: Color::DarkCyan,
+            document_highlight_write_bg: Color::DarkMagenta,
             jump_label_bg: Color::Green,
             jump_label_fg: Color::Black,
             diagnostic_error_color: Color::Red,
@@ -13879,10 +15188,12 @@ mod tests {
             mode: &Mode::Visual,
             is_cursor_line: false,
             search_matches: &search_matches,
+            document_highlights: &[],
             jump_labels: &jump_labels,
             diagnostics: &diagnostics,
             colors,
             tab_width: 4,
+            list: false,
         };
         let mut output = Vec::new();
 
@@ -13902,6 +15213,74 @@ mod tests {
         assert!(rendered.contains("\x1b[48;5;4m "));
     }
 
+    #[test]
+    fn render_line_text_dims_unnecessary_and_strikes_deprecated_code() {
+        crossterm::style::force_color_output(true);
+        let mut unused = diagnostic(0, 0, 2, DiagnosticSeverity::Hint);
+        unused.tags = vec![DiagnosticTag::Unnecessary];
+        let mut deprecated = diagnostic(0, 3, 5, DiagnosticSeverity::Warning);
+        deprecated.tags = vec![DiagnosticTag::Deprecated];
+        let diagnostics = vec![&unused, &deprecated];
+        let highlights = Vec::<HighlightSpan>::new();
+        let search_matches = Vec::<(usize, usize, usize)>::new();
+        let jump_labels = Vec::<(usize, char)>::new();
+        let colors = RenderLineColors {
+            editor_bg: Color::Black,
+            editor_fg: Color::White,
+            cursor_line_bg: Color::DarkGrey,
+            selection_bg: Color::DarkBlue,
+            search_match_bg: Color::Yellow,
+            search_match_fg: Color::Black,
+            document_highlight_bg: Color::DarkCyan,
+            document_highlight_write_bg: Color::DarkMagenta,
+            jump_label_bg: Color::Green,
+            jump_label_fg: Color::Black,
+            diagnostic_error_color: Color::Red,
+            diagnostic_hint_color: Color::DarkGrey,
+        };
+        let context = RenderLineContext {
+            line_idx: 0,
+            col_offset: 0,
+            virtual_prefix_chars: 0,
+            highlights: &highlights,
+            visual_range: None,
+            mode: &Mode::Normal,
+            is_cursor_line: false,
+            search_matches: &search_matches,
+            document_highlights: &[],
+            jump_labels: &jump_labels,
+            diagnostics: &diagnostics,
+            colors,
+            tab_width: 4,
+            list: false,
+        };
+
+        let unused_cell = context.style_for_source_col(1);
+        assert!(unused_cell.dim && !unused_cell.strikethrough);
+        let deprecated_cell = context.style_for_source_col(4);
+        assert!(deprecated_cell.strikethrough && !deprecated_cell.dim);
+        let plain = context.style_for_source_col(2);
+        assert!(!plain.dim && !plain.strikethrough);
+
+        let mut output = Vec::new();
+        render_line_text_with_context(
+            &mut output,
+            "ab cd",
+            &context,
+            RenderTextOptions {
+                extend_visual_selection_past_line_end: false,
+            },
+        )
+        .expect("render line text");
+        let rendered = String::from_utf8(output).expect("utf8");
+        // Dim (2) for `ab`, normal intensity (22) before the space, then
+        // crossed out (9) for `cd`.
+        let dim = rendered.find("\x1b[2m").expect("dim");
+        let normal = rendered.find("\x1b[22m").expect("normal intensity");
+        let crossed = rendered.find("\x1b[9m").expect("crossed out");
+        assert!(dim < normal && normal < crossed);
+    }
+
     #[test]
     fn terminal_render_line_text_scope_preserves_wrapped_vs_logical_line_eol_selection() {
         crossterm::style::force_color_output(true);
@@ -13916,6 +15295,8 @@ mod tests {
             selection_bg: Color::DarkBlue,
             search_match_bg: Color::Yellow,
             search_match_fg: Color::Black,
+            document_highlight_bg: Color::DarkCyan,
+            document_highlight_write_bg: Color::DarkMagenta,
             jump_label_bg: Color::Green,
             jump_label_fg: Color::Black,
             diagnostic_error_color: Color::Red,
@@ -13930,10 +15311,12 @@ mod tests {
             mode: &Mode::Visual,
             is_cursor_line: false,
             search_matches: &search_matches,
+            document_highlights: &[],
             jump_labels: &jump_labels,
             diagnostics: &diagnostics,
             colors,
             tab_width: 4,
+            list: false,
         };
 
         let wrapped_output = SharedOutput::default();
@@ -14101,6 +15484,30 @@ mod tests {
         assert!(text.contains(":checkhealth"));
     }
 
+    #[test]
+    fn lsp_server_commands_queue_their_action_and_open_reports() {
+        let mut editor = Editor::default();
+
+        execute_command(&mut editor, Command::LspRestart(Some("python".to_string())));
+        assert_eq!(
+            editor.pending_lsp_action.take(),
+            Some(LspAction::LspRestart(Some("python".to_string())))
+        );
+        execute_command(&mut editor, Command::LspInfo);
+        assert!(
+            editor
+                .pending_lsp_action
+                .take()
+                .is_some_and(|action| action.manages_servers())
+        );
+
+        editor.open_lsp_log_report("// LSP protocol log\n{}\n");
+        assert_eq!(editor.buffer().display_name(), "[lsp-log]");
+        assert!(editor.buffer().is_read_only());
+        assert!(!editor.buffer().dirty);
+        assert_eq!(editor.buffer().content(), "// LSP protocol log\n{}\n");
+    }
+
     #[test]
     fn flight_recorder_command_opens_flight_report_buffer() {
         let mut editor = Editor::default();
@@ -14439,6 +15846,7 @@ mod tests {
                         timeout: 5,
                     }),
                     tab_width: None,
+                    ..Default::default()
                 },
             )]),
         }
@@ -15453,6 +16861,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn normal_gy_sets_goto_type_definition_lsp_action() {
+        let mut editor = Editor::default();
+
+        handle_key(&mut editor, key('g'));
+        handle_key(&mut editor, key('y'));
+
+        assert_eq!(
+            format!("{:?}", editor.pending_lsp_action),
+            "Some(GotoTypeDefinition)"
+        );
+    }
+
     #[test]
     fn normal_apostrophe_apostrophe_jumps_to_previous_jump_line_first_non_blank() {
         let mut editor = Editor::default();
@@ -2,7 +2,7 @@
 //!
 //! Handles parsing theme TOML files and converting them to Theme structs.
 
-use super::{DiagnosticColors, GitColors, StyleDef, SyntaxColors, Theme, UiColors};
+use super::{DiagnosticColors, GitColors, ModifierDef, StyleDef, SyntaxColors, Theme, UiColors};
 use crossterm::style::Color;
 use serde::Deserialize;
 use std::collections::HashMap;
@@ -58,6 +58,20 @@ pub struct SyntaxToml {
     pub method: Option<StyleToml>,
     pub constructor: Option<StyleToml>,
     pub boolean: Option<StyleToml>,
+    // Semantic token groups; unset ones follow `type`, `constructor` and `variable`
+    pub interface: Option<StyleToml>,
+    #[serde(rename = "struct")]
+    pub struct_: Option<StyleToml>,
+    #[serde(rename = "enum")]
+    pub enum_: Option<StyleToml>,
+    pub enum_member: Option<StyleToml>,
+    pub type_parameter: Option<StyleToml>,
+    pub parameter: Option<StyleToml>,
+    // Semantic token modifiers
+    pub mutable: Option<StyleToml>,
+    pub deprecated: Option<StyleToml>,
+    #[serde(rename = "async")]
+    pub async_: Option<StyleToml>,
 }
 
 #[derive(Debug, Default, Deserialize)]
@@ -69,6 +83,9 @@ pub struct UiToml {
     pub selection: Option<String>,
     pub line_number: Option<String>,
     pub line_number_active: Option<String>,
+    pub inlay_hint: Option<String>,
+    pub document_highlight: Option<String>,
+    pub document_highlight_write: Option<String>,
     pub visual_bg: Option<String>,
 
     // Status line (nested table)
@@ -220,6 +237,21 @@ fn parse_style(
     }
 }
 
+fn parse_modifier(
+    style_toml: &Option<StyleToml>,
+    palette: &HashMap<String, String>,
+    default: ModifierDef,
+) -> ModifierDef {
+    match style_toml {
+        Some(s) => ModifierDef {
+            fg: s.fg.as_ref().and_then(|v| resolve_color(v, palette)),
+            bold: s.bold,
+            italic: s.italic,
+        },
+        None => default,
+    }
+}
+
 /// Load a theme from TOML content (returns Result for error reporting)
 pub fn try_load_theme_from_toml(name: &str, content: &str) -> Result<Theme, String> {
     let toml: ThemeToml =
@@ -241,16 +273,19 @@ fn load_theme_from_toml_inner(name: &str, toml: &ThemeToml) -> Theme {
     let base = Theme::onedark();
 
     // Parse syntax colors
+    let type_ = parse_style(&toml.syntax.type_, palette, base.syntax.type_);
+    let constructor = parse_style(&toml.syntax.constructor, palette, base.syntax.constructor);
+    let variable = parse_style(&toml.syntax.variable, palette, base.syntax.variable);
     let syntax = SyntaxColors {
         keyword: parse_style(&toml.syntax.keyword, palette, base.syntax.keyword),
         function: parse_style(&toml.syntax.function, palette, base.syntax.function),
-        type_: parse_style(&toml.syntax.type_, palette, base.syntax.type_),
+        type_,
         string: parse_style(&toml.syntax.string, palette, base.syntax.string),
         number: parse_style(&toml.syntax.number, palette, base.syntax.number),
         comment: parse_style(&toml.syntax.comment, palette, base.syntax.comment),
         operator: parse_style(&toml.syntax.operator, palette, base.syntax.operator),
         punctuation: parse_style(&toml.syntax.punctuation, palette, base.syntax.punctuation),
-        variable: parse_style(&toml.syntax.variable, palette, base.syntax.variable),
+        variable,
         constant: parse_style(&toml.syntax.constant, palette, base.syntax.constant),
         attribute: parse_style(&toml.syntax.attribute, palette, base.syntax.attribute),
         namespace: parse_style(&toml.syntax.namespace, palette, base.syntax.namespace),
@@ -261,8 +296,17 @@ fn load_theme_from_toml_inner(name: &str, toml: &ThemeToml) -> Theme {
         // New groups
         macro_: parse_style(&toml.syntax.macro_, palette, base.syntax.macro_),
         method: parse_style(&toml.syntax.method, palette, base.syntax.method),
-        constructor: parse_style(&toml.syntax.constructor, palette, base.syntax.constructor),
+        constructor,
         boolean: parse_style(&toml.syntax.boolean, palette, base.syntax.boolean),
+        interface: parse_style(&toml.syntax.interface, palette, type_),
+        struct_: parse_style(&toml.syntax.struct_, palette, type_),
+        enum_: parse_style(&toml.syntax.enum_, palette, type_),
+        enum_member: parse_style(&toml.syntax.enum_member, palette, constructor),
+        type_parameter: parse_style(&toml.syntax.type_parameter, palette, type_),
+        parameter: parse_style(&toml.syntax.parameter, palette, variable),
+        mutable: parse_modifier(&toml.syntax.mutable, palette, base.syntax.mutable),
+        deprecated: parse_modifier(&toml.syntax.deprecated, palette, base.syntax.deprecated),
+        async_: parse_modifier(&toml.syntax.async_, palette, base.syntax.async_),
     };
 
     // Parse UI colors
@@ -303,6 +347,31 @@ fn load_theme_from_toml_inner(name: &str, toml: &ThemeToml) -> Theme {
             .as_ref()
             .and_then(|v| resolve_color(v, palette))
             .unwrap_or(base.ui.line_number_active),
+        // Themes without their own hint color dim hints like line numbers.
+        inlay_hint: toml
+            .ui
+            .inlay_hint
+            .as_ref()
+            .or(toml.ui.line_number.as_ref())
+            .and_then(|v| resolve_color(v, palette))
+            .unwrap_or(base.ui.inlay_hint),
+        // References to the symbol under the cursor look selected unless the
+        // theme says otherwise; writes fall back to the read color.
+        document_highlight: toml
+            .ui
+            .document_highlight
+            .as_ref()
+            .or(toml.ui.selection.as_ref())
+            .and_then(|v| resolve_color(v, palette))
+            .unwrap_or(base.ui.document_highlight),
+        document_highlight_write: toml
+            .ui
+            .document_highlight_write
+            .as_ref()
+            .or(toml.ui.document_highlight.as_ref())
+            .or(toml.ui.selection.as_ref())
+            .and_then(|v| resolve_color(v, palette))
+            .unwrap_or(base.ui.document_highlight_write),
 
         statusline_bg: toml
             .ui
@@ -699,6 +768,19 @@ macro = { fg = "cyan" }                  # format!, println!
 method = { fg = "blue" }                 # .clone(), .ok()
 constructor = { fg = "cyan" }            # Some, None, Ok, Err
 boolean = { fg = "orange" }              # true, false
+# Semantic token groups (from the language server; unset ones follow
+# type, constructor and variable)
+interface = { fg = "yellow", italic = true } # traits, interfaces
+struct = { fg = "yellow" }               # structs, classes
+enum = { fg = "yellow" }                 # enums
+enum_member = { fg = "cyan" }            # enum variants
+type_parameter = { fg = "yellow" }       # T in Vec<T>
+parameter = { fg = "red", italic = true } # function parameters
+# Semantic token modifiers change the token's style; without fg the
+# token keeps its color
+# mutable = { bold = true }              # `mut` bindings
+# deprecated = { fg = "gray" }           # deprecated items
+# async = { italic = true }              # async functions
 
 # =============================================================================
 # UI COLORS
@@ -711,6 +793,9 @@ cursor_line = "#2c313c"                  # current line highlight
 selection = "#3e4451"                    # selected text background
 line_number = "gray"                     # line numbers
 line_number_active = "fg"                # current line number
+inlay_hint = "gray"                      # LSP inlay hints
+document_highlight = "#3e4451"           # LSP reads of the symbol under the cursor
+document_highlight_write = "#4b5263"     # LSP writes of the symbol under the cursor
 visual_bg = "#3e4451"                    # visual mode selection
 
 # Status line colors
@@ -49,7 +49,17 @@ impl StyleDef {
     }
 }
 
-/// Syntax highlighting colors (20 groups)
+/// Change a semantic token modifier makes to its token's style. Without
+/// `fg` the token keeps its color.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ModifierDef {
+    pub fg: Option<Color>,
+    pub bold: bool,
+    pub italic: bool,
+}
+
+/// Syntax highlighting colors (20 tree-sitter groups, plus the groups and
+/// modifiers only LSP semantic tokens produce)
 #[derive(Debug, Clone)]
 pub struct SyntaxColors {
     pub keyword: StyleDef,
@@ -73,6 +83,17 @@ pub struct SyntaxColors {
     pub method: StyleDef,      // .clone(), .ok()
     pub constructor: StyleDef, // Some, None, Ok, Err
     pub boolean: StyleDef,     // true, false
+    // Semantic token groups
+    pub interface: StyleDef,      // traits, interfaces
+    pub struct_: StyleDef,        // structs, classes
+    pub enum_: StyleDef,          // enums
+    pub enum_member: StyleDef,    // enum variants
+    pub type_parameter: StyleDef, // T in Vec<T>
+    pub parameter: StyleDef,      // function parameters
+    // Semantic token modifiers
+    pub mutable: ModifierDef,    // `mut` bindings
+    pub deprecated: ModifierDef, // #[deprecated], @deprecated
+    pub async_: ModifierDef,     // async functions
 }
 
 /// UI element colors
@@ -85,6 +106,9 @@ pub struct UiColors {
     pub selection: Color,
     pub line_number: Color,
     pub line_number_active: Color,
+    pub inlay_hint: Color,
+    pub document_highlight: Color,
+    pub document_highlight_write: Color,
 
     // Status line
     pub statusline_bg: Color,
@@ -240,6 +264,15 @@ impl Theme {
                 method: StyleDef::new(blue),
                 constructor: StyleDef::new(cyan),
                 boolean: StyleDef::new(orange),
+                interface: StyleDef::new(yellow).with_italic(),
+                struct_: StyleDef::new(yellow),
+                enum_: StyleDef::new(yellow),
+                enum_member: StyleDef::new(cyan),
+                type_parameter: StyleDef::new(yellow),
+                parameter: StyleDef::new(red).with_italic(),
+                mutable: ModifierDef::default(),
+                deprecated: ModifierDef::default(),
+                async_: ModifierDef::default(),
             },
             ui: UiColors {
                 background: bg,
@@ -256,6 +289,17 @@ impl Theme {
                 },
                 line_number: gray,
                 line_number_active: fg,
+                inlay_hint: gray,
+                document_highlight: Color::Rgb {
+                    r: 62,
+                    g: 68,
+                    b: 81,
+                },
+                document_highlight_write: Color::Rgb {
+                    r: 75,
+                    g: 82,
+                    b: 99,
+                },
 
                 statusline_bg: bg_dark,
                 statusline_fg: fg,
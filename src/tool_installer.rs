@@ -125,6 +125,9 @@ where
         add_lsp_tool(&mut grouped, "go", &servers.go, &is_command_available);
         add_lsp_tool(&mut grouped, "ruby", &servers.ruby, &is_command_available);
         add_lsp_tool(&mut grouped, "shell", &servers.shell, &is_command_available);
+        for (name, config) in &servers.additional {
+            add_lsp_tool(&mut grouped, name, config, &is_command_available);
+        }
     }
 
     for (language, config) in &languages_config.languages {
@@ -332,6 +335,7 @@ mod tests {
                         timeout: 5,
                     }),
                     tab_width: None,
+                    ..Default::default()
                 },
             )]),
         };